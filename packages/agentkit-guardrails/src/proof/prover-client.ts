@@ -37,6 +37,20 @@ export interface PublicInputs {
   outputHash: string;
   output: number[];
   timestamp: number;
+  /** Hashing scheme used for the commitments (e.g. `sha256-v1`) */
+  commitmentScheme?: string;
+  /** Same commitments under a circuit-friendly hash (e.g. `keccak256-v1`) */
+  circuitCommitments?: CircuitCommitments;
+}
+
+/**
+ * Commitments under a circuit-friendly hash, bound into the same proof
+ */
+export interface CircuitCommitments {
+  scheme: string;
+  model_commitment: string;
+  input_hash: string;
+  output_hash: string;
 }
 
 /**
@@ -178,6 +192,8 @@ export class ProverServiceClient {
         output_hash: string;
         output: number[];
         timestamp: number;
+        commitment_scheme?: string;
+        circuit_commitments?: CircuitCommitments;
       };
       proving_time_ms: number;
       error?: string;
@@ -202,6 +218,8 @@ export class ProverServiceClient {
         outputHash: response.public_inputs.output_hash,
         output: response.public_inputs.output,
         timestamp: response.public_inputs.timestamp,
+        commitmentScheme: response.public_inputs.commitment_scheme,
+        circuitCommitments: response.public_inputs.circuit_commitments,
       },
       provingTimeMs: response.proving_time_ms,
     };
@@ -226,6 +244,7 @@ export class ProverServiceClient {
       model_commitment: modelCommitment,
       input_hash: inputHash,
       output_hash: outputHash,
+      commitment_scheme: publicInputs?.commitmentScheme,
       public_inputs: publicInputs
         ? {
             model_commitment: publicInputs.modelCommitment,
@@ -233,6 +252,8 @@ export class ProverServiceClient {
             output_hash: publicInputs.outputHash,
             output: publicInputs.output,
             timestamp: publicInputs.timestamp,
+            commitment_scheme: publicInputs.commitmentScheme,
+            circuit_commitments: publicInputs.circuitCommitments,
           }
         : undefined,
     });
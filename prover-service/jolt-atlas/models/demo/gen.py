@@ -0,0 +1,136 @@
+#!/usr/bin/env python3
+"""
+Generate the demo models embedded in the prover service (DEMO_MODE=true).
+
+Both models are tiny and have fixed, hand-picked or seeded weights, so the
+files (and therefore their commitments) are reproducible byte for byte. The
+ONNX protobuf is written directly, so this script needs no dependencies.
+
+  logistic.onnx  x[1,4] -> MatMul -> Add -> Sigmoid -> y[1,1]
+  mlp.onnx       x[1,8] -> MatMul -> Add -> Relu -> MatMul -> Add -> y[1,2]
+"""
+
+import os
+import struct
+
+IR_VERSION = 8
+OPSET = 13
+FLOAT = 1
+
+
+def varint(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field_varint(number, value):
+    return varint(number << 3) + varint(value)
+
+
+def field_bytes(number, data):
+    if isinstance(data, str):
+        data = data.encode()
+    return varint(number << 3 | 2) + varint(len(data)) + data
+
+
+def tensor(name, dims, values):
+    raw = b"".join(struct.pack("<f", v) for v in values)
+    return (
+        b"".join(field_varint(1, d) for d in dims)
+        + field_varint(2, FLOAT)
+        + field_bytes(8, name)
+        + field_bytes(9, raw)
+    )
+
+
+def value_info(name, dims):
+    shape = b"".join(field_bytes(1, field_varint(1, d)) for d in dims)
+    tensor_type = field_varint(1, FLOAT) + field_bytes(2, shape)
+    return field_bytes(1, name) + field_bytes(2, field_bytes(1, tensor_type))
+
+
+def node(op_type, inputs, outputs, name):
+    return (
+        b"".join(field_bytes(1, i) for i in inputs)
+        + b"".join(field_bytes(2, o) for o in outputs)
+        + field_bytes(3, name)
+        + field_bytes(4, op_type)
+    )
+
+
+def model(name, nodes, initializers, inputs, outputs):
+    graph = (
+        b"".join(field_bytes(1, n) for n in nodes)
+        + field_bytes(2, name)
+        + b"".join(field_bytes(5, t) for t in initializers)
+        + b"".join(field_bytes(11, i) for i in inputs)
+        + b"".join(field_bytes(12, o) for o in outputs)
+    )
+    opset = field_bytes(1, "") + field_varint(2, OPSET)
+    return (
+        field_varint(1, IR_VERSION)
+        + field_bytes(2, "jolt-atlas-demo")
+        + field_bytes(7, graph)
+        + field_bytes(8, opset)
+    )
+
+
+def seeded(count, seed):
+    """Deterministic weights in [-1, 1) from a linear congruential generator"""
+    values = []
+    state = seed
+    for _ in range(count):
+        state = (state * 1103515245 + 12345) % 2**31
+        values.append(round(state / 2**30 - 1.0, 4))
+    return values
+
+
+def logistic():
+    return model(
+        "logistic_regression",
+        [
+            node("MatMul", ["x", "w"], ["logit_raw"], "matmul"),
+            node("Add", ["logit_raw", "b"], ["logit"], "add"),
+            node("Sigmoid", ["logit"], ["y"], "sigmoid"),
+        ],
+        [tensor("w", [4, 1], [0.8, -0.5, 1.2, -0.3]), tensor("b", [1], [-0.1])],
+        [value_info("x", [1, 4])],
+        [value_info("y", [1, 1])],
+    )
+
+
+def mlp():
+    return model(
+        "mlp",
+        [
+            node("MatMul", ["x", "w1"], ["h_raw"], "matmul1"),
+            node("Add", ["h_raw", "b1"], ["h_pre"], "add1"),
+            node("Relu", ["h_pre"], ["h"], "relu"),
+            node("MatMul", ["h", "w2"], ["y_raw"], "matmul2"),
+            node("Add", ["y_raw", "b2"], ["y"], "add2"),
+        ],
+        [
+            tensor("w1", [8, 8], seeded(64, 1)),
+            tensor("b1", [8], seeded(8, 2)),
+            tensor("w2", [8, 2], seeded(16, 3)),
+            tensor("b2", [2], seeded(2, 4)),
+        ],
+        [value_info("x", [1, 8])],
+        [value_info("y", [1, 2])],
+    )
+
+
+if __name__ == "__main__":
+    here = os.path.dirname(os.path.abspath(__file__))
+    for file_name, build in [("logistic.onnx", logistic), ("mlp.onnx", mlp)]:
+        path = os.path.join(here, file_name)
+        with open(path, "wb") as f:
+            f.write(build())
+        print(f"Wrote {path}")
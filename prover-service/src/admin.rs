@@ -0,0 +1,729 @@
+//! Admin API
+//!
+//! Operator-only routes mounted under `/admin`. Every handler requires the
+//! `AdminAuth` extractor, i.e. a valid `Authorization: Bearer <ADMIN_TOKEN>`.
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_keys::{ApiKeyRecord, CreateApiKey, IssuedApiKey};
+use crate::audit::{AuditAction, AuditEntry};
+use crate::auth::AdminAuth;
+use crate::backup;
+use crate::cluster;
+use crate::experiments;
+use crate::flags;
+use crate::gc::{GcReport, GcStats};
+use crate::metering::UsageReport;
+use crate::migrate;
+use crate::overview;
+use crate::queue::{ModelLimits, QueueStats};
+use crate::quotas::{KeyQuota, QuotaLimits};
+use crate::retention::StorageUsage;
+use crate::shadow;
+use crate::sharding;
+use crate::signing::PublicKeyInfo;
+use crate::snapshot;
+use crate::tenants;
+use crate::types::{api_error, ApiError, ModelInfo};
+use crate::warm::WarmStats;
+use crate::webhooks::DeadLetterSummary;
+use crate::AppState;
+
+/// Build the admin router
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/usage", get(export_usage))
+        .route("/usage/close", post(close_usage_period))
+        .route("/usage/closed", get(list_closed_usage))
+        .route("/usage/closed/:period_id/ack", post(acknowledge_usage))
+        .route("/quotas", get(list_quotas))
+        .route(
+            "/quotas/:key_id",
+            get(get_quota).put(set_quota).delete(delete_quota),
+        )
+        .route("/keys", get(list_keys).post(create_key))
+        .route("/keys/:key_id/disable", post(disable_key))
+        .route("/keys/:key_id/rotate", post(rotate_key))
+        .route("/signing-keys/rotate", post(rotate_signing_key))
+        .route("/audit", get(get_audit_log))
+        .route("/gc", get(get_gc_stats).post(run_gc))
+        .route("/queue", get(get_queue_stats))
+        .route("/overview", get(overview::get_overview))
+        .route("/flags", get(flags::get_flags))
+        .route("/flags/:flag", axum::routing::put(flags::set_flag))
+        .route("/experiments", get(experiments::list_experiments))
+        .route(
+            "/experiments/:model_id",
+            axum::routing::put(experiments::set_experiment).delete(experiments::delete_experiment),
+        )
+        .route("/tenants", get(tenants::list_tenant_defaults))
+        .route(
+            "/tenants/:tenant/defaults",
+            get(tenants::get_tenant_defaults)
+                .put(tenants::set_tenant_defaults)
+                .delete(tenants::delete_tenant_defaults),
+        )
+        .route("/shadow", get(shadow::get_shadow).put(shadow::set_shadow))
+        .route("/snapshot", get(snapshot::get_snapshot))
+        .route("/restore", post(snapshot::restore_snapshot))
+        .route("/import", post(migrate::import))
+        .route("/cluster", get(cluster::get_cluster))
+        .route(
+            "/backups",
+            get(backup::list_backups).post(backup::run_backup),
+        )
+        .route("/backups/:id/restore", post(backup::restore_backup))
+        .route("/storage", get(get_storage_usage))
+        .route("/models/:id", axum::routing::delete(purge_model))
+        .route("/models/:id/archive", post(archive_model))
+        .route("/models/:id/restore", post(restore_model))
+        .route("/models/:id/pin", post(pin_model))
+        .route("/models/:id/unpin", post(unpin_model))
+        .route("/models/:id/shards", axum::routing::put(set_model_shards))
+        .route("/models/:id/hot", axum::routing::put(set_model_hot))
+        .route("/models/:id/limits", axum::routing::put(set_model_limits))
+        .route("/webhooks/dead-letters", get(list_dead_letters))
+        .route(
+            "/webhooks/dead-letters/:id",
+            axum::routing::delete(drop_dead_letter),
+        )
+        .route("/webhooks/dead-letters/:id/retry", post(retry_dead_letter))
+}
+
+/// Export usage of the open metering period
+async fn export_usage(_admin: AdminAuth, State(state): State<Arc<AppState>>) -> Json<UsageReport> {
+    Json(state.meter.snapshot())
+}
+
+/// Close the open metering period and export its usage for billing
+async fn close_usage_period(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<UsageReport>, ApiError> {
+    let report = state.meter.close_period().map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "USAGE_CLOSE_FAILED",
+            e.to_string(),
+        )
+    })?;
+    tracing::info!("Closed metering period {}", report.period_id);
+    Ok(Json(report))
+}
+
+/// Closed metering periods that were not acknowledged yet
+async fn list_closed_usage(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<UsageReport>> {
+    Json(state.meter.closed_periods())
+}
+
+/// Acknowledge a billed period, dropping its report
+async fn acknowledge_usage(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(period_id): Path<u64>,
+) -> StatusCode {
+    match state.meter.acknowledge(period_id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::error!("Failed to acknowledge metering period {}: {}", period_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// List all keys with configured quotas
+async fn list_quotas(_admin: AdminAuth, State(state): State<Arc<AppState>>) -> Json<Vec<KeyQuota>> {
+    Json(state.quotas.list())
+}
+
+/// Get limits and current usage for a key
+async fn get_quota(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+) -> Result<Json<KeyQuota>, ApiError> {
+    state.quotas.get(&key_id).map(Json).ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            "QUOTA_NOT_FOUND",
+            format!("No quota configured for {}", key_id),
+        )
+    })
+}
+
+/// Set the limits for a key
+async fn set_quota(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+    Json(limits): Json<QuotaLimits>,
+) -> Result<Json<KeyQuota>, ApiError> {
+    tracing::info!("Setting quota for {}", key_id);
+    state.quotas.set_limits(&key_id, limits).map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "QUOTA_UPDATE_FAILED",
+            e.to_string(),
+        )
+    })?;
+    get_quota(AdminAuth, State(state), Path(key_id)).await
+}
+
+/// Remove the limits for a key
+async fn delete_quota(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+) -> StatusCode {
+    match state.quotas.remove_limits(&key_id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::error!("Failed to remove quota for {}: {}", key_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// List issued API keys (secrets and hashes are never returned)
+async fn list_keys(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<ApiKeyRecord>> {
+    Json(state.api_keys.list())
+}
+
+/// Issue a new API key; the secret is only returned in this response
+async fn create_key(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreateApiKey>,
+) -> Result<Json<IssuedApiKey>, ApiError> {
+    let issued = state.api_keys.create(request).map_err(|e| {
+        api_error(
+            StatusCode::BAD_REQUEST,
+            "KEY_CREATION_FAILED",
+            e.to_string(),
+        )
+    })?;
+    tracing::info!(
+        "Issued API key {} for tenant {}",
+        issued.record.key_id,
+        issued.record.tenant
+    );
+    Ok(Json(issued))
+}
+
+/// Disable an API key immediately
+async fn disable_key(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKeyRecord>, ApiError> {
+    let record = state
+        .api_keys
+        .disable(&key_id)
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, "KEY_NOT_FOUND", e.to_string()))?;
+    tracing::info!("Disabled API key {}", key_id);
+    Ok(Json(record))
+}
+
+#[derive(Deserialize)]
+struct RotateQuery {
+    /// Seconds the previous secret stays valid
+    #[serde(default)]
+    grace_secs: u64,
+}
+
+/// Rotate an API key's secret
+async fn rotate_key(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+    Query(query): Query<RotateQuery>,
+) -> Result<Json<IssuedApiKey>, ApiError> {
+    let issued = state
+        .api_keys
+        .rotate(&key_id, query.grace_secs)
+        .map_err(|e| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "KEY_ROTATION_FAILED",
+                e.to_string(),
+            )
+        })?;
+    tracing::info!("Rotated API key {} (grace {}s)", key_id, query.grace_secs);
+    Ok(Json(issued))
+}
+
+/// Rotate the service signing key immediately
+async fn rotate_signing_key(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PublicKeyInfo>>, ApiError> {
+    state.signing_keys.rotate().await.map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "KEY_ROTATION_FAILED",
+            e.to_string(),
+        )
+    })?;
+    Ok(Json(state.signing_keys.public_keys()))
+}
+
+#[derive(Serialize)]
+struct AuditLogResponse {
+    /// Whether the hash chain verified
+    verified: bool,
+    error: Option<String>,
+    entries: Vec<AuditEntry>,
+}
+
+/// Registry audit log with a fresh verification of its hash chain
+async fn get_audit_log(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AuditLogResponse>, ApiError> {
+    let (entries, verified) = state.audit.entries().map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "AUDIT_LOG_UNREADABLE",
+            e.to_string(),
+        )
+    })?;
+    Ok(Json(AuditLogResponse {
+        verified: verified.is_ok(),
+        error: verified.err().map(|e| e.to_string()),
+        entries,
+    }))
+}
+
+/// Cumulative garbage collection metrics
+async fn get_gc_stats(_admin: AdminAuth, State(state): State<Arc<AppState>>) -> Json<GcStats> {
+    Json(state.gc.stats())
+}
+
+/// Storage held by proofs, job records, checkpoints and models
+async fn get_storage_usage(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<StorageUsage> {
+    Json(state.retention.usage(&state).await)
+}
+
+/// Proving queue depth, per-tenant wait times and per-model usage
+async fn get_queue_stats(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<QueueStats> {
+    let mut stats = state.queue.stats();
+    stats.shed = state.admission.shed_count();
+    Json(stats)
+}
+
+#[derive(Deserialize)]
+struct GcQuery {
+    /// Report what would be collected without deleting anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Sweep orphaned artifacts now
+async fn run_gc(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<GcQuery>,
+) -> Json<GcReport> {
+    Json(state.gc.run(&state, query.dry_run).await)
+}
+
+#[derive(Serialize)]
+struct ModelStatus {
+    model_id: String,
+    name: String,
+    archived_at: Option<u64>,
+    /// Earliest time the archived model may be purged
+    purge_after: Option<u64>,
+    shards: u32,
+    hot: bool,
+    pinned: bool,
+    #[serde(skip_serializing_if = "ModelLimits::is_unlimited")]
+    limits: ModelLimits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warm: Option<WarmStats>,
+}
+
+impl ModelStatus {
+    fn new(model: &ModelInfo, retention_secs: u64) -> Self {
+        Self {
+            model_id: model.id.clone(),
+            name: model.name.clone(),
+            archived_at: model.archived_at,
+            purge_after: model.archived_at.map(|t| t + retention_secs),
+            shards: model.shards,
+            hot: model.hot,
+            pinned: model.pinned,
+            limits: model.limits,
+            warm: None,
+        }
+    }
+}
+
+/// Archive a model: it stops serving new proofs but stays registered
+async fn archive_model(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    set_archived(&state, &model_id, Some(now_secs())).await
+}
+
+/// Return an archived model to service
+async fn restore_model(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    set_archived(&state, &model_id, None).await
+}
+
+/// Pin a business-critical model: until unpinned it cannot be archived or
+/// purged, and its warm state is not released
+async fn pin_model(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    set_pinned(&state, &model_id, true).await
+}
+
+/// Make a pinned model subject to archiving and eviction again
+async fn unpin_model(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    set_pinned(&state, &model_id, false).await
+}
+
+#[derive(Deserialize)]
+struct SetShardsRequest {
+    shards: u32,
+}
+
+/// Tune how many shards a model's proofs are split into
+async fn set_model_shards(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+    Json(body): Json<SetShardsRequest>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let shards = sharding::validate_shards(body.shards)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_SHARDS", e.to_string()))?;
+    let mut prover = state.prover.write().await;
+    let model = prover
+        .set_shards(&model_id, shards)
+        .ok_or_else(|| model_not_found(&model_id))?;
+    tracing::info!("Model {} now proves in {} shards", model_id, shards);
+    Ok(Json(ModelStatus::new(
+        model,
+        state.config.model_retention_secs,
+    )))
+}
+
+/// Cap a model's concurrent proofs and requests per minute
+async fn set_model_limits(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+    Json(body): Json<ModelLimits>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let limits = body
+        .validate()
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_LIMITS", e.to_string()))?;
+    let mut prover = state.prover.write().await;
+    let model = prover
+        .set_limits(&model_id, limits)
+        .ok_or_else(|| model_not_found(&model_id))?;
+    tracing::info!("Model {} limits: {:?}", model_id, limits);
+    Ok(Json(ModelStatus::new(
+        model,
+        state.config.model_retention_secs,
+    )))
+}
+
+#[derive(Deserialize)]
+struct SetHotRequest {
+    hot: bool,
+}
+
+/// Pin a model as hot (warming it now) or release its warm state
+async fn set_model_hot(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+    Json(body): Json<SetHotRequest>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let mut prover = state.prover.write().await;
+    let pinned = prover
+        .get_model(&model_id)
+        .ok_or_else(|| model_not_found(&model_id))?
+        .pinned;
+    if pinned && !body.hot {
+        return Err(model_pinned(&model_id));
+    }
+    let model = prover.set_hot(&model_id, body.hot).await.map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "MODEL_WARMUP_FAILED",
+            e.to_string(),
+        )
+    })?;
+    let mut status = ModelStatus::new(model, state.config.model_retention_secs);
+    status.warm = prover.warm_stats(&model_id);
+    tracing::info!("Model {} hot: {}", model_id, body.hot);
+    Ok(Json(status))
+}
+
+async fn set_pinned(
+    state: &AppState,
+    model_id: &str,
+    pinned: bool,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let mut prover = state.prover.write().await;
+    let previous = prover
+        .get_model(model_id)
+        .ok_or_else(|| model_not_found(model_id))?
+        .pinned;
+    if previous != pinned {
+        let action = if pinned {
+            AuditAction::Pin
+        } else {
+            AuditAction::Unpin
+        };
+        state
+            .audit
+            .append(action, model_id, "admin", serde_json::json!({}))
+            .map_err(audit_failed)?;
+        tracing::info!("Model {} pinned: {}", model_id, pinned);
+    }
+
+    let model = prover
+        .set_pinned(model_id, pinned)
+        .ok_or_else(|| model_not_found(model_id))?;
+    let mut status = ModelStatus::new(model, state.config.model_retention_secs);
+    status.warm = prover.warm_stats(model_id);
+    Ok(Json(status))
+}
+
+async fn set_archived(
+    state: &AppState,
+    model_id: &str,
+    archived_at: Option<u64>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let mut prover = state.prover.write().await;
+    let model = prover
+        .get_model(model_id)
+        .ok_or_else(|| model_not_found(model_id))?;
+    if model.pinned && archived_at.is_some() {
+        return Err(model_pinned(model_id));
+    }
+    let previous = model.archived_at;
+    if previous.is_some() == archived_at.is_some() {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            if previous.is_some() {
+                "MODEL_ARCHIVED"
+            } else {
+                "MODEL_NOT_ARCHIVED"
+            },
+            format!(
+                "Model {} is {}archived",
+                model_id,
+                if previous.is_some() {
+                    "already "
+                } else {
+                    "not "
+                }
+            ),
+        ));
+    }
+
+    let action = if archived_at.is_some() {
+        AuditAction::Archive
+    } else {
+        AuditAction::Restore
+    };
+    state
+        .audit
+        .append(action, model_id, "admin", serde_json::json!({}))
+        .map_err(audit_failed)?;
+
+    let model = prover
+        .set_archived(model_id, archived_at)
+        .ok_or_else(|| model_not_found(model_id))?;
+    tracing::info!(
+        "Model {} {}",
+        model_id,
+        if archived_at.is_some() {
+            "archived"
+        } else {
+            "restored"
+        }
+    );
+    Ok(Json(ModelStatus::new(
+        model,
+        state.config.model_retention_secs,
+    )))
+}
+
+/// Permanently remove an archived model once its retention window has passed
+async fn purge_model(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.config.immutable_registry {
+        return Err(api_error(
+            StatusCode::FORBIDDEN,
+            "REGISTRY_IMMUTABLE",
+            "Models cannot be purged while the registry is immutable",
+        ));
+    }
+
+    let mut prover = state.prover.write().await;
+    let model = prover
+        .get_model(&model_id)
+        .ok_or_else(|| model_not_found(&model_id))?;
+    if model.pinned {
+        return Err(model_pinned(&model_id));
+    }
+    let status = ModelStatus::new(model, state.config.model_retention_secs);
+    match status.purge_after {
+        None => {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "MODEL_NOT_ARCHIVED",
+                "Only archived models can be purged",
+            ))
+        }
+        Some(purge_after) if purge_after > now_secs() => {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "RETENTION_NOT_ELAPSED",
+                format!(
+                    "Model {} may not be purged before {}",
+                    model_id, purge_after
+                ),
+            ))
+        }
+        Some(_) => {}
+    }
+
+    state
+        .audit
+        .append(
+            AuditAction::Purge,
+            &model_id,
+            "admin",
+            serde_json::json!({ "name": status.name, "commitment": model.commitment }),
+        )
+        .map_err(audit_failed)?;
+    prover.purge_model(&model_id);
+    state.setups.remove(&model_id);
+    state.warmups.remove(&model_id);
+    tracing::info!("Purged model {}", model_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Webhook deliveries that exhausted their retries
+async fn list_dead_letters(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<DeadLetterSummary>> {
+    Json(state.webhooks.dead_letters())
+}
+
+/// Redeliver a dead-lettered webhook (dead-lettered again if it keeps failing)
+async fn retry_dead_letter(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.webhooks.retry_dead_letter(&id) {
+        Ok(true) => Ok(StatusCode::ACCEPTED),
+        Ok(false) => Err(dead_letter_not_found(&id)),
+        Err(e) => Err(dead_letter_update_failed(e)),
+    }
+}
+
+/// Discard a dead-lettered webhook
+async fn drop_dead_letter(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.webhooks.drop_dead_letter(&id) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(dead_letter_not_found(&id)),
+        Err(e) => Err(dead_letter_update_failed(e)),
+    }
+}
+
+fn dead_letter_not_found(id: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "DEAD_LETTER_NOT_FOUND",
+        format!("No dead letter {}", id),
+    )
+}
+
+fn dead_letter_update_failed(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "DEAD_LETTER_UPDATE_FAILED",
+        e.to_string(),
+    )
+}
+
+fn model_not_found(model_id: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "MODEL_NOT_FOUND",
+        format!("Model not found: {}", model_id),
+    )
+}
+
+fn model_pinned(model_id: &str) -> ApiError {
+    api_error(
+        StatusCode::CONFLICT,
+        "MODEL_PINNED",
+        format!("Model {} is pinned; unpin it first", model_id),
+    )
+}
+
+fn audit_failed(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "AUDIT_LOG_FAILED",
+        e.to_string(),
+    )
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
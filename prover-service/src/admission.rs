@@ -0,0 +1,214 @@
+//! Load-based admission control
+//!
+//! Under overload, accepting more proving work only makes every request
+//! slower. With any of these thresholds set, new synchronous proving
+//! requests (`POST /prove`, `/prove/stream` and `/replay/:proof_id`) are shed
+//! with 503 `OVERLOADED` and a `Retry-After` of `ADMISSION_RETRY_AFTER_SECS`
+//! while the service is over one of them:
+//!
+//! - `ADMISSION_MAX_LOAD`: 1-minute load average per CPU
+//! - `ADMISSION_MIN_AVAILABLE_MB`: memory available to new work
+//! - `ADMISSION_MAX_QUEUE_DEPTH`: proving jobs waiting for a slot
+//!
+//! Verification, health checks and everything else are never shed, so the
+//! service stays observable and keeps verifying while it sheds proving
+//! load. Durable jobs (`POST /jobs`) are queued rather than shed. CPU load
+//! and memory are read from `/proc` and are not checked on systems without
+//! it.
+
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::config::ServiceConfig;
+use crate::types::api_error;
+use crate::AppState;
+
+/// Load thresholds over which proving requests are shed
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdmissionThresholds {
+    pub max_load_per_cpu: Option<f64>,
+    pub min_available_mb: Option<u64>,
+    pub max_queue_depth: Option<usize>,
+}
+
+/// System load as sampled for an admission decision
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct LoadSample {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_per_cpu: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_mb: Option<u64>,
+    pub queue_depth: usize,
+}
+
+impl AdmissionThresholds {
+    /// Why `sample` is over a threshold, if it is
+    pub fn exceeded(&self, sample: &LoadSample) -> Option<String> {
+        if let (Some(max), Some(load)) = (self.max_load_per_cpu, sample.load_per_cpu) {
+            if load > max {
+                return Some(format!("load {:.2} per CPU is over {:.2}", load, max));
+            }
+        }
+        if let (Some(min), Some(available)) = (self.min_available_mb, sample.available_mb) {
+            if available < min {
+                return Some(format!(
+                    "{}MB of memory available is under {}MB",
+                    available, min
+                ));
+            }
+        }
+        if let Some(max) = self.max_queue_depth {
+            if sample.queue_depth > max {
+                return Some(format!(
+                    "{} queued proving jobs is over {}",
+                    sample.queue_depth, max
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Admission decisions for proving requests
+pub struct AdmissionControl {
+    thresholds: AdmissionThresholds,
+    retry_after_secs: u64,
+    shed: AtomicU64,
+}
+
+impl AdmissionControl {
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            thresholds: config.admission,
+            retry_after_secs: config.admission_retry_after_secs.max(1),
+            shed: AtomicU64::new(0),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        let t = &self.thresholds;
+        t.max_load_per_cpu.is_some() || t.min_available_mb.is_some() || t.max_queue_depth.is_some()
+    }
+
+    /// Requests shed since the service started
+    pub fn shed_count(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether `method` `path` starts synchronous proving work
+fn is_proving(method: &Method, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    method == Method::POST
+        && (path == "/prove" || path == "/prove/stream" || path.starts_with("/replay/"))
+}
+
+/// Sample the system load, with `queue_depth` jobs waiting
+fn sample(queue_depth: usize) -> LoadSample {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    LoadSample {
+        load_per_cpu: std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| s.split_whitespace().next()?.parse::<f64>().ok())
+            .map(|load| load / cpus as f64),
+        available_mb: std::fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|s| mem_available_kb(&s))
+            .map(|kb| kb / 1024),
+        queue_depth,
+    }
+}
+
+/// `MemAvailable` of a `/proc/meminfo`, in kB
+fn mem_available_kb(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Middleware shedding proving requests while the service is overloaded
+pub async fn enforce(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let admission = &state.admission;
+    if !admission.enabled() || !is_proving(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let load = sample(state.queue.stats().queued);
+    let Some(reason) = admission.thresholds.exceeded(&load) else {
+        return next.run(request).await;
+    };
+    admission.shed.fetch_add(1, Ordering::Relaxed);
+    tracing::warn!("Shedding {}: {}", request.uri().path(), reason);
+    let mut response = api_error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "OVERLOADED",
+        format!("Service is overloaded ({}); retry later", reason),
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(admission.retry_after_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheds_only_proving_over_thresholds() {
+        let thresholds = AdmissionThresholds {
+            max_load_per_cpu: Some(2.0),
+            min_available_mb: Some(512),
+            max_queue_depth: Some(8),
+        };
+        let calm = LoadSample {
+            load_per_cpu: Some(0.5),
+            available_mb: Some(4096),
+            queue_depth: 2,
+        };
+        assert!(thresholds.exceeded(&calm).is_none());
+        for busy in [
+            LoadSample {
+                load_per_cpu: Some(3.0),
+                ..calm
+            },
+            LoadSample {
+                available_mb: Some(100),
+                ..calm
+            },
+            LoadSample {
+                queue_depth: 9,
+                ..calm
+            },
+        ] {
+            assert!(thresholds.exceeded(&busy).is_some());
+        }
+        // Unknown load is not held against the request
+        let unknown = LoadSample {
+            load_per_cpu: None,
+            available_mb: None,
+            queue_depth: 0,
+        };
+        assert!(thresholds.exceeded(&unknown).is_none());
+
+        assert!(is_proving(&Method::POST, "/prove"));
+        assert!(is_proving(&Method::POST, "/replay/proof:sha256:ab"));
+        assert!(!is_proving(&Method::POST, "/verify"));
+        assert!(!is_proving(&Method::GET, "/health"));
+
+        let meminfo = "MemTotal:       16384000 kB\nMemAvailable:    2048000 kB\n";
+        assert_eq!(mem_available_kb(meminfo), Some(2048000));
+    }
+}
@@ -0,0 +1,465 @@
+//! Model aliases
+//!
+//! Models can be referenced as `<name>:<tag>` instead of by id. The `latest`
+//! tag floats: `fraud-model:latest` (or plain `fraud-model`) always resolves
+//! to the most recent registration named `fraud-model`. Any other tag, e.g.
+//! `fraud-model:stable`, is pinned explicitly through `PUT /aliases/:alias`
+//! and persisted to `aliases.json` in the data directory.
+//!
+//! Proofs always record the resolved model id and commitment, never the
+//! alias, so a proof stays unambiguous after an alias moves.
+//!
+//! A pinned alias can also split `/prove` traffic for a blue/green rollout:
+//! with `split: {"model_id": <candidate>, "percent": 10}`, 10% of proofs
+//! through the alias use the candidate and the rest the pinned model. Each
+//! such proof carries a signed `rollout` tag in its public inputs naming the
+//! alias, the model actually used and whether it was the candidate, next to
+//! that model's commitment.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::api_keys::Scope;
+use crate::audit::AuditAction;
+use crate::auth::Caller;
+use crate::persist::{load_json, save_json};
+use crate::prover::JoltAtlasProver;
+use crate::types::{api_error, ApiError, ModelInfo};
+use crate::AppState;
+
+/// Floating tag that always tracks the newest registration
+pub const LATEST_TAG: &str = "latest";
+
+/// A pinned alias
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AliasRecord {
+    pub alias: String,
+    pub model_id: String,
+    /// Share of proving traffic sent to a candidate model instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<TrafficSplit>,
+    pub updated_at: u64,
+}
+
+/// Share of an alias's proving traffic sent to a candidate model
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficSplit {
+    pub model_id: String,
+    /// Percentage of proofs, 0-100, that use the candidate
+    pub percent: u8,
+}
+
+impl TrafficSplit {
+    /// Whether a request drawing `roll` (0-99) goes to the candidate
+    fn selects_candidate(&self, roll: u8) -> bool {
+        roll < self.percent
+    }
+}
+
+/// Which side of a traffic split served a proof
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RolloutTag {
+    pub alias: String,
+    /// Model the proof was generated with
+    pub model_id: String,
+    /// Whether that model is the alias's candidate rather than its pinned model
+    pub candidate: bool,
+    pub percent: u8,
+}
+
+/// Persistent store of pinned aliases
+pub struct AliasStore {
+    path: PathBuf,
+    aliases: Mutex<BTreeMap<String, AliasRecord>>,
+}
+
+impl AliasStore {
+    /// Open (or create) the alias store in the data directory
+    pub fn open(data_dir: &std::path::Path) -> Result<Self> {
+        let path = data_dir.join("aliases.json");
+        let aliases = load_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            path,
+            aliases: Mutex::new(aliases),
+        })
+    }
+
+    /// All pinned aliases
+    pub fn list(&self) -> Vec<AliasRecord> {
+        let aliases = self.aliases.lock().unwrap();
+        aliases.values().cloned().collect()
+    }
+
+    /// A pinned alias
+    pub fn get(&self, alias: &str) -> Option<AliasRecord> {
+        self.aliases.lock().unwrap().get(alias).cloned()
+    }
+
+    /// Pin `alias` to a model, optionally splitting traffic with a candidate
+    pub fn set(
+        &self,
+        alias: &str,
+        model_id: &str,
+        split: Option<TrafficSplit>,
+    ) -> Result<AliasRecord> {
+        let record = AliasRecord {
+            alias: alias.to_string(),
+            model_id: model_id.to_string(),
+            split,
+            updated_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        };
+        let mut aliases = self.aliases.lock().unwrap();
+        aliases.insert(alias.to_string(), record.clone());
+        save_json(&self.path, &*aliases)?;
+        Ok(record)
+    }
+
+    /// Remove a pinned alias
+    pub fn remove(&self, alias: &str) -> Result<Option<AliasRecord>> {
+        let mut aliases = self.aliases.lock().unwrap();
+        let removed = aliases.remove(alias);
+        save_json(&self.path, &*aliases)?;
+        Ok(removed)
+    }
+
+    /// Replace the aliases with the same names by `records`
+    pub fn restore(&self, records: Vec<AliasRecord>) -> Result<()> {
+        let mut aliases = self.aliases.lock().unwrap();
+        for record in records {
+            aliases.insert(record.alias.clone(), record);
+        }
+        save_json(&self.path, &*aliases)
+    }
+
+    /// Resolve a model id, pinned alias or `<name>[:latest]` to a model id
+    pub fn resolve(&self, reference: &str, prover: &JoltAtlasProver) -> Option<String> {
+        if prover.get_model(reference).is_some() {
+            return Some(reference.to_string());
+        }
+        if let Some(record) = self.aliases.lock().unwrap().get(reference) {
+            return Some(record.model_id.clone());
+        }
+        let (name, tag) = reference.split_once(':').unwrap_or((reference, LATEST_TAG));
+        if tag == LATEST_TAG {
+            prover.latest_model(name).map(|m| m.id.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Resolve a reference for proving, drawing a side of the alias's
+    /// traffic split if it has one
+    pub fn route(
+        &self,
+        reference: &str,
+        prover: &JoltAtlasProver,
+    ) -> Option<(String, Option<RolloutTag>)> {
+        let split = self
+            .get(reference)
+            .and_then(|record| Some((record.model_id, record.split?)));
+        match split {
+            // A model registered under the alias's own name shadows it
+            Some((model_id, split)) if prover.get_model(reference).is_none() => {
+                let candidate = split.selects_candidate(rand::thread_rng().gen_range(0..100));
+                let tag = RolloutTag {
+                    alias: reference.to_string(),
+                    model_id: if candidate { split.model_id } else { model_id },
+                    candidate,
+                    percent: split.percent,
+                };
+                Some((tag.model_id.clone(), Some(tag)))
+            }
+            _ => self.resolve(reference, prover).map(|id| (id, None)),
+        }
+    }
+}
+
+/// Split and validate a pinnable alias
+fn parse_alias(alias: &str) -> Result<(&str, &str)> {
+    let (name, tag) = alias
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Alias must have the form <name>:<tag>"))?;
+    if name.is_empty() || tag.is_empty() || tag.contains(':') {
+        return Err(anyhow!("Alias must have the form <name>:<tag>"));
+    }
+    if tag == LATEST_TAG {
+        return Err(anyhow!("The `latest` tag floats and cannot be pinned"));
+    }
+    Ok((name, tag))
+}
+
+/// Routes mounted under `/aliases`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/", get(list_aliases)).route(
+        "/:alias",
+        get(get_alias).put(set_alias).delete(delete_alias),
+    )
+}
+
+#[derive(Deserialize)]
+struct SetAliasRequest {
+    model_id: String,
+    #[serde(default)]
+    split: Option<TrafficSplit>,
+}
+
+#[derive(Serialize)]
+struct ResolvedAlias {
+    alias: String,
+    model_id: String,
+    commitment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    split: Option<ResolvedSplit>,
+}
+
+#[derive(Serialize)]
+struct ResolvedSplit {
+    model_id: String,
+    commitment: String,
+    percent: u8,
+}
+
+async fn list_aliases(State(state): State<Arc<AppState>>) -> Json<Vec<AliasRecord>> {
+    Json(state.aliases.list())
+}
+
+/// Resolve any reference (pinned or floating) to a model id and commitment
+async fn get_alias(
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+) -> Result<Json<ResolvedAlias>, ApiError> {
+    let prover = state.prover.read().await;
+    let model = state
+        .aliases
+        .resolve(&alias, &prover)
+        .and_then(|id| prover.get_model(&id))
+        .ok_or_else(|| alias_not_found(&alias))?;
+    let split = state
+        .aliases
+        .get(&alias)
+        .filter(|record| record.model_id == model.id)
+        .and_then(|record| record.split)
+        .and_then(|split| {
+            let candidate = prover.get_model(&split.model_id)?;
+            Some(ResolvedSplit {
+                model_id: candidate.id.clone(),
+                commitment: candidate.commitment.clone(),
+                percent: split.percent,
+            })
+        });
+    Ok(Json(ResolvedAlias {
+        model_id: model.id.clone(),
+        commitment: model.commitment.clone(),
+        alias,
+        split,
+    }))
+}
+
+/// Pin an alias to a registered model of the same name
+async fn set_alias(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(alias): Path<String>,
+    Json(request): Json<SetAliasRequest>,
+) -> Result<Json<AliasRecord>, ApiError> {
+    caller.require(Scope::Register)?;
+    let (name, _) = parse_alias(&alias)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_ALIAS", e.to_string()))?;
+
+    let prover = state.prover.read().await;
+    let model = aliasable_model(&prover, &alias, name, &request.model_id)?;
+    let candidate = match &request.split {
+        Some(split) => {
+            if split.percent > 100 {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_SPLIT",
+                    "Split percent must be between 0 and 100",
+                ));
+            }
+            if split.model_id == model.id {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_SPLIT",
+                    "A split needs a candidate other than the pinned model",
+                ));
+            }
+            Some(aliasable_model(&prover, &alias, name, &split.model_id)?)
+        }
+        None => None,
+    };
+
+    let record = state
+        .aliases
+        .set(&alias, &model.id, request.split.clone())
+        .map_err(internal_error)?;
+    let details = match (&request.split, candidate) {
+        (Some(split), Some(candidate)) => serde_json::json!({
+            "alias": alias,
+            "commitment": model.commitment,
+            "split": {
+                "model_id": candidate.id,
+                "commitment": candidate.commitment,
+                "percent": split.percent,
+            },
+        }),
+        _ => serde_json::json!({ "alias": alias, "commitment": model.commitment }),
+    };
+    state
+        .audit
+        .append(AuditAction::SetAlias, &model.id, &caller.key_id, details)
+        .map_err(internal_error)?;
+
+    match &request.split {
+        Some(split) => tracing::info!(
+            "Alias {} now points at {}, sending {}% to {}",
+            alias,
+            model.id,
+            split.percent,
+            split.model_id
+        ),
+        None => tracing::info!("Alias {} now points at {}", alias, model.id),
+    }
+    Ok(Json(record))
+}
+
+/// A registered, live model named `name` that `alias` may point at
+fn aliasable_model<'a>(
+    prover: &'a JoltAtlasProver,
+    alias: &str,
+    name: &str,
+    model_id: &str,
+) -> Result<&'a ModelInfo, ApiError> {
+    let model = prover
+        .get_model(model_id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "MODEL_NOT_FOUND", "Model not found"))?;
+    if model.archived_at.is_some() {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            "MODEL_ARCHIVED",
+            format!("Model {} is archived", model.id),
+        ));
+    }
+    if model.name != name {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ALIAS",
+            format!(
+                "Alias {} cannot point at model {} named {}",
+                alias, model.id, model.name
+            ),
+        ));
+    }
+    Ok(model)
+}
+
+/// Remove a pinned alias (refused when the registry is immutable)
+async fn delete_alias(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    caller.require(Scope::Register)?;
+    if state.config.immutable_registry {
+        return Err(api_error(
+            StatusCode::FORBIDDEN,
+            "REGISTRY_IMMUTABLE",
+            "Aliases cannot be deleted while the registry is immutable",
+        ));
+    }
+
+    let removed = state
+        .aliases
+        .remove(&alias)
+        .map_err(internal_error)?
+        .ok_or_else(|| alias_not_found(&alias))?;
+    state
+        .audit
+        .append(
+            AuditAction::RemoveAlias,
+            &removed.model_id,
+            &caller.key_id,
+            serde_json::json!({ "alias": alias }),
+        )
+        .map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn alias_not_found(alias: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "ALIAS_NOT_FOUND",
+        format!("No model matches {}", alias),
+    )
+}
+
+fn internal_error(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "ALIAS_UPDATE_FAILED",
+        e.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alias() {
+        assert_eq!(
+            parse_alias("fraud-model:stable").unwrap(),
+            ("fraud-model", "stable")
+        );
+        assert!(parse_alias("fraud-model").is_err());
+        assert!(parse_alias("fraud-model:latest").is_err());
+        assert!(parse_alias(":stable").is_err());
+    }
+
+    #[test]
+    fn test_pinned_aliases_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AliasStore::open(dir.path()).unwrap();
+        store.set("fraud-model:stable", "m1", None).unwrap();
+
+        let reopened = AliasStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.list()[0].model_id, "m1");
+        assert!(reopened.remove("fraud-model:stable").unwrap().is_some());
+        assert!(reopened.list().is_empty());
+    }
+
+    #[test]
+    fn test_traffic_split_selection() {
+        let split = TrafficSplit {
+            model_id: "m2".to_string(),
+            percent: 10,
+        };
+        let candidate = (0..100)
+            .filter(|&roll| split.selects_candidate(roll))
+            .count();
+        assert_eq!(candidate, 10);
+        assert!(!TrafficSplit {
+            percent: 0,
+            ..split.clone()
+        }
+        .selects_candidate(0));
+        assert!(TrafficSplit {
+            percent: 100,
+            ..split
+        }
+        .selects_candidate(99));
+    }
+}
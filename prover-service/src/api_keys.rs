@@ -0,0 +1,355 @@
+//! API key lifecycle
+//!
+//! Keys are created, listed, disabled and rotated through the admin API and
+//! persisted to `api_keys.json` in the data directory. Only the SHA-256 hash
+//! of each secret is stored; the secret itself is returned exactly once, at
+//! creation or rotation. A key's id stays stable across rotations so quotas
+//! and metering keep following it.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::persist::{load_json, save_json};
+use crate::verification::ct_eq;
+
+/// Operation a key is permitted to perform
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Generate proofs
+    Prove,
+    /// Verify proofs
+    Verify,
+    /// Register models
+    Register,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Prove => "prove",
+            Scope::Verify => "verify",
+            Scope::Register => "register",
+        }
+    }
+}
+
+/// Stored API key (never contains the secret)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    pub name: String,
+    pub tenant: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub disabled: bool,
+    pub rotated_at: Option<u64>,
+
+    /// SHA-256 of the current secret
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    key_hash: String,
+
+    /// SHA-256 of the pre-rotation secret, accepted until `previous_expires_at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_hash: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_expires_at: Option<u64>,
+}
+
+impl ApiKeyRecord {
+    /// Whether the key has passed its expiry
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
+
+    /// Public view of the record with hashes stripped
+    pub fn redacted(&self) -> ApiKeyRecord {
+        ApiKeyRecord {
+            key_hash: String::new(),
+            previous_hash: None,
+            ..self.clone()
+        }
+    }
+
+    fn matches(&self, hash: &str, now: u64) -> bool {
+        if ct_eq(&self.key_hash, hash) {
+            return true;
+        }
+        match (&self.previous_hash, self.previous_expires_at) {
+            (Some(prev), Some(until)) => ct_eq(prev, hash) && now < until,
+            _ => false,
+        }
+    }
+}
+
+/// Parameters for creating a key
+#[derive(Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub tenant: String,
+    pub scopes: Vec<Scope>,
+    /// Lifetime in seconds (no expiry when omitted)
+    pub expires_in_secs: Option<u64>,
+}
+
+/// A key record together with its one-time secret
+#[derive(Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub record: ApiKeyRecord,
+    pub secret: String,
+}
+
+/// Outcome of looking up a presented secret
+pub enum KeyLookup {
+    Valid(ApiKeyRecord),
+    Disabled(String),
+    Expired(String),
+    Unknown,
+}
+
+/// Persistent API key store
+pub struct ApiKeyStore {
+    path: PathBuf,
+    keys: Mutex<BTreeMap<String, ApiKeyRecord>>,
+}
+
+impl ApiKeyStore {
+    /// Open (or create) the key store in the data directory
+    pub fn open(data_dir: &std::path::Path) -> Result<Self> {
+        let path = data_dir.join("api_keys.json");
+        let keys: BTreeMap<String, ApiKeyRecord> = load_json(&path)?.unwrap_or_default();
+        tracing::info!("Loaded {} API keys from {}", keys.len(), path.display());
+        Ok(Self {
+            path,
+            keys: Mutex::new(keys),
+        })
+    }
+
+    /// Issue a new key
+    pub fn create(&self, request: CreateApiKey) -> Result<IssuedApiKey> {
+        if request.scopes.is_empty() {
+            return Err(anyhow!("At least one scope is required"));
+        }
+
+        let now = now_secs();
+        let secret = generate_secret();
+        let record = ApiKeyRecord {
+            key_id: format!("key_{}", &uuid::Uuid::new_v4().simple().to_string()[..16]),
+            name: request.name,
+            tenant: request.tenant,
+            scopes: request.scopes,
+            created_at: now,
+            expires_at: request.expires_in_secs.map(|secs| now + secs),
+            disabled: false,
+            rotated_at: None,
+            key_hash: hash_secret(&secret),
+            previous_hash: None,
+            previous_expires_at: None,
+        };
+
+        self.update(|keys| {
+            keys.insert(record.key_id.clone(), record.clone());
+            Ok(())
+        })?;
+
+        Ok(IssuedApiKey {
+            record: record.redacted(),
+            secret,
+        })
+    }
+
+    /// All keys, redacted
+    pub fn list(&self) -> Vec<ApiKeyRecord> {
+        let keys = self.keys.lock().unwrap();
+        keys.values().map(ApiKeyRecord::redacted).collect()
+    }
+
+    /// All keys with their secret hashes, for a registry snapshot
+    pub fn snapshot(&self) -> Vec<ApiKeyRecord> {
+        self.keys.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Replace the keys with the same ids by `records`
+    pub fn restore(&self, records: Vec<ApiKeyRecord>) -> Result<()> {
+        self.update(|keys| {
+            for record in records {
+                keys.insert(record.key_id.clone(), record);
+            }
+            Ok(())
+        })
+    }
+
+    /// Disable a key immediately
+    pub fn disable(&self, key_id: &str) -> Result<ApiKeyRecord> {
+        self.update(|keys| {
+            let record = keys
+                .get_mut(key_id)
+                .ok_or_else(|| anyhow!("API key not found: {}", key_id))?;
+            record.disabled = true;
+            Ok(record.redacted())
+        })
+    }
+
+    /// Replace a key's secret, keeping the old one valid for `grace_secs`
+    pub fn rotate(&self, key_id: &str, grace_secs: u64) -> Result<IssuedApiKey> {
+        let now = now_secs();
+        let secret = generate_secret();
+
+        let redacted = self.update(|keys| {
+            let record = keys
+                .get_mut(key_id)
+                .ok_or_else(|| anyhow!("API key not found: {}", key_id))?;
+            if record.disabled {
+                return Err(anyhow!("Cannot rotate disabled key {}", key_id));
+            }
+
+            let old_hash = std::mem::replace(&mut record.key_hash, hash_secret(&secret));
+            if grace_secs > 0 {
+                record.previous_hash = Some(old_hash);
+                record.previous_expires_at = Some(now + grace_secs);
+            } else {
+                record.previous_hash = None;
+                record.previous_expires_at = None;
+            }
+            record.rotated_at = Some(now);
+            Ok(record.redacted())
+        })?;
+
+        Ok(IssuedApiKey {
+            record: redacted,
+            secret,
+        })
+    }
+
+    /// Apply `change` to a copy of the keys and install it only once it is
+    /// persisted, so a failed write leaves the store as it was
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut BTreeMap<String, ApiKeyRecord>) -> Result<T>,
+    ) -> Result<T> {
+        let mut keys = self.keys.lock().unwrap();
+        let mut updated = keys.clone();
+        let result = change(&mut updated)?;
+        save_json(&self.path, &updated)?;
+        *keys = updated;
+        Ok(result)
+    }
+
+    /// Resolve a presented secret
+    pub fn lookup(&self, secret: &str) -> KeyLookup {
+        let hash = hash_secret(secret);
+        let now = now_secs();
+        let keys = self.keys.lock().unwrap();
+
+        match keys.values().find(|record| record.matches(&hash, now)) {
+            Some(record) if record.disabled => KeyLookup::Disabled(record.key_id.clone()),
+            Some(record) if record.is_expired(now) => KeyLookup::Expired(record.key_id.clone()),
+            Some(record) => KeyLookup::Valid(record.redacted()),
+            None => KeyLookup::Unknown,
+        }
+    }
+}
+
+fn generate_secret() -> String {
+    format!(
+        "pk_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, ApiKeyStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::open(dir.path()).unwrap();
+        (dir, store)
+    }
+
+    fn create(store: &ApiKeyStore) -> IssuedApiKey {
+        store
+            .create(CreateApiKey {
+                name: "ci".to_string(),
+                tenant: "acme".to_string(),
+                scopes: vec![Scope::Prove],
+                expires_in_secs: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_create_lookup_and_persist() {
+        let (dir, store) = store();
+        let issued = create(&store);
+
+        assert!(matches!(store.lookup(&issued.secret), KeyLookup::Valid(r) if r.tenant == "acme"));
+        assert!(matches!(store.lookup("pk_wrong"), KeyLookup::Unknown));
+
+        let raw = std::fs::read_to_string(dir.path().join("api_keys.json")).unwrap();
+        assert!(!raw.contains(&issued.secret));
+
+        let reopened = ApiKeyStore::open(dir.path()).unwrap();
+        assert!(matches!(
+            reopened.lookup(&issued.secret),
+            KeyLookup::Valid(_)
+        ));
+    }
+
+    #[test]
+    fn test_rotate_with_grace_and_disable() {
+        let (_dir, store) = store();
+        let issued = create(&store);
+        let key_id = issued.record.key_id.clone();
+
+        let rotated = store.rotate(&key_id, 3600).unwrap();
+        assert_eq!(rotated.record.key_id, key_id);
+        assert!(matches!(store.lookup(&issued.secret), KeyLookup::Valid(_)));
+        assert!(matches!(store.lookup(&rotated.secret), KeyLookup::Valid(_)));
+
+        let rotated_again = store.rotate(&key_id, 0).unwrap();
+        assert!(matches!(store.lookup(&rotated.secret), KeyLookup::Unknown));
+
+        store.disable(&key_id).unwrap();
+        assert!(matches!(
+            store.lookup(&rotated_again.secret),
+            KeyLookup::Disabled(_)
+        ));
+    }
+
+    #[test]
+    fn test_failed_save_leaves_keys_unchanged() {
+        let (dir, store) = store();
+        let issued = create(&store);
+        let key_id = issued.record.key_id.clone();
+
+        // A directory in place of the document makes every save fail
+        let path = dir.path().join("api_keys.json");
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+
+        assert!(store.rotate(&key_id, 0).is_err());
+        assert!(store.disable(&key_id).is_err());
+        assert!(matches!(store.lookup(&issued.secret), KeyLookup::Valid(_)));
+    }
+}
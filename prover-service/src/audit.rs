@@ -0,0 +1,257 @@
+//! Registry audit log
+//!
+//! Every change to the model registry, and every runtime feature flag
+//! change, is appended to `registry_audit.jsonl` in the data directory. Each
+//! entry carries the hash of the entry before it, so editing or removing a
+//! past entry breaks the chain and is detected when the log is opened or
+//! checked through `GET /admin/audit`.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::verification::ct_eq;
+
+/// Hash preceding the first entry
+const GENESIS_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Kind of registry change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Register,
+    SetAlias,
+    RemoveAlias,
+    Archive,
+    Restore,
+    Purge,
+    Pin,
+    Unpin,
+    /// A runtime feature flag changed; `model_id` is empty
+    SetFlag,
+}
+
+/// One entry of the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub action: AuditAction,
+    pub model_id: String,
+    /// Key id of the caller that made the change
+    pub actor: String,
+    /// Action-specific details (name, commitments, ...)
+    pub details: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> Result<String> {
+        let unhashed = AuditEntry {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(serde_json::to_vec(&unhashed)?);
+        Ok(format!("0x{}", hex::encode(hasher.finalize())))
+    }
+}
+
+/// Append-only, hash-chained audit log
+pub struct AuditLog {
+    path: PathBuf,
+    /// `(next seq, hash of the last entry)`
+    head: Mutex<(u64, String)>,
+    /// Model name -> id of every registration ever logged
+    registered_names: Mutex<HashMap<String, String>>,
+}
+
+impl AuditLog {
+    /// Open the log, verifying the existing chain
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("registry_audit.jsonl");
+        let entries = read_entries(&path)?;
+        verify_chain(&entries)?;
+
+        let head = match entries.last() {
+            Some(last) => (last.seq + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        tracing::info!(
+            "Registry audit log {} verified ({} entries)",
+            path.display(),
+            entries.len()
+        );
+
+        let registered_names = entries
+            .iter()
+            .filter_map(|e| registered_name(e).map(|name| (name, e.model_id.clone())))
+            .collect();
+
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+            registered_names: Mutex::new(registered_names),
+        })
+    }
+
+    /// Append an entry, returning it with its hash
+    pub fn append(
+        &self,
+        action: AuditAction,
+        model_id: &str,
+        actor: &str,
+        details: Value,
+    ) -> Result<AuditEntry> {
+        let mut head = self.head.lock().unwrap();
+        let mut entry = AuditEntry {
+            seq: head.0,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            action,
+            model_id: model_id.to_string(),
+            actor: actor.to_string(),
+            details,
+            prev_hash: head.1.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.sync_data()?;
+
+        *head = (entry.seq + 1, entry.hash.clone());
+        if let Some(name) = registered_name(&entry) {
+            let mut names = self.registered_names.lock().unwrap();
+            names.insert(name, entry.model_id.clone());
+        }
+        Ok(entry)
+    }
+
+    /// Id of the model a name was (ever) registered under, per the log
+    pub fn model_for_name(&self, name: &str) -> Option<String> {
+        let names = self.registered_names.lock().unwrap();
+        names.get(name).cloned()
+    }
+
+    /// Whether the log records a registration of this model id
+    pub fn is_registered(&self, model_id: &str) -> bool {
+        let names = self.registered_names.lock().unwrap();
+        names.values().any(|id| id == model_id)
+    }
+
+    /// The log file as written, for backups
+    pub fn export(&self) -> Result<Vec<u8>> {
+        let _head = self.head.lock().unwrap();
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// All entries together with the result of re-verifying the chain
+    pub fn entries(&self) -> Result<(Vec<AuditEntry>, Result<()>)> {
+        let _head = self.head.lock().unwrap();
+        let entries = read_entries(&self.path)?;
+        let verified = verify_chain(&entries);
+        Ok((entries, verified))
+    }
+}
+
+fn registered_name(entry: &AuditEntry) -> Option<String> {
+    match entry.action {
+        AuditAction::Register => entry.details["name"].as_str().map(String::from),
+        _ => None,
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Malformed audit entry on line {}", i + 1))
+        })
+        .collect()
+}
+
+fn verify_chain(entries: &[AuditEntry]) -> Result<()> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.seq != i as u64 || !ct_eq(&entry.prev_hash, &prev_hash) {
+            return Err(anyhow!("Audit log chain broken at entry {}", i));
+        }
+        if !ct_eq(&entry.compute_hash()?, &entry.hash) {
+            return Err(anyhow!("Audit log entry {} has been modified", i));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_chain_survives_reopen_and_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::open(dir.path()).unwrap();
+        let first = log
+            .append(
+                AuditAction::Register,
+                "m1",
+                "anonymous",
+                json!({ "name": "a" }),
+            )
+            .unwrap();
+
+        let log = AuditLog::open(dir.path()).unwrap();
+        let second = log
+            .append(
+                AuditAction::Register,
+                "m2",
+                "anonymous",
+                json!({ "name": "b" }),
+            )
+            .unwrap();
+        assert_eq!(second.prev_hash, first.hash);
+        assert_eq!(log.model_for_name("a").as_deref(), Some("m1"));
+        assert!(log.entries().unwrap().1.is_ok());
+
+        let path = dir.path().join("registry_audit.jsonl");
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"name\":\"a\"", "\"name\":\"x\"");
+        std::fs::write(&path, tampered).unwrap();
+
+        assert!(log.entries().unwrap().1.is_err());
+        assert!(AuditLog::open(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Caller identification and admin authentication
+//!
+//! Callers identify themselves with an `X-API-Key` header. Keys issued
+//! through the admin API resolve to their stored key id, tenant and scopes.
+//! Unless `REQUIRE_API_KEY` is set, requests without a key are accepted as
+//! anonymous and attributed to the fixed `anonymous` tenant, as nothing
+//! vouches for any tenant they could name; a key that is presented but
+//! unknown is always rejected. Keys are never stored
+//! or logged in the clear.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap, StatusCode},
+};
+use std::sync::Arc;
+
+use crate::api_keys::{KeyLookup, Scope};
+use crate::types::{api_error, ApiError};
+use crate::verification::ct_eq;
+use crate::AppState;
+
+/// Header carrying the caller's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Tenant used when the caller does not name one
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Key id used for requests without an API key
+pub const ANONYMOUS_KEY_ID: &str = "anonymous";
+
+/// Tenant usage of requests without an API key is attributed to
+pub const ANONYMOUS_TENANT: &str = "anonymous";
+
+/// Identity of the caller making a request
+#[derive(Clone, Debug)]
+pub struct Caller {
+    /// Stable, non-secret identifier of the API key
+    pub key_id: String,
+
+    /// Tenant the usage is attributed to
+    pub tenant: String,
+
+    /// Permitted operations (`None` for callers not backed by an issued key)
+    pub scopes: Option<Vec<Scope>>,
+}
+
+impl Caller {
+    /// Anonymous caller of a request without an API key
+    pub fn anonymous() -> Self {
+        Self {
+            key_id: ANONYMOUS_KEY_ID.to_string(),
+            tenant: ANONYMOUS_TENANT.to_string(),
+            scopes: None,
+        }
+    }
+
+    /// Fail unless the caller's key grants `scope`
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(api_error(
+                StatusCode::FORBIDDEN,
+                "INSUFFICIENT_SCOPE",
+                format!(
+                    "API key {} lacks the '{}' scope",
+                    self.key_id,
+                    scope.as_str()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Caller {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let require_key = state.config.require_api_key;
+
+        let Some(secret) = header_str(&parts.headers, API_KEY_HEADER) else {
+            if require_key {
+                return Err(unauthorized("API key required"));
+            }
+            return Ok(Caller::anonymous());
+        };
+
+        match state.api_keys.lookup(secret) {
+            KeyLookup::Valid(record) => Ok(Caller {
+                key_id: record.key_id,
+                tenant: record.tenant,
+                scopes: Some(record.scopes),
+            }),
+            KeyLookup::Disabled(key_id) => {
+                Err(unauthorized(format!("API key {} is disabled", key_id)))
+            }
+            KeyLookup::Expired(key_id) => {
+                Err(unauthorized(format!("API key {} has expired", key_id)))
+            }
+            // A presented key is never ignored: falling back to an anonymous
+            // caller would let any made-up key shed a key's scopes and tenant
+            KeyLookup::Unknown => Err(unauthorized("Unknown API key")),
+        }
+    }
+}
+
+/// Proof that the request carried a valid admin token
+pub struct AdminAuth;
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AdminAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(expected) = state.config.admin_token.as_deref() else {
+            return Err(api_error(
+                StatusCode::FORBIDDEN,
+                "ADMIN_DISABLED",
+                "Admin API is disabled (ADMIN_TOKEN not set)",
+            ));
+        };
+
+        let provided = header_str(&parts.headers, AUTHORIZATION.as_str())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        match provided {
+            Some(token) if ct_eq(token, expected) => Ok(AdminAuth),
+            _ => Err(unauthorized("Missing or invalid admin token")),
+        }
+    }
+}
+
+fn unauthorized(message: impl Into<String>) -> ApiError {
+    api_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", message)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
@@ -0,0 +1,498 @@
+//! Backups to artifact storage
+//!
+//! With `BACKUP_INTERVAL_SECS` set, the service periodically ships a backup
+//! to the configured artifact storage (see `storage`): its objects under
+//! `backups/<backup-id>/`:
+//!
+//! - `registry.json`: a registry snapshot (models' metadata and commitments,
+//!   aliases, experiments, API key records), without signing keys;
+//! - `models/<model-id>.onnx`: the model files;
+//! - `proofs/<proof-id>.json`: the stored proofs;
+//! - `registry_audit.jsonl`: the registry audit log;
+//! - `transparency_log.jsonl`: the transparency log of issued proofs.
+//!
+//! Its manifest, `backups/<backup-id>.json`, records the SHA-256 and size of
+//! every object above. The manifest is written last, so a backup without one
+//! is incomplete.
+//!
+//! The last `BACKUP_KEEP` backups are kept. `POST /admin/backups` takes one
+//! now and `GET /admin/backups` lists them. `POST /admin/backups/:id/restore`
+//! downloads a backup, checks every object against the manifest, restores the
+//! registry (re-verifying each model's commitment, as `snapshot` does) and
+//! puts back stored proofs that are missing. The audit log is kept for
+//! forensics only: a restore appends its own entries to the local log. So is
+//! the transparency log, which is never rolled back.
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth::AdminAuth;
+use crate::snapshot::{self, RegistrySnapshot, RestoreReport};
+use crate::storage::Storage;
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// Key prefix of backups
+const PREFIX: &str = "backups/";
+
+/// Key prefix of stored proofs
+const PROOFS: &str = "proofs/";
+
+const REGISTRY: &str = "registry.json";
+const AUDIT_LOG: &str = "registry_audit.jsonl";
+const TRANSPARENCY_LOG: &str = "transparency_log.jsonl";
+
+/// An object of a backup
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Index of a complete backup
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub id: String,
+    pub created_at: u64,
+    /// Commitment of every backed-up model, by model id
+    pub models: BTreeMap<String, String>,
+    pub proofs: usize,
+    /// Every object, by key relative to the backup
+    pub objects: BTreeMap<String, ManifestEntry>,
+}
+
+impl BackupManifest {
+    /// Check `bytes` are the object `key` the manifest lists
+    fn check(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let entry = self
+            .objects
+            .get(key)
+            .ok_or_else(|| anyhow!("{} is not in the manifest of backup {}", key, self.id))?;
+        if entry.size != bytes.len() as u64 || entry.sha256 != hex::encode(Sha256::digest(bytes)) {
+            return Err(anyhow!("{} of backup {} is corrupt", key, self.id));
+        }
+        Ok(())
+    }
+}
+
+/// A backup as listed
+#[derive(Serialize)]
+pub struct BackupSummary {
+    pub id: String,
+    pub created_at: u64,
+    pub models: usize,
+    pub proofs: usize,
+    pub bytes: u64,
+}
+
+impl From<&BackupManifest> for BackupSummary {
+    fn from(manifest: &BackupManifest) -> Self {
+        Self {
+            id: manifest.id.clone(),
+            created_at: manifest.created_at,
+            models: manifest.models.len(),
+            proofs: manifest.proofs,
+            bytes: manifest.objects.values().map(|entry| entry.size).sum(),
+        }
+    }
+}
+
+/// Outcome of restoring a backup
+#[derive(Serialize)]
+pub struct BackupRestoreReport {
+    pub backup_id: String,
+    #[serde(flatten)]
+    pub registry: RestoreReport,
+    /// Stored proofs put back because they were missing
+    pub proofs_restored: usize,
+}
+
+/// Writes the objects of one backup, recording them in its manifest
+struct BackupWriter<'a> {
+    storage: &'a dyn Storage,
+    manifest: BackupManifest,
+}
+
+impl BackupWriter<'_> {
+    async fn put(&mut self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        self.manifest.objects.insert(
+            key.to_string(),
+            ManifestEntry {
+                sha256: hex::encode(Sha256::digest(&bytes)),
+                size: bytes.len() as u64,
+            },
+        );
+        self.storage
+            .put(&object_key(&self.manifest.id, key), bytes)
+            .await
+    }
+}
+
+fn object_key(backup_id: &str, key: &str) -> String {
+    format!("{}{}/{}", PREFIX, backup_id, key)
+}
+
+fn manifest_key(backup_id: &str) -> String {
+    format!("{}{}.json", PREFIX, backup_id)
+}
+
+/// Backups of this service
+pub struct Backups {
+    storage: Arc<dyn Storage>,
+    keep: usize,
+    /// Held while a backup or restore runs
+    running: tokio::sync::Mutex<()>,
+}
+
+impl Backups {
+    pub fn new(storage: Arc<dyn Storage>, keep: usize) -> Self {
+        Self {
+            storage,
+            keep: keep.max(1),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Take a backup now
+    pub async fn run(&self, state: &AppState) -> Result<BackupManifest> {
+        let _running = self
+            .running
+            .try_lock()
+            .map_err(|_| anyhow!("A backup or restore is already running"))?;
+        let manifest = self.take(state).await?;
+        if let Err(e) = self.prune().await {
+            tracing::warn!("Failed to remove old backups: {:#}", e);
+        }
+        Ok(manifest)
+    }
+
+    async fn take(&self, state: &AppState) -> Result<BackupManifest> {
+        let mut registry = snapshot::capture(state, false).await?;
+        let created_at = registry.created_at;
+        let mut writer = BackupWriter {
+            storage: self.storage.as_ref(),
+            manifest: BackupManifest {
+                id: format!(
+                    "bkp_{}_{}",
+                    created_at,
+                    &uuid::Uuid::new_v4().simple().to_string()[..8]
+                ),
+                created_at,
+                models: BTreeMap::new(),
+                proofs: 0,
+                objects: BTreeMap::new(),
+            },
+        };
+
+        // Model files travel as objects of their own rather than inline
+        for model in &mut registry.models {
+            let bytes = BASE64.decode(std::mem::take(&mut model.model_bytes))?;
+            writer
+                .put(&format!("models/{}.onnx", model.id), bytes)
+                .await?;
+            writer
+                .manifest
+                .models
+                .insert(model.id.clone(), model.commitment.clone());
+        }
+        writer
+            .put(REGISTRY, serde_json::to_vec_pretty(&registry)?)
+            .await?;
+        writer.put(AUDIT_LOG, state.audit.export()?).await?;
+        writer
+            .put(TRANSPARENCY_LOG, state.transparency.export()?)
+            .await?;
+
+        for object in self.storage.list(PROOFS).await? {
+            // Proofs may expire between listing and reading them
+            let Some(bytes) = self.storage.get(&object.key).await? else {
+                continue;
+            };
+            writer.put(&object.key, bytes).await?;
+            writer.manifest.proofs += 1;
+        }
+
+        let manifest = writer.manifest;
+        self.storage
+            .put(
+                &manifest_key(&manifest.id),
+                serde_json::to_vec_pretty(&manifest)?,
+            )
+            .await?;
+        tracing::info!(
+            "Backup {} written: {} models, {} proofs",
+            manifest.id,
+            manifest.models.len(),
+            manifest.proofs
+        );
+        Ok(manifest)
+    }
+
+    /// Manifests of every complete backup, oldest first
+    pub async fn list(&self) -> Result<Vec<BackupManifest>> {
+        let mut manifests = Vec::new();
+        for object in self.storage.list(PREFIX).await? {
+            // Remote storage lists backup objects too, local storage does not
+            let Some(name) = object.key.strip_prefix(PREFIX) else {
+                continue;
+            };
+            if name.contains('/') || !name.ends_with(".json") {
+                continue;
+            }
+            if let Some(bytes) = self.storage.get(&object.key).await? {
+                manifests.push(
+                    serde_json::from_slice::<BackupManifest>(&bytes)
+                        .with_context(|| format!("Corrupt backup manifest {}", object.key))?,
+                );
+            }
+        }
+        manifests.sort_by_key(|manifest| manifest.created_at);
+        Ok(manifests)
+    }
+
+    /// Remove every backup but the newest `keep`
+    async fn prune(&self) -> Result<()> {
+        let manifests = self.list().await?;
+        let stale = manifests.len().saturating_sub(self.keep);
+        for manifest in &manifests[..stale] {
+            // The manifest goes first, so a half-removed backup is incomplete
+            self.storage.delete(&manifest_key(&manifest.id)).await?;
+            for key in manifest.objects.keys() {
+                self.storage.delete(&object_key(&manifest.id, key)).await?;
+            }
+            tracing::info!("Removed backup {}", manifest.id);
+        }
+        Ok(())
+    }
+
+    /// Download an object of a backup and check it against the manifest
+    async fn fetch(&self, manifest: &BackupManifest, key: &str) -> Result<Vec<u8>> {
+        let bytes = self
+            .storage
+            .get(&object_key(&manifest.id, key))
+            .await?
+            .ok_or_else(|| anyhow!("{} of backup {} is missing", key, manifest.id))?;
+        manifest.check(key, &bytes)?;
+        Ok(bytes)
+    }
+
+    /// Download and check a backup's registry, with model files inline
+    async fn fetch_registry(&self, manifest: &BackupManifest) -> Result<RegistrySnapshot> {
+        let mut registry: RegistrySnapshot =
+            serde_json::from_slice(&self.fetch(manifest, REGISTRY).await?)?;
+        for model in &mut registry.models {
+            if manifest.models.get(&model.id) != Some(&model.commitment) {
+                return Err(anyhow!(
+                    "Model {} of backup {} does not match its manifest",
+                    model.id,
+                    manifest.id
+                ));
+            }
+            let bytes = self
+                .fetch(manifest, &format!("models/{}.onnx", model.id))
+                .await?;
+            model.model_bytes = BASE64.encode(bytes);
+        }
+        Ok(registry)
+    }
+}
+
+fn backup_failed(code: &str, e: anyhow::Error) -> ApiError {
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, code, format!("{:#}", e))
+}
+
+/// Complete backups, oldest first
+pub async fn list_backups(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<BackupSummary>>, ApiError> {
+    let manifests = state
+        .backups
+        .list()
+        .await
+        .map_err(|e| backup_failed("BACKUP_LIST_FAILED", e))?;
+    Ok(Json(manifests.iter().map(BackupSummary::from).collect()))
+}
+
+/// Take a backup now
+pub async fn run_backup(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<BackupSummary>, ApiError> {
+    let manifest = state
+        .backups
+        .run(&state)
+        .await
+        .map_err(|e| backup_failed("BACKUP_FAILED", e))?;
+    Ok(Json(BackupSummary::from(&manifest)))
+}
+
+/// Restore a backup
+pub async fn restore_backup(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(backup_id): Path<String>,
+) -> Result<Json<BackupRestoreReport>, ApiError> {
+    let backups = &state.backups;
+    let _running = backups.running.try_lock().map_err(|_| {
+        api_error(
+            StatusCode::CONFLICT,
+            "BACKUP_RUNNING",
+            "A backup or restore is already running",
+        )
+    })?;
+    let manifest = backups
+        .list()
+        .await
+        .map_err(|e| backup_failed("BACKUP_LIST_FAILED", e))?
+        .into_iter()
+        .find(|manifest| manifest.id == backup_id)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "BACKUP_NOT_FOUND",
+                format!("No complete backup {}", backup_id),
+            )
+        })?;
+
+    // Everything is downloaded and checked before the registry changes
+    let registry = backups.fetch_registry(&manifest).await.map_err(|e| {
+        api_error(
+            StatusCode::BAD_REQUEST,
+            "BACKUP_CORRUPT",
+            format!("{:#}", e),
+        )
+    })?;
+    let report = snapshot::restore(&state, registry).await?;
+
+    let mut proofs_restored = 0;
+    for key in manifest
+        .objects
+        .keys()
+        .filter(|key| key.starts_with(PROOFS))
+    {
+        let restore = async {
+            if backups.storage.get(key).await?.is_some() {
+                return Ok(false);
+            }
+            let bytes = backups.fetch(&manifest, key).await?;
+            backups.storage.put(key, bytes).await?;
+            Ok::<_, anyhow::Error>(true)
+        };
+        match restore.await {
+            Ok(restored) => proofs_restored += restored as usize,
+            Err(e) => tracing::warn!("Failed to restore {} from {}: {:#}", key, backup_id, e),
+        }
+    }
+
+    tracing::info!(
+        "Restored backup {}: {} models, {} proofs",
+        backup_id,
+        report.models_restored.len(),
+        proofs_restored
+    );
+    Ok(Json(BackupRestoreReport {
+        backup_id,
+        registry: report,
+        proofs_restored,
+    }))
+}
+
+/// Background task taking a backup every `interval_secs`
+pub async fn run_forever(state: Arc<AppState>, interval_secs: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    // The first tick fires immediately; back up one interval after startup
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if !state.cluster.is_leader() {
+            continue;
+        }
+        if let Err(e) = state.backups.run(&state).await {
+            tracing::error!("Scheduled backup failed: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+
+    fn manifest(id: &str, created_at: u64, objects: &[(&str, &[u8])]) -> BackupManifest {
+        BackupManifest {
+            id: id.to_string(),
+            created_at,
+            models: BTreeMap::new(),
+            proofs: 0,
+            objects: objects
+                .iter()
+                .map(|(key, bytes)| {
+                    (
+                        key.to_string(),
+                        ManifestEntry {
+                            sha256: hex::encode(Sha256::digest(bytes)),
+                            size: bytes.len() as u64,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backups_are_checked_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(dir.path()).unwrap());
+        let backups = Backups::new(storage.clone(), 2);
+
+        for (i, id) in ["bkp_1", "bkp_2", "bkp_3"].iter().enumerate() {
+            let manifest = manifest(id, i as u64, &[("models/m.onnx", b"model")]);
+            storage
+                .put(&object_key(id, "models/m.onnx"), b"model".to_vec())
+                .await
+                .unwrap();
+            storage
+                .put(&manifest_key(id), serde_json::to_vec(&manifest).unwrap())
+                .await
+                .unwrap();
+        }
+        // A backup without a manifest is incomplete and never listed
+        storage
+            .put(&object_key("bkp_4", REGISTRY), b"{}".to_vec())
+            .await
+            .unwrap();
+
+        backups.prune().await.unwrap();
+        let kept = backups.list().await.unwrap();
+        assert_eq!(
+            kept.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+            ["bkp_2", "bkp_3"]
+        );
+        assert!(storage
+            .get(&object_key("bkp_1", "models/m.onnx"))
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            backups.fetch(&kept[0], "models/m.onnx").await.unwrap(),
+            b"model"
+        );
+        storage
+            .put(&object_key("bkp_2", "models/m.onnx"), b"tampered".to_vec())
+            .await
+            .unwrap();
+        assert!(backups.fetch(&kept[0], "models/m.onnx").await.is_err());
+        assert!(backups.fetch(&kept[0], "registry.json").await.is_err());
+    }
+}
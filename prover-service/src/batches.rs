@@ -0,0 +1,304 @@
+//! Arrow and Parquet batch uploads
+//!
+//! `POST /batches` takes an Arrow IPC stream
+//! (`Content-Type: application/vnd.apache.arrow.stream`) or a Parquet file
+//! (`application/vnd.apache.parquet`) in which each row is one inference
+//! input, and queues one durable job per row for the model in `X-Model-Id`.
+//! The input features are the table's numeric columns in schema order, or
+//! the columns listed in `X-Input-Columns`. The response is a manifest of
+//! per-row job ids; with an `Idempotency-Key`, re-uploading the same batch
+//! returns the same jobs. Requires the `arrow` feature.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Json, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::raw_input::MODEL_ID_HEADER;
+use crate::request_id::RequestId;
+use crate::types::{api_error, ApiError, ProveRequest};
+use crate::AppState;
+
+/// Header listing the feature columns, comma-separated
+const INPUT_COLUMNS_HEADER: &str = "x-input-columns";
+
+/// Largest batch upload accepted
+pub const MAX_BATCH_BYTES: usize = 512 << 20;
+
+/// File format of an upload
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BatchFormat {
+    ArrowStream,
+    Parquet,
+}
+
+impl BatchFormat {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        match content_type.split(';').next()?.trim() {
+            "application/vnd.apache.arrow.stream" => Some(BatchFormat::ArrowStream),
+            "application/vnd.apache.parquet" | "application/x-parquet" => {
+                Some(BatchFormat::Parquet)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Job queued for one row
+#[derive(Serialize)]
+pub struct BatchRow {
+    pub row: usize,
+    pub job_id: String,
+}
+
+/// Jobs queued for a batch, in row order
+#[derive(Serialize)]
+pub struct BatchManifest {
+    pub model_id: String,
+    pub rows: usize,
+    pub jobs: Vec<BatchRow>,
+}
+
+/// Routes mounted under `/batches`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route(
+        "/",
+        post(submit_batch).layer(DefaultBodyLimit::max(MAX_BATCH_BYTES)),
+    )
+}
+
+/// Queue one job per row of an Arrow or Parquet upload
+async fn submit_batch(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    request_id: RequestId,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<BatchManifest>), ApiError> {
+    caller.require(Scope::Prove)?;
+    let format = BatchFormat::from_headers(&headers).ok_or_else(|| {
+        api_error(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_BATCH_FORMAT",
+            "Upload an Arrow IPC stream or a Parquet file",
+        )
+    })?;
+    if !cfg!(feature = "arrow") {
+        return Err(api_error(
+            StatusCode::NOT_IMPLEMENTED,
+            "UNSUPPORTED_BATCH_FORMAT",
+            "Batch uploads require the `arrow` feature",
+        ));
+    }
+    let model_id = header(&headers, MODEL_ID_HEADER)
+        .ok_or_else(|| invalid_batch("Batch uploads require an X-Model-Id header"))?
+        .to_string();
+    let columns: Option<Vec<String>> = header(&headers, INPUT_COLUMNS_HEADER)
+        .map(|columns| columns.split(',').map(|c| c.trim().to_string()).collect());
+
+    // Decoding is CPU-bound; keep it off the async workers
+    let max_rows = state.config.batch_max_rows;
+    let rows = tokio::task::spawn_blocking(move || {
+        decode_rows(format, body, columns.as_deref(), max_rows)
+    })
+    .await
+    .map_err(|e| invalid_batch(e.to_string()))?
+    .map_err(|e| invalid_batch(e.to_string()))?;
+
+    let requests: Vec<ProveRequest> = rows
+        .into_iter()
+        .map(|inputs| ProveRequest {
+            model_id: model_id.clone(),
+            inputs,
+            ..Default::default()
+        })
+        .collect();
+    let keys: Vec<Option<String>> = (0..requests.len())
+        .map(|row| header(&headers, "idempotency-key").map(|key| format!("{}:{}", key, row)))
+        .collect();
+    let jobs = state
+        .jobs
+        .submit_all(
+            &caller,
+            &request_id,
+            keys.iter().map(|k| k.as_deref()).zip(&requests),
+        )
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "JOB_SUBMIT_FAILED",
+                e.to_string(),
+            )
+        })?;
+
+    tracing::info!(
+        "Queued batch of {} rows for model {} from {}",
+        jobs.len(),
+        model_id,
+        caller.key_id
+    );
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(BatchManifest {
+            model_id,
+            rows: jobs.len(),
+            jobs: jobs
+                .into_iter()
+                .enumerate()
+                .map(|(row, job)| BatchRow {
+                    row,
+                    job_id: job.id,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+}
+
+fn invalid_batch(message: impl Into<String>) -> ApiError {
+    api_error(StatusCode::BAD_REQUEST, "INVALID_BATCH", message)
+}
+
+/// Decode an upload into one feature vector per row
+#[cfg_attr(not(feature = "arrow"), allow(unused_variables))]
+fn decode_rows(
+    format: BatchFormat,
+    body: Bytes,
+    columns: Option<&[String]>,
+    max_rows: usize,
+) -> Result<Vec<Vec<f32>>> {
+    #[cfg(feature = "arrow")]
+    {
+        return arrow::decode_rows(format, body, columns, max_rows);
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow!("Built without the arrow feature"))
+}
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use super::*;
+    use arrow_array::{cast::AsArray, types::Float32Type, Array, RecordBatch};
+    use arrow_schema::DataType;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    pub fn decode_rows(
+        format: BatchFormat,
+        body: Bytes,
+        columns: Option<&[String]>,
+        max_rows: usize,
+    ) -> Result<Vec<Vec<f32>>> {
+        let batches: Box<dyn Iterator<Item = Result<RecordBatch>>> = match format {
+            BatchFormat::ArrowStream => Box::new(
+                arrow_ipc::reader::StreamReader::try_new(std::io::Cursor::new(body), None)?
+                    .map(|batch| batch.map_err(Into::into)),
+            ),
+            BatchFormat::Parquet => Box::new(
+                ParquetRecordBatchReaderBuilder::try_new(body)?
+                    .build()?
+                    .map(|batch| batch.map_err(Into::into)),
+            ),
+        };
+
+        let mut rows = Vec::new();
+        for batch in batches {
+            let batch = batch?;
+            if rows.len() + batch.num_rows() > max_rows {
+                return Err(anyhow!("Batches are limited to {} rows", max_rows));
+            }
+            let features = feature_columns(&batch, columns)?;
+            let start = rows.len();
+            rows.resize(start + batch.num_rows(), Vec::with_capacity(features.len()));
+            for column in &features {
+                let values = column.as_primitive::<Float32Type>();
+                for (i, row) in rows[start..].iter_mut().enumerate() {
+                    if values.is_null(i) {
+                        return Err(anyhow!("Row {} has a null feature", start + i));
+                    }
+                    row.push(values.value(i));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// The feature columns of `batch`, cast to f32
+    fn feature_columns(
+        batch: &RecordBatch,
+        columns: Option<&[String]>,
+    ) -> Result<Vec<Arc<dyn Array>>> {
+        let schema = batch.schema();
+        let names: Vec<&str> = match columns {
+            Some(columns) => columns.iter().map(String::as_str).collect(),
+            None => schema
+                .fields()
+                .iter()
+                .filter(|field| field.data_type().is_numeric())
+                .map(|field| field.name().as_str())
+                .collect(),
+        };
+        if names.is_empty() {
+            return Err(anyhow!("The batch has no numeric columns"));
+        }
+        names
+            .into_iter()
+            .map(|name| {
+                let column = batch
+                    .column_by_name(name)
+                    .ok_or_else(|| anyhow!("No column named {}", name))?;
+                arrow_cast::cast(column, &DataType::Float32)
+                    .map_err(|e| anyhow!("Column {} is not numeric: {}", name, e))
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use arrow_array::{Float64Array, Int32Array, StringArray};
+
+        #[test]
+        fn test_arrow_stream_rows() {
+            let batch = RecordBatch::try_from_iter([
+                (
+                    "id",
+                    Arc::new(StringArray::from(vec!["a", "b"])) as Arc<dyn Array>,
+                ),
+                ("amount", Arc::new(Float64Array::from(vec![1.5, 2.5]))),
+                ("count", Arc::new(Int32Array::from(vec![3, 4]))),
+            ])
+            .unwrap();
+            let mut stream = Vec::new();
+            let mut writer =
+                arrow_ipc::writer::StreamWriter::try_new(&mut stream, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+
+            let body = Bytes::from(stream);
+            let rows = decode_rows(BatchFormat::ArrowStream, body.clone(), None, 10).unwrap();
+            assert_eq!(rows, vec![vec![1.5, 3.0], vec![2.5, 4.0]]);
+            let columns = ["count".to_string()];
+            let rows =
+                decode_rows(BatchFormat::ArrowStream, body.clone(), Some(&columns), 10).unwrap();
+            assert_eq!(rows, vec![vec![3.0], vec![4.0]]);
+            assert!(decode_rows(BatchFormat::ArrowStream, body, None, 1).is_err());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Request body size limits
+//!
+//! Every route has a largest accepted request body: `MAX_MODEL_BODY_BYTES`
+//! for model registration and registry restores, which carry model files,
+//! `MAX_RAW_BODY_BYTES` for raw f32 prove requests (see `raw_input`),
+//! `MAX_BODY_BYTES` for everything else (prove, verify, jobs, admin), and
+//! batch uploads keep their own limit (see `batches`). A body whose
+//! `Content-Length` is over the limit is refused with 413 before any of it is
+//! read; a chunked body is cut off with 413 as soon as the bytes streamed in
+//! pass the limit, so an oversized upload is never buffered whole.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::{LengthLimitError, Limited};
+
+use crate::batches;
+use crate::config::ServiceConfig;
+use crate::raw_input;
+use crate::types::{api_error, ApiError};
+
+/// Body limit of the route a request was sent to, as a request extension
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimit(pub usize);
+
+/// Body size limits by route
+#[derive(Clone, Copy)]
+pub struct BodyLimits {
+    default: usize,
+    models: usize,
+    raw: usize,
+}
+
+impl BodyLimits {
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            default: config.max_body_bytes,
+            models: config.max_model_body_bytes,
+            raw: config.max_raw_body_bytes,
+        }
+    }
+
+    /// Largest body accepted by `method` `path`, for a raw f32 body if `raw`
+    fn limit_for(&self, method: &Method, path: &str, raw: bool) -> usize {
+        if method != Method::POST {
+            return self.default;
+        }
+        match path.trim_end_matches('/') {
+            "/models" | "/admin/restore" => self.models,
+            "/prove" if raw => self.raw,
+            "/batches" => batches::MAX_BATCH_BYTES,
+            _ => self.default,
+        }
+    }
+}
+
+/// 413 for a body over `limit` bytes
+pub fn too_large(limit: usize) -> ApiError {
+    api_error(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "PAYLOAD_TOO_LARGE",
+        format!(
+            "Request body exceeds the {} byte limit of this route",
+            limit
+        ),
+    )
+}
+
+/// Whether reading a body failed because it passed its limit
+pub fn exceeded(err: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(err) = source {
+        if err.is::<LengthLimitError>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Middleware enforcing the body limit of each route
+pub async fn enforce(State(limits): State<BodyLimits>, request: Request, next: Next) -> Response {
+    let limit = limits.limit_for(
+        request.method(),
+        request.uri().path(),
+        raw_input::is_raw(request.headers()),
+    );
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return too_large(limit).into_response();
+    }
+
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(BodyLimit(limit));
+    let response = next
+        .run(Request::from_parts(
+            parts,
+            Body::new(Limited::new(body, limit)),
+        ))
+        .await;
+
+    // Extractors reject a body cut off mid-stream in plain text
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json {
+        return too_large(limit).into_response();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_limits_by_route_and_while_streaming() {
+        let limits = BodyLimits {
+            default: 1 << 10,
+            models: 1 << 20,
+            raw: 1 << 16,
+        };
+        assert_eq!(limits.limit_for(&Method::POST, "/models", false), 1 << 20);
+        assert_eq!(
+            limits.limit_for(&Method::POST, "/admin/restore/", false),
+            1 << 20
+        );
+        assert_eq!(limits.limit_for(&Method::POST, "/prove", false), 1 << 10);
+        assert_eq!(limits.limit_for(&Method::POST, "/prove", true), 1 << 16);
+        assert_eq!(limits.limit_for(&Method::POST, "/verify", true), 1 << 10);
+        assert_eq!(
+            limits.limit_for(&Method::POST, "/batches", false),
+            batches::MAX_BATCH_BYTES
+        );
+
+        let body = Body::new(Limited::new(Body::from(vec![0u8; 16]), 8));
+        let err = axum::body::to_bytes(body, usize::MAX).await.unwrap_err();
+        assert!(exceeded(&err));
+        let body = Body::new(Limited::new(Body::from(vec![0u8; 8]), 8));
+        assert_eq!(
+            axum::body::to_bytes(body, usize::MAX).await.unwrap().len(),
+            8
+        );
+    }
+}
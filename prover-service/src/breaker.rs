@@ -0,0 +1,300 @@
+//! Circuit breakers around the ONNX runtime and proving backends
+//!
+//! Each backend (by id, see `GET /capabilities`) and the ONNX runtime has a
+//! breaker. After `BREAKER_FAILURE_THRESHOLD` consecutive failures, or calls
+//! running past `BREAKER_CALL_TIMEOUT_SECS`, it opens: calls then fail fast
+//! with `BACKEND_UNAVAILABLE` instead of piling up behind a wedged session.
+//! After `BREAKER_COOLDOWN_SECS` a single probe call is let through; its
+//! success closes the breaker and its failure opens it again. A probe that
+//! is abandoned before it finishes (a request timeout, a client hanging up)
+//! counts as a failed probe, so the breaker never stays half-open.
+//!
+//! Failures caused by the request itself (an output mismatch, inputs the
+//! model cannot take, a strict mode refusal) or by its caller going away say
+//! nothing about the backend and are not counted.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Name of the ONNX runtime's breaker
+pub const ONNX_RUNTIME: &str = "onnx-runtime";
+
+/// A breaker refused the call because its backend keeps failing
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Backend {backend} is failing; circuit open, retry in {}s",
+    retry_after.as_secs().max(1)
+)]
+pub struct BackendUnavailable {
+    pub backend: String,
+    pub retry_after: Duration,
+}
+
+/// When breakers open and how they recover
+#[derive(Clone, Debug)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open a breaker
+    pub failure_threshold: u32,
+    /// How long an open breaker fails fast before probing
+    pub cooldown: Duration,
+    /// Calls running longer than this are abandoned and count as failures
+    pub call_timeout: Option<Duration>,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+            call_timeout: None,
+        }
+    }
+}
+
+/// State of one breaker, as reported in `GET /capabilities`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+/// Outcome of asking a breaker to let a call through
+#[derive(Debug, PartialEq)]
+enum Admission {
+    Call,
+    Probe,
+    Refused(Duration),
+}
+
+/// Circuit breakers by backend
+#[derive(Default)]
+pub struct Breakers {
+    config: BreakerConfig,
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+impl Breakers {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `call` on `backend` unless its breaker is open; errors for which
+    /// `counts` is false leave the breaker as it is
+    pub async fn call<T, F>(
+        &self,
+        backend: &str,
+        counts: impl Fn(&anyhow::Error) -> bool,
+        call: F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let admission = self.admit(backend, Instant::now());
+        if let Admission::Refused(retry_after) = admission {
+            return Err(BackendUnavailable {
+                backend: backend.to_string(),
+                retry_after,
+            }
+            .into());
+        }
+        let probe = admission == Admission::Probe;
+        let mut guard = ProbeGuard {
+            breakers: self,
+            backend,
+            armed: probe,
+        };
+
+        let result = match self.config.call_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!(
+                    "Backend {} did not finish within {}s",
+                    backend,
+                    timeout.as_secs()
+                )),
+            },
+            None => call.await,
+        };
+        guard.armed = false;
+        match &result {
+            Ok(_) => self.record(backend, probe, Some(true), Instant::now()),
+            Err(e) if counts(e) => self.record(backend, probe, Some(false), Instant::now()),
+            Err(_) => self.record(backend, probe, None, Instant::now()),
+        }
+        result
+    }
+
+    /// State of `backend`'s breaker
+    pub fn state(&self, backend: &str) -> BreakerState {
+        let breakers = self.breakers.lock().unwrap();
+        match breakers.get(backend) {
+            Some(breaker) if breaker.probing => BreakerState::HalfOpen,
+            Some(Breaker {
+                opened_at: Some(opened_at),
+                ..
+            }) if opened_at.elapsed() >= self.config.cooldown => BreakerState::HalfOpen,
+            Some(Breaker {
+                opened_at: Some(_), ..
+            }) => BreakerState::Open,
+            _ => BreakerState::Closed,
+        }
+    }
+
+    fn admit(&self, backend: &str, now: Instant) -> Admission {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(backend.to_string()).or_default();
+        let Some(opened_at) = breaker.opened_at else {
+            return Admission::Call;
+        };
+        let open_for = now.saturating_duration_since(opened_at);
+        if breaker.probing || open_for < self.config.cooldown {
+            return Admission::Refused(self.config.cooldown.saturating_sub(open_for));
+        }
+        breaker.probing = true;
+        Admission::Probe
+    }
+
+    /// Record the outcome of a call: success, a counted failure, or `None`
+    /// for a failure that does not count
+    fn record(&self, backend: &str, probe: bool, success: Option<bool>, now: Instant) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(backend.to_string()).or_default();
+        if probe {
+            breaker.probing = false;
+        }
+        match success {
+            Some(true) => {
+                if breaker.opened_at.take().is_some() {
+                    tracing::info!("Circuit breaker for {} closed", backend);
+                }
+                breaker.failures = 0;
+            }
+            Some(false) => {
+                breaker.failures += 1;
+                if probe || breaker.failures >= self.config.failure_threshold {
+                    if breaker.opened_at.is_none() || probe {
+                        tracing::warn!(
+                            "Circuit breaker for {} opened after {} failure(s)",
+                            backend,
+                            breaker.failures
+                        );
+                    }
+                    breaker.opened_at = Some(now);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Reopens the breaker if a probe is dropped before its outcome is recorded
+struct ProbeGuard<'a> {
+    breakers: &'a Breakers,
+    backend: &'a str,
+    armed: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            tracing::warn!("Probe of {} abandoned before it finished", self.backend);
+            self.breakers
+                .record(self.backend, true, Some(false), Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_fails_fast_and_probes() {
+        let breakers = Breakers::new(BreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(30),
+            call_timeout: None,
+        });
+        let start = Instant::now();
+        assert_eq!(breakers.admit("b", start), Admission::Call);
+        breakers.record("b", false, Some(false), start);
+        breakers.record("b", false, None, start);
+        assert_eq!(breakers.state("b"), BreakerState::Closed);
+        breakers.record("b", false, Some(false), start);
+        assert_eq!(breakers.state("b"), BreakerState::Open);
+        assert_eq!(
+            breakers.admit("b", start + Duration::from_secs(10)),
+            Admission::Refused(Duration::from_secs(20))
+        );
+        assert_eq!(breakers.admit("other", start), Admission::Call);
+
+        // One probe at a time; a failed probe reopens the breaker
+        let later = start + Duration::from_secs(31);
+        assert_eq!(breakers.admit("b", later), Admission::Probe);
+        assert!(matches!(breakers.admit("b", later), Admission::Refused(_)));
+        breakers.record("b", true, Some(false), later);
+        assert!(matches!(breakers.admit("b", later), Admission::Refused(_)));
+
+        let recovered = later + Duration::from_secs(31);
+        assert_eq!(breakers.admit("b", recovered), Admission::Probe);
+        breakers.record("b", true, Some(true), recovered);
+        assert_eq!(breakers.state("b"), BreakerState::Closed);
+        assert_eq!(breakers.admit("b", recovered), Admission::Call);
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_refuses_calls() {
+        let breakers = Breakers::new(BreakerConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        });
+        let failed: Result<()> = breakers
+            .call("b", |_| true, async { Err(anyhow::anyhow!("wedged")) })
+            .await;
+        assert_eq!(failed.unwrap_err().to_string(), "wedged");
+        let refused: Result<()> = breakers.call("b", |_| true, async { Ok(()) }).await;
+        assert!(refused.unwrap_err().is::<BackendUnavailable>());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_reopens_breaker() {
+        let breakers = Breakers::new(BreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_millis(20),
+            call_timeout: None,
+        });
+        let failed: Result<()> = breakers
+            .call("b", |_| true, async { Err(anyhow::anyhow!("wedged")) })
+            .await;
+        assert!(failed.is_err());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // The probe hangs and its future is dropped mid-call
+        let probe = breakers.call("b", |_| true, std::future::pending::<Result<()>>());
+        let abandoned = tokio::time::timeout(Duration::from_millis(10), probe).await;
+        assert!(abandoned.is_err());
+        assert_eq!(breakers.state("b"), BreakerState::Open);
+
+        // After another cooldown the next probe is let through
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let recovered: Result<()> = breakers.call("b", |_| true, async { Ok(()) }).await;
+        assert!(recovered.is_ok());
+        assert_eq!(breakers.state("b"), BreakerState::Closed);
+    }
+}
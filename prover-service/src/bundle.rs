@@ -0,0 +1,485 @@
+//! Proof bundles (`.zkbundle`)
+//!
+//! A bundle is a single JSON file carrying everything needed to check a
+//! proof without the service: the proof, its public inputs, the commitment
+//! and hashes it is checked against, the proof format, the verifying key
+//! (the backend's circuit version and the circuit hash binding the proof to
+//! it) and, when the proof was signed, the service signature together with
+//! the public key that made it.
+//!
+//! `GET /proofs/:id/bundle` exports a stored proof as a bundle, and
+//! `--verify-bundle <file>` checks one offline: the proof id, the
+//! commitments and public inputs against the proof, the verifying key, the
+//! proof itself with a local backend (`--backend <spec>`, as `AB_BACKEND`;
+//! the build's default backend otherwise) and every signature. The report
+//! goes to stdout, and the exit code is 0 only if every check passed.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::jolt_atlas::{
+    circuit_mismatch, create_backend, create_prover, deserialize_proof, proof_format,
+    JoltAtlasProof, ZkmlProver,
+};
+use crate::manifest::ReproducibilityManifest;
+use crate::proofs::{proof_digest, proof_id, StoredProof};
+use crate::prover::{bind_statements, check_public_inputs, named_outputs_match};
+use crate::signing::{prove_response_message, verify_signature, ServiceSignature};
+use crate::types::{api_error, ApiError, CommitmentScheme, PublicInputs};
+use crate::verification::verify_commitments;
+use crate::AppState;
+
+/// Format tag of the bundles this service writes
+pub const BUNDLE_FORMAT: &str = "zkbundle-v1";
+
+/// Command-line flag for verifying a bundle offline
+pub const VERIFY_BUNDLE_ARG: &str = "--verify-bundle";
+
+/// Everything needed to verify one proof offline
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ZkBundle {
+    /// Bundle format, `zkbundle-v1`
+    pub format: String,
+    /// Content-addressed id of the proof (see `proofs`)
+    pub proof_id: String,
+    /// The proof (base64 encoded)
+    pub proof: String,
+    /// Proof format, such as `jolt-atlas-v2`
+    pub proof_format: String,
+    /// Scheme of `model_commitment`, `input_hash` and `output_hash`
+    pub commitment_scheme: CommitmentScheme,
+    /// Commitment to the model alone (not composed with output checks)
+    pub model_commitment: String,
+    pub input_hash: String,
+    pub output_hash: String,
+    pub public_inputs: PublicInputs,
+    pub verifying_key: VerifyingKey,
+    /// Service signatures over the proof id and public inputs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<BundleSignature>,
+    /// How the proven computation can be reproduced (see `manifest`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<ReproducibilityManifest>,
+    pub created_at: u64,
+}
+
+/// What a proof verifies against: the circuit of one backend version
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKey {
+    /// Prover id of the backend
+    pub prover_id: String,
+    /// Circuit version the backend builds
+    pub circuit_version: String,
+    /// Hash binding the proof to that circuit and the model; proofs that
+    /// predate circuit hashes carry none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_hash: Option<String>,
+}
+
+/// A service signature with the public key to check it against
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BundleSignature {
+    #[serde(flatten)]
+    pub signature: ServiceSignature,
+    /// Ed25519 public key (hex)
+    pub public_key: String,
+}
+
+impl ZkBundle {
+    /// Bundle a stored proof, verified with a backend at `circuit_version`
+    pub fn from_stored(
+        stored: &StoredProof,
+        circuit_version: String,
+        public_key: Option<String>,
+    ) -> Result<Self> {
+        let response = &stored.response;
+        let proof = deserialize_proof(&response.proof)?;
+        let public_inputs = &response.public_inputs;
+        // Responses issued under a tenant's circuit-friendly scheme carry
+        // those commitments at the top level
+        let commitment_scheme = match &public_inputs.circuit_commitments {
+            Some(circuit)
+                if response.model_commitment != public_inputs.model_commitment
+                    && response.input_hash == circuit.input_hash =>
+            {
+                circuit.scheme
+            }
+            _ => public_inputs.commitment_scheme,
+        };
+        let signatures = match (&response.signature, public_key) {
+            (Some(signature), Some(public_key)) => vec![BundleSignature {
+                signature: signature.clone(),
+                public_key,
+            }],
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            format: BUNDLE_FORMAT.to_string(),
+            proof_id: stored.id.clone(),
+            proof: response.proof.clone(),
+            proof_format: proof_format(proof.version),
+            commitment_scheme,
+            model_commitment: response.model_commitment.clone(),
+            input_hash: response.input_hash.clone(),
+            output_hash: response.output_hash.clone(),
+            public_inputs: public_inputs.clone(),
+            verifying_key: VerifyingKey {
+                prover_id: proof.prover_id.clone(),
+                circuit_version,
+                circuit_hash: proof.circuit_hash.clone(),
+            },
+            signatures,
+            manifest: response.manifest.clone(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        })
+    }
+}
+
+/// Outcome of one offline check
+#[derive(Debug, Serialize)]
+pub struct BundleCheck {
+    pub check: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of verifying a bundle offline
+#[derive(Debug, Serialize)]
+pub struct BundleReport {
+    pub valid: bool,
+    pub proof_id: String,
+    pub checks: Vec<BundleCheck>,
+}
+
+/// Check `bundle` offline, verifying the proof with `backend`
+pub fn verify_bundle(bundle: &ZkBundle, backend: &dyn ZkmlProver) -> BundleReport {
+    let mut checks = Vec::new();
+    let mut record = |check: &str, result: Result<()>| {
+        checks.push(BundleCheck {
+            check: check.to_string(),
+            passed: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        })
+    };
+
+    record(
+        "format",
+        (bundle.format == BUNDLE_FORMAT)
+            .then_some(())
+            .ok_or_else(|| anyhow!("Unknown bundle format {}", bundle.format)),
+    );
+    record(
+        "proof_id",
+        (proof_id(&bundle.proof) == bundle.proof_id)
+            .then_some(())
+            .ok_or_else(|| anyhow!("Proof does not hash to {}", bundle.proof_id)),
+    );
+
+    match deserialize_proof(&bundle.proof) {
+        Ok(proof) => {
+            record("commitments", check_commitments(bundle, &proof));
+            record("verifying_key", check_verifying_key(bundle, &proof));
+            record("proof", check_proof(bundle, &proof, backend));
+        }
+        Err(e) => record("proof", Err(e)),
+    }
+
+    let message = prove_response_message(&bundle.proof_id, &bundle.public_inputs);
+    for signature in &bundle.signatures {
+        let result = message
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|message| {
+                verify_signature(&signature.public_key, message, &signature.signature)
+            });
+        record(&format!("signature:{}", signature.signature.kid), result);
+    }
+
+    BundleReport {
+        valid: checks.iter().all(|check| check.passed),
+        proof_id: bundle.proof_id.clone(),
+        checks,
+    }
+}
+
+/// The proof commits to the bundle's model, inputs and outputs, and its
+/// public inputs describe the same statement
+fn check_commitments(bundle: &ZkBundle, proof: &JoltAtlasProof) -> Result<()> {
+    let scheme = bundle.commitment_scheme;
+    if !scheme.is_supported() {
+        return Err(anyhow!("Unsupported commitment scheme: {}", scheme));
+    }
+    check_public_inputs(proof, &bundle.public_inputs)?;
+    let (model_commitment, input_hash, output_hash) = proof
+        .commitments_for(scheme)
+        .ok_or_else(|| anyhow!("Proof carries no {} commitments", scheme))?;
+    let expected_model = bind_statements(
+        scheme,
+        &bundle.model_commitment,
+        proof.preprocessor.as_deref(),
+        proof.quantization.as_ref(),
+        proof.expected_output.as_ref(),
+        proof.predicate.as_ref(),
+    )?;
+    if !verify_commitments(
+        model_commitment,
+        input_hash,
+        output_hash,
+        &expected_model,
+        &bundle.input_hash,
+        &bundle.output_hash,
+    ) {
+        return Err(anyhow!(
+            "Proof does not commit to the bundled model, inputs and outputs"
+        ));
+    }
+    if let Some(outputs) = &bundle.public_inputs.outputs {
+        if !named_outputs_match(proof, outputs) {
+            return Err(anyhow!("Output tensors do not match the proof"));
+        }
+    }
+    Ok(())
+}
+
+/// The proof was generated for the bundled verifying key
+fn check_verifying_key(bundle: &ZkBundle, proof: &JoltAtlasProof) -> Result<()> {
+    let key = &bundle.verifying_key;
+    if key.prover_id != proof.prover_id || key.circuit_hash != proof.circuit_hash {
+        return Err(anyhow!("Verifying key does not match the proof"));
+    }
+    if proof_format(proof.version) != bundle.proof_format {
+        return Err(anyhow!(
+            "Proof is in format {}, not {}",
+            proof_format(proof.version),
+            bundle.proof_format
+        ));
+    }
+    match circuit_mismatch(proof, &key.circuit_version) {
+        Some(mismatch) => Err(anyhow!(mismatch)),
+        None => Ok(()),
+    }
+}
+
+/// The proof verifies with a local backend at the bundled circuit version
+fn check_proof(bundle: &ZkBundle, proof: &JoltAtlasProof, backend: &dyn ZkmlProver) -> Result<()> {
+    let key = &bundle.verifying_key;
+    if backend.prover_id() != key.prover_id || backend.circuit_version() != key.circuit_version {
+        return Err(anyhow!(
+            "Local backend is {} at {}, the bundle needs {} at {}",
+            backend.prover_id(),
+            backend.circuit_version(),
+            key.prover_id,
+            key.circuit_version
+        ));
+    }
+    let result = backend.verify(proof)?;
+    if result.valid {
+        Ok(())
+    } else {
+        Err(anyhow!(result
+            .error
+            .unwrap_or_else(|| "Proof does not verify".to_string())))
+    }
+}
+
+/// Export a stored proof generated for the calling key as a bundle
+pub async fn get_bundle(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> Result<(HeaderMap, Json<ZkBundle>), ApiError> {
+    caller.require(Scope::Verify)?;
+    let stored = state
+        .proofs
+        .get(&id)
+        .await
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PROOF_STORE_UNREADABLE",
+                e.to_string(),
+            )
+        })?
+        .filter(|proof| proof.key_id == caller.key_id)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "PROOF_NOT_FOUND",
+                format!("No stored proof {}", id),
+            )
+        })?;
+
+    let bundle_failed = |e: anyhow::Error| {
+        api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "BUNDLE_FAILED",
+            e.to_string(),
+        )
+    };
+    let proof = deserialize_proof(&stored.response.proof).map_err(bundle_failed)?;
+    let circuit_version = state.prover.read().await.circuit_version_for(&proof).await;
+    // Signatures by keys retired since cannot be checked offline
+    let public_key = stored.response.signature.as_ref().and_then(|signature| {
+        state
+            .signing_keys
+            .public_keys()
+            .into_iter()
+            .find(|key| key.kid == signature.kid)
+            .map(|key| key.public_key)
+    });
+    let bundle =
+        ZkBundle::from_stored(&stored, circuit_version, public_key).map_err(bundle_failed)?;
+
+    let name = proof_digest(&bundle.proof_id).unwrap_or_else(|| bundle.proof_id.clone());
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename=\"{}.zkbundle\"", name))
+    {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    Ok((headers, Json(bundle)))
+}
+
+/// Entry point for `--verify-bundle`, returning the process exit code
+pub fn verify_bundle_main(args: impl Iterator<Item = String>) -> i32 {
+    let args: Vec<String> = args.collect();
+    let (path, backend) = match args.as_slice() {
+        [path] => (path, None),
+        [flag, spec, path] if flag == "--backend" => (path, Some(spec)),
+        _ => {
+            eprintln!("usage: {} [--backend <spec>] <file>", VERIFY_BUNDLE_ARG);
+            return 2;
+        }
+    };
+    let bundle: ZkBundle = match std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+    {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("Failed to read bundle {}: {}", path, e);
+            return 2;
+        }
+    };
+    let backend = match backend {
+        Some(spec) => create_backend(spec),
+        None => create_prover(),
+    };
+    let backend = match backend {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("Failed to create backend: {}", e);
+            return 2;
+        }
+    };
+
+    let report = verify_bundle(&bundle, backend.as_ref());
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("reports serialize")
+    );
+    if report.valid {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::{serialize_proof, ModelCommitments};
+    use crate::signing::KeyRing;
+    use crate::types::ProveResponse;
+
+    fn stored_proof(keys: &KeyRing) -> StoredProof {
+        let model = ModelCommitments::compute(b"model");
+        let (inputs, outputs) = ([1.0, 2.0], [0.25, 0.75]);
+        let proof = create_prover()
+            .unwrap()
+            .prove(&model, &inputs, &outputs, None)
+            .unwrap();
+        let encoded = serialize_proof(&proof).unwrap();
+        let public_inputs: PublicInputs = serde_json::from_value(serde_json::json!({
+            "model_commitment": model.sha256,
+            "input_hash": proof.input_hash,
+            "output_hash": proof.output_hash,
+            "output": outputs,
+            "timestamp": proof.timestamp,
+            "commitment_scheme": "sha256-v2",
+            "circuit_hash": proof.circuit_hash,
+            "prover_id": proof.prover_id,
+        }))
+        .unwrap();
+        let id = proof_id(&encoded);
+        let signature = keys
+            .sign(&prove_response_message(&id, &public_inputs).unwrap())
+            .unwrap();
+        let response: ProveResponse = serde_json::from_value(serde_json::json!({
+            "success": true,
+            "model_id": "m",
+            "proof_id": id,
+            "proof": encoded,
+            "model_commitment": model.sha256,
+            "input_hash": proof.input_hash,
+            "output_hash": proof.output_hash,
+            "public_inputs": public_inputs,
+            "proving_time_ms": 1,
+            "signature": signature,
+        }))
+        .unwrap();
+        StoredProof {
+            id,
+            created_at: 0,
+            source: "test".to_string(),
+            key_id: "key".to_string(),
+            tenant: "acme".to_string(),
+            response,
+        }
+    }
+
+    #[test]
+    fn test_bundle_verifies_offline() {
+        let keys = KeyRing::ephemeral(3600);
+        let stored = stored_proof(&keys);
+        let backend = create_prover().unwrap();
+        let public_key = keys.public_keys()[0].public_key.clone();
+        let bundle =
+            ZkBundle::from_stored(&stored, backend.circuit_version(), Some(public_key)).unwrap();
+
+        // Round-trips through the file format
+        let bundle: ZkBundle =
+            serde_json::from_slice(&serde_json::to_vec(&bundle).unwrap()).unwrap();
+        let report = verify_bundle(&bundle, backend.as_ref());
+        assert!(report.valid, "{:?}", report.checks);
+        assert_eq!(report.checks.len(), 6);
+
+        let mut tampered = bundle.clone();
+        tampered.public_inputs.output = vec![0.75, 0.25];
+        let report = verify_bundle(&tampered, backend.as_ref());
+        assert!(!report.valid);
+        let failed: Vec<&str> = report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.check.as_str())
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].starts_with("signature:"));
+
+        let mut tampered = bundle;
+        tampered.verifying_key.circuit_version = "other".to_string();
+        assert!(!verify_bundle(&tampered, backend.as_ref()).valid);
+    }
+}
@@ -0,0 +1,160 @@
+//! Service-issued challenges for liveness checks
+//!
+//! Protocols that need a proof generated *after* a given moment fetch a
+//! nonce with `GET /challenge` and have the prover send it as `nonce` in
+//! its `ProveRequest`. The service absorbs the nonce into the proof
+//! transcript as the proof's challenge, so a verifier holding the nonce
+//! checks the proof with `challenge` set to it, exactly as for a
+//! verifier-supplied challenge.
+//!
+//! Each nonce is random, signed together with the time it was issued (see
+//! `signing::challenge_message`), expires after `CHALLENGE_TTL_SECS`, and is
+//! redeemed by the first proof request presenting it; stale and reused
+//! nonces are rejected. Nonces live in memory only: a restart invalidates
+//! the outstanding ones.
+
+use axum::{extract::State, http::StatusCode, Json};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth::Caller;
+use crate::signing::{challenge_message, ServiceSignature};
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// Random bytes in a nonce
+const NONCE_BYTES: usize = 32;
+
+/// A nonce issued by `GET /challenge`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuedChallenge {
+    /// The nonce (hex, `0x`-prefixed)
+    pub nonce: String,
+    /// When the nonce was issued (unix seconds)
+    pub issued_at: u64,
+    /// When the nonce stops being accepted (unix seconds)
+    pub expires_at: u64,
+    /// Service signature over `signing::challenge_message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ServiceSignature>,
+}
+
+struct Outstanding {
+    challenge: IssuedChallenge,
+    redeemed: bool,
+}
+
+/// Outstanding nonces
+pub struct ChallengeStore {
+    ttl_secs: u64,
+    outstanding: Mutex<HashMap<String, Outstanding>>,
+}
+
+impl ChallengeStore {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs,
+            outstanding: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn issue(&self) -> IssuedChallenge {
+        let mut bytes = [0u8; NONCE_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let now = now_secs();
+        let challenge = IssuedChallenge {
+            nonce: format!("0x{}", hex::encode(bytes)),
+            issued_at: now,
+            expires_at: now + self.ttl_secs,
+            signature: None,
+        };
+
+        let mut outstanding = self.outstanding.lock().unwrap();
+        outstanding.retain(|_, o| o.challenge.expires_at > now);
+        outstanding.insert(
+            challenge.nonce.clone(),
+            Outstanding {
+                challenge: challenge.clone(),
+                redeemed: false,
+            },
+        );
+        challenge
+    }
+
+    /// Consume `nonce`, returning it in canonical form
+    pub fn redeem(&self, nonce: &str) -> Result<String, ApiError> {
+        let nonce = format!("0x{}", nonce.trim_start_matches("0x").to_ascii_lowercase());
+        let mut outstanding = self.outstanding.lock().unwrap();
+        let Some(entry) = outstanding.get_mut(&nonce) else {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "UNKNOWN_CHALLENGE",
+                format!("Nonce {} was not issued by this service", nonce),
+            ));
+        };
+        if entry.challenge.expires_at <= now_secs() {
+            outstanding.remove(&nonce);
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "STALE_CHALLENGE",
+                format!("Nonce {} has expired; fetch a new one", nonce),
+            ));
+        }
+        if entry.redeemed {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "CHALLENGE_REUSED",
+                format!("Nonce {} was already used by another proof", nonce),
+            ));
+        }
+        entry.redeemed = true;
+        Ok(nonce)
+    }
+}
+
+/// Issue a fresh nonce for a subsequent proof request
+pub async fn issue_challenge(
+    State(state): State<Arc<AppState>>,
+    _caller: Caller,
+) -> Json<IssuedChallenge> {
+    let mut challenge = state.challenges.issue();
+    challenge.signature = state
+        .signing_keys
+        .sign(&challenge_message(&challenge.nonce, challenge.issued_at))
+        .map_err(|e| tracing::error!("Failed to sign challenge: {}", e))
+        .ok();
+    Json(challenge)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_redeems_once_before_expiry() {
+        let store = ChallengeStore::new(3600);
+        let nonce = store.issue().nonce;
+        assert!(crate::prover::normalize_challenge(&nonce).is_ok());
+
+        assert_eq!(store.redeem(&nonce.to_uppercase()[2..]).unwrap(), nonce);
+        assert_eq!(store.redeem(&nonce).unwrap_err().0, StatusCode::CONFLICT);
+        assert_eq!(store.redeem("0x00").unwrap_err().0, StatusCode::BAD_REQUEST);
+
+        let stale = ChallengeStore::new(0);
+        let nonce = stale.issue().nonce;
+        assert_eq!(
+            stale.redeem(&nonce).unwrap_err().1 .0.code,
+            "STALE_CHALLENGE"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Checkpointed proving
+//!
+//! With `CHECKPOINT_INTERVAL_SECS` set, backends that support it save their
+//! proving state under `checkpoints/` in the data directory about that
+//! often. Checkpoints are keyed by what is being proven (model, inputs,
+//! outputs and challenge), so when a long proof is interrupted by a crash,
+//! a killed worker or preemption, the next attempt at the same job (e.g. a
+//! re-queued durable job) resumes from the last checkpoint instead of
+//! starting over. A checkpoint is removed once its proof is done; ones left
+//! behind by abandoned jobs are pruned at startup after
+//! `CHECKPOINT_MAX_AGE_SECS`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::jolt_atlas::{hash_floats, ModelCommitments};
+
+/// Where checkpoints are kept and how often they are taken
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    pub dir: PathBuf,
+    pub interval_secs: u64,
+}
+
+/// Checkpoint of one proving job
+pub struct Checkpoint {
+    dir: PathBuf,
+    interval: Duration,
+}
+
+impl Checkpoint {
+    /// Checkpoint for proving `outputs` of `model` on `inputs`
+    pub fn for_job(
+        config: &CheckpointConfig,
+        model: &ModelCommitments,
+        inputs: &[f32],
+        outputs: &[f32],
+        challenge: Option<&str>,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(model.sha256.as_bytes());
+        hasher.update(model.keccak256.as_bytes());
+        hasher.update(hash_floats(inputs).as_bytes());
+        hasher.update(hash_floats(outputs).as_bytes());
+        if let Some(challenge) = challenge {
+            hasher.update(b"challenge");
+            hasher.update(challenge.as_bytes());
+        }
+        Self {
+            dir: config.dir.join(hex::encode(hasher.finalize())),
+            interval: Duration::from_secs(config.interval_secs),
+        }
+    }
+
+    /// Directory the backend keeps this job's checkpoint files in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// How often the backend should save its state
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Remove the checkpoint once the proof is done
+    pub fn clear(&self) -> Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Remove checkpoints not written to for `max_age`, returning how many
+pub fn prune(dir: &Path, max_age: Duration) -> Result<usize> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut pruned = 0;
+    for entry in entries {
+        let entry = entry?;
+        let modified = entry.metadata()?.modified()?;
+        if SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age > max_age)
+        {
+            std::fs::remove_dir_all(entry.path())?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_are_keyed_by_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CheckpointConfig {
+            dir: dir.path().to_path_buf(),
+            interval_secs: 3600,
+        };
+        let model = ModelCommitments {
+            sha256: "0xaa".to_string(),
+            keccak256: "0xbb".to_string(),
+        };
+
+        let checkpoint = Checkpoint::for_job(&config, &model, &[1.0], &[0.5], None);
+        std::fs::create_dir_all(checkpoint.dir()).unwrap();
+
+        // A later attempt at the same job finds the checkpoint; another job
+        // or challenge does not
+        let resumed = Checkpoint::for_job(&config, &model, &[1.0], &[0.5], None);
+        assert!(resumed.dir().exists());
+        let other = Checkpoint::for_job(&config, &model, &[1.0], &[0.5], Some("0x01"));
+        assert!(!other.dir().exists());
+
+        assert_eq!(prune(dir.path(), Duration::from_secs(3600)).unwrap(), 0);
+        resumed.clear().unwrap();
+        resumed.clear().unwrap();
+        assert!(!checkpoint.dir().exists());
+    }
+}
@@ -0,0 +1,396 @@
+//! Cluster membership and leader election
+//!
+//! Replicas sharing storage would otherwise all run the singleton background
+//! duties: scheduled proofs, GC, retention cleanup, backups and signing key
+//! rotation. With `LEADER_ELECTION=true` the replicas elect a leader that
+//! owns those duties through a lease renewed every third of
+//! `LEADER_LEASE_SECS`. If the leader stops renewing (it crashed or lost its
+//! connection), another replica takes the lease once it expires, so the
+//! duties neither run N times nor stop.
+//!
+//! The lease lives in Redis when `REDIS_URL` is set (build with `--features
+//! redis`), and otherwise in a SQLite database (`cluster.sqlite3`) in the data
+//! directory, which the replicas must then share. SQLite leases compare
+//! wall-clock times across replicas, so their clocks must agree to well
+//! within the lease. A replica that cannot renew its lease stops acting as
+//! leader when the lease it last held runs out.
+//!
+//! Every replica also records a heartbeat; `GET /admin/cluster` lists the
+//! replicas seen within the last three leases and the current leader.
+//! Without leader election the replica is alone and always leads.
+
+// Without the redis feature only the SQLite backend is compiled in
+#![cfg_attr(not(feature = "redis"), allow(unused_variables))]
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth::AdminAuth;
+use crate::config::ServiceConfig;
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// Name of the lease held by the leader
+const LEADER_LEASE: &str = "leader";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS leases (
+        name TEXT PRIMARY KEY,
+        holder TEXT NOT NULL,
+        expires_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS members (
+        id TEXT PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL
+    );
+";
+
+#[cfg(feature = "redis")]
+const REDIS_LEASE_KEY: &str = "cluster:leader";
+
+#[cfg(feature = "redis")]
+const REDIS_MEMBERS_KEY: &str = "cluster:members";
+
+/// Take the lease if it is free, renew it if we hold it; returns the holder
+#[cfg(feature = "redis")]
+const REDIS_ACQUIRE: &str = "
+    local holder = redis.call('GET', KEYS[1])
+    if not holder then
+        redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+        return ARGV[1]
+    end
+    if holder == ARGV[1] then
+        redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    end
+    return holder
+";
+
+/// A replica of the service
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub id: String,
+    /// Unix time (ms) the replica started
+    pub started_at: u64,
+    /// Unix time (ms) of its last heartbeat
+    pub last_seen: u64,
+}
+
+#[derive(Serialize)]
+pub struct ClusterStatus {
+    pub replica_id: String,
+    pub backend: &'static str,
+    pub is_leader: bool,
+    /// Leader as of this replica's last election round
+    pub leader: Option<String>,
+    pub members: Vec<Member>,
+}
+
+enum Backend {
+    /// No election: this replica is the only one
+    Single,
+    Sqlite(Mutex<Connection>),
+    #[cfg(feature = "redis")]
+    Redis(redis::aio::ConnectionManager),
+}
+
+/// This replica's view of the cluster
+pub struct Cluster {
+    replica_id: String,
+    started_at: u64,
+    lease: Duration,
+    backend: Backend,
+    /// Unix time (ms) until which this replica holds the lease
+    leading_until: AtomicU64,
+    /// Leader seen in the last election round
+    leader: Mutex<Option<String>>,
+}
+
+impl Cluster {
+    pub async fn from_config(config: &ServiceConfig) -> Result<Self> {
+        let replica_id = config.replica_id.clone().unwrap_or_else(|| {
+            format!(
+                "replica-{}",
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            )
+        });
+        let lease = Duration::from_secs(config.leader_lease_secs.max(3));
+        let backend = if !config.leader_election {
+            Backend::Single
+        } else {
+            match &config.redis_url {
+                None => Backend::Sqlite(Mutex::new(open_sqlite(&config.data_dir)?)),
+                #[cfg(feature = "redis")]
+                Some(url) => {
+                    let client = redis::Client::open(url.as_str())?;
+                    Backend::Redis(redis::aio::ConnectionManager::new(client).await?)
+                }
+                #[cfg(not(feature = "redis"))]
+                Some(_) => return Err(anyhow!("REDIS_URL requires the `redis` feature")),
+            }
+        };
+        Ok(Self::new(replica_id, lease, backend))
+    }
+
+    fn new(replica_id: String, lease: Duration, backend: Backend) -> Self {
+        Self {
+            replica_id,
+            started_at: now_ms(),
+            lease,
+            backend,
+            leading_until: AtomicU64::new(0),
+            leader: Mutex::new(None),
+        }
+    }
+
+    /// Name of the backend, for logs
+    pub fn backend(&self) -> &'static str {
+        match self.backend {
+            Backend::Single => "single",
+            Backend::Sqlite(_) => "sqlite",
+            #[cfg(feature = "redis")]
+            Backend::Redis(_) => "redis",
+        }
+    }
+
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// Whether this replica should run singleton duties now
+    pub fn is_leader(&self) -> bool {
+        matches!(self.backend, Backend::Single)
+            || now_ms() < self.leading_until.load(Ordering::SeqCst)
+    }
+
+    /// Record a heartbeat and take or renew the leader lease
+    async fn elect(&self) -> Result<()> {
+        // The lease is counted from before the round, never from its answer
+        let now = now_ms();
+        let lease_ms = self.lease.as_millis() as u64;
+        let member = Member {
+            id: self.replica_id.clone(),
+            started_at: self.started_at,
+            last_seen: now,
+        };
+        let leader = match &self.backend {
+            Backend::Single => self.replica_id.clone(),
+            Backend::Sqlite(conn) => {
+                let conn = conn.lock().unwrap();
+                sqlite_heartbeat(&conn, &member)?;
+                sqlite_acquire(&conn, &self.replica_id, now, lease_ms)?
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(connection) => {
+                use redis::AsyncCommands;
+                let mut connection = connection.clone();
+                let _: () = connection
+                    .hset(
+                        REDIS_MEMBERS_KEY,
+                        &self.replica_id,
+                        serde_json::to_string(&member)?,
+                    )
+                    .await?;
+                redis::cmd("EVAL")
+                    .arg(REDIS_ACQUIRE)
+                    .arg(1)
+                    .arg(REDIS_LEASE_KEY)
+                    .arg(&self.replica_id)
+                    .arg(lease_ms)
+                    .query_async(&mut connection)
+                    .await?
+            }
+        };
+
+        let leading = leader == self.replica_id;
+        let was_leading = self.is_leader();
+        self.leading_until
+            .store(if leading { now + lease_ms } else { 0 }, Ordering::SeqCst);
+        if leading && !was_leading {
+            tracing::info!("Replica {} is now the leader", self.replica_id);
+        } else if !leading && was_leading {
+            tracing::warn!("Replica {} lost the lead to {}", self.replica_id, leader);
+        }
+        *self.leader.lock().unwrap() = Some(leader);
+        Ok(())
+    }
+
+    /// Replicas seen within the last three leases
+    async fn members(&self) -> Result<Vec<Member>> {
+        let since = now_ms().saturating_sub(3 * self.lease.as_millis() as u64);
+        let mut members = match &self.backend {
+            Backend::Single => vec![Member {
+                id: self.replica_id.clone(),
+                started_at: self.started_at,
+                last_seen: now_ms(),
+            }],
+            Backend::Sqlite(conn) => sqlite_members(&conn.lock().unwrap(), since)?,
+            #[cfg(feature = "redis")]
+            Backend::Redis(connection) => {
+                use redis::AsyncCommands;
+                let mut connection = connection.clone();
+                let entries: std::collections::HashMap<String, String> =
+                    connection.hgetall(REDIS_MEMBERS_KEY).await?;
+                let mut members = Vec::new();
+                for (id, value) in entries {
+                    match serde_json::from_str::<Member>(&value) {
+                        Ok(member) if member.last_seen >= since => members.push(member),
+                        // Replicas gone for good are forgotten
+                        _ => {
+                            let _: () = connection.hdel(REDIS_MEMBERS_KEY, id).await?;
+                        }
+                    }
+                }
+                members
+            }
+        };
+        members.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(members)
+    }
+
+    pub async fn status(&self) -> Result<ClusterStatus> {
+        Ok(ClusterStatus {
+            replica_id: self.replica_id.clone(),
+            backend: self.backend(),
+            is_leader: self.is_leader(),
+            leader: match self.backend {
+                Backend::Single => Some(self.replica_id.clone()),
+                _ => self.leader.lock().unwrap().clone(),
+            },
+            members: self.members().await?,
+        })
+    }
+}
+
+fn open_sqlite(data_dir: &Path) -> Result<Connection> {
+    let conn = Connection::open(data_dir.join("cluster.sqlite3"))?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn sqlite_heartbeat(conn: &Connection, member: &Member) -> Result<()> {
+    conn.execute(
+        "INSERT INTO members (id, started_at, last_seen) VALUES (?1, ?2, ?3)
+         ON CONFLICT (id) DO UPDATE SET last_seen = excluded.last_seen",
+        params![member.id, member.started_at, member.last_seen],
+    )?;
+    Ok(())
+}
+
+/// Take the lease if it is free or expired, renew it if `replica_id` holds
+/// it; returns the holder
+fn sqlite_acquire(conn: &Connection, replica_id: &str, now: u64, lease_ms: u64) -> Result<String> {
+    conn.execute(
+        "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT (name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+         WHERE leases.holder = excluded.holder OR leases.expires_at <= ?4",
+        params![LEADER_LEASE, replica_id, now + lease_ms, now],
+    )?;
+    conn.query_row(
+        "SELECT holder FROM leases WHERE name = ?1",
+        params![LEADER_LEASE],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| anyhow!("Leader lease vanished"))
+}
+
+fn sqlite_members(conn: &Connection, since: u64) -> Result<Vec<Member>> {
+    conn.execute("DELETE FROM members WHERE last_seen < ?1", params![since])?;
+    let mut stmt = conn.prepare("SELECT id, started_at, last_seen FROM members")?;
+    let members = stmt
+        .query_map([], |row| {
+            Ok(Member {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                last_seen: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(members)
+}
+
+/// Cluster membership and the current leader
+pub async fn get_cluster(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ClusterStatus>, ApiError> {
+    state.cluster.status().await.map(Json).map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "CLUSTER_UNAVAILABLE",
+            format!("{:#}", e),
+        )
+    })
+}
+
+/// Background task running the election, every third of a lease
+pub async fn run_forever(state: Arc<AppState>) {
+    let cluster = &state.cluster;
+    if matches!(cluster.backend, Backend::Single) {
+        return;
+    }
+    let mut interval = tokio::time::interval(cluster.lease / 3);
+    loop {
+        interval.tick().await;
+        if let Err(e) = cluster.elect().await {
+            tracing::warn!("Leader election round failed: {:#}", e);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_lease_fails_over_when_it_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = open_sqlite(dir.path()).unwrap();
+        let b = open_sqlite(dir.path()).unwrap();
+
+        assert_eq!(sqlite_acquire(&a, "a", 1_000, 300).unwrap(), "a");
+        assert_eq!(sqlite_acquire(&b, "b", 1_100, 300).unwrap(), "a");
+        // The holder renews its lease
+        assert_eq!(sqlite_acquire(&a, "a", 1_200, 300).unwrap(), "a");
+        assert_eq!(sqlite_acquire(&b, "b", 1_400, 300).unwrap(), "a");
+        // The holder stopped renewing
+        assert_eq!(sqlite_acquire(&b, "b", 1_500, 300).unwrap(), "b");
+        assert_eq!(sqlite_acquire(&a, "a", 1_600, 300).unwrap(), "b");
+
+        for (conn, id) in [(&a, "a"), (&b, "b")] {
+            sqlite_heartbeat(
+                conn,
+                &Member {
+                    id: id.to_string(),
+                    started_at: 0,
+                    last_seen: if id == "a" { 1_000 } else { 1_600 },
+                },
+            )
+            .unwrap();
+        }
+        let members = sqlite_members(&a, 1_100).unwrap();
+        assert_eq!(
+            members.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+            ["b"]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Commit-then-prove input flow
+//!
+//! A client can commit to its inputs before asking for a proof:
+//!
+//! 1. `POST /commitments` with `commitment = input_commitment(salt, inputs)`
+//!    returns a commitment id and records when the commitment was made.
+//! 2. `POST /prove` with `commitment_id` and `salt` alongside the inputs.
+//!    The service recomputes the commitment, rejects any mismatch, and records
+//!    the commitment and its time in `PublicInputs`.
+//!
+//! Since the commitment predates the proof request, the signed public inputs
+//! show the inputs were fixed before any output was seen. Commitments are
+//! single-use, bound to the API key that made them, and expire after
+//! `INPUT_COMMITMENT_TTL_SECS`.
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::types::{api_error, ApiError};
+use crate::verification::ct_eq;
+use crate::AppState;
+
+/// Domain separator of input commitments
+const INPUT_COMMITMENT_DOMAIN: &[u8] = b"jolt-atlas-prover/input-commitment/v1\n";
+
+/// Commitment to inputs as recorded in `PublicInputs`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputCommitment {
+    pub commitment_id: String,
+    pub commitment: String,
+    /// When the commitment was made (unix seconds)
+    pub committed_at: u64,
+}
+
+/// `sha256(domain || salt || inputs as little-endian f32)`, hex with `0x`
+pub fn input_commitment(salt: &[u8], inputs: &[f32]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(INPUT_COMMITMENT_DOMAIN);
+    hasher.update(salt);
+    for v in inputs {
+        hasher.update(v.to_le_bytes());
+    }
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+struct PendingCommitment {
+    commitment: InputCommitment,
+    key_id: String,
+}
+
+/// Outstanding input commitments
+pub struct CommitmentStore {
+    ttl_secs: u64,
+    pending: Mutex<HashMap<String, PendingCommitment>>,
+}
+
+impl CommitmentStore {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn create(&self, key_id: &str, commitment: String) -> InputCommitment {
+        let now = now_secs();
+        let record = InputCommitment {
+            commitment_id: format!("cmt_{}", uuid::Uuid::new_v4().simple()),
+            commitment,
+            committed_at: now,
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.commitment.committed_at + self.ttl_secs > now);
+        pending.insert(
+            record.commitment_id.clone(),
+            PendingCommitment {
+                commitment: record.clone(),
+                key_id: key_id.to_string(),
+            },
+        );
+        record
+    }
+
+    fn get(&self, key_id: &str, commitment_id: &str) -> Option<InputCommitment> {
+        let pending = self.pending.lock().unwrap();
+        pending
+            .get(commitment_id)
+            .filter(|p| p.key_id == key_id && !self.expired(&p.commitment))
+            .map(|p| p.commitment.clone())
+    }
+
+    /// Consume a commitment, checking it opens to `inputs` under `salt`
+    pub fn open(
+        &self,
+        key_id: &str,
+        commitment_id: &str,
+        salt: &str,
+        inputs: &[f32],
+    ) -> Result<InputCommitment, ApiError> {
+        let salt = hex::decode(salt.trim_start_matches("0x")).map_err(|_| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_COMMITMENT_SALT",
+                "Commitment salt must be hex",
+            )
+        })?;
+
+        let mut pending = self.pending.lock().unwrap();
+        let record = match pending.get(commitment_id) {
+            Some(p) if p.key_id == key_id && !self.expired(&p.commitment) => p.commitment.clone(),
+            _ => return Err(commitment_not_found(commitment_id)),
+        };
+        if !ct_eq(&input_commitment(&salt, inputs), &record.commitment) {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "INPUT_COMMITMENT_MISMATCH",
+                "Inputs do not match the commitment",
+            ));
+        }
+        pending.remove(commitment_id);
+        Ok(record)
+    }
+
+    fn expired(&self, commitment: &InputCommitment) -> bool {
+        commitment.committed_at + self.ttl_secs <= now_secs()
+    }
+}
+
+/// Routes mounted under `/commitments`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(create_commitment))
+        .route("/:id", get(get_commitment))
+}
+
+#[derive(Deserialize)]
+struct CreateCommitmentRequest {
+    /// `input_commitment(salt, inputs)`
+    commitment: String,
+}
+
+/// Commit to inputs ahead of a proof request
+async fn create_commitment(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Json(request): Json<CreateCommitmentRequest>,
+) -> Result<Json<InputCommitment>, ApiError> {
+    caller.require(Scope::Prove)?;
+    let digest = request.commitment.trim_start_matches("0x");
+    if digest.len() != 64 || hex::decode(digest).is_err() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_COMMITMENT",
+            "Commitment must be a 32-byte hex digest",
+        ));
+    }
+
+    let record = state
+        .commitments
+        .create(&caller.key_id, format!("0x{}", digest.to_ascii_lowercase()));
+    Ok(Json(record))
+}
+
+/// Look up an outstanding commitment
+async fn get_commitment(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(commitment_id): Path<String>,
+) -> Result<Json<InputCommitment>, ApiError> {
+    caller.require(Scope::Prove)?;
+    state
+        .commitments
+        .get(&caller.key_id, &commitment_id)
+        .map(Json)
+        .ok_or_else(|| commitment_not_found(&commitment_id))
+}
+
+fn commitment_not_found(commitment_id: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "COMMITMENT_NOT_FOUND",
+        format!("No open commitment {}", commitment_id),
+    )
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_opens_once_for_its_key() {
+        let store = CommitmentStore::new(3600);
+        let inputs = [0.5, 1.0];
+        let record = store.create("key_a", input_commitment(b"salt", &inputs));
+        let id = &record.commitment_id;
+        let salt = hex::encode(b"salt");
+
+        assert!(store.open("key_b", id, &salt, &inputs).is_err());
+        assert_eq!(
+            store.open("key_a", id, &salt, &[0.5, 2.0]).unwrap_err().0,
+            StatusCode::BAD_REQUEST
+        );
+        assert!(store.open("key_a", id, &salt, &inputs).is_ok());
+        assert_eq!(
+            store.open("key_a", id, &salt, &inputs).unwrap_err().0,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
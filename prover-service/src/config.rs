@@ -0,0 +1,432 @@
+//! Service configuration
+//!
+//! All settings are read from environment variables once at startup;
+//! `--verify-only` on the command line is the same as `VERIFY_ONLY=true`.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::admission::AdmissionThresholds;
+use crate::breaker::BreakerConfig;
+use crate::inference::InferenceEngine;
+use crate::isolation::{Isolation, IsolationMode, JobLimits};
+use crate::quotas::QuotaLimits;
+use crate::sandbox::WasmSandbox;
+use crate::secrets::VaultConfig;
+use crate::warmup::MAX_WARMUP_INFERENCES;
+use crate::witness::WitnessBudget;
+
+/// Command-line flag starting a verification-only node
+pub const VERIFY_ONLY_ARG: &str = "--verify-only";
+
+/// Runtime configuration for the prover service
+pub struct ServiceConfig {
+    /// Bearer token required on `/admin/*` routes (admin API disabled when unset)
+    ///
+    /// Loaded from the `admin_token` secret when Vault is configured.
+    pub admin_token: Option<String>,
+
+    /// External secret store (secrets come from env/disk when unset)
+    pub vault: Option<VaultConfig>,
+
+    /// Directory for persistent service state (API keys, registry metadata)
+    pub data_dir: PathBuf,
+
+    /// Reject requests without a valid issued API key
+    pub require_api_key: bool,
+
+    /// Quota shared by anonymous callers (see `quotas`)
+    pub anonymous_quota: QuotaLimits,
+
+    /// Rotate the service signing key once it is this old (no scheduled rotation when unset)
+    pub signing_key_rotation_secs: Option<u64>,
+
+    /// How long a rotated-out signing key stays advertised and verifiable
+    pub signing_key_retire_secs: u64,
+
+    /// Reject model registrations without a registrant signature
+    pub require_registrant_signature: bool,
+
+    /// Public keys (hex) allowed to register models (any signer when empty)
+    pub registrant_allowlist: Vec<String>,
+
+    /// Append-only registry: no deletes and no reuse of model names
+    pub immutable_registry: bool,
+
+    /// Refuse mock inference and mock proofs (on by default with
+    /// `USE_REAL_PROVER=true`)
+    pub strict_mode: bool,
+
+    /// Verification-only node: only `/verify`, `/verify/proof` and
+    /// `/proofs/inspect` are served, no models are loaded and no background
+    /// tasks run
+    pub verify_only: bool,
+
+    /// Alternate backend for A/B experiments: `mock` or the path of a Jolt
+    /// Atlas binary
+    pub ab_backend: Option<String>,
+
+    /// Extra backends consensus proofs are cross-checked on, as for
+    /// `ab_backend`
+    pub consensus_backends: Vec<String>,
+
+    /// Backend a sample of requests is shadow-proven on, as for `ab_backend`
+    pub shadow_backend: Option<String>,
+
+    /// Backends, in order, a proof is retried on when the preferred backend
+    /// fails, as for `ab_backend`
+    pub fallback_backends: Vec<String>,
+
+    /// Circuit breakers around the backends and the ONNX runtime
+    pub breakers: BreakerConfig,
+
+    /// Share of requests, 0-1, shadow-proven (adjustable at runtime)
+    pub shadow_fraction: f64,
+
+    /// Prove on the GPU, for backends that support it (switchable at
+    /// runtime through the `gpu` feature flag)
+    pub gpu: bool,
+
+    /// Engine native inference runs on (`ort` or `tract`)
+    pub inference_engine: InferenceEngine,
+
+    /// Prove and verify the bundled self-test model at startup, holding
+    /// `/ready` until it passes
+    pub self_test: bool,
+
+    /// Register the bundled example models under fixed `demo-*` ids
+    pub demo_mode: bool,
+
+    /// How long a model stays archived before it may be purged
+    pub model_retention_secs: u64,
+
+    /// Public keys (hex) of oracles trusted to sign prover inputs
+    pub oracle_keys: Vec<String>,
+
+    /// Base URL of the Coinbase Exchange API used for market-data inputs
+    pub coinbase_exchange_url: String,
+
+    /// How long an input commitment stays open for a proof request
+    pub input_commitment_ttl_secs: u64,
+
+    /// How long a nonce from `GET /challenge` stays valid for a proof request
+    pub challenge_ttl_secs: u64,
+
+    /// Sweep orphaned artifacts this often (no background GC when unset)
+    pub gc_interval_secs: Option<u64>,
+
+    /// Minimum age before an unreferenced artifact may be collected
+    pub gc_min_age_secs: u64,
+
+    /// JSON-RPC endpoint of the Base node watched for events (no watcher when unset)
+    pub base_rpc_url: Option<String>,
+
+    /// JSON file listing the contract events that trigger proofs
+    pub event_watchers_file: Option<PathBuf>,
+
+    /// Blocks an event must be buried under before it is proven
+    pub event_confirmations: u64,
+
+    /// Broker to consume prove requests from (`nats://...` or `kafka://...`)
+    pub ingest_url: Option<String>,
+
+    /// Subject/topic prove requests are consumed from
+    pub ingest_topic: String,
+
+    /// Subject/topic results are published to
+    pub ingest_result_topic: String,
+
+    /// Queue group / consumer group shared by service replicas
+    pub ingest_group: String,
+
+    /// Delivery attempts per webhook before it is dead-lettered
+    pub webhook_max_attempts: u32,
+
+    /// How proving jobs are isolated, with per-job limits for worker processes
+    pub isolation: Isolation,
+
+    /// Most memory a proving job may use (see `memory`; no budget when unset)
+    pub job_memory_budget: Option<u64>,
+
+    /// WASM inference engine for sandboxed models (no sandbox when unset)
+    pub wasm_sandbox: Option<WasmSandbox>,
+
+    /// Seconds between proving checkpoints (no checkpointing when unset)
+    pub checkpoint_interval_secs: Option<u64>,
+
+    /// Age after which checkpoints of abandoned proofs are removed
+    pub checkpoint_max_age_secs: u64,
+
+    /// How long stored proofs are kept (forever when unset)
+    pub proof_ttl_secs: Option<u64>,
+
+    /// How long finished job records are kept (forever when unset)
+    pub job_ttl_secs: Option<u64>,
+
+    /// Per-tenant proof TTLs (`tenant=secs`)
+    pub tenant_proof_ttls: Vec<String>,
+
+    /// Per-tenant job record TTLs (`tenant=secs`)
+    pub tenant_job_ttls: Vec<String>,
+
+    /// Remove expired results this often (no background cleanup when unset)
+    pub retention_interval_secs: Option<u64>,
+
+    /// Back up the registry, models and proofs this often (no scheduled
+    /// backups when unset)
+    pub backup_interval_secs: Option<u64>,
+
+    /// Backups kept in storage; older ones are removed
+    pub backup_keep: usize,
+
+    /// How long `POST /prove` responses are cached (no caching when unset)
+    pub proof_cache_ttl_secs: Option<u64>,
+
+    /// How long `POST /verify` verdicts are cached (no caching when unset)
+    pub verify_cache_ttl_secs: Option<u64>,
+
+    /// Verdicts kept in the verification cache
+    pub verify_cache_max_entries: usize,
+
+    /// Record every proof's inference for `POST /replay/:proof_id`
+    pub record_inference: bool,
+
+    /// Redis instance the proof cache and leader lease are shared through
+    /// (in memory and SQLite when unset)
+    pub redis_url: Option<String>,
+
+    /// Elect one replica to run scheduled proofs, GC, retention and backups
+    pub leader_election: bool,
+
+    /// Lease of the elected leader; another replica takes over this long
+    /// after the leader stops renewing it
+    pub leader_lease_secs: u64,
+
+    /// Name of this replica in the cluster (random when unset)
+    pub replica_id: Option<String>,
+
+    /// Memory budget for streamed witness generation (no budget when unset)
+    pub witness_budget: Option<WitnessBudget>,
+
+    /// Proofs generated concurrently; further requests wait in the fair queue
+    pub prove_concurrency: usize,
+
+    /// Starts of a durable job before it is abandoned (restarts included)
+    pub job_max_attempts: u32,
+
+    /// Backoff before a job's first retry after a transient failure,
+    /// doubling with each further attempt
+    pub job_retry_base: Duration,
+
+    /// Longest backoff between job retries
+    pub job_retry_max: Duration,
+
+    /// Most rows accepted in one Arrow/Parquet batch upload
+    pub batch_max_rows: usize,
+
+    /// Largest request body accepted, model uploads aside (see `body_limit`)
+    pub max_body_bytes: usize,
+
+    /// Largest model registration or registry restore body
+    pub max_model_body_bytes: usize,
+
+    /// Largest raw f32 `/prove` body (see `raw_input`)
+    pub max_raw_body_bytes: usize,
+
+    /// Time budget of routes without their own (see `timeouts`)
+    pub request_timeout_secs: u64,
+
+    /// Time budget of proving and large-upload routes
+    pub prove_timeout_secs: u64,
+
+    /// Time budget of verification routes
+    pub verify_timeout_secs: u64,
+
+    /// Time budget of the health and readiness probes
+    pub health_timeout_secs: u64,
+
+    /// Load over which proving requests are shed (see `admission`)
+    pub admission: AdmissionThresholds,
+
+    /// `Retry-After` of shed proving requests
+    pub admission_retry_after_secs: u64,
+
+    /// Fair-queue weight per tenant (`tenant=weight`, default 1)
+    pub tenant_weights: Vec<String>,
+
+    /// Names of models kept warm whenever registered (see `warm`)
+    pub hot_models: Vec<String>,
+
+    /// Warm-up inferences run after each registration that does not ask
+    /// for a number itself (see `warmup`)
+    pub model_warmup_inferences: u32,
+}
+
+impl ServiceConfig {
+    /// Load configuration from the environment
+    pub fn from_env() -> Self {
+        let job_memory_budget = env_string("JOB_MEMORY_BUDGET_MB")
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|mb| mb << 20);
+        Self {
+            admin_token: env_string("ADMIN_TOKEN"),
+            vault: VaultConfig::from_env(),
+            data_dir: env_string("DATA_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("./data")),
+            require_api_key: env_flag("REQUIRE_API_KEY", false),
+            anonymous_quota: QuotaLimits {
+                daily_proofs: Some(env_parse("ANONYMOUS_DAILY_PROOFS", 100)),
+                monthly_proofs: env_string("ANONYMOUS_MONTHLY_PROOFS").and_then(|v| v.parse().ok()),
+                daily_proving_seconds: env_string("ANONYMOUS_DAILY_PROVING_SECONDS")
+                    .and_then(|v| v.parse().ok()),
+                monthly_proving_seconds: env_string("ANONYMOUS_MONTHLY_PROVING_SECONDS")
+                    .and_then(|v| v.parse().ok()),
+            },
+            signing_key_rotation_secs: env_string("SIGNING_KEY_ROTATION_SECS")
+                .and_then(|v| v.parse().ok()),
+            signing_key_retire_secs: env_parse("SIGNING_KEY_RETIRE_SECS", 7 * 24 * 3600),
+            require_registrant_signature: env_flag("REQUIRE_REGISTRANT_SIGNATURE", false),
+            registrant_allowlist: env_list("REGISTRANT_ALLOWLIST"),
+            immutable_registry: env_flag("IMMUTABLE_REGISTRY", false),
+            strict_mode: env_flag("STRICT_MODE", env_flag("USE_REAL_PROVER", false)),
+            verify_only: env_flag("VERIFY_ONLY", false)
+                || std::env::args().any(|arg| arg == VERIFY_ONLY_ARG),
+            ab_backend: env_string("AB_BACKEND"),
+            consensus_backends: env_list("CONSENSUS_BACKENDS"),
+            shadow_backend: env_string("SHADOW_BACKEND"),
+            fallback_backends: env_list("FALLBACK_BACKENDS"),
+            breakers: BreakerConfig {
+                failure_threshold: env_parse("BREAKER_FAILURE_THRESHOLD", 5).max(1),
+                cooldown: Duration::from_secs(env_parse("BREAKER_COOLDOWN_SECS", 30)),
+                call_timeout: env_string("BREAKER_CALL_TIMEOUT_SECS")
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_secs),
+            },
+            shadow_fraction: env_parse("SHADOW_FRACTION", 0.0_f64).clamp(0.0, 1.0),
+            gpu: env_flag("PROVE_ON_GPU", false),
+            inference_engine: env_parse("INFERENCE_ENGINE", InferenceEngine::default()),
+            self_test: env_flag("SELF_TEST", true),
+            demo_mode: env_flag("DEMO_MODE", false),
+            model_retention_secs: env_parse("MODEL_RETENTION_SECS", 30 * 24 * 3600),
+            oracle_keys: env_list("ORACLE_KEYS"),
+            coinbase_exchange_url: env_string("COINBASE_EXCHANGE_URL")
+                .unwrap_or_else(|| "https://api.exchange.coinbase.com".to_string()),
+            input_commitment_ttl_secs: env_parse("INPUT_COMMITMENT_TTL_SECS", 24 * 3600),
+            challenge_ttl_secs: env_parse("CHALLENGE_TTL_SECS", 300),
+            gc_interval_secs: env_string("GC_INTERVAL_SECS").and_then(|v| v.parse().ok()),
+            gc_min_age_secs: env_parse("GC_MIN_AGE_SECS", 3600),
+            base_rpc_url: env_string("BASE_RPC_URL"),
+            event_watchers_file: env_string("EVENT_WATCHERS_FILE").map(PathBuf::from),
+            event_confirmations: env_parse("EVENT_CONFIRMATIONS", 2),
+            ingest_url: env_string("INGEST_URL"),
+            ingest_topic: env_string("INGEST_TOPIC")
+                .unwrap_or_else(|| "prove.requests".to_string()),
+            ingest_result_topic: env_string("INGEST_RESULT_TOPIC")
+                .unwrap_or_else(|| "prove.results".to_string()),
+            ingest_group: env_string("INGEST_GROUP")
+                .unwrap_or_else(|| "jolt-atlas-prover".to_string()),
+            webhook_max_attempts: env_parse("WEBHOOK_MAX_ATTEMPTS", 6),
+            isolation: Isolation {
+                mode: env_parse("PROVE_ISOLATION", IsolationMode::Thread),
+                limits: JobLimits {
+                    max_rss_bytes: env_string("PROVE_JOB_MAX_RSS_MB")
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(|mb| mb << 20)
+                        .into_iter()
+                        .chain(job_memory_budget)
+                        .min(),
+                    max_cpu_secs: env_string("PROVE_JOB_MAX_CPU_SECS").and_then(|v| v.parse().ok()),
+                },
+            },
+            job_memory_budget,
+            wasm_sandbox: env_string("WASM_ENGINE_PATH").map(|path| WasmSandbox {
+                engine_path: PathBuf::from(path),
+                fuel: env_parse("WASM_FUEL", 10_000_000_000),
+                max_memory_bytes: env_parse::<usize>("WASM_MEMORY_MB", 512) << 20,
+            }),
+            checkpoint_interval_secs: env_string("CHECKPOINT_INTERVAL_SECS")
+                .and_then(|v| v.parse().ok()),
+            checkpoint_max_age_secs: env_parse("CHECKPOINT_MAX_AGE_SECS", 7 * 24 * 3600),
+            proof_ttl_secs: env_string("PROOF_TTL_SECS").and_then(|v| v.parse().ok()),
+            job_ttl_secs: env_string("JOB_TTL_SECS").and_then(|v| v.parse().ok()),
+            tenant_proof_ttls: env_list("TENANT_PROOF_TTL_SECS"),
+            tenant_job_ttls: env_list("TENANT_JOB_TTL_SECS"),
+            retention_interval_secs: env_string("RETENTION_INTERVAL_SECS")
+                .and_then(|v| v.parse().ok()),
+            backup_interval_secs: env_string("BACKUP_INTERVAL_SECS").and_then(|v| v.parse().ok()),
+            backup_keep: env_parse("BACKUP_KEEP", 7),
+            proof_cache_ttl_secs: env_string("PROOF_CACHE_TTL_SECS").and_then(|v| v.parse().ok()),
+            verify_cache_ttl_secs: env_string("VERIFY_CACHE_TTL_SECS").and_then(|v| v.parse().ok()),
+            verify_cache_max_entries: env_parse("VERIFY_CACHE_MAX_ENTRIES", 100_000),
+            record_inference: env_flag("RECORD_INFERENCE", false),
+            redis_url: env_string("REDIS_URL"),
+            leader_election: env_flag("LEADER_ELECTION", false),
+            leader_lease_secs: env_parse("LEADER_LEASE_SECS", 15),
+            replica_id: env_string("REPLICA_ID"),
+            witness_budget: env_string("WITNESS_MEMORY_BUDGET_MB")
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|mb| WitnessBudget {
+                    max_bytes: mb << 20,
+                    chunk_rows: env_parse("WITNESS_CHUNK_ROWS", 1 << 16),
+                }),
+            prove_concurrency: env_parse("PROVE_CONCURRENCY", 4),
+            job_max_attempts: env_parse("JOB_MAX_ATTEMPTS", 3),
+            job_retry_base: Duration::from_secs(env_parse("JOB_RETRY_BASE_SECS", 2)),
+            job_retry_max: Duration::from_secs(env_parse("JOB_RETRY_MAX_SECS", 60)),
+            batch_max_rows: env_parse("BATCH_MAX_ROWS", 100_000),
+            max_body_bytes: env_parse("MAX_BODY_BYTES", 4 << 20),
+            max_model_body_bytes: env_parse("MAX_MODEL_BODY_BYTES", 1 << 30),
+            max_raw_body_bytes: env_parse("MAX_RAW_BODY_BYTES", 64 << 20),
+            request_timeout_secs: env_parse("REQUEST_TIMEOUT_SECS", 120),
+            prove_timeout_secs: env_parse("PROVE_TIMEOUT_SECS", 900),
+            verify_timeout_secs: env_parse("VERIFY_TIMEOUT_SECS", 30),
+            health_timeout_secs: env_parse("HEALTH_TIMEOUT_SECS", 5),
+            admission: AdmissionThresholds {
+                max_load_per_cpu: env_string("ADMISSION_MAX_LOAD").and_then(|v| v.parse().ok()),
+                min_available_mb: env_string("ADMISSION_MIN_AVAILABLE_MB")
+                    .and_then(|v| v.parse().ok()),
+                max_queue_depth: env_string("ADMISSION_MAX_QUEUE_DEPTH")
+                    .and_then(|v| v.parse().ok()),
+            },
+            admission_retry_after_secs: env_parse("ADMISSION_RETRY_AFTER_SECS", 5),
+            tenant_weights: env_list("TENANT_WEIGHTS"),
+            hot_models: env_list("HOT_MODELS"),
+            model_warmup_inferences: env_parse("MODEL_WARMUP_INFERENCES", 0_u32)
+                .min(MAX_WARMUP_INFERENCES),
+        }
+    }
+}
+
+/// Read a non-empty string variable
+fn env_string(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Read a boolean variable (`1`/`true`/`yes`/`on`)
+fn env_flag(name: &str, default: bool) -> bool {
+    match env_string(name) {
+        Some(v) => matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"),
+        None => default,
+    }
+}
+
+/// Read a comma-separated list variable
+fn env_list(name: &str) -> Vec<String> {
+    env_string(name)
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse a variable, falling back to `default` when unset or invalid
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env_string(name)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
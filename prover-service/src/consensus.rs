@@ -0,0 +1,200 @@
+//! Multi-prover consensus
+//!
+//! With `CONSENSUS_BACKENDS` set to a comma-separated list of extra backends
+//! (`mock`, or paths of Jolt Atlas binaries, as for `AB_BACKEND`), a prove
+//! request with `"consensus": true` is proven on the primary backend and on
+//! every extra backend at once. Each proof must verify with the backend that
+//! made it, and all of them must commit to the same model, inputs and
+//! outputs. Only then is the primary backend's proof returned, with the
+//! backends that agreed listed in its public inputs (`consensus`), so the
+//! service signature and the transparency log cover the agreement.
+//!
+//! Any divergence fails the request with `CONSENSUS_DIVERGENCE`, naming what
+//! each dissenting backend produced; it is also logged as an error. This is
+//! defense in depth for high-value settlements: a single buggy backend can
+//! no longer get a wrong result signed on its own.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::jolt_atlas::deserialize_proof;
+use crate::prover::JoltAtlasProver;
+use crate::types::{ProofResult, ProveRequest};
+
+/// Backends that agreed on a consensus proof
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consensus {
+    /// Prover ids of every backend, the primary one first
+    pub provers: Vec<String>,
+}
+
+/// Consensus was requested without extra backends to reach it with
+#[derive(Debug, thiserror::Error)]
+#[error("Consensus proving is not enabled (set CONSENSUS_BACKENDS)")]
+pub struct ConsensusUnavailable;
+
+/// Backends did not all produce agreeing, verifying proofs
+#[derive(Debug, thiserror::Error)]
+#[error("Backends diverged: {}", .0.join("; "))]
+pub struct ConsensusDivergence(pub Vec<String>);
+
+/// What a backend's proof commits to
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Claims {
+    prover_id: String,
+    model_commitment: String,
+    input_hash: String,
+    output_hash: String,
+}
+
+impl Claims {
+    fn of(result: &ProofResult) -> Result<Self> {
+        Ok(Self {
+            prover_id: deserialize_proof(&result.proof)?.prover_id,
+            model_commitment: result.model_commitment.clone(),
+            input_hash: result.input_hash.clone(),
+            output_hash: result.output_hash.clone(),
+        })
+    }
+
+    fn agrees_with(&self, other: &Claims) -> bool {
+        self.model_commitment == other.model_commitment
+            && self.input_hash == other.input_hash
+            && self.output_hash == other.output_hash
+    }
+}
+
+/// Prove `request` on the primary and every consensus backend, returning the
+/// primary backend's proof if they all agree
+pub async fn prove(prover: &JoltAtlasProver, request: &ProveRequest) -> Result<ProofResult> {
+    let backends = prover.consensus_backends();
+    if backends.is_empty() {
+        return Err(ConsensusUnavailable.into());
+    }
+
+    let primary = async {
+        let result = prover.generate_proof(request).await?;
+        let verified = prover
+            .verify_embedded(&result.proof, request.challenge.as_deref())
+            .await?
+            .is_some();
+        anyhow::Ok((result, verified))
+    };
+    let others = futures_util::future::join_all(
+        (0..backends.len()).map(|index| prover.generate_consensus_proof(request, index)),
+    );
+    let (primary, others) = tokio::join!(primary, others);
+
+    // The primary backend's own errors (e.g. an output mismatch) stand as is
+    let (mut result, verified) = primary?;
+    let claims = Claims::of(&result)?;
+    let others = backends
+        .into_iter()
+        .zip(others)
+        .map(|(backend, other)| {
+            let outcome = match other {
+                Ok((other, true)) => Claims::of(&other).map_err(|e| e.to_string()),
+                Ok((_, false)) => Err("proof does not verify".to_string()),
+                Err(e) => Err(format!("failed ({})", e)),
+            };
+            (backend, outcome)
+        })
+        .collect();
+    let consensus = cross_check(&claims, verified, others).map_err(|divergence| {
+        tracing::error!(
+            "Consensus proof of model {} failed: {}",
+            request.model_id,
+            divergence
+        );
+        divergence
+    })?;
+    result.public_inputs.consensus = Some(consensus);
+    Ok(result)
+}
+
+/// Check every extra backend produced a verifying proof with the primary
+/// backend's claims
+fn cross_check(
+    primary: &Claims,
+    primary_verified: bool,
+    others: Vec<(String, Result<Claims, String>)>,
+) -> Result<Consensus, ConsensusDivergence> {
+    let mut provers = vec![primary.prover_id.clone()];
+    let mut divergences = Vec::new();
+    if !primary_verified {
+        divergences.push(format!("{}: proof does not verify", primary.prover_id));
+    }
+    for (backend, outcome) in others {
+        match outcome {
+            Ok(claims) if claims.agrees_with(primary) => provers.push(claims.prover_id),
+            Ok(claims) => divergences.push(format!(
+                "{}: model {}, input hash {}, output hash {} (primary: {}, {}, {})",
+                backend,
+                claims.model_commitment,
+                claims.input_hash,
+                claims.output_hash,
+                primary.model_commitment,
+                primary.input_hash,
+                primary.output_hash
+            )),
+            Err(reason) => divergences.push(format!("{}: {}", backend, reason)),
+        }
+    }
+    if divergences.is_empty() {
+        Ok(Consensus { provers })
+    } else {
+        Err(ConsensusDivergence(divergences))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(prover_id: &str, output_hash: &str) -> Claims {
+        Claims {
+            prover_id: prover_id.to_string(),
+            model_commitment: "0xmodel".to_string(),
+            input_hash: "0xin".to_string(),
+            output_hash: output_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_consensus_requires_agreeing_verified_proofs() {
+        let primary = claims("a", "0xout");
+        let consensus = cross_check(
+            &primary,
+            true,
+            vec![
+                ("b".to_string(), Ok(claims("b", "0xout"))),
+                ("c".to_string(), Ok(claims("c", "0xout"))),
+            ],
+        )
+        .unwrap();
+        assert_eq!(consensus.provers, ["a", "b", "c"]);
+
+        let divergence = cross_check(
+            &primary,
+            true,
+            vec![
+                ("b".to_string(), Ok(claims("b", "0xother"))),
+                ("c".to_string(), Err("proof does not verify".to_string())),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(divergence.0.len(), 2);
+        assert!(
+            divergence.0[0].starts_with("b: model 0xmodel, input hash 0xin, output hash 0xother")
+        );
+        assert_eq!(divergence.0[1], "c: proof does not verify");
+
+        let divergence = cross_check(
+            &primary,
+            false,
+            vec![("b".to_string(), Ok(claims("b", "0xout")))],
+        )
+        .unwrap_err();
+        assert_eq!(divergence.0, ["a: proof does not verify"]);
+    }
+}
@@ -0,0 +1,148 @@
+//! Bundled example models
+//!
+//! With `DEMO_MODE=true` the models below are registered at startup under
+//! fixed ids, so a new user can call `/prove` straight away, e.g. with
+//! `{"model_id": "demo-logistic", "inputs": [1.0, 0.5, 0.25, 2.0]}`.
+//! `GET /demo` lists them with example inputs. The models are embedded in the
+//! binary; `jolt-atlas/models/demo/gen.py` regenerates the small ones.
+
+use anyhow::Result;
+use axum::{extract::State, Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::prover::JoltAtlasProver;
+use crate::queue::ModelLimits;
+use crate::sandbox::ModelExecution;
+use crate::types::RegisterModelRequest;
+use crate::AppState;
+
+/// An example model registered in demo mode
+pub struct DemoModel {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub example_inputs: &'static [f32],
+    bytes: &'static [u8],
+}
+
+/// The bundled example models
+pub const MODELS: &[DemoModel] = &[
+    DemoModel {
+        id: "demo-logistic",
+        name: "demo-logistic-regression",
+        description: "Logistic regression over 4 features; outputs one probability",
+        example_inputs: &[1.0, 0.5, 0.25, 2.0],
+        bytes: include_bytes!("../jolt-atlas/models/demo/logistic.onnx"),
+    },
+    DemoModel {
+        id: "demo-mlp",
+        name: "demo-mlp",
+        description: "Two-layer ReLU network over 8 features; outputs 2 scores",
+        example_inputs: &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8],
+        bytes: include_bytes!("../jolt-atlas/models/demo/mlp.onnx"),
+    },
+    DemoModel {
+        id: "demo-authorization",
+        name: "demo-authorization",
+        description: "Transaction authorization classifier over 64 one-hot features; \
+                      outputs 4 logits",
+        example_inputs: &[
+            1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0,
+        ],
+        bytes: include_bytes!("../jolt-atlas/models/authorization/network.onnx"),
+    },
+];
+
+impl DemoModel {
+    /// The model file as bundled
+    pub fn bytes(&self) -> &'static [u8] {
+        self.bytes
+    }
+
+    fn registration(&self) -> RegisterModelRequest {
+        RegisterModelRequest {
+            name: self.name.to_string(),
+            model_bytes: BASE64.encode(self.bytes),
+            description: Some(self.description.to_string()),
+            provenance: None,
+            registrant: None,
+            execution: ModelExecution::Native,
+            shards: None,
+            hot: false,
+            limits: ModelLimits::default(),
+            quantization: None,
+            preprocessor: None,
+            warmup: None,
+        }
+    }
+}
+
+/// Register every example model under its fixed id
+pub async fn register(prover: &mut JoltAtlasProver) -> Result<()> {
+    for model in MODELS {
+        let info = prover
+            .register_model_as(model.id.to_string(), &model.registration(), None)
+            .await?;
+        tracing::info!(
+            "Demo model {} registered with commitment {}",
+            info.id,
+            info.commitment
+        );
+    }
+    Ok(())
+}
+
+/// A registered example model, as listed to users
+#[derive(Serialize)]
+pub struct DemoModelInfo {
+    pub model_id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub commitment: String,
+    pub example_inputs: &'static [f32],
+}
+
+/// List the example models registered in demo mode
+pub async fn list(State(state): State<Arc<AppState>>) -> Json<Vec<DemoModelInfo>> {
+    let prover = state.prover.read().await;
+    Json(
+        MODELS
+            .iter()
+            .filter_map(|model| {
+                let info = prover.get_model(model.id)?;
+                Some(DemoModelInfo {
+                    model_id: model.id,
+                    name: model.name,
+                    description: model.description,
+                    commitment: info.commitment.clone(),
+                    example_inputs: model.example_inputs,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onnx;
+
+    #[test]
+    fn test_demo_models_parse_and_have_weights() {
+        for model in MODELS {
+            let canonical = onnx::canonicalize(model.bytes).unwrap();
+            assert!(
+                !onnx::weight_tensors(&canonical).unwrap().is_empty(),
+                "{} has no weights",
+                model.id
+            );
+            let request = model.registration();
+            assert_eq!(request.decode_model_bytes().unwrap(), model.bytes);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! End-to-end encrypted prover inputs
+//!
+//! The service holds an X25519 key pair whose public half is advertised at
+//! `GET /keys/encryption`. Clients seal their input features to it with a
+//! libsodium-compatible sealed box (`crypto_box_seal`) and send the result as
+//! `encrypted_inputs` instead of `inputs`. Gateways and queues in between only
+//! ever see ciphertext; the features are decrypted in memory right before
+//! proving and never logged or persisted.
+//!
+//! The key is persisted to `input_key.json` in the data directory, or to the
+//! `input_key` secret when an external secret store is configured.
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use crypto_box::{PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::persist::{load_json, save_json};
+use crate::secrets::Secrets;
+
+/// Encryption scheme identifier
+pub const INPUT_ENCRYPTION_ALG: &str = "x25519-xsalsa20poly1305-sealedbox";
+
+/// Name of the secret holding the key in an external secret store
+const INPUT_KEY_SECRET: &str = "input_key";
+
+/// Input features sealed to the service's encryption key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptedInputs {
+    /// Id of the key the inputs were sealed to
+    pub kid: String,
+
+    /// Sealed box over the JSON-encoded feature vector (base64)
+    pub ciphertext: String,
+}
+
+/// Public encryption key as advertised by `GET /keys/encryption`
+#[derive(Clone, Serialize)]
+pub struct InputKeyInfo {
+    pub kid: String,
+    pub alg: String,
+    /// X25519 public key (hex)
+    pub public_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredInputKey {
+    /// X25519 secret key (hex)
+    secret: String,
+}
+
+/// The service's input decryption key
+pub struct InputKey {
+    kid: String,
+    secret: SecretKey,
+}
+
+impl InputKey {
+    /// Load the key, generating one on first start
+    pub async fn open(data_dir: &Path, secrets: Arc<Secrets>) -> Result<Self> {
+        let stored = if secrets.is_external() {
+            match secrets.get(INPUT_KEY_SECRET).await? {
+                Some(json) => serde_json::from_str(&json)?,
+                None => {
+                    let stored = StoredInputKey::generate();
+                    secrets
+                        .put(INPUT_KEY_SECRET, &serde_json::to_string(&stored)?)
+                        .await?;
+                    stored
+                }
+            }
+        } else {
+            let path = data_dir.join("input_key.json");
+            match load_json(&path)? {
+                Some(stored) => stored,
+                None => {
+                    let stored = StoredInputKey::generate();
+                    save_json(&path, &stored)?;
+                    stored
+                }
+            }
+        };
+        Self::from_stored(&stored)
+    }
+
+    fn from_stored(stored: &StoredInputKey) -> Result<Self> {
+        let bytes: [u8; 32] = hex::decode(&stored.secret)?
+            .try_into()
+            .map_err(|_| anyhow!("Input encryption key has invalid length"))?;
+        let secret = SecretKey::from_bytes(bytes);
+        Ok(Self {
+            kid: key_id(&secret.public_key()),
+            secret,
+        })
+    }
+
+    pub fn public_key_info(&self) -> InputKeyInfo {
+        InputKeyInfo {
+            kid: self.kid.clone(),
+            alg: INPUT_ENCRYPTION_ALG.to_string(),
+            public_key: hex::encode(self.secret.public_key().as_bytes()),
+        }
+    }
+
+    /// Decrypt sealed inputs into a feature vector
+    pub fn open_inputs(&self, sealed: &EncryptedInputs) -> Result<Vec<f32>> {
+        if sealed.kid != self.kid {
+            return Err(anyhow!(
+                "Inputs are sealed to unknown key {} (current key is {})",
+                sealed.kid,
+                self.kid
+            ));
+        }
+        let ciphertext = base64::engine::general_purpose::STANDARD
+            .decode(&sealed.ciphertext)
+            .map_err(|_| anyhow!("Invalid ciphertext encoding"))?;
+        let plaintext = self
+            .secret
+            .unseal(&ciphertext)
+            .map_err(|_| anyhow!("Inputs could not be decrypted"))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|_| anyhow!("Decrypted inputs are not a JSON array of numbers"))
+    }
+}
+
+impl StoredInputKey {
+    fn generate() -> Self {
+        Self {
+            secret: hex::encode(SecretKey::generate(&mut OsRng).to_bytes()),
+        }
+    }
+}
+
+/// Key id: truncated SHA-256 of the public key
+fn key_id(public: &PublicKey) -> String {
+    format!(
+        "enc_{}",
+        &hex::encode(Sha256::digest(public.as_bytes()))[..16]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_inputs_round_trip() {
+        let key = InputKey::from_stored(&StoredInputKey::generate()).unwrap();
+        let info = key.public_key_info();
+        let public: [u8; 32] = hex::decode(&info.public_key).unwrap().try_into().unwrap();
+
+        let ciphertext = PublicKey::from_bytes(public)
+            .seal(&mut OsRng, b"[0.5,1.25,-3.0]")
+            .unwrap();
+        let sealed = EncryptedInputs {
+            kid: info.kid,
+            ciphertext: base64::engine::general_purpose::STANDARD.encode(&ciphertext),
+        };
+        assert_eq!(key.open_inputs(&sealed).unwrap(), vec![0.5, 1.25, -3.0]);
+
+        let other = InputKey::from_stored(&StoredInputKey::generate()).unwrap();
+        let resealed = EncryptedInputs {
+            kid: other.kid.clone(),
+            ..sealed
+        };
+        assert!(other.open_inputs(&resealed).is_err());
+    }
+}
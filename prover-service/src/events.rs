@@ -0,0 +1,450 @@
+//! Onchain event-triggered proving
+//!
+//! When `BASE_RPC_URL` and `EVENT_WATCHERS_FILE` are set, the service polls
+//! the chain with `eth_getLogs` for the contract events listed in the
+//! watchers file and runs a proof for every matching log. Each watcher maps
+//! 32-byte words of the log (indexed topics or ABI-encoded data) to model
+//! inputs:
+//!
+//! ```json
+//! [{
+//!   "id": "eth-usd",
+//!   "address": "0x...",
+//!   "event": "AnswerUpdated(int256,uint256,uint256)",
+//!   "model_id": "risk-model",
+//!   "inputs": [{ "source": "topic", "index": 1, "signed": true, "scale": 1e-8 }],
+//!   "webhook_url": "https://agent.example/hooks/proofs",
+//!   "webhook_secret": "..."
+//! }]
+//! ```
+//!
+//! Results are kept in the proof store and, when `webhook_url` is set,
+//! posted as a `proof.completed` (or `proof.failed`) webhook, signed with
+//! `webhook_secret`, together with the triggering log. Logs are processed
+//! once `EVENT_CONFIRMATIONS` blocks deep; the last processed block per
+//! watcher is persisted to `event_cursors.json`, and a new watcher starts at
+//! the current head.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
+
+use crate::auth::Caller;
+use crate::persist::{load_json, save_json};
+use crate::request_id::RequestId;
+use crate::types::ProveRequest;
+use crate::webhooks::WebhookEndpoint;
+use crate::AppState;
+
+/// How often the chain is polled for new blocks
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Most blocks requested in a single `eth_getLogs` call
+const MAX_BLOCK_RANGE: u64 = 500;
+
+/// Which part of a log a model input is read from
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordSource {
+    /// `topics[index]` (topic 0 is the event signature)
+    Topic,
+    /// The `index`-th 32-byte word of the log data
+    Data,
+}
+
+/// Mapping of one log word to one model input
+#[derive(Clone, Debug, Deserialize)]
+pub struct InputMapping {
+    pub source: WordSource,
+    pub index: usize,
+    /// Interpret the word as a two's-complement `int256`
+    #[serde(default)]
+    pub signed: bool,
+    /// Multiplier applied to the integer value
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// A contract event that triggers proofs
+#[derive(Clone, Debug, Deserialize)]
+pub struct WatcherConfig {
+    pub id: String,
+    /// Contract address
+    pub address: String,
+    /// Event signature, e.g. `Transfer(address,address,uint256)`
+    pub event: String,
+    pub model_id: String,
+    pub inputs: Vec<InputMapping>,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Secret webhook deliveries are signed with (required with `webhook_url`)
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Tenant the proofs are attributed to
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+}
+
+fn default_tenant() -> String {
+    crate::auth::DEFAULT_TENANT.to_string()
+}
+
+impl WatcherConfig {
+    fn validate(&self) -> Result<()> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("Invalid watcher id {:?}", self.id));
+        }
+        let address = self.address.trim_start_matches("0x");
+        if address.len() != 40 || hex::decode(address).is_err() {
+            return Err(anyhow!("Watcher {}: invalid address", self.id));
+        }
+        if self.inputs.is_empty() {
+            return Err(anyhow!("Watcher {}: no inputs mapped", self.id));
+        }
+        if self.webhook_url.is_some() && self.webhook_secret.is_none() {
+            return Err(anyhow!(
+                "Watcher {}: webhook_url needs a webhook_secret",
+                self.id
+            ));
+        }
+        self.webhook()
+            .transpose()
+            .map_err(|e| anyhow!("Watcher {}: {}", self.id, e))?;
+        Ok(())
+    }
+
+    fn webhook(&self) -> Option<Result<WebhookEndpoint, String>> {
+        let url = self.webhook_url.as_ref()?;
+        Some(WebhookEndpoint::new(url, self.webhook_secret.clone()))
+    }
+
+    fn topic0(&self) -> String {
+        event_topic(&self.event)
+    }
+
+    /// Map a log to model inputs
+    fn map_inputs(&self, log: &Log) -> Result<Vec<f32>> {
+        let data = decode_hex(&log.data)?;
+        self.inputs
+            .iter()
+            .map(|mapping| {
+                let word = match mapping.source {
+                    WordSource::Topic => log
+                        .topics
+                        .get(mapping.index)
+                        .map(|t| decode_hex(t))
+                        .transpose()?,
+                    WordSource::Data => {
+                        data.chunks_exact(32).nth(mapping.index).map(<[u8]>::to_vec)
+                    }
+                };
+                let word: [u8; 32] = word.and_then(|w| w.try_into().ok()).ok_or_else(|| {
+                    anyhow!("Log has no {:?} word {}", mapping.source, mapping.index)
+                })?;
+                Ok((word_to_f64(&word, mapping.signed) * mapping.scale) as f32)
+            })
+            .collect()
+    }
+}
+
+/// Topic 0 of an event: Keccak-256 of its signature
+pub fn event_topic(signature: &str) -> String {
+    format!("0x{}", hex::encode(Keccak256::digest(signature.as_bytes())))
+}
+
+/// Value of a big-endian 256-bit word
+fn word_to_f64(word: &[u8; 32], signed: bool) -> f64 {
+    let magnitude = |bytes: &[u8; 32]| bytes.iter().fold(0.0, |acc, b| acc * 256.0 + *b as f64);
+    if signed && word[0] & 0x80 != 0 {
+        // Two's complement: -(!word + 1)
+        let mut negated = word.map(|b| !b);
+        for byte in negated.iter_mut().rev() {
+            let (sum, carry) = byte.overflowing_add(1);
+            *byte = sum;
+            if !carry {
+                break;
+            }
+        }
+        -magnitude(&negated)
+    } else {
+        magnitude(word)
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).context("Invalid hex in log")
+}
+
+fn parse_quantity(value: &str) -> Result<u64> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).context("Invalid quantity")
+}
+
+/// A log as returned by `eth_getLogs`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub block_number: String,
+    pub transaction_hash: String,
+    pub log_index: String,
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// Minimal JSON-RPC client
+struct ChainRpc {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl ChainRpc {
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        #[derive(Deserialize)]
+        struct RpcResponse<T> {
+            result: Option<T>,
+            error: Option<serde_json::Value>,
+        }
+
+        let response: RpcResponse<T> = self
+            .http
+            .post(&self.url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match (response.result, response.error) {
+            (Some(result), None) => Ok(result),
+            (_, error) => Err(anyhow!("{} failed: {}", method, error.unwrap_or_default())),
+        }
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        parse_quantity(&self.call::<String>("eth_blockNumber", json!([])).await?)
+    }
+
+    async fn logs(&self, address: &str, topic0: &str, from: u64, to: u64) -> Result<Vec<Log>> {
+        self.call(
+            "eth_getLogs",
+            json!([{
+                "address": address,
+                "topics": [topic0],
+                "fromBlock": format!("{:#x}", from),
+                "toBlock": format!("{:#x}", to),
+            }]),
+        )
+        .await
+    }
+}
+
+/// Polls the chain and proves on configured events
+pub struct EventWatcher {
+    rpc: ChainRpc,
+    watchers: Vec<WatcherConfig>,
+    confirmations: u64,
+    cursors_path: PathBuf,
+    cursors: BTreeMap<String, u64>,
+}
+
+impl EventWatcher {
+    /// Load and validate the watchers file
+    pub fn load(
+        rpc_url: &str,
+        watchers_file: &Path,
+        data_dir: &Path,
+        confirmations: u64,
+    ) -> Result<Self> {
+        let watchers: Vec<WatcherConfig> = serde_json::from_slice(
+            &std::fs::read(watchers_file)
+                .with_context(|| format!("Failed to read {}", watchers_file.display()))?,
+        )?;
+        for watcher in &watchers {
+            watcher.validate()?;
+        }
+        let cursors_path = data_dir.join("event_cursors.json");
+        let cursors = load_json(&cursors_path)?.unwrap_or_default();
+        Ok(Self {
+            rpc: ChainRpc {
+                http: reqwest::Client::new(),
+                url: rpc_url.to_string(),
+            },
+            watchers,
+            confirmations,
+            cursors_path,
+            cursors,
+        })
+    }
+
+    pub async fn run(mut self, state: Arc<AppState>) {
+        tracing::info!("Watching {} contract event(s)", self.watchers.len());
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.poll(&state).await {
+                tracing::warn!("Event watcher poll failed: {}", e);
+            }
+        }
+    }
+
+    async fn poll(&mut self, state: &AppState) -> Result<()> {
+        let head = self
+            .rpc
+            .block_number()
+            .await?
+            .saturating_sub(self.confirmations);
+        for watcher in self.watchers.clone() {
+            let Some(&cursor) = self.cursors.get(&watcher.id) else {
+                // New watchers start at the current head rather than replaying history
+                self.advance(&watcher.id, head)?;
+                continue;
+            };
+            if cursor >= head {
+                continue;
+            }
+            let to = head.min(cursor + MAX_BLOCK_RANGE);
+            let logs = self
+                .rpc
+                .logs(&watcher.address, &watcher.topic0(), cursor + 1, to)
+                .await?;
+            for log in logs.into_iter().filter(|log| !log.removed) {
+                let request_id = RequestId::generate();
+                let span = request_id.span();
+                prove_event(state, &watcher, log, &request_id)
+                    .instrument(span)
+                    .await;
+            }
+            self.advance(&watcher.id, to)?;
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self, watcher_id: &str, block: u64) -> Result<()> {
+        self.cursors.insert(watcher_id.to_string(), block);
+        save_json(&self.cursors_path, &self.cursors)
+    }
+}
+
+#[derive(Serialize)]
+struct EventOutcome<'a> {
+    watcher_id: &'a str,
+    model_id: &'a str,
+    log: &'a Log,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<&'a crate::proofs::StoredProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+async fn prove_event(state: &AppState, watcher: &WatcherConfig, log: Log, request_id: &RequestId) {
+    tracing::info!(
+        "Event {} matched watcher {} in tx {}",
+        watcher.event,
+        watcher.id,
+        log.transaction_hash
+    );
+    let caller = Caller {
+        key_id: format!("watcher:{}", watcher.id),
+        tenant: watcher.tenant.clone(),
+        scopes: None,
+    };
+    let source = format!(
+        "event:{}:{}:{}",
+        watcher.id, log.transaction_hash, log.log_index
+    );
+
+    let result = match watcher.map_inputs(&log) {
+        Ok(inputs) => {
+            let request = ProveRequest {
+                model_id: watcher.model_id.clone(),
+                inputs,
+                ..Default::default()
+            };
+            match crate::execute_proof(state, &caller, request).await {
+                Ok((_, response)) => state
+                    .proofs
+                    .put(&source, &caller, response)
+                    .await
+                    .map_err(|e| format!("Failed to store proof: {}", e)),
+                Err((_, axum::Json(e))) => Err(e.error),
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = &result {
+        tracing::warn!("Proof for {} failed: {}", source, e);
+    }
+
+    if let Some(Ok(endpoint)) = watcher.webhook() {
+        let outcome = EventOutcome {
+            watcher_id: &watcher.id,
+            model_id: &watcher.model_id,
+            log: &log,
+            proof: result.as_ref().ok(),
+            error: result.as_ref().err().cloned(),
+        };
+        let event = match result {
+            Ok(_) => "proof.completed",
+            Err(_) => "proof.failed",
+        };
+        state.webhooks.send(&endpoint, event, request_id, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_words_map_to_inputs() {
+        assert_eq!(
+            event_topic("Transfer(address,address,uint256)"),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+
+        let watcher: WatcherConfig = serde_json::from_value(json!({
+            "id": "feed",
+            "address": "0x0000000000000000000000000000000000000001",
+            "event": "AnswerUpdated(int256,uint256,uint256)",
+            "model_id": "m",
+            "inputs": [
+                { "source": "topic", "index": 1, "signed": true, "scale": 0.5 },
+                { "source": "data", "index": 0 }
+            ]
+        }))
+        .unwrap();
+        watcher.validate().unwrap();
+
+        let log = Log {
+            address: watcher.address.clone(),
+            topics: vec![watcher.topic0(), format!("0x{}", "ff".repeat(32))],
+            data: format!("0x{:064x}", 300),
+            block_number: "0x1".to_string(),
+            transaction_hash: "0xab".to_string(),
+            log_index: "0x0".to_string(),
+            removed: false,
+        };
+        assert_eq!(watcher.map_inputs(&log).unwrap(), vec![-0.5, 300.0]);
+    }
+}
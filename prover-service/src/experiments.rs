@@ -0,0 +1,395 @@
+//! Backend A/B experiments
+//!
+//! With `AB_BACKEND` set to an alternate backend (`mock`, or the path of a
+//! Jolt Atlas binary such as a new release), a fraction of a model's proofs
+//! can be routed through it with `PUT /admin/experiments/:model_id`
+//! (`{"fraction": 0.05}`). Every proof of a model under experiment, on either
+//! arm, is timed, sized and verified with the primary backend, and
+//! `GET /admin/experiments` compares the two arms.
+//!
+//! The alternate arm is never trusted blindly: when it fails or its proof
+//! does not verify with the primary backend, the request is proven again on
+//! the primary backend and the fallback is counted. Experiments are persisted
+//! to `experiments.json`; metrics restart with the service.
+
+use anyhow::Result;
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::auth::AdminAuth;
+use crate::persist::{load_json, save_json};
+use crate::prover::JoltAtlasProver;
+use crate::types::{api_error, ApiError, ProofResult, ProveRequest};
+use crate::AppState;
+
+/// Which backend a proof was routed to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arm {
+    Control,
+    Alternate,
+}
+
+/// A model whose proofs are split between the backends
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub model_id: String,
+    /// Fraction of proofs, 0-1, routed to the alternate backend
+    pub fraction: f64,
+    pub started_at: u64,
+}
+
+/// Counters of one arm of an experiment
+#[derive(Clone, Default, Serialize)]
+pub struct ArmMetrics {
+    pub proofs: u64,
+    pub failures: u64,
+    pub verified: u64,
+    pub verification_failures: u64,
+    pub total_proving_ms: u64,
+    pub max_proving_ms: u64,
+    pub total_proof_bytes: u64,
+}
+
+impl ArmMetrics {
+    fn record(&mut self, proving_ms: u64, outcome: Option<(usize, bool)>) {
+        self.proofs += 1;
+        self.total_proving_ms += proving_ms;
+        self.max_proving_ms = self.max_proving_ms.max(proving_ms);
+        match outcome {
+            Some((proof_bytes, verified)) => {
+                self.total_proof_bytes += proof_bytes as u64;
+                if verified {
+                    self.verified += 1;
+                } else {
+                    self.verification_failures += 1;
+                }
+            }
+            None => self.failures += 1,
+        }
+    }
+
+    fn report(&self) -> ArmReport {
+        let produced = self.verified + self.verification_failures;
+        let ratio = |n: u64, d: u64| (d > 0).then(|| n as f64 / d as f64);
+        ArmReport {
+            mean_proving_ms: ratio(self.total_proving_ms, self.proofs),
+            mean_proof_bytes: ratio(self.total_proof_bytes, produced),
+            verification_rate: ratio(self.verified, produced),
+            metrics: self.clone(),
+        }
+    }
+}
+
+/// One arm's counters with their derived means
+#[derive(Serialize)]
+pub struct ArmReport {
+    #[serde(flatten)]
+    pub metrics: ArmMetrics,
+    pub mean_proving_ms: Option<f64>,
+    pub mean_proof_bytes: Option<f64>,
+    /// Share of produced proofs that verify with the primary backend
+    pub verification_rate: Option<f64>,
+}
+
+/// An experiment with the metrics gathered since it started
+#[derive(Serialize)]
+pub struct ExperimentReport {
+    #[serde(flatten)]
+    pub experiment: Experiment,
+    pub control: ArmReport,
+    pub alternate: ArmReport,
+    /// Alternate-arm requests proven again on the primary backend
+    pub fallbacks: u64,
+}
+
+#[derive(Default)]
+struct ExperimentMetrics {
+    control: ArmMetrics,
+    alternate: ArmMetrics,
+    fallbacks: u64,
+}
+
+/// Persistent set of running experiments and their in-memory metrics
+pub struct Experiments {
+    path: PathBuf,
+    experiments: Mutex<BTreeMap<String, Experiment>>,
+    metrics: Mutex<HashMap<String, ExperimentMetrics>>,
+}
+
+impl Experiments {
+    /// Open (or create) the experiment store in the data directory
+    pub fn open(data_dir: &std::path::Path) -> Result<Self> {
+        let path = data_dir.join("experiments.json");
+        let experiments = load_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            path,
+            experiments: Mutex::new(experiments),
+            metrics: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<ExperimentReport> {
+        let experiments = self.experiments.lock().unwrap();
+        let metrics = self.metrics.lock().unwrap();
+        experiments
+            .values()
+            .map(|experiment| {
+                let empty = ExperimentMetrics::default();
+                let m = metrics.get(&experiment.model_id).unwrap_or(&empty);
+                ExperimentReport {
+                    experiment: experiment.clone(),
+                    control: m.control.report(),
+                    alternate: m.alternate.report(),
+                    fallbacks: m.fallbacks,
+                }
+            })
+            .collect()
+    }
+
+    /// Start (or restart, with fresh metrics) an experiment on a model
+    pub fn set(&self, model_id: &str, fraction: f64) -> Result<Experiment> {
+        let experiment = Experiment {
+            model_id: model_id.to_string(),
+            fraction,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        };
+        let mut experiments = self.experiments.lock().unwrap();
+        experiments.insert(model_id.to_string(), experiment.clone());
+        save_json(&self.path, &*experiments)?;
+        self.metrics.lock().unwrap().remove(model_id);
+        Ok(experiment)
+    }
+
+    /// Stop the experiment on a model
+    pub fn remove(&self, model_id: &str) -> Result<Option<Experiment>> {
+        let mut experiments = self.experiments.lock().unwrap();
+        let removed = experiments.remove(model_id);
+        save_json(&self.path, &*experiments)?;
+        self.metrics.lock().unwrap().remove(model_id);
+        Ok(removed)
+    }
+
+    /// Every running experiment, without metrics
+    pub fn snapshot(&self) -> Vec<Experiment> {
+        self.experiments.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Start the experiments in `restored`, with fresh metrics
+    pub fn restore(&self, restored: Vec<Experiment>) -> Result<()> {
+        let mut experiments = self.experiments.lock().unwrap();
+        let mut metrics = self.metrics.lock().unwrap();
+        for experiment in restored {
+            metrics.remove(&experiment.model_id);
+            experiments.insert(experiment.model_id.clone(), experiment);
+        }
+        save_json(&self.path, &*experiments)
+    }
+
+    /// Draw the arm a proof of `model_id` goes to, if it is under experiment
+    fn draw(&self, model_id: &str) -> Option<Arm> {
+        let fraction = self.experiments.lock().unwrap().get(model_id)?.fraction;
+        Some(Self::arm_for(rand::thread_rng().gen::<f64>(), fraction))
+    }
+
+    fn arm_for(roll: f64, fraction: f64) -> Arm {
+        if roll < fraction {
+            Arm::Alternate
+        } else {
+            Arm::Control
+        }
+    }
+
+    /// Record a proof attempt: its proving time and, if it produced a
+    /// proof, the proof's size and whether it verified
+    fn record(&self, model_id: &str, arm: Arm, proving_ms: u64, outcome: Option<(usize, bool)>) {
+        let mut metrics = self.metrics.lock().unwrap();
+        let m = metrics.entry(model_id.to_string()).or_default();
+        match arm {
+            Arm::Control => m.control.record(proving_ms, outcome),
+            Arm::Alternate => m.alternate.record(proving_ms, outcome),
+        }
+    }
+
+    fn record_fallback(&self, model_id: &str) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.entry(model_id.to_string()).or_default().fallbacks += 1;
+    }
+}
+
+/// Prove `request`, routing it through an experiment if its model has one
+pub async fn prove(
+    state: &AppState,
+    prover: &JoltAtlasProver,
+    request: &ProveRequest,
+) -> Result<ProofResult> {
+    let arm = match state.experiments.draw(&request.model_id) {
+        Some(arm) if prover.has_alternate_backend() => arm,
+        _ => return prover.generate_proof(request).await,
+    };
+
+    let start = Instant::now();
+    let result = prover
+        .generate_proof_on(request, arm == Arm::Alternate)
+        .await;
+    let proving_ms = start.elapsed().as_millis() as u64;
+    let verified = match &result {
+        Ok(proof) => prover
+            .verify_embedded(&proof.proof, request.challenge.as_deref())
+            .await
+            .is_ok_and(|public_inputs| public_inputs.is_some()),
+        Err(_) => false,
+    };
+    let outcome = result
+        .as_ref()
+        .ok()
+        .map(|proof| (proof.proof.len(), verified));
+    state
+        .experiments
+        .record(&request.model_id, arm, proving_ms, outcome);
+
+    match (arm, result) {
+        (Arm::Alternate, Ok(proof)) if verified => Ok(proof),
+        (Arm::Alternate, result) => {
+            tracing::warn!(
+                "Alternate backend {} for model {}; proving on the primary backend",
+                match result {
+                    Ok(_) => "produced a proof that does not verify".to_string(),
+                    Err(e) => format!("failed ({})", e),
+                },
+                request.model_id
+            );
+            state.experiments.record_fallback(&request.model_id);
+            prover.generate_proof(request).await
+        }
+        (Arm::Control, result) => result,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetExperimentRequest {
+    pub fraction: f64,
+}
+
+/// Running experiments and how their arms compare
+pub async fn list_experiments(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<ExperimentReport>> {
+    Json(state.experiments.list())
+}
+
+/// Route a fraction of a model's proofs through the alternate backend
+pub async fn set_experiment(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+    Json(request): Json<SetExperimentRequest>,
+) -> Result<Json<Experiment>, ApiError> {
+    if !(0.0..=1.0).contains(&request.fraction) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_FRACTION",
+            "fraction must be between 0 and 1",
+        ));
+    }
+    {
+        let prover = state.prover.read().await;
+        if !prover.has_alternate_backend() {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "NO_ALTERNATE_BACKEND",
+                "Set AB_BACKEND to run backend experiments",
+            ));
+        }
+        if prover.get_model(&model_id).is_none() {
+            return Err(api_error(
+                StatusCode::NOT_FOUND,
+                "MODEL_NOT_FOUND",
+                format!("Model {} not found", model_id),
+            ));
+        }
+    }
+
+    let experiment = state
+        .experiments
+        .set(&model_id, request.fraction)
+        .map_err(experiment_update_failed)?;
+    tracing::info!(
+        "Routing {:.1}% of proofs of {} to the alternate backend",
+        request.fraction * 100.0,
+        model_id
+    );
+    Ok(Json(experiment))
+}
+
+/// Stop an experiment
+pub async fn delete_experiment(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(model_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .experiments
+        .remove(&model_id)
+        .map_err(experiment_update_failed)?
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "EXPERIMENT_NOT_FOUND",
+                format!("No experiment on model {}", model_id),
+            )
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn experiment_update_failed(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "EXPERIMENT_UPDATE_FAILED",
+        e.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_experiment_metrics_compare_arms() {
+        assert_eq!(Experiments::arm_for(0.04, 0.05), Arm::Alternate);
+        assert_eq!(Experiments::arm_for(0.05, 0.05), Arm::Control);
+        assert_eq!(Experiments::arm_for(0.0, 0.0), Arm::Control);
+
+        let dir = tempfile::tempdir().unwrap();
+        let experiments = Experiments::open(dir.path()).unwrap();
+        experiments.set("m", 0.5).unwrap();
+        experiments.record("m", Arm::Control, 100, Some((1000, true)));
+        experiments.record("m", Arm::Control, 300, Some((3000, true)));
+        experiments.record("m", Arm::Alternate, 50, Some((500, false)));
+        experiments.record("m", Arm::Alternate, 10, None);
+        experiments.record_fallback("m");
+
+        let report = &experiments.list()[0];
+        assert_eq!(report.control.mean_proving_ms, Some(200.0));
+        assert_eq!(report.control.mean_proof_bytes, Some(2000.0));
+        assert_eq!(report.control.verification_rate, Some(1.0));
+        assert_eq!(report.alternate.metrics.failures, 1);
+        assert_eq!(report.alternate.verification_rate, Some(0.0));
+        assert_eq!(report.fallbacks, 1);
+
+        let reopened = Experiments::open(dir.path()).unwrap();
+        assert_eq!(reopened.list()[0].control.metrics.proofs, 0);
+        assert!(reopened.remove("m").unwrap().is_some());
+    }
+}
@@ -0,0 +1,200 @@
+//! Runtime feature flags
+//!
+//! A few behaviors can be switched without a restart through
+//! `PUT /admin/flags/:flag`:
+//!
+//! - `accept_mock_proofs`: the inverse of strict mode;
+//! - `allow_model_registration`: refuse `POST /models` when off;
+//! - `gpu`: prove on the GPU, for backends that support it;
+//! - `pause_intake`: refuse new proving work with 503 and leave queued jobs
+//!   queued, while proofs already running finish.
+//!
+//! Flags start from the service configuration, every change is appended to
+//! the registry audit log, and the current values are reported by `/health`.
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::audit::AuditAction;
+use crate::auth::AdminAuth;
+use crate::config::ServiceConfig;
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// A runtime-switchable behavior
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    AcceptMockProofs,
+    AllowModelRegistration,
+    Gpu,
+    PauseIntake,
+}
+
+/// Current value of every flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct FlagValues {
+    pub accept_mock_proofs: bool,
+    pub allow_model_registration: bool,
+    pub gpu: bool,
+    pub pause_intake: bool,
+}
+
+impl FlagValues {
+    /// Flags as configured at startup
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            accept_mock_proofs: !config.strict_mode,
+            allow_model_registration: true,
+            gpu: config.gpu,
+            pause_intake: false,
+        }
+    }
+
+    fn get_mut(&mut self, flag: Flag) -> &mut bool {
+        match flag {
+            Flag::AcceptMockProofs => &mut self.accept_mock_proofs,
+            Flag::AllowModelRegistration => &mut self.allow_model_registration,
+            Flag::Gpu => &mut self.gpu,
+            Flag::PauseIntake => &mut self.pause_intake,
+        }
+    }
+}
+
+/// The service's feature flags
+pub struct FeatureFlags {
+    values: Mutex<FlagValues>,
+}
+
+impl FeatureFlags {
+    pub fn new(values: FlagValues) -> Self {
+        Self {
+            values: Mutex::new(values),
+        }
+    }
+
+    pub fn get(&self) -> FlagValues {
+        *self.values.lock().unwrap()
+    }
+
+    /// Set `flag`, returning its previous value
+    pub fn set(&self, flag: Flag, enabled: bool) -> bool {
+        std::mem::replace(self.values.lock().unwrap().get_mut(flag), enabled)
+    }
+
+    /// Refuse new proving work while intake is paused
+    pub fn check_intake(&self) -> Result<(), ApiError> {
+        if self.get().pause_intake {
+            return Err(api_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "INTAKE_PAUSED",
+                "Proving intake is paused; retry later",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse model registration while it is switched off
+    pub fn check_registration(&self) -> Result<(), ApiError> {
+        if !self.get().allow_model_registration {
+            return Err(api_error(
+                StatusCode::FORBIDDEN,
+                "REGISTRATION_DISABLED",
+                "Model registration is currently disabled",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetFlagRequest {
+    pub enabled: bool,
+}
+
+/// Current feature flags
+pub async fn get_flags(_admin: AdminAuth, State(state): State<Arc<AppState>>) -> Json<FlagValues> {
+    Json(state.flags.get())
+}
+
+/// Switch a feature flag
+pub async fn set_flag(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(flag): Path<String>,
+    Json(request): Json<SetFlagRequest>,
+) -> Result<Json<FlagValues>, ApiError> {
+    let flag: Flag =
+        serde_json::from_value(serde_json::Value::String(flag.clone())).map_err(|_| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "FLAG_NOT_FOUND",
+                format!("No feature flag {}", flag),
+            )
+        })?;
+
+    state
+        .audit
+        .append(
+            AuditAction::SetFlag,
+            "",
+            "admin",
+            serde_json::json!({ "flag": flag, "enabled": request.enabled }),
+        )
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "AUDIT_LOG_FAILED",
+                e.to_string(),
+            )
+        })?;
+
+    // Flags the prover reads are applied under its lock
+    match flag {
+        Flag::AcceptMockProofs => {
+            let mut prover = state.prover.write().await;
+            state.flags.set(flag, request.enabled);
+            prover.set_strict(!request.enabled);
+        }
+        Flag::Gpu => {
+            let mut prover = state.prover.write().await;
+            state.flags.set(flag, request.enabled);
+            prover.set_gpu(request.enabled);
+        }
+        Flag::AllowModelRegistration | Flag::PauseIntake => {
+            state.flags.set(flag, request.enabled);
+        }
+    }
+    tracing::info!("Feature flag {:?} set to {}", flag, request.enabled);
+    Ok(Json(state.flags.get()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_switch_and_gate() {
+        let flags = FeatureFlags::new(FlagValues {
+            accept_mock_proofs: false,
+            allow_model_registration: true,
+            gpu: false,
+            pause_intake: false,
+        });
+        assert!(flags.check_intake().is_ok());
+        assert!(flags.check_registration().is_ok());
+
+        assert!(!flags.set(Flag::PauseIntake, true));
+        assert_eq!(flags.check_intake().unwrap_err().1 .0.code, "INTAKE_PAUSED");
+        assert!(flags.set(Flag::AllowModelRegistration, false));
+        assert!(flags.check_registration().is_err());
+        assert_eq!(
+            serde_json::from_str::<Flag>("\"accept_mock_proofs\"").unwrap(),
+            Flag::AcceptMockProofs
+        );
+    }
+}
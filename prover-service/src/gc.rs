@@ -0,0 +1,254 @@
+//! Garbage collection of orphaned artifacts
+//!
+//! A background task (every `GC_INTERVAL_SECS`, disabled when unset) and the
+//! `POST /admin/gc` endpoint sweep files the service wrote but no longer
+//! references:
+//!
+//! - model files in the model directory without a registry entry
+//! - temporary files left behind by interrupted writes in the data directory
+//!
+//! Only files older than `GC_MIN_AGE_SECS` are considered, so artifacts of
+//! in-flight operations are never collected. The in-memory registry starts
+//! out empty after a restart, so model files of any model the audit log
+//! records a registration of are kept as well: only files of registrations
+//! that never made it into the log are orphans. A dry run reports what would
+//! be removed without touching anything.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::audit::AuditLog;
+use crate::AppState;
+
+/// Kind of collected artifact
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Model file with no registry entry
+    OrphanedModel,
+    /// Leftover temporary file from an interrupted write
+    StaleTempFile,
+}
+
+/// A file selected for collection
+#[derive(Clone, Debug, Serialize)]
+pub struct GcItem {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Result of one sweep
+#[derive(Debug, Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub items: Vec<GcItem>,
+    /// Bytes freed (or that would be freed on a dry run)
+    pub reclaimed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// Cumulative collector metrics
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GcStats {
+    pub runs: u64,
+    pub files_removed: u64,
+    pub bytes_reclaimed: u64,
+    pub last_run_at: Option<u64>,
+    pub last_run_reclaimed_bytes: u64,
+}
+
+/// Sweeps orphaned artifacts and keeps metrics on what it reclaimed
+pub struct GarbageCollector {
+    min_age: Duration,
+    stats: Mutex<GcStats>,
+}
+
+impl GarbageCollector {
+    pub fn new(min_age_secs: u64) -> Self {
+        Self {
+            min_age: Duration::from_secs(min_age_secs),
+            stats: Mutex::new(GcStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> GcStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Run one sweep
+    pub async fn run(&self, state: &AppState, dry_run: bool) -> GcReport {
+        // Hold the registry lock so a registration cannot race the sweep
+        let prover = state.prover.read().await;
+        let live: HashSet<String> = prover.model_ids().into_iter().collect();
+        let protected = |id: &str| is_protected(&live, &state.audit, id);
+
+        let mut items = orphaned_models(prover.model_dir(), &protected, self.min_age);
+        items.extend(stale_temp_files(&state.config.data_dir, self.min_age));
+
+        let mut errors = Vec::new();
+        let mut reclaimed_bytes = 0;
+        items.retain(|item| {
+            if !dry_run {
+                if let Err(e) = std::fs::remove_file(&item.path) {
+                    errors.push(format!("{}: {}", item.path.display(), e));
+                    return false;
+                }
+            }
+            reclaimed_bytes += item.bytes;
+            true
+        });
+        drop(prover);
+
+        if !dry_run {
+            let mut stats = self.stats.lock().unwrap();
+            stats.runs += 1;
+            stats.files_removed += items.len() as u64;
+            stats.bytes_reclaimed += reclaimed_bytes;
+            stats.last_run_at = Some(now_secs());
+            stats.last_run_reclaimed_bytes = reclaimed_bytes;
+        }
+        if !items.is_empty() {
+            tracing::info!(
+                "GC {} {} artifacts ({} bytes)",
+                if dry_run { "would remove" } else { "removed" },
+                items.len(),
+                reclaimed_bytes
+            );
+        }
+
+        GcReport {
+            dry_run,
+            items,
+            reclaimed_bytes,
+            errors,
+        }
+    }
+}
+
+/// Background task sweeping every `interval_secs`
+pub async fn run_forever(state: std::sync::Arc<AppState>, interval_secs: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        if !state.cluster.is_leader() {
+            continue;
+        }
+        let report = state.gc.run(&state, false).await;
+        for error in report.errors {
+            tracing::warn!("GC failed to remove {}", error);
+        }
+    }
+}
+
+/// Whether the model file of `model_id` must be kept: the model is
+/// registered now, or was registered before the last restart
+fn is_protected(live: &HashSet<String>, audit: &AuditLog, model_id: &str) -> bool {
+    live.contains(model_id) || audit.is_registered(model_id)
+}
+
+/// `<id>.onnx` files in the model directory whose id is not protected
+fn orphaned_models(
+    model_dir: &Path,
+    protected: &dyn Fn(&str) -> bool,
+    min_age: Duration,
+) -> Vec<GcItem> {
+    old_files(model_dir, min_age)
+        .filter(|(path, _)| {
+            path.extension().is_some_and(|ext| ext == "onnx")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|id| !protected(id))
+        })
+        .map(|(path, bytes)| GcItem {
+            kind: ArtifactKind::OrphanedModel,
+            path,
+            bytes,
+        })
+        .collect()
+}
+
+/// `*.tmp` files in the data directory
+fn stale_temp_files(data_dir: &Path, min_age: Duration) -> Vec<GcItem> {
+    old_files(data_dir, min_age)
+        .filter(|(path, _)| path.extension().is_some_and(|ext| ext == "tmp"))
+        .map(|(path, bytes)| GcItem {
+            kind: ArtifactKind::StaleTempFile,
+            path,
+            bytes,
+        })
+        .collect()
+}
+
+/// Regular files in `dir` last modified at least `min_age` ago, with sizes
+fn old_files(dir: &Path, min_age: Duration) -> impl Iterator<Item = (PathBuf, u64)> {
+    let now = SystemTime::now();
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(move |entry| {
+            let metadata = entry.metadata().ok()?;
+            let age = now.duration_since(metadata.modified().ok()?).ok()?;
+            (metadata.is_file() && age >= min_age).then(|| (entry.path(), metadata.len()))
+        })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditAction;
+
+    #[test]
+    fn test_finds_only_unreferenced_models_and_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("live.onnx"), b"model").unwrap();
+        std::fs::write(dir.path().join("orphan.onnx"), b"orphan").unwrap();
+        std::fs::write(dir.path().join("api_keys.json"), b"{}").unwrap();
+        std::fs::write(dir.path().join("api_keys.json.tmp"), b"{").unwrap();
+
+        let protected = |id: &str| id == "live";
+        let orphans = orphaned_models(dir.path(), &protected, Duration::ZERO);
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].path.ends_with("orphan.onnx"));
+        assert_eq!(orphans[0].bytes, 6);
+
+        let temps = stale_temp_files(dir.path(), Duration::ZERO);
+        assert_eq!(temps.len(), 1);
+        assert_eq!(temps[0].kind, ArtifactKind::StaleTempFile);
+
+        // Fresh files are left alone
+        assert!(orphaned_models(dir.path(), &protected, Duration::from_secs(3600)).is_empty());
+    }
+
+    #[test]
+    fn test_models_registered_before_a_restart_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        AuditLog::open(dir.path())
+            .unwrap()
+            .append(
+                AuditAction::Register,
+                "before-restart",
+                "key",
+                serde_json::json!({ "name": "m" }),
+            )
+            .unwrap();
+
+        // After a restart the in-memory registry is empty
+        let audit = AuditLog::open(dir.path()).unwrap();
+        let live = HashSet::new();
+        assert!(is_protected(&live, &audit, "before-restart"));
+        assert!(!is_protected(&live, &audit, "never-logged"));
+    }
+}
@@ -0,0 +1,232 @@
+//! ONNX inference engines
+//!
+//! Native inference runs on one of two engines, chosen per deployment with
+//! `INFERENCE_ENGINE`:
+//!
+//! - `ort` (feature `ort`): ONNX Runtime, which needs its shared library at
+//!   run time
+//! - `tract` (feature `tract`): the pure-Rust `tract` engine, so the service
+//!   can be linked statically and shipped without ONNX Runtime
+//!
+//! It defaults to whichever engine the binary was built with, preferring
+//! `ort`. An engine that was not compiled in falls back to mock inference,
+//! which strict mode refuses.
+//!
+//! Inference yields every output tensor of the model by name, in the order
+//! the model declares them. Their concatenation is what gets proven, so a
+//! model with a single output is proven exactly as before.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::onnx::{self, Dimension};
+use crate::prover::InvalidInput;
+
+/// Engine native inference runs on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InferenceEngine {
+    Ort,
+    Tract,
+}
+
+impl InferenceEngine {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ort => "ort",
+            Self::Tract => "tract",
+        }
+    }
+
+    /// Whether the engine was compiled into this binary
+    pub fn available(self) -> bool {
+        match self {
+            Self::Ort => cfg!(feature = "ort"),
+            Self::Tract => cfg!(feature = "tract"),
+        }
+    }
+
+    /// Runtime the engine is built against, as pinned in `Cargo.toml`, or
+    /// `None` if it was not compiled in
+    pub fn runtime(self) -> Option<&'static str> {
+        match self {
+            Self::Ort if self.available() => Some("ort-1.16"),
+            Self::Tract if self.available() => Some("tract-onnx-0.21"),
+            _ => None,
+        }
+    }
+}
+
+impl Default for InferenceEngine {
+    fn default() -> Self {
+        if cfg!(feature = "tract") && !cfg!(feature = "ort") {
+            Self::Tract
+        } else {
+            Self::Ort
+        }
+    }
+}
+
+impl FromStr for InferenceEngine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ort" => Ok(Self::Ort),
+            "tract" => Ok(Self::Tract),
+            other => Err(anyhow!("Unknown inference engine {}", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for InferenceEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One named output tensor of an inference
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NamedOutput {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+/// Name of a model's first declared output, or `output` if it has none
+pub fn first_output_name(model_bytes: &[u8]) -> String {
+    onnx::interface(model_bytes)
+        .ok()
+        .and_then(|(_, outputs)| outputs.into_iter().next())
+        .map(|output| output.name)
+        .unwrap_or_else(|| "output".to_string())
+}
+
+/// `values` as the only output of the model in `model_bytes`, for engines
+/// that produce a single tensor
+pub fn single_output(model_bytes: &[u8], values: Vec<f32>) -> Vec<NamedOutput> {
+    vec![NamedOutput {
+        name: first_output_name(model_bytes),
+        values,
+    }]
+}
+
+/// All output values, concatenated in the model's output order
+pub fn flatten(outputs: &[NamedOutput]) -> Vec<f32> {
+    outputs
+        .iter()
+        .flat_map(|output| output.values.iter().copied())
+        .collect()
+}
+
+/// Check `inputs` fit the first input the model in `model_bytes` declares,
+/// for engines that feed them to the model as a single batch row
+///
+/// Only a shape that is fixed apart from its leading (batch) dimension is
+/// checked; a model whose interface cannot be read is left to the engine.
+pub fn check_inputs(model_bytes: &[u8], inputs: &[f32]) -> Result<(), InvalidInput> {
+    let Some(input) = onnx::interface(model_bytes)
+        .ok()
+        .and_then(|(inputs, _)| inputs.into_iter().next())
+    else {
+        return Ok(());
+    };
+    let row = match input.shape.len() {
+        0 => return Ok(()),
+        1 => &input.shape[..],
+        _ => &input.shape[1..],
+    };
+    let features = row.iter().try_fold(1usize, |n, dim| match dim {
+        Dimension::Fixed(size) if *size > 0 => Some(n * *size as usize),
+        _ => None,
+    });
+    match features {
+        Some(features) if features != inputs.len() => Err(InvalidInput(format!(
+            "model input {} takes {} features, got {}",
+            input.name,
+            features,
+            inputs.len()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Run inference with `tract`, feeding `inputs` as a single batch row
+#[cfg(feature = "tract")]
+pub fn run_tract(model_path: &std::path::Path, inputs: &[f32]) -> Result<Vec<NamedOutput>> {
+    use tract_onnx::prelude::*;
+
+    let model = tract_onnx::onnx()
+        .model_for_path(model_path)?
+        .with_input_fact(0, f32::fact([1, inputs.len()]).into())?
+        .into_optimized()?
+        .into_runnable()?;
+    let names: Vec<String> = model
+        .model()
+        .output_outlets()?
+        .iter()
+        .map(|outlet| model.model().node(outlet.node).name.clone())
+        .collect();
+    let input: Tensor =
+        tract_ndarray::Array2::from_shape_vec((1, inputs.len()), inputs.to_vec())?.into();
+    let outputs = model.run(tvec!(input.into()))?;
+    names
+        .into_iter()
+        .zip(outputs.iter())
+        .map(|(name, tensor)| {
+            Ok(NamedOutput {
+                name,
+                values: tensor.to_array_view::<f32>()?.iter().copied().collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_flatten_in_model_order() {
+        let outputs = vec![
+            NamedOutput {
+                name: "probabilities".to_string(),
+                values: vec![0.25, 0.75],
+            },
+            NamedOutput {
+                name: "label".to_string(),
+                values: vec![1.0],
+            },
+        ];
+        assert_eq!(flatten(&outputs), vec![0.25, 0.75, 1.0]);
+        assert_eq!(single_output(b"not onnx", vec![0.5])[0].name, "output");
+    }
+
+    #[test]
+    fn test_inputs_checked_against_declared_shape() {
+        let model = include_bytes!("../models/demo-mlp.onnx");
+        assert!(check_inputs(model, &[0.0; 8]).is_ok());
+        let error = check_inputs(model, &[0.0; 3]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: model input x takes 8 features, got 3"
+        );
+        assert!(check_inputs(b"not onnx", &[0.0; 3]).is_ok());
+    }
+
+    #[test]
+    fn test_engine_names_round_trip() {
+        for engine in [InferenceEngine::Ort, InferenceEngine::Tract] {
+            assert_eq!(engine.as_str().parse::<InferenceEngine>().unwrap(), engine);
+            assert_eq!(
+                serde_json::to_value(engine).unwrap(),
+                serde_json::json!(engine.as_str())
+            );
+        }
+        assert!("onnxruntime".parse::<InferenceEngine>().is_err());
+        assert_eq!(
+            InferenceEngine::default().available(),
+            cfg!(any(feature = "ort", feature = "tract"))
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Message-queue ingestion of prove requests
+//!
+//! As an alternative to `POST /prove` for batch pipelines, the service can
+//! consume prove requests from a NATS subject or Kafka topic and publish the
+//! results to an output subject/topic. `INGEST_URL` selects the broker:
+//!
+//! - `nats://host:4222` (build with `--features nats`): queue-subscribes to
+//!   `INGEST_TOPIC` in group `INGEST_GROUP`. Messages sent with a reply
+//!   subject (NATS request/reply) are answered there, others on
+//!   `INGEST_RESULT_TOPIC`.
+//! - `kafka://broker1:9092,broker2:9092` (build with `--features kafka`):
+//!   consumes `INGEST_TOPIC` as consumer group `INGEST_GROUP` and produces
+//!   results to `INGEST_RESULT_TOPIC`, keyed like the request. Offsets are
+//!   committed once the result is published (at-least-once).
+//!
+//! A message is a `ProveRequest` with an optional correlation `id` and
+//! `tenant`; the result carries the same `id` and either the signed
+//! `response` or an `error`. Requests run through the same pipeline as HTTP
+//! ones, attributed to the `ingest` key.
+
+// Without a broker feature only configuration is compiled in
+#![cfg_attr(
+    not(any(feature = "nats", feature = "kafka")),
+    allow(dead_code, unused_variables)
+)]
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::{Caller, DEFAULT_TENANT};
+use crate::config::ServiceConfig;
+use crate::types::{ErrorResponse, ProveRequest, ProveResponse};
+use crate::AppState;
+
+/// Key id queued requests are attributed to
+pub const INGEST_KEY_ID: &str = "ingest";
+
+/// A prove request received from the queue
+#[derive(Deserialize)]
+struct IngestMessage {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    tenant: Option<String>,
+    #[serde(flatten)]
+    request: ProveRequest,
+}
+
+/// Result published for each message
+#[derive(Serialize)]
+struct IngestResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<ProveResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorResponse>,
+}
+
+/// Broker to consume from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Broker {
+    Nats(String),
+    Kafka(String),
+}
+
+impl Broker {
+    fn parse(url: &str) -> Result<Self> {
+        if url.starts_with("nats://") || url.starts_with("tls://") {
+            Ok(Broker::Nats(url.to_string()))
+        } else if let Some(brokers) = url.strip_prefix("kafka://") {
+            Ok(Broker::Kafka(brokers.to_string()))
+        } else {
+            Err(anyhow!("INGEST_URL must start with nats:// or kafka://"))
+        }
+    }
+}
+
+/// Queue consumer settings
+pub struct Ingest {
+    broker: Broker,
+    topic: String,
+    result_topic: String,
+    group: String,
+}
+
+impl Ingest {
+    /// Ingestion settings, or `None` when `INGEST_URL` is unset
+    pub fn from_config(config: &ServiceConfig) -> Result<Option<Self>> {
+        let Some(url) = &config.ingest_url else {
+            return Ok(None);
+        };
+        let broker = Broker::parse(url)?;
+        match &broker {
+            Broker::Nats(_) if !cfg!(feature = "nats") => {
+                return Err(anyhow!("NATS ingestion requires the `nats` feature"))
+            }
+            Broker::Kafka(_) if !cfg!(feature = "kafka") => {
+                return Err(anyhow!("Kafka ingestion requires the `kafka` feature"))
+            }
+            _ => {}
+        }
+        Ok(Some(Self {
+            broker,
+            topic: config.ingest_topic.clone(),
+            result_topic: config.ingest_result_topic.clone(),
+            group: config.ingest_group.clone(),
+        }))
+    }
+
+    /// Consume until the connection fails
+    pub async fn run(self, state: Arc<AppState>) {
+        let (Broker::Nats(endpoint) | Broker::Kafka(endpoint)) = &self.broker;
+        tracing::info!(
+            "Consuming prove requests from {} on {} (group {}), results to {}",
+            self.topic,
+            endpoint,
+            self.group,
+            self.result_topic
+        );
+        let result: Result<()> = match &self.broker {
+            #[cfg(feature = "nats")]
+            Broker::Nats(url) => self.run_nats(url, state).await,
+            #[cfg(feature = "kafka")]
+            Broker::Kafka(brokers) => self.run_kafka(brokers, state).await,
+            #[allow(unreachable_patterns)]
+            _ => Err(anyhow!("Broker support not compiled in")),
+        };
+        if let Err(e) = result {
+            tracing::error!("Queue ingestion stopped: {}", e);
+        }
+    }
+
+    #[cfg(feature = "nats")]
+    async fn run_nats(&self, url: &str, state: Arc<AppState>) -> Result<()> {
+        use futures::StreamExt;
+
+        let client = async_nats::connect(url).await?;
+        let mut subscriber = client
+            .queue_subscribe(self.topic.clone(), self.group.clone())
+            .await?;
+        while let Some(message) = subscriber.next().await {
+            let client = client.clone();
+            let state = state.clone();
+            let subject = message
+                .reply
+                .clone()
+                .unwrap_or_else(|| self.result_topic.clone().into());
+            // Core NATS has no redelivery, so requests are handled concurrently
+            tokio::spawn(async move {
+                let result = handle_message(&state, &message.payload).await;
+                if let Err(e) = client.publish(subject, result.into()).await {
+                    tracing::warn!("Failed to publish prove result: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "kafka")]
+    async fn run_kafka(&self, brokers: &str, state: Arc<AppState>) -> Result<()> {
+        use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+        use rdkafka::producer::{FutureProducer, FutureRecord};
+        use rdkafka::{ClientConfig, Message};
+
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", &self.group)
+            .set("enable.auto.commit", "false")
+            .create()?;
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .create()?;
+        consumer.subscribe(&[&self.topic])?;
+
+        loop {
+            let message = consumer.recv().await?;
+            let result = handle_message(&state, message.payload().unwrap_or_default()).await;
+            let mut record = FutureRecord::to(&self.result_topic).payload(&result);
+            if let Some(key) = message.key() {
+                record = record.key(key);
+            }
+            producer
+                .send(record, std::time::Duration::from_secs(30))
+                .await
+                .map_err(|(e, _)| e)?;
+            consumer.commit_message(&message, CommitMode::Async)?;
+        }
+    }
+}
+
+/// Prove one queued request, returning the serialized result
+async fn handle_message(state: &AppState, payload: &[u8]) -> Vec<u8> {
+    let result = match serde_json::from_slice::<IngestMessage>(payload) {
+        Ok(message) => {
+            let caller = Caller {
+                key_id: INGEST_KEY_ID.to_string(),
+                tenant: message.tenant.unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+                scopes: None,
+            };
+            match crate::execute_proof(state, &caller, message.request).await {
+                Ok((_, response)) => IngestResult {
+                    id: message.id,
+                    response: Some(response),
+                    error: None,
+                },
+                Err((_, axum::Json(error))) => IngestResult {
+                    id: message.id,
+                    response: None,
+                    error: Some(error),
+                },
+            }
+        }
+        Err(e) => IngestResult {
+            id: None,
+            response: None,
+            error: Some(ErrorResponse {
+                error: format!("Invalid prove request: {}", e),
+                code: "INVALID_REQUEST".to_string(),
+            }),
+        },
+    };
+    serde_json::to_vec(&result).expect("prove results serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broker_urls() {
+        assert_eq!(
+            Broker::parse("nats://localhost:4222").unwrap(),
+            Broker::Nats("nats://localhost:4222".to_string())
+        );
+        assert_eq!(
+            Broker::parse("kafka://a:9092,b:9092").unwrap(),
+            Broker::Kafka("a:9092,b:9092".to_string())
+        );
+        assert!(Broker::parse("amqp://localhost").is_err());
+    }
+
+    #[test]
+    fn test_messages_carry_correlation_id() {
+        let message: IngestMessage = serde_json::from_str(
+            r#"{"id":"job-7","tenant":"desk-a","model_id":"m","inputs":[1.0,2.0]}"#,
+        )
+        .unwrap();
+        assert_eq!(message.id.as_deref(), Some("job-7"));
+        assert_eq!(message.request.model_id, "m");
+        assert_eq!(message.request.inputs, vec![1.0, 2.0]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Proof inspection from the command line, and plain-language explanations
+//!
+//! `trustless-agentkit-prover --inspect [--explain] <file>` decodes a proof
+//! offline, like `POST /proofs/inspect`, and prints the inspection as JSON.
+//! The file (`-` for stdin) holds the proof, base64 or decoded, or a whole
+//! prove response or stored proof carrying it in `proof`. The exit status is
+//! 1 if the proof is not well-formed.
+//!
+//! With `--explain` (`?explain=true` on the endpoint) the output is a
+//! summary for reviewers who are not cryptographers: which model was run on
+//! which inputs, when, what it produced and which backend proved it. It
+//! restates what the proof claims and does not verify it. The endpoint names
+//! the model when it is registered with the service; offline, only its
+//! commitment is known.
+
+use chrono::{TimeZone, Utc};
+use std::io::Read;
+
+use crate::verification::{inspect_proof, ProofFormat, ProofInspection};
+
+/// Command-line flag starting the binary as a proof inspector
+pub const INSPECT_ARG: &str = "--inspect";
+
+/// Plain-language summary of an inspected proof, naming `model_id` if the
+/// proof is known to commit to that registered model
+pub fn explain(inspection: &ProofInspection, model_id: Option<&str>) -> String {
+    let mut sentences = Vec::new();
+    let claims = match (&inspection.public_inputs, inspection.format) {
+        (Some(claims), ProofFormat::JoltAtlas | ProofFormat::Legacy) => claims,
+        _ => {
+            return format!(
+                "This is not a proof this service can decode: {}.",
+                inspection.problems.join("; ")
+            )
+        }
+    };
+    if !inspection.well_formed {
+        sentences.push(format!(
+            "This proof is not well-formed ({}), so the claims below may not hold.",
+            inspection.problems.join("; ")
+        ));
+    }
+
+    let field = |name: &str| claims[name].as_str().unwrap_or("(missing)").to_string();
+    let model = match model_id {
+        Some(id) => format!("Model {} (commitment {})", id, field("model_commitment")),
+        None => format!("A model with commitment {}", field("model_commitment")),
+    };
+    let time = claims["timestamp"]
+        .as_i64()
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "an unknown time".to_string());
+    sentences.push(format!(
+        "{} was run on inputs hashing to {} at {}, producing outputs {} (hashing to {}).",
+        model,
+        field("input_hash"),
+        time,
+        claims["outputs"],
+        field("output_hash")
+    ));
+
+    let backend = match inspection.backend {
+        Some("mock") => "the mock backend",
+        _ => "the Jolt Atlas backend",
+    };
+    sentences.push(format!(
+        "It was proven with {} ({}, proof format version {}); hashes use {}.",
+        backend,
+        inspection.prover_id.as_deref().unwrap_or("unknown prover"),
+        inspection.version.unwrap_or_default(),
+        claims["commitment_scheme"].as_str().unwrap_or("sha256-v1")
+    ));
+    if let Some(circuit) = claims["circuit_hash"].as_str() {
+        sentences.push(format!(
+            "It is bound to circuit {}, and only verifies against that circuit version.",
+            circuit
+        ));
+    }
+    if let Some(plugin) = claims["preprocessor"].as_str() {
+        sentences.push(format!(
+            "The inputs were produced by preprocessing plugin {}.",
+            plugin
+        ));
+    }
+    if claims.get("quantization").is_some() {
+        sentences.push("The model was proven in fixed-point arithmetic.".to_string());
+    }
+    if claims.get("expected_output").is_some() {
+        sentences
+            .push("The proof also attests that the output matched an expected value.".to_string());
+    }
+    if claims.get("predicate").is_some() {
+        sentences.push(
+            "The output shown is the result of a check on the model output, not the model output itself."
+                .to_string(),
+        );
+    }
+    if let Some(challenge) = claims["challenge"].as_str() {
+        sentences.push(format!(
+            "It includes the verifier challenge {}, so it was generated after that challenge was issued.",
+            challenge
+        ));
+    }
+    if inspection.backend == Some("mock") {
+        sentences.push(
+            "Mock proofs are for development only and prove nothing cryptographically.".to_string(),
+        );
+    }
+    sentences.push(
+        "This summary restates what the proof claims; verify the proof (POST /verify/proof) before relying on it."
+            .to_string(),
+    );
+    sentences.join(" ")
+}
+
+/// The proof in `input`: the text itself, or the `proof` field of a JSON
+/// document carrying one
+fn extract_proof(input: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(input)
+        .ok()
+        .and_then(|document| document["proof"].as_str().map(String::from))
+        .unwrap_or_else(|| input.trim().to_string())
+}
+
+/// Entry point for `--inspect`, returning the process exit code
+pub fn inspect_main(args: impl Iterator<Item = String>) -> i32 {
+    let (flags, paths): (Vec<String>, Vec<String>) = args.partition(|arg| arg == "--explain");
+    let [path] = paths.as_slice() else {
+        eprintln!("usage: {} [--explain] <file | ->", INSPECT_ARG);
+        return 2;
+    };
+    let mut input = String::new();
+    let read = match path.as_str() {
+        "-" => std::io::stdin().read_to_string(&mut input).map(|_| ()),
+        path => std::fs::read_to_string(path).map(|text| input = text),
+    };
+    if let Err(e) = read {
+        eprintln!("Failed to read {}: {}", path, e);
+        return 2;
+    }
+
+    let inspection = inspect_proof(&extract_proof(&input));
+    if !flags.is_empty() {
+        println!("{}", explain(&inspection, None));
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&inspection).expect("inspections serialize")
+        );
+    }
+    if inspection.well_formed {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::{create_prover, serialize_proof, ModelCommitments};
+
+    #[test]
+    fn test_explain_restates_the_claims() {
+        let model = ModelCommitments::compute(b"model");
+        let mut proof = create_prover()
+            .unwrap()
+            .prove(&model, &[1.0, 2.0], &[0.5], None)
+            .unwrap();
+        proof.timestamp = 1_700_000_000;
+        let encoded = serialize_proof(&proof).unwrap();
+        let response = serde_json::json!({ "success": true, "proof": encoded }).to_string();
+        assert_eq!(extract_proof(&response), encoded);
+        assert_eq!(extract_proof(&format!(" {}\n", encoded)), encoded);
+
+        let inspection = inspect_proof(&encoded);
+        let text = explain(&inspection, Some("credit-risk"));
+        assert!(text.starts_with(&format!(
+            "Model credit-risk (commitment {}) was run on inputs hashing to {} at 2023-11-14 22:13:20 UTC, producing outputs [0.5]",
+            model.sha256,
+            model.input_hash(&[1.0, 2.0])
+        )));
+        assert!(text.contains("the mock backend (jolt-atlas-mock-v1, proof format version 1)"));
+        assert!(text.contains("hashes use sha256-v2"));
+        assert!(explain(&inspection, None).starts_with("A model with commitment"));
+
+        let text = explain(&inspect_proof("garbage"), None);
+        assert!(text.starts_with("This is not a proof this service can decode"));
+    }
+}
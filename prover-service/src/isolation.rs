@@ -0,0 +1,404 @@
+//! Proving job isolation
+//!
+//! `PROVE_ISOLATION` selects how a proving job (inference plus proof
+//! generation) is separated from the HTTP service:
+//!
+//! - `thread` (default): the job runs on its own blocking thread, off the
+//!   async runtime's workers; a panic in the backend or ONNX runtime fails
+//!   that job only. If the request is abandoned (it timed out or its client
+//!   went away), the job is cancelled: it stops before its next stage, and a
+//!   proving binary it is running is killed.
+//! - `process`: the job runs in a worker child process (this binary started
+//!   with `--prove-worker`), so aborts and out-of-memory kills are contained
+//!   too. The supervisor samples the worker's RSS and CPU time and kills it
+//!   when it exceeds `PROVE_JOB_MAX_RSS_MB` (or the job memory budget, see
+//!   `memory`) or `PROVE_JOB_MAX_CPU_SECS`. Workers report the CPU time the
+//!   job consumed, which is returned with the proof and metered. The worker
+//!   is killed if the request is abandoned.
+//! - `none`: the job runs inline on the request task.
+//!
+//! The worker reads a JSON `ProveJob` on stdin and writes a JSON
+//! `WorkerOutcome` on stdout.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+use crate::jolt_atlas::{create_backend, create_prover, ZkmlProver};
+use crate::memory::ResourceExhausted;
+use crate::prover::{InvalidInput, OutputMismatch, ProveJob, StrictModeViolation};
+use crate::types::ProofResult;
+
+/// Command-line flag starting the binary as a proving worker
+pub const WORKER_ARG: &str = "--prove-worker";
+
+/// How often the supervisor samples worker resource usage
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Kernel clock ticks per second used in `/proc/<pid>/stat` (USER_HZ)
+const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+/// A proving job died without reporting an outcome: it panicked, or its
+/// worker process crashed
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct WorkerCrashed(pub String);
+
+/// A worker process used up its CPU-time limit and was killed
+#[derive(Debug, thiserror::Error)]
+#[error("Proving job killed: CPU time over the {limit_secs}s limit")]
+pub struct CpuLimitExceeded {
+    pub limit_secs: u64,
+}
+
+/// A proving job was cancelled because its request was abandoned
+#[derive(Debug, thiserror::Error)]
+#[error("Proving job cancelled: its request was abandoned")]
+pub struct JobCancelled;
+
+/// Cancellation flag shared with a running proving job
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with `JobCancelled` once cancelled
+    pub fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(JobCancelled.into()),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Cancels a job when the future waiting for it is dropped
+struct CancelOnDrop(Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Where proving jobs run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IsolationMode {
+    None,
+    #[default]
+    Thread,
+    Process,
+}
+
+impl FromStr for IsolationMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "thread" => Ok(Self::Thread),
+            "process" => Ok(Self::Process),
+            other => Err(anyhow!("Unknown isolation mode {}", other)),
+        }
+    }
+}
+
+/// Resource limits for a worker process
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JobLimits {
+    pub max_rss_bytes: Option<u64>,
+    pub max_cpu_secs: Option<u64>,
+}
+
+/// Runs proving jobs in the configured isolation mode
+#[derive(Clone, Debug, Default)]
+pub struct Isolation {
+    pub mode: IsolationMode,
+    pub limits: JobLimits,
+}
+
+/// Result a worker reports on stdout
+#[derive(Serialize, Deserialize)]
+enum WorkerOutcome {
+    Proof(Box<ProofResult>),
+    Failed {
+        error: String,
+        /// Tolerance of a failed expected-output check
+        output_mismatch: Option<f32>,
+        /// Set when strict mode refused the job
+        #[serde(default)]
+        strict_mode: bool,
+        /// Set when the job went over its memory budget
+        #[serde(default)]
+        resource_exhausted: bool,
+        /// Set when the request's inputs did not fit the model
+        #[serde(default)]
+        invalid_input: bool,
+    },
+}
+
+impl From<Result<ProofResult>> for WorkerOutcome {
+    fn from(result: Result<ProofResult>) -> Self {
+        match result {
+            Ok(proof) => WorkerOutcome::Proof(Box::new(proof)),
+            Err(e) => WorkerOutcome::Failed {
+                output_mismatch: e.downcast_ref::<OutputMismatch>().map(|m| m.tolerance),
+                strict_mode: e.is::<StrictModeViolation>(),
+                resource_exhausted: e.is::<ResourceExhausted>(),
+                invalid_input: e.is::<InvalidInput>(),
+                error: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<WorkerOutcome> for Result<ProofResult> {
+    fn from(outcome: WorkerOutcome) -> Self {
+        match outcome {
+            WorkerOutcome::Proof(proof) => Ok(*proof),
+            WorkerOutcome::Failed {
+                output_mismatch: Some(tolerance),
+                ..
+            } => Err(OutputMismatch { tolerance }.into()),
+            WorkerOutcome::Failed {
+                error,
+                strict_mode: true,
+                ..
+            } => Err(
+                StrictModeViolation(error.trim_start_matches("Strict mode: ").to_string()).into(),
+            ),
+            WorkerOutcome::Failed {
+                error,
+                resource_exhausted: true,
+                ..
+            } => Err(ResourceExhausted(
+                error
+                    .trim_start_matches("Memory budget exceeded: ")
+                    .to_string(),
+            )
+            .into()),
+            WorkerOutcome::Failed {
+                error,
+                invalid_input: true,
+                ..
+            } => Err(InvalidInput(error.trim_start_matches("Invalid input: ").to_string()).into()),
+            WorkerOutcome::Failed { error, .. } => Err(anyhow!(error)),
+        }
+    }
+}
+
+impl Isolation {
+    /// Run `job`, in process with `prover` unless isolated in a worker
+    pub async fn run(
+        &self,
+        mut job: ProveJob,
+        prover: Arc<RwLock<Box<dyn ZkmlProver>>>,
+    ) -> Result<ProofResult> {
+        match self.mode {
+            IsolationMode::None => job.run(&**prover.read().await).await,
+            IsolationMode::Thread => {
+                // A blocking thread cannot be aborted, so the job is told to
+                // stop when this future is dropped
+                let cancellation = Cancellation::default();
+                let _cancel_on_drop = CancelOnDrop(cancellation.clone());
+                job.cancellation = Some(cancellation);
+                // Proving is synchronous, so it gets a blocking thread rather
+                // than holding up a runtime worker
+                let runtime = tokio::runtime::Handle::current();
+                let task = tokio::task::spawn_blocking(move || {
+                    runtime.block_on(async { job.run(&**prover.read().await).await })
+                });
+                match task.await {
+                    Ok(result) => result,
+                    Err(e) if e.is_panic() => {
+                        Err(WorkerCrashed("Proving job panicked".to_string()).into())
+                    }
+                    Err(e) => Err(anyhow!("Proving job failed: {}", e)),
+                }
+            }
+            IsolationMode::Process => self.run_worker(&job).await,
+        }
+    }
+
+    /// Run `job` in a child process under the supervisor's limits
+    async fn run_worker(&self, job: &ProveJob) -> Result<ProofResult> {
+        let mut child = tokio::process::Command::new(std::env::current_exe()?)
+            .arg(WORKER_ARG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().expect("worker stdin is piped");
+        stdin.write_all(&serde_json::to_vec(job)?).await?;
+        drop(stdin);
+        let mut stdout = child.stdout.take().expect("worker stdout is piped");
+        let output = tokio::spawn(async move {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).await.map(|_| output)
+        });
+
+        let mut violation = None;
+        let status = loop {
+            tokio::select! {
+                status = child.wait() => break status?,
+                _ = tokio::time::sleep(SAMPLE_INTERVAL) => {
+                    if violation.is_none() {
+                        violation = child.id().and_then(|pid| self.check_limits(pid));
+                        if violation.is_some() {
+                            child.start_kill()?;
+                        }
+                    }
+                }
+            }
+        };
+
+        if let Some(violation) = violation {
+            return Err(violation);
+        }
+        let output = output.await??;
+        match serde_json::from_slice::<WorkerOutcome>(&output) {
+            Ok(outcome) => outcome.into(),
+            Err(_) => Err(WorkerCrashed(format!("Proving worker crashed ({})", status)).into()),
+        }
+    }
+
+    /// Error for the limit a worker exceeds, if any
+    fn check_limits(&self, pid: u32) -> Option<anyhow::Error> {
+        let usage = ProcessUsage::sample(pid)?;
+        if let Some(max) = self.limits.max_rss_bytes {
+            if usage.rss_bytes > max {
+                return Some(
+                    ResourceExhausted(format!(
+                        "proving job killed at {} MB RSS, over the {} MB limit",
+                        usage.rss_bytes >> 20,
+                        max >> 20
+                    ))
+                    .into(),
+                );
+            }
+        }
+        if let Some(max) = self.limits.max_cpu_secs {
+            if usage.cpu_ms > max.saturating_mul(1000) {
+                return Some(CpuLimitExceeded { limit_secs: max }.into());
+            }
+        }
+        None
+    }
+}
+
+/// Resource usage of a process, from procfs
+#[derive(Debug, PartialEq)]
+pub struct ProcessUsage {
+    pub rss_bytes: u64,
+    /// User and system CPU time
+    pub cpu_ms: u64,
+}
+
+impl ProcessUsage {
+    pub fn sample(pid: u32) -> Option<Self> {
+        let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        Self::parse(&status, &stat)
+    }
+
+    fn parse(status: &str, stat: &str) -> Option<Self> {
+        let rss_kb: u64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()?;
+        // Fields after the parenthesised command name start at `state`
+        // (field 3); utime and stime are fields 14 and 15
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let ticks: u64 =
+            fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+        Some(Self {
+            rss_bytes: rss_kb * 1024,
+            cpu_ms: ticks * 1000 / CLOCK_TICKS_PER_SEC,
+        })
+    }
+}
+
+/// Entry point of a worker process: prove the job on stdin, report on stdout
+pub async fn worker_main() -> i32 {
+    let mut input = Vec::new();
+    let outcome: WorkerOutcome = async {
+        tokio::io::stdin().read_to_end(&mut input).await?;
+        let job: ProveJob = serde_json::from_slice(&input)?;
+        let prover = match &job.backend {
+            Some(spec) => create_backend(spec)?,
+            None => create_prover()?,
+        };
+        let mut result = job.run(prover.as_ref()).await?;
+        result.cpu_time_ms = ProcessUsage::sample(std::process::id()).map(|usage| usage.cpu_ms);
+        Ok(result)
+    }
+    .await
+    .into();
+
+    let encoded = serde_json::to_vec(&outcome).expect("worker outcomes serialize");
+    let mut stdout = tokio::io::stdout();
+    match stdout.write_all(&encoded).await.and(stdout.flush().await) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_usage_from_procfs() {
+        let status = "Name:\tprover\nVmPeak:\t  900000 kB\nVmRSS:\t  524288 kB\nThreads:\t4\n";
+        let stat = "4242 (jolt (worker)) R 1 4242 4242 0 -1 4194304 100 0 0 0 \
+                    1250 250 0 0 20 0 4 0 100 1000000 131072";
+        assert_eq!(
+            ProcessUsage::parse(status, stat),
+            Some(ProcessUsage {
+                rss_bytes: 512 << 20,
+                cpu_ms: 15_000,
+            })
+        );
+        assert_eq!(ProcessUsage::parse("Name:\tprover\n", stat), None);
+        assert_eq!(
+            "process".parse::<IsolationMode>().unwrap(),
+            IsolationMode::Process
+        );
+    }
+
+    #[test]
+    fn test_worker_outcome_keeps_strict_mode_refusals() {
+        let refused: Result<ProofResult> =
+            Err(StrictModeViolation("refusing mock inference".to_string()).into());
+        let outcome: WorkerOutcome =
+            serde_json::from_slice(&serde_json::to_vec(&WorkerOutcome::from(refused)).unwrap())
+                .unwrap();
+        let error = Result::<ProofResult>::from(outcome).err().unwrap();
+        assert!(error.is::<StrictModeViolation>());
+        assert_eq!(error.to_string(), "Strict mode: refusing mock inference");
+
+        let invalid: Result<ProofResult> = Err(InvalidInput("no inputs".to_string()).into());
+        let error = Result::<ProofResult>::from(WorkerOutcome::from(invalid))
+            .err()
+            .unwrap();
+        assert!(error.is::<InvalidInput>());
+        assert_eq!(error.to_string(), "Invalid input: no inputs");
+    }
+}
@@ -0,0 +1,1020 @@
+//! Durable proving jobs
+//!
+//! `POST /jobs` queues a prove request and returns immediately; the result is
+//! polled from `GET /jobs/:id`. Clients that cannot use webhooks can
+//! long-poll with `GET /jobs/:id?wait=30s`, which holds the request until the
+//! job finishes or the wait (at most 60s) runs out. Job state lives in a
+//! SQLite database (`jobs.sqlite3` in the data directory), so a restart does
+//! not lose work: jobs that were running when the service stopped are put
+//! back in the queue on startup and run again (at-least-once). A job is
+//! abandoned after `JOB_MAX_ATTEMPTS` starts, so a request that crashes the
+//! service cannot keep it in a restart loop. The model registry does not
+//! survive a restart, so a job carried over from before one whose model is
+//! no longer loaded fails with `MODEL_NOT_LOADED` instead of running.
+//!
+//! Result storage is idempotent: only the first completion of a job is
+//! recorded, and a client that retries a submission with the same
+//! `Idempotency-Key` header gets the original job back. With a job TTL
+//! configured, finished jobs are removed by the retention task once expired.
+//!
+//! Jobs that fail for a transient reason (a backend whose circuit breaker is
+//! open, a crashed proving worker, a storage or upstream blip) are queued
+//! again after an exponential backoff of `JOB_RETRY_BASE_SECS` doubling up
+//! to `JOB_RETRY_MAX_SECS`, within the same `JOB_MAX_ATTEMPTS`; other
+//! failures are final. Each failed attempt is kept in the job's `history`.
+//!
+//! Jobs keep the request id of the call that submitted them (see
+//! `request_id`) and are run in a span carrying it.
+
+use anyhow::Result;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tracing::Instrument;
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::request_id::RequestId;
+use crate::types::{api_error, ApiError, ErrorResponse, ProveRequest, ProveResponse};
+use crate::AppState;
+
+/// Header carrying a client-chosen key that deduplicates submissions
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// How often idle workers look for jobs they were not notified about
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest a `GET /jobs/:id?wait=` long-poll is held
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS jobs (
+        id TEXT PRIMARY KEY,
+        key_id TEXT NOT NULL,
+        tenant TEXT NOT NULL,
+        idempotency_key TEXT,
+        request TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL,
+        started_at INTEGER,
+        finished_at INTEGER,
+        response TEXT,
+        error TEXT,
+        error_code TEXT,
+        request_id TEXT,
+        retry_at INTEGER,
+        history TEXT,
+        UNIQUE (key_id, idempotency_key)
+    );
+    CREATE INDEX IF NOT EXISTS jobs_queued ON jobs (status, created_at);
+";
+
+/// Error codes of failures a later attempt may not hit
+const TRANSIENT_ERRORS: &[&str] = &[
+    "BACKEND_UNAVAILABLE",
+    "PROVER_CRASHED",
+    "TRANSPARENCY_LOG_FAILED",
+    "MARKET_DATA_UNAVAILABLE",
+    "MODEL_RATE_LIMITED",
+];
+
+/// Whether a job that failed with `code` is worth running again
+pub fn is_transient(code: &str) -> bool {
+    TRANSIENT_ERRORS.contains(&code)
+}
+
+/// Backoff before running a job again after its `attempt`th start failed
+pub fn retry_delay(attempt: u32, base: Duration, max: Duration) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(max)
+}
+
+/// A failed run of a job
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobAttempt {
+    pub attempt: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    pub finished_at: u64,
+    pub error: String,
+    pub code: String,
+    /// Whether the job was queued again after it
+    pub retried: bool,
+}
+
+/// Lifecycle of a job
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> rusqlite::Result<Self> {
+        match s {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "succeeded" => Ok(JobStatus::Succeeded),
+            "failed" => Ok(JobStatus::Failed),
+            other => Err(rusqlite::Error::InvalidColumnType(
+                0,
+                format!("status {}", other),
+                rusqlite::types::Type::Text,
+            )),
+        }
+    }
+}
+
+/// A job as reported to its owner
+#[derive(Serialize)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    pub model_id: String,
+    /// Times the job has been started, including runs cut short by a restart
+    pub attempts: u32,
+    pub created_at: u64,
+    /// Request id of the call that submitted the job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<ProveResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+    /// When a job queued again after a transient failure next runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<u64>,
+    /// Failed attempts, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<JobAttempt>,
+    #[serde(skip)]
+    key_id: String,
+}
+
+/// A job claimed by a worker
+pub struct ClaimedJob {
+    pub id: String,
+    pub caller: Caller,
+    pub request: ProveRequest,
+    pub request_id: RequestId,
+    /// Starts of the job so far, this one included
+    pub attempt: u32,
+    /// Whether the job was submitted before the service last started
+    pub carried_over: bool,
+}
+
+/// SQLite-backed job queue
+pub struct JobStore {
+    conn: Mutex<Connection>,
+    max_attempts: u32,
+    /// Jobs pending when the store was opened, not yet claimed
+    carried_over: Mutex<HashSet<String>>,
+    /// Wakes idle workers when a job is submitted
+    submitted: Notify,
+    /// Wakes long-polling clients when a job finishes
+    finished: Notify,
+}
+
+impl JobStore {
+    /// Open the job database, re-queueing jobs interrupted by a restart
+    pub fn open(data_dir: &std::path::Path, max_attempts: u32) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let conn = Connection::open(data_dir.join("jobs.sqlite3"))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        // Databases created before jobs recorded request ids and retries
+        for (column, kind) in [
+            ("request_id", "TEXT"),
+            ("retry_at", "INTEGER"),
+            ("history", "TEXT"),
+        ] {
+            let exists: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('jobs') WHERE name = ?1",
+                [column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                conn.execute(
+                    &format!("ALTER TABLE jobs ADD COLUMN {} {}", column, kind),
+                    [],
+                )?;
+            }
+        }
+        let requeued = conn.execute(
+            "UPDATE jobs SET status = 'queued', started_at = NULL WHERE status = 'running'",
+            [],
+        )?;
+        if requeued > 0 {
+            tracing::warn!("Re-queued {} jobs interrupted by a restart", requeued);
+        }
+        let carried_over = conn
+            .prepare("SELECT id FROM jobs WHERE status = 'queued'")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            max_attempts: max_attempts.max(1),
+            carried_over: Mutex::new(carried_over),
+            submitted: Notify::new(),
+            finished: Notify::new(),
+        })
+    }
+
+    /// Queue `request` for `caller`, or return the job already submitted
+    /// under the same idempotency key
+    pub fn submit(
+        &self,
+        caller: &Caller,
+        request_id: &RequestId,
+        idempotency_key: Option<&str>,
+        request: &ProveRequest,
+    ) -> Result<Job> {
+        let mut jobs = self.submit_all(caller, request_id, [(idempotency_key, request)])?;
+        Ok(jobs.remove(0))
+    }
+
+    /// Queue several requests in one transaction
+    pub fn submit_all<'a>(
+        &self,
+        caller: &Caller,
+        request_id: &RequestId,
+        requests: impl IntoIterator<Item = (Option<&'a str>, &'a ProveRequest)>,
+    ) -> Result<Vec<Job>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut jobs = Vec::new();
+        let mut inserted = 0;
+        for (idempotency_key, request) in requests {
+            let id = format!("job_{}", uuid::Uuid::new_v4().simple());
+            let added = tx.execute(
+                "INSERT INTO jobs (id, key_id, tenant, idempotency_key, request, status, created_at,
+                    request_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'queued', ?6, ?7)
+                 ON CONFLICT (key_id, idempotency_key) DO NOTHING",
+                params![
+                    id,
+                    caller.key_id,
+                    caller.tenant,
+                    idempotency_key,
+                    serde_json::to_string(request)?,
+                    now_secs(),
+                    request_id.as_str(),
+                ],
+            )?;
+            let job = match (added, idempotency_key) {
+                (0, Some(key)) => tx.query_row(
+                    &format!("{} WHERE key_id = ?1 AND idempotency_key = ?2", SELECT_JOB),
+                    params![caller.key_id, key],
+                    job_from_row,
+                )?,
+                _ => tx.query_row(
+                    &format!("{} WHERE id = ?1", SELECT_JOB),
+                    [&id],
+                    job_from_row,
+                )?,
+            };
+            inserted += added;
+            jobs.push(job);
+        }
+        tx.commit()?;
+        drop(conn);
+
+        if inserted > 0 {
+            self.submitted.notify_one();
+        }
+        if inserted > 1 {
+            self.submitted.notify_waiters();
+        }
+        Ok(jobs)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Job>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(&format!("{} WHERE id = ?1", SELECT_JOB), [id], job_from_row)
+            .optional()?)
+    }
+
+    /// Mark the oldest queued job due to run running and return it
+    ///
+    /// Jobs that have used up their attempts are failed instead.
+    pub fn claim(&self) -> Result<Option<ClaimedJob>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let claimed = loop {
+            let next = tx
+                .query_row(
+                    "SELECT id, key_id, tenant, request, attempts, request_id FROM jobs
+                     WHERE status = 'queued' AND (retry_at IS NULL OR retry_at <= ?1)
+                     ORDER BY created_at, rowid LIMIT 1",
+                    [now_secs()],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                            row.get::<_, u32>(4)?,
+                            row.get::<_, Option<String>>(5)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((id, key_id, tenant, request, attempts, request_id)) = next else {
+                break None;
+            };
+
+            let carried_over = self.carried_over.lock().unwrap().remove(&id);
+            if attempts >= self.max_attempts {
+                tracing::warn!("Job {} abandoned after {} attempts", id, attempts);
+                finish(
+                    &tx,
+                    &id,
+                    JobStatus::Failed,
+                    None,
+                    Some((
+                        format!("Job was interrupted {} times", attempts),
+                        "JOB_ATTEMPTS_EXHAUSTED",
+                    )),
+                )?;
+                continue;
+            }
+            tx.execute(
+                "UPDATE jobs SET status = 'running', attempts = attempts + 1, started_at = ?2,
+                    retry_at = NULL
+                 WHERE id = ?1",
+                params![id, now_secs()],
+            )?;
+            break Some(ClaimedJob {
+                carried_over,
+                id,
+                caller: Caller {
+                    key_id,
+                    tenant,
+                    scopes: None,
+                },
+                request: serde_json::from_str(&request)?,
+                request_id: request_id
+                    .map(RequestId)
+                    .unwrap_or_else(RequestId::generate),
+                attempt: attempts + 1,
+            });
+        };
+        tx.commit()?;
+        self.finished.notify_waiters();
+        Ok(claimed)
+    }
+
+    /// Record the outcome of a running job
+    ///
+    /// Returns `false` if the job already has a result, in which case the
+    /// stored result is left untouched.
+    pub fn complete(
+        &self,
+        id: &str,
+        result: Result<&ProveResponse, &ErrorResponse>,
+    ) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let updated = match result {
+            Ok(response) => finish(
+                &conn,
+                id,
+                JobStatus::Succeeded,
+                Some(serde_json::to_string(response)?),
+                None,
+            )?,
+            Err(error) => {
+                record_attempt(&conn, id, error, false)?;
+                finish(
+                    &conn,
+                    id,
+                    JobStatus::Failed,
+                    None,
+                    Some((error.error.clone(), &error.code)),
+                )?
+            }
+        };
+        drop(conn);
+        if updated {
+            self.finished.notify_waiters();
+        }
+        Ok(updated)
+    }
+
+    /// Fail a running job carried over from before a restart whose model is
+    /// no longer loaded
+    pub fn fail_model_not_loaded(&self, id: &str, model_id: &str) -> Result<bool> {
+        let error = ErrorResponse {
+            error: format!(
+                "Model {} was registered before the service restarted and is no longer \
+                 loaded; register it again and resubmit the job",
+                model_id
+            ),
+            code: "MODEL_NOT_LOADED".to_string(),
+        };
+        self.complete(id, Err(&error))
+    }
+
+    /// Queue a running job that failed with `error` again, to run after
+    /// `delay`
+    ///
+    /// Returns `false` if the job is no longer running or has used up its
+    /// attempts, in which case it is left for `complete`.
+    pub fn retry(&self, id: &str, error: &ErrorResponse, delay: Duration) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let attempts: Option<u32> = conn
+            .query_row(
+                "SELECT attempts FROM jobs WHERE id = ?1 AND status = 'running'",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if attempts.is_none_or(|attempts| attempts >= self.max_attempts) {
+            return Ok(false);
+        }
+        record_attempt(&conn, id, error, true)?;
+        conn.execute(
+            "UPDATE jobs SET status = 'queued', started_at = NULL, retry_at = ?2 WHERE id = ?1",
+            params![id, now_secs() + delay.as_secs()],
+        )?;
+        Ok(true)
+    }
+
+    /// Remove finished jobs older than their tenant's TTL, returning how many
+    pub fn expire(&self, ttl: &dyn Fn(&str) -> Option<Duration>) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let tenants = conn
+            .prepare("SELECT DISTINCT tenant FROM jobs WHERE finished_at IS NOT NULL")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let now = now_secs();
+        let mut removed = 0;
+        for tenant in tenants {
+            if let Some(ttl) = ttl(&tenant) {
+                removed += conn.execute(
+                    "DELETE FROM jobs WHERE tenant = ?1 AND finished_at IS NOT NULL
+                     AND finished_at <= ?2",
+                    params![tenant, now.saturating_sub(ttl.as_secs())],
+                )?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Number of queued and running jobs
+    pub fn depth(&self) -> Result<(u64, u64)> {
+        let conn = self.conn.lock().unwrap();
+        let count = |status: JobStatus| -> Result<u64> {
+            Ok(conn.query_row(
+                "SELECT COUNT(*) FROM jobs WHERE status = ?1",
+                [status.as_str()],
+                |row| row.get(0),
+            )?)
+        };
+        Ok((count(JobStatus::Queued)?, count(JobStatus::Running)?))
+    }
+
+    /// Number of job records and the size of the database in bytes
+    pub fn usage(&self) -> Result<(u64, u64)> {
+        let conn = self.conn.lock().unwrap();
+        let jobs = conn.query_row("SELECT COUNT(*) FROM jobs", [], |row| row.get(0))?;
+        let bytes = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok((jobs, bytes))
+    }
+
+    /// Wait up to `timeout` for a job to finish, then return its state
+    pub async fn wait(&self, id: &str, timeout: Duration) -> Result<Option<Job>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // Register before reading, so a completion in between is not missed
+            let finished = self.finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+
+            let job = self.get(id)?;
+            let pending = job
+                .as_ref()
+                .is_some_and(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running));
+            if !pending || tokio::time::timeout_at(deadline, finished).await.is_err() {
+                return Ok(job);
+            }
+        }
+    }
+
+    /// Wait until a job may have been submitted
+    async fn wait_for_work(&self) {
+        let _ = tokio::time::timeout(POLL_INTERVAL, self.submitted.notified()).await;
+    }
+}
+
+const SELECT_JOB: &str = "SELECT id, key_id, request, status, attempts, created_at, started_at,
+    finished_at, response, error, error_code, request_id, retry_at, history FROM jobs";
+
+fn job_from_row(row: &Row) -> rusqlite::Result<Job> {
+    let request: String = row.get(2)?;
+    let response: Option<String> = row.get(8)?;
+    let error: Option<String> = row.get(9)?;
+    let error_code: Option<String> = row.get(10)?;
+    let history: Option<String> = row.get(13)?;
+    Ok(Job {
+        id: row.get(0)?,
+        key_id: row.get(1)?,
+        model_id: serde_json::from_str::<ProveRequest>(&request)
+            .map(|r| r.model_id)
+            .unwrap_or_default(),
+        status: JobStatus::parse(&row.get::<_, String>(3)?)?,
+        attempts: row.get(4)?,
+        created_at: row.get(5)?,
+        request_id: row.get(11)?,
+        started_at: row.get(6)?,
+        finished_at: row.get(7)?,
+        response: response.and_then(|r| serde_json::from_str(&r).ok()),
+        error: error.map(|error| ErrorResponse {
+            error,
+            code: error_code.unwrap_or_default(),
+        }),
+        retry_at: row.get(12)?,
+        history: history
+            .and_then(|h| serde_json::from_str(&h).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// Append the failed current attempt of a running job to its history
+fn record_attempt(conn: &Connection, id: &str, error: &ErrorResponse, retried: bool) -> Result<()> {
+    let current = conn
+        .query_row(
+            "SELECT attempts, started_at, history FROM jobs
+             WHERE id = ?1 AND status IN ('queued', 'running')",
+            [id],
+            |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, Option<u64>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((attempt, started_at, history)) = current else {
+        return Ok(());
+    };
+    let mut history: Vec<JobAttempt> = history
+        .and_then(|h| serde_json::from_str(&h).ok())
+        .unwrap_or_default();
+    history.push(JobAttempt {
+        attempt,
+        started_at,
+        finished_at: now_secs(),
+        error: error.error.clone(),
+        code: error.code.clone(),
+        retried,
+    });
+    conn.execute(
+        "UPDATE jobs SET history = ?2 WHERE id = ?1",
+        params![id, serde_json::to_string(&history)?],
+    )?;
+    Ok(())
+}
+
+/// Store a final result if the job does not have one yet
+fn finish(
+    conn: &Connection,
+    id: &str,
+    status: JobStatus,
+    response: Option<String>,
+    error: Option<(String, &str)>,
+) -> Result<bool> {
+    let (error, error_code) = error.unzip();
+    let updated = conn.execute(
+        "UPDATE jobs SET status = ?2, finished_at = ?3, response = ?4, error = ?5, error_code = ?6
+         WHERE id = ?1 AND status IN ('queued', 'running')",
+        params![id, status.as_str(), now_secs(), response, error, error_code],
+    )?;
+    Ok(updated > 0)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Background task: one worker per proving slot claims and runs queued jobs
+pub async fn run_forever(state: Arc<AppState>) {
+    for _ in 0..state.config.prove_concurrency.max(1) {
+        tokio::spawn(work(state.clone()));
+    }
+}
+
+async fn work(state: Arc<AppState>) {
+    loop {
+        // Queued jobs stay queued while intake is paused
+        if state.flags.get().pause_intake {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        }
+        let job = match state.jobs.claim() {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                state.jobs.wait_for_work().await;
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Failed to claim a job: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if job.carried_over && !model_loaded(&state, &job.request.model_id).await {
+            tracing::warn!(
+                "Job {} references model {}, which is not loaded since the restart",
+                job.id,
+                job.request.model_id
+            );
+            if let Err(e) = state
+                .jobs
+                .fail_model_not_loaded(&job.id, &job.request.model_id)
+            {
+                tracing::error!("Failed to store result of job {}: {}", job.id, e);
+            }
+            continue;
+        }
+
+        let span = job.request_id.span();
+        let result = crate::execute_proof(&state, &job.caller, job.request)
+            .instrument(span)
+            .await;
+        let outcome = match &result {
+            Ok((_, response)) => Ok(response),
+            Err((_, Json(error))) => Err(error),
+        };
+        if let Err(error) = outcome {
+            if is_transient(&error.code) {
+                let delay = retry_delay(
+                    job.attempt,
+                    state.config.job_retry_base,
+                    state.config.job_retry_max,
+                );
+                match state.jobs.retry(&job.id, error, delay) {
+                    Ok(true) => {
+                        tracing::warn!(
+                            "Job {} attempt {} failed ({}); retrying in {}s",
+                            job.id,
+                            job.attempt,
+                            error.code,
+                            delay.as_secs()
+                        );
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Failed to requeue job {}: {}", job.id, e),
+                }
+            }
+        }
+        match state.jobs.complete(&job.id, outcome) {
+            Ok(true) => tracing::info!("Job {} finished", job.id),
+            Ok(false) => tracing::info!("Job {} already had a result", job.id),
+            Err(e) => tracing::error!("Failed to store result of job {}: {}", job.id, e),
+        }
+    }
+}
+
+/// Whether `reference` (a model id or alias) resolves to a loaded model
+async fn model_loaded(state: &AppState, reference: &str) -> bool {
+    let prover = state.prover.read().await;
+    state
+        .aliases
+        .resolve(reference, &prover)
+        .is_some_and(|id| prover.get_model(&id).is_some())
+}
+
+/// Routes mounted under `/jobs`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(submit_job))
+        .route("/:id", get(get_job))
+}
+
+/// Queue a prove request
+async fn submit_job(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    request_id: RequestId,
+    headers: HeaderMap,
+    Json(request): Json<ProveRequest>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    caller.require(Scope::Prove)?;
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let job = state
+        .jobs
+        .submit(&caller, &request_id, idempotency_key, &request)
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "JOB_SUBMIT_FAILED",
+                e.to_string(),
+            )
+        })?;
+    tracing::info!("Queued job {} for {}", job.id, caller.key_id);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[derive(Deserialize)]
+struct JobQuery {
+    /// How long to wait for the job to finish, e.g. `30s` or `500ms`
+    wait: Option<String>,
+}
+
+/// Status and, once finished, result of a job owned by the caller
+async fn get_job(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    Query(query): Query<JobQuery>,
+) -> Result<Json<Job>, ApiError> {
+    let job = match query.wait.as_deref().map(parse_wait).transpose()? {
+        // Only the owner may hold a request open on a job
+        Some(wait) if owned_by(&state, &id, &caller) => state.jobs.wait(&id, wait).await,
+        _ => state.jobs.get(&id),
+    };
+    job.ok()
+        .flatten()
+        .filter(|job| job.key_id == caller.key_id)
+        .map(Json)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "JOB_NOT_FOUND",
+                format!("Job {} not found", id),
+            )
+        })
+}
+
+fn owned_by(state: &AppState, id: &str, caller: &Caller) -> bool {
+    matches!(state.jobs.get(id), Ok(Some(job)) if job.key_id == caller.key_id)
+}
+
+/// Parse a duration such as `30s`, `500ms`, `2m` or `30` (seconds)
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (amount, unit) = duration.split_at(split);
+    amount.parse::<u64>().ok().and_then(|n| match unit {
+        "" | "s" => Some(Duration::from_secs(n)),
+        "ms" => Some(Duration::from_millis(n)),
+        "m" => n.checked_mul(60).map(Duration::from_secs),
+        _ => None,
+    })
+}
+
+/// Parse a wait (see [`parse_duration`]), capped at [`MAX_WAIT`]
+fn parse_wait(wait: &str) -> Result<Duration, ApiError> {
+    let wait = wait.trim();
+    parse_duration(wait)
+        .map(|d| d.min(MAX_WAIT))
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_WAIT",
+                format!("Invalid wait {:?}; use e.g. 30s or 500ms", wait),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller() -> Caller {
+        Caller {
+            key_id: "key_a".to_string(),
+            tenant: "desk".to_string(),
+            scopes: None,
+        }
+    }
+
+    #[test]
+    fn test_restart_requeues_running_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let request = ProveRequest {
+            model_id: "m".to_string(),
+            inputs: vec![1.0],
+            ..Default::default()
+        };
+        let store = JobStore::open(dir.path(), 2).unwrap();
+        let request_id = RequestId("req_submit".to_string());
+        let job = store
+            .submit(&caller(), &request_id, Some("once"), &request)
+            .unwrap();
+        assert_eq!(job.request_id.as_deref(), Some("req_submit"));
+        let retried = RequestId::generate();
+        assert_eq!(
+            store
+                .submit(&caller(), &retried, Some("once"), &request)
+                .unwrap()
+                .id,
+            job.id
+        );
+        let claimed = store.claim().unwrap().unwrap();
+        assert_eq!(
+            (claimed.id, claimed.request_id),
+            (job.id.clone(), request_id)
+        );
+        assert!(store.claim().unwrap().is_none());
+        drop(store);
+
+        // The interrupted job runs again after a restart, then is abandoned
+        let store = JobStore::open(dir.path(), 2).unwrap();
+        assert_eq!(
+            store.get(&job.id).unwrap().unwrap().status,
+            JobStatus::Queued
+        );
+        assert_eq!(store.claim().unwrap().unwrap().id, job.id);
+        drop(store);
+        let store = JobStore::open(dir.path(), 2).unwrap();
+        assert!(store.claim().unwrap().is_none());
+        let job = store.get(&job.id).unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.unwrap().code, "JOB_ATTEMPTS_EXHAUSTED");
+
+        // Only the first result of a job is kept
+        let error = ErrorResponse {
+            error: "late".to_string(),
+            code: "PROOF_GENERATION_FAILED".to_string(),
+        };
+        assert!(!store.complete(&job.id, Err(&error)).unwrap());
+    }
+
+    #[test]
+    fn test_jobs_carried_over_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path(), 3).unwrap();
+        let submit = |store: &JobStore, model_id: &str| {
+            let request = ProveRequest {
+                model_id: model_id.to_string(),
+                ..Default::default()
+            };
+            store
+                .submit(&caller(), &RequestId::generate(), None, &request)
+                .unwrap()
+                .id
+        };
+        let lost = submit(&store, "lost");
+        let kept = submit(&store, "kept");
+        drop(store);
+
+        let store = JobStore::open(dir.path(), 3).unwrap();
+        let fresh = submit(&store, "fresh");
+
+        // A carried-over job whose model is gone fails with a clear error
+        let claimed = store.claim().unwrap().unwrap();
+        assert_eq!(
+            (claimed.id.as_str(), claimed.carried_over),
+            (lost.as_str(), true)
+        );
+        assert!(store.fail_model_not_loaded(&lost, "lost").unwrap());
+        let job = store.get(&lost).unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.unwrap().code, "MODEL_NOT_LOADED");
+
+        // One whose model is loaded runs again
+        let claimed = store.claim().unwrap().unwrap();
+        assert_eq!(
+            (claimed.id.as_str(), claimed.carried_over),
+            (kept.as_str(), true)
+        );
+        assert_eq!(claimed.request.model_id, "kept");
+        assert_eq!(claimed.attempt, 1);
+
+        let claimed = store.claim().unwrap().unwrap();
+        assert_eq!(
+            (claimed.id.as_str(), claimed.carried_over),
+            (fresh.as_str(), false)
+        );
+    }
+
+    #[test]
+    fn test_transient_failures_are_retried_with_backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path(), 3).unwrap();
+        let request = ProveRequest {
+            model_id: "m".to_string(),
+            ..Default::default()
+        };
+        let job = store
+            .submit(&caller(), &RequestId::generate(), None, &request)
+            .unwrap();
+        let crashed = ErrorResponse {
+            error: "Proving worker crashed (signal: 9)".to_string(),
+            code: "PROVER_CRASHED".to_string(),
+        };
+        assert!(is_transient(&crashed.code));
+        assert!(!is_transient("OUTPUT_MISMATCH"));
+
+        // A job waiting out its backoff is not claimed
+        assert_eq!(store.claim().unwrap().unwrap().attempt, 1);
+        assert!(store
+            .retry(&job.id, &crashed, Duration::from_secs(60))
+            .unwrap());
+        let queued = store.get(&job.id).unwrap().unwrap();
+        assert_eq!(queued.status, JobStatus::Queued);
+        assert!(queued.retry_at.is_some());
+        assert!(store.claim().unwrap().is_none());
+
+        let conn = store.conn.lock().unwrap();
+        conn.execute("UPDATE jobs SET retry_at = 0", []).unwrap();
+        drop(conn);
+        assert_eq!(store.claim().unwrap().unwrap().attempt, 2);
+        assert!(store.retry(&job.id, &crashed, Duration::ZERO).unwrap());
+        assert_eq!(store.claim().unwrap().unwrap().attempt, 3);
+
+        // The last attempt's failure is final
+        assert!(!store.retry(&job.id, &crashed, Duration::ZERO).unwrap());
+        assert!(store.complete(&job.id, Err(&crashed)).unwrap());
+        let job = store.get(&job.id).unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(
+            job.history
+                .iter()
+                .map(|a| (a.attempt, a.retried))
+                .collect::<Vec<_>>(),
+            [(1, true), (2, true), (3, false)]
+        );
+
+        let (base, max) = (Duration::from_secs(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(1, base, max), Duration::from_secs(2));
+        assert_eq!(retry_delay(3, base, max), Duration::from_secs(8));
+        assert_eq!(retry_delay(40, base, max), max);
+    }
+
+    #[tokio::test]
+    async fn test_wait_returns_when_job_finishes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(JobStore::open(dir.path(), 2).unwrap());
+        let request = ProveRequest {
+            model_id: "m".to_string(),
+            ..Default::default()
+        };
+        let job = store
+            .submit(&caller(), &RequestId::generate(), None, &request)
+            .unwrap();
+
+        let waited = store.wait(&job.id, Duration::from_millis(10)).await;
+        assert_eq!(waited.unwrap().unwrap().status, JobStatus::Queued);
+
+        let waiter = {
+            let (store, id) = (store.clone(), job.id.clone());
+            tokio::spawn(async move { store.wait(&id, Duration::from_secs(30)).await })
+        };
+        let error = ErrorResponse {
+            error: "bad".to_string(),
+            code: "PROOF_GENERATION_FAILED".to_string(),
+        };
+        tokio::task::yield_now().await;
+        assert!(store.complete(&job.id, Err(&error)).unwrap());
+        let job = waiter.await.unwrap().unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+
+        assert_eq!(parse_wait("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_wait("250ms").unwrap(), Duration::from_millis(250));
+        assert!(parse_wait("1h").is_err());
+        assert_eq!(parse_wait("10m").unwrap(), MAX_WAIT);
+    }
+}
@@ -21,7 +21,15 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::path::Path;
+use sha3::Keccak256;
+
+use crate::checkpoint::Checkpoint;
+use crate::isolation::Cancellation;
+use crate::predicate::{ExpectedOutput, OutputPredicate};
+use crate::quantization::Quantization;
+use crate::types::{CircuitCommitments, CommitmentScheme};
+use crate::verification::ct_eq;
+use crate::witness::WitnessBudget;
 
 /// Proof generated by Jolt Atlas
 #[derive(Clone, Serialize, Deserialize)]
@@ -41,14 +49,143 @@ pub struct JoltAtlasProof {
     /// Hash of output predictions
     pub output_hash: String,
 
+    /// Scheme of the commitment and hashes above (`sha256-v1` for proofs
+    /// that predate the field)
+    #[serde(default)]
+    pub commitment_scheme: CommitmentScheme,
+
     /// The actual inference output
     pub outputs: Vec<f32>,
 
     /// Timestamp of proof generation
     pub timestamp: u64,
 
+    /// The same commitments under the circuit-friendly hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_commitments: Option<CircuitCommitments>,
+
+    /// Expected output check composed with the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_output: Option<ExpectedOutput>,
+
+    /// Verifier-supplied challenge absorbed into the transcript
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+
+    /// Predicate composed with the model; `outputs` is then the predicate bit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<OutputPredicate>,
+
+    /// Fixed-point encoding the model was proven under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+
+    /// Hash of the preprocessing plugin the inputs were produced by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+
+    /// Hash of the circuit the proof was generated for (see `circuit_hash`);
+    /// absent on proofs that predate it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_hash: Option<String>,
+
     /// The SNARK proof data
     pub proof_data: ProofData,
+
+    /// How long the backend spent on the witness and commitments, when it
+    /// reports them; not part of the proof
+    #[serde(skip)]
+    pub backend_timings: Option<BackendTimings>,
+}
+
+/// Stage timings a backend reports for a proof, in milliseconds
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BackendTimings {
+    pub witness_ms: Option<u64>,
+    pub commitment_ms: Option<u64>,
+}
+
+impl JoltAtlasProof {
+    /// Model commitment, input hash and output hash under `scheme`
+    pub fn commitments_for(&self, scheme: CommitmentScheme) -> Option<(&str, &str, &str)> {
+        match scheme {
+            _ if scheme == self.commitment_scheme => {
+                Some((&self.model_commitment, &self.input_hash, &self.output_hash))
+            }
+            _ => self
+                .circuit_commitments
+                .as_ref()
+                .filter(|c| c.scheme == scheme)
+                .map(|c| {
+                    (
+                        c.model_commitment.as_str(),
+                        c.input_hash.as_str(),
+                        c.output_hash.as_str(),
+                    )
+                }),
+        }
+    }
+}
+
+/// Commitments to a model under each supported hash
+#[derive(Clone, Debug)]
+pub struct ModelCommitments {
+    /// SHA-256 commitment (for offchain tooling)
+    pub sha256: String,
+
+    /// Keccak-256 commitment (for circuits and contracts)
+    pub keccak256: String,
+}
+
+/// Models at least this large are hashed under both hashes at once
+pub const PARALLEL_HASH_MIN_BYTES: usize = 1 << 20;
+
+/// Floats converted to bytes per hasher update
+const FLOAT_BLOCK: usize = 4096;
+
+impl ModelCommitments {
+    /// Commit to canonical model bytes under both hashes
+    pub fn compute(model_bytes: &[u8]) -> Self {
+        if model_bytes.len() < PARALLEL_HASH_MIN_BYTES {
+            return Self {
+                sha256: compute_model_commitment(model_bytes),
+                keccak256: compute_model_commitment_keccak(model_bytes),
+            };
+        }
+        std::thread::scope(|scope| {
+            let keccak256 = scope.spawn(|| compute_model_commitment_keccak(model_bytes));
+            let sha256 = compute_model_commitment(model_bytes);
+            Self {
+                sha256,
+                keccak256: keccak256.join().expect("Keccak-256 hashing panicked"),
+            }
+        })
+    }
+
+    /// `sha256-v2` hash of an input to this model
+    pub fn input_hash(&self, inputs: &[f32]) -> String {
+        hash_tensor(&self.sha256, "input", &[inputs.len()], inputs)
+    }
+
+    /// `sha256-v2` hash of an output of this model
+    pub fn output_hash(&self, outputs: &[f32]) -> String {
+        hash_tensor(&self.sha256, "output", &[outputs.len()], outputs)
+    }
+
+    /// `sha256-v2` hash of the output tensor `name` of this model
+    pub fn named_output_hash(&self, name: &str, values: &[f32]) -> String {
+        named_output_hash(&self.sha256, name, values)
+    }
+
+    /// Circuit-friendly commitments for an inference over this model
+    pub fn circuit_commitments(&self, inputs: &[f32], outputs: &[f32]) -> CircuitCommitments {
+        CircuitCommitments {
+            scheme: CommitmentScheme::Keccak256V1,
+            model_commitment: self.keccak256.clone(),
+            input_hash: keccak_floats(inputs),
+            output_hash: keccak_floats(outputs),
+        }
+    }
 }
 
 /// SNARK proof components
@@ -67,6 +204,31 @@ pub struct ProofData {
     pub opening_proofs: Vec<String>,
 }
 
+/// Partial result for one shard of a sharded proof
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofShard {
+    /// Position of the shard, from 0 to `count - 1`
+    pub index: u32,
+    pub count: u32,
+    /// Commitment to the shard's slice of the witness
+    pub commitment: String,
+}
+
+/// Optional proving features, each set only if the backend supports it
+#[derive(Default)]
+pub struct ProveOptions {
+    /// Save progress here and resume from it
+    pub checkpoint: Option<Checkpoint>,
+    /// Stream the witness in chunks within this memory budget
+    pub witness_budget: Option<WitnessBudget>,
+    /// Prove on the GPU
+    pub gpu: bool,
+    /// Kill the proof once it uses more memory than this (see `memory`)
+    pub memory_budget: Option<u64>,
+    /// Stop proving once this is cancelled
+    pub cancellation: Option<Cancellation>,
+}
+
 /// Verification result
 #[derive(Debug)]
 pub struct VerificationResult {
@@ -81,18 +243,105 @@ pub struct VerificationResult {
 /// Jolt Atlas prover depending on the feature flags.
 pub trait ZkmlProver: Send + Sync {
     /// Generate a proof for ONNX model inference
+    ///
+    /// Both the SHA-256 and circuit-friendly commitments are bound into the
+    /// proof, so neither can be swapped out independently of the other. A
+    /// verifier-supplied `challenge` is absorbed into the Fiat-Shamir
+    /// transcript, so the proof cannot have been computed before it was issued.
     fn prove(
         &self,
-        model_commitment: &str,
+        model: &ModelCommitments,
         inputs: &[f32],
         outputs: &[f32],
+        challenge: Option<&str>,
     ) -> Result<JoltAtlasProof>;
 
+    /// Whether `prove_with` can checkpoint and resume an interrupted proof
+    fn supports_checkpoints(&self) -> bool {
+        false
+    }
+
+    /// Whether `prove_with` can stream the witness within a memory budget
+    /// instead of materializing the full execution trace
+    fn supports_witness_streaming(&self) -> bool {
+        false
+    }
+
+    /// Whether `prove_with` can move proving work to a GPU
+    fn supports_gpu(&self) -> bool {
+        false
+    }
+
+    /// Like `prove`, with the optional features in `options`
+    fn prove_with(
+        &self,
+        model: &ModelCommitments,
+        inputs: &[f32],
+        outputs: &[f32],
+        challenge: Option<&str>,
+        options: &ProveOptions,
+    ) -> Result<JoltAtlasProof> {
+        let _ = options;
+        self.prove(model, inputs, outputs, challenge)
+    }
+
+    /// Whether the witness and commitment work can be split into shards
+    fn supports_sharding(&self) -> bool {
+        false
+    }
+
+    /// Prove shard `index` of `count`; shards are independent and may run
+    /// in parallel
+    fn prove_shard(
+        &self,
+        model: &ModelCommitments,
+        inputs: &[f32],
+        outputs: &[f32],
+        challenge: Option<&str>,
+        index: u32,
+        count: u32,
+    ) -> Result<ProofShard> {
+        let _ = (model, inputs, outputs, challenge, index, count);
+        Err(anyhow!("{} does not support sharding", self.prover_id()))
+    }
+
+    /// Combine the results of every shard into a proof
+    fn combine_shards(
+        &self,
+        model: &ModelCommitments,
+        inputs: &[f32],
+        outputs: &[f32],
+        challenge: Option<&str>,
+        shards: Vec<ProofShard>,
+    ) -> Result<JoltAtlasProof> {
+        let _ = (model, inputs, outputs, challenge, shards);
+        Err(anyhow!("{} does not support sharding", self.prover_id()))
+    }
+
+    /// Build any per-model proving state (e.g. preprocessing) ahead of the
+    /// first proof, so requests do not pay for it
+    fn preprocess(&self, model: &ModelCommitments) -> Result<()> {
+        let _ = model;
+        Ok(())
+    }
+
     /// Verify a proof
     fn verify(&self, proof: &JoltAtlasProof) -> Result<VerificationResult>;
 
     /// Get prover identifier
     fn prover_id(&self) -> &str;
+
+    /// Version of the backend's constraint generation, changing whenever an
+    /// upgrade changes the circuits it builds
+    fn circuit_version(&self) -> String;
+
+    /// Version of the proofs the backend produces (see `proof_format`)
+    fn proof_version(&self) -> u8;
+}
+
+/// Name of the proof format of `version`, as in `GET /capabilities`
+pub fn proof_format(version: u8) -> String {
+    format!("jolt-atlas-v{}", version)
 }
 
 // ============================================================================
@@ -104,6 +353,9 @@ pub mod mock {
     use super::*;
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    /// Constraint generation of the mock backend
+    const MOCK_CIRCUIT_VERSION: &str = "jolt-atlas-mock-v1/circuit-1";
+
     /// Mock prover for development and testing
     pub struct MockProver;
 
@@ -119,15 +371,21 @@ pub mod mock {
         }
     }
 
-    impl ZkmlProver for MockProver {
-        fn prove(
+    impl MockProver {
+        /// Build a proof, committing to `shard_commitments` after the seed
+        fn build_proof(
             &self,
-            model_commitment: &str,
+            model: &ModelCommitments,
             inputs: &[f32],
             outputs: &[f32],
-        ) -> Result<JoltAtlasProof> {
-            let input_hash = hash_floats(inputs);
-            let output_hash = hash_floats(outputs);
+            challenge: Option<&str>,
+            shard_commitments: Vec<String>,
+        ) -> JoltAtlasProof {
+            let model_commitment = model.sha256.as_str();
+            let input_hash = model.input_hash(inputs);
+            let output_hash = model.output_hash(outputs);
+            let circuit_commitments = model.circuit_commitments(inputs, outputs);
+            let circuit_hash = circuit_hash(MOCK_CIRCUIT_VERSION, model_commitment);
 
             let timestamp = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -135,13 +393,19 @@ pub mod mock {
                 .as_secs();
 
             // Generate deterministic mock proof data
-            let seed = generate_proof_seed(model_commitment, &input_hash, &output_hash);
+            let seed = generate_proof_seed(
+                model_commitment,
+                &input_hash,
+                &output_hash,
+                Some(&circuit_commitments),
+                Some(&circuit_hash),
+                challenge,
+            );
 
+            let mut commitments = vec![hex::encode(&seed[0..32]), hex::encode(&seed[32..64])];
+            commitments.extend(shard_commitments);
             let proof_data = ProofData {
-                commitments: vec![
-                    hex::encode(&seed[0..32]),
-                    hex::encode(&seed[32..64]),
-                ],
+                commitments,
                 sumcheck_proof: hex::encode(hash_with_domain(&seed, b"sumcheck")),
                 lookup_proof: hex::encode(hash_with_domain(&seed, b"lookup")),
                 opening_proofs: vec![
@@ -150,18 +414,88 @@ pub mod mock {
                 ],
             };
 
-            Ok(JoltAtlasProof {
+            JoltAtlasProof {
                 version: 1,
                 prover_id: "jolt-atlas-mock-v1".to_string(),
                 model_commitment: model_commitment.to_string(),
                 input_hash,
                 output_hash,
+                commitment_scheme: CommitmentScheme::Sha256V2,
                 outputs: outputs.to_vec(),
                 timestamp,
+                circuit_commitments: Some(circuit_commitments),
+                expected_output: None,
+                challenge: challenge.map(String::from),
+                predicate: None,
+                quantization: None,
+                preprocessor: None,
+                circuit_hash: Some(circuit_hash),
                 proof_data,
+                backend_timings: None,
+            }
+        }
+    }
+
+    impl ZkmlProver for MockProver {
+        fn prove(
+            &self,
+            model: &ModelCommitments,
+            inputs: &[f32],
+            outputs: &[f32],
+            challenge: Option<&str>,
+        ) -> Result<JoltAtlasProof> {
+            Ok(self.build_proof(model, inputs, outputs, challenge, Vec::new()))
+        }
+
+        fn supports_sharding(&self) -> bool {
+            true
+        }
+
+        fn prove_shard(
+            &self,
+            model: &ModelCommitments,
+            inputs: &[f32],
+            outputs: &[f32],
+            challenge: Option<&str>,
+            index: u32,
+            count: u32,
+        ) -> Result<ProofShard> {
+            let seed = generate_proof_seed(
+                &model.sha256,
+                &model.input_hash(inputs),
+                &model.output_hash(outputs),
+                Some(&model.circuit_commitments(inputs, outputs)),
+                Some(&circuit_hash(MOCK_CIRCUIT_VERSION, &model.sha256)),
+                challenge,
+            );
+            Ok(ProofShard {
+                index,
+                count,
+                commitment: shard_commitment(&seed, index, count),
             })
         }
 
+        fn combine_shards(
+            &self,
+            model: &ModelCommitments,
+            inputs: &[f32],
+            outputs: &[f32],
+            challenge: Option<&str>,
+            mut shards: Vec<ProofShard>,
+        ) -> Result<JoltAtlasProof> {
+            shards.sort_by_key(|shard| shard.index);
+            let count = shards.len() as u32;
+            if shards
+                .iter()
+                .enumerate()
+                .any(|(i, shard)| shard.index != i as u32 || shard.count != count)
+            {
+                return Err(anyhow!("Shard results are incomplete"));
+            }
+            let commitments = shards.into_iter().map(|shard| shard.commitment).collect();
+            Ok(self.build_proof(model, inputs, outputs, challenge, commitments))
+        }
+
         fn verify(&self, proof: &JoltAtlasProof) -> Result<VerificationResult> {
             // Verify version
             if proof.version != 1 {
@@ -179,15 +513,25 @@ pub mod mock {
                 });
             }
 
+            if let Some(error) = circuit_mismatch(proof, MOCK_CIRCUIT_VERSION) {
+                return Ok(VerificationResult {
+                    valid: false,
+                    error: Some(error),
+                });
+            }
+
             // Verify proof data consistency
             let expected_seed = generate_proof_seed(
                 &proof.model_commitment,
                 &proof.input_hash,
                 &proof.output_hash,
+                proof.circuit_commitments.as_ref(),
+                proof.circuit_hash.as_deref(),
+                proof.challenge.as_deref(),
             );
 
             let expected_sumcheck = hex::encode(hash_with_domain(&expected_seed, b"sumcheck"));
-            if proof.proof_data.sumcheck_proof != expected_sumcheck {
+            if !ct_eq(&proof.proof_data.sumcheck_proof, &expected_sumcheck) {
                 return Ok(VerificationResult {
                     valid: false,
                     error: Some("Sumcheck proof mismatch".to_string()),
@@ -195,13 +539,26 @@ pub mod mock {
             }
 
             let expected_lookup = hex::encode(hash_with_domain(&expected_seed, b"lookup"));
-            if proof.proof_data.lookup_proof != expected_lookup {
+            if !ct_eq(&proof.proof_data.lookup_proof, &expected_lookup) {
                 return Ok(VerificationResult {
                     valid: false,
                     error: Some("Lookup proof mismatch".to_string()),
                 });
             }
 
+            // Commitments after the seed's two come from the proof's shards
+            let shards = proof.proof_data.commitments.get(2..).unwrap_or_default();
+            let count = shards.len() as u32;
+            let shards_match = shards.iter().zip(0..count).all(|(commitment, i)| {
+                ct_eq(commitment, &shard_commitment(&expected_seed, i, count))
+            });
+            if !shards_match {
+                return Ok(VerificationResult {
+                    valid: false,
+                    error: Some("Shard commitment mismatch".to_string()),
+                });
+            }
+
             Ok(VerificationResult {
                 valid: true,
                 error: None,
@@ -211,17 +568,43 @@ pub mod mock {
         fn prover_id(&self) -> &str {
             "jolt-atlas-mock-v1"
         }
+
+        fn circuit_version(&self) -> String {
+            MOCK_CIRCUIT_VERSION.to_string()
+        }
+
+        fn proof_version(&self) -> u8 {
+            1
+        }
     }
 
-    fn generate_proof_seed(model_commitment: &str, input_hash: &str, output_hash: &str) -> Vec<u8> {
+    fn generate_proof_seed(
+        model_commitment: &str,
+        input_hash: &str,
+        output_hash: &str,
+        circuit_commitments: Option<&CircuitCommitments>,
+        circuit_hash: Option<&str>,
+        challenge: Option<&str>,
+    ) -> Vec<u8> {
         let mut hasher = Sha256::new();
         hasher.update(model_commitment.as_bytes());
         hasher.update(input_hash.as_bytes());
         hasher.update(output_hash.as_bytes());
+        if let Some(circuit) = circuit_commitments {
+            hasher.update(circuit.binding());
+        }
+        if let Some(circuit_hash) = circuit_hash {
+            hasher.update(b"circuit");
+            hasher.update(circuit_hash.as_bytes());
+        }
+        if let Some(challenge) = challenge {
+            hasher.update(b"challenge");
+            hasher.update(challenge.as_bytes());
+        }
         let h1 = hasher.finalize();
 
         let mut hasher2 = Sha256::new();
-        hasher2.update(&h1);
+        hasher2.update(h1);
         hasher2.update(b"proof_seed");
         let h2 = hasher2.finalize();
 
@@ -234,6 +617,11 @@ pub mod mock {
         hasher.update(domain);
         hasher.finalize().to_vec()
     }
+
+    fn shard_commitment(seed: &[u8], index: u32, count: u32) -> String {
+        let domain = format!("shard_{}_of_{}", index, count);
+        hex::encode(hash_with_domain(seed, domain.as_bytes()))
+    }
 }
 
 // ============================================================================
@@ -242,6 +630,8 @@ pub mod mock {
 
 pub mod real {
     use super::*;
+    use crate::isolation::JobCancelled;
+    use crate::memory::{self, ResourceExhausted};
     use std::path::PathBuf;
     use std::process::Command;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -256,11 +646,18 @@ pub mod real {
         proof_size: usize,
         prove_time_ms: u64,
         verify_time_ms: u64,
+        /// Reported by binaries that time their stages
+        #[serde(default)]
+        witness_time_ms: Option<u64>,
+        #[serde(default)]
+        commit_time_ms: Option<u64>,
+        #[allow(dead_code)]
         input_features: InputFeatures,
         error: Option<String>,
     }
 
     #[derive(Deserialize)]
+    #[allow(dead_code)]
     struct InputFeatures {
         budget: usize,
         trust: usize,
@@ -272,6 +669,9 @@ pub mod real {
         risk: usize,
     }
 
+    /// Authorization model features in binary argument order
+    type AuthorizationFeatures = (usize, usize, usize, usize, usize, usize, usize, usize);
+
     /// Real Jolt Atlas prover using the authorization_json binary
     ///
     /// This implementation calls the precompiled Jolt Atlas binary
@@ -279,15 +679,23 @@ pub mod real {
     pub struct RealProver {
         binary_path: PathBuf,
         working_dir: PathBuf,
+        /// The binary generates the constraints, so it names the circuit
+        circuit_version: String,
     }
 
     impl RealProver {
+        #[cfg_attr(not(feature = "real-prover"), allow(dead_code))]
         pub fn new() -> Result<Self> {
+            let jolt_dir = std::env::current_dir()?.join("jolt-atlas");
+            Self::with_binary(jolt_dir.join("bin/authorization_json"))
+        }
+
+        /// A prover calling the Jolt Atlas binary at `binary_path`, e.g. a
+        /// new release under evaluation
+        pub fn with_binary(binary_path: PathBuf) -> Result<Self> {
             // Find the binary and model files
-            let base_dir = std::env::current_dir()?;
-            let jolt_dir = base_dir.join("jolt-atlas");
+            let jolt_dir = std::env::current_dir()?.join("jolt-atlas");
 
-            let binary_path = jolt_dir.join("bin/authorization_json");
             if !binary_path.exists() {
                 return Err(anyhow!(
                     "Jolt Atlas binary not found at {}. Run setup first.",
@@ -304,14 +712,22 @@ pub mod real {
                 ));
             }
 
+            let binary = std::fs::read(&binary_path)?;
+            let circuit_version = format!(
+                "jolt-atlas-real-v1/sha256:{}",
+                hex::encode(Sha256::digest(&binary))
+            );
+
             tracing::info!(
-                "Initialized real Jolt Atlas prover with binary at {}",
-                binary_path.display()
+                "Initialized real Jolt Atlas prover with binary at {} ({})",
+                binary_path.display(),
+                circuit_version
             );
 
             Ok(Self {
                 binary_path,
                 working_dir: jolt_dir,
+                circuit_version,
             })
         }
 
@@ -319,10 +735,10 @@ pub mod real {
         ///
         /// The authorization model expects 8 features:
         /// [budget, trust, amount, category, velocity, day, time, risk]
-        fn inputs_to_features(&self, inputs: &[f32]) -> Result<(usize, usize, usize, usize, usize, usize, usize, usize)> {
+        fn inputs_to_features(&self, inputs: &[f32]) -> Result<AuthorizationFeatures> {
             if inputs.len() < 8 {
                 // Use defaults for missing features
-                let budget = inputs.get(0).map(|v| *v as usize).unwrap_or(10);
+                let budget = inputs.first().map(|v| *v as usize).unwrap_or(10);
                 let trust = inputs.get(1).map(|v| *v as usize).unwrap_or(5);
                 let amount = inputs.get(2).map(|v| *v as usize).unwrap_or(5);
                 let category = inputs.get(3).map(|v| *v as usize).unwrap_or(0);
@@ -344,14 +760,21 @@ pub mod real {
                 inputs[7] as usize,
             ))
         }
-    }
 
-    impl ZkmlProver for RealProver {
-        fn prove(
+        /// Run the binary with `options` passed in its environment
+        ///
+        /// Binaries built with checkpoint support save their state to
+        /// `JOLT_ATLAS_CHECKPOINT_DIR` every `JOLT_ATLAS_CHECKPOINT_INTERVAL_SECS`
+        /// and resume from it; those with witness streaming generate the trace
+        /// in chunks within `JOLT_ATLAS_WITNESS_BUDGET_MB`; those with GPU
+        /// support prove on the GPU when `JOLT_ATLAS_GPU=1`. Others ignore the
+        /// variables.
+        fn run(
             &self,
-            model_commitment: &str,
+            model: &ModelCommitments,
             inputs: &[f32],
-            _outputs: &[f32],
+            challenge: Option<&str>,
+            options: &ProveOptions,
         ) -> Result<JoltAtlasProof> {
             let (budget, trust, amount, category, velocity, day, time, risk) =
                 self.inputs_to_features(inputs)?;
@@ -362,7 +785,21 @@ pub mod real {
             );
 
             // Call the Jolt Atlas binary
-            let output = Command::new(&self.binary_path)
+            let mut command = Command::new(&self.binary_path);
+            command.env("JOLT_ATLAS_GPU", if options.gpu { "1" } else { "0" });
+            if let Some(budget) = &options.witness_budget {
+                command.envs(budget.env());
+            }
+            if let Some(checkpoint) = &options.checkpoint {
+                std::fs::create_dir_all(checkpoint.dir())?;
+                command
+                    .env("JOLT_ATLAS_CHECKPOINT_DIR", checkpoint.dir())
+                    .env(
+                        "JOLT_ATLAS_CHECKPOINT_INTERVAL_SECS",
+                        checkpoint.interval().as_secs().to_string(),
+                    );
+            }
+            command
                 .args([
                     budget.to_string(),
                     trust.to_string(),
@@ -373,9 +810,19 @@ pub mod real {
                     time.to_string(),
                     risk.to_string(),
                 ])
-                .current_dir(&self.working_dir)
-                .output()
-                .map_err(|e| anyhow!("Failed to execute Jolt Atlas binary: {}", e))?;
+                .current_dir(&self.working_dir);
+            let output = match (options.memory_budget, &options.cancellation) {
+                (None, None) => command.output().map_err(anyhow::Error::from),
+                (budget, cancellation) => {
+                    memory::output_within(&mut command, budget, cancellation.as_ref())
+                }
+            }
+            .map_err(|e| {
+                match e.is::<ResourceExhausted>() || e.is::<JobCancelled>() {
+                    true => e,
+                    false => anyhow!("Failed to execute Jolt Atlas binary: {}", e),
+                }
+            })?;
 
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
@@ -399,7 +846,7 @@ pub mod real {
                 ));
             }
 
-            let input_hash = hash_floats(inputs);
+            let input_hash = model.input_hash(inputs);
             let output_hash = format!("0x{}", binary_output.proof_hash);
 
             let timestamp = SystemTime::now()
@@ -407,12 +854,24 @@ pub mod real {
                 .unwrap()
                 .as_secs();
 
-            // Decision to output values
-            let decision_value = if binary_output.decision == "AUTHORIZED" { 1.0 } else { 0.0 };
             let confidence = binary_output.confidence / 100.0;
+            let outputs = vec![1.0 - confidence, confidence]; // [deny_prob, authorize_prob]
+
+            // The binary cannot take extra public inputs, so the circuit-friendly
+            // commitments are bound alongside its proof hash instead
+            let circuit_commitments = model.circuit_commitments(inputs, &outputs);
+            let circuit_hash = circuit_hash(&self.circuit_version, &model.sha256);
 
             let proof_data = ProofData {
-                commitments: vec![binary_output.proof_hash.clone()],
+                commitments: vec![
+                    binary_output.proof_hash.clone(),
+                    real_binding(
+                        &binary_output.proof_hash,
+                        &circuit_commitments,
+                        Some(&circuit_hash),
+                        challenge,
+                    ),
+                ],
                 sumcheck_proof: format!("real:{}", binary_output.prove_time_ms),
                 lookup_proof: format!("verified:{}", binary_output.verify_time_ms),
                 opening_proofs: vec![
@@ -432,14 +891,61 @@ pub mod real {
             Ok(JoltAtlasProof {
                 version: 2, // Version 2 = real prover
                 prover_id: "jolt-atlas-real-v1".to_string(),
-                model_commitment: model_commitment.to_string(),
+                model_commitment: model.sha256.clone(),
                 input_hash,
                 output_hash,
-                outputs: vec![1.0 - confidence, confidence], // [deny_prob, authorize_prob]
+                commitment_scheme: CommitmentScheme::Sha256V2,
+                outputs,
                 timestamp,
+                circuit_commitments: Some(circuit_commitments),
+                expected_output: None,
+                challenge: challenge.map(String::from),
+                predicate: None,
+                quantization: None,
+                preprocessor: None,
+                circuit_hash: Some(circuit_hash),
                 proof_data,
+                backend_timings: Some(BackendTimings {
+                    witness_ms: binary_output.witness_time_ms,
+                    commitment_ms: binary_output.commit_time_ms,
+                }),
             })
         }
+    }
+
+    impl ZkmlProver for RealProver {
+        fn prove(
+            &self,
+            model: &ModelCommitments,
+            inputs: &[f32],
+            _outputs: &[f32],
+            challenge: Option<&str>,
+        ) -> Result<JoltAtlasProof> {
+            self.run(model, inputs, challenge, &ProveOptions::default())
+        }
+
+        fn supports_checkpoints(&self) -> bool {
+            true
+        }
+
+        fn supports_witness_streaming(&self) -> bool {
+            true
+        }
+
+        fn supports_gpu(&self) -> bool {
+            true
+        }
+
+        fn prove_with(
+            &self,
+            model: &ModelCommitments,
+            inputs: &[f32],
+            _outputs: &[f32],
+            challenge: Option<&str>,
+            options: &ProveOptions,
+        ) -> Result<JoltAtlasProof> {
+            self.run(model, inputs, challenge, options)
+        }
 
         fn verify(&self, proof: &JoltAtlasProof) -> Result<VerificationResult> {
             // The binary already verifies during proof generation
@@ -459,6 +965,36 @@ pub mod real {
                 });
             }
 
+            if let Some(error) = circuit_mismatch(proof, &self.circuit_version) {
+                return Ok(VerificationResult {
+                    valid: false,
+                    error: Some(error),
+                });
+            }
+
+            // Circuit-friendly commitments, when present, must match the binding
+            if let Some(circuit) = &proof.circuit_commitments {
+                let bound = proof.proof_data.commitments.first().is_some_and(|hash| {
+                    proof.proof_data.commitments.get(1).is_some_and(|bound| {
+                        ct_eq(
+                            bound,
+                            &real_binding(
+                                hash,
+                                circuit,
+                                proof.circuit_hash.as_deref(),
+                                proof.challenge.as_deref(),
+                            ),
+                        )
+                    })
+                });
+                if !bound {
+                    return Ok(VerificationResult {
+                        valid: false,
+                        error: Some("Circuit commitments not bound to proof".to_string()),
+                    });
+                }
+            }
+
             // Check that proof data contains verification timing (indicating it was verified)
             let has_verify_time = proof.proof_data.lookup_proof.starts_with("verified:");
             if !has_verify_time {
@@ -477,6 +1013,38 @@ pub mod real {
         fn prover_id(&self) -> &str {
             "jolt-atlas-real-v1"
         }
+
+        fn circuit_version(&self) -> String {
+            self.circuit_version.clone()
+        }
+
+        fn proof_version(&self) -> u8 {
+            2
+        }
+    }
+
+    /// Binds the commitments and challenge to the binary's proof
+    ///
+    /// The binary does not take a challenge yet, so it is bound here rather
+    /// than absorbed into the binary's own transcript.
+    fn real_binding(
+        proof_hash: &str,
+        circuit: &CircuitCommitments,
+        circuit_hash: Option<&str>,
+        challenge: Option<&str>,
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(proof_hash.as_bytes());
+        hasher.update(circuit.binding());
+        if let Some(circuit_hash) = circuit_hash {
+            hasher.update(b"circuit");
+            hasher.update(circuit_hash.as_bytes());
+        }
+        if let Some(challenge) = challenge {
+            hasher.update(b"challenge");
+            hasher.update(challenge.as_bytes());
+        }
+        format!("binding:{}", hex::encode(hasher.finalize()))
     }
 }
 
@@ -484,15 +1052,117 @@ pub mod real {
 // Utility Functions
 // ============================================================================
 
-/// Hash a slice of floats
+/// Hash of the circuit a backend at `circuit_version` builds for the model
+/// (composed with any bound statements) committed to by `model_commitment`
+///
+/// Bound into the proof and its public inputs, so a proof is only accepted
+/// by a verifier building the same circuit: a backend upgrade that changes
+/// constraint generation changes the hash instead of silently validating
+/// proofs against a different circuit.
+pub fn circuit_hash(circuit_version: &str, model_commitment: &str) -> String {
+    let mut hasher = Sha256::new();
+    for field in ["circuit-v1", circuit_version, model_commitment] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Why `proof` was not generated for the circuit a backend at
+/// `circuit_version` builds, if it was not; proofs that predate circuit
+/// hashes are not checked
+pub fn circuit_mismatch(proof: &JoltAtlasProof, circuit_version: &str) -> Option<String> {
+    let hash = proof.circuit_hash.as_deref()?;
+    let expected = circuit_hash(circuit_version, &proof.model_commitment);
+    (!ct_eq(hash, &expected)).then(|| {
+        format!(
+            "Proof was generated for circuit {}, this verifier builds {}",
+            hash, expected
+        )
+    })
+}
+
+/// Whether a prover id names the mock backend
+pub fn is_mock_prover(prover_id: &str) -> bool {
+    prover_id.contains("mock")
+}
+
+/// Hash a slice of floats (`sha256-v1`)
 pub fn hash_floats(values: &[f32]) -> String {
     let mut hasher = Sha256::new();
-    for v in values {
-        hasher.update(v.to_le_bytes());
+    update_floats(&mut hasher, values, |v| v);
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Feed `values`, mapped by `map`, to `hasher` as little-endian bytes
+///
+/// Equivalent to updating with each float in turn, but converts them in
+/// blocks so large tensors are hashed at the hash's own speed.
+fn update_floats(hasher: &mut impl sha2::digest::Update, values: &[f32], map: impl Fn(f32) -> f32) {
+    let mut block = [0u8; 4 * FLOAT_BLOCK];
+    for chunk in values.chunks(FLOAT_BLOCK) {
+        for (bytes, v) in block.chunks_exact_mut(4).zip(chunk) {
+            bytes.copy_from_slice(&map(*v).to_le_bytes());
+        }
+        hasher.update(&block[..4 * chunk.len()]);
+    }
+}
+
+/// Hash a tensor under `sha256-v2`
+///
+/// `hash_floats` (`sha256-v1`) hashes only the raw values, so the same
+/// floats hash identically wherever they appear. Here the digest also
+/// covers the scheme tag, the commitment of the model the tensor belongs to,
+/// the tensor's name, dtype and shape, and each variable-length field is
+/// length-prefixed so no two encodings collide. Values are hashed in
+/// canonical form (see `canonical_f32`).
+pub fn hash_tensor(model_commitment: &str, name: &str, shape: &[usize], values: &[f32]) -> String {
+    debug_assert_eq!(shape.iter().product::<usize>(), values.len());
+    let mut hasher = Sha256::new();
+    for field in [
+        CommitmentScheme::Sha256V2.as_str(),
+        model_commitment,
+        name,
+        "f32",
+    ] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
     }
+    hasher.update((shape.len() as u64).to_le_bytes());
+    for dim in shape {
+        hasher.update((*dim as u64).to_le_bytes());
+    }
+    hasher.update((values.len() as u64).to_le_bytes());
+    update_floats(&mut hasher, values, canonical_f32);
     format!("0x{}", hex::encode(hasher.finalize()))
 }
 
+/// `sha256-v2` hash of the output tensor `name` of the model committed to by
+/// `model_commitment`, kept apart from the hash of all outputs together
+pub fn named_output_hash(model_commitment: &str, name: &str, values: &[f32]) -> String {
+    hash_tensor(
+        model_commitment,
+        &format!("output:{}", name),
+        &[values.len()],
+        values,
+    )
+}
+
+/// Canonical form of a float for hashing
+///
+/// Runtimes disagree on values that compare equal or carry no meaning in
+/// their bits: every NaN becomes the quiet NaN `0x7fc00000`, and negative
+/// zero and subnormals (which some runtimes flush) become `+0.0`.
+pub fn canonical_f32(v: f32) -> f32 {
+    if v.is_nan() {
+        f32::from_bits(0x7fc0_0000)
+    } else if v == 0.0 || v.is_subnormal() {
+        0.0
+    } else {
+        v
+    }
+}
+
 /// Compute model commitment from ONNX bytes
 pub fn compute_model_commitment(model_bytes: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -500,10 +1170,26 @@ pub fn compute_model_commitment(model_bytes: &[u8]) -> String {
     format!("0x{}", hex::encode(hasher.finalize()))
 }
 
+/// Hash a slice of floats with Keccak-256, in canonical form (see
+/// `canonical_f32`) so circuit and on-chain commitments agree across runtimes
+pub fn keccak_floats(values: &[f32]) -> String {
+    let mut hasher = Keccak256::new();
+    update_floats(&mut hasher, values, canonical_f32);
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Compute the Keccak-256 model commitment from ONNX bytes
+pub fn compute_model_commitment_keccak(model_bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(Keccak256::digest(model_bytes)))
+}
+
 /// Serialize a proof to base64
 pub fn serialize_proof(proof: &JoltAtlasProof) -> Result<String> {
     let json = serde_json::to_vec(proof)?;
-    Ok(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, json))
+    Ok(base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        json,
+    ))
 }
 
 /// Deserialize a proof from base64
@@ -537,6 +1223,21 @@ pub fn create_prover() -> Result<Box<dyn ZkmlProver>> {
     }
 }
 
+/// Create a backend from a spec: `mock`, or the path of a Jolt Atlas binary
+pub fn create_backend(spec: &str) -> Result<Box<dyn ZkmlProver>> {
+    if spec == "mock" {
+        #[cfg(feature = "mock-prover")]
+        {
+            return Ok(Box::new(mock::MockProver::new()));
+        }
+        #[allow(unreachable_code)]
+        return Err(anyhow!("This build has no mock prover"));
+    }
+    Ok(Box::new(real::RealProver::with_binary(
+        std::path::PathBuf::from(spec),
+    )?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -549,6 +1250,83 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_large_inputs_hash_as_before() {
+        // Blocked and parallel hashing must not change any digest
+        let values: Vec<f32> = (0..3 * FLOAT_BLOCK + 7).map(|i| i as f32 * 0.5).collect();
+        let mut hasher = Sha256::new();
+        for v in &values {
+            hasher.update(v.to_le_bytes());
+        }
+        assert_eq!(
+            hash_floats(&values),
+            format!("0x{}", hex::encode(hasher.finalize()))
+        );
+
+        let model_bytes = vec![7u8; PARALLEL_HASH_MIN_BYTES + 1];
+        let commitments = ModelCommitments::compute(&model_bytes);
+        assert_eq!(commitments.sha256, compute_model_commitment(&model_bytes));
+        assert_eq!(
+            commitments.keccak256,
+            compute_model_commitment_keccak(&model_bytes)
+        );
+    }
+
+    #[test]
+    fn test_tensor_hash_is_domain_separated() {
+        let a = ModelCommitments::compute(b"model a");
+        let b = ModelCommitments::compute(b"model b");
+        let values = [1.0, 2.0];
+
+        assert_eq!(a.input_hash(&values), a.input_hash(&values));
+        assert_ne!(a.input_hash(&values), b.input_hash(&values));
+        assert_ne!(a.input_hash(&values), a.output_hash(&values));
+        assert_ne!(
+            a.named_output_hash("output", &values),
+            a.output_hash(&values)
+        );
+        assert_ne!(
+            a.named_output_hash("probabilities", &values),
+            a.named_output_hash("label", &values)
+        );
+        assert_ne!(a.input_hash(&values), hash_floats(&values));
+        assert_ne!(
+            hash_tensor(&a.sha256, "input", &[2], &values),
+            hash_tensor(&a.sha256, "input", &[1, 2], &values)
+        );
+    }
+
+    #[test]
+    fn test_tensor_hash_canonicalizes_floats() {
+        let model = ModelCommitments::compute(b"model");
+        let hash = |v: f32| model.input_hash(&[1.0, v]);
+
+        assert_eq!(hash(-0.0), hash(0.0));
+        assert_eq!(hash(f32::MIN_POSITIVE / 2.0), hash(0.0));
+        assert_eq!(hash(-f32::MIN_POSITIVE / 4.0), hash(0.0));
+        assert_eq!(hash(f32::from_bits(0xffc0_0001)), hash(f32::NAN));
+        assert_ne!(hash(f32::NAN), hash(0.0));
+        assert_ne!(hash(f32::MIN_POSITIVE), hash(0.0));
+        assert_ne!(hash(f32::INFINITY), hash(f32::NEG_INFINITY));
+    }
+
+    #[cfg(feature = "mock-prover")]
+    #[test]
+    fn test_proofs_without_scheme_keep_sha256_v1_hashes() {
+        let prover = mock::MockProver::new();
+        let model = ModelCommitments::compute(b"fake onnx model data");
+        let proof = prover.prove(&model, &[1.0], &[0.5], None).unwrap();
+        assert!(proof.commitments_for(CommitmentScheme::Sha256V2).is_some());
+        assert!(proof.commitments_for(CommitmentScheme::Sha256V1).is_none());
+
+        let mut json = serde_json::to_value(&proof).unwrap();
+        json.as_object_mut().unwrap().remove("commitment_scheme");
+        let legacy: JoltAtlasProof = serde_json::from_value(json).unwrap();
+        let (_, input_hash, _) = legacy.commitments_for(CommitmentScheme::Sha256V1).unwrap();
+        assert_eq!(input_hash, proof.input_hash);
+        assert!(prover.verify(&legacy).unwrap().valid);
+    }
+
     #[test]
     fn test_model_commitment() {
         let model_bytes = b"fake onnx model data";
@@ -562,13 +1340,91 @@ mod tests {
     fn test_mock_prover_roundtrip() {
         let prover = mock::MockProver::new();
 
-        let model_commitment = "0x1234567890abcdef";
+        let model = ModelCommitments::compute(b"fake onnx model data");
         let inputs = vec![1.0, 2.0, 3.0];
         let outputs = vec![0.9, 0.1];
 
-        let proof = prover.prove(model_commitment, &inputs, &outputs).unwrap();
+        let proof = prover.prove(&model, &inputs, &outputs, None).unwrap();
         let result = prover.verify(&proof).unwrap();
 
         assert!(result.valid);
     }
+
+    #[cfg(feature = "mock-prover")]
+    #[test]
+    fn test_mock_prover_binds_circuit_commitments() {
+        let prover = mock::MockProver::new();
+        let model = ModelCommitments::compute(b"fake onnx model data");
+
+        let mut proof = prover
+            .prove(&model, &[1.0, 2.0], &[0.9, 0.1], None)
+            .unwrap();
+        let circuit = proof.circuit_commitments.clone().unwrap();
+        assert_eq!(circuit.model_commitment, model.keccak256);
+        assert_eq!(circuit.input_hash, keccak_floats(&[1.0, 2.0]));
+        assert_ne!(circuit.input_hash, proof.input_hash);
+
+        proof.circuit_commitments.as_mut().unwrap().output_hash = keccak_floats(&[0.1, 0.9]);
+        assert!(!prover.verify(&proof).unwrap().valid);
+    }
+
+    #[cfg(feature = "mock-prover")]
+    #[test]
+    fn test_mock_prover_binds_circuit_hash() {
+        let prover = mock::MockProver::new();
+        let model = ModelCommitments::compute(b"fake onnx model data");
+
+        let mut proof = prover.prove(&model, &[1.0], &[0.5], None).unwrap();
+        let expected = circuit_hash(&prover.circuit_version(), &model.sha256);
+        assert_eq!(proof.circuit_hash.as_deref(), Some(expected.as_str()));
+        assert!(prover.verify(&proof).unwrap().valid);
+
+        // A proof for another circuit version is rejected
+        let other = circuit_hash("jolt-atlas-mock-v1/circuit-2", &model.sha256);
+        assert_ne!(other, expected);
+        proof.circuit_hash = Some(other);
+        let result = prover.verify(&proof).unwrap();
+        assert!(!result.valid);
+        assert!(result.error.unwrap().contains("generated for circuit"));
+    }
+
+    #[cfg(feature = "mock-prover")]
+    #[test]
+    fn test_mock_prover_absorbs_challenge() {
+        let prover = mock::MockProver::new();
+        let model = ModelCommitments::compute(b"fake onnx model data");
+
+        let mut proof = prover.prove(&model, &[1.0], &[0.5], Some("0x01")).unwrap();
+        assert!(prover.verify(&proof).unwrap().valid);
+        let unchallenged = prover.prove(&model, &[1.0], &[0.5], None).unwrap();
+        assert_ne!(
+            proof.proof_data.sumcheck_proof,
+            unchallenged.proof_data.sumcheck_proof
+        );
+
+        proof.challenge = Some("0x02".to_string());
+        assert!(!prover.verify(&proof).unwrap().valid);
+    }
+
+    #[test]
+    fn test_keccak_hash_canonicalizes_floats() {
+        assert_eq!(keccak_floats(&[1.0, -0.0]), keccak_floats(&[1.0, 0.0]));
+        assert_eq!(
+            keccak_floats(&[f32::from_bits(0xffc0_0001)]),
+            keccak_floats(&[f32::NAN])
+        );
+        assert_eq!(
+            keccak_floats(&[f32::MIN_POSITIVE / 2.0]),
+            keccak_floats(&[0.0])
+        );
+    }
+
+    #[test]
+    fn test_keccak_commitment_matches_known_vector() {
+        // keccak256("") as used by Solidity
+        assert_eq!(
+            compute_model_commitment_keccak(b""),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
 }
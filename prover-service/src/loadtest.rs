@@ -0,0 +1,453 @@
+//! Built-in load generator
+//!
+//! `trustless-agentkit-prover --loadtest [options]` drives synthetic
+//! `/prove` and `/verify/proof` traffic against a running service and prints
+//! throughput and latency percentiles as JSON, so capacity planning and soak
+//! tests need no external harness:
+//!
+//! - `--url <base>`: service to load (default `http://127.0.0.1:3001`);
+//! - `--concurrency <n>`: requests kept in flight (default 4);
+//! - `--duration <d>`: how long to run, e.g. `60s` or `30m` (default `30s`);
+//! - `--requests <n>`: stop after `n` prove requests instead;
+//! - `--models <id[=weight],...>`: model mix (default: the demo models);
+//! - `--verify <fraction>`: share of proofs that are also verified (default 1);
+//! - `--features <n>`: input length for models without example inputs
+//!   (default 8).
+//!
+//! `PROVER_API_KEY`, when set, is sent as `X-API-Key`. Models listed by
+//! `GET /demo` are proven over their example inputs; others over random ones.
+
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::auth::API_KEY_HEADER;
+use crate::jobs::parse_duration;
+
+/// Command-line flag starting the binary as a load generator
+pub const LOADTEST_ARG: &str = "--loadtest";
+
+/// What to load the service with
+#[derive(Debug, PartialEq)]
+pub struct LoadTestOptions {
+    pub url: String,
+    pub concurrency: usize,
+    pub duration: Duration,
+    pub requests: Option<u64>,
+    /// Model ids and their relative weights
+    pub models: Vec<(String, u32)>,
+    pub verify_fraction: f64,
+    pub features: usize,
+}
+
+impl Default for LoadTestOptions {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:3001".to_string(),
+            concurrency: 4,
+            duration: Duration::from_secs(30),
+            requests: None,
+            models: Vec::new(),
+            verify_fraction: 1.0,
+            features: 8,
+        }
+    }
+}
+
+impl LoadTestOptions {
+    /// Parse the arguments following `--loadtest`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("{} needs a value", flag))?;
+            let invalid = || anyhow!("Invalid {} {:?}", flag, value);
+            match flag.as_str() {
+                "--url" => options.url = value.trim_end_matches('/').to_string(),
+                "--concurrency" => {
+                    options.concurrency = value.parse().map_err(|_| invalid())?;
+                    if options.concurrency == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "--duration" => options.duration = parse_duration(&value).ok_or_else(invalid)?,
+                "--requests" => options.requests = Some(value.parse().map_err(|_| invalid())?),
+                "--models" => options.models = parse_model_mix(&value).ok_or_else(invalid)?,
+                "--verify" => {
+                    options.verify_fraction = value.parse().map_err(|_| invalid())?;
+                    if !(0.0..=1.0).contains(&options.verify_fraction) {
+                        return Err(invalid());
+                    }
+                }
+                "--features" => options.features = value.parse().map_err(|_| invalid())?,
+                _ => return Err(anyhow!("Unknown load test option {}", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Parse `id[=weight],...`
+fn parse_model_mix(mix: &str) -> Option<Vec<(String, u32)>> {
+    let models = mix
+        .split(',')
+        .map(|entry| {
+            let (id, weight) = match entry.split_once('=') {
+                Some((id, weight)) => (id, weight.parse().ok()?),
+                None => (entry, 1),
+            };
+            (!id.is_empty()).then(|| (id.to_string(), weight))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    models
+        .iter()
+        .any(|(_, weight)| *weight > 0)
+        .then_some(models)
+}
+
+/// Latencies and outcomes of one kind of request
+#[derive(Serialize)]
+pub struct LatencyReport {
+    pub requests: u64,
+    pub errors: u64,
+    pub throughput_per_sec: f64,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+impl LatencyReport {
+    /// Report on latencies in microseconds
+    fn new(mut latencies: Vec<u64>, errors: u64, elapsed: Duration) -> Self {
+        latencies.sort_unstable();
+        let ms = |us: Option<u64>| us.map(|us| us as f64 / 1000.0);
+        Self {
+            requests: latencies.len() as u64 + errors,
+            errors,
+            throughput_per_sec: latencies.len() as f64 / elapsed.as_secs_f64().max(1e-9),
+            p50_ms: ms(percentile(&latencies, 0.50)),
+            p90_ms: ms(percentile(&latencies, 0.90)),
+            p99_ms: ms(percentile(&latencies, 0.99)),
+            max_ms: ms(latencies.last().copied()),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.max(1) - 1).copied()
+}
+
+/// Result of a load test
+#[derive(Serialize)]
+pub struct LoadTestReport {
+    pub url: String,
+    pub concurrency: usize,
+    pub elapsed_secs: f64,
+    /// Successful prove requests per model
+    pub proofs_by_model: BTreeMap<String, u64>,
+    pub prove: LatencyReport,
+    pub verify: LatencyReport,
+    /// Proofs the service itself reported as invalid
+    pub invalid_proofs: u64,
+    /// Failed requests by error code (or HTTP status / transport error)
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct Samples {
+    prove_us: Vec<u64>,
+    prove_errors: u64,
+    verify_us: Vec<u64>,
+    verify_errors: u64,
+    invalid_proofs: u64,
+    proofs_by_model: BTreeMap<String, u64>,
+    errors: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+struct DemoModel {
+    model_id: String,
+    example_inputs: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ProveReply {
+    proof: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyReply {
+    valid: bool,
+}
+
+#[derive(Deserialize)]
+struct ErrorReply {
+    code: String,
+}
+
+/// Everything the workers share
+struct LoadTest {
+    options: LoadTestOptions,
+    http: reqwest::Client,
+    api_key: Option<String>,
+    /// Model id, weight and inputs
+    models: Vec<(String, u32, Vec<f32>)>,
+    deadline: Instant,
+    started: AtomicU64,
+    samples: Mutex<Samples>,
+}
+
+impl LoadTest {
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.post(format!("{}{}", self.options.url, path));
+        match &self.api_key {
+            Some(key) => request.header(API_KEY_HEADER, key),
+            None => request,
+        }
+    }
+
+    /// Reserve the next request, unless the test is over
+    fn next_request(&self) -> bool {
+        if Instant::now() >= self.deadline {
+            return false;
+        }
+        let n = self.started.fetch_add(1, Ordering::Relaxed);
+        self.options.requests.is_none_or(|limit| n < limit)
+    }
+
+    fn pick_model(&self) -> &(String, u32, Vec<f32>) {
+        let total: u32 = self.models.iter().map(|(_, weight, _)| weight).sum();
+        let mut roll = rand::thread_rng().gen_range(0..total);
+        for model in &self.models {
+            if roll < model.1 {
+                return model;
+            }
+            roll -= model.1;
+        }
+        unreachable!("roll is below the total weight")
+    }
+
+    /// Send `body` to `path`, returning the latency in microseconds and the
+    /// decoded reply or the error key
+    async fn call<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+    ) -> (u64, Result<T, String>) {
+        let start = Instant::now();
+        let reply = async {
+            let response = self
+                .post(path)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| format!("transport: {}", e.without_url()))?;
+            let status = response.status();
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| format!("transport: {}", e.without_url()))?;
+            if !status.is_success() {
+                return Err(serde_json::from_slice::<ErrorReply>(&bytes)
+                    .map(|error| error.code)
+                    .unwrap_or_else(|_| format!("HTTP {}", status.as_u16())));
+            }
+            serde_json::from_slice(&bytes).map_err(|_| "malformed response".to_string())
+        }
+        .await;
+        (start.elapsed().as_micros() as u64, reply)
+    }
+
+    async fn worker(self: Arc<Self>) {
+        while self.next_request() {
+            let (model_id, _, inputs) = self.pick_model();
+            let body = serde_json::json!({ "model_id": model_id, "inputs": inputs });
+            let (prove_us, reply) = self.call::<ProveReply>("/prove", body).await;
+            let proof = {
+                let mut samples = self.samples.lock().unwrap();
+                match reply {
+                    Ok(reply) => {
+                        samples.prove_us.push(prove_us);
+                        *samples.proofs_by_model.entry(model_id.clone()).or_default() += 1;
+                        Some(reply.proof)
+                    }
+                    Err(error) => {
+                        samples.prove_errors += 1;
+                        *samples.errors.entry(error).or_default() += 1;
+                        None
+                    }
+                }
+            };
+
+            let Some(proof) = proof else { continue };
+            if !rand::thread_rng().gen_bool(self.options.verify_fraction) {
+                continue;
+            }
+            let body = serde_json::json!({ "proof": proof });
+            let (verify_us, reply) = self.call::<VerifyReply>("/verify/proof", body).await;
+            let mut samples = self.samples.lock().unwrap();
+            match reply {
+                Ok(reply) => {
+                    samples.verify_us.push(verify_us);
+                    if !reply.valid {
+                        samples.invalid_proofs += 1;
+                    }
+                }
+                Err(error) => {
+                    samples.verify_errors += 1;
+                    *samples.errors.entry(error).or_default() += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Run a load test and report on it
+pub async fn run(options: LoadTestOptions) -> Result<LoadTestReport> {
+    let http = reqwest::Client::new();
+
+    // Demo models come with inputs they accept
+    let demo: HashMap<String, Vec<f32>> = match http
+        .get(format!("{}/demo", options.url))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => response
+            .json::<Vec<DemoModel>>()
+            .await
+            .context("Malformed /demo response")?
+            .into_iter()
+            .map(|model| (model.model_id, model.example_inputs))
+            .collect(),
+        Err(e) => {
+            eprintln!("Cannot list demo models ({}); using random inputs", e);
+            HashMap::new()
+        }
+    };
+    let mix = if options.models.is_empty() {
+        let mut ids: Vec<_> = demo.keys().map(|id| (id.clone(), 1)).collect();
+        ids.sort();
+        ids
+    } else {
+        options.models.clone()
+    };
+    if mix.is_empty() {
+        return Err(anyhow!(
+            "No --models given and the service has no demo models (DEMO_MODE)"
+        ));
+    }
+    let models = mix
+        .into_iter()
+        .map(|(id, weight)| {
+            let inputs = demo.get(&id).cloned().unwrap_or_else(|| {
+                let mut rng = rand::thread_rng();
+                (0..options.features).map(|_| rng.gen::<f32>()).collect()
+            });
+            (id, weight, inputs)
+        })
+        .collect();
+
+    eprintln!(
+        "Loading {} with {} concurrent requests for up to {:?}",
+        options.url, options.concurrency, options.duration
+    );
+    let start = Instant::now();
+    let test = Arc::new(LoadTest {
+        deadline: start + options.duration,
+        api_key: std::env::var("PROVER_API_KEY").ok(),
+        http,
+        models,
+        started: AtomicU64::new(0),
+        samples: Mutex::new(Samples::default()),
+        options,
+    });
+    let workers: Vec<_> = (0..test.options.concurrency)
+        .map(|_| tokio::spawn(test.clone().worker()))
+        .collect();
+    for worker in workers {
+        worker.await?;
+    }
+    let elapsed = start.elapsed();
+
+    let samples = std::mem::take(&mut *test.samples.lock().unwrap());
+    Ok(LoadTestReport {
+        url: test.options.url.clone(),
+        concurrency: test.options.concurrency,
+        elapsed_secs: elapsed.as_secs_f64(),
+        proofs_by_model: samples.proofs_by_model,
+        prove: LatencyReport::new(samples.prove_us, samples.prove_errors, elapsed),
+        verify: LatencyReport::new(samples.verify_us, samples.verify_errors, elapsed),
+        invalid_proofs: samples.invalid_proofs,
+        errors: samples.errors,
+    })
+}
+
+/// Entry point of `--loadtest`: run and print the report on stdout
+pub async fn loadtest_main(args: impl IntoIterator<Item = String>) -> i32 {
+    let report = match LoadTestOptions::parse(args) {
+        Ok(options) => run(options).await,
+        Err(e) => Err(e),
+    };
+    match report {
+        Ok(report) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("reports serialize")
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Load test failed: {:#}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loadtest_options_and_percentiles() {
+        let args = [
+            "--concurrency",
+            "16",
+            "--duration",
+            "2m",
+            "--models",
+            "demo-mlp=3,demo-logistic",
+            "--verify",
+            "0.25",
+        ];
+        let options = LoadTestOptions::parse(args.map(String::from)).unwrap();
+        assert_eq!(options.concurrency, 16);
+        assert_eq!(options.duration, Duration::from_secs(120));
+        assert_eq!(
+            options.models,
+            vec![
+                ("demo-mlp".to_string(), 3),
+                ("demo-logistic".to_string(), 1)
+            ]
+        );
+        assert_eq!(options.verify_fraction, 0.25);
+        assert!(LoadTestOptions::parse(["--verify", "2"].map(String::from)).is_err());
+        assert!(LoadTestOptions::parse(["--models", "m=0"].map(String::from)).is_err());
+        assert!(LoadTestOptions::parse(["--concurrency"].map(String::from)).is_err());
+
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 0.50), Some(50));
+        assert_eq!(percentile(&sorted, 0.99), Some(99));
+        assert_eq!(percentile(&[7], 0.99), Some(7));
+        assert_eq!(percentile(&[], 0.5), None);
+    }
+}
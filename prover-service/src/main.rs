@@ -4,14 +4,90 @@
 //! This service wraps the Jolt Atlas proving system and exposes
 //! a simple REST API for proof generation and verification.
 
+mod admin;
+mod admission;
+mod aliases;
+mod api_keys;
+mod audit;
+mod auth;
+mod backup;
+mod batches;
+mod body_limit;
+mod breaker;
+mod bundle;
+mod challenges;
+mod checkpoint;
+mod cluster;
+mod commitments;
+mod config;
+mod consensus;
+mod demo;
+mod encryption;
+mod events;
+mod experiments;
+mod flags;
+mod gc;
+mod inference;
+mod ingest;
+mod inspect;
+mod isolation;
+mod jobs;
 mod jolt_atlas;
+mod loadtest;
+mod manifest;
+mod market_data;
+mod memory;
+mod merkle;
+mod metering;
+mod migrate;
+mod msgpack;
+mod offline_audit;
+mod onnx;
+mod oracles;
+mod overview;
+mod persist;
+mod plugins;
+mod predicate;
+mod proof_cache;
+mod proofs;
+mod prove_stream;
+mod provenance;
 mod prover;
+mod quantization;
+mod queue;
+mod quotas;
+mod raw_input;
+mod registrants;
+mod replay;
+mod request_id;
+mod retention;
+mod sandbox;
+mod schedules;
+mod secrets;
+mod selftest;
+mod setup;
+mod shadow;
+mod sharding;
+mod signing;
+mod snapshot;
+mod status;
+mod storage;
+mod tenants;
+mod timeouts;
+mod transparency;
 mod types;
+mod vectors;
 mod verification;
+mod verify_cache;
+mod warm;
+mod warmup;
+mod webhooks;
+mod weights;
+mod witness;
 
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Json, Query, State},
+    http::{HeaderMap, StatusCode},
     routing::{get, post},
     Router,
 };
@@ -21,41 +97,454 @@ use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::prover::JoltAtlasProver;
+use crate::admission::AdmissionControl;
+use crate::aliases::AliasStore;
+use crate::api_keys::{ApiKeyStore, Scope};
+use crate::audit::{AuditAction, AuditLog};
+use crate::auth::Caller;
+use crate::backup::Backups;
+use crate::body_limit::BodyLimits;
+use crate::breaker::BackendUnavailable;
+use crate::challenges::ChallengeStore;
+use crate::checkpoint::CheckpointConfig;
+use crate::cluster::Cluster;
+use crate::commitments::CommitmentStore;
+use crate::config::ServiceConfig;
+use crate::consensus::{ConsensusDivergence, ConsensusUnavailable};
+use crate::encryption::{InputKey, InputKeyInfo};
+use crate::events::EventWatcher;
+use crate::experiments::Experiments;
+use crate::flags::{FeatureFlags, FlagValues};
+use crate::gc::GarbageCollector;
+use crate::ingest::Ingest;
+use crate::isolation::{CpuLimitExceeded, WorkerCrashed};
+use crate::jobs::JobStore;
+use crate::manifest::ReproducibilityManifest;
+use crate::market_data::CoinbaseMarketData;
+use crate::memory::ResourceExhausted;
+use crate::metering::Meter;
+use crate::oracles::OracleRegistry;
+use crate::overview::{ProofLatency, Recent, RecentError};
+use crate::proof_cache::ProofCache;
+use crate::proofs::ProofStore;
+use crate::prover::{
+    normalize_challenge, InvalidInput, JoltAtlasProver, OutputMismatch, StrictModeViolation,
+    UnsupportedSelection,
+};
+use crate::queue::FairQueue;
+use crate::quotas::{QuotaManager, QuotaRemaining};
+use crate::raw_input::ProveBody;
+use crate::registrants::RegistrantPolicy;
+use crate::replay::{InferenceRecord, Recorder};
+use crate::retention::Retention;
+use crate::sandbox::WasmSandbox;
+use crate::schedules::ScheduleStore;
+use crate::secrets::Secrets;
+use crate::selftest::SelfTest;
+use crate::setup::SetupTracker;
+use crate::shadow::Shadow;
+use crate::signing::{prove_response_message, KeyRing, PublicKeyInfo};
+use crate::storage::StorageConfig;
+use crate::tenants::TenantStore;
+use crate::timeouts::RequestTimeouts;
+use crate::transparency::TransparencyLog;
 use crate::types::*;
+use crate::verify_cache::VerifyCache;
+use crate::warmup::{WarmupTracker, MAX_WARMUP_INFERENCES};
+use crate::webhooks::WebhookSender;
+use crate::weights::{WeightProof, WeightsTree};
 
 /// Application state shared across handlers
 struct AppState {
+    config: ServiceConfig,
     prover: RwLock<JoltAtlasProver>,
+    meter: Meter,
+    quotas: QuotaManager,
+    api_keys: ApiKeyStore,
+    signing_keys: KeyRing,
+    input_key: InputKey,
+    registrants: RegistrantPolicy,
+    oracles: OracleRegistry,
+    market_data: CoinbaseMarketData,
+    audit: AuditLog,
+    transparency: TransparencyLog,
+    aliases: AliasStore,
+    commitments: CommitmentStore,
+    challenges: ChallengeStore,
+    gc: GarbageCollector,
+    proofs: ProofStore,
+    webhooks: WebhookSender,
+    schedules: ScheduleStore,
+    queue: FairQueue,
+    admission: AdmissionControl,
+    jobs: JobStore,
+    retention: Retention,
+    proof_cache: Option<ProofCache>,
+    verify_cache: Option<VerifyCache>,
+    setups: SetupTracker,
+    warmups: WarmupTracker,
+    self_test: SelfTest,
+    recorder: Option<Recorder>,
+    errors: Recent<RecentError>,
+    latencies: Recent<ProofLatency>,
+    flags: FeatureFlags,
+    experiments: Experiments,
+    tenants: TenantStore,
+    shadow: Shadow,
+    backups: Backups,
+    cluster: Cluster,
 }
 
 #[tokio::main]
 async fn main() {
+    // Worker processes only prove the job on stdin; stdout carries the result
+    if std::env::args().nth(1).as_deref() == Some(isolation::WORKER_ARG) {
+        std::process::exit(isolation::worker_main().await);
+    }
+
+    // The load generator drives a running service; stdout carries its report
+    if std::env::args().nth(1).as_deref() == Some(loadtest::LOADTEST_ARG) {
+        std::process::exit(loadtest::loadtest_main(std::env::args().skip(2)).await);
+    }
+
+    // The proof inspector decodes a proof offline; stdout carries the inspection
+    if std::env::args().nth(1).as_deref() == Some(inspect::INSPECT_ARG) {
+        std::process::exit(inspect::inspect_main(std::env::args().skip(2)));
+    }
+
+    // So does the test-vector exporter
+    if std::env::args().nth(1).as_deref() == Some(vectors::EXPORT_VECTORS_ARG) {
+        std::process::exit(vectors::export_main(std::env::args().skip(2)).await);
+    }
+
+    if std::env::args().nth(1).as_deref() == Some(bundle::VERIFY_BUNDLE_ARG) {
+        std::process::exit(bundle::verify_bundle_main(std::env::args().skip(2)));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some(offline_audit::AUDIT_ARG) {
+        std::process::exit(offline_audit::audit_main(std::env::args().skip(2)).await);
+    }
+
     // Initialize tracing
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "jolt_atlas_prover_service=debug,tower_http=debug".into()),
+                .unwrap_or_else(|_| "trustless_agentkit_prover=debug,tower_http=debug".into()),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
 
     // Initialize prover
-    let prover = JoltAtlasProver::new().expect("Failed to initialize prover");
+    let mut prover = JoltAtlasProver::new().expect("Failed to initialize prover");
+    let mut config = ServiceConfig::from_env();
+    prover.set_isolation(config.isolation.clone());
+    if config.wasm_sandbox.is_some() && !WasmSandbox::AVAILABLE {
+        panic!("WASM_ENGINE_PATH is set but this build lacks the wasm-sandbox feature");
+    }
+    prover.set_wasm_sandbox(config.wasm_sandbox.clone());
+    prover.set_witness_budget(config.witness_budget.clone());
+    prover.set_memory_budget(config.job_memory_budget);
+    prover.set_hot_models(config.hot_models.clone());
+    prover.set_strict(config.strict_mode);
+    prover.set_gpu(config.gpu);
+    if !config.inference_engine.available() {
+        tracing::warn!(
+            "Inference engine {} is not compiled into this binary",
+            config.inference_engine
+        );
+    }
+    prover.set_inference_engine(config.inference_engine);
+    if let Some(spec) = &config.ab_backend {
+        prover
+            .set_alternate_backend(spec)
+            .expect("Failed to create the AB_BACKEND backend");
+    }
+    prover
+        .set_consensus_backends(&config.consensus_backends)
+        .expect("Failed to create the CONSENSUS_BACKENDS backends");
+    prover.set_breakers(config.breakers.clone());
+    prover
+        .set_fallback_backends(&config.fallback_backends)
+        .expect("Failed to create the FALLBACK_BACKENDS backends");
+    if let Some(spec) = &config.shadow_backend {
+        prover
+            .set_shadow_backend(spec)
+            .expect("Failed to create the SHADOW_BACKEND backend");
+    } else if config.shadow_fraction > 0.0 {
+        tracing::warn!("SHADOW_FRACTION is set without a SHADOW_BACKEND; nothing is shadowed");
+    }
+    if config.strict_mode {
+        if prover.is_mock().await {
+            tracing::error!(
+                "Strict mode is on but this build proves with the mock backend; \
+                 every /prove will be refused"
+            );
+        } else {
+            tracing::info!("Strict mode: mock inference and mock proofs are refused");
+        }
+    }
+    if config.demo_mode && !config.verify_only {
+        demo::register(&mut prover)
+            .await
+            .expect("Failed to register demo models");
+    }
+    if let Some(interval_secs) = config
+        .checkpoint_interval_secs
+        .filter(|_| !config.verify_only)
+    {
+        let dir = config.data_dir.join("checkpoints");
+        let max_age = std::time::Duration::from_secs(config.checkpoint_max_age_secs);
+        match checkpoint::prune(&dir, max_age) {
+            Ok(0) => {}
+            Ok(pruned) => tracing::info!("Removed {} stale proving checkpoints", pruned),
+            Err(e) => tracing::warn!("Failed to prune proving checkpoints: {}", e),
+        }
+        prover.set_checkpoints(Some(CheckpointConfig { dir, interval_secs }));
+    }
+    let secrets = Arc::new(
+        Secrets::connect(config.vault.clone())
+            .await
+            .expect("Failed to connect to secret store"),
+    );
+    if secrets.is_external() {
+        config.admin_token = secrets
+            .get("admin_token")
+            .await
+            .expect("Failed to load admin token");
+        tokio::spawn(secrets.clone().renew_forever());
+    }
+    let storage = StorageConfig::from_env(&secrets)
+        .await
+        .and_then(|storage| storage.open(&config.data_dir))
+        .expect("Invalid storage configuration");
+    tracing::info!("Artifact storage: {}", storage.name());
+    prover.set_storage(Some(storage.clone()).filter(|storage| storage.is_remote()));
+
+    let api_keys = ApiKeyStore::open(&config.data_dir).expect("Failed to open API key store");
+    let quotas = QuotaManager::open(&config.data_dir, config.anonymous_quota.clone())
+        .expect("Failed to open quota store");
+    let meter = Meter::open(&config.data_dir).expect("Failed to open usage store");
+    let signing_keys = KeyRing::open(
+        &config.data_dir,
+        secrets.clone(),
+        config.signing_key_retire_secs,
+    )
+    .await
+    .expect("Failed to load signing keys");
+    tracing::info!(
+        "Service signing key: {}",
+        signing_keys.active_kid().unwrap_or_default()
+    );
+    let input_key = InputKey::open(&config.data_dir, secrets.clone())
+        .await
+        .expect("Failed to load input encryption key");
+    let registrants = RegistrantPolicy::from_config(&config);
+    let oracles = OracleRegistry::new(&config.oracle_keys);
+    let market_data = CoinbaseMarketData::new(&config.coinbase_exchange_url);
+    let audit = AuditLog::open(&config.data_dir).expect("Registry audit log failed verification");
+    let transparency =
+        TransparencyLog::open(&config.data_dir).expect("Transparency log failed verification");
+    let aliases = AliasStore::open(&config.data_dir).expect("Failed to open alias store");
+    let admission = AdmissionControl::from_config(&config);
+    let commitments = CommitmentStore::new(config.input_commitment_ttl_secs);
+    let challenges = ChallengeStore::new(config.challenge_ttl_secs);
+    let gc = GarbageCollector::new(config.gc_min_age_secs);
+    let proofs = ProofStore::new(storage.clone());
+    let backups = Backups::new(storage.clone(), config.backup_keep);
+    let recorder = config
+        .record_inference
+        .then(|| Recorder::new(storage.clone()));
+    let webhooks = WebhookSender::open(&config.data_dir, config.webhook_max_attempts)
+        .expect("Failed to open webhook dead-letter store");
+    let schedules = ScheduleStore::open(&config.data_dir).expect("Failed to open schedule store");
+    let event_watcher = match (&config.base_rpc_url, &config.event_watchers_file) {
+        (Some(rpc_url), Some(watchers_file)) => Some(
+            EventWatcher::load(
+                rpc_url,
+                watchers_file,
+                &config.data_dir,
+                config.event_confirmations,
+            )
+            .expect("Invalid event watcher configuration"),
+        ),
+        _ => None,
+    };
+    let queue = FairQueue::new(
+        config.prove_concurrency,
+        queue::parse_weights(&config.tenant_weights),
+    );
+    let jobs = JobStore::open(&config.data_dir, config.job_max_attempts)
+        .expect("Failed to open job store");
+    let retention = Retention::from_config(&config);
+    let proof_cache = ProofCache::from_config(&config)
+        .await
+        .expect("Invalid proof cache configuration");
+    if let Some(cache) = &proof_cache {
+        tracing::info!("Caching proofs in {}", cache.backend());
+    }
+    let verify_cache = VerifyCache::from_config(&config);
+    let cluster = Cluster::from_config(&config)
+        .await
+        .expect("Invalid leader election configuration");
+    tracing::info!(
+        "Replica {} (leader election: {})",
+        cluster.replica_id(),
+        cluster.backend()
+    );
+    let ingest = Ingest::from_config(&config).expect("Invalid queue ingestion configuration");
+    if config.immutable_registry {
+        tracing::info!("Registry is immutable (append-only)");
+    }
+    let self_test = SelfTest::new(config.self_test && !config.verify_only);
+    let flags = FeatureFlags::new(FlagValues::from_config(&config));
+    let experiments = Experiments::open(&config.data_dir).expect("Failed to open experiment store");
+    let tenants =
+        TenantStore::open(&config.data_dir).expect("Failed to open tenant defaults store");
+    let shadow = Shadow::new(config.shadow_fraction);
+
     let state = Arc::new(AppState {
+        config,
         prover: RwLock::new(prover),
+        meter,
+        quotas,
+        api_keys,
+        signing_keys,
+        input_key,
+        registrants,
+        oracles,
+        market_data,
+        audit,
+        transparency,
+        aliases,
+        commitments,
+        challenges,
+        gc,
+        proofs,
+        webhooks,
+        schedules,
+        queue,
+        admission,
+        jobs,
+        retention,
+        proof_cache,
+        verify_cache,
+        setups: SetupTracker::new(),
+        warmups: WarmupTracker::new(),
+        self_test,
+        recorder,
+        errors: Recent::new(),
+        latencies: Recent::new(),
+        flags,
+        experiments,
+        tenants,
+        shadow,
+        backups,
+        cluster,
     });
 
+    tokio::spawn(metering::run_forever(state.clone()));
+
+    // Verification nodes run no other background duties
+    if !state.config.verify_only {
+        tokio::spawn(cluster::run_forever(state.clone()));
+
+        if let Some(max_age) = state.config.signing_key_rotation_secs {
+            tokio::spawn(rotate_signing_keys(state.clone(), max_age));
+        }
+        if let Some(interval) = state.config.gc_interval_secs {
+            tokio::spawn(gc::run_forever(state.clone(), interval));
+        }
+        if let Some(interval) = state.config.retention_interval_secs {
+            tokio::spawn(retention::run_forever(state.clone(), interval));
+        }
+        if let Some(interval) = state.config.backup_interval_secs {
+            tokio::spawn(backup::run_forever(state.clone(), interval));
+        }
+        if state.config.self_test {
+            tokio::spawn(selftest::run(state.clone()));
+        }
+        tokio::spawn(schedules::run_forever(state.clone()));
+        tokio::spawn(jobs::run_forever(state.clone()));
+        if let Some(event_watcher) = event_watcher {
+            tokio::spawn(event_watcher.run(state.clone()));
+        }
+        if let Some(ingest) = ingest {
+            tokio::spawn(ingest.run(state.clone()));
+        }
+    }
+
     // Build router
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .route("/prove", post(generate_proof))
-        .route("/verify", post(verify_proof))
-        .route("/models", post(register_model))
-        .route("/models/:id/commitment", get(get_model_commitment))
-        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let body_limits = BodyLimits::from_config(&state.config);
+    let timeouts = RequestTimeouts::from_config(&state.config);
+    let app = if state.config.verify_only {
+        tracing::info!("Verification-only node: proving and registration are disabled");
+        Router::new()
+            .route("/health", get(health_check))
+            .route("/ready", get(selftest::ready))
+            .route("/verify", post(verify_proof))
+            .route("/verify/aggregate", post(verify_aggregate))
+            .route("/verify/proof", post(verify_embedded_proof))
+            .route("/proofs/inspect", post(proofs::inspect))
+    } else {
+        Router::new()
+            .route("/health", get(health_check))
+            .route("/ready", get(selftest::ready))
+            .route("/status", get(status::status_page))
+            .route("/demo", get(demo::list))
+            .route("/keys", get(list_signing_keys))
+            .route("/keys/encryption", get(get_encryption_key))
+            .route("/capabilities", get(get_capabilities))
+            .route("/challenge", get(challenges::issue_challenge))
+            .route("/prove", post(generate_proof))
+            .route("/prove/stream", post(prove_stream::prove_stream))
+            .route("/verify", post(verify_proof))
+            .route("/verify/aggregate", post(verify_aggregate))
+            .route("/verify/proof", post(verify_embedded_proof))
+            .route("/replay/:proof_id", post(replay::replay))
+            .route("/models", post(register_model))
+            .route("/models/:id", get(get_model_detail))
+            .route("/models/:id/commitment", get(get_model_commitment))
+            .route("/models/:id/provenance", get(get_model_provenance))
+            .route("/models/:id/weights", get(get_model_weights))
+            .route("/models/:id/weights/proof", get(get_weight_proof))
+            .route(
+                "/models/:id/setup",
+                post(setup::start_setup).get(setup::get_setup),
+            )
+            .nest("/aliases", aliases::routes())
+            .nest("/batches", batches::routes())
+            .nest("/commitments", commitments::routes())
+            .nest("/jobs", jobs::routes())
+            .nest("/log", transparency::routes())
+            .nest("/proofs", proofs::routes())
+            .nest("/schedules", schedules::routes())
+            .nest("/admin", admin::routes())
+    }
+    // Limits are enforced by `body_limit` instead of each extractor
+    .layer(DefaultBodyLimit::disable())
+    .layer(axum::middleware::from_fn(msgpack::negotiate))
+    .layer(axum::middleware::from_fn_with_state(
+        body_limits,
+        body_limit::enforce,
+    ))
+    .layer(axum::middleware::from_fn_with_state(
+        timeouts,
+        timeouts::enforce,
+    ))
+    .layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        admission::enforce,
+    ))
+    .layer(
+        CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers(Any),
+    )
+    .layer(TraceLayer::new_for_http())
+    .layer(axum::middleware::from_fn(request_id::propagate))
+    .with_state(state);
 
     let addr = std::env::var("PROVER_ADDR").unwrap_or_else(|_| "0.0.0.0:3001".to_string());
     tracing::info!("Starting Jolt Atlas Prover Service on {}", addr);
@@ -65,31 +554,335 @@ async fn main() {
 }
 
 /// Health check endpoint
-async fn health_check() -> Json<HealthResponse> {
+async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "healthy".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         prover: "jolt-atlas".to_string(),
+        flags: state.flags.get(),
     })
 }
 
+/// Public signing keys for verifying service signatures
+async fn list_signing_keys(State(state): State<Arc<AppState>>) -> Json<Vec<PublicKeyInfo>> {
+    Json(state.signing_keys.public_keys())
+}
+
+/// Public key for sealing prover inputs
+async fn get_encryption_key(State(state): State<Arc<AppState>>) -> Json<InputKeyInfo> {
+    Json(state.input_key.public_key_info())
+}
+
+/// Configured backends and what each supports, for selecting one per request
+async fn get_capabilities(State(state): State<Arc<AppState>>) -> Json<Vec<BackendCapabilities>> {
+    Json(state.prover.read().await.capabilities().await)
+}
+
+/// Background task rotating the signing key once it reaches `max_age` seconds
+///
+/// Only the leader rotates the ring the replicas share; the others reload it
+/// to pick up the keys the leader rotated in.
+async fn rotate_signing_keys(state: Arc<AppState>, max_age: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        if !state.cluster.is_leader() {
+            if let Err(e) = state.signing_keys.reload().await {
+                tracing::warn!("Failed to reload signing keys: {}", e);
+            }
+            continue;
+        }
+        if let Err(e) = state.signing_keys.rotate_if_due(max_age).await {
+            tracing::error!("Scheduled signing key rotation failed: {}", e);
+        }
+    }
+}
+
 /// Generate a zkML proof for model inference
+///
+/// Takes a JSON `ProveRequest` or raw f32 features (see `raw_input`).
 async fn generate_proof(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ProveRequest>,
-) -> Result<Json<ProveResponse>, (StatusCode, Json<ErrorResponse>)> {
+    caller: Caller,
+    headers: HeaderMap,
+    ProveBody(mut request): ProveBody,
+) -> Result<(HeaderMap, Json<ProveResponse>), (StatusCode, Json<ErrorResponse>)> {
+    caller.require(Scope::Prove)?;
+    // Tenant defaults change the response, so they are part of the cache key
+    state.tenants.apply(&caller.tenant, &mut request);
+
+    // Repeated requests are answered from the proof cache, if enabled; a
+    // nonce proves only once, so requests carrying one never are
+    let Some(cache) = state
+        .proof_cache
+        .as_ref()
+        .filter(|_| request.nonce.is_none())
+    else {
+        let (remaining, response) = prove_with_defaults(&state, &caller, request).await?;
+        return Ok((remaining.headers(), Json(response)));
+    };
+    let idempotency_key = headers
+        .get(jobs::IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let key = ProofCache::key(&caller, idempotency_key, &request);
+    if let Some(response) = cache.get(&key).await {
+        let remaining = proof_cache::charge_hit(&state.quotas, &state.meter, &caller, &response)
+            .inspect_err(|_| tracing::warn!("Quota rejected cached proof for {}", caller.key_id))?;
+        let mut headers = remaining.headers();
+        headers.insert(proof_cache::CACHE_STATUS_HEADER, "hit".parse().unwrap());
+        return Ok((headers, Json(response)));
+    }
+    let (remaining, response) = prove_with_defaults(&state, &caller, request).await?;
+    cache.put(&key, &response).await;
+    let mut headers = remaining.headers();
+    headers.insert(proof_cache::CACHE_STATUS_HEADER, "miss".parse().unwrap());
+    Ok((headers, Json(response)))
+}
+
+/// Run a proof request end to end on behalf of `caller`
+///
+/// Shared by `POST /prove` and scheduled jobs: prepares the inputs, charges
+/// the caller's quota, proves, and signs the response.
+async fn execute_proof(
+    state: &AppState,
+    caller: &Caller,
+    mut request: ProveRequest,
+) -> Result<(QuotaRemaining, ProveResponse), ApiError> {
+    state.tenants.apply(&caller.tenant, &mut request);
+    prove_with_defaults(state, caller, request).await
+}
+
+/// `execute_proof` for a request the caller's tenant defaults were already
+/// applied to
+async fn prove_with_defaults(
+    state: &AppState,
+    caller: &Caller,
+    mut request: ProveRequest,
+) -> Result<(QuotaRemaining, ProveResponse), ApiError> {
+    state.flags.check_intake()?;
+    let received = std::time::Instant::now();
+    let commitment_scheme = request
+        .commitment_scheme
+        .unwrap_or(CommitmentScheme::Sha256V2);
+    tenants::check_scheme(commitment_scheme).map_err(|e| {
+        api_error(
+            StatusCode::BAD_REQUEST,
+            "UNSUPPORTED_COMMITMENT_SCHEME",
+            e.to_string(),
+        )
+    })?;
     tracing::info!(
-        "Generating proof for model: {}, inputs: {} features",
+        "Generating proof for model: {}, inputs: {}",
         request.model_id,
-        request.inputs.len()
+        match request.encrypted_inputs {
+            Some(_) => "sealed".to_string(),
+            None => format!("{} features", request.inputs.len()),
+        }
     );
 
+    // Sealed inputs are decrypted in memory only, right before proving
+    let inputs_sealed = request.encrypted_inputs.is_some();
+    if let Some(sealed) = request.encrypted_inputs.take() {
+        if !request.inputs.is_empty() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_ENCRYPTED_INPUTS",
+                "Send either inputs or encrypted_inputs, not both",
+            ));
+        }
+        request.inputs = state.input_key.open_inputs(&sealed).map_err(|e| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_ENCRYPTED_INPUTS",
+                e.to_string(),
+            )
+        })?;
+    }
+
+    // Market-data inputs are fetched server-side so the client cannot alter them
+    let mut market_data = None;
+    if let Some(source) = request.market_data.take() {
+        if !request.inputs.is_empty() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_MARKET_DATA_REQUEST",
+                "Send either inputs or market_data, not both",
+            ));
+        }
+        let window = source.window().map_err(|e| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_MARKET_DATA_REQUEST",
+                e.to_string(),
+            )
+        })?;
+        let (inputs, record) = state
+            .market_data
+            .fetch(&source, window)
+            .await
+            .map_err(|e| {
+                api_error(
+                    StatusCode::BAD_GATEWAY,
+                    "MARKET_DATA_UNAVAILABLE",
+                    e.to_string(),
+                )
+            })?;
+        request.inputs = inputs;
+        market_data = Some(record);
+    }
+
+    // Raw data is turned into features by the model's preprocessing plugin
+    let mut raw_input_hash = None;
+    let mut rollout = None;
+    if let Some(raw) = request.raw.take() {
+        if !request.inputs.is_empty() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_RAW_INPUT",
+                "Send either inputs or raw, not both",
+            ));
+        }
+        // The model is picked here, so the features come from the plugin of
+        // the same side of a traffic split that is proven
+        let prover = state.prover.read().await;
+        if let Some((model_id, tag)) = state.aliases.route(&request.model_id, &prover) {
+            request.model_id = model_id;
+            rollout = tag;
+        }
+        request.inputs = prover
+            .run_preprocessor(&request.model_id, &raw)
+            .map_err(|e| {
+                api_error(
+                    StatusCode::BAD_REQUEST,
+                    "PREPROCESSING_FAILED",
+                    e.to_string(),
+                )
+            })?;
+        raw_input_hash = Some(plugins::raw_input_hash(&raw));
+    }
+
+    // Non-finite inputs usually mean a broken feature pipeline
+    if !request.allow_non_finite {
+        if let Some(i) = request.inputs.iter().position(|v| !v.is_finite()) {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "NON_FINITE_INPUT",
+                format!(
+                    "Input {} is {}; set allow_non_finite to prove it anyway",
+                    i, request.inputs[i]
+                ),
+            ));
+        }
+    }
+
+    if let Some(nonce) = request.nonce.take() {
+        if request.challenge.is_some() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_CHALLENGE",
+                "Send either challenge or nonce, not both",
+            ));
+        }
+        request.challenge = Some(state.challenges.redeem(&nonce)?);
+    }
+    if let Some(challenge) = &request.challenge {
+        normalize_challenge(challenge)
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_CHALLENGE", e.to_string()))?;
+    }
+
+    let oracle = request
+        .oracle_signature
+        .as_ref()
+        .map(|signature| state.oracles.attest(&request.inputs, signature))
+        .transpose()?;
+
+    let input_commitment = match &request.commitment_id {
+        Some(commitment_id) => Some(state.commitments.open(
+            &caller.key_id,
+            commitment_id,
+            request.salt.as_deref().unwrap_or_default(),
+            &request.inputs,
+        )?),
+        None => None,
+    };
+
+    let validation_ms = received.elapsed().as_millis() as u64;
+
+    // Returned to the quota unless committed below, also when this future is
+    // dropped by a timeout or a disconnecting client
+    let reservation = state.quotas.reserve(&caller.key_id).map_err(|e| {
+        tracing::warn!("Quota rejected {}: {}", caller.key_id, e);
+        ApiError::from(e)
+    })?;
+
+    // Resolve aliases up front so the proof records a concrete model, and
+    // is queued within that model's limits
+    let limits = {
+        let prover = state.prover.read().await;
+        if let Some((model_id, tag)) = state.aliases.route(&request.model_id, &prover) {
+            if model_id != request.model_id {
+                tracing::info!("Resolved {} to model {}", request.model_id, model_id);
+                request.model_id = model_id;
+            }
+            rollout = rollout.or(tag);
+        }
+        prover
+            .get_model(&request.model_id)
+            .map(|model| model.limits)
+            .unwrap_or_default()
+    };
+
+    // Wait for a proving slot; tenants are served fairly under load
+    let permit = state
+        .queue
+        .acquire_model(&caller.tenant, &request.model_id, limits)
+        .await
+        .map_err(ApiError::from)?;
+
     let start = std::time::Instant::now();
 
     let prover = state.prover.read().await;
+    permit.set_job(&request.model_id);
+    if let Some(archived_at) = prover
+        .get_model(&request.model_id)
+        .and_then(|m| m.archived_at)
+    {
+        return Err(api_error(
+            StatusCode::GONE,
+            "MODEL_ARCHIVED",
+            format!(
+                "Model {} was archived at {} and no longer serves proofs",
+                request.model_id, archived_at
+            ),
+        ));
+    }
 
-    match prover.generate_proof(&request).await {
-        Ok(proof_result) => {
+    let result = if request.consensus {
+        consensus::prove(&prover, &request).await
+    } else {
+        experiments::prove(state, &prover, &request).await
+    };
+    match result {
+        Ok(mut proof_result) => {
+            state.shadow.sample(&prover, &request, &proof_result);
+            proof_result.public_inputs.market_data = market_data;
+            proof_result.public_inputs.raw_input_hash = raw_input_hash;
+            proof_result.public_inputs.oracle = oracle;
+            proof_result.public_inputs.input_commitment = input_commitment;
+            proof_result.public_inputs.rollout = rollout;
+            let circuit_commitment = prover
+                .get_model(&request.model_id)
+                .map(|model| model.circuit_commitment.clone())
+                .unwrap_or_default();
+            if let Err(e) =
+                tenants::present(&mut proof_result, commitment_scheme, &circuit_commitment)
+            {
+                return Err(api_error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "UNSUPPORTED_COMMITMENT_SCHEME",
+                    e.to_string(),
+                ));
+            }
             let elapsed = start.elapsed();
             tracing::info!(
                 "Proof generated in {:?}, size: {} bytes",
@@ -97,24 +890,138 @@ async fn generate_proof(
                 proof_result.proof.len()
             );
 
-            Ok(Json(ProveResponse {
+            let proof_id = proofs::proof_id(&proof_result.proof);
+
+            // A response the service did not sign could not be attributed to
+            // it, so it is not issued
+            let signature = prove_response_message(&proof_id, &proof_result.public_inputs)
+                .and_then(|message| state.signing_keys.sign(&message))
+                .map_err(|e| {
+                    tracing::error!("Failed to sign proof response: {}", e);
+                    api_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "SIGNING_FAILED",
+                        e.to_string(),
+                    )
+                })?;
+
+            // A proof missing from the transparency log could not be audited,
+            // so it is not issued
+            let log_entry = serde_json::to_value(&proof_result.public_inputs)
+                .map_err(anyhow::Error::from)
+                .and_then(|public_inputs| state.transparency.append(&proof_id, public_inputs))
+                .map_err(|e| {
+                    tracing::error!("Failed to log proof {}: {}", proof_id, e);
+                    api_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "TRANSPARENCY_LOG_FAILED",
+                        e.to_string(),
+                    )
+                })?;
+            state
+                .meter
+                .record_proof(caller, elapsed, proof_result.proof.len());
+            state.meter.record_model_proof(&request.model_id, elapsed);
+            if let Some(cpu_time_ms) = proof_result.cpu_time_ms {
+                state
+                    .meter
+                    .record_cpu_time(caller, std::time::Duration::from_millis(cpu_time_ms));
+            }
+            state
+                .latencies
+                .record(&request.model_id, elapsed.as_millis() as u64);
+            let remaining = reservation.commit(elapsed);
+
+            let log_inclusion = state
+                .transparency
+                .entry_inclusion(&log_entry, &state.signing_keys)
+                .map_err(|e| tracing::error!("Failed to prove {} logged: {}", proof_id, e))
+                .ok();
+
+            let manifest = match verification::decode_proof(&proof_result.proof) {
+                Some(proof) => Some(ReproducibilityManifest::new(
+                    &proof,
+                    prover.circuit_version_for(&proof).await,
+                    prover.inference_engine(),
+                    &prover
+                        .get_model(&request.model_id)
+                        .map(|model| model.commitment.clone())
+                        .unwrap_or_default(),
+                    &proof_result.public_inputs,
+                )),
+                None => None,
+            };
+
+            let response = ProveResponse {
                 success: true,
+                model_id: request.model_id,
+                proof_id,
                 proof: proof_result.proof,
                 model_commitment: proof_result.model_commitment,
                 input_hash: proof_result.input_hash,
                 output_hash: proof_result.output_hash,
                 public_inputs: proof_result.public_inputs,
                 proving_time_ms: elapsed.as_millis() as u64,
+                cpu_time_ms: proof_result.cpu_time_ms,
+                timings: proof_result.timings.map(|timings| StageTimings {
+                    validation_ms,
+                    ..timings
+                }),
+                signature: Some(signature),
+                log_index: Some(log_entry.index),
+                log_inclusion,
+                fallback: proof_result.fallback,
+                manifest,
                 error: None,
-            }))
+            };
+
+            // Sealed inputs must never be persisted in plaintext
+            if let Some(recorder) = state.recorder.as_ref().filter(|_| !inputs_sealed) {
+                let backend = prover.get_prover_info().await;
+                let record = InferenceRecord::new(caller, request.inputs, &response, backend);
+                if let Err(e) = recorder.put(&record).await {
+                    tracing::warn!("Failed to record inference of {}: {}", record.proof_id, e);
+                }
+            }
+
+            Ok((remaining, response))
         }
         Err(e) => {
             tracing::error!("Proof generation failed: {}", e);
+            let (status, code) = if e.is::<OutputMismatch>() {
+                (StatusCode::UNPROCESSABLE_ENTITY, "OUTPUT_MISMATCH")
+            } else if e.is::<InvalidInput>() {
+                (StatusCode::BAD_REQUEST, "INVALID_INPUT")
+            } else if e.is::<StrictModeViolation>() {
+                (StatusCode::SERVICE_UNAVAILABLE, "MOCK_INFERENCE_REFUSED")
+            } else if e.is::<ConsensusUnavailable>() {
+                (StatusCode::BAD_REQUEST, "CONSENSUS_UNAVAILABLE")
+            } else if e.is::<ConsensusDivergence>() {
+                (StatusCode::BAD_GATEWAY, "CONSENSUS_DIVERGENCE")
+            } else if e.is::<UnsupportedSelection>() {
+                (StatusCode::BAD_REQUEST, "UNSUPPORTED_BACKEND")
+            } else if e.is::<BackendUnavailable>() {
+                (StatusCode::SERVICE_UNAVAILABLE, "BACKEND_UNAVAILABLE")
+            } else if e.is::<WorkerCrashed>() {
+                (StatusCode::INTERNAL_SERVER_ERROR, "PROVER_CRASHED")
+            } else if e.is::<ResourceExhausted>() {
+                (StatusCode::UNPROCESSABLE_ENTITY, "RESOURCE_EXHAUSTED")
+            } else if e.is::<CpuLimitExceeded>() {
+                (StatusCode::UNPROCESSABLE_ENTITY, "CPU_LIMIT_EXCEEDED")
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, "PROOF_GENERATION_FAILED")
+            };
+            state.errors.record(
+                code,
+                &e.to_string(),
+                Some(&request.model_id),
+                &caller.tenant,
+            );
             Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(ErrorResponse {
                     error: e.to_string(),
-                    code: "PROOF_GENERATION_FAILED".to_string(),
+                    code: code.to_string(),
                 }),
             ))
         }
@@ -124,58 +1031,286 @@ async fn generate_proof(
 /// Verify a zkML proof
 async fn verify_proof(
     State(state): State<Arc<AppState>>,
+    caller: Caller,
+    headers: HeaderMap,
     Json(request): Json<VerifyRequest>,
-) -> Result<Json<VerifyResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(HeaderMap, Json<VerifyResponse>), (StatusCode, Json<ErrorResponse>)> {
     tracing::info!("Verifying proof for model: {}", request.model_commitment);
+    caller.require(Scope::Verify)?;
 
     let start = std::time::Instant::now();
-    let prover = state.prover.read().await;
 
+    // Repeated checks of the same proof are answered from the cache
+    let mut response_headers = HeaderMap::new();
+    let cache_key = state.verify_cache.as_ref().map(|cache| {
+        let key = VerifyCache::key(&request, !state.flags.get().accept_mock_proofs);
+        (cache, key)
+    });
+    if let Some((cache, key)) = &cache_key {
+        if let Some(valid) = cache.get(key).filter(|_| !VerifyCache::bypassed(&headers)) {
+            state.meter.record_verification(&caller);
+            response_headers.insert(verify_cache::CACHE_STATUS_HEADER, "hit".parse().unwrap());
+            return Ok((
+                response_headers,
+                Json(VerifyResponse {
+                    valid,
+                    verification_time_ms: start.elapsed().as_millis() as u64,
+                    error: None,
+                    receipt: verification_receipt(&state, &request, valid),
+                }),
+            ));
+        }
+        response_headers.insert(verify_cache::CACHE_STATUS_HEADER, "miss".parse().unwrap());
+    }
+
+    let prover = state.prover.read().await;
     match prover.verify_proof(&request).await {
         Ok(valid) => {
             let elapsed = start.elapsed();
             tracing::info!("Proof verification: {}, took {:?}", valid, elapsed);
+            state.meter.record_verification(&caller);
+            if let Some((cache, key)) = cache_key {
+                cache.put(key, valid);
+            }
 
-            Ok(Json(VerifyResponse {
-                valid,
-                verification_time_ms: elapsed.as_millis() as u64,
-                error: None,
-            }))
+            Ok((
+                response_headers,
+                Json(VerifyResponse {
+                    valid,
+                    verification_time_ms: elapsed.as_millis() as u64,
+                    error: None,
+                    receipt: verification_receipt(&state, &request, valid),
+                }),
+            ))
         }
         Err(e) => {
             tracing::error!("Proof verification failed: {}", e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                    code: "VERIFICATION_FAILED".to_string(),
-                }),
-            ))
+            Err(verification_error(e))
         }
     }
 }
 
+/// Signed receipt of a `/verify` verdict, if the request asked for one
+fn verification_receipt(
+    state: &AppState,
+    request: &VerifyRequest,
+    valid: bool,
+) -> Option<VerificationReceipt> {
+    if !request.receipt {
+        return None;
+    }
+    state
+        .signing_keys
+        .receipt(&proofs::proof_id(&request.proof), valid)
+        .map_err(|e| tracing::error!("Failed to sign verification receipt: {}", e))
+        .ok()
+}
+
+/// Verify a batch of proofs, reporting each instance and the overall result
+///
+/// A proof that fails to verify fails its instance, not the batch.
+async fn verify_aggregate(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Json(request): Json<AggregateVerifyRequest>,
+) -> Result<Json<AggregateVerifyResponse>, ApiError> {
+    caller.require(Scope::Verify)?;
+    if request.instances.is_empty() || request.instances.len() > MAX_AGGREGATE_INSTANCES {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REQUEST",
+            format!(
+                "A batch holds between 1 and {} proofs",
+                MAX_AGGREGATE_INSTANCES
+            ),
+        ));
+    }
+    tracing::info!("Verifying batch of {} proofs", request.instances.len());
+
+    let start = std::time::Instant::now();
+    let prover = state.prover.read().await;
+    let mut instances = Vec::with_capacity(request.instances.len());
+    for (index, instance) in request.instances.iter().enumerate() {
+        let outcome = prover.verify_proof(instance).await;
+        state.meter.record_verification(&caller);
+        instances.push(match outcome {
+            Ok(valid) => InstanceVerification {
+                index,
+                valid,
+                code: None,
+                error: None,
+            },
+            Err(e) => {
+                let (_, Json(error)) = verification_error(e);
+                InstanceVerification {
+                    index,
+                    valid: false,
+                    code: Some(error.code),
+                    error: Some(error.error),
+                }
+            }
+        });
+    }
+    let verified = instances.iter().filter(|i| i.valid).count();
+    let elapsed = start.elapsed();
+    tracing::info!(
+        "Batch verification: {}/{} valid, took {:?}",
+        verified,
+        instances.len(),
+        elapsed
+    );
+
+    Ok(Json(AggregateVerifyResponse {
+        valid: verified == instances.len(),
+        verified,
+        failed: instances.len() - verified,
+        verification_time_ms: elapsed.as_millis() as u64,
+        instances,
+    }))
+}
+
+/// Verify a proof on its own and return the public inputs it proves
+async fn verify_embedded_proof(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Json(request): Json<VerifyProofRequest>,
+) -> Result<Json<VerifyProofResponse>, ApiError> {
+    caller.require(Scope::Verify)?;
+
+    let start = std::time::Instant::now();
+    let prover = state.prover.read().await;
+    let public_inputs = prover
+        .verify_embedded(&request.proof, request.challenge.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::error!("Proof verification failed: {}", e);
+            verification_error(e)
+        })?;
+    let elapsed = start.elapsed();
+    tracing::info!(
+        "Self-contained proof verification: {}, took {:?}",
+        public_inputs.is_some(),
+        elapsed
+    );
+    state.meter.record_verification(&caller);
+
+    Ok(Json(VerifyProofResponse {
+        valid: public_inputs.is_some(),
+        verification_time_ms: elapsed.as_millis() as u64,
+        public_inputs,
+    }))
+}
+
+fn verification_error(e: anyhow::Error) -> ApiError {
+    let code = if e.is::<StrictModeViolation>() {
+        "MOCK_PROOF_REJECTED"
+    } else {
+        "VERIFICATION_FAILED"
+    };
+    api_error(StatusCode::BAD_REQUEST, code, e.to_string())
+}
+
 /// Register an ONNX model for proving
 async fn register_model(
     State(state): State<Arc<AppState>>,
+    caller: Caller,
     Json(request): Json<RegisterModelRequest>,
 ) -> Result<Json<RegisterModelResponse>, (StatusCode, Json<ErrorResponse>)> {
     tracing::info!("Registering model: {}", request.name);
+    caller.require(Scope::Register)?;
+    state.flags.check_registration()?;
+    let warmup = request
+        .warmup
+        .unwrap_or(state.config.model_warmup_inferences);
+    if warmup > MAX_WARMUP_INFERENCES {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_WARMUP",
+            format!(
+                "At most {} warm-up inferences can be requested",
+                MAX_WARMUP_INFERENCES
+            ),
+        ));
+    }
+
+    // Check the uploader's signature before taking the registry lock
+    let registrant = match &request.registrant {
+        Some(signature) => {
+            let model_bytes = request.decode_model_bytes().map_err(|e| {
+                api_error(
+                    StatusCode::BAD_REQUEST,
+                    "MODEL_REGISTRATION_FAILED",
+                    e.to_string(),
+                )
+            })?;
+            state.registrants.authorize(&model_bytes, Some(signature))?
+        }
+        None => state.registrants.authorize(&[], None)?,
+    };
 
     let mut prover = state.prover.write().await;
 
-    match prover.register_model(&request).await {
+    if state.config.immutable_registry {
+        if let Some(existing) = state.audit.model_for_name(&request.name) {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "MODEL_NAME_TAKEN",
+                format!(
+                    "Model name {} is already registered as {} and the registry is immutable",
+                    request.name, existing
+                ),
+            ));
+        }
+    }
+
+    match prover.register_model(&request, registrant).await {
         Ok(model_info) => {
+            let details = serde_json::json!({
+                "name": model_info.name,
+                "commitment": model_info.commitment,
+                "circuit_commitment": model_info.circuit_commitment,
+                "weights_root": model_info.weights_root,
+                "registrant": model_info.registrant.as_ref().map(|r| &r.key_id),
+            });
+            if let Err(e) = state.audit.append(
+                AuditAction::Register,
+                &model_info.id,
+                &caller.key_id,
+                details,
+            ) {
+                tracing::error!(
+                    "Failed to audit-log registration of {}: {}",
+                    model_info.id,
+                    e
+                );
+                prover.rollback_registration(&model_info.id);
+                return Err(api_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "AUDIT_LOG_FAILED",
+                    "Registration could not be recorded in the audit log",
+                ));
+            }
+
             tracing::info!(
-                "Model registered: {} with commitment {}",
+                "Model registered: {} with commitment {} ({})",
                 model_info.id,
-                model_info.commitment
+                model_info.commitment,
+                onnx::CANONICALIZATION_VERSION
             );
+            if warmup > 0 {
+                warmup::start(&state, &model_info.id, warmup);
+            }
 
             Ok(Json(RegisterModelResponse {
                 success: true,
                 model_id: model_info.id,
                 commitment: model_info.commitment,
+                circuit_commitment: model_info.circuit_commitment,
+                registrant: model_info.registrant.map(|r| r.key_id),
+                extended_commitment: model_info.provenance.map(|p| p.extended_commitment),
+                execution: model_info.execution,
+                shards: model_info.shards,
+                hot: model_info.hot,
                 error: None,
             }))
         }
@@ -199,10 +1334,22 @@ async fn get_model_commitment(
 ) -> Result<Json<ModelCommitmentResponse>, (StatusCode, Json<ErrorResponse>)> {
     let prover = state.prover.read().await;
 
-    match prover.get_model_commitment(&model_id) {
-        Some(commitment) => Ok(Json(ModelCommitmentResponse {
+    match prover.get_model(&model_id) {
+        Some(model_info) => Ok(Json(ModelCommitmentResponse {
+            circuit_hash: prover.circuit_hash(model_info).await.map_err(|e| {
+                api_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "CIRCUIT_HASH_FAILED",
+                    e.to_string(),
+                )
+            })?,
+            commitment: model_info.commitment.clone(),
+            circuit_commitment: model_info.circuit_commitment.clone(),
+            weights_root: model_info.weights_root.clone(),
+            archived_at: model_info.archived_at,
+            quantization: model_info.quantization.clone(),
+            preprocessor: model_info.preprocessor.clone(),
             model_id,
-            commitment,
         })),
         None => Err((
             StatusCode::NOT_FOUND,
@@ -213,3 +1360,156 @@ async fn get_model_commitment(
         )),
     }
 }
+
+/// Get a model's interface, commitments, setup, lineage and usage in one call
+async fn get_model_detail(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(model_id): axum::extract::Path<String>,
+) -> Result<Json<ModelDetailResponse>, ApiError> {
+    let prover = state.prover.read().await;
+    let model_info = prover
+        .get_model(&model_id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "MODEL_NOT_FOUND", "Model not found"))?;
+    let circuit_hash = prover.circuit_hash(model_info).await.map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "CIRCUIT_HASH_FAILED",
+            e.to_string(),
+        )
+    })?;
+    let model_bytes = tokio::fs::read(&model_info.path).await.map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "MODEL_READ_FAILED",
+            e.to_string(),
+        )
+    })?;
+    let interface = onnx::interface(&model_bytes).ok();
+
+    let versions = prover
+        .models()
+        .filter(|m| m.name == model_info.name)
+        .map(|m| ModelVersion {
+            model_id: m.id.clone(),
+            commitment: m.commitment.clone(),
+            archived_at: m.archived_at,
+        })
+        .collect();
+    let mut aliases: Vec<String> = state
+        .aliases
+        .list()
+        .into_iter()
+        .filter(|record| {
+            record.model_id == model_id
+                || record
+                    .split
+                    .as_ref()
+                    .is_some_and(|split| split.model_id == model_id)
+        })
+        .map(|record| record.alias)
+        .collect();
+    aliases.sort();
+
+    Ok(Json(ModelDetailResponse {
+        name: model_info.name.clone(),
+        description: model_info.description.clone(),
+        commitment: model_info.commitment.clone(),
+        circuit_commitment: model_info.circuit_commitment.clone(),
+        weights_root: model_info.weights_root.clone(),
+        circuit_hash,
+        size_bytes: model_bytes.len() as u64,
+        inputs: interface.as_ref().map(|(inputs, _)| inputs.clone()),
+        outputs: interface.map(|(_, outputs)| outputs),
+        backend: prover.backend_id().await,
+        execution: model_info.execution,
+        shards: model_info.shards,
+        hot: model_info.hot,
+        pinned: model_info.pinned,
+        quantization: model_info.quantization.clone(),
+        preprocessor: model_info.preprocessor.clone(),
+        archived_at: model_info.archived_at,
+        setup_status: state.setups.get(&model_id).map(|run| run.status),
+        warmup: state.warmups.get(&model_id),
+        versions,
+        aliases,
+        usage: state.meter.model_usage(&model_id),
+        model_id,
+    }))
+}
+
+/// Get the provenance record bound into a model's extended commitment
+async fn get_model_provenance(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(model_id): axum::extract::Path<String>,
+) -> Result<Json<ModelProvenanceResponse>, ApiError> {
+    let prover = state.prover.read().await;
+    let model_info = prover
+        .get_model(&model_id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "MODEL_NOT_FOUND", "Model not found"))?;
+    let provenance = model_info.provenance.clone().ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            "PROVENANCE_NOT_FOUND",
+            format!("Model {} was registered without provenance", model_id),
+        )
+    })?;
+
+    Ok(Json(ModelProvenanceResponse {
+        commitment: model_info.commitment.clone(),
+        model_id,
+        provenance,
+    }))
+}
+
+/// List the weight tensors committed under a model's weights root
+async fn get_model_weights(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(model_id): axum::extract::Path<String>,
+) -> Result<Json<ModelWeightsResponse>, ApiError> {
+    let prover = state.prover.read().await;
+    let tree = load_weights_tree(&prover, &model_id)?;
+
+    Ok(Json(ModelWeightsResponse {
+        model_id,
+        weights_root: tree.root(),
+        layers: tree.layers(),
+    }))
+}
+
+/// Merkle opening proving a weight tensor is part of the committed model
+async fn get_weight_proof(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(model_id): axum::extract::Path<String>,
+    Query(query): Query<WeightProofQuery>,
+) -> Result<Json<WeightProof>, ApiError> {
+    let prover = state.prover.read().await;
+    let tree = load_weights_tree(&prover, &model_id)?;
+
+    tree.prove(&model_id, &query.layer)
+        .map(Json)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "LAYER_NOT_FOUND",
+                format!("Model {} has no weight tensor {}", model_id, query.layer),
+            )
+        })
+}
+
+fn load_weights_tree(prover: &JoltAtlasProver, model_id: &str) -> Result<WeightsTree, ApiError> {
+    if prover.get_model(model_id).is_none() {
+        return Err(api_error(
+            StatusCode::NOT_FOUND,
+            "MODEL_NOT_FOUND",
+            "Model not found",
+        ));
+    }
+    prover.weights_tree(model_id).map_err(|e| {
+        tracing::error!("Failed to rebuild weights tree for {}: {}", model_id, e);
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "WEIGHTS_UNAVAILABLE",
+            e.to_string(),
+        )
+    })
+}
@@ -0,0 +1,126 @@
+//! Reproducibility manifests
+//!
+//! Every prove response carries a `manifest` recording everything needed to
+//! reproduce its computation outside the service: the service version, the
+//! backend and its circuit version, the inference engine and runtime, the
+//! registered model commitment with the canonicalization rules it was
+//! computed under, the hash scheme of the public inputs, and the
+//! quantization and preprocessing the inputs went through. An auditor
+//! holding the model file re-canonicalizes it, re-runs inference on the
+//! same engine, and recomputes the hashes to compare with the public
+//! inputs byte for byte.
+//!
+//! The manifest describes how the proof was produced; it is not covered by
+//! the response signature, which attests the public inputs. Stored proofs
+//! keep it, and `.zkbundle` exports carry it (see `bundle`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::inference::InferenceEngine;
+use crate::jolt_atlas::{proof_format, JoltAtlasProof};
+use crate::onnx::CANONICALIZATION_VERSION;
+use crate::quantization::Quantization;
+use crate::types::{CommitmentScheme, PublicInputs};
+
+/// Version of the manifest layout
+pub const MANIFEST_VERSION: &str = "repro-manifest-v1";
+
+/// Inference engine name recorded when the configured one was not compiled
+/// in and inference was mocked
+pub const MOCK_ENGINE: &str = "mock";
+
+/// How a proof's computation can be reproduced
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReproducibilityManifest {
+    pub manifest_version: String,
+    /// Version of the prover service
+    pub service_version: String,
+    /// Prover id of the backend that generated the proof
+    pub backend: String,
+    /// Circuit version of that backend
+    pub backend_version: String,
+    pub proof_format: String,
+    /// `ort`, `tract`, or `mock`
+    pub inference_engine: String,
+    /// Runtime the inference engine was built against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inference_runtime: Option<String>,
+    /// Registered commitment of the canonicalized model file
+    pub model_commitment: String,
+    /// Canonicalization rules the model was committed under
+    pub canonicalization_version: String,
+    /// Scheme of the public inputs' commitments and hashes
+    pub hash_scheme: CommitmentScheme,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+    /// Hash of the preprocessing plugin that produced the inputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+}
+
+impl ReproducibilityManifest {
+    /// Manifest of `proof`, made by a backend at `circuit_version` for the
+    /// model registered as `model_commitment`, with inference on `engine`
+    pub fn new(
+        proof: &JoltAtlasProof,
+        circuit_version: String,
+        engine: InferenceEngine,
+        model_commitment: &str,
+        public_inputs: &PublicInputs,
+    ) -> Self {
+        Self {
+            manifest_version: MANIFEST_VERSION.to_string(),
+            service_version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: proof.prover_id.clone(),
+            backend_version: circuit_version,
+            proof_format: proof_format(proof.version),
+            inference_engine: if engine.available() {
+                engine.to_string()
+            } else {
+                MOCK_ENGINE.to_string()
+            },
+            inference_runtime: engine.runtime().map(String::from),
+            model_commitment: model_commitment.to_string(),
+            canonicalization_version: CANONICALIZATION_VERSION.to_string(),
+            hash_scheme: public_inputs.commitment_scheme,
+            quantization: public_inputs.quantization.clone(),
+            preprocessor: public_inputs.preprocessor.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::{create_prover, ModelCommitments};
+
+    #[test]
+    fn test_manifest_records_backend_and_engine() {
+        let prover = create_prover().unwrap();
+        let model = ModelCommitments::compute(b"model");
+        let proof = prover.prove(&model, &[1.0], &[0.5], None).unwrap();
+        let public_inputs: PublicInputs = serde_json::from_value(serde_json::json!({
+            "model_commitment": model.sha256,
+            "input_hash": proof.input_hash,
+            "output_hash": proof.output_hash,
+            "output": [0.5],
+            "timestamp": proof.timestamp
+        }))
+        .unwrap();
+
+        let manifest = ReproducibilityManifest::new(
+            &proof,
+            prover.circuit_version(),
+            InferenceEngine::Tract,
+            &model.sha256,
+            &public_inputs,
+        );
+        assert_eq!(manifest.backend, proof.prover_id);
+        assert_eq!(manifest.proof_format, proof_format(proof.version));
+        assert_eq!(manifest.canonicalization_version, CANONICALIZATION_VERSION);
+        assert_eq!(manifest.hash_scheme, public_inputs.commitment_scheme);
+        let available = InferenceEngine::Tract.available();
+        assert_eq!(manifest.inference_engine == MOCK_ENGINE, !available);
+        assert_eq!(manifest.inference_runtime.is_some(), available);
+    }
+}
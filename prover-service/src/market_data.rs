@@ -0,0 +1,218 @@
+//! Coinbase market-data input source
+//!
+//! Instead of sending features, a `ProveRequest` can name a Coinbase Exchange
+//! product and candle window in `market_data`. The service fetches the
+//! candles itself from the public Exchange API (`COINBASE_EXCHANGE_URL`),
+//! turns them into the feature vector and records the resolved fetch
+//! parameters and a hash of the candle data in `PublicInputs`, so clients
+//! cannot tamper with the data the model ran on.
+//!
+//! Features are `[open, high, low, close, volume]` per candle, oldest first.
+//! The window must be complete: a missing candle fails the request rather
+//! than silently shifting the features.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source identifier recorded in public inputs
+pub const COINBASE_EXCHANGE_SOURCE: &str = "coinbase-exchange";
+
+/// Candle granularities (seconds) the Exchange API supports
+const GRANULARITIES: [u64; 6] = [60, 300, 900, 3600, 21600, 86400];
+
+/// Most candles the Exchange API returns per request
+const MAX_CANDLES: usize = 300;
+
+/// Candle window requested in a `ProveRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MarketDataRequest {
+    /// Exchange product, e.g. `BTC-USD`
+    pub product_id: String,
+
+    /// Candle size in seconds
+    pub granularity: u64,
+
+    /// Number of candles ending at `end`
+    pub candles: usize,
+
+    /// End of the window (unix seconds); the last closed candle when unset
+    #[serde(default)]
+    pub end: Option<u64>,
+}
+
+/// Fetched market data as recorded in `PublicInputs`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarketDataRecord {
+    pub source: String,
+    pub product_id: String,
+    pub granularity: u64,
+    /// Window start (inclusive, unix seconds)
+    pub start: u64,
+    /// Window end (exclusive, unix seconds)
+    pub end: u64,
+    pub candles: usize,
+    /// SHA-256 over the canonical candle rows
+    pub data_hash: String,
+}
+
+/// One candle as returned by the Exchange API
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct Candle(u64, f64, f64, f64, f64, f64);
+
+/// Client for the Coinbase Exchange public API
+pub struct CoinbaseMarketData {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl CoinbaseMarketData {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetch the `(start, end)` window of `request` (see
+    /// [`MarketDataRequest::window`]), returning the features and their record
+    pub async fn fetch(
+        &self,
+        request: &MarketDataRequest,
+        (start, end): (u64, u64),
+    ) -> Result<(Vec<f32>, MarketDataRecord)> {
+        let url = format!("{}/products/{}/candles", self.base_url, request.product_id);
+        let candles: Vec<Candle> = self
+            .http
+            .get(&url)
+            .header("User-Agent", "jolt-atlas-prover")
+            .query(&[
+                ("granularity", request.granularity),
+                ("start", start),
+                // The Exchange API treats `end` as inclusive
+                ("end", end - request.granularity),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        canonicalize(request, start, end, candles)
+    }
+}
+
+impl MarketDataRequest {
+    /// Validate the request and pin its window to candle boundaries
+    pub fn window(&self) -> Result<(u64, u64)> {
+        resolve_window(self)
+    }
+}
+
+fn resolve_window(request: &MarketDataRequest) -> Result<(u64, u64)> {
+    if !GRANULARITIES.contains(&request.granularity) {
+        return Err(anyhow!(
+            "Granularity must be one of {:?} seconds",
+            GRANULARITIES
+        ));
+    }
+    if request.candles == 0 || request.candles > MAX_CANDLES {
+        return Err(anyhow!(
+            "Candle count must be between 1 and {}",
+            MAX_CANDLES
+        ));
+    }
+    let valid_product = !request.product_id.is_empty()
+        && request
+            .product_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid_product {
+        return Err(anyhow!("Invalid product id {}", request.product_id));
+    }
+
+    let end = request.end.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    });
+    let end = end - end % request.granularity;
+    let start = end
+        .checked_sub(request.granularity * request.candles as u64)
+        .ok_or_else(|| anyhow!("Window starts before the epoch"))?;
+    Ok((start, end))
+}
+
+/// Order the candles, check the window is complete and derive the features
+fn canonicalize(
+    request: &MarketDataRequest,
+    start: u64,
+    end: u64,
+    mut candles: Vec<Candle>,
+) -> Result<(Vec<f32>, MarketDataRecord)> {
+    candles.retain(|c| c.0 >= start && c.0 < end);
+    candles.sort_by_key(|c| c.0);
+    candles.dedup_by_key(|c| c.0);
+    if candles.len() != request.candles {
+        return Err(anyhow!(
+            "Expected {} candles for {} but the exchange returned {}",
+            request.candles,
+            request.product_id,
+            candles.len()
+        ));
+    }
+
+    let mut hasher = Sha256::new();
+    let mut features = Vec::with_capacity(candles.len() * 5);
+    for Candle(time, low, high, open, close, volume) in candles {
+        hasher.update(time.to_le_bytes());
+        for value in [open, high, low, close, volume] {
+            hasher.update(value.to_le_bytes());
+            features.push(value as f32);
+        }
+    }
+
+    let record = MarketDataRecord {
+        source: COINBASE_EXCHANGE_SOURCE.to_string(),
+        product_id: request.product_id.clone(),
+        granularity: request.granularity,
+        start,
+        end,
+        candles: request.candles,
+        data_hash: format!("0x{}", hex::encode(hasher.finalize())),
+    };
+    Ok((features, record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candles_are_ordered_and_window_must_be_complete() {
+        let request = MarketDataRequest {
+            product_id: "BTC-USD".to_string(),
+            granularity: 60,
+            candles: 2,
+            end: Some(1_700_000_110),
+        };
+        let (start, end) = resolve_window(&request).unwrap();
+        assert_eq!((start, end), (1_699_999_980, 1_700_000_100));
+
+        // The API returns newest first as [time, low, high, open, close, volume]
+        let candles: Vec<Candle> = serde_json::from_str(
+            "[[1700000040,9.0,11.0,10.0,10.5,3.0],[1699999980,8.0,10.0,9.0,10.0,2.0]]",
+        )
+        .unwrap();
+        let (features, record) = canonicalize(&request, start, end, candles.clone()).unwrap();
+        assert_eq!(
+            features,
+            vec![9.0, 10.0, 8.0, 10.0, 2.0, 10.0, 11.0, 9.0, 10.5, 3.0]
+        );
+        assert_eq!(record.source, COINBASE_EXCHANGE_SOURCE);
+
+        assert!(canonicalize(&request, start, end, candles[..1].to_vec()).is_err());
+    }
+}
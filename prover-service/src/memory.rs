@@ -0,0 +1,147 @@
+//! Per-job memory budgets
+//!
+//! With `JOB_MEMORY_BUDGET_MB` set, a proving job may use at most that much
+//! memory, and one that needs more fails with `RESOURCE_EXHAUSTED` instead of
+//! taking the whole service down with it through the OOM killer:
+//!
+//! - Before inference, the job's footprint is estimated from the model size
+//!   (the ONNX session holds about `SESSION_OVERHEAD` copies of the weights)
+//!   plus the streamed witness budget, and a job that cannot fit is refused.
+//! - External proving binaries get the budget as `JOLT_ATLAS_MEMORY_BUDGET_MB`
+//!   and are killed once their RSS goes over it (or their job is cancelled,
+//!   see `isolation`).
+//! - Worker processes (`PROVE_ISOLATION=process`) are killed once their RSS
+//!   goes over it, as for `PROVE_JOB_MAX_RSS_MB`.
+
+use anyhow::{anyhow, Result};
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+use crate::isolation::{Cancellation, JobCancelled, ProcessUsage};
+use crate::witness::WitnessBudget;
+
+/// Copies of the model weights an inference session holds
+const SESSION_OVERHEAD: u64 = 2;
+
+/// How often a proving binary's memory is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A job needed more memory than its budget
+#[derive(Debug, thiserror::Error)]
+#[error("Memory budget exceeded: {0}")]
+pub struct ResourceExhausted(pub String);
+
+/// Refuse a job over a model of `model_bytes` that cannot fit in `budget`
+pub fn check_estimate(
+    model_bytes: u64,
+    witness: Option<&WitnessBudget>,
+    budget: u64,
+) -> Result<()> {
+    let estimate = model_bytes
+        .saturating_mul(SESSION_OVERHEAD)
+        .saturating_add(witness.map_or(0, |witness| witness.max_bytes));
+    if estimate > budget {
+        return Err(ResourceExhausted(format!(
+            "the job needs an estimated {} MB, over the {} MB job budget",
+            estimate.div_ceil(1 << 20),
+            budget >> 20
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Run `command` to completion like `Command::output`, killing it once its
+/// RSS goes over `budget` bytes or `cancellation` is cancelled
+pub fn output_within(
+    command: &mut Command,
+    budget: Option<u64>,
+    cancellation: Option<&Cancellation>,
+) -> Result<Output> {
+    if let Some(budget) = budget {
+        command.env(
+            "JOLT_ATLAS_MEMORY_BUDGET_MB",
+            (budget >> 20).max(1).to_string(),
+        );
+    }
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain the pipes so a chatty binary cannot block on a full one
+    let drain = |mut pipe: Box<dyn Read + Send>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            pipe.read_to_end(&mut buf).map(|_| buf)
+        })
+    };
+    let stdout = drain(Box::new(child.stdout.take().expect("stdout is piped")));
+    let stderr = drain(Box::new(child.stderr.take().expect("stderr is piped")));
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancellation.is_some_and(Cancellation::is_cancelled) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(JobCancelled.into());
+        }
+        let usage = budget.and_then(|budget| Some((ProcessUsage::sample(child.id())?, budget)));
+        if let Some((usage, budget)) = usage {
+            if usage.rss_bytes > budget {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(ResourceExhausted(format!(
+                    "proving binary killed at {} MB RSS, over the {} MB job budget",
+                    usage.rss_bytes >> 20,
+                    budget >> 20
+                ))
+                .into());
+            }
+        }
+        std::thread::sleep(SAMPLE_INTERVAL);
+    };
+    let join = |handle: std::thread::JoinHandle<std::io::Result<Vec<u8>>>| {
+        handle
+            .join()
+            .map_err(|_| anyhow!("Failed to read proving binary output"))?
+            .map_err(anyhow::Error::from)
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_that_cannot_fit_are_refused() {
+        let witness = WitnessBudget {
+            max_bytes: 256 << 20,
+            chunk_rows: 1024,
+        };
+        assert!(check_estimate(100 << 20, Some(&witness), 512 << 20).is_ok());
+        let error = check_estimate(200 << 20, Some(&witness), 512 << 20).unwrap_err();
+        assert!(error.is::<ResourceExhausted>());
+        assert_eq!(
+            error.to_string(),
+            "Memory budget exceeded: the job needs an estimated 656 MB, over the 512 MB job budget"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_binaries_are_killed() {
+        let cancellation = Cancellation::default();
+        cancellation.cancel();
+        let error =
+            output_within(Command::new("sleep").arg("10"), None, Some(&cancellation)).unwrap_err();
+        assert!(error.is::<JobCancelled>());
+    }
+}
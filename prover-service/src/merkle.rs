@@ -0,0 +1,155 @@
+//! Keccak-256 Merkle trees
+//!
+//! Used for the `merkle-keccak-v1` weights commitment: every weight tensor of
+//! a model becomes a leaf, so a single tensor can be shown to be part of the
+//! committed model without revealing the rest. Leaves and interior nodes are
+//! domain separated (`0x00` / `0x01` prefixes) so a node can never be passed
+//! off as a leaf, and an unpaired node at the end of a level is promoted
+//! unchanged to the next level.
+
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+pub type Hash = [u8; 32];
+
+/// Which side of the running hash a sibling sits on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One step of an inclusion proof
+#[derive(Clone, Debug, Serialize)]
+pub struct ProofStep {
+    /// Sibling hash (hex, 0x-prefixed)
+    pub hash: String,
+    pub side: Side,
+}
+
+/// Merkle tree with all levels kept for proof generation
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// Build a tree over already-hashed leaves
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// Root hash (keccak256 of the empty string for an empty tree)
+    pub fn root(&self) -> Hash {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => *root,
+            None => Keccak256::digest([]).into(),
+        }
+    }
+
+    /// Sibling path from leaf `index` up to the root
+    pub fn proof(&self, mut index: usize) -> Option<Vec<ProofStep>> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+        let mut steps = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                steps.push(ProofStep {
+                    hash: to_hex(hash),
+                    side: if sibling < index {
+                        Side::Left
+                    } else {
+                        Side::Right
+                    },
+                });
+            }
+            index /= 2;
+        }
+        Some(steps)
+    }
+}
+
+/// Leaf hash: `keccak256(0x00 || data)`
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Interior node hash: `keccak256(0x01 || left || right)`
+pub fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Recompute the root from a leaf hash and its proof
+#[cfg(test)]
+pub fn root_from_proof(leaf: Hash, proof: &[ProofStep]) -> Option<Hash> {
+    proof.iter().try_fold(leaf, |acc, step| {
+        let sibling: Hash = hex::decode(step.hash.trim_start_matches("0x"))
+            .ok()?
+            .try_into()
+            .ok()?;
+        Some(match step.side {
+            Side::Left => hash_node(&sibling, &acc),
+            Side::Right => hash_node(&acc, &sibling),
+        })
+    })
+}
+
+pub fn to_hex(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n).map(|i| hash_leaf(&[i as u8])).collect()
+    }
+
+    #[test]
+    fn test_every_leaf_proves_to_root() {
+        for n in 1..=9 {
+            let leaves = leaves(n);
+            let tree = MerkleTree::new(leaves.clone());
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert_eq!(
+                    root_from_proof(*leaf, &proof),
+                    Some(tree.root()),
+                    "n={n} i={i}"
+                );
+            }
+            assert!(tree.proof(n).is_none());
+        }
+    }
+
+    #[test]
+    fn test_wrong_leaf_fails() {
+        let tree = MerkleTree::new(leaves(4));
+        let proof = tree.proof(1).unwrap();
+        assert_ne!(root_from_proof(hash_leaf(b"x"), &proof), Some(tree.root()));
+    }
+}
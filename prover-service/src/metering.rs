@@ -0,0 +1,456 @@
+//! Usage metering
+//!
+//! Records proving seconds, proof bytes and verification counts per tenant
+//! and API key. Usage is persisted to `metering.json` in the data directory,
+//! so a restart does not lose unbilled usage. Recording only updates memory;
+//! a background task writes the file every few seconds off the async runtime,
+//! so a crash loses at most the usage of those last seconds.
+//!
+//! The billing pipeline reads the open metering period from
+//! `GET /admin/usage`. `POST /admin/usage/close` ends the period and returns
+//! its report, which is kept (and listed by `GET /admin/usage/closed`) until
+//! the pipeline acknowledges its `period_id` with
+//! `POST /admin/usage/closed/:period_id/ack`, so a failed or retried fetch
+//! loses no usage.
+//!
+//! Proofs are also counted per model since startup, for `GET /models/:id`;
+//! those counters are neither persisted nor reset with the metering period.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth::Caller;
+use crate::persist::{load_json, save_json};
+use crate::AppState;
+
+/// How often recorded usage is written to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Accumulated usage counters
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct UsageCounters {
+    /// Number of proofs generated or served from the proof cache
+    pub proofs: u64,
+
+    /// Of those, proofs served from the proof cache
+    pub cached_proofs: u64,
+
+    /// Total wall-clock proving time in seconds
+    pub proving_seconds: f64,
+
+    /// Total CPU time of proving jobs run in worker processes, in seconds
+    pub cpu_seconds: f64,
+
+    /// Total size of the encoded proofs returned
+    pub proof_bytes: u64,
+
+    /// Number of verifications performed
+    pub verifications: u64,
+}
+
+impl UsageCounters {
+    fn add(&mut self, other: &UsageCounters) {
+        self.proofs += other.proofs;
+        self.cached_proofs += other.cached_proofs;
+        self.proving_seconds += other.proving_seconds;
+        self.cpu_seconds += other.cpu_seconds;
+        self.proof_bytes += other.proof_bytes;
+        self.verifications += other.verifications;
+    }
+}
+
+/// Usage attributed to a single API key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub tenant: String,
+    pub key_id: String,
+    #[serde(flatten)]
+    pub usage: UsageCounters,
+}
+
+/// Usage aggregated over all keys of a tenant
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant: String,
+    #[serde(flatten)]
+    pub usage: UsageCounters,
+}
+
+/// Usage export for one metering period
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Id to acknowledge the period with once it is closed
+    pub period_id: u64,
+
+    /// Start of the period (unix seconds)
+    pub period_start: u64,
+
+    /// End of the period (unix seconds)
+    pub period_end: u64,
+
+    /// Per-key usage
+    pub keys: Vec<KeyUsage>,
+
+    /// Per-tenant totals
+    pub tenants: Vec<TenantUsage>,
+}
+
+/// Proofs of a single model since startup
+#[derive(Clone, Default, Serialize)]
+pub struct ModelUsage {
+    pub proofs: u64,
+    pub proving_seconds: f64,
+    /// Mean wall-clock proving time
+    pub mean_proving_ms: u64,
+    /// When the model last produced a proof (unix seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_proof_at: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct MeterState {
+    period_id: u64,
+    period_start: u64,
+    #[serde(with = "stored_usage")]
+    usage: HashMap<(String, String), UsageCounters>,
+    /// Closed periods awaiting acknowledgement, oldest first
+    closed: Vec<UsageReport>,
+    #[serde(skip)]
+    models: HashMap<String, ModelUsage>,
+}
+
+impl MeterState {
+    fn starting_now() -> Self {
+        Self {
+            period_id: 1,
+            period_start: now_secs(),
+            usage: HashMap::new(),
+            closed: Vec::new(),
+            models: HashMap::new(),
+        }
+    }
+}
+
+/// Thread-safe usage recorder
+pub struct Meter {
+    /// Where usage is persisted (in memory only when unset)
+    path: Option<PathBuf>,
+    state: Mutex<MeterState>,
+    /// Set when usage was recorded since the last write
+    dirty: AtomicBool,
+    /// Held while writing, so an older state never overwrites a newer one
+    writes: Mutex<()>,
+}
+
+impl Meter {
+    /// Usage kept in memory only
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            state: Mutex::new(MeterState::starting_now()),
+            dirty: AtomicBool::new(false),
+            writes: Mutex::new(()),
+        }
+    }
+
+    /// Open (or create) the usage store in the data directory
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("metering.json");
+        let state = load_json(&path)?.unwrap_or_else(MeterState::starting_now);
+        tracing::info!(
+            "Loaded metering period {} ({} closed periods unacknowledged) from {}",
+            state.period_id,
+            state.closed.len(),
+            path.display()
+        );
+        Ok(Self {
+            path: Some(path),
+            state: Mutex::new(state),
+            dirty: AtomicBool::new(false),
+            writes: Mutex::new(()),
+        })
+    }
+
+    fn save(&self, state: &MeterState) -> Result<()> {
+        match &self.path {
+            Some(path) => save_json(path, state),
+            None => Ok(()),
+        }
+    }
+
+    /// Write recorded usage to disk if it changed since the last write
+    pub fn flush(&self) -> Result<()> {
+        let _writes = self.writes.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let state = self.state.lock().unwrap().clone();
+        self.save(&state)
+            .inspect_err(|_| self.dirty.store(true, Ordering::SeqCst))
+    }
+
+    /// Record a generated proof
+    pub fn record_proof(&self, caller: &Caller, proving_time: Duration, proof_bytes: usize) {
+        self.update(caller, |usage| {
+            usage.proofs += 1;
+            usage.proving_seconds += proving_time.as_secs_f64();
+            usage.proof_bytes += proof_bytes as u64;
+        });
+    }
+
+    /// Record a proof served from the proof cache, which took no proving time
+    pub fn record_cached_proof(&self, caller: &Caller, proof_bytes: usize) {
+        self.update(caller, |usage| {
+            usage.proofs += 1;
+            usage.cached_proofs += 1;
+            usage.proof_bytes += proof_bytes as u64;
+        });
+    }
+
+    /// Record a proof generated by `model_id`
+    pub fn record_model_proof(&self, model_id: &str, proving_time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let usage = state.models.entry(model_id.to_string()).or_default();
+        usage.proofs += 1;
+        usage.proving_seconds += proving_time.as_secs_f64();
+        usage.mean_proving_ms = (usage.proving_seconds * 1000.0 / usage.proofs as f64) as u64;
+        usage.last_proof_at = Some(now_secs());
+    }
+
+    /// Proofs of `model_id` since startup
+    pub fn model_usage(&self, model_id: &str) -> ModelUsage {
+        let state = self.state.lock().unwrap();
+        state.models.get(model_id).cloned().unwrap_or_default()
+    }
+
+    /// Record the CPU time a proving job consumed
+    pub fn record_cpu_time(&self, caller: &Caller, cpu_time: Duration) {
+        self.update(caller, |usage| usage.cpu_seconds += cpu_time.as_secs_f64());
+    }
+
+    /// Record a verification
+    pub fn record_verification(&self, caller: &Caller) {
+        self.update(caller, |usage| usage.verifications += 1);
+    }
+
+    /// Report usage for the current period without resetting it
+    pub fn snapshot(&self) -> UsageReport {
+        let state = self.state.lock().unwrap();
+        build_report(state.period_id, state.period_start, &state.usage)
+    }
+
+    /// End the current period and start a new one, keeping the report of
+    /// the closed period until it is acknowledged
+    pub fn close_period(&self) -> Result<UsageReport> {
+        let _writes = self.writes.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        let report = build_report(state.period_id, state.period_start, &state.usage);
+        let mut next = state.clone();
+        next.usage.clear();
+        next.closed.push(report.clone());
+        next.period_id += 1;
+        next.period_start = report.period_end;
+        self.save(&next)?;
+        *state = next;
+        Ok(report)
+    }
+
+    /// Closed periods not acknowledged yet, oldest first
+    pub fn closed_periods(&self) -> Vec<UsageReport> {
+        self.state.lock().unwrap().closed.clone()
+    }
+
+    /// Drop the report of the closed period `period_id` once billed;
+    /// `false` if there is no such unacknowledged period
+    pub fn acknowledge(&self, period_id: u64) -> Result<bool> {
+        let _writes = self.writes.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        if !state.closed.iter().any(|r| r.period_id == period_id) {
+            return Ok(false);
+        }
+        let mut next = state.clone();
+        next.closed.retain(|r| r.period_id != period_id);
+        self.save(&next)?;
+        *state = next;
+        Ok(true)
+    }
+
+    fn update(&self, caller: &Caller, f: impl FnOnce(&mut UsageCounters)) {
+        let mut state = self.state.lock().unwrap();
+        let usage = state
+            .usage
+            .entry((caller.tenant.clone(), caller.key_id.clone()))
+            .or_default();
+        f(usage);
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Background task writing recorded usage every `FLUSH_INTERVAL`
+pub async fn run_forever(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let state = state.clone();
+        // A failed write is retried on the next tick
+        if let Ok(Err(e)) = tokio::task::spawn_blocking(move || state.meter.flush()).await {
+            tracing::warn!("Failed to persist usage: {}", e);
+        }
+    }
+}
+
+/// Per-key usage persisted as a list, as JSON maps need string keys
+mod stored_usage {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        usage: &HashMap<(String, String), UsageCounters>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let stored: Vec<KeyUsage> = usage
+            .iter()
+            .map(|((tenant, key_id), usage)| KeyUsage {
+                tenant: tenant.clone(),
+                key_id: key_id.clone(),
+                usage: usage.clone(),
+            })
+            .collect();
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(String, String), UsageCounters>, D::Error> {
+        let stored = Vec::<KeyUsage>::deserialize(deserializer)?;
+        Ok(stored
+            .into_iter()
+            .map(|key| ((key.tenant, key.key_id), key.usage))
+            .collect())
+    }
+}
+
+fn build_report(
+    period_id: u64,
+    period_start: u64,
+    usage: &HashMap<(String, String), UsageCounters>,
+) -> UsageReport {
+    let mut keys: Vec<KeyUsage> = usage
+        .iter()
+        .map(|((tenant, key_id), usage)| KeyUsage {
+            tenant: tenant.clone(),
+            key_id: key_id.clone(),
+            usage: usage.clone(),
+        })
+        .collect();
+    keys.sort_by(|a, b| (&a.tenant, &a.key_id).cmp(&(&b.tenant, &b.key_id)));
+
+    let mut totals: BTreeMap<String, UsageCounters> = BTreeMap::new();
+    for key in &keys {
+        totals
+            .entry(key.tenant.clone())
+            .or_default()
+            .add(&key.usage);
+    }
+
+    UsageReport {
+        period_id,
+        period_start,
+        period_end: now_secs(),
+        keys,
+        tenants: totals
+            .into_iter()
+            .map(|(tenant, usage)| TenantUsage { tenant, usage })
+            .collect(),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(tenant: &str, key_id: &str) -> Caller {
+        Caller {
+            key_id: key_id.to_string(),
+            tenant: tenant.to_string(),
+            scopes: None,
+        }
+    }
+
+    #[test]
+    fn test_usage_aggregated_per_tenant() {
+        let meter = Meter::new();
+        meter.record_proof(&caller("acme", "key_a"), Duration::from_millis(1500), 100);
+        meter.record_proof(&caller("acme", "key_b"), Duration::from_millis(500), 50);
+        meter.record_cpu_time(&caller("acme", "key_b"), Duration::from_millis(1250));
+        meter.record_verification(&caller("acme", "key_a"));
+        meter.record_verification(&caller("globex", "key_c"));
+
+        let report = meter.snapshot();
+        assert_eq!(report.keys.len(), 3);
+        assert_eq!(report.tenants.len(), 2);
+
+        let acme = &report.tenants[0];
+        assert_eq!(acme.tenant, "acme");
+        assert_eq!(acme.usage.proofs, 2);
+        assert_eq!(acme.usage.proof_bytes, 150);
+        assert_eq!(acme.usage.verifications, 1);
+        assert!((acme.usage.proving_seconds - 2.0).abs() < 1e-9);
+        assert!((acme.usage.cpu_seconds - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_closed_periods_kept_until_acknowledged() {
+        let dir = tempfile::tempdir().unwrap();
+        let meter = Meter::open(dir.path()).unwrap();
+        meter.record_verification(&caller("acme", "key_a"));
+
+        let first = meter.close_period().unwrap();
+        assert_eq!(first.keys.len(), 1);
+        meter.record_verification(&caller("acme", "key_b"));
+        assert!(Meter::open(dir.path()).unwrap().snapshot().keys.is_empty());
+        meter.flush().unwrap();
+
+        let second = meter.snapshot();
+        assert_eq!(second.keys.len(), 1);
+        assert_eq!(second.period_id, first.period_id + 1);
+        assert_eq!(second.period_start, first.period_end);
+
+        // Open and closed usage survive a restart
+        let reopened = Meter::open(dir.path()).unwrap();
+        assert_eq!(reopened.snapshot().keys[0].key_id, "key_b");
+        assert_eq!(reopened.closed_periods().len(), 1);
+
+        assert!(!reopened.acknowledge(second.period_id).unwrap());
+        assert!(reopened.acknowledge(first.period_id).unwrap());
+        assert!(reopened.closed_periods().is_empty());
+        assert!(Meter::open(dir.path()).unwrap().closed_periods().is_empty());
+    }
+
+    #[test]
+    fn test_model_usage_survives_period_close() {
+        let meter = Meter::new();
+        meter.record_model_proof("m", Duration::from_millis(1000));
+        meter.record_model_proof("m", Duration::from_millis(3000));
+        meter.close_period().unwrap();
+
+        let usage = meter.model_usage("m");
+        assert_eq!(usage.proofs, 2);
+        assert_eq!(usage.mean_proving_ms, 2000);
+        assert!(usage.last_proof_at.is_some());
+        assert_eq!(meter.model_usage("other").proofs, 0);
+    }
+}
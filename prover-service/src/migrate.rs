@@ -0,0 +1,120 @@
+//! Instance-to-instance migration
+//!
+//! `POST /admin/import` pulls the registry of another prover-service instance
+//! through its admin API (`GET /admin/snapshot`, authenticated with that
+//! instance's admin token) and restores it here, as `POST /admin/restore`
+//! would. Models keep their ids and commitments, so references baked into
+//! smart contracts keep resolving after an environment is migrated; every
+//! commitment is recomputed from the downloaded model file before anything
+//! is registered. Signing keys are never pulled: the new instance keeps its
+//! own, and its attestations name the key that signed them.
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth::AdminAuth;
+use crate::snapshot::{self, RegistrySnapshot, RestoreReport};
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// Time allowed to download the source's snapshot
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Deserialize)]
+pub struct ImportRequest {
+    /// Base URL of the source instance, e.g. `https://prover.staging:3001`
+    pub source: String,
+    /// Admin token of the source instance
+    pub admin_token: String,
+}
+
+/// Outcome of an import
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub source: String,
+    /// When the source took the snapshot
+    pub snapshot_created_at: u64,
+    #[serde(flatten)]
+    pub registry: RestoreReport,
+}
+
+/// URL of the snapshot endpoint of the instance at `source`
+fn snapshot_url(source: &str) -> Result<String> {
+    let source = source.trim_end_matches('/');
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return Err(anyhow!("Source {} is not an http(s) URL", source));
+    }
+    Ok(format!("{}/admin/snapshot", source))
+}
+
+/// Download the registry snapshot of the instance at `source`
+async fn fetch_snapshot(source: &str, admin_token: &str) -> Result<RegistrySnapshot> {
+    let url = snapshot_url(source)?;
+    let response = reqwest::Client::builder()
+        .timeout(IMPORT_TIMEOUT)
+        .build()?
+        .get(&url)
+        .bearer_auth(admin_token)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("{} answered {}: {}", url, status, body));
+    }
+    response
+        .json()
+        .await
+        .with_context(|| format!("Invalid snapshot from {}", url))
+}
+
+/// Import the registry of another instance
+pub async fn import(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ImportRequest>,
+) -> Result<Json<ImportReport>, ApiError> {
+    let snapshot = fetch_snapshot(&req.source, &req.admin_token)
+        .await
+        .map_err(|e| api_error(StatusCode::BAD_GATEWAY, "IMPORT_FAILED", format!("{:#}", e)))?;
+    let snapshot_created_at = snapshot.created_at;
+    let models = snapshot.models.len();
+    let registry = snapshot::restore(&state, snapshot).await?;
+    tracing::info!(
+        "Imported {} models from {} ({} already present)",
+        models,
+        req.source,
+        registry.models_present.len()
+    );
+    Ok(Json(ImportReport {
+        source: req.source,
+        snapshot_created_at,
+        registry,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_url() {
+        assert_eq!(
+            snapshot_url("https://prover.staging:3001/").unwrap(),
+            "https://prover.staging:3001/admin/snapshot"
+        );
+        assert_eq!(
+            snapshot_url("http://10.0.0.2:3001").unwrap(),
+            "http://10.0.0.2:3001/admin/snapshot"
+        );
+        assert!(snapshot_url("prover.staging:3001").is_err());
+        assert!(snapshot_url("file:///etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! MessagePack content negotiation
+//!
+//! Every endpoint speaks MessagePack as well as JSON. Request bodies sent
+//! with `Content-Type: application/msgpack` and responses requested with
+//! `Accept: application/msgpack` are transcoded at the edge, value by value
+//! without an intermediate document tree, so handlers and the serde types in
+//! `types.rs` stay format-agnostic.
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::body_limit::{self, BodyLimit};
+use crate::types::api_error;
+
+/// MessagePack media type
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Media types clients use for MessagePack
+const MSGPACK_ALIASES: [&str; 3] = [
+    MSGPACK_CONTENT_TYPE,
+    "application/x-msgpack",
+    "application/vnd.msgpack",
+];
+
+/// Largest MessagePack body accepted where no route limit applies
+const MAX_BODY_BYTES: usize = 64 << 20;
+
+/// Middleware translating MessagePack requests and responses to and from JSON
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let wants_msgpack = accepts_msgpack(request.headers());
+
+    let request = if is_msgpack(request.headers().get(CONTENT_TYPE)) {
+        let (mut parts, body) = request.into_parts();
+        let limit = parts
+            .extensions
+            .get::<BodyLimit>()
+            .map_or(MAX_BODY_BYTES, |limit| limit.0);
+        let json = match axum::body::to_bytes(body, limit).await {
+            Ok(bytes) => msgpack_to_json(&bytes),
+            Err(e) if body_limit::exceeded(&e) => {
+                let rejection = body_limit::too_large(limit);
+                return encode_response(rejection.into_response(), wants_msgpack).await;
+            }
+            Err(e) => Err(e.into()),
+        };
+        match json {
+            Ok(json) => {
+                parts
+                    .headers
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                parts.headers.remove(CONTENT_LENGTH);
+                Request::from_parts(parts, Body::from(json))
+            }
+            Err(e) => {
+                let rejection = api_error(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_MSGPACK",
+                    format!("Invalid MessagePack body: {}", e),
+                );
+                return encode_response(rejection.into_response(), wants_msgpack).await;
+            }
+        }
+    } else {
+        request
+    };
+
+    encode_response(next.run(request).await, wants_msgpack).await
+}
+
+/// Re-encode a JSON response as MessagePack if the client asked for it
+async fn encode_response(response: Response, wants_msgpack: bool) -> Response {
+    if !wants_msgpack {
+        return response;
+    }
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let encoded = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => json_to_msgpack(&bytes),
+        Err(e) => Err(e.into()),
+    };
+    match encoded {
+        Ok(msgpack) => {
+            parts
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static(MSGPACK_CONTENT_TYPE));
+            parts.headers.remove(CONTENT_LENGTH);
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("accept"));
+            Response::from_parts(parts, Body::from(msgpack))
+        }
+        Err(e) => {
+            tracing::error!("Failed to encode response as MessagePack: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn is_msgpack(content_type: Option<&HeaderValue>) -> bool {
+    content_type
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|v| {
+            MSGPACK_ALIASES
+                .iter()
+                .any(|alias| v.trim().eq_ignore_ascii_case(alias))
+        })
+}
+
+/// Whether the client lists MessagePack among the types it accepts
+fn accepts_msgpack(headers: &HeaderMap) -> bool {
+    headers.get_all(ACCEPT).iter().any(|accept| {
+        accept.to_str().is_ok_and(|accept| {
+            accept
+                .split(',')
+                .any(|media| is_msgpack(HeaderValue::from_str(media).ok().as_ref()))
+        })
+    })
+}
+
+fn msgpack_to_json(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut json = Vec::with_capacity(bytes.len() * 2);
+    let mut deserializer = rmp_serde::Deserializer::new(bytes);
+    serde_transcode::transcode(
+        &mut deserializer,
+        &mut serde_json::Serializer::new(&mut json),
+    )?;
+    Ok(json)
+}
+
+fn json_to_msgpack(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut msgpack = Vec::with_capacity(bytes.len());
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    serde_transcode::transcode(
+        &mut deserializer,
+        &mut rmp_serde::Serializer::new(&mut msgpack),
+    )?;
+    Ok(msgpack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProveRequest;
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let request = ProveRequest {
+            model_id: "m".to_string(),
+            inputs: vec![0.1, -2.5, 1e-7],
+            ..Default::default()
+        };
+        let json = serde_json::to_vec(&request).unwrap();
+        let msgpack = json_to_msgpack(&json).unwrap();
+        assert!(msgpack.len() < json.len());
+
+        let decoded: ProveRequest =
+            serde_json::from_slice(&msgpack_to_json(&msgpack).unwrap()).unwrap();
+        assert_eq!(decoded.inputs, request.inputs);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ACCEPT,
+            "text/html, application/x-msgpack;q=0.9".parse().unwrap(),
+        );
+        assert!(accepts_msgpack(&headers));
+    }
+}
@@ -0,0 +1,308 @@
+//! Offline re-execution audits
+//!
+//! `--audit --bundle <x.zkbundle> --model <model.onnx> [--inputs <file>]`
+//! checks a proof's computation against the model file, independently of
+//! the service, following the bundle's reproducibility manifest (see
+//! `manifest`):
+//!
+//! - the manifest's canonicalization rules and hash scheme are the ones this
+//!   binary implements
+//! - the model file, canonicalized, commits to the manifest's and the
+//!   public inputs' model commitment
+//! - with `--inputs`, a JSON array of the proven input features (after any
+//!   preprocessing), inference re-runs locally on the manifest's engine and
+//!   the input hash, output hash and disclosed output are recomputed and
+//!   compared with the public inputs
+//! - without it, the output hash is recomputed from the disclosed output
+//!
+//! The report is printed as JSON; the exit status is 0 when every check
+//! passed, 1 when one failed and 2 when the audit could not run. Only the
+//! computation is audited: `--verify-bundle` checks the proof itself.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use crate::bundle::{BundleCheck, BundleReport, ZkBundle};
+use crate::inference::{self, InferenceEngine};
+use crate::jolt_atlas::ModelCommitments;
+use crate::manifest::{ReproducibilityManifest, MOCK_ENGINE};
+use crate::onnx::{self, CANONICALIZATION_VERSION};
+use crate::prover::JoltAtlasProver;
+use crate::replay::{check_replayed, replay_hashes};
+use crate::verification::ct_eq;
+
+/// Command-line flag for auditing a bundle against its model offline
+pub const AUDIT_ARG: &str = "--audit";
+
+/// Audit `bundle` against the model in `model_path`, re-running inference
+/// over `inputs` when given
+pub async fn audit_bundle(
+    bundle: &ZkBundle,
+    model_path: &Path,
+    inputs: Option<&[f32]>,
+) -> BundleReport {
+    let mut checks = Vec::new();
+    let mut record = |check: &str, result: Result<()>| {
+        checks.push(BundleCheck {
+            check: check.to_string(),
+            passed: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        })
+    };
+    let public_inputs = &bundle.public_inputs;
+
+    let manifest = bundle.manifest.as_ref();
+    record(
+        "manifest",
+        manifest
+            .map(check_manifest)
+            .unwrap_or_else(|| Err(anyhow!("Bundle carries no reproducibility manifest"))),
+    );
+    record(
+        "model_commitment",
+        check_model(model_path, manifest, &public_inputs.model_commitment),
+    );
+
+    match inputs {
+        Some(inputs) => {
+            let engine = manifest
+                .and_then(|m| m.inference_engine.parse().ok())
+                .unwrap_or_default();
+            let replayed = JoltAtlasProver::run_inference(model_path, inputs, engine, true)
+                .await
+                .and_then(|outputs| {
+                    check_replayed(public_inputs, inputs, inference::flatten(&outputs))
+                });
+            match replayed {
+                Ok((input_hash_matches, output_hash_matches, output)) => {
+                    record("inference", Ok(()));
+                    record(
+                        "input_hash",
+                        matches(input_hash_matches, "Inputs do not hash to the input hash"),
+                    );
+                    record(
+                        "output_hash",
+                        matches(
+                            output_hash_matches,
+                            "Re-run output does not hash to the output hash",
+                        ),
+                    );
+                    if !public_inputs.output.is_empty() {
+                        record(
+                            "output",
+                            matches(
+                                output == public_inputs.output,
+                                "Re-run output differs from the disclosed output",
+                            ),
+                        );
+                    }
+                }
+                Err(e) => record("inference", Err(e)),
+            }
+        }
+        // The output hash does not cover the inputs, so it can be recomputed
+        // from a disclosed output alone
+        None if !public_inputs.output.is_empty() => record(
+            "output_hash",
+            replay_hashes(public_inputs, &[], &public_inputs.output).and_then(|(_, hash)| {
+                matches(
+                    ct_eq(&hash, &public_inputs.output_hash),
+                    "Disclosed output does not hash to the output hash",
+                )
+            }),
+        ),
+        None => {}
+    }
+
+    BundleReport {
+        valid: checks.iter().all(|check| check.passed),
+        proof_id: bundle.proof_id.clone(),
+        checks,
+    }
+}
+
+fn matches(matched: bool, error: &str) -> Result<()> {
+    matched.then_some(()).ok_or_else(|| anyhow!("{}", error))
+}
+
+/// The manifest is reproducible by this binary
+fn check_manifest(manifest: &ReproducibilityManifest) -> Result<()> {
+    if manifest.canonicalization_version != CANONICALIZATION_VERSION {
+        return Err(anyhow!(
+            "Model was committed under {}, this binary implements {}",
+            manifest.canonicalization_version,
+            CANONICALIZATION_VERSION
+        ));
+    }
+    if !manifest.hash_scheme.is_supported() {
+        return Err(anyhow!("Unsupported hash scheme {}", manifest.hash_scheme));
+    }
+    if manifest.inference_engine == MOCK_ENGINE {
+        return Err(anyhow!(
+            "The proof's inference was mocked, so it cannot be reproduced"
+        ));
+    }
+    let engine: InferenceEngine = manifest.inference_engine.parse()?;
+    if !engine.available() {
+        return Err(anyhow!(
+            "{} inference engine is not compiled into this binary",
+            engine
+        ));
+    }
+    Ok(())
+}
+
+/// The model file commits to the manifest's and the public inputs' model
+/// commitment
+fn check_model(
+    model_path: &Path,
+    manifest: Option<&ReproducibilityManifest>,
+    public_commitment: &str,
+) -> Result<()> {
+    let canonical = onnx::canonicalize(&std::fs::read(model_path)?)?;
+    let commitment = ModelCommitments::compute(&canonical).sha256;
+    if let Some(manifest) = manifest {
+        if !ct_eq(&commitment, &manifest.model_commitment) {
+            return Err(anyhow!(
+                "Model commits to {}, the manifest to {}",
+                commitment,
+                manifest.model_commitment
+            ));
+        }
+    }
+    if !ct_eq(&commitment, public_commitment) {
+        return Err(anyhow!(
+            "Model commits to {}, the public inputs to {}",
+            commitment,
+            public_commitment
+        ));
+    }
+    Ok(())
+}
+
+/// Entry point of `--audit`; returns the process exit code
+pub async fn audit_main(args: impl Iterator<Item = String>) -> i32 {
+    let (mut bundle_path, mut model_path, mut inputs_path) = (None, None, None);
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--bundle" => &mut bundle_path,
+            "--model" => &mut model_path,
+            "--inputs" => &mut inputs_path,
+            _ => {
+                bundle_path = None;
+                break;
+            }
+        };
+        *slot = args.next();
+    }
+    let (Some(bundle_path), Some(model_path)) = (bundle_path, model_path) else {
+        eprintln!(
+            "usage: {} --bundle <x.zkbundle> --model <model.onnx> [--inputs <inputs.json>]",
+            AUDIT_ARG
+        );
+        return 2;
+    };
+
+    let bundle: ZkBundle = match read_json(&bundle_path) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("Failed to read bundle {}: {}", bundle_path, e);
+            return 2;
+        }
+    };
+    let inputs: Option<Vec<f32>> = match inputs_path.as_deref().map(read_json).transpose() {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("Failed to read inputs: {}", e);
+            return 2;
+        }
+    };
+
+    let report = audit_bundle(&bundle, Path::new(&model_path), inputs.as_deref()).await;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("reports serialize")
+    );
+    if report.valid {
+        0
+    } else {
+        1
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGISTIC: &[u8] = include_bytes!("../jolt-atlas/models/demo/logistic.onnx");
+    const MLP: &[u8] = include_bytes!("../jolt-atlas/models/demo/mlp.onnx");
+
+    #[tokio::test]
+    async fn test_audit_checks_model_and_disclosed_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_bytes = LOGISTIC;
+        let model_path = dir.path().join("model.onnx");
+        std::fs::write(&model_path, model_bytes).unwrap();
+
+        let model = ModelCommitments::compute(&onnx::canonicalize(model_bytes).unwrap());
+        let output = [0.75];
+        let engine = InferenceEngine::default();
+        let bundle: ZkBundle = serde_json::from_value(serde_json::json!({
+            "format": crate::bundle::BUNDLE_FORMAT,
+            "proof_id": "proof:sha256:00",
+            "proof": "",
+            "proof_format": "jolt-atlas-v2",
+            "commitment_scheme": "sha256-v2",
+            "model_commitment": model.sha256,
+            "input_hash": model.input_hash(&[1.0, 2.0]),
+            "output_hash": model.output_hash(&output),
+            "public_inputs": {
+                "model_commitment": model.sha256,
+                "input_hash": model.input_hash(&[1.0, 2.0]),
+                "output_hash": model.output_hash(&output),
+                "output": output,
+                "timestamp": 0,
+                "commitment_scheme": "sha256-v2"
+            },
+            "verifying_key": { "prover_id": "mock", "circuit_version": "mock" },
+            "manifest": {
+                "manifest_version": crate::manifest::MANIFEST_VERSION,
+                "service_version": "0.1.0",
+                "backend": "mock",
+                "backend_version": "mock",
+                "proof_format": "jolt-atlas-v2",
+                "inference_engine": engine.as_str(),
+                "model_commitment": model.sha256,
+                "canonicalization_version": CANONICALIZATION_VERSION,
+                "hash_scheme": "sha256-v2"
+            },
+            "created_at": 0
+        }))
+        .unwrap();
+
+        let report = audit_bundle(&bundle, &model_path, None).await;
+        let passed = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.check == name)
+                .map(|check| check.passed)
+        };
+        assert_eq!(passed("model_commitment"), Some(true));
+        assert_eq!(passed("output_hash"), Some(true));
+        assert_eq!(passed("manifest"), Some(engine.available()));
+
+        std::fs::write(&model_path, MLP).unwrap();
+        let report = audit_bundle(&bundle, &model_path, None).await;
+        assert!(!report.valid);
+        assert!(report
+            .checks
+            .iter()
+            .any(|check| check.check == "model_commitment" && !check.passed));
+    }
+}
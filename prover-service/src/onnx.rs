@@ -0,0 +1,704 @@
+//! ONNX model canonicalization
+//!
+//! Two semantically identical exports of a model rarely serialize to the
+//! same bytes: exporters stamp producer metadata and docstrings, order
+//! initializers differently and invent their own names for intermediate
+//! tensors. Committing to raw bytes therefore makes commitments depend on
+//! the export tool. `canonicalize` rewrites a serialized `ModelProto` into a
+//! canonical form before it is hashed:
+//!
+//! - producer, domain, model version, docstrings and metadata props are dropped
+//! - node names, the graph name and inferred `value_info` are dropped
+//! - opset imports and initializers are sorted (initializers by name)
+//! - intermediate tensors are renamed `%0`, `%1`, ... in order of production
+//! - fields are re-encoded in field-number order with minimal varints
+//!
+//! Graph inputs, outputs and initializer names are the model's interface and
+//! are preserved. The rewrite works directly on the protobuf wire format, so
+//! unknown fields pass through untouched.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Identifier of the canonicalization rules, bumped whenever they change
+pub const CANONICALIZATION_VERSION: &str = "onnx-canon-v1";
+
+// ModelProto
+const MODEL_PRODUCER_NAME: u32 = 2;
+const MODEL_PRODUCER_VERSION: u32 = 3;
+const MODEL_DOMAIN: u32 = 4;
+const MODEL_VERSION: u32 = 5;
+const MODEL_DOC_STRING: u32 = 6;
+const MODEL_GRAPH: u32 = 7;
+const MODEL_OPSET_IMPORT: u32 = 8;
+const MODEL_METADATA_PROPS: u32 = 14;
+
+// GraphProto
+const GRAPH_NODE: u32 = 1;
+const GRAPH_NAME: u32 = 2;
+const GRAPH_INITIALIZER: u32 = 5;
+const GRAPH_DOC_STRING: u32 = 10;
+const GRAPH_INPUT: u32 = 11;
+const GRAPH_OUTPUT: u32 = 12;
+const GRAPH_VALUE_INFO: u32 = 13;
+
+// NodeProto
+const NODE_INPUT: u32 = 1;
+const NODE_OUTPUT: u32 = 2;
+const NODE_NAME: u32 = 3;
+const NODE_ATTRIBUTE: u32 = 5;
+const NODE_DOC_STRING: u32 = 6;
+
+// AttributeProto
+const ATTRIBUTE_G: u32 = 6;
+const ATTRIBUTE_GRAPHS: u32 = 11;
+const ATTRIBUTE_DOC_STRING: u32 = 13;
+
+// TensorProto / ValueInfoProto
+const TENSOR_NAME: u32 = 8;
+const TENSOR_DOC_STRING: u32 = 12;
+const VALUE_INFO_NAME: u32 = 1;
+const VALUE_INFO_TYPE: u32 = 2;
+const VALUE_INFO_DOC_STRING: u32 = 3;
+
+// TypeProto / TypeProto.Tensor / TensorShapeProto
+const TYPE_TENSOR: u32 = 1;
+const TENSOR_TYPE_ELEM_TYPE: u32 = 1;
+const TENSOR_TYPE_SHAPE: u32 = 2;
+const SHAPE_DIM: u32 = 1;
+const DIM_VALUE: u32 = 1;
+const DIM_PARAM: u32 = 2;
+
+/// A single protobuf wire value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WireValue {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(Vec<u8>),
+    Fixed32([u8; 4]),
+}
+
+/// A tagged protobuf field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Field {
+    pub number: u32,
+    pub value: WireValue,
+}
+
+impl Field {
+    fn bytes(&self) -> Option<&[u8]> {
+        match &self.value {
+            WireValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> Option<&str> {
+        self.bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+}
+
+/// Canonicalize a serialized ONNX `ModelProto`
+pub fn canonicalize(model_bytes: &[u8]) -> Result<Vec<u8>> {
+    let fields = parse_message(model_bytes).map_err(|e| anyhow!("Invalid ONNX model: {}", e))?;
+    if !fields.iter().any(|f| f.number == MODEL_GRAPH) {
+        return Err(anyhow!("Invalid ONNX model: no graph"));
+    }
+
+    let mut out = Vec::new();
+    let mut opsets = Vec::new();
+    for field in fields {
+        match field.number {
+            MODEL_PRODUCER_NAME
+            | MODEL_PRODUCER_VERSION
+            | MODEL_DOMAIN
+            | MODEL_VERSION
+            | MODEL_DOC_STRING
+            | MODEL_METADATA_PROPS => {}
+            MODEL_GRAPH => out.push(map_bytes(field, canonicalize_graph)?),
+            MODEL_OPSET_IMPORT => opsets.push(field),
+            _ => out.push(field),
+        }
+    }
+    opsets.sort_by(|a, b| a.bytes().cmp(&b.bytes()));
+    out.extend(opsets);
+
+    Ok(encode_message(sorted(out)))
+}
+
+/// Weight tensors (graph initializers) of a model as `(name, TensorProto bytes)`
+///
+/// Given canonical bytes, tensors come back sorted by name with docstrings
+/// stripped, i.e. exactly as they were committed.
+pub fn weight_tensors(model_bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let graph = parse_message(model_bytes)?
+        .into_iter()
+        .find(|f| f.number == MODEL_GRAPH)
+        .ok_or_else(|| anyhow!("Invalid ONNX model: no graph"))?;
+
+    let mut tensors = Vec::new();
+    for field in parse_message(graph.bytes().unwrap_or_default())? {
+        if field.number != GRAPH_INITIALIZER {
+            continue;
+        }
+        let bytes = field.bytes().unwrap_or_default();
+        let name = parse_message(bytes)?
+            .into_iter()
+            .find(|f| f.number == TENSOR_NAME)
+            .and_then(|f| f.string().map(String::from))
+            .unwrap_or_default();
+        tensors.push((name, bytes.to_vec()));
+    }
+    Ok(tensors)
+}
+
+/// A graph input or output as declared by the model
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TensorSchema {
+    pub name: String,
+    /// ONNX element type, e.g. `float` or `int64`
+    pub elem_type: String,
+    pub shape: Vec<Dimension>,
+}
+
+/// One dimension of a declared shape: a size, a symbolic name such as
+/// `batch`, or `null` when the export left it open
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Dimension {
+    Fixed(i64),
+    Symbolic(String),
+    Unknown,
+}
+
+/// Declared inputs and outputs of a model, as `(inputs, outputs)`
+///
+/// Initializers some exporters also list as graph inputs are weights, not
+/// part of the interface, and are left out.
+pub fn interface(model_bytes: &[u8]) -> Result<(Vec<TensorSchema>, Vec<TensorSchema>)> {
+    let graph = parse_message(model_bytes)
+        .map_err(|e| anyhow!("Invalid ONNX model: {}", e))?
+        .into_iter()
+        .find(|f| f.number == MODEL_GRAPH)
+        .ok_or_else(|| anyhow!("Invalid ONNX model: no graph"))?;
+    let fields = parse_message(graph.bytes().unwrap_or_default())?;
+
+    let mut weights = HashSet::new();
+    for field in fields.iter().filter(|f| f.number == GRAPH_INITIALIZER) {
+        if let Some(name) = parse_message(field.bytes().unwrap_or_default())?
+            .into_iter()
+            .find(|f| f.number == TENSOR_NAME)
+            .and_then(|f| f.string().map(String::from))
+        {
+            weights.insert(name);
+        }
+    }
+
+    let (mut inputs, mut outputs) = (Vec::new(), Vec::new());
+    for field in fields {
+        let schema = match field.number {
+            GRAPH_INPUT | GRAPH_OUTPUT => tensor_schema(field.bytes().unwrap_or_default())?,
+            _ => continue,
+        };
+        if field.number == GRAPH_OUTPUT {
+            outputs.push(schema);
+        } else if !weights.contains(&schema.name) {
+            inputs.push(schema);
+        }
+    }
+    Ok((inputs, outputs))
+}
+
+fn tensor_schema(value_info: &[u8]) -> Result<TensorSchema> {
+    let mut schema = TensorSchema {
+        name: String::new(),
+        elem_type: elem_type_name(0).to_string(),
+        shape: Vec::new(),
+    };
+    let mut tensor_type = Vec::new();
+    for field in parse_message(value_info)? {
+        match field.number {
+            VALUE_INFO_NAME => schema.name = field.string().unwrap_or_default().to_string(),
+            VALUE_INFO_TYPE => {
+                if let Some(tensor) = parse_message(field.bytes().unwrap_or_default())?
+                    .into_iter()
+                    .find(|f| f.number == TYPE_TENSOR)
+                {
+                    tensor_type = parse_message(tensor.bytes().unwrap_or_default())?;
+                }
+            }
+            _ => {}
+        }
+    }
+    for field in tensor_type {
+        match (field.number, &field.value) {
+            (TENSOR_TYPE_ELEM_TYPE, WireValue::Varint(v)) => {
+                schema.elem_type = elem_type_name(*v).to_string()
+            }
+            (TENSOR_TYPE_SHAPE, _) => {
+                for dim in parse_message(field.bytes().unwrap_or_default())? {
+                    if dim.number == SHAPE_DIM {
+                        schema
+                            .shape
+                            .push(dimension(dim.bytes().unwrap_or_default())?);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(schema)
+}
+
+fn dimension(bytes: &[u8]) -> Result<Dimension> {
+    for field in parse_message(bytes)? {
+        match (field.number, &field.value) {
+            (DIM_VALUE, WireValue::Varint(v)) => return Ok(Dimension::Fixed(*v as i64)),
+            (DIM_PARAM, _) => {
+                return Ok(Dimension::Symbolic(
+                    field.string().unwrap_or_default().to_string(),
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(Dimension::Unknown)
+}
+
+/// Name of a `TensorProto.DataType`
+fn elem_type_name(elem_type: u64) -> &'static str {
+    match elem_type {
+        1 => "float",
+        2 => "uint8",
+        3 => "int8",
+        4 => "uint16",
+        5 => "int16",
+        6 => "int32",
+        7 => "int64",
+        8 => "string",
+        9 => "bool",
+        10 => "float16",
+        11 => "double",
+        12 => "uint32",
+        13 => "uint64",
+        16 => "bfloat16",
+        _ => "undefined",
+    }
+}
+
+fn canonicalize_graph(bytes: &[u8]) -> Result<Vec<u8>> {
+    let fields = parse_message(bytes)?;
+
+    // Renaming is skipped when control-flow subgraphs may capture outer names
+    let mut renames = if has_subgraphs(&fields)? {
+        HashMap::new()
+    } else {
+        intermediate_renames(&fields)?
+    };
+
+    let mut out = Vec::new();
+    let mut initializers = Vec::new();
+    for field in fields {
+        match field.number {
+            GRAPH_NAME | GRAPH_DOC_STRING | GRAPH_VALUE_INFO => {}
+            GRAPH_NODE => out.push(map_bytes(field, |b| canonicalize_node(b, &mut renames))?),
+            GRAPH_INITIALIZER => {
+                let field = map_bytes(field, |b| strip(b, &[TENSOR_DOC_STRING]))?;
+                let name = parse_message(field.bytes().unwrap_or_default())?
+                    .into_iter()
+                    .find(|f| f.number == TENSOR_NAME)
+                    .and_then(|f| f.string().map(String::from))
+                    .unwrap_or_default();
+                initializers.push((name, field));
+            }
+            GRAPH_INPUT | GRAPH_OUTPUT => {
+                out.push(map_bytes(field, |b| strip(b, &[VALUE_INFO_DOC_STRING]))?)
+            }
+            _ => out.push(field),
+        }
+    }
+    initializers.sort_by(|a, b| a.0.cmp(&b.0));
+    out.extend(initializers.into_iter().map(|(_, f)| f));
+
+    Ok(encode_message(sorted(out)))
+}
+
+fn canonicalize_node(bytes: &[u8], renames: &mut HashMap<String, String>) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for field in parse_message(bytes)? {
+        match field.number {
+            NODE_NAME | NODE_DOC_STRING => {}
+            NODE_INPUT | NODE_OUTPUT => {
+                let name = field.string().unwrap_or_default();
+                let renamed = renames
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| name.to_string());
+                out.push(Field {
+                    number: field.number,
+                    value: WireValue::Bytes(renamed.into_bytes()),
+                });
+            }
+            NODE_ATTRIBUTE => out.push(map_bytes(field, |b| strip(b, &[ATTRIBUTE_DOC_STRING]))?),
+            _ => out.push(field),
+        }
+    }
+    Ok(encode_message(sorted(out)))
+}
+
+/// Map intermediate tensor names to `%<n>` in order of production
+fn intermediate_renames(graph: &[Field]) -> Result<HashMap<String, String>> {
+    let mut interface = std::collections::HashSet::new();
+    for field in graph {
+        let name_field = match field.number {
+            GRAPH_INPUT | GRAPH_OUTPUT => VALUE_INFO_NAME,
+            GRAPH_INITIALIZER => TENSOR_NAME,
+            _ => continue,
+        };
+        for f in parse_message(field.bytes().unwrap_or_default())? {
+            if f.number == name_field {
+                interface.insert(f.string().unwrap_or_default().to_string());
+            }
+        }
+    }
+
+    let mut renames = HashMap::new();
+    for node in graph.iter().filter(|f| f.number == GRAPH_NODE) {
+        for f in parse_message(node.bytes().unwrap_or_default())? {
+            let name = f.string().unwrap_or_default();
+            if f.number == NODE_OUTPUT
+                && !name.is_empty()
+                && !interface.contains(name)
+                && !renames.contains_key(name)
+            {
+                let canonical = format!("%{}", renames.len());
+                renames.insert(name.to_string(), canonical);
+            }
+        }
+    }
+    Ok(renames)
+}
+
+fn has_subgraphs(graph: &[Field]) -> Result<bool> {
+    for node in graph.iter().filter(|f| f.number == GRAPH_NODE) {
+        for attr in parse_message(node.bytes().unwrap_or_default())? {
+            if attr.number != NODE_ATTRIBUTE {
+                continue;
+            }
+            if parse_message(attr.bytes().unwrap_or_default())?
+                .iter()
+                .any(|f| f.number == ATTRIBUTE_G || f.number == ATTRIBUTE_GRAPHS)
+            {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Remove the given field numbers from an embedded message
+fn strip(bytes: &[u8], drop: &[u32]) -> Result<Vec<u8>> {
+    let fields = parse_message(bytes)?
+        .into_iter()
+        .filter(|f| !drop.contains(&f.number))
+        .collect();
+    Ok(encode_message(sorted(fields)))
+}
+
+fn map_bytes(field: Field, f: impl FnOnce(&[u8]) -> Result<Vec<u8>>) -> Result<Field> {
+    match field.value {
+        WireValue::Bytes(bytes) => Ok(Field {
+            number: field.number,
+            value: WireValue::Bytes(f(&bytes)?),
+        }),
+        _ => Err(anyhow!("Field {} is not a message", field.number)),
+    }
+}
+
+/// Stable sort by field number, preserving the order of repeated fields
+fn sorted(mut fields: Vec<Field>) -> Vec<Field> {
+    fields.sort_by_key(|f| f.number);
+    fields
+}
+
+// ============================================================================
+// Wire format
+// ============================================================================
+
+/// Parse a protobuf message into its fields
+pub(crate) fn parse_message(mut buf: &[u8]) -> Result<Vec<Field>> {
+    let mut fields = Vec::new();
+    while !buf.is_empty() {
+        let key = read_varint(&mut buf)?;
+        let number = u32::try_from(key >> 3).map_err(|_| anyhow!("Field number too large"))?;
+        if number == 0 {
+            return Err(anyhow!("Invalid field number 0"));
+        }
+        let value = match key & 7 {
+            0 => WireValue::Varint(read_varint(&mut buf)?),
+            1 => WireValue::Fixed64(take(&mut buf, 8)?.try_into().unwrap()),
+            2 => {
+                let len = usize::try_from(read_varint(&mut buf)?)?;
+                WireValue::Bytes(take(&mut buf, len)?.to_vec())
+            }
+            5 => WireValue::Fixed32(take(&mut buf, 4)?.try_into().unwrap()),
+            wire_type => return Err(anyhow!("Unsupported wire type {}", wire_type)),
+        };
+        fields.push(Field { number, value });
+    }
+    Ok(fields)
+}
+
+/// Encode fields in the given order
+pub(crate) fn encode_message(fields: Vec<Field>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for field in fields {
+        let number = u64::from(field.number) << 3;
+        match field.value {
+            WireValue::Varint(v) => {
+                write_varint(&mut out, number);
+                write_varint(&mut out, v);
+            }
+            WireValue::Fixed64(b) => {
+                write_varint(&mut out, number | 1);
+                out.extend_from_slice(&b);
+            }
+            WireValue::Bytes(b) => {
+                write_varint(&mut out, number | 2);
+                write_varint(&mut out, b.len() as u64);
+                out.extend_from_slice(&b);
+            }
+            WireValue::Fixed32(b) => {
+                write_varint(&mut out, number | 5);
+                out.extend_from_slice(&b);
+            }
+        }
+    }
+    out
+}
+
+fn read_varint(buf: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf
+            .split_first()
+            .ok_or_else(|| anyhow!("Truncated varint"))?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(anyhow!("Varint too long"))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(anyhow!("Truncated field"));
+    }
+    let (head, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHORIZATION_MODEL: &[u8] =
+        include_bytes!("../jolt-atlas/models/authorization/network.onnx");
+
+    fn bytes_field(number: u32, bytes: &[u8]) -> Field {
+        Field {
+            number,
+            value: WireValue::Bytes(bytes.to_vec()),
+        }
+    }
+
+    fn string_field(number: u32, s: &str) -> Field {
+        bytes_field(number, s.as_bytes())
+    }
+
+    fn tensor(name: &str, raw: &[u8]) -> Field {
+        bytes_field(
+            GRAPH_INITIALIZER,
+            &encode_message(vec![string_field(TENSOR_NAME, name), bytes_field(9, raw)]),
+        )
+    }
+
+    fn node(name: &str, inputs: &[&str], output: &str) -> Field {
+        let mut fields: Vec<Field> = inputs.iter().map(|i| string_field(NODE_INPUT, i)).collect();
+        fields.push(string_field(NODE_OUTPUT, output));
+        fields.push(string_field(NODE_NAME, name));
+        fields.push(string_field(4, "Gemm"));
+        bytes_field(GRAPH_NODE, &encode_message(fields))
+    }
+
+    fn value_info(number: u32, name: &str) -> Field {
+        bytes_field(
+            number,
+            &encode_message(vec![string_field(VALUE_INFO_NAME, name)]),
+        )
+    }
+
+    fn model(producer: &str, graph: Vec<Field>) -> Vec<u8> {
+        encode_message(vec![
+            Field {
+                number: 1,
+                value: WireValue::Varint(8),
+            },
+            string_field(MODEL_PRODUCER_NAME, producer),
+            bytes_field(MODEL_GRAPH, &encode_message(graph)),
+        ])
+    }
+
+    #[test]
+    fn test_equivalent_exports_canonicalize_identically() {
+        let a = model(
+            "pytorch",
+            vec![
+                node("/fc1/Gemm", &["x", "w1"], "/fc1/out"),
+                node("/fc2/Gemm", &["/fc1/out", "w2"], "y"),
+                string_field(GRAPH_NAME, "main_graph"),
+                tensor("w1", b"\x01"),
+                tensor("w2", b"\x02"),
+                value_info(GRAPH_INPUT, "x"),
+                value_info(GRAPH_OUTPUT, "y"),
+            ],
+        );
+        let b = model(
+            "tf2onnx",
+            vec![
+                node("Gemm_0", &["x", "w1"], "onnx::Gemm_5"),
+                node("Gemm_1", &["onnx::Gemm_5", "w2"], "y"),
+                tensor("w2", b"\x02"),
+                tensor("w1", b"\x01"),
+                value_info(GRAPH_INPUT, "x"),
+                value_info(GRAPH_OUTPUT, "y"),
+                value_info(GRAPH_VALUE_INFO, "onnx::Gemm_5"),
+            ],
+        );
+
+        assert_ne!(a, b);
+        assert_eq!(canonicalize(&a).unwrap(), canonicalize(&b).unwrap());
+    }
+
+    #[test]
+    fn test_weight_change_changes_canonical_form() {
+        let graph = |w: &[u8]| vec![tensor("w1", w), value_info(GRAPH_INPUT, "x")];
+        assert_ne!(
+            canonicalize(&model("p", graph(b"\x01"))).unwrap(),
+            canonicalize(&model("p", graph(b"\x03"))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_real_model_ignores_docstring() {
+        let canonical = canonicalize(AUTHORIZATION_MODEL).unwrap();
+
+        // Appending a docstring field is a valid protobuf edit of the same model
+        let mut annotated = AUTHORIZATION_MODEL.to_vec();
+        annotated.extend(encode_message(vec![string_field(
+            MODEL_DOC_STRING,
+            "exported again",
+        )]));
+
+        assert_eq!(canonicalize(&annotated).unwrap(), canonical);
+        assert_eq!(canonicalize(&canonical).unwrap(), canonical);
+    }
+
+    #[test]
+    fn test_weight_tensors_sorted_by_name() {
+        let canonical = canonicalize(&model(
+            "p",
+            vec![tensor("w2", b"\x02"), tensor("w1", b"\x01")],
+        ))
+        .unwrap();
+        let names: Vec<_> = weight_tensors(&canonical)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["w1", "w2"]);
+
+        assert!(!weight_tensors(AUTHORIZATION_MODEL).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interface_skips_initializers() {
+        let shaped = |name: &str, dims: Vec<Field>| {
+            let shape = encode_message(
+                dims.into_iter()
+                    .map(|d| bytes_field(SHAPE_DIM, &encode_message(vec![d])))
+                    .collect(),
+            );
+            let tensor_type = encode_message(vec![
+                Field {
+                    number: TENSOR_TYPE_ELEM_TYPE,
+                    value: WireValue::Varint(1),
+                },
+                bytes_field(TENSOR_TYPE_SHAPE, &shape),
+            ]);
+            bytes_field(
+                GRAPH_INPUT,
+                &encode_message(vec![
+                    string_field(VALUE_INFO_NAME, name),
+                    bytes_field(
+                        VALUE_INFO_TYPE,
+                        &encode_message(vec![bytes_field(TYPE_TENSOR, &tensor_type)]),
+                    ),
+                ]),
+            )
+        };
+        let (inputs, outputs) = interface(&model(
+            "p",
+            vec![
+                shaped(
+                    "x",
+                    vec![
+                        string_field(DIM_PARAM, "batch"),
+                        Field {
+                            number: DIM_VALUE,
+                            value: WireValue::Varint(8),
+                        },
+                    ],
+                ),
+                shaped("w", vec![]),
+                tensor("w", b"\x01"),
+                value_info(GRAPH_OUTPUT, "y"),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            inputs,
+            [TensorSchema {
+                name: "x".to_string(),
+                elem_type: "float".to_string(),
+                shape: vec![
+                    Dimension::Symbolic("batch".to_string()),
+                    Dimension::Fixed(8)
+                ],
+            }]
+        );
+        assert_eq!(outputs[0].name, "y");
+        assert_eq!(outputs[0].elem_type, "undefined");
+
+        let (inputs, outputs) = interface(AUTHORIZATION_MODEL).unwrap();
+        assert!(!inputs.is_empty() && !outputs.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_non_onnx_bytes() {
+        assert!(canonicalize(b"fake onnx model data").is_err());
+        assert!(canonicalize(&[]).is_err());
+    }
+}
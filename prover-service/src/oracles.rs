@@ -0,0 +1,125 @@
+//! Signed oracle inputs
+//!
+//! A `ProveRequest` may carry an `oracle_signature` from a data oracle (a
+//! signed price feed, an exchange attestation, ...) over the input features.
+//! The service checks the signature and that the signer is one of the
+//! trusted keys in `ORACLE_KEYS` (comma-separated hex public keys), then
+//! records the oracle key id and the hash of the signed data in
+//! `PublicInputs`, so the proof attests to inference over authenticated data.
+//!
+//! Oracles sign `ORACLE_DOMAIN || inputs as little-endian f32` with the same
+//! Ed25519 / ECDSA P-256 envelope used for registrant signatures.
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::registrants::{normalize_key, RegistrantSignature};
+use crate::types::{api_error, ApiError};
+
+/// Domain separator of oracle-signed inputs
+pub const ORACLE_DOMAIN: &[u8] = b"jolt-atlas-prover/oracle-inputs/v1\n";
+
+/// Oracle signature over the input features
+pub type OracleSignature = RegistrantSignature;
+
+/// Verified oracle data as recorded in `PublicInputs`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OracleAttestation {
+    /// Short identifier derived from the oracle public key
+    pub key_id: String,
+    /// SHA-256 of the signed message
+    pub data_hash: String,
+}
+
+/// Message an oracle signs for `inputs`
+pub fn oracle_message(inputs: &[f32]) -> Vec<u8> {
+    let mut message = ORACLE_DOMAIN.to_vec();
+    for v in inputs {
+        message.extend_from_slice(&v.to_le_bytes());
+    }
+    message
+}
+
+/// Trusted oracle keys
+pub struct OracleRegistry {
+    trusted: Vec<String>,
+}
+
+impl OracleRegistry {
+    pub fn new(trusted: &[String]) -> Self {
+        Self {
+            trusted: trusted.iter().map(|k| normalize_key(k)).collect(),
+        }
+    }
+
+    /// Check an oracle signature over `inputs`
+    pub fn attest(
+        &self,
+        inputs: &[f32],
+        signature: &OracleSignature,
+    ) -> Result<OracleAttestation, ApiError> {
+        let key_id = oracle_key_id(&signature.public_key);
+        if !self.trusted.contains(&normalize_key(&signature.public_key)) {
+            return Err(api_error(
+                StatusCode::FORBIDDEN,
+                "ORACLE_NOT_TRUSTED",
+                format!("Oracle key {} is not trusted", key_id),
+            ));
+        }
+
+        let message = oracle_message(inputs);
+        signature.verify(&message).map_err(|_| {
+            api_error(
+                StatusCode::UNAUTHORIZED,
+                "INVALID_ORACLE_SIGNATURE",
+                "Oracle signature does not verify over the inputs",
+            )
+        })?;
+
+        Ok(OracleAttestation {
+            key_id,
+            data_hash: format!("0x{}", hex::encode(Sha256::digest(&message))),
+        })
+    }
+}
+
+/// Key id: truncated SHA-256 of the public key bytes
+fn oracle_key_id(public_key: &str) -> String {
+    let public = hex::decode(normalize_key(public_key)).unwrap_or_default();
+    format!("orc_{}", &hex::encode(Sha256::digest(public))[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registrants::RegistrantAlg;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_only_trusted_oracles_attest() {
+        let key = SigningKey::generate(&mut OsRng);
+        let inputs = [101.5, 99.25];
+        let signature = OracleSignature {
+            alg: RegistrantAlg::Ed25519,
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            signature: hex::encode(key.sign(&oracle_message(&inputs)).to_bytes()),
+        };
+
+        let registry = OracleRegistry::new(std::slice::from_ref(&signature.public_key));
+        let attestation = registry.attest(&inputs, &signature).unwrap();
+        assert!(attestation.key_id.starts_with("orc_"));
+        assert_eq!(
+            registry.attest(&[101.5, 0.0], &signature).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            OracleRegistry::new(&[])
+                .attest(&inputs, &signature)
+                .unwrap_err()
+                .0,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Operator overview
+//!
+//! `GET /admin/overview` gathers in one response what is otherwise pieced
+//! together from logs during an incident: queue depth per tenant (weights
+//! are the queue's priorities) and of the durable job queue, what each
+//! worker slot is proving and for how long, how much of the model cache is
+//! in use, the latencies of the most recent proofs, the most recent proving
+//! errors, and the health of the proving backend. The `/status` page renders
+//! it.
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth::AdminAuth;
+use crate::queue::{QueueStats, WorkerStatus};
+use crate::selftest::SelfTestStatus;
+use crate::AppState;
+
+/// Errors and proof latencies kept for the overview
+const MAX_RECENT: usize = 50;
+
+/// A generated proof and how long it took
+#[derive(Clone, Serialize)]
+pub struct ProofLatency {
+    pub at: u64,
+    pub model_id: String,
+    pub proving_time_ms: u64,
+}
+
+/// A failed proof
+#[derive(Clone, Serialize)]
+pub struct RecentError {
+    pub at: u64,
+    pub code: String,
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    pub tenant: String,
+}
+
+/// The most recent entries of some kind, newest first
+pub struct Recent<T> {
+    entries: Mutex<VecDeque<T>>,
+}
+
+impl<T: Clone> Recent<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push(&self, entry: T) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_front(entry);
+        entries.truncate(MAX_RECENT);
+    }
+
+    pub fn list(&self) -> Vec<T> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl<T: Clone> Default for Recent<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recent<RecentError> {
+    pub fn record(&self, code: &str, error: &str, model_id: Option<&str>, tenant: &str) {
+        self.push(RecentError {
+            at: now_secs(),
+            code: code.to_string(),
+            error: error.to_string(),
+            model_id: model_id.map(String::from),
+            tenant: tenant.to_string(),
+        });
+    }
+}
+
+impl Recent<ProofLatency> {
+    pub fn record(&self, model_id: &str, proving_time_ms: u64) {
+        self.push(ProofLatency {
+            at: now_secs(),
+            model_id: model_id.to_string(),
+            proving_time_ms,
+        });
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Durable job queue depth
+#[derive(Serialize)]
+pub struct JobQueueDepth {
+    pub queued: u64,
+    pub running: u64,
+}
+
+/// Occupancy of the model registry and its caches
+#[derive(Serialize)]
+pub struct ModelCacheOccupancy {
+    pub registered: usize,
+    pub hot: usize,
+    pub pinned: usize,
+    /// Models currently resident in memory
+    pub warm: usize,
+    pub warm_resident_bytes: u64,
+    /// Backend of the `/prove` response cache, when enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_cache: Option<&'static str>,
+}
+
+/// Health of the proving backend
+#[derive(Serialize)]
+pub struct BackendHealth {
+    pub prover: String,
+    pub mock: bool,
+    pub strict_mode: bool,
+    pub self_test: SelfTestStatus,
+}
+
+/// Everything `GET /admin/overview` reports
+#[derive(Serialize)]
+pub struct Overview {
+    pub generated_at: u64,
+    pub queue: QueueStats,
+    /// `None` when the job database could not be read
+    pub jobs: Option<JobQueueDepth>,
+    pub workers: Vec<WorkerStatus>,
+    /// Busy workers, longest running first
+    pub in_flight: Vec<WorkerStatus>,
+    pub models: ModelCacheOccupancy,
+    pub recent_proofs: Vec<ProofLatency>,
+    pub recent_errors: Vec<RecentError>,
+    pub backend: BackendHealth,
+}
+
+/// Snapshot of queues, workers, models and recent errors
+pub async fn get_overview(_admin: AdminAuth, State(state): State<Arc<AppState>>) -> Json<Overview> {
+    let workers = state.queue.workers();
+    let mut in_flight: Vec<WorkerStatus> = workers
+        .iter()
+        .filter(|worker| worker.busy)
+        .cloned()
+        .collect();
+    in_flight.sort_by_key(|worker| std::cmp::Reverse(worker.elapsed_ms));
+
+    let jobs = match state.jobs.depth() {
+        Ok((queued, running)) => Some(JobQueueDepth { queued, running }),
+        Err(e) => {
+            tracing::warn!("Failed to read job queue depth: {}", e);
+            None
+        }
+    };
+
+    let (models, backend) = {
+        let prover = state.prover.read().await;
+        let ids = prover.model_ids();
+        let warm: Vec<_> = ids.iter().filter_map(|id| prover.warm_stats(id)).collect();
+        let models = ModelCacheOccupancy {
+            registered: ids.len(),
+            hot: ids
+                .iter()
+                .filter(|id| prover.get_model(id).is_some_and(|m| m.hot))
+                .count(),
+            pinned: ids
+                .iter()
+                .filter(|id| prover.get_model(id).is_some_and(|m| m.pinned))
+                .count(),
+            warm: warm.len(),
+            warm_resident_bytes: warm.iter().map(|stats| stats.resident_bytes).sum(),
+            proof_cache: state.proof_cache.as_ref().map(|cache| cache.backend()),
+        };
+        let backend = BackendHealth {
+            prover: prover.get_prover_info().await,
+            mock: prover.is_mock().await,
+            strict_mode: !state.flags.get().accept_mock_proofs,
+            self_test: state.self_test.status(),
+        };
+        (models, backend)
+    };
+
+    Json(Overview {
+        generated_at: now_secs(),
+        queue: state.queue.stats(),
+        jobs,
+        workers,
+        in_flight,
+        models,
+        recent_proofs: state.latencies.list(),
+        recent_errors: state.errors.list(),
+        backend,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_entries_are_bounded_newest_first() {
+        let errors = Recent::<RecentError>::new();
+        for i in 0..MAX_RECENT + 5 {
+            errors.record("PROOF_GENERATION_FAILED", &i.to_string(), Some("m"), "t");
+        }
+        let list = errors.list();
+        assert_eq!(list.len(), MAX_RECENT);
+        assert_eq!(list[0].error, (MAX_RECENT + 4).to_string());
+
+        let latencies = Recent::<ProofLatency>::new();
+        latencies.record("m", 12);
+        assert_eq!(latencies.list()[0].proving_time_ms, 12);
+    }
+}
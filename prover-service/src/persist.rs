@@ -0,0 +1,59 @@
+//! JSON file persistence helpers
+//!
+//! Small stores (API keys, registry metadata) are kept as JSON documents in
+//! the data directory. Writes go to a temporary file that is renamed over
+//! the target so a crash never leaves a half-written document behind.
+//! Documents holding secrets are written readable by the owner only.
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Load a JSON document, returning `None` if the file does not exist
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let value = serde_json::from_slice(&bytes)
+                .with_context(|| format!("Corrupt JSON document {}", path.display()))?;
+            Ok(Some(value))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Atomically replace a JSON document
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_json(path, value, false)
+}
+
+/// Atomically replace a JSON document holding secrets, with mode 0600
+pub fn save_secret_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_json(path, value, true)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T, private: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // A leftover temporary file keeps its mode, so reset it
+        if tmp.exists() {
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    options
+        .open(&tmp)?
+        .write_all(&serde_json::to_vec_pretty(value)?)?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
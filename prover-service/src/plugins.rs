@@ -0,0 +1,204 @@
+//! WASM preprocessing plugins
+//!
+//! A model may be registered with a small WASM `preprocessor` that turns raw
+//! request data into its feature vector (custom normalization, feature
+//! encoding). A prove request then sends `raw` (any JSON value) instead of
+//! `inputs`; the service runs the plugin over the compact JSON encoding of
+//! `raw` and proves the model over the features it returns. The plugin's
+//! SHA-256 is folded into the model commitment the proof is made against and
+//! carried in the public inputs, next to the hash of the raw data, so a
+//! verifier knows exactly which code produced the proven features. The plugin
+//! itself runs outside the circuit.
+//!
+//! Plugins run under wasmtime with no host imports, NaN canonicalization and
+//! deterministic relaxed SIMD, metered with fuel and capped in memory, so a
+//! plugin produces the same features for the same raw data on every host.
+//! Requires the `wasm-sandbox` feature.
+//!
+//! Plugin ABI (pointers are offsets into the exported `memory`):
+//!
+//! - `alloc(len: i32) -> i32` reserves `len` bytes;
+//! - `preprocess(raw_ptr, raw_len: i32) -> i64` reads the raw JSON and
+//!   returns `features_ptr << 32 | feature_count` of little-endian f32, or a
+//!   negative error code.
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::jolt_atlas::ModelCommitments;
+use crate::predicate::{bind_both, compose};
+use crate::types::CommitmentScheme;
+
+/// Domain separator for commitments to a model composed with a plugin
+const PREPROCESSOR_DOMAIN: &str = "jolt-atlas-prover/preprocessor/v1\n";
+
+/// Fuel each plugin run may consume
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(dead_code))]
+const PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// Linear memory each plugin run may grow to
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(dead_code))]
+const PLUGIN_MEMORY_BYTES: usize = 64 << 20;
+
+/// `0x`-prefixed SHA-256 of a plugin module
+pub fn plugin_hash(module: &[u8]) -> String {
+    format!("0x{}", hex::encode(Sha256::digest(module)))
+}
+
+/// `0x`-prefixed SHA-256 of the raw data a plugin is run over
+pub fn raw_input_hash(raw: &serde_json::Value) -> String {
+    format!("0x{}", hex::encode(Sha256::digest(raw.to_string())))
+}
+
+/// Where a model's plugin is stored, next to the model file
+pub fn plugin_path(model_path: &Path) -> PathBuf {
+    model_path.with_extension("preprocessor.wasm")
+}
+
+/// Commitment to the model preceded by the plugin `plugin_hash`
+pub fn bind_commitment(
+    plugin_hash: &str,
+    scheme: CommitmentScheme,
+    model_commitment: &str,
+) -> Result<String> {
+    compose(PREPROCESSOR_DOMAIN, scheme, model_commitment, plugin_hash)
+}
+
+/// Commitments to the model preceded by the plugin `plugin_hash`
+pub fn bind(plugin_hash: &str, model: &ModelCommitments) -> Result<ModelCommitments> {
+    bind_both(model, |scheme, c| bind_commitment(plugin_hash, scheme, c))
+}
+
+/// Check that `module` is a loadable plugin
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(unused_variables))]
+pub fn validate(module: &[u8]) -> Result<()> {
+    #[cfg(feature = "wasm-sandbox")]
+    {
+        return runtime::validate(module);
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow!(
+        "Preprocessing plugins require the wasm-sandbox feature"
+    ))
+}
+
+/// Run the plugin `module` over `raw`, returning the feature vector
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(unused_variables))]
+pub fn run(module: &[u8], raw: &serde_json::Value) -> Result<Vec<f32>> {
+    #[cfg(feature = "wasm-sandbox")]
+    {
+        return runtime::run(module, raw.to_string().as_bytes());
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow!(
+        "Preprocessing plugins require the wasm-sandbox feature"
+    ))
+}
+
+/// Split a packed `features_ptr << 32 | feature_count` result
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(dead_code))]
+fn unpack_features(packed: i64) -> Result<(usize, usize)> {
+    if packed < 0 {
+        return Err(anyhow!("Preprocessing plugin failed with code {}", packed));
+    }
+    Ok(((packed >> 32) as usize, (packed & 0xffff_ffff) as usize))
+}
+
+#[cfg(feature = "wasm-sandbox")]
+mod runtime {
+    use super::*;
+    use wasmtime::{
+        Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+    };
+
+    fn engine() -> Result<Engine> {
+        let mut config = Config::new();
+        config
+            .consume_fuel(true)
+            .cranelift_nan_canonicalization(true)
+            .relaxed_simd_deterministic(true);
+        Engine::new(&config)
+    }
+
+    fn instantiate(module: &[u8]) -> Result<(Store<StoreLimits>, Instance)> {
+        let engine = engine()?;
+        let module = Module::new(&engine, module)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(PLUGIN_MEMORY_BYTES)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(PLUGIN_FUEL)?;
+
+        // No host functions: a plugin with imports fails to instantiate
+        let instance = Linker::new(&engine).instantiate(&mut store, &module)?;
+        Ok((store, instance))
+    }
+
+    pub fn validate(module: &[u8]) -> Result<()> {
+        let (mut store, instance) = instantiate(module)?;
+        instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("Preprocessing plugin exports no memory"))?;
+        instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        instance.get_typed_func::<(i32, i32), i64>(&mut store, "preprocess")?;
+        Ok(())
+    }
+
+    pub fn run(module: &[u8], raw: &[u8]) -> Result<Vec<f32>> {
+        let (mut store, instance) = instantiate(module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("Preprocessing plugin exports no memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let preprocess = instance.get_typed_func::<(i32, i32), i64>(&mut store, "preprocess")?;
+
+        let raw_ptr = alloc.call(&mut store, raw.len() as i32)?;
+        memory.write(&mut store, raw_ptr as usize, raw)?;
+        let packed = preprocess
+            .call(&mut store, (raw_ptr, raw.len() as i32))
+            .map_err(|e| match store.get_fuel() {
+                Ok(0) => anyhow!("Preprocessing plugin ran out of fuel"),
+                _ => anyhow!("Preprocessing plugin trapped: {}", e),
+            })?;
+        let (features_ptr, feature_count) = unpack_features(packed)?;
+
+        let mut features = vec![0u8; feature_count * 4];
+        memory.read(&store, features_ptr, &mut features)?;
+        Ok(features
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_hash_binds_commitment() {
+        let model = ModelCommitments::compute(b"model");
+        let plugin = plugin_hash(b"\0asm plugin");
+        let other = plugin_hash(b"\0asm other plugin");
+        assert_ne!(bind(&plugin, &model).unwrap().sha256, model.sha256);
+        assert_ne!(
+            bind(&plugin, &model).unwrap().sha256,
+            bind(&other, &model).unwrap().sha256
+        );
+        assert_eq!(
+            plugin_path(Path::new("models/m.onnx")),
+            PathBuf::from("models/m.preprocessor.wasm")
+        );
+        assert_eq!(
+            raw_input_hash(&serde_json::json!({"b": 1, "a": 2})),
+            raw_input_hash(&serde_json::json!({"a": 2, "b": 1}))
+        );
+        assert_eq!(unpack_features((64 << 32) | 3).unwrap(), (64, 3));
+        assert!(unpack_features(-1).is_err());
+    }
+}
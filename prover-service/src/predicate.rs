@@ -0,0 +1,219 @@
+//! Statements proven over model outputs
+//!
+//! In predicate mode the proven circuit is the model followed by a single
+//! comparison, e.g. `output[1] > 0.8`. Its only output is the predicate bit
+//! (`1.0` when it holds, `0.0` otherwise), so the proof and public inputs
+//! reveal whether the threshold was met but never the raw score.
+//!
+//! With an expected output, the circuit additionally checks the model output
+//! equals a pre-committed value within a declared quantization tolerance.
+//!
+//! Such checks are part of the circuit, so they are folded into the model
+//! commitment the proof is made against: a verifier recomputes that
+//! commitment from the registered model commitment and the statements, and a
+//! proof cannot be replayed under a different threshold or expected output.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use std::fmt;
+
+use crate::jolt_atlas::{hash_floats, ModelCommitments};
+use crate::types::CommitmentScheme;
+
+/// Domain separator for commitments to a model composed with a predicate
+const PREDICATE_DOMAIN: &str = "jolt-atlas-prover/predicate/v1\n";
+
+/// Domain separator for commitments to a model composed with an output check
+const EXPECTED_OUTPUT_DOMAIN: &str = "jolt-atlas-prover/expected-output/v1\n";
+
+/// Comparison applied to an output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PredicateOp {
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Gte,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Lte,
+}
+
+impl PredicateOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PredicateOp::Gt => ">",
+            PredicateOp::Gte => ">=",
+            PredicateOp::Lt => "<",
+            PredicateOp::Lte => "<=",
+        }
+    }
+}
+
+/// `output[output_index] <op> threshold`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputPredicate {
+    pub output_index: usize,
+    pub op: PredicateOp,
+    pub threshold: f32,
+}
+
+impl OutputPredicate {
+    /// Evaluate against the model output
+    pub fn evaluate(&self, output: &[f32]) -> Result<bool> {
+        let value = *output.get(self.output_index).ok_or_else(|| {
+            anyhow!(
+                "Predicate refers to output[{}] but the model has {} outputs",
+                self.output_index,
+                output.len()
+            )
+        })?;
+        Ok(match self.op {
+            PredicateOp::Gt => value > self.threshold,
+            PredicateOp::Gte => value >= self.threshold,
+            PredicateOp::Lt => value < self.threshold,
+            PredicateOp::Lte => value <= self.threshold,
+        })
+    }
+
+    /// The circuit output proving this predicate evaluated to `holds`
+    pub fn disclosed_output(holds: bool) -> Vec<f32> {
+        vec![if holds { 1.0 } else { 0.0 }]
+    }
+
+    /// Commitment to the model composed with this predicate under `scheme`
+    pub fn bind_commitment(
+        &self,
+        scheme: CommitmentScheme,
+        model_commitment: &str,
+    ) -> Result<String> {
+        let statement = format!("{}\n{}", self, hex::encode(self.threshold.to_le_bytes()));
+        compose(PREDICATE_DOMAIN, scheme, model_commitment, &statement)
+    }
+
+    /// Commitments to the model composed with this predicate
+    pub fn bind(&self, model: &ModelCommitments) -> Result<ModelCommitments> {
+        bind_both(model, |scheme, c| self.bind_commitment(scheme, c))
+    }
+}
+
+/// Check that the output equals a pre-committed value
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedOutput {
+    /// `hash_floats` of the expected output
+    pub expected_output_hash: String,
+    /// Maximum absolute difference allowed per element
+    pub tolerance: f32,
+}
+
+impl ExpectedOutput {
+    pub fn new(expected: &[f32], tolerance: f32) -> Self {
+        Self {
+            expected_output_hash: hash_floats(expected),
+            tolerance,
+        }
+    }
+
+    /// Check `output` against `expected` within the tolerance
+    pub fn matches(expected: &[f32], output: &[f32], tolerance: f32) -> bool {
+        expected.len() == output.len()
+            && expected
+                .iter()
+                .zip(output)
+                .all(|(e, o)| (e - o).abs() <= tolerance)
+    }
+
+    /// Commitment to the model composed with this check under `scheme`
+    pub fn bind_commitment(
+        &self,
+        scheme: CommitmentScheme,
+        model_commitment: &str,
+    ) -> Result<String> {
+        let statement = format!(
+            "{}\n{}",
+            self.expected_output_hash,
+            hex::encode(self.tolerance.to_le_bytes())
+        );
+        compose(EXPECTED_OUTPUT_DOMAIN, scheme, model_commitment, &statement)
+    }
+
+    /// Commitments to the model composed with this check
+    pub fn bind(&self, model: &ModelCommitments) -> Result<ModelCommitments> {
+        bind_both(model, |scheme, c| self.bind_commitment(scheme, c))
+    }
+}
+
+/// Commitment to the model extended by `statement`, under `scheme`
+pub fn compose(
+    domain: &str,
+    scheme: CommitmentScheme,
+    model_commitment: &str,
+    statement: &str,
+) -> Result<String> {
+    let message = format!("{}{}\n{}", domain, model_commitment, statement);
+    let digest = match scheme {
+        // Both SHA-256 schemes commit to the model the same way
+        CommitmentScheme::Sha256V1 | CommitmentScheme::Sha256V2 => Sha256::digest(message).to_vec(),
+        CommitmentScheme::Keccak256V1 => Keccak256::digest(message).to_vec(),
+        other => return Err(anyhow!("Unsupported commitment scheme: {}", other)),
+    };
+    Ok(format!("0x{}", hex::encode(digest)))
+}
+
+/// Apply `bind` to both of a model's commitments
+pub fn bind_both(
+    model: &ModelCommitments,
+    bind: impl Fn(CommitmentScheme, &str) -> Result<String>,
+) -> Result<ModelCommitments> {
+    Ok(ModelCommitments {
+        sha256: bind(CommitmentScheme::Sha256V1, &model.sha256)?,
+        keccak256: bind(CommitmentScheme::Keccak256V1, &model.keccak256)?,
+    })
+}
+
+impl fmt::Display for OutputPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "output[{}] {} {}",
+            self.output_index,
+            self.op.as_str(),
+            self.threshold
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predicate_evaluates_and_binds_threshold() {
+        let predicate: OutputPredicate =
+            serde_json::from_str(r#"{"output_index":1,"op":">","threshold":0.8}"#).unwrap();
+        assert_eq!(predicate.to_string(), "output[1] > 0.8");
+        assert!(predicate.evaluate(&[0.1, 0.9]).unwrap());
+        assert!(!predicate.evaluate(&[0.9, 0.1]).unwrap());
+        assert!(predicate.evaluate(&[0.9]).is_err());
+
+        let model = ModelCommitments::compute(b"model");
+        let looser = OutputPredicate {
+            threshold: 0.5,
+            ..predicate.clone()
+        };
+        assert_ne!(
+            predicate.bind(&model).unwrap().sha256,
+            looser.bind(&model).unwrap().sha256
+        );
+        assert_ne!(predicate.bind(&model).unwrap().sha256, model.sha256);
+    }
+
+    #[test]
+    fn test_expected_output_tolerance() {
+        assert!(ExpectedOutput::matches(&[0.5, 0.25], &[0.5004, 0.25], 1e-3));
+        assert!(!ExpectedOutput::matches(&[0.5, 0.25], &[0.51, 0.25], 1e-3));
+        assert!(!ExpectedOutput::matches(&[0.5], &[0.5, 0.25], 1e-3));
+    }
+}
@@ -0,0 +1,261 @@
+//! Proof cache
+//!
+//! With `PROOF_CACHE_TTL_SECS` set, `POST /prove` responses are cached per
+//! API key and tenant, keyed by a hash of the request itself and its
+//! `Idempotency-Key` header, if any. Anonymous callers all share one key id,
+//! so a reused idempotency key with a different request body never returns
+//! another caller's proof. A repeated request gets the cached, already
+//! signed response (marked `X-Proof-Cache: hit`) without proving again. A
+//! hit still counts against the key's proof quota and is metered as a
+//! cached proof, but consumes no proving time.
+//!
+//! The cache lives in memory by default, so each replica only sees its own
+//! proofs. With `REDIS_URL` set (build with `--features redis`) it lives in
+//! Redis instead, and a proof generated on one replica is served from the
+//! cache by every other.
+
+// Without the redis feature only the in-memory backend is compiled in
+#![cfg_attr(not(feature = "redis"), allow(unused_variables))]
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::auth::Caller;
+use crate::config::ServiceConfig;
+use crate::metering::Meter;
+use crate::quotas::{QuotaManager, QuotaRemaining};
+use crate::types::{ApiError, ProveRequest, ProveResponse};
+
+/// Response header reporting whether a proof came from the cache
+pub const CACHE_STATUS_HEADER: &str = "x-proof-cache";
+
+/// Cache of signed prove responses
+pub struct ProofCache {
+    ttl: Duration,
+    backend: Backend,
+}
+
+enum Backend {
+    Memory(Mutex<HashMap<String, (Instant, ProveResponse)>>),
+    #[cfg(feature = "redis")]
+    Redis(redis::aio::ConnectionManager),
+}
+
+impl ProofCache {
+    /// The configured cache, or `None` when caching is disabled
+    pub async fn from_config(config: &ServiceConfig) -> Result<Option<Self>> {
+        let Some(ttl_secs) = config.proof_cache_ttl_secs else {
+            return Ok(None);
+        };
+        let ttl = Duration::from_secs(ttl_secs);
+        let backend = match &config.redis_url {
+            None => return Ok(Some(Self::in_memory(ttl))),
+            #[cfg(feature = "redis")]
+            Some(url) => {
+                let client = redis::Client::open(url.as_str())?;
+                Backend::Redis(redis::aio::ConnectionManager::new(client).await?)
+            }
+            #[cfg(not(feature = "redis"))]
+            Some(_) => return Err(anyhow!("REDIS_URL requires the `redis` feature")),
+        };
+        #[allow(unreachable_code)]
+        Ok(Some(Self { ttl, backend }))
+    }
+
+    /// In-memory cache holding entries for `ttl`
+    pub fn in_memory(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            backend: Backend::Memory(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Name of the backend, for logs
+    pub fn backend(&self) -> &'static str {
+        match self.backend {
+            Backend::Memory(_) => "memory",
+            #[cfg(feature = "redis")]
+            Backend::Redis(_) => "redis",
+        }
+    }
+
+    /// Cache key of a request made by `caller`
+    pub fn key(caller: &Caller, idempotency_key: Option<&str>, request: &ProveRequest) -> String {
+        let mut hasher = Sha256::new();
+        for part in [&caller.key_id, &caller.tenant] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        if let Some(key) = idempotency_key {
+            hasher.update(b"idempotency-key");
+            hasher.update(key.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(b"request");
+        hasher.update(serde_json::to_vec(request).unwrap_or_default());
+        format!("proof-cache:{}", hex::encode(hasher.finalize()))
+    }
+
+    /// Cached response for `key`; backend errors count as a miss
+    pub async fn get(&self, key: &str) -> Option<ProveResponse> {
+        match self.fetch(key).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!("Proof cache lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Cache `response` under `key`; failures are logged, not returned
+    pub async fn put(&self, key: &str, response: &ProveResponse) {
+        if let Err(e) = self.store(key, response).await {
+            tracing::warn!("Failed to cache proof: {}", e);
+        }
+    }
+
+    async fn fetch(&self, key: &str) -> Result<Option<ProveResponse>> {
+        match &self.backend {
+            Backend::Memory(entries) => {
+                let entries = entries.lock().unwrap();
+                Ok(entries
+                    .get(key)
+                    .filter(|(expires, _)| *expires > Instant::now())
+                    .map(|(_, response)| response.clone()))
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(connection) => {
+                use redis::AsyncCommands;
+                let value: Option<Vec<u8>> = connection.clone().get(key).await?;
+                value
+                    .map(|bytes| serde_json::from_slice(&bytes).map_err(|e| anyhow!(e)))
+                    .transpose()
+            }
+        }
+    }
+
+    async fn store(&self, key: &str, response: &ProveResponse) -> Result<()> {
+        match &self.backend {
+            Backend::Memory(entries) => {
+                let now = Instant::now();
+                let mut entries = entries.lock().unwrap();
+                entries.retain(|_, (expires, _)| *expires > now);
+                entries.insert(key.to_string(), (now + self.ttl, response.clone()));
+                Ok(())
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(connection) => {
+                use redis::AsyncCommands;
+                let value = serde_json::to_vec(response)?;
+                let ttl = self.ttl.as_secs().max(1);
+                let _: () = connection.clone().set_ex(key, value, ttl).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Charge `caller` for `response`, served from the cache
+pub fn charge_hit(
+    quotas: &QuotaManager,
+    meter: &Meter,
+    caller: &Caller,
+    response: &ProveResponse,
+) -> Result<QuotaRemaining, ApiError> {
+    let remaining = quotas.reserve(&caller.key_id)?.commit(Duration::ZERO);
+    meter.record_cached_proof(caller, response.proof.len());
+    Ok(remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(key_id: &str) -> Caller {
+        Caller {
+            key_id: key_id.to_string(),
+            tenant: "desk".to_string(),
+            scopes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_proofs_are_scoped_and_expire() {
+        let request = ProveRequest {
+            model_id: "m".to_string(),
+            inputs: vec![1.0],
+            ..Default::default()
+        };
+        let key = ProofCache::key(&caller("key_a"), None, &request);
+        assert_eq!(key, ProofCache::key(&caller("key_a"), None, &request));
+        assert_ne!(key, ProofCache::key(&caller("key_b"), None, &request));
+        assert_ne!(
+            key,
+            ProofCache::key(&caller("key_a"), Some("once"), &request)
+        );
+        let other_tenant = Caller {
+            tenant: "other".to_string(),
+            ..caller("key_a")
+        };
+        assert_ne!(key, ProofCache::key(&other_tenant, None, &request));
+
+        // Reusing an idempotency key with another body is a different entry
+        let other = ProveRequest {
+            inputs: vec![2.0],
+            ..request.clone()
+        };
+        let anonymous = Caller::anonymous();
+        assert_ne!(
+            ProofCache::key(&anonymous, Some("once"), &request),
+            ProofCache::key(&anonymous, Some("once"), &other)
+        );
+
+        let response: ProveResponse = serde_json::from_value(serde_json::json!({
+            "success": true,
+            "model_id": "m",
+            "proof": "0x00",
+            "model_commitment": "0xaa",
+            "input_hash": "0xbb",
+            "output_hash": "0xcc",
+            "public_inputs": {
+                "model_commitment": "0xaa",
+                "input_hash": "0xbb",
+                "output_hash": "0xcc",
+                "output": [0.5],
+                "timestamp": 0
+            },
+            "proving_time_ms": 1
+        }))
+        .unwrap();
+
+        let cache = ProofCache::in_memory(Duration::from_secs(60));
+        assert!(cache.get(&key).await.is_none());
+        cache.put(&key, &response).await;
+        assert_eq!(cache.get(&key).await.unwrap().proof, "0x00");
+
+        let expired = ProofCache::in_memory(Duration::ZERO);
+        expired.put(&key, &response).await;
+        assert!(expired.get(&key).await.is_none());
+
+        // Hits are charged against the proof quota and metered
+        let (quotas, meter) = (QuotaManager::new(), Meter::new());
+        quotas
+            .set_limits(
+                "key_a",
+                crate::quotas::QuotaLimits {
+                    daily_proofs: Some(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let remaining = charge_hit(&quotas, &meter, &caller("key_a"), &response).unwrap();
+        assert_eq!(remaining.proofs, Some(0));
+        assert!(charge_hit(&quotas, &meter, &caller("key_a"), &response).is_err());
+        let usage = &meter.snapshot().keys[0].usage;
+        assert_eq!((usage.proofs, usage.cached_proofs), (1, 1));
+        assert_eq!(usage.proving_seconds, 0.0);
+    }
+}
@@ -0,0 +1,273 @@
+//! Proof store
+//!
+//! Every proof has a content-addressed id, `proof:sha256:<hex>`: the SHA-256
+//! of its proof bytes (the base64-decoded `proof`). Anyone holding the bytes
+//! derives the same id, so parties referring to a proof by id always mean
+//! the same bytes. Prove responses carry the id and the service signature
+//! covers it (see `signing`).
+//!
+//! Proofs produced without a client waiting on the response (scheduled
+//! jobs, for now) are kept as one JSON document per proof under `proofs/`
+//! in the configured storage backend, keyed by the id's digest, and served
+//! from `GET /proofs/:id`, which checks the stored bytes still hash to the
+//! id. Proofs stored before ids were content-addressed keep their `prf_`
+//! ids. With a proof TTL configured they are removed by the retention task
+//! once expired.
+//!
+//! `GET /proofs/:id/bundle` exports a stored proof as a single-file bundle
+//! for offline verification (see `bundle`).
+//!
+//! `POST /proofs/inspect` decodes any proof, stored or not, and reports its
+//! format, backend, public inputs and component sizes and whether it is
+//! well-formed, without verifying it (see `verification::inspect_proof`).
+//! With `?explain=true` it adds a plain-language summary (see `inspect`).
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::storage::Storage;
+use crate::types::{api_error, ApiError, InspectProofRequest, ProveResponse};
+use crate::verification::{decode_proof, inspect_proof, ProofInspection};
+use crate::AppState;
+
+/// Prefix of content-addressed proof ids
+pub const PROOF_ID_PREFIX: &str = "proof:sha256:";
+
+/// Content-addressed id of a proof (base64, as in prove responses); a proof
+/// that is not valid base64 is hashed as is
+pub fn proof_id(proof: &str) -> String {
+    let bytes = BASE64
+        .decode(proof)
+        .unwrap_or_else(|_| proof.as_bytes().to_vec());
+    format!("{}{}", PROOF_ID_PREFIX, hex::encode(Sha256::digest(bytes)))
+}
+
+/// Lowercase hex digest named by a proof id, or `None` if `id` is not one
+pub fn proof_digest(id: &str) -> Option<String> {
+    let digest = id.strip_prefix(PROOF_ID_PREFIX)?;
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| digest.to_ascii_lowercase())
+}
+
+/// A stored proof
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredProof {
+    pub id: String,
+    pub created_at: u64,
+    /// What produced the proof, e.g. `schedule:<id>`
+    pub source: String,
+    /// Key id the proof was generated for
+    pub key_id: String,
+    /// Tenant the proof is attributed to
+    #[serde(default)]
+    pub tenant: String,
+    #[serde(flatten)]
+    pub response: ProveResponse,
+}
+
+/// Stored proofs, one JSON object per proof under `proofs/`
+pub struct ProofStore {
+    storage: Arc<dyn Storage>,
+}
+
+impl ProofStore {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Store a proof, returning its record
+    pub async fn put(
+        &self,
+        source: &str,
+        caller: &Caller,
+        response: ProveResponse,
+    ) -> Result<StoredProof> {
+        let proof = StoredProof {
+            id: proof_id(&response.proof),
+            created_at: now_secs(),
+            source: source.to_string(),
+            key_id: caller.key_id.clone(),
+            tenant: caller.tenant.clone(),
+            response,
+        };
+        let digest = proof_digest(&proof.id).expect("proof ids are well-formed");
+        self.storage
+            .put(&key(&digest), serde_json::to_vec_pretty(&proof)?)
+            .await?;
+        Ok(proof)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<StoredProof>> {
+        if let Some(digest) = proof_digest(id) {
+            let Some(proof) = self.load(&key(&digest)).await? else {
+                return Ok(None);
+            };
+            if proof_digest(&proof_id(&proof.response.proof)).as_deref() != Some(&digest) {
+                return Err(anyhow!("Stored proof {} does not hash to its id", id));
+            }
+            return Ok(Some(proof));
+        }
+        // Ids issued before proofs were content-addressed
+        if !id.starts_with("prf_") || !id[4..].chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        self.load(&key(id)).await
+    }
+
+    /// Remove proofs older than their tenant's TTL, returning how many
+    pub async fn expire(&self, ttl: &(dyn Fn(&str) -> Option<Duration> + Sync)) -> Result<usize> {
+        let now = now_secs();
+        let mut removed = 0;
+        for object in self.storage.list(PREFIX).await? {
+            let Some(proof) = self.load(&object.key).await? else {
+                continue;
+            };
+            let expired = ttl(&proof.tenant)
+                .is_some_and(|ttl| now.saturating_sub(proof.created_at) >= ttl.as_secs());
+            if expired {
+                self.storage.delete(&object.key).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Number of stored proofs and their total size in bytes
+    pub async fn usage(&self) -> Result<(u64, u64)> {
+        let objects = self.storage.list(PREFIX).await?;
+        Ok((
+            objects.len() as u64,
+            objects.iter().map(|object| object.size).sum(),
+        ))
+    }
+
+    async fn load(&self, key: &str) -> Result<Option<StoredProof>> {
+        self.storage
+            .get(key)
+            .await?
+            .map(|bytes| {
+                serde_json::from_slice(&bytes)
+                    .with_context(|| format!("Corrupt stored proof {}", key))
+            })
+            .transpose()
+    }
+}
+
+/// Key prefix of stored proofs
+const PREFIX: &str = "proofs/";
+
+/// Key of the proof stored under `name`: an id's digest, or a legacy id
+fn key(name: &str) -> String {
+    format!("{}{}.json", PREFIX, name)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Routes mounted under `/proofs`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/inspect", post(inspect))
+        .route("/:id", get(get_proof))
+        .route("/:id/bundle", get(crate::bundle::get_bundle))
+}
+
+#[derive(Deserialize)]
+pub struct InspectQuery {
+    /// Add a plain-language summary of the proof
+    #[serde(default)]
+    explain: bool,
+}
+
+/// Decode a proof and report its structure, without verifying it
+pub async fn inspect(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Query(query): Query<InspectQuery>,
+    Json(request): Json<InspectProofRequest>,
+) -> Result<Json<ProofInspection>, ApiError> {
+    caller.require(Scope::Verify)?;
+    let mut inspection = inspect_proof(&request.proof);
+    if query.explain {
+        let prover = state.prover.read().await;
+        let model_id = decode_proof(&request.proof)
+            .and_then(|proof| prover.proven_model(&proof).map(|model| model.id.clone()));
+        inspection.explanation = Some(crate::inspect::explain(&inspection, model_id.as_deref()));
+    }
+    Ok(Json(inspection))
+}
+
+/// Fetch a stored proof generated for the calling key
+async fn get_proof(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> Result<Json<StoredProof>, ApiError> {
+    state
+        .proofs
+        .get(&id)
+        .await
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PROOF_STORE_UNREADABLE",
+                e.to_string(),
+            )
+        })?
+        .filter(|proof| proof.key_id == caller.key_id)
+        .map(Json)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "PROOF_NOT_FOUND",
+                format!("No stored proof {}", id),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_ids_are_content_addressed() {
+        let proof = BASE64.encode(b"proof bytes");
+        let id = proof_id(&proof);
+        assert_eq!(
+            id,
+            format!(
+                "{}{}",
+                PROOF_ID_PREFIX,
+                hex::encode(Sha256::digest(b"proof bytes"))
+            )
+        );
+        assert_eq!(proof_id(&proof), id);
+        assert_ne!(proof_id(&BASE64.encode(b"other bytes")), id);
+
+        let upper = format!(
+            "{}{}",
+            PROOF_ID_PREFIX,
+            id[PROOF_ID_PREFIX.len()..].to_ascii_uppercase()
+        );
+        let digest = proof_digest(&upper);
+        assert_eq!(digest.as_deref(), id.strip_prefix(PROOF_ID_PREFIX));
+        assert_eq!(proof_digest("prf_0123"), None);
+        assert_eq!(proof_digest("proof:sha256:abc"), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Streaming batch proving
+//!
+//! `POST /prove/stream` takes newline-delimited JSON prove requests and
+//! streams back one newline-delimited result per request as each proof
+//! finishes, so batch clients see results incrementally. Results arrive in
+//! completion order; each carries the `index` of its request (blank lines
+//! are not counted) and either the `proof` or an `error`. At most
+//! `PROVE_CONCURRENCY` requests of a stream are in flight at once, and the
+//! body is read no faster than they complete.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::types::{api_error, ApiError, ErrorResponse, ProveRequest, ProveResponse};
+use crate::AppState;
+
+/// Content type of newline-delimited JSON
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Longest request line accepted
+const MAX_LINE_BYTES: usize = 64 << 20;
+
+/// Result of one request of a stream
+#[derive(Serialize)]
+struct StreamResult {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<ProveResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorResponse>,
+}
+
+impl StreamResult {
+    fn new(index: usize, result: Result<ProveResponse, ApiError>) -> Self {
+        match result {
+            Ok(proof) => Self {
+                index,
+                proof: Some(proof),
+                error: None,
+            },
+            Err((_, error)) => Self {
+                index,
+                proof: None,
+                error: Some(error.0),
+            },
+        }
+    }
+
+    fn to_line(&self) -> Bytes {
+        let mut line = serde_json::to_vec(self).unwrap_or_default();
+        line.push(b'\n');
+        Bytes::from(line)
+    }
+}
+
+/// Prove each line of an NDJSON body, streaming results as they finish
+pub async fn prove_stream(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    body: Body,
+) -> Result<Response, ApiError> {
+    caller.require(Scope::Prove)?;
+
+    let concurrency = state.config.prove_concurrency.max(1);
+    let (tx, rx) = mpsc::channel(concurrency);
+    tokio::spawn(run(state, caller, body, tx, concurrency));
+
+    let results = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|line| (Ok::<_, Infallible>(line), rx))
+    });
+    Ok((
+        [(CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(results),
+    )
+        .into_response())
+}
+
+/// Read request lines and prove them, sending each result to `tx`
+async fn run(
+    state: Arc<AppState>,
+    caller: Caller,
+    body: Body,
+    tx: mpsc::Sender<Bytes>,
+    concurrency: usize,
+) {
+    let in_flight = Arc::new(Semaphore::new(concurrency));
+    let mut chunks = body.into_data_stream();
+    let mut lines = LineBuffer::default();
+    let mut index = 0;
+
+    loop {
+        let chunk = chunks.next().await;
+        let finished = match chunk {
+            Some(Ok(bytes)) => {
+                lines.extend(&bytes);
+                false
+            }
+            Some(Err(e)) => {
+                let error = invalid_stream(format!("Failed to read request body: {}", e));
+                let _ = tx
+                    .send(StreamResult::new(index, Err(error)).to_line())
+                    .await;
+                return;
+            }
+            None => true,
+        };
+
+        let mut ready = lines.take_lines();
+        if finished {
+            ready.extend(lines.take_rest());
+        } else if lines.pending() > MAX_LINE_BYTES {
+            let error = invalid_stream(format!(
+                "Request lines are limited to {} bytes",
+                MAX_LINE_BYTES
+            ));
+            let _ = tx
+                .send(StreamResult::new(index, Err(error)).to_line())
+                .await;
+            return;
+        }
+
+        for line in ready {
+            // Wait for a free slot, so a slow stream is not read ahead of
+            // its proofs
+            let Ok(permit) = in_flight.clone().acquire_owned().await else {
+                return;
+            };
+            if tx.is_closed() {
+                tracing::debug!("Prove stream from {} closed by client", caller.key_id);
+                return;
+            }
+            let (state, caller, tx) = (state.clone(), caller.clone(), tx.clone());
+            tokio::spawn(async move {
+                let result = match serde_json::from_slice::<ProveRequest>(&line) {
+                    Ok(request) => crate::execute_proof(&state, &caller, request)
+                        .await
+                        .map(|(_, response)| response),
+                    Err(e) => Err(invalid_stream(format!("Invalid request: {}", e))),
+                };
+                let _ = tx.send(StreamResult::new(index, result).to_line()).await;
+                drop(permit);
+            });
+            index += 1;
+        }
+
+        if finished {
+            tracing::info!(
+                "Prove stream from {} sent {} requests",
+                caller.key_id,
+                index
+            );
+            return;
+        }
+    }
+}
+
+fn invalid_stream(message: impl Into<String>) -> ApiError {
+    api_error(StatusCode::BAD_REQUEST, "INVALID_STREAM_REQUEST", message)
+}
+
+/// Splits body chunks into non-blank lines
+#[derive(Default)]
+struct LineBuffer {
+    buffer: Vec<u8>,
+    scanned: usize,
+}
+
+impl LineBuffer {
+    fn extend(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Bytes of the incomplete last line
+    fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Complete lines received so far
+    fn take_lines(&mut self) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for end in self.scanned..self.buffer.len() {
+            if self.buffer[end] == b'\n' {
+                push_line(&mut lines, &self.buffer[start..end]);
+                start = end + 1;
+            }
+        }
+        self.buffer.drain(..start);
+        self.scanned = self.buffer.len();
+        lines
+    }
+
+    /// A final line without a trailing newline
+    fn take_rest(&mut self) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        push_line(&mut lines, &self.buffer);
+        self.buffer.clear();
+        self.scanned = 0;
+        lines
+    }
+}
+
+fn push_line(lines: &mut Vec<Vec<u8>>, line: &[u8]) {
+    if !line.trim_ascii().is_empty() {
+        lines.push(line.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut lines = LineBuffer::default();
+        lines.extend(b"{\"a\":1}\n{\"b\"");
+        assert_eq!(lines.take_lines(), vec![b"{\"a\":1}".to_vec()]);
+        lines.extend(b":2}\r\n\n  \n{\"c\":3}");
+        assert_eq!(lines.take_lines(), vec![b"{\"b\":2}\r".to_vec()]);
+        assert_eq!(lines.pending(), 7);
+        assert_eq!(lines.take_rest(), vec![b"{\"c\":3}".to_vec()]);
+        assert!(lines.take_rest().is_empty());
+
+        let error = StreamResult::new(2, Err(invalid_stream("bad")));
+        assert_eq!(
+            error.to_line(),
+            Bytes::from_static(
+                b"{\"index\":2,\"error\":{\"error\":\"bad\",\"code\":\"INVALID_STREAM_REQUEST\"}}\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Model provenance
+//!
+//! A registrant may attach a provenance record describing where a model came
+//! from: the hash of its training data, the framework that produced it, who
+//! trained it and where its model card lives. The record is signed by the
+//! trainer's Ed25519 key and its hash is folded, together with the model
+//! commitment, into an extended commitment, so a proof over the model is
+//! also a statement about its provenance.
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Provenance fields covered by the signature and the provenance hash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvenanceClaims {
+    /// Hash of the training dataset
+    pub training_data_hash: String,
+
+    /// Framework and version used for training/export (e.g. `pytorch-2.2.1`)
+    pub framework_version: String,
+
+    /// Identity of the trainer (person, team or pipeline)
+    pub trainer: String,
+
+    /// Where the model card is published
+    pub model_card_uri: Option<String>,
+}
+
+impl ProvenanceClaims {
+    /// Bytes the trainer signs: a domain tag followed by the claims as JSON
+    pub fn signing_message(&self) -> Result<Vec<u8>> {
+        let mut message = b"jolt-atlas-prover/provenance/v1\n".to_vec();
+        message.extend_from_slice(&serde_json::to_vec(self)?);
+        Ok(message)
+    }
+
+    /// SHA-256 of the signing message
+    pub fn hash(&self) -> Result<String> {
+        Ok(format!(
+            "0x{}",
+            hex::encode(Sha256::digest(self.signing_message()?))
+        ))
+    }
+}
+
+/// Signed provenance record submitted at registration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvenanceRecord {
+    #[serde(flatten)]
+    pub claims: ProvenanceClaims,
+
+    /// Trainer's Ed25519 public key (hex)
+    pub public_key: String,
+
+    /// Ed25519 signature over the claims (hex)
+    pub signature: String,
+}
+
+impl ProvenanceRecord {
+    /// Check the trainer's signature over the claims
+    pub fn verify(&self) -> Result<()> {
+        let public: [u8; 32] = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid provenance public key"))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid provenance signature encoding"))?;
+
+        VerifyingKey::from_bytes(&public)
+            .map_err(|_| anyhow!("Invalid provenance public key"))?
+            .verify(
+                &self.claims.signing_message()?,
+                &Signature::from_bytes(&signature),
+            )
+            .map_err(|_| anyhow!("Provenance signature does not verify"))
+    }
+}
+
+/// Provenance as stored with a registered model
+#[derive(Clone, Debug, Serialize)]
+pub struct ModelProvenance {
+    pub record: ProvenanceRecord,
+    pub provenance_hash: String,
+    pub extended_commitment: String,
+}
+
+impl ModelProvenance {
+    /// Verify a submitted record and bind it to the model commitment
+    pub fn bind(record: ProvenanceRecord, model_commitment: &str) -> Result<Self> {
+        record.verify()?;
+        let provenance_hash = record.claims.hash()?;
+        Ok(Self {
+            extended_commitment: extended_commitment(model_commitment, &provenance_hash),
+            provenance_hash,
+            record,
+        })
+    }
+}
+
+/// Commitment binding a model to its provenance
+///
+/// `sha256("jolt-atlas-prover/extended-commitment/v1\n" || commitment || provenance_hash)`
+pub fn extended_commitment(model_commitment: &str, provenance_hash: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"jolt-atlas-prover/extended-commitment/v1\n");
+    hasher.update(model_commitment.as_bytes());
+    hasher.update(provenance_hash.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    fn signed(claims: ProvenanceClaims) -> ProvenanceRecord {
+        let key = SigningKey::generate(&mut OsRng);
+        let signature = key.sign(&claims.signing_message().unwrap());
+        ProvenanceRecord {
+            claims,
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    fn claims() -> ProvenanceClaims {
+        ProvenanceClaims {
+            training_data_hash: "0xdata".to_string(),
+            framework_version: "pytorch-2.2.1".to_string(),
+            trainer: "risk-team".to_string(),
+            model_card_uri: Some("https://example.com/card".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_signed_record_verifies_and_tampering_fails() {
+        let mut record = signed(claims());
+        assert!(record.verify().is_ok());
+
+        record.claims.trainer = "someone-else".to_string();
+        assert!(record.verify().is_err());
+    }
+
+    #[test]
+    fn test_extended_commitment_depends_on_provenance() {
+        let a = extended_commitment("0xmodel", &claims().hash().unwrap());
+        let mut other = claims();
+        other.training_data_hash = "0xother".to_string();
+        let b = extended_commitment("0xmodel", &other.hash().unwrap());
+        assert_ne!(a, b);
+    }
+}
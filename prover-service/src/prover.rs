@@ -6,29 +6,567 @@
 //! - Model commitment computation
 
 use anyhow::{anyhow, Result};
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 
+use crate::breaker::{BreakerConfig, Breakers, ONNX_RUNTIME};
+use crate::checkpoint::{Checkpoint, CheckpointConfig};
+use crate::inference::{self, InferenceEngine, NamedOutput};
+use crate::isolation::{Cancellation, CpuLimitExceeded, Isolation, JobCancelled};
 use crate::jolt_atlas::{
-    create_prover, compute_model_commitment, deserialize_proof, hash_floats,
-    serialize_proof, JoltAtlasProof, ZkmlProver,
+    circuit_hash, circuit_mismatch, create_backend, create_prover, deserialize_proof, hash_tensor,
+    is_mock_prover, named_output_hash, proof_format, serialize_proof, JoltAtlasProof,
+    ModelCommitments, ProveOptions, ZkmlProver,
 };
+use crate::memory::{self, ResourceExhausted};
+use crate::onnx;
+use crate::plugins;
+use crate::predicate::{ExpectedOutput, OutputPredicate};
+use crate::provenance::ModelProvenance;
+use crate::quantization::Quantization;
+use crate::queue::ModelLimits;
+use crate::registrants::Registrant;
+use crate::sandbox::{ModelExecution, WasmSandbox};
+use crate::setup::SetupJob;
+use crate::sharding;
+use crate::storage::Storage;
+use crate::tenants::OutputDisclosure;
 use crate::types::*;
+use crate::verification::{ct_eq, verify_commitments};
+use crate::warm::{WarmModel, WarmStats};
+use crate::weights::WeightsTree;
+use crate::witness::WitnessBudget;
+
+/// Minimum challenge length in bytes
+const MIN_CHALLENGE_BYTES: usize = 16;
+
+/// Validate a hex challenge and bring it into canonical `0x`-prefixed form
+pub fn normalize_challenge(challenge: &str) -> Result<String> {
+    let bytes = hex::decode(challenge.trim_start_matches("0x"))
+        .map_err(|_| anyhow!("Challenge must be hex"))?;
+    if bytes.len() < MIN_CHALLENGE_BYTES {
+        return Err(anyhow!(
+            "Challenge must be at least {} bytes",
+            MIN_CHALLENGE_BYTES
+        ));
+    }
+    Ok(format!("0x{}", hex::encode(bytes)))
+}
+
+/// Commitment `proof` must have been made against for a model committed to
+/// by `model_commitment`: the model composed with the proof's preprocessing
+/// plugin, quantization, expected output check and predicate
+fn proven_commitment(
+    proof: &JoltAtlasProof,
+    scheme: CommitmentScheme,
+    model_commitment: &str,
+) -> Result<String> {
+    bind_statements(
+        scheme,
+        model_commitment,
+        proof.preprocessor.as_deref(),
+        proof.quantization.as_ref(),
+        proof.expected_output.as_ref(),
+        proof.predicate.as_ref(),
+    )
+}
+
+/// `model_commitment` composed with a preprocessing plugin, quantization,
+/// expected output check and predicate, in the order they are proven
+pub fn bind_statements(
+    scheme: CommitmentScheme,
+    model_commitment: &str,
+    preprocessor: Option<&str>,
+    quantization: Option<&Quantization>,
+    expected_output: Option<&ExpectedOutput>,
+    predicate: Option<&OutputPredicate>,
+) -> Result<String> {
+    let mut commitment = model_commitment.to_string();
+    if let Some(plugin_hash) = preprocessor {
+        commitment = plugins::bind_commitment(plugin_hash, scheme, &commitment)?;
+    }
+    if let Some(quantization) = quantization {
+        commitment = quantization.bind_commitment(scheme, &commitment)?;
+    }
+    if let Some(check) = expected_output {
+        commitment = check.bind_commitment(scheme, &commitment)?;
+    }
+    if let Some(predicate) = predicate {
+        commitment = predicate.bind_commitment(scheme, &commitment)?;
+    }
+    Ok(commitment)
+}
+
+/// Check `public_inputs` describe the same statement as `proof`
+pub fn check_public_inputs(proof: &JoltAtlasProof, public_inputs: &PublicInputs) -> Result<()> {
+    // Proofs with output checks commit to the model composed with them
+    if public_inputs.predicate != proof.predicate
+        || public_inputs.expected_output != proof.expected_output
+        || public_inputs.quantization != proof.quantization
+        || public_inputs.preprocessor != proof.preprocessor
+    {
+        return Err(anyhow!(
+            "Output checks, quantization or preprocessor in public inputs do not match the proof"
+        ));
+    }
+    // Public inputs recorded before circuit hashes carry none
+    if public_inputs.circuit_hash.is_some() && public_inputs.circuit_hash != proof.circuit_hash {
+        return Err(anyhow!(
+            "Circuit hash in public inputs does not match the proof"
+        ));
+    }
+    if public_inputs
+        .prover_id
+        .as_ref()
+        .is_some_and(|prover_id| *prover_id != proof.prover_id)
+    {
+        return Err(anyhow!("Backend in public inputs does not match the proof"));
+    }
+    Ok(())
+}
+
+/// Whether each tensor in `outputs` hashes to its recorded hash, and all of
+/// them together to the output `proof` was made for
+pub fn named_outputs_match(proof: &JoltAtlasProof, outputs: &[OutputTensor]) -> bool {
+    if proof.commitment_scheme != CommitmentScheme::Sha256V2 {
+        return false;
+    }
+    let model = proof.model_commitment.as_str();
+    let all: Vec<f32> = outputs
+        .iter()
+        .flat_map(|o| o.values.iter().copied())
+        .collect();
+    outputs
+        .iter()
+        .all(|o| ct_eq(&named_output_hash(model, &o.name, &o.values), &o.hash))
+        && ct_eq(
+            &hash_tensor(model, "output", &[all.len()], &all),
+            &proof.output_hash,
+        )
+}
+
+/// Strict mode refused a mock inference or proof
+#[derive(Debug, thiserror::Error)]
+#[error("Strict mode: {0}")]
+pub struct StrictModeViolation(pub String);
+
+/// The request's inputs cannot be fed to the model, or its predicate or
+/// challenge is malformed
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid input: {0}")]
+pub struct InvalidInput(pub String);
+
+/// The model output does not match the expected output
+#[derive(Debug, thiserror::Error)]
+#[error("Model output differs from the expected output by more than {tolerance}")]
+pub struct OutputMismatch {
+    pub tolerance: f32,
+}
 
 /// Jolt Atlas prover wrapper
 pub struct JoltAtlasProver {
     /// Registered models
     models: HashMap<String, ModelInfo>,
 
+    /// Model ids in registration order
+    registration_order: Vec<String>,
+
     /// Model storage directory
     model_dir: PathBuf,
 
     /// The underlying zkML prover
     zkml_prover: Arc<RwLock<Box<dyn ZkmlProver>>>,
+
+    /// How proving jobs are isolated from the service
+    isolation: Isolation,
+
+    /// Engine for models registered with WASM execution
+    wasm_sandbox: Option<WasmSandbox>,
+
+    /// Where long proofs checkpoint their progress (none when unset)
+    checkpoints: Option<CheckpointConfig>,
+
+    /// Memory budget for streamed witness generation (none when unset)
+    witness_budget: Option<WitnessBudget>,
+
+    /// Most memory a proving job may use (none when unset)
+    memory_budget: Option<u64>,
+
+    /// Remote storage model files are copied to on registration
+    storage: Option<Arc<dyn Storage>>,
+
+    /// Resident state of hot models, by model id
+    warm: HashMap<String, WarmModel>,
+
+    /// Names of models warmed whenever registered
+    hot_names: HashSet<String>,
+
+    /// Refuse mock inference and mock proofs
+    strict: bool,
+
+    /// Prove on the GPU, if the backend supports it
+    gpu: bool,
+
+    /// Engine native inference runs on
+    inference_engine: InferenceEngine,
+
+    /// Alternate backend under evaluation
+    alternate: Option<AlternateBackend>,
+
+    /// Extra backends consensus proofs are cross-checked on
+    consensus: Vec<AlternateBackend>,
+
+    /// Backend a sample of requests is shadow-proven on (see `shadow`)
+    shadow: Option<AlternateBackend>,
+
+    /// Backends, in order, proofs are retried on when the preferred one fails
+    fallback: Vec<AlternateBackend>,
+
+    /// Circuit breakers around the backends and the ONNX runtime
+    breakers: Arc<Breakers>,
+}
+
+/// A backend proofs can be routed to instead of the default one
+struct AlternateBackend {
+    /// Spec it was created from, passed on to worker processes
+    spec: String,
+    prover: Arc<RwLock<Box<dyn ZkmlProver>>>,
+    /// Circuit version, which identifies the backend (see `capabilities`)
+    id: String,
+    prover_id: String,
+    proof_version: u8,
+}
+
+impl AlternateBackend {
+    fn create(spec: &str) -> Result<Self> {
+        let backend = create_backend(spec)?;
+        Ok(Self {
+            spec: spec.to_string(),
+            id: backend.circuit_version(),
+            prover_id: backend.prover_id().to_string(),
+            proof_version: backend.proof_version(),
+            prover: Arc::new(RwLock::new(backend)),
+        })
+    }
+}
+
+/// Whether `e` is the backend's fault rather than the request's, so that
+/// another backend or a later attempt could succeed
+fn backend_fault(e: &anyhow::Error) -> bool {
+    !(e.is::<OutputMismatch>()
+        || e.is::<InvalidInput>()
+        || e.is::<JobCancelled>()
+        || e.is::<StrictModeViolation>()
+        || e.is::<ResourceExhausted>()
+        || e.is::<CpuLimitExceeded>())
+}
+
+/// A prove request asked for a backend or proof format the service lacks
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct UnsupportedSelection(pub String);
+
+/// A proving job: inference plus proof generation for one request
+///
+/// Self-contained so it can run on its own task or in a worker process.
+#[derive(Serialize, Deserialize)]
+pub struct ProveJob {
+    pub model_path: PathBuf,
+    /// The model file, already in memory when the model is warm
+    #[serde(skip)]
+    pub model_bytes: Option<Arc<Vec<u8>>>,
+    pub commitment: String,
+    pub circuit_commitment: String,
+    pub request: ProveRequest,
+    /// Run inference in this sandbox instead of natively
+    pub sandbox: Option<WasmSandbox>,
+    /// Checkpoint proving progress here, if the backend supports it
+    pub checkpoints: Option<CheckpointConfig>,
+    /// Shards to split the proof into, if the backend supports it
+    pub shards: u32,
+    /// Stream the witness within this budget, if the backend supports it
+    pub witness_budget: Option<WitnessBudget>,
+    /// Most memory the job may use, in bytes (see `memory`)
+    #[serde(default)]
+    pub memory_budget: Option<u64>,
+    /// Fixed-point encoding of a quantized model
+    pub quantization: Option<Quantization>,
+    /// Hash of the plugin that produced the inputs from raw data
+    #[serde(default)]
+    pub preprocessor: Option<String>,
+    /// Refuse to fall back to mock inference or a mock prover
+    #[serde(default)]
+    pub strict: bool,
+    /// Prove on the GPU, if the backend supports it
+    #[serde(default)]
+    pub gpu: bool,
+    /// Spec of the backend to prove with instead of the default one
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Engine native inference runs on
+    #[serde(default)]
+    pub inference_engine: InferenceEngine,
+    /// Set once the request waiting for the job is abandoned
+    #[serde(skip)]
+    pub cancellation: Option<Cancellation>,
+}
+
+impl ProveJob {
+    fn commitments(&self) -> ModelCommitments {
+        ModelCommitments {
+            sha256: self.commitment.clone(),
+            keccak256: self.circuit_commitment.clone(),
+        }
+    }
+
+    /// Fail with `JobCancelled` once the job was cancelled
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation {
+            Some(cancellation) => cancellation.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Run inference and prove with `prover`
+    pub async fn run(&self, prover: &dyn ZkmlProver) -> Result<ProofResult> {
+        let request = &self.request;
+        self.check_cancelled()?;
+
+        // Refuse a job that cannot fit its memory budget before loading it
+        if let Some(budget) = self.memory_budget {
+            let model_bytes = match &self.model_bytes {
+                Some(bytes) => bytes.len() as u64,
+                None => std::fs::metadata(&self.model_path)?.len(),
+            };
+            let witness = self
+                .witness_budget
+                .as_ref()
+                .filter(|_| prover.supports_witness_streaming());
+            memory::check_estimate(model_bytes, witness, budget)?;
+        }
+
+        // Run ONNX inference to get outputs; inputs the model cannot take are
+        // the request's fault, not the backend's
+        let inference_start = Instant::now();
+        let outputs = match &self.sandbox {
+            Some(sandbox) => {
+                let bytes = match &self.model_bytes {
+                    Some(bytes) => bytes.clone(),
+                    None => Arc::new(std::fs::read(&self.model_path)?),
+                };
+                inference::check_inputs(&bytes, &request.inputs)?;
+                inference::single_output(&bytes, sandbox.infer(&bytes, &request.inputs)?)
+            }
+            None => {
+                JoltAtlasProver::run_inference(
+                    &self.model_path,
+                    &request.inputs,
+                    self.inference_engine,
+                    self.strict,
+                )
+                .await?
+            }
+        };
+        let mut output = inference::flatten(&outputs);
+
+        let inference_ms = inference_start.elapsed().as_millis() as u64;
+
+        // A model with a preprocessing plugin is proven together with it, and
+        // a quantized model together with its fixed-point encoding
+        let mut model = self.commitments();
+        if let Some(plugin_hash) = &self.preprocessor {
+            model = plugins::bind(plugin_hash, &model)?;
+        }
+        if let Some(quantization) = &self.quantization {
+            model = quantization.bind(&model)?;
+        }
+
+        // With an expected output, the circuit also checks the output equals it
+        let expected_output = match &request.expected_output {
+            Some(expected) => {
+                if !ExpectedOutput::matches(expected, &output, request.output_tolerance) {
+                    return Err(OutputMismatch {
+                        tolerance: request.output_tolerance,
+                    }
+                    .into());
+                }
+                let check = ExpectedOutput::new(expected, request.output_tolerance);
+                model = check.bind(&model)?;
+                Some(check)
+            }
+            None => None,
+        };
+
+        // In predicate mode, prove the model composed with the predicate so
+        // only the predicate bit leaves the circuit
+        if let Some(predicate) = &request.predicate {
+            let holds = predicate
+                .evaluate(&output)
+                .map_err(|e| InvalidInput(e.to_string()))?;
+            output = OutputPredicate::disclosed_output(holds);
+            model = predicate.bind(&model)?;
+        }
+
+        if self.strict && is_mock_prover(prover.prover_id()) {
+            return Err(StrictModeViolation(format!(
+                "refusing to prove with the mock backend {}",
+                prover.prover_id()
+            ))
+            .into());
+        }
+
+        // Generate zkML proof
+        self.check_cancelled()?;
+        let challenge = request
+            .challenge
+            .as_deref()
+            .map(normalize_challenge)
+            .transpose()
+            .map_err(|e| InvalidInput(e.to_string()))?;
+        if self.witness_budget.is_some() && !prover.supports_witness_streaming() {
+            tracing::warn!(
+                "{} cannot stream the witness; proving without a memory budget",
+                prover.prover_id()
+            );
+        }
+        let options = ProveOptions {
+            checkpoint: self
+                .checkpoints
+                .as_ref()
+                .filter(|_| prover.supports_checkpoints())
+                .map(|config| {
+                    Checkpoint::for_job(
+                        config,
+                        &model,
+                        &request.inputs,
+                        &output,
+                        challenge.as_deref(),
+                    )
+                }),
+            witness_budget: self
+                .witness_budget
+                .clone()
+                .filter(|_| prover.supports_witness_streaming()),
+            gpu: self.gpu && prover.supports_gpu(),
+            memory_budget: self.memory_budget,
+            cancellation: self.cancellation.clone(),
+        };
+        let proof_start = Instant::now();
+        let mut proof = if self.shards > 1 && prover.supports_sharding() {
+            sharding::prove_sharded(
+                prover,
+                &model,
+                &request.inputs,
+                &output,
+                challenge.as_deref(),
+                self.shards,
+            )?
+        } else {
+            prover.prove_with(
+                &model,
+                &request.inputs,
+                &output,
+                challenge.as_deref(),
+                &options,
+            )?
+        };
+        if let Some(checkpoint) = &options.checkpoint {
+            if let Err(e) = checkpoint.clear() {
+                tracing::warn!("Failed to remove proving checkpoint: {}", e);
+            }
+        }
+        let proving_ms = proof_start.elapsed().as_millis() as u64;
+        self.check_cancelled()?;
+        proof.expected_output = expected_output.clone();
+        proof.predicate = request.predicate.clone();
+        proof.quantization = self.quantization.clone();
+        proof.preprocessor = self.preprocessor.clone();
+
+        // With hash-only disclosure the output leaves the job as its hash
+        // alone; the proof binds the output by hash, so it verifies without it
+        let hash_only = request.output_disclosure == Some(OutputDisclosure::HashOnly);
+        if hash_only {
+            proof.outputs.clear();
+        }
+
+        // Serialize proof
+        let serialization_start = Instant::now();
+        let proof_encoded = serialize_proof(&proof)?;
+
+        // Compute hashes for public inputs, bound to the proven model
+        let input_hash = model.input_hash(&request.inputs);
+        let output_hash = model.output_hash(&output);
+
+        // Each tensor of a multi-output model is also hashed on its own, so
+        // consumers can check the outputs they use without the others
+        let named_outputs =
+            (outputs.len() > 1 && request.predicate.is_none() && !hash_only).then(|| {
+                outputs
+                    .iter()
+                    .map(|o| OutputTensor {
+                        hash: model.named_output_hash(&o.name, &o.values),
+                        name: o.name.clone(),
+                        values: o.values.clone(),
+                    })
+                    .collect()
+            });
+
+        let public_inputs = PublicInputs {
+            model_commitment: self.commitment.clone(),
+            input_hash: input_hash.clone(),
+            output_hash: output_hash.clone(),
+            output: if hash_only {
+                Vec::new()
+            } else {
+                output.clone()
+            },
+            outputs: named_outputs,
+            timestamp: proof.timestamp,
+            commitment_scheme: CommitmentScheme::Sha256V2,
+            circuit_commitments: proof.circuit_commitments.clone(),
+            predicate: request.predicate.clone(),
+            challenge,
+            expected_output_enforced: expected_output.is_some(),
+            expected_output,
+            market_data: None,
+            quantization: self.quantization.clone(),
+            preprocessor: self.preprocessor.clone(),
+            raw_input_hash: None,
+            circuit_hash: proof.circuit_hash.clone(),
+            prover_id: Some(proof.prover_id.clone()),
+            oracle: None,
+            input_commitment: None,
+            rollout: None,
+            consensus: None,
+        };
+
+        let backend = proof.backend_timings.unwrap_or_default();
+        let timings = StageTimings {
+            validation_ms: 0,
+            inference_ms,
+            witness_ms: backend.witness_ms,
+            commitment_ms: backend.commitment_ms,
+            proof_ms: proving_ms
+                .saturating_sub(backend.witness_ms.unwrap_or(0))
+                .saturating_sub(backend.commitment_ms.unwrap_or(0)),
+            serialization_ms: serialization_start.elapsed().as_millis() as u64,
+        };
+
+        Ok(ProofResult {
+            proof: proof_encoded,
+            model_commitment: self.commitment.clone(),
+            input_hash,
+            output_hash,
+            public_inputs,
+            fallback: None,
+            cpu_time_ms: None,
+            timings: Some(timings),
+        })
+    }
 }
 
 impl JoltAtlasProver {
@@ -51,23 +589,354 @@ impl JoltAtlasProver {
 
         Ok(Self {
             models: HashMap::new(),
+            registration_order: Vec::new(),
             model_dir,
             zkml_prover: Arc::new(RwLock::new(zkml_prover)),
+            isolation: Isolation::default(),
+            wasm_sandbox: None,
+            checkpoints: None,
+            witness_budget: None,
+            memory_budget: None,
+            storage: None,
+            warm: HashMap::new(),
+            hot_names: HashSet::new(),
+            strict: false,
+            gpu: false,
+            inference_engine: InferenceEngine::default(),
+            alternate: None,
+            consensus: Vec::new(),
+            shadow: None,
+            fallback: Vec::new(),
+            breakers: Arc::default(),
         })
     }
 
+    /// Set how proving jobs are isolated
+    pub fn set_isolation(&mut self, isolation: Isolation) {
+        self.isolation = isolation;
+    }
+
+    /// Set the engine sandboxed models run in
+    pub fn set_wasm_sandbox(&mut self, sandbox: Option<WasmSandbox>) {
+        self.wasm_sandbox = sandbox;
+    }
+
+    /// Set where long proofs checkpoint their progress
+    pub fn set_checkpoints(&mut self, checkpoints: Option<CheckpointConfig>) {
+        self.checkpoints = checkpoints;
+    }
+
+    /// Set the memory budget for streamed witness generation
+    pub fn set_witness_budget(&mut self, budget: Option<WitnessBudget>) {
+        self.witness_budget = budget;
+    }
+
+    /// Set the most memory a proving job may use
+    pub fn set_memory_budget(&mut self, budget: Option<u64>) {
+        self.memory_budget = budget;
+    }
+
+    /// Copy registered model files to remote storage
+    pub fn set_storage(&mut self, storage: Option<Arc<dyn Storage>>) {
+        self.storage = storage;
+    }
+
+    /// Refuse mock inference and mock proofs
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Prove on the GPU when the backend supports it
+    pub fn set_gpu(&mut self, gpu: bool) {
+        self.gpu = gpu;
+    }
+
+    /// Run native inference on `engine`
+    pub fn set_inference_engine(&mut self, engine: InferenceEngine) {
+        self.inference_engine = engine;
+    }
+
+    /// Engine native inference runs on
+    pub fn inference_engine(&self) -> InferenceEngine {
+        self.inference_engine
+    }
+
+    /// Create the alternate backend experiments route proofs through
+    pub fn set_alternate_backend(&mut self, spec: &str) -> Result<()> {
+        let backend = AlternateBackend::create(spec)?;
+        tracing::info!(
+            "Alternate backend {} ({}) available for experiments",
+            backend.prover_id,
+            spec
+        );
+        self.alternate = Some(backend);
+        Ok(())
+    }
+
+    /// Whether an alternate backend is configured
+    pub fn has_alternate_backend(&self) -> bool {
+        self.alternate.is_some()
+    }
+
+    /// Create the extra backends consensus proofs are cross-checked on
+    pub fn set_consensus_backends(&mut self, specs: &[String]) -> Result<()> {
+        self.consensus = specs
+            .iter()
+            .map(|spec| {
+                let backend = AlternateBackend::create(spec)?;
+                tracing::info!("Consensus backend {} ({})", backend.prover_id, spec);
+                Ok(backend)
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// Create the backend a sample of requests is shadow-proven on
+    pub fn set_shadow_backend(&mut self, spec: &str) -> Result<()> {
+        let backend = AlternateBackend::create(spec)?;
+        tracing::info!("Shadow backend {} ({})", backend.prover_id, spec);
+        self.shadow = Some(backend);
+        Ok(())
+    }
+
+    /// Set when backend circuit breakers open and how they recover
+    pub fn set_breakers(&mut self, config: BreakerConfig) {
+        self.breakers = Arc::new(Breakers::new(config));
+    }
+
+    /// Create the backends proofs fall back to, in order
+    pub fn set_fallback_backends(&mut self, specs: &[String]) -> Result<()> {
+        self.fallback = specs
+            .iter()
+            .map(|spec| {
+                let backend = AlternateBackend::create(spec)?;
+                tracing::info!("Fallback backend {} ({})", backend.prover_id, spec);
+                Ok(backend)
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// Prover id of the shadow backend, if one is configured
+    pub fn shadow_backend(&self) -> Option<String> {
+        self.shadow
+            .as_ref()
+            .map(|backend| backend.prover_id.clone())
+    }
+
+    /// Prover ids of the extra consensus backends
+    pub fn consensus_backends(&self) -> Vec<String> {
+        self.consensus
+            .iter()
+            .map(|backend| backend.prover_id.clone())
+            .collect()
+    }
+
+    /// Every configured backend, what the service uses it for and what it
+    /// supports
+    pub async fn capabilities(&self) -> Vec<BackendCapabilities> {
+        let roles = std::iter::once((&self.zkml_prover, "default"))
+            .chain(self.alternate.iter().map(|b| (&b.prover, "experiment")))
+            .chain(self.consensus.iter().map(|b| (&b.prover, "consensus")))
+            .chain(self.fallback.iter().map(|b| (&b.prover, "fallback")))
+            .chain(self.shadow.iter().map(|b| (&b.prover, "shadow")));
+        let mut backends: Vec<BackendCapabilities> = Vec::new();
+        for (prover, role) in roles {
+            let prover = prover.read().await;
+            let id = prover.circuit_version();
+            if let Some(backend) = backends.iter_mut().find(|backend| backend.id == id) {
+                if !backend.roles.contains(&role) {
+                    backend.roles.push(role);
+                }
+                continue;
+            }
+            let breaker = self.breakers.state(&id);
+            backends.push(BackendCapabilities {
+                id,
+                prover_id: prover.prover_id().to_string(),
+                roles: vec![role],
+                selectable: false,
+                breaker,
+                proof_format: proof_format(prover.proof_version()),
+                checkpoints: prover.supports_checkpoints(),
+                witness_streaming: prover.supports_witness_streaming(),
+                gpu: prover.supports_gpu(),
+                sharding: prover.supports_sharding(),
+            });
+        }
+        // Shadow backends are still being validated
+        for backend in &mut backends {
+            backend.selectable = backend.roles.iter().any(|role| *role != "shadow");
+        }
+        backends
+    }
+
+    /// Backends other than the default one that requests may select
+    fn selectable(&self) -> impl Iterator<Item = &AlternateBackend> {
+        self.alternate
+            .iter()
+            .chain(&self.consensus)
+            .chain(&self.fallback)
+    }
+
+    /// Backend `request` selects with `backend` and `proof_format`, `None`
+    /// standing for the default one
+    async fn selected_backend(&self, request: &ProveRequest) -> Result<Option<&AlternateBackend>> {
+        if request.backend.is_none() && request.proof_format.is_none() {
+            return Ok(None);
+        }
+        let default = {
+            let prover = self.zkml_prover.read().await;
+            (
+                None,
+                prover.circuit_version(),
+                prover.prover_id().to_string(),
+                prover.proof_version(),
+            )
+        };
+        let others = self.selectable().map(|backend| {
+            (
+                Some(backend),
+                backend.id.clone(),
+                backend.prover_id.clone(),
+                backend.proof_version,
+            )
+        });
+        // Backends are named by id, or by prover id with the default first
+        let named: Vec<_> = std::iter::once(default)
+            .chain(others)
+            .filter(|(_, id, prover_id, _)| {
+                request
+                    .backend
+                    .as_ref()
+                    .is_none_or(|name| name == id || name == prover_id)
+            })
+            .collect();
+        if named.is_empty() {
+            return Err(UnsupportedSelection(format!(
+                "No backend {}; see GET /capabilities",
+                request.backend.as_deref().unwrap_or_default()
+            ))
+            .into());
+        }
+        named
+            .into_iter()
+            .find(|(.., version)| {
+                request
+                    .proof_format
+                    .as_ref()
+                    .is_none_or(|format| *format == proof_format(*version))
+            })
+            .map(|(backend, ..)| backend)
+            .ok_or_else(|| {
+                UnsupportedSelection(format!(
+                    "No {} backend produces {} proofs; see GET /capabilities",
+                    request.backend.as_deref().unwrap_or("selectable"),
+                    request.proof_format.as_deref().unwrap_or_default()
+                ))
+                .into()
+            })
+    }
+
+    /// Backend to verify `proof` with: the configured backend that made it,
+    /// by prover id and circuit, or else the default one
+    async fn verifier_for(&self, proof: &JoltAtlasProof) -> Arc<RwLock<Box<dyn ZkmlProver>>> {
+        {
+            let prover = self.zkml_prover.read().await;
+            if prover.prover_id() == proof.prover_id
+                && circuit_mismatch(proof, &prover.circuit_version()).is_none()
+            {
+                return self.zkml_prover.clone();
+            }
+        }
+        self.selectable()
+            .chain(&self.shadow)
+            .find(|backend| {
+                backend.prover_id == proof.prover_id
+                    && circuit_mismatch(proof, &backend.id).is_none()
+            })
+            .map_or_else(
+                || self.zkml_prover.clone(),
+                |backend| backend.prover.clone(),
+            )
+    }
+
+    /// Circuit version of the backend that verifies `proof`
+    pub async fn circuit_version_for(&self, proof: &JoltAtlasProof) -> String {
+        self.verifier_for(proof)
+            .await
+            .read()
+            .await
+            .circuit_version()
+    }
+
+    /// Whether the configured backend is the mock prover
+    pub async fn is_mock(&self) -> bool {
+        is_mock_prover(self.zkml_prover.read().await.prover_id())
+    }
+
+    /// Set the names of models warmed whenever they are registered
+    pub fn set_hot_models(&mut self, names: impl IntoIterator<Item = String>) {
+        self.hot_names = names.into_iter().collect();
+    }
+
     /// Register an ONNX model
-    pub async fn register_model(&mut self, request: &RegisterModelRequest) -> Result<ModelInfo> {
+    ///
+    /// `registrant` is the already verified uploader signature, if any.
+    pub async fn register_model(
+        &mut self,
+        request: &RegisterModelRequest,
+        registrant: Option<Registrant>,
+    ) -> Result<ModelInfo> {
+        let model_id = uuid::Uuid::new_v4().to_string();
+        self.register_model_as(model_id, request, registrant).await
+    }
+
+    /// Register an ONNX model under a well-known id
+    pub async fn register_model_as(
+        &mut self,
+        model_id: String,
+        request: &RegisterModelRequest,
+        registrant: Option<Registrant>,
+    ) -> Result<ModelInfo> {
+        if self.models.contains_key(&model_id) {
+            return Err(anyhow!("Model {} is already registered", model_id));
+        }
+        if request.execution == ModelExecution::Wasm && self.wasm_sandbox.is_none() {
+            return Err(anyhow!("WASM execution is not configured on this service"));
+        }
+
+        let shards = sharding::validate_shards(request.shards.unwrap_or(1))?;
+        let limits = request.limits.validate()?;
+        if let Some(quantization) = &request.quantization {
+            quantization.validate()?;
+        }
+
         // Decode model bytes
-        let model_bytes = BASE64
-            .decode(&request.model_bytes)
-            .map_err(|e| anyhow!("Invalid base64: {}", e))?;
+        let model_bytes = request.decode_model_bytes()?;
+        let preprocessor = request.decode_preprocessor()?;
+        if let Some(module) = &preprocessor {
+            plugins::validate(module)?;
+        }
 
-        // Compute model commitment (hash of weights)
-        let commitment = compute_model_commitment(&model_bytes);
+        // Compute model commitment over the canonical form, so re-exports of
+        // the same model commit identically
+        let canonical = onnx::canonicalize(&model_bytes)?;
+        let (commitments, weights_tree) = std::thread::scope(|scope| {
+            let weights_tree = scope.spawn(|| WeightsTree::build(&canonical));
+            let commitments = ModelCommitments::compute(&canonical);
+            (commitments, weights_tree.join())
+        });
+        let weights_root = weights_tree
+            .map_err(|_| anyhow!("Weights tree hashing panicked"))??
+            .root();
 
-        // Generate model ID
-        let model_id = uuid::Uuid::new_v4().to_string();
+        // Verify provenance and fold it into an extended commitment
+        let provenance = request
+            .provenance
+            .clone()
+            .map(|record| ModelProvenance::bind(record, &commitments.sha256))
+            .transpose()?;
 
         // Save model to disk
         let model_path = self.model_dir.join(format!("{}.onnx", model_id));
@@ -75,85 +944,550 @@ impl JoltAtlasProver {
 
         // Verify model can be loaded
         self.verify_model_loadable(&model_path).await?;
+        if let Some(module) = &preprocessor {
+            std::fs::write(plugins::plugin_path(&model_path), module)?;
+        }
+        if let Some(storage) = &self.storage {
+            storage
+                .put(&format!("models/{}.onnx", model_id), model_bytes)
+                .await?;
+        }
 
         let model_info = ModelInfo {
             id: model_id.clone(),
             name: request.name.clone(),
-            commitment,
+            description: request.description.clone(),
+            commitment: commitments.sha256,
+            circuit_commitment: commitments.keccak256,
+            weights_root,
+            provenance,
+            registrant,
+            archived_at: None,
+            execution: request.execution,
+            shards,
+            hot: request.hot || self.hot_names.contains(&request.name),
+            pinned: false,
+            limits,
+            quantization: request.quantization.clone(),
+            preprocessor: preprocessor.as_deref().map(plugins::plugin_hash),
             path: model_path,
         };
 
-        self.models.insert(model_id, model_info.clone());
+        self.registration_order.push(model_id.clone());
+        self.models.insert(model_id.clone(), model_info.clone());
+        if model_info.hot {
+            // A model that fails to warm still serves proofs, just cold
+            if let Err(e) = self.warm_model(&model_id).await {
+                tracing::warn!("Failed to warm model {}: {}", model_id, e);
+            }
+        }
 
         Ok(model_info)
     }
 
-    /// Get model commitment by ID
-    pub fn get_model_commitment(&self, model_id: &str) -> Option<String> {
-        self.models.get(model_id).map(|m| m.commitment.clone())
+    /// Get a model by ID
+    pub fn get_model(&self, model_id: &str) -> Option<&ModelInfo> {
+        self.models.get(model_id)
+    }
+
+    /// Registered models, in registration order
+    pub fn models(&self) -> impl Iterator<Item = &ModelInfo> {
+        self.registration_order
+            .iter()
+            .filter_map(|id| self.models.get(id))
+    }
+
+    /// Ids of all registered models
+    pub fn model_ids(&self) -> Vec<String> {
+        self.models.keys().cloned().collect()
+    }
+
+    /// Directory registered model files are stored in
+    pub fn model_dir(&self) -> &std::path::Path {
+        &self.model_dir
+    }
+
+    /// Most recently registered model with the given name
+    pub fn latest_model(&self, name: &str) -> Option<&ModelInfo> {
+        self.registration_order
+            .iter()
+            .rev()
+            .filter_map(|id| self.models.get(id))
+            .find(|m| m.name == name && m.archived_at.is_none())
+    }
+
+    /// Undo a registration that could not be recorded
+    pub fn rollback_registration(&mut self, model_id: &str) {
+        self.purge_model(model_id);
+    }
+
+    /// Archive (`Some(timestamp)`) or restore (`None`) a model
+    pub fn set_archived(&mut self, model_id: &str, archived_at: Option<u64>) -> Option<&ModelInfo> {
+        let model_info = self.models.get_mut(model_id)?;
+        model_info.archived_at = archived_at;
+        Some(model_info)
     }
 
-    /// Generate a zkML proof
+    /// Change the shard count of a model's proofs
+    pub fn set_shards(&mut self, model_id: &str, shards: u32) -> Option<&ModelInfo> {
+        let model = self.models.get_mut(model_id)?;
+        model.shards = shards;
+        Some(model)
+    }
+
+    /// Change the proving limits of a model
+    pub fn set_limits(&mut self, model_id: &str, limits: ModelLimits) -> Option<&ModelInfo> {
+        let model = self.models.get_mut(model_id)?;
+        model.limits = limits;
+        Some(model)
+    }
+
+    /// Pin (`true`) or unpin (`false`) a model against archiving and eviction
+    pub fn set_pinned(&mut self, model_id: &str, pinned: bool) -> Option<&ModelInfo> {
+        let model = self.models.get_mut(model_id)?;
+        model.pinned = pinned;
+        Some(model)
+    }
+
+    /// Pin (`true`) or unpin (`false`) a model as hot, warming or releasing it
+    pub async fn set_hot(&mut self, model_id: &str, hot: bool) -> Result<&ModelInfo> {
+        let model = self
+            .models
+            .get_mut(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        model.hot = hot;
+        if hot {
+            self.warm_model(model_id).await?;
+        } else {
+            self.warm.remove(model_id);
+        }
+        Ok(&self.models[model_id])
+    }
+
+    /// Load a model and its proving preprocessing into memory
+    pub async fn warm_model(&mut self, model_id: &str) -> Result<WarmStats> {
+        let model_info = self
+            .models
+            .get(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        let commitments = ModelCommitments {
+            sha256: model_info.commitment.clone(),
+            keccak256: model_info.circuit_commitment.clone(),
+        };
+        let warm = {
+            let prover = self.zkml_prover.read().await;
+            WarmModel::load(&model_info.path, &commitments, prover.as_ref())?
+        };
+        let stats = warm.stats;
+        tracing::info!(
+            "Model {} warm in {}ms ({} bytes resident)",
+            model_id,
+            stats.warm_ms,
+            stats.resident_bytes
+        );
+        self.warm.insert(model_id.to_string(), warm);
+        Ok(stats)
+    }
+
+    /// The proving setup of a model, to run without holding the registry
+    pub fn setup_job(&self, model_id: &str) -> Option<SetupJob> {
+        let model_info = self.models.get(model_id)?;
+        Some(SetupJob {
+            model: ModelCommitments {
+                sha256: model_info.commitment.clone(),
+                keccak256: model_info.circuit_commitment.clone(),
+            },
+            prover: self.zkml_prover.clone(),
+        })
+    }
+
+    /// Warm-up statistics of a model, if it is warm
+    pub fn warm_stats(&self, model_id: &str) -> Option<WarmStats> {
+        self.warm.get(model_id).map(|warm| warm.stats)
+    }
+
+    /// Remove a model from the registry and delete its file
+    pub fn purge_model(&mut self, model_id: &str) -> Option<ModelInfo> {
+        self.warm.remove(model_id);
+        self.registration_order.retain(|id| id != model_id);
+        let model_info = self.models.remove(model_id)?;
+        let _ = std::fs::remove_file(&model_info.path);
+        if model_info.preprocessor.is_some() {
+            let _ = std::fs::remove_file(plugins::plugin_path(&model_info.path));
+        }
+        Some(model_info)
+    }
+
+    /// Rebuild the weights tree of a registered model from disk
+    pub fn weights_tree(&self, model_id: &str) -> Result<WeightsTree> {
+        let model_info = self
+            .models
+            .get(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        let canonical = onnx::canonicalize(&std::fs::read(&model_info.path)?)?;
+        let tree = WeightsTree::build(&canonical)?;
+
+        if !ct_eq(&tree.root(), &model_info.weights_root) {
+            return Err(anyhow!(
+                "Stored model {} no longer matches its commitment",
+                model_id
+            ));
+        }
+        Ok(tree)
+    }
+
+    /// Generate a zkML proof, on the backend the request selects if any
+    ///
+    /// When that backend fails the proof is retried down the fallback chain,
+    /// skipping backends of another proof format than the one requested.
+    /// Requests naming a backend are only proven on it.
     pub async fn generate_proof(&self, request: &ProveRequest) -> Result<ProofResult> {
+        let preferred = self.selected_backend(request).await?;
+        if request.backend.is_some() || self.fallback.is_empty() {
+            return self.prove_with(request, preferred).await;
+        }
+        let preferred_id = match preferred {
+            Some(backend) => backend.id.clone(),
+            None => self.zkml_prover.read().await.circuit_version(),
+        };
+        let fallback = self.fallback.iter().filter(|backend| {
+            backend.id != preferred_id
+                && request
+                    .proof_format
+                    .as_ref()
+                    .is_none_or(|format| *format == proof_format(backend.proof_version))
+        });
+
+        let mut failures: Vec<BackendFailure> = Vec::new();
+        let mut last_error = None;
+        for (id, backend) in std::iter::once((preferred_id.clone(), preferred))
+            .chain(fallback.map(|backend| (backend.id.clone(), Some(backend))))
+        {
+            let job = self.job_for(request, backend)?;
+            match self.run_job(job, backend).await {
+                Ok(mut result) => {
+                    if !failures.is_empty() {
+                        tracing::info!(
+                            "Proof of {} fell back to backend {} after {} failure(s)",
+                            request.model_id,
+                            id,
+                            failures.len()
+                        );
+                        result.fallback = Some(Fallback {
+                            backend: id,
+                            failures,
+                        });
+                    }
+                    return Ok(result);
+                }
+                // The request itself is at fault, so every backend would fail
+                Err(e) if !backend_fault(&e) => return Err(e),
+                Err(e) => {
+                    tracing::warn!(
+                        "Proving {} on backend {} failed: {}",
+                        request.model_id,
+                        id,
+                        e
+                    );
+                    failures.push(BackendFailure {
+                        backend: id,
+                        error: e.to_string(),
+                    });
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("the preferred backend is always tried"))
+    }
+
+    /// Generate a zkML proof, on the alternate backend if `alternate`
+    pub async fn generate_proof_on(
+        &self,
+        request: &ProveRequest,
+        alternate: bool,
+    ) -> Result<ProofResult> {
+        match (&self.alternate, alternate) {
+            (Some(alternate), true) => self.prove_with(request, Some(alternate)).await,
+            (None, true) => Err(anyhow!("No alternate backend is configured")),
+            (_, false) => self.prove_with(request, None).await,
+        }
+    }
+
+    /// Generate a zkML proof on consensus backend `index`, along with
+    /// whether that backend verifies it
+    pub async fn generate_consensus_proof(
+        &self,
+        request: &ProveRequest,
+        index: usize,
+    ) -> Result<(ProofResult, bool)> {
+        let backend = self
+            .consensus
+            .get(index)
+            .ok_or_else(|| anyhow!("No consensus backend {}", index))?;
+        let result = self.prove_with(request, Some(backend)).await?;
+        let proof = deserialize_proof(&result.proof)?;
+        let verification = backend.prover.read().await.verify(&proof)?;
+        Ok((result, verification.valid))
+    }
+
+    /// Generate a zkML proof on `backend`, or the default one
+    async fn prove_with(
+        &self,
+        request: &ProveRequest,
+        backend: Option<&AlternateBackend>,
+    ) -> Result<ProofResult> {
+        let job = self.job_for(request, backend)?;
+        self.run_job(job, backend).await
+    }
+
+    /// Run `job` on `backend`, or the default one, behind its breaker
+    async fn run_job(
+        &self,
+        job: ProveJob,
+        backend: Option<&AlternateBackend>,
+    ) -> Result<ProofResult> {
+        let (id, prover) = match backend {
+            Some(backend) => (backend.id.clone(), backend.prover.clone()),
+            None => (
+                self.zkml_prover.read().await.circuit_version(),
+                self.zkml_prover.clone(),
+            ),
+        };
+        self.breakers
+            .call(&id, backend_fault, self.isolation.run(job, prover))
+            .await
+    }
+
+    /// Proving job for `request` on `backend`, or the default one
+    fn job_for(
+        &self,
+        request: &ProveRequest,
+        backend: Option<&AlternateBackend>,
+    ) -> Result<ProveJob> {
         // Get model info
         let model_info = self
             .models
             .get(&request.model_id)
             .ok_or_else(|| anyhow!("Model not found: {}", request.model_id))?;
 
-        // Run ONNX inference to get outputs
-        let output = self.run_inference(&model_info.path, &request.inputs).await?;
-
-        // Generate zkML proof
-        let prover = self.zkml_prover.read().await;
-        let proof = prover.prove(&model_info.commitment, &request.inputs, &output)?;
+        Ok(ProveJob {
+            model_path: model_info.path.clone(),
+            model_bytes: self
+                .warm
+                .get(&request.model_id)
+                .map(|warm| warm.bytes.clone()),
+            commitment: model_info.commitment.clone(),
+            circuit_commitment: model_info.circuit_commitment.clone(),
+            request: request.clone(),
+            sandbox: match model_info.execution {
+                ModelExecution::Native => None,
+                ModelExecution::Wasm => self.wasm_sandbox.clone(),
+            },
+            // Checkpoints are keyed by job rather than backend, and other
+            // backends may run the same job alongside the default one
+            checkpoints: self.checkpoints.clone().filter(|_| backend.is_none()),
+            shards: model_info.shards,
+            witness_budget: self.witness_budget.clone(),
+            memory_budget: self.memory_budget,
+            quantization: model_info.quantization.clone(),
+            preprocessor: model_info.preprocessor.clone(),
+            strict: self.strict,
+            gpu: self.gpu,
+            backend: backend.map(|backend| backend.spec.clone()),
+            inference_engine: self.inference_engine,
+            cancellation: None,
+        })
+    }
 
-        // Serialize proof
-        let proof_encoded = serialize_proof(&proof)?;
+    /// Proof of `request` on the shadow backend, along with whether that
+    /// backend verifies it, as a task independent of the prover
+    pub fn shadow_proof(
+        &self,
+        request: &ProveRequest,
+    ) -> Result<impl std::future::Future<Output = Result<(ProofResult, bool)>> + Send + 'static>
+    {
+        let backend = self
+            .shadow
+            .as_ref()
+            .ok_or_else(|| anyhow!("No shadow backend is configured"))?;
+        let job = self.job_for(request, Some(backend))?;
+        let isolation = self.isolation.clone();
+        let breakers = self.breakers.clone();
+        let (id, prover) = (backend.id.clone(), backend.prover.clone());
+        Ok(async move {
+            let result = breakers
+                .call(&id, backend_fault, isolation.run(job, prover.clone()))
+                .await?;
+            let proof = deserialize_proof(&result.proof)?;
+            let verification = prover.read().await.verify(&proof)?;
+            Ok((result, verification.valid))
+        })
+    }
 
-        // Compute hashes for public inputs
-        let input_hash = hash_floats(&request.inputs);
-        let output_hash = hash_floats(&output);
+    /// Prove and verify an unregistered model end to end
+    ///
+    /// The proof goes through the same isolation, backend and verification
+    /// as a registered model's, and must carry the model's canonical
+    /// commitment and the hash of `inputs`.
+    pub async fn round_trip(&self, model_path: &Path, inputs: &[f32]) -> Result<()> {
+        let commitments =
+            ModelCommitments::compute(&onnx::canonicalize(&std::fs::read(model_path)?)?);
+        let job = ProveJob {
+            model_path: model_path.to_path_buf(),
+            model_bytes: None,
+            commitment: commitments.sha256.clone(),
+            circuit_commitment: commitments.keccak256.clone(),
+            request: ProveRequest {
+                model_id: "self-test".to_string(),
+                inputs: inputs.to_vec(),
+                ..Default::default()
+            },
+            sandbox: None,
+            checkpoints: None,
+            shards: 1,
+            witness_budget: self.witness_budget.clone(),
+            memory_budget: self.memory_budget,
+            quantization: None,
+            preprocessor: None,
+            strict: self.strict,
+            gpu: self.gpu,
+            backend: None,
+            inference_engine: self.inference_engine,
+            cancellation: None,
+        };
+        let result = self.isolation.run(job, self.zkml_prover.clone()).await?;
+        if !ct_eq(&result.input_hash, &commitments.input_hash(inputs)) {
+            return Err(anyhow!("Proof does not commit to the self-test inputs"));
+        }
 
-        let public_inputs = PublicInputs {
-            model_commitment: model_info.commitment.clone(),
-            input_hash: input_hash.clone(),
-            output_hash: output_hash.clone(),
-            output: output.clone(),
-            timestamp: proof.timestamp,
+        let request = VerifyRequest {
+            proof: result.proof,
+            model_commitment: commitments.sha256,
+            input_hash: result.input_hash,
+            output_hash: result.output_hash,
+            commitment_scheme: result.public_inputs.commitment_scheme,
+            public_inputs: Some(result.public_inputs),
+            challenge: None,
+            receipt: false,
         };
+        if !self.verify_proof(&request).await? {
+            return Err(anyhow!("Proof did not verify"));
+        }
+        Ok(())
+    }
 
-        Ok(ProofResult {
-            proof: proof_encoded,
-            model_commitment: model_info.commitment.clone(),
-            input_hash,
-            output_hash,
-            public_inputs,
-        })
+    /// Re-run inference over a registered model, the way its proofs run it
+    pub async fn infer(&self, model_id: &str, inputs: &[f32]) -> Result<Vec<NamedOutput>> {
+        let model_info = self
+            .models
+            .get(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        match (&model_info.execution, &self.wasm_sandbox) {
+            (ModelExecution::Wasm, Some(sandbox)) => {
+                let bytes = std::fs::read(&model_info.path)?;
+                let values = sandbox.infer(&bytes, inputs)?;
+                Ok(inference::single_output(&bytes, values))
+            }
+            _ => {
+                self.breakers
+                    .call(
+                        ONNX_RUNTIME,
+                        backend_fault,
+                        Self::run_inference(
+                            &model_info.path,
+                            inputs,
+                            self.inference_engine,
+                            self.strict,
+                        ),
+                    )
+                    .await
+            }
+        }
+    }
+
+    /// Turn raw data into a model's feature vector with its plugin
+    pub fn run_preprocessor(&self, model_id: &str, raw: &serde_json::Value) -> Result<Vec<f32>> {
+        let model_info = self
+            .models
+            .get(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        if model_info.preprocessor.is_none() {
+            return Err(anyhow!("Model {} has no preprocessing plugin", model_id));
+        }
+        let module = std::fs::read(plugins::plugin_path(&model_info.path))?;
+        plugins::run(&module, raw)
     }
 
     /// Verify a zkML proof
     pub async fn verify_proof(&self, request: &VerifyRequest) -> Result<bool> {
+        // The expected values must have been computed under a scheme we can
+        // recompute, and agree with the scheme recorded in the public inputs
+        let scheme = request.commitment_scheme;
+        if !scheme.is_supported() {
+            return Err(anyhow!("Unsupported commitment scheme: {}", scheme));
+        }
+        if let Some(public_inputs) = &request.public_inputs {
+            if public_inputs.commitment_scheme != scheme {
+                return Err(anyhow!(
+                    "Commitment scheme mismatch: request uses {}, public inputs use {}",
+                    scheme,
+                    public_inputs.commitment_scheme
+                ));
+            }
+        }
+
         // Deserialize the proof
         let proof = deserialize_proof(&request.proof)?;
+        self.check_not_mock(&proof)?;
 
-        // Verify model commitment matches
-        if proof.model_commitment != request.model_commitment {
-            return Ok(false);
+        // Compare against the proof's commitments under the requested scheme
+        let Some((model_commitment, input_hash, output_hash)) = proof.commitments_for(scheme)
+        else {
+            return Err(anyhow!("Proof carries no {} commitments", scheme));
+        };
+
+        if let Some(public_inputs) = &request.public_inputs {
+            check_public_inputs(&proof, public_inputs)?;
+        }
+        // A verifier that issued a challenge only accepts proofs absorbing it
+        if let Some(challenge) = &request.challenge {
+            if proof.challenge.as_ref() != Some(&normalize_challenge(challenge)?) {
+                tracing::warn!("Proof does not absorb the verifier challenge");
+                return Ok(false);
+            }
         }
 
-        // Verify input/output hashes match
-        if proof.input_hash != request.input_hash {
+        let expected_model_commitment =
+            proven_commitment(&proof, scheme, &request.model_commitment)?;
+
+        // Verify model commitment and input/output hashes match
+        if !verify_commitments(
+            model_commitment,
+            input_hash,
+            output_hash,
+            &expected_model_commitment,
+            &request.input_hash,
+            &request.output_hash,
+        ) {
             return Ok(false);
         }
 
-        if proof.output_hash != request.output_hash {
-            return Ok(false);
+        // Per-output hashes must hash their tensors, which together must be
+        // the proven output
+        if let Some(outputs) = request
+            .public_inputs
+            .as_ref()
+            .and_then(|p| p.outputs.as_ref())
+        {
+            if !named_outputs_match(&proof, outputs) {
+                tracing::warn!("Output tensors in public inputs do not match the proof");
+                return Ok(false);
+            }
         }
 
-        // Verify the actual zkML proof
-        let prover = self.zkml_prover.read().await;
-        let result = prover.verify(&proof)?;
+        // Verify the actual zkML proof, with the backend that made it
+        let prover = self.verifier_for(&proof).await;
+        let result = prover.read().await.verify(&proof)?;
 
         if !result.valid {
             tracing::warn!("Proof verification failed: {:?}", result.error);
@@ -162,37 +1496,149 @@ impl JoltAtlasProver {
         Ok(result.valid)
     }
 
-    /// Run ONNX model inference
-    async fn run_inference(&self, model_path: &PathBuf, inputs: &[f32]) -> Result<Vec<f32>> {
-        // Try to use ONNX runtime if available
-        #[cfg(feature = "ort")]
-        {
-            return self.run_onnx_inference(model_path, inputs).await;
+    /// Verify a proof on its own, returning the public inputs it proves
+    ///
+    /// Nothing is compared against caller-supplied values; the proof's own
+    /// commitments are checked by the backend and handed back, together with
+    /// the registered model they belong to if it is registered here.
+    pub async fn verify_embedded(
+        &self,
+        proof: &str,
+        challenge: Option<&str>,
+    ) -> Result<Option<EmbeddedPublicInputs>> {
+        let proof = deserialize_proof(proof)?;
+        self.check_not_mock(&proof)?;
+        let scheme = proof.commitment_scheme;
+        if !scheme.is_supported() {
+            return Err(anyhow!("Unsupported commitment scheme: {}", scheme));
+        }
+        if let Some(challenge) = challenge {
+            if proof.challenge.as_ref() != Some(&normalize_challenge(challenge)?) {
+                tracing::warn!("Proof does not absorb the verifier challenge");
+                return Ok(None);
+            }
+        }
+
+        let result = self
+            .verifier_for(&proof)
+            .await
+            .read()
+            .await
+            .verify(&proof)?;
+        if !result.valid {
+            tracing::warn!("Proof verification failed: {:?}", result.error);
+            return Ok(None);
+        }
+
+        let model = self.proven_model(&proof);
+        Ok(Some(EmbeddedPublicInputs {
+            model_id: model.map(|m| m.id.clone()),
+            model_commitment: model.map(|m| m.commitment.clone()),
+            proven_commitment: proof.model_commitment,
+            input_hash: proof.input_hash,
+            output_hash: proof.output_hash,
+            commitment_scheme: scheme,
+            output: proof.outputs,
+            timestamp: proof.timestamp,
+            prover_id: proof.prover_id,
+            circuit_commitments: proof.circuit_commitments,
+            predicate: proof.predicate,
+            expected_output: proof.expected_output,
+            quantization: proof.quantization,
+            preprocessor: proof.preprocessor,
+            challenge: proof.challenge,
+            circuit_hash: proof.circuit_hash,
+        }))
+    }
+
+    /// The registered model `proof` commits to, if any
+    pub fn proven_model(&self, proof: &JoltAtlasProof) -> Option<&ModelInfo> {
+        self.models.values().find(|model| {
+            proven_commitment(proof, proof.commitment_scheme, &model.commitment)
+                .is_ok_and(|commitment| ct_eq(&commitment, &proof.model_commitment))
+        })
+    }
+
+    /// In strict mode, reject proofs made by the mock backend
+    fn check_not_mock(&self, proof: &JoltAtlasProof) -> Result<()> {
+        if self.strict && is_mock_prover(&proof.prover_id) {
+            return Err(StrictModeViolation(format!(
+                "refusing a proof made by the mock backend {}",
+                proof.prover_id
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Run ONNX model inference on `engine`, falling back to mock inference
+    /// unless `strict`
+    #[cfg_attr(not(any(feature = "ort", feature = "tract")), allow(unused_variables))]
+    pub async fn run_inference(
+        model_path: &Path,
+        inputs: &[f32],
+        engine: InferenceEngine,
+        strict: bool,
+    ) -> Result<Vec<NamedOutput>> {
+        if engine.available() {
+            inference::check_inputs(&std::fs::read(model_path)?, inputs)?;
+        }
+        match engine {
+            // Try to use ONNX runtime if available
+            #[cfg(feature = "ort")]
+            InferenceEngine::Ort => return Self::run_onnx_inference(model_path, inputs).await,
+            #[cfg(feature = "tract")]
+            InferenceEngine::Tract => {
+                let model_path = model_path.to_path_buf();
+                let inputs = inputs.to_vec();
+                return tokio::task::spawn_blocking(move || {
+                    inference::run_tract(&model_path, &inputs)
+                })
+                .await?;
+            }
+            #[allow(unreachable_patterns)]
+            _ => {}
         }
 
         // Fallback: mock inference based on input features
-        #[allow(unreachable_code)]
-        {
-            tracing::warn!("ONNX runtime not available, using mock inference");
-            Ok(self.mock_inference(inputs))
+        if strict {
+            return Err(StrictModeViolation(format!(
+                "{} inference engine not available; refusing mock inference",
+                engine
+            ))
+            .into());
         }
+        tracing::warn!(
+            "{} inference engine not available, using mock inference",
+            engine
+        );
+        let model_bytes = std::fs::read(model_path).unwrap_or_default();
+        Ok(inference::single_output(
+            &model_bytes,
+            Self::mock_inference(inputs),
+        ))
     }
 
     /// Run inference using ONNX runtime
     #[cfg(feature = "ort")]
-    async fn run_onnx_inference(&self, model_path: &PathBuf, inputs: &[f32]) -> Result<Vec<f32>> {
-        use ort::{Session, Value};
+    async fn run_onnx_inference(model_path: &Path, inputs: &[f32]) -> Result<Vec<NamedOutput>> {
         use ndarray::Array2;
+        use ort::{Session, Value};
 
         let session = Session::builder()?.with_model_from_file(model_path)?;
 
         // Get input shape from model
         let input_info = &session.inputs[0];
-        let input_dims = input_info.input_type.tensor_dimensions()
+        let input_dims = input_info
+            .input_type
+            .tensor_dimensions()
             .ok_or_else(|| anyhow!("Cannot get input dimensions"))?;
 
         let batch_size = input_dims.get(0).and_then(|d| *d).unwrap_or(1) as usize;
-        let features = input_dims.get(1).and_then(|d| *d).unwrap_or(inputs.len() as i64) as usize;
+        let features = input_dims
+            .get(1)
+            .and_then(|d| *d)
+            .unwrap_or(inputs.len() as i64) as usize;
 
         // Reshape inputs
         let input_array = Array2::from_shape_vec((batch_size, features), inputs.to_vec())?;
@@ -200,13 +1646,22 @@ impl JoltAtlasProver {
 
         // Run inference
         let outputs = session.run(vec![input_value])?;
-        let output_tensor = outputs[0].extract_tensor::<f32>()?;
-
-        Ok(output_tensor.view().iter().copied().collect())
+        session
+            .outputs
+            .iter()
+            .zip(outputs.iter())
+            .map(|(info, value)| {
+                let tensor = value.extract_tensor::<f32>()?;
+                Ok(NamedOutput {
+                    name: info.name.clone(),
+                    values: tensor.view().iter().copied().collect(),
+                })
+            })
+            .collect()
     }
 
     /// Mock inference for testing
-    fn mock_inference(&self, inputs: &[f32]) -> Vec<f32> {
+    fn mock_inference(inputs: &[f32]) -> Vec<f32> {
         // Simple mock: sigmoid-like output based on input sum
         let sum: f32 = inputs.iter().sum();
         let normalized = 1.0 / (1.0 + (-sum / inputs.len() as f32).exp());
@@ -216,7 +1671,7 @@ impl JoltAtlasProver {
     }
 
     /// Verify model can be loaded
-    async fn verify_model_loadable(&self, model_path: &PathBuf) -> Result<()> {
+    async fn verify_model_loadable(&self, model_path: &Path) -> Result<()> {
         // Check file exists and is readable
         if !model_path.exists() {
             return Err(anyhow!("Model file does not exist: {:?}", model_path));
@@ -232,6 +1687,26 @@ impl JoltAtlasProver {
         Ok(())
     }
 
+    /// Hash of the circuit the backend builds for `model`, as bound into its
+    /// proofs without an expected output check or predicate
+    pub async fn circuit_hash(&self, model: &ModelInfo) -> Result<String> {
+        let commitment = bind_statements(
+            CommitmentScheme::Sha256V2,
+            &model.commitment,
+            model.preprocessor.as_deref(),
+            model.quantization.as_ref(),
+            None,
+            None,
+        )?;
+        let version = self.zkml_prover.read().await.circuit_version();
+        Ok(circuit_hash(&version, &commitment))
+    }
+
+    /// Id of the default backend, as listed in `GET /capabilities`
+    pub async fn backend_id(&self) -> String {
+        self.zkml_prover.read().await.circuit_version()
+    }
+
     /// Get prover information
     pub async fn get_prover_info(&self) -> String {
         let prover = self.zkml_prover.read().await;
@@ -0,0 +1,160 @@
+//! Public fixed-point encoding parameters
+//!
+//! A model registered with `quantization` computes over integers: its real
+//! inputs are encoded and its integer outputs decoded with the declared
+//! parameters. Those parameters are carried in the public inputs of every
+//! proof over the model and, like output checks, folded into the model
+//! commitment the proof is made against, so a verifier reconstructs the
+//! real-valued outputs with exactly the parameters the proof was made under.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::jolt_atlas::ModelCommitments;
+use crate::predicate::{bind_both, compose};
+use crate::types::CommitmentScheme;
+
+/// Domain separator for commitments to a model with its fixed-point encoding
+const QUANTIZATION_DOMAIN: &str = "jolt-atlas-prover/quantization/v1\n";
+
+/// Largest supported binary fixed-point precision
+const MAX_FRACTIONAL_BITS: u8 = 32;
+
+/// Mapping between real values and the integers a circuit computes over
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FixedPoint {
+    /// `real = scale * (q - zero_point)`, as in ONNX `QuantizeLinear`
+    Affine { scale: f32, zero_point: i32 },
+    /// `real = q / 2^fractional_bits`
+    Binary { fractional_bits: u8 },
+}
+
+impl FixedPoint {
+    fn validate(&self) -> Result<()> {
+        match *self {
+            FixedPoint::Affine { scale, .. } if !(scale.is_finite() && scale > 0.0) => Err(
+                anyhow!("Quantization scale must be positive, got {}", scale),
+            ),
+            FixedPoint::Binary { fractional_bits } if fractional_bits > MAX_FRACTIONAL_BITS => {
+                Err(anyhow!(
+                    "At most {} fractional bits are supported, got {}",
+                    MAX_FRACTIONAL_BITS,
+                    fractional_bits
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Real value of the integer `q`
+    pub fn decode(&self, q: i64) -> f64 {
+        match *self {
+            FixedPoint::Affine { scale, zero_point } => {
+                scale as f64 * (q - zero_point as i64) as f64
+            }
+            FixedPoint::Binary { fractional_bits } => q as f64 / (1u64 << fractional_bits) as f64,
+        }
+    }
+
+    /// Nearest integer encoding `real`
+    pub fn encode(&self, real: f32) -> i64 {
+        match *self {
+            FixedPoint::Affine { scale, zero_point } => {
+                (real as f64 / scale as f64).round() as i64 + zero_point as i64
+            }
+            FixedPoint::Binary { fractional_bits } => {
+                (real as f64 * (1u64 << fractional_bits) as f64).round() as i64
+            }
+        }
+    }
+
+    fn statement(&self) -> String {
+        match *self {
+            FixedPoint::Affine { scale, zero_point } => {
+                format!("affine {} {}", hex::encode(scale.to_le_bytes()), zero_point)
+            }
+            FixedPoint::Binary { fractional_bits } => format!("binary {}", fractional_bits),
+        }
+    }
+}
+
+/// Fixed-point encodings of a model's inputs and outputs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quantization {
+    pub input: FixedPoint,
+    pub output: FixedPoint,
+}
+
+impl Quantization {
+    pub fn validate(&self) -> Result<()> {
+        self.input.validate()?;
+        self.output.validate()
+    }
+
+    /// Commitment to the model with this encoding under `scheme`
+    pub fn bind_commitment(
+        &self,
+        scheme: CommitmentScheme,
+        model_commitment: &str,
+    ) -> Result<String> {
+        let statement = format!(
+            "input {}\noutput {}",
+            self.input.statement(),
+            self.output.statement()
+        );
+        compose(QUANTIZATION_DOMAIN, scheme, model_commitment, &statement)
+    }
+
+    /// Commitments to the model with this encoding
+    pub fn bind(&self, model: &ModelCommitments) -> Result<ModelCommitments> {
+        bind_both(model, |scheme, c| self.bind_commitment(scheme, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_point_round_trips_and_binds() {
+        let affine = FixedPoint::Affine {
+            scale: 0.5,
+            zero_point: 10,
+        };
+        assert_eq!(affine.encode(1.5), 13);
+        assert_eq!(affine.decode(13), 1.5);
+        let binary = FixedPoint::Binary { fractional_bits: 8 };
+        assert_eq!(binary.encode(0.75), 192);
+        assert_eq!(binary.decode(192), 0.75);
+
+        let quantization: Quantization = serde_json::from_value(serde_json::json!({
+            "input": { "kind": "affine", "scale": 0.5, "zero_point": 10 },
+            "output": { "kind": "binary", "fractional_bits": 8 }
+        }))
+        .unwrap();
+        assert!(quantization.validate().is_ok());
+
+        let model = ModelCommitments::compute(b"model");
+        let finer = Quantization {
+            output: FixedPoint::Binary {
+                fractional_bits: 16,
+            },
+            ..quantization.clone()
+        };
+        assert_ne!(quantization.bind(&model).unwrap().sha256, model.sha256);
+        assert_ne!(
+            quantization.bind(&model).unwrap().sha256,
+            finer.bind(&model).unwrap().sha256
+        );
+
+        let invalid = Quantization {
+            input: FixedPoint::Affine {
+                scale: 0.0,
+                zero_point: 0,
+            },
+            ..quantization
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
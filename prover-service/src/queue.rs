@@ -0,0 +1,539 @@
+//! Fair proving queue
+//!
+//! At most `PROVE_CONCURRENCY` proofs run at once. Requests beyond that wait
+//! in per-tenant queues that are served by weighted fair queuing: each job
+//! gets a virtual finish tag `max(now, tenant's last tag) + 1 / weight`, and
+//! a free slot goes to the job with the smallest tag. A tenant that submits
+//! a thousand jobs therefore only competes with its own backlog, and a
+//! single interactive proof from another tenant is served next. Weights come
+//! from `TENANT_WEIGHTS` (`tenant=weight,...`, default 1).
+//!
+//! Models can carry their own limits (see `ModelLimits`): a cap on their
+//! proofs running at once and on prove requests accepted per minute. A job
+//! whose model is at its cap waits without holding up the tenant's other
+//! jobs; a request over the per-minute limit is refused at once.
+//!
+//! Per-tenant and per-model queue metrics are served from `GET /admin/queue`.
+//! Each running proof holds one of the `PROVE_CONCURRENCY` worker slots,
+//! whose occupants `GET /admin/overview` lists.
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::types::{api_error, ApiError};
+
+/// Window of per-model request rate limits
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Proving limits of one model, part of its registry metadata
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelLimits {
+    /// Proofs of the model running at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<u32>,
+    /// Prove requests for the model accepted per minute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+}
+
+impl ModelLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_concurrent.is_none() && self.requests_per_minute.is_none()
+    }
+
+    /// Reject zero limits, which would never let a proof through
+    pub fn validate(self) -> anyhow::Result<Self> {
+        if self.max_concurrent == Some(0) || self.requests_per_minute == Some(0) {
+            return Err(anyhow::anyhow!(
+                "Model limits must be at least 1; leave a limit unset to lift it"
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// A prove request over its model's per-minute limit
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Model {model_id} accepts {limit} prove requests per minute; retry in {}s",
+    retry_after.as_secs().max(1)
+)]
+pub struct ModelRateLimited {
+    pub model_id: String,
+    pub limit: u32,
+    pub retry_after: Duration,
+}
+
+impl From<ModelRateLimited> for ApiError {
+    fn from(err: ModelRateLimited) -> Self {
+        api_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "MODEL_RATE_LIMITED",
+            err.to_string(),
+        )
+    }
+}
+
+/// A job waiting for a slot
+struct Waiter {
+    tag: f64,
+    enqueued_at: Instant,
+    /// Model the job proves, when it has limits
+    model: Option<String>,
+    max_concurrent: Option<u32>,
+    /// Receives the worker slot granted to the job
+    ready: oneshot::Sender<usize>,
+}
+
+impl Waiter {
+    /// Whether the job's model has room for it
+    fn may_start(&self, models: &HashMap<String, ModelUsage>) -> bool {
+        match (&self.model, self.max_concurrent) {
+            (Some(model), Some(cap)) => {
+                models.get(model).map_or(0, |usage| usage.running) < cap.max(1)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// The job occupying a worker slot
+struct Slot {
+    tenant: String,
+    job: Option<String>,
+    started_at: Instant,
+}
+
+#[derive(Default)]
+struct TenantQueue {
+    last_tag: f64,
+    waiting: VecDeque<Waiter>,
+    running: usize,
+    dispatched: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+#[derive(Default)]
+struct ModelUsage {
+    running: u32,
+    /// When requests were accepted within the last rate window
+    accepted: VecDeque<Instant>,
+}
+
+struct QueueState {
+    running: usize,
+    /// Virtual time: tag of the most recently dispatched job
+    virtual_time: f64,
+    tenants: HashMap<String, TenantQueue>,
+    /// Usage of models with limits
+    models: HashMap<String, ModelUsage>,
+    slots: Vec<Option<Slot>>,
+}
+
+/// Queue metrics for one tenant
+#[derive(Serialize)]
+pub struct TenantQueueStats {
+    pub tenant: String,
+    pub weight: f64,
+    pub queued: usize,
+    pub running: usize,
+    /// Jobs started since the service started
+    pub dispatched: u64,
+    pub avg_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+/// Queue metrics for one model with limits
+#[derive(Serialize)]
+pub struct ModelQueueStats {
+    pub model_id: String,
+    pub queued: usize,
+    pub running: u32,
+    pub requests_last_minute: usize,
+}
+
+/// Queue-wide metrics
+#[derive(Serialize)]
+pub struct QueueStats {
+    pub capacity: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub tenants: Vec<TenantQueueStats>,
+    pub models: Vec<ModelQueueStats>,
+    /// Proving requests shed under load since the service started (see
+    /// `admission`)
+    pub shed: u64,
+}
+
+/// What one worker slot is doing
+#[derive(Clone, Serialize)]
+pub struct WorkerStatus {
+    pub slot: usize,
+    pub busy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// What the running job is proving, once it has said so
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+/// Weighted fair queue in front of the prover
+pub struct FairQueue {
+    capacity: usize,
+    weights: HashMap<String, f64>,
+    state: Arc<Mutex<QueueState>>,
+}
+
+/// A running slot; frees it for the next job when dropped
+pub struct QueuePermit {
+    tenant: String,
+    model: Option<String>,
+    slot: Option<usize>,
+    capacity: usize,
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl FairQueue {
+    pub fn new(capacity: usize, weights: HashMap<String, f64>) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            weights,
+            state: Arc::new(Mutex::new(QueueState {
+                running: 0,
+                virtual_time: 0.0,
+                tenants: HashMap::new(),
+                models: HashMap::new(),
+                slots: (0..capacity).map(|_| None).collect(),
+            })),
+        }
+    }
+
+    fn weight(&self, tenant: &str) -> f64 {
+        self.weights
+            .get(tenant)
+            .copied()
+            .filter(|w| *w > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    /// Wait for a proving slot for `tenant`
+    pub async fn acquire(&self, tenant: &str) -> QueuePermit {
+        self.enqueue(tenant, None, None).await
+    }
+
+    /// Wait for a proving slot for `tenant` to prove `model_id`, within the
+    /// model's `limits`
+    pub async fn acquire_model(
+        &self,
+        tenant: &str,
+        model_id: &str,
+        limits: ModelLimits,
+    ) -> Result<QueuePermit, ModelRateLimited> {
+        if limits.is_unlimited() {
+            return Ok(self.acquire(tenant).await);
+        }
+        if let Some(limit) = limits.requests_per_minute {
+            let mut state = self.state.lock().unwrap();
+            let usage = state.models.entry(model_id.to_string()).or_default();
+            let now = Instant::now();
+            while usage
+                .accepted
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW)
+            {
+                usage.accepted.pop_front();
+            }
+            let limit = limit.max(1);
+            if usage.accepted.len() >= limit as usize {
+                let oldest = usage.accepted.front().copied().unwrap_or(now);
+                return Err(ModelRateLimited {
+                    model_id: model_id.to_string(),
+                    limit,
+                    retry_after: RATE_WINDOW.saturating_sub(now.duration_since(oldest)),
+                });
+            }
+            usage.accepted.push_back(now);
+        }
+        Ok(self
+            .enqueue(tenant, Some(model_id), limits.max_concurrent)
+            .await)
+    }
+
+    async fn enqueue(
+        &self,
+        tenant: &str,
+        model: Option<&str>,
+        max_concurrent: Option<u32>,
+    ) -> QueuePermit {
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap();
+            let virtual_time = state.virtual_time;
+            let queue = state.tenants.entry(tenant.to_string()).or_default();
+            let tag = queue.last_tag.max(virtual_time) + 1.0 / self.weight(tenant);
+            queue.last_tag = tag;
+            queue.waiting.push_back(Waiter {
+                tag,
+                enqueued_at: Instant::now(),
+                model: model.map(String::from),
+                max_concurrent,
+                ready: tx,
+            });
+            dispatch(&mut state, self.capacity);
+        }
+
+        // The sender is only dropped after being granted a slot
+        let slot = rx.await.ok();
+        QueuePermit {
+            tenant: tenant.to_string(),
+            model: model.map(String::from),
+            slot,
+            capacity: self.capacity,
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.state.lock().unwrap();
+        let tenants: BTreeMap<_, _> = state.tenants.iter().collect();
+        QueueStats {
+            capacity: self.capacity,
+            running: state.running,
+            queued: state.tenants.values().map(|q| q.waiting.len()).sum(),
+            tenants: tenants
+                .into_iter()
+                .map(|(tenant, queue)| TenantQueueStats {
+                    tenant: tenant.clone(),
+                    weight: self.weight(tenant),
+                    queued: queue.waiting.len(),
+                    running: queue.running,
+                    dispatched: queue.dispatched,
+                    avg_wait_ms: queue
+                        .total_wait
+                        .as_millis()
+                        .checked_div(queue.dispatched as u128)
+                        .unwrap_or(0) as u64,
+                    max_wait_ms: queue.max_wait.as_millis() as u64,
+                })
+                .collect(),
+            models: state
+                .models
+                .iter()
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .map(|(model_id, usage)| ModelQueueStats {
+                    model_id: model_id.clone(),
+                    queued: state
+                        .tenants
+                        .values()
+                        .flat_map(|q| &q.waiting)
+                        .filter(|w| w.model.as_ref() == Some(model_id))
+                        .count(),
+                    running: usage.running,
+                    requests_last_minute: usage
+                        .accepted
+                        .iter()
+                        .filter(|t| t.elapsed() < RATE_WINDOW)
+                        .count(),
+                })
+                .collect(),
+            shed: 0,
+        }
+    }
+
+    /// Status of every worker slot
+    pub fn workers(&self) -> Vec<WorkerStatus> {
+        let state = self.state.lock().unwrap();
+        state
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(slot, occupant)| WorkerStatus {
+                slot,
+                busy: occupant.is_some(),
+                tenant: occupant.as_ref().map(|o| o.tenant.clone()),
+                job: occupant.as_ref().and_then(|o| o.job.clone()),
+                elapsed_ms: occupant
+                    .as_ref()
+                    .map(|o| o.started_at.elapsed().as_millis() as u64),
+            })
+            .collect()
+    }
+}
+
+impl QueuePermit {
+    /// Describe the job running in this slot, e.g. the model being proven
+    pub fn set_job(&self, job: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(Some(occupant)) = self.slot.and_then(|slot| state.slots.get_mut(slot)) {
+            occupant.job = Some(job.to_string());
+        }
+    }
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        if let Some(slot) = self.slot {
+            state.slots[slot] = None;
+        }
+        if let Some(queue) = state.tenants.get_mut(&self.tenant) {
+            queue.running -= 1;
+        }
+        if let Some(usage) = self.model.as_ref().and_then(|m| state.models.get_mut(m)) {
+            usage.running -= 1;
+        }
+        dispatch(&mut state, self.capacity);
+    }
+}
+
+/// Start waiting jobs, smallest finish tag first, while slots are free
+fn dispatch(state: &mut QueueState, capacity: usize) {
+    while state.running < capacity {
+        let models = &state.models;
+        let next = state
+            .tenants
+            .iter()
+            .filter_map(|(tenant, queue)| {
+                // Jobs whose model is at its cap are passed over, not waited on
+                let position = queue.waiting.iter().position(|w| w.may_start(models))?;
+                Some((queue.waiting[position].tag, tenant, position))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, tenant, position)| (tenant.clone(), position));
+        let Some((tenant, position)) = next else {
+            return;
+        };
+
+        let Some(slot) = state.slots.iter().position(Option::is_none) else {
+            return;
+        };
+        let queue = state.tenants.get_mut(&tenant).expect("tenant has waiters");
+        let waiter = queue.waiting.remove(position).expect("tenant has waiters");
+        // A waiter whose request was cancelled no longer needs the slot
+        if waiter.ready.send(slot).is_err() {
+            continue;
+        }
+        let waited = waiter.enqueued_at.elapsed();
+        queue.running += 1;
+        queue.dispatched += 1;
+        queue.total_wait += waited;
+        queue.max_wait = queue.max_wait.max(waited);
+        if let Some(model) = &waiter.model {
+            state.models.entry(model.clone()).or_default().running += 1;
+        }
+        state.running += 1;
+        state.virtual_time = state.virtual_time.max(waiter.tag);
+        state.slots[slot] = Some(Slot {
+            tenant,
+            job: None,
+            started_at: Instant::now(),
+        });
+    }
+}
+
+/// Parse `tenant=weight` pairs
+pub fn parse_weights(entries: &[String]) -> HashMap<String, f64> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (tenant, weight) = entry.split_once('=')?;
+            Some((tenant.trim().to_string(), weight.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_single_job_overtakes_a_backlog() {
+        let queue = Arc::new(FairQueue::new(1, HashMap::new()));
+        let running = queue.acquire("bulk").await;
+        running.set_job("model-a");
+        let workers = queue.workers();
+        assert!(workers[0].busy);
+        assert_eq!(workers[0].job.as_deref(), Some("model-a"));
+
+        // The bulk tenant queues a backlog, then another tenant arrives
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for tenant in ["bulk", "bulk", "bulk", "interactive"] {
+            let (queue, order) = (queue.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                let _permit = queue.acquire(tenant).await;
+                order.lock().unwrap().push(tenant);
+            }));
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(queue.stats().queued, 4);
+
+        drop(running);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert!(queue.workers().iter().all(|worker| !worker.busy));
+        // The interactive job ties with the head of the backlog at worst
+        let order = order.lock().unwrap();
+        assert!(order[..2].contains(&"interactive"));
+        assert_eq!(
+            parse_weights(&["a=2".into(), "bad".into()]),
+            HashMap::from([("a".to_string(), 2.0)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_model_limits_cap_concurrency_and_rate() {
+        let queue = Arc::new(FairQueue::new(4, HashMap::new()));
+        let limits = ModelLimits {
+            max_concurrent: Some(1),
+            requests_per_minute: Some(3),
+        };
+        let running = queue.acquire_model("a", "expensive", limits).await.unwrap();
+
+        // The second expensive proof waits; the tenant's cheap one does not
+        let (q, order) = (queue.clone(), Arc::new(Mutex::new(Vec::new())));
+        let waiting = {
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _permit = q.acquire_model("a", "expensive", limits).await.unwrap();
+                order.lock().unwrap().push("expensive");
+            })
+        };
+        tokio::task::yield_now().await;
+        let _cheap = queue.acquire("a").await;
+        let stats = queue.stats();
+        assert_eq!(stats.running, 2);
+        assert_eq!(stats.models[0].queued, 1);
+
+        drop(running);
+        waiting.await.unwrap();
+        assert_eq!(*order.lock().unwrap(), ["expensive"]);
+
+        // The third request this minute is the last one accepted
+        let _third = queue.acquire_model("b", "expensive", limits).await.unwrap();
+        let err = queue
+            .acquire_model("b", "expensive", limits)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.limit, 3);
+        assert!(err.retry_after <= RATE_WINDOW);
+        assert!(ModelLimits {
+            max_concurrent: Some(0),
+            requests_per_minute: None,
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,631 @@
+//! Per-API-key quotas and budgets
+//!
+//! Limits the number of proofs and total proving seconds a key may consume
+//! per calendar day and month (UTC). A proof slot is reserved before proving
+//! starts and released again unless the proof is issued, so concurrent
+//! requests cannot overshoot a proof-count quota. The reservation is released
+//! when dropped, so a request that fails, times out, is cancelled by its
+//! client or panics does not keep its slot; a release refunds the window the
+//! slot was charged to, even once the day or month has rolled over.
+//!
+//! Limits are managed through the admin API. Keys without configured limits
+//! are unlimited, except anonymous callers, who share the `ANONYMOUS_*`
+//! default quota. Limits and usage are persisted to `quotas.json` in the
+//! data directory, so a restart does not reset them.
+
+use anyhow::Result;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth::ANONYMOUS_KEY_ID;
+use crate::persist::{load_json, save_json};
+use crate::types::{api_error, ApiError};
+
+/// Remaining proofs in the tightest window
+pub const QUOTA_REMAINING_HEADER: &str = "x-quota-remaining";
+
+/// Remaining proving seconds in the tightest window
+pub const QUOTA_REMAINING_SECONDS_HEADER: &str = "x-quota-remaining-seconds";
+
+/// Configured limits for one API key (`None` = unlimited)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QuotaLimits {
+    pub daily_proofs: Option<u64>,
+    pub monthly_proofs: Option<u64>,
+    pub daily_proving_seconds: Option<f64>,
+    pub monthly_proving_seconds: Option<f64>,
+}
+
+impl QuotaLimits {
+    fn is_limited(&self) -> bool {
+        self.daily_proofs.is_some()
+            || self.monthly_proofs.is_some()
+            || self.daily_proving_seconds.is_some()
+            || self.monthly_proving_seconds.is_some()
+    }
+}
+
+/// Consumption in the current day and month windows
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub daily_proofs: u64,
+    pub monthly_proofs: u64,
+    pub daily_proving_seconds: f64,
+    pub monthly_proving_seconds: f64,
+    #[serde(skip)]
+    day: u64,
+    #[serde(skip)]
+    month: u64,
+}
+
+impl QuotaUsage {
+    /// Reset counters whose window has rolled over
+    fn roll(&mut self, now: u64) {
+        let (day, month) = (day_index(now), month_index(now));
+        if self.day != day {
+            self.day = day;
+            self.daily_proofs = 0;
+            self.daily_proving_seconds = 0.0;
+        }
+        if self.month != month {
+            self.month = month;
+            self.monthly_proofs = 0;
+            self.monthly_proving_seconds = 0.0;
+        }
+    }
+}
+
+/// Remaining allowance after a reservation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QuotaRemaining {
+    pub proofs: Option<u64>,
+    pub proving_seconds: Option<f64>,
+    /// Day and month indices the reserved proof was counted in
+    charged: Option<(u64, u64)>,
+}
+
+impl QuotaRemaining {
+    /// Allowance `usage` leaves under `limits`
+    fn left(limits: &QuotaLimits, usage: &QuotaUsage) -> Self {
+        Self {
+            proofs: [
+                limits
+                    .daily_proofs
+                    .map(|l| l.saturating_sub(usage.daily_proofs)),
+                limits
+                    .monthly_proofs
+                    .map(|l| l.saturating_sub(usage.monthly_proofs)),
+            ]
+            .into_iter()
+            .flatten()
+            .min(),
+            proving_seconds: [
+                limits
+                    .daily_proving_seconds
+                    .map(|l| (l - usage.daily_proving_seconds).max(0.0)),
+                limits
+                    .monthly_proving_seconds
+                    .map(|l| (l - usage.monthly_proving_seconds).max(0.0)),
+            ]
+            .into_iter()
+            .flatten()
+            .reduce(f64::min),
+            charged: None,
+        }
+    }
+
+    /// Render as `X-Quota-Remaining*` response headers
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(proofs) = self.proofs {
+            headers.insert(QUOTA_REMAINING_HEADER, HeaderValue::from(proofs));
+        }
+        if let Some(seconds) = self.proving_seconds {
+            if let Ok(value) = HeaderValue::from_str(&format!("{:.3}", seconds)) {
+                headers.insert(QUOTA_REMAINING_SECONDS_HEADER, value);
+            }
+        }
+        headers
+    }
+}
+
+/// A proof reserved against a key's quota, returned to it when dropped
+/// without being committed
+pub struct QuotaReservation<'a> {
+    quotas: &'a QuotaManager,
+    key_id: String,
+    remaining: QuotaRemaining,
+    committed: bool,
+}
+
+impl QuotaReservation<'_> {
+    /// Allowance left with this proof reserved
+    #[cfg(test)]
+    pub fn remaining(&self) -> QuotaRemaining {
+        self.remaining
+    }
+
+    /// Keep the reserved proof, charging `proving_time` to the budget;
+    /// returns the allowance left after the charge
+    pub fn commit(mut self, proving_time: Duration) -> QuotaRemaining {
+        self.committed = true;
+        self.quotas.commit(&self.key_id, proving_time)
+    }
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.quotas.release(&self.key_id, &self.remaining);
+        }
+    }
+}
+
+/// Reason a request was refused
+#[derive(Debug, thiserror::Error)]
+pub enum QuotaError {
+    #[error("{window} proof quota exhausted")]
+    ProofsExhausted { window: &'static str },
+
+    #[error("{window} proving-time budget exhausted")]
+    BudgetExhausted { window: &'static str },
+}
+
+impl From<QuotaError> for ApiError {
+    fn from(err: QuotaError) -> Self {
+        match err {
+            QuotaError::ProofsExhausted { .. } => api_error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "QUOTA_EXCEEDED",
+                err.to_string(),
+            ),
+            QuotaError::BudgetExhausted { .. } => api_error(
+                StatusCode::PAYMENT_REQUIRED,
+                "BUDGET_EXHAUSTED",
+                err.to_string(),
+            ),
+        }
+    }
+}
+
+/// Limits and usage for one key, as reported by the admin API
+#[derive(Serialize)]
+pub struct KeyQuota {
+    pub key_id: String,
+    pub limits: QuotaLimits,
+    pub usage: QuotaUsage,
+}
+
+/// Usage as persisted, with the windows it was counted in
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredUsage {
+    #[serde(flatten)]
+    usage: QuotaUsage,
+    day: u64,
+    month: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct QuotaState {
+    limits: HashMap<String, QuotaLimits>,
+    #[serde(with = "stored_usage")]
+    usage: HashMap<String, QuotaUsage>,
+}
+
+/// Enforces per-key quotas
+#[derive(Default)]
+pub struct QuotaManager {
+    /// Where limits and usage are persisted (in memory only when unset)
+    path: Option<PathBuf>,
+    /// Limits of anonymous callers without limits of their own
+    anonymous: QuotaLimits,
+    state: Mutex<QuotaState>,
+}
+
+impl QuotaManager {
+    /// Quotas kept in memory only, with anonymous callers unlimited
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open (or create) the quota store in the data directory
+    pub fn open(data_dir: &Path, anonymous: QuotaLimits) -> Result<Self> {
+        let path = data_dir.join("quotas.json");
+        let state: QuotaState = load_json(&path)?.unwrap_or_default();
+        tracing::info!(
+            "Loaded quotas of {} keys from {}",
+            state.limits.len(),
+            path.display()
+        );
+        Ok(Self {
+            path: Some(path),
+            anonymous,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn save(&self, state: &QuotaState) -> Result<()> {
+        match &self.path {
+            Some(path) => save_json(path, state),
+            None => Ok(()),
+        }
+    }
+
+    /// Persist usage; a failed write is logged rather than failing the request
+    fn save_usage(&self, state: &QuotaState) {
+        if let Err(e) = self.save(state) {
+            tracing::warn!("Failed to persist quota usage: {}", e);
+        }
+    }
+
+    /// Limits applying to a key
+    fn limits_for(&self, state: &QuotaState, key_id: &str) -> Option<QuotaLimits> {
+        match state.limits.get(key_id) {
+            Some(limits) => Some(limits.clone()),
+            None if key_id == ANONYMOUS_KEY_ID && self.anonymous.is_limited() => {
+                Some(self.anonymous.clone())
+            }
+            None => None,
+        }
+    }
+
+    /// Set the limits for a key
+    pub fn set_limits(&self, key_id: &str, limits: QuotaLimits) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.limits.insert(key_id.to_string(), limits);
+        if let Err(e) = self.save(&state) {
+            match previous {
+                Some(previous) => state.limits.insert(key_id.to_string(), previous),
+                None => state.limits.remove(key_id),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove the limits for a key, making it unlimited
+    pub fn remove_limits(&self, key_id: &str) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let Some(previous) = state.limits.remove(key_id) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save(&state) {
+            state.limits.insert(key_id.to_string(), previous);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    /// Limits and usage for a key
+    pub fn get(&self, key_id: &str) -> Option<KeyQuota> {
+        let mut state = self.state.lock().unwrap();
+        let limits = self.limits_for(&state, key_id)?;
+        let usage = state.usage.entry(key_id.to_string()).or_default();
+        usage.roll(now_secs());
+        Some(KeyQuota {
+            key_id: key_id.to_string(),
+            limits,
+            usage: usage.clone(),
+        })
+    }
+
+    /// All keys with configured limits, and anonymous callers when limited
+    pub fn list(&self) -> Vec<KeyQuota> {
+        let key_ids: Vec<String> = {
+            let state = self.state.lock().unwrap();
+            let mut key_ids: Vec<String> = state.limits.keys().cloned().collect();
+            if self.anonymous.is_limited() && !state.limits.contains_key(ANONYMOUS_KEY_ID) {
+                key_ids.push(ANONYMOUS_KEY_ID.to_string());
+            }
+            key_ids
+        };
+        let mut quotas: Vec<KeyQuota> = key_ids.iter().filter_map(|id| self.get(id)).collect();
+        quotas.sort_by(|a, b| a.key_id.cmp(&b.key_id));
+        quotas
+    }
+
+    /// Reserve one proof for a key, failing if any quota is exhausted
+    pub fn reserve(&self, key_id: &str) -> Result<QuotaReservation<'_>, QuotaError> {
+        let remaining = self.reserve_at(key_id, now_secs())?;
+        Ok(QuotaReservation {
+            quotas: self,
+            key_id: key_id.to_string(),
+            remaining,
+            committed: false,
+        })
+    }
+
+    fn reserve_at(&self, key_id: &str, now: u64) -> Result<QuotaRemaining, QuotaError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(limits) = self.limits_for(&state, key_id) else {
+            return Ok(QuotaRemaining::default());
+        };
+        let usage = state.usage.entry(key_id.to_string()).or_default();
+        usage.roll(now);
+
+        let proofs_left = [
+            limits
+                .daily_proofs
+                .map(|l| (l.saturating_sub(usage.daily_proofs), "daily")),
+            limits
+                .monthly_proofs
+                .map(|l| (l.saturating_sub(usage.monthly_proofs), "monthly")),
+        ];
+        let seconds_left = [
+            limits
+                .daily_proving_seconds
+                .map(|l| ((l - usage.daily_proving_seconds).max(0.0), "daily")),
+            limits
+                .monthly_proving_seconds
+                .map(|l| ((l - usage.monthly_proving_seconds).max(0.0), "monthly")),
+        ];
+
+        if let Some((_, window)) = proofs_left.iter().flatten().find(|(left, _)| *left == 0) {
+            return Err(QuotaError::ProofsExhausted { window });
+        }
+        if let Some((_, window)) = seconds_left.iter().flatten().find(|(left, _)| *left <= 0.0) {
+            return Err(QuotaError::BudgetExhausted { window });
+        }
+
+        usage.daily_proofs += 1;
+        usage.monthly_proofs += 1;
+        let charged = Some((usage.day, usage.month));
+        self.save_usage(&state);
+
+        Ok(QuotaRemaining {
+            proofs: proofs_left.iter().flatten().map(|(left, _)| left - 1).min(),
+            proving_seconds: seconds_left
+                .iter()
+                .flatten()
+                .map(|(left, _)| *left)
+                .reduce(f64::min),
+            charged,
+        })
+    }
+
+    /// Charge the proving time of a completed reservation, returning the
+    /// allowance left
+    fn commit(&self, key_id: &str, proving_time: Duration) -> QuotaRemaining {
+        self.commit_at(key_id, proving_time, now_secs())
+    }
+
+    fn commit_at(&self, key_id: &str, proving_time: Duration, now: u64) -> QuotaRemaining {
+        let mut state = self.state.lock().unwrap();
+        let limits = self.limits_for(&state, key_id);
+        let Some(usage) = state.usage.get_mut(key_id) else {
+            return QuotaRemaining::default();
+        };
+        usage.roll(now);
+        usage.daily_proving_seconds += proving_time.as_secs_f64();
+        usage.monthly_proving_seconds += proving_time.as_secs_f64();
+        let remaining = limits
+            .map(|limits| QuotaRemaining::left(&limits, usage))
+            .unwrap_or_default();
+        self.save_usage(&state);
+        remaining
+    }
+
+    /// Return `reservation`, whose proof was not produced, to the windows it
+    /// was charged to; windows that have since rolled over are left alone
+    fn release(&self, key_id: &str, reservation: &QuotaRemaining) {
+        let Some((day, month)) = reservation.charged else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let Some(usage) = state.usage.get_mut(key_id) else {
+            return;
+        };
+        if usage.day == day {
+            usage.daily_proofs = usage.daily_proofs.saturating_sub(1);
+        }
+        if usage.month == month {
+            usage.monthly_proofs = usage.monthly_proofs.saturating_sub(1);
+        }
+        self.save_usage(&state);
+    }
+}
+
+/// Usage persisted together with its windows, which the API does not show
+mod stored_usage {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        usage: &HashMap<String, QuotaUsage>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let stored: HashMap<&String, StoredUsage> = usage
+            .iter()
+            .map(|(key_id, usage)| {
+                let stored = StoredUsage {
+                    usage: usage.clone(),
+                    day: usage.day,
+                    month: usage.month,
+                };
+                (key_id, stored)
+            })
+            .collect();
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, QuotaUsage>, D::Error> {
+        let stored = HashMap::<String, StoredUsage>::deserialize(deserializer)?;
+        Ok(stored
+            .into_iter()
+            .map(|(key_id, stored)| {
+                let usage = QuotaUsage {
+                    day: stored.day,
+                    month: stored.month,
+                    ..stored.usage
+                };
+                (key_id, usage)
+            })
+            .collect())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn day_index(secs: u64) -> u64 {
+    secs / 86_400
+}
+
+/// Months since 1970-01 for a unix timestamp (UTC)
+fn month_index(secs: u64) -> u64 {
+    // Civil-from-days conversion (H. Hinnant), restricted to post-epoch dates
+    let z = day_index(secs) + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year - 1970) * 12 + (month - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_index() {
+        assert_eq!(month_index(0), 0);
+        // 2024-02-29T12:00:00Z
+        assert_eq!(month_index(1_709_208_000), 54 * 12 + 1);
+        // 2024-03-01T00:00:00Z
+        assert_eq!(month_index(1_709_251_200), 54 * 12 + 2);
+    }
+
+    #[test]
+    fn test_proof_quota_enforced_and_released() {
+        let quotas = QuotaManager::new();
+        quotas
+            .set_limits(
+                "key_a",
+                QuotaLimits {
+                    daily_proofs: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let first = quotas.reserve("key_a").unwrap();
+        assert_eq!(first.remaining().proofs, Some(1));
+        let second = quotas.reserve("key_a").unwrap();
+        assert_eq!(second.remaining().proofs, Some(0));
+        assert!(matches!(
+            quotas.reserve("key_a"),
+            Err(QuotaError::ProofsExhausted { window: "daily" })
+        ));
+
+        // Dropping an uncommitted reservation releases it, committing keeps it
+        drop(first);
+        quotas.reserve("key_a").unwrap().commit(Duration::ZERO);
+        assert!(quotas.reserve("key_a").is_err());
+        drop(second);
+        assert!(quotas.reserve("key_a").is_ok());
+
+        // Unconfigured keys are unlimited
+        let unlimited = quotas.reserve("key_b").unwrap();
+        assert!(unlimited.remaining().proofs.is_none());
+    }
+
+    #[test]
+    fn test_budget_exhausted_and_daily_rollover() {
+        let quotas = QuotaManager::new();
+        quotas
+            .set_limits(
+                "key_a",
+                QuotaLimits {
+                    daily_proving_seconds: Some(1.0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let day = 20_000 * 86_400;
+        let reserved = quotas.reserve_at("key_a", day).unwrap();
+        assert_eq!(reserved.proving_seconds, Some(1.0));
+        let committed = quotas.commit_at("key_a", Duration::from_millis(250), day);
+        assert_eq!(committed.proving_seconds, Some(0.75));
+        quotas.reserve_at("key_a", day + 30).unwrap();
+        let committed = quotas.commit_at("key_a", Duration::from_millis(1200), day + 30);
+        assert_eq!(committed.proving_seconds, Some(0.0));
+        assert!(matches!(
+            quotas.reserve_at("key_a", day + 60),
+            Err(QuotaError::BudgetExhausted { window: "daily" })
+        ));
+
+        assert!(quotas.reserve_at("key_a", day + 86_400).is_ok());
+    }
+
+    #[test]
+    fn test_release_refunds_the_charged_window() {
+        let quotas = QuotaManager::new();
+        quotas
+            .set_limits(
+                "key_a",
+                QuotaLimits {
+                    daily_proofs: Some(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Reserved late on one day, released after midnight
+        let day = 20_000 * 86_400;
+        let yesterday = quotas.reserve_at("key_a", day - 60).unwrap();
+        let today = quotas.reserve_at("key_a", day).unwrap();
+        quotas.release("key_a", &yesterday);
+        assert!(matches!(
+            quotas.reserve_at("key_a", day + 60),
+            Err(QuotaError::ProofsExhausted { window: "daily" })
+        ));
+        quotas.release("key_a", &today);
+        assert!(quotas.reserve_at("key_a", day + 60).is_ok());
+    }
+
+    #[test]
+    fn test_usage_persists_and_anonymous_default_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        let anonymous = QuotaLimits {
+            daily_proofs: Some(1),
+            ..Default::default()
+        };
+        let quotas = QuotaManager::open(dir.path(), anonymous.clone()).unwrap();
+        quotas
+            .set_limits(
+                "key_a",
+                QuotaLimits {
+                    daily_proofs: Some(5),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        quotas
+            .reserve("key_a")
+            .unwrap()
+            .commit(Duration::from_secs(2));
+        let _anonymous = quotas.reserve(ANONYMOUS_KEY_ID).unwrap();
+        assert!(quotas.reserve(ANONYMOUS_KEY_ID).is_err());
+
+        let reopened = QuotaManager::open(dir.path(), anonymous).unwrap();
+        let usage = reopened.get("key_a").unwrap().usage;
+        assert_eq!(usage.daily_proofs, 1);
+        assert_eq!(usage.daily_proving_seconds, 2.0);
+        assert!(reopened.reserve(ANONYMOUS_KEY_ID).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Raw binary prove requests
+//!
+//! Besides JSON, `POST /prove` accepts `Content-Type: application/x-f32-le`
+//! bodies: the input features as a packed array of little-endian f32, with
+//! the model named in `X-Model-Id` and an optional `X-Input-Shape` (e.g.
+//! `1,1024,1024`) that must account for every element. Large feature vectors
+//! then skip JSON parsing and float re-encoding entirely. NaN and infinite
+//! features are rejected unless `X-Allow-Non-Finite: true` is sent.
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::body_limit::{self, BodyLimit};
+use crate::types::{api_error, ApiError, ProveRequest};
+
+/// Content type of raw little-endian f32 bodies
+pub const F32_LE_CONTENT_TYPE: &str = "application/x-f32-le";
+
+/// Header naming the model of a raw request
+pub const MODEL_ID_HEADER: &str = "x-model-id";
+
+/// Header carrying the comma-separated input shape of a raw request
+pub const INPUT_SHAPE_HEADER: &str = "x-input-shape";
+
+/// Header accepting non-finite features in a raw request
+pub const ALLOW_NON_FINITE_HEADER: &str = "x-allow-non-finite";
+
+/// A prove request read from either a JSON or a raw f32 body
+pub struct ProveBody(pub ProveRequest);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for ProveBody {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_raw(request.headers()) {
+            let Json(request) = Json::<ProveRequest>::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(ProveBody(request));
+        }
+
+        // Bounded by the route's `MAX_RAW_BODY_BYTES` limit (see `body_limit`)
+        let (parts, body) = request.into_parts();
+        let limit = parts
+            .extensions
+            .get::<BodyLimit>()
+            .map_or(usize::MAX, |limit| limit.0);
+        let bytes = axum::body::to_bytes(body, limit).await.map_err(|e| {
+            match body_limit::exceeded(&e) {
+                true => body_limit::too_large(limit),
+                false => invalid_raw(format!("Failed to read raw inputs: {}", e)),
+            }
+            .into_response()
+        })?;
+        raw_request(&parts.headers, &bytes)
+            .map(ProveBody)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+/// Whether a request carries a raw f32 body
+pub fn is_raw(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case(F32_LE_CONTENT_TYPE))
+}
+
+/// Build a prove request from raw headers and body
+fn raw_request(headers: &HeaderMap, body: &[u8]) -> Result<ProveRequest, ApiError> {
+    let model_id = headers
+        .get(MODEL_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| invalid_raw("Raw inputs require an X-Model-Id header"))?;
+    let inputs = decode_f32_le(body)?;
+
+    if let Some(shape) = headers.get(INPUT_SHAPE_HEADER) {
+        let dims = shape
+            .to_str()
+            .ok()
+            .and_then(|shape| {
+                shape
+                    .split(',')
+                    .map(|d| d.trim().parse::<usize>().ok())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| invalid_raw("X-Input-Shape must be comma-separated integers"))?;
+        let elements = dims.iter().try_fold(1usize, |n, d| n.checked_mul(*d));
+        if elements != Some(inputs.len()) {
+            return Err(invalid_raw(format!(
+                "X-Input-Shape {:?} does not match the {} features sent",
+                dims,
+                inputs.len()
+            )));
+        }
+    }
+
+    Ok(ProveRequest {
+        model_id: model_id.to_string(),
+        inputs,
+        allow_non_finite: headers
+            .get(ALLOW_NON_FINITE_HEADER)
+            .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"true")),
+        ..Default::default()
+    })
+}
+
+/// Decode a packed little-endian f32 array
+fn decode_f32_le(body: &[u8]) -> Result<Vec<f32>, ApiError> {
+    if !body.len().is_multiple_of(4) {
+        return Err(invalid_raw(format!(
+            "Raw body length {} is not a multiple of 4",
+            body.len()
+        )));
+    }
+    Ok(body
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn invalid_raw(message: impl Into<String>) -> ApiError {
+    api_error(StatusCode::BAD_REQUEST, "INVALID_RAW_INPUTS", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_requests() {
+        let body: Vec<u8> = [1.5f32, -2.0, 0.25, 8.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, F32_LE_CONTENT_TYPE.parse().unwrap());
+        headers.insert(MODEL_ID_HEADER, "m".parse().unwrap());
+        headers.insert(INPUT_SHAPE_HEADER, "2, 2".parse().unwrap());
+        assert!(is_raw(&headers));
+
+        let request = raw_request(&headers, &body).unwrap();
+        assert_eq!(request.model_id, "m");
+        assert_eq!(request.inputs, vec![1.5, -2.0, 0.25, 8.0]);
+
+        headers.insert(INPUT_SHAPE_HEADER, "3".parse().unwrap());
+        assert!(raw_request(&headers, &body).is_err());
+        assert!(raw_request(&headers, &body[..3]).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Registrant signatures
+//!
+//! Uploaders can sign the model bytes they register with an Ed25519 or
+//! ECDSA P-256 key. The signature is checked at registration and stored with
+//! the model, making the uploader cryptographically accountable for it.
+//!
+//! With `REQUIRE_REGISTRANT_SIGNATURE` set, unsigned registrations are
+//! rejected; with `REGISTRANT_ALLOWLIST` set (comma-separated hex public
+//! keys), only listed keys may register models.
+
+use anyhow::{anyhow, Result};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::ServiceConfig;
+use crate::types::{api_error, ApiError};
+
+/// Signature algorithm used by a registrant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegistrantAlg {
+    /// Ed25519 (32-byte public key, 64-byte signature)
+    Ed25519,
+    /// ECDSA over P-256 with SHA-256 (SEC1 public key, raw or DER signature)
+    EcdsaP256,
+}
+
+/// Signature over the uploaded model bytes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistrantSignature {
+    pub alg: RegistrantAlg,
+
+    /// Public key (hex)
+    pub public_key: String,
+
+    /// Signature over the decoded model bytes (hex)
+    pub signature: String,
+}
+
+/// Verified registrant stored with a model
+#[derive(Clone, Debug, Serialize)]
+pub struct Registrant {
+    /// Short identifier derived from the public key
+    pub key_id: String,
+    #[serde(flatten)]
+    pub signature: RegistrantSignature,
+}
+
+impl RegistrantSignature {
+    /// Verify the signature over `message`
+    pub fn verify(&self, message: &[u8]) -> Result<()> {
+        let public = decode_hex(&self.public_key, "public key")?;
+        let signature = decode_hex(&self.signature, "signature")?;
+
+        match self.alg {
+            RegistrantAlg::Ed25519 => {
+                use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+                let public: [u8; 32] = public
+                    .try_into()
+                    .map_err(|_| anyhow!("Ed25519 public key must be 32 bytes"))?;
+                let signature = Signature::from_slice(&signature)
+                    .map_err(|_| anyhow!("Ed25519 signature must be 64 bytes"))?;
+                VerifyingKey::from_bytes(&public)
+                    .map_err(|_| anyhow!("Invalid Ed25519 public key"))?
+                    .verify(message, &signature)
+                    .map_err(|_| anyhow!("Registrant signature does not verify"))
+            }
+            RegistrantAlg::EcdsaP256 => {
+                use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+                let key = VerifyingKey::from_sec1_bytes(&public)
+                    .map_err(|_| anyhow!("Invalid P-256 public key"))?;
+                let signature = Signature::from_slice(&signature)
+                    .or_else(|_| Signature::from_der(&signature))
+                    .map_err(|_| anyhow!("Invalid P-256 signature encoding"))?;
+                key.verify(message, &signature)
+                    .map_err(|_| anyhow!("Registrant signature does not verify"))
+            }
+        }
+    }
+
+    /// Key id: truncated SHA-256 of the public key bytes
+    pub fn key_id(&self) -> String {
+        let public = hex::decode(normalize_key(&self.public_key)).unwrap_or_default();
+        format!("reg_{}", &hex::encode(Sha256::digest(public))[..16])
+    }
+}
+
+/// Who may register models
+pub struct RegistrantPolicy {
+    required: bool,
+    allowlist: Vec<String>,
+}
+
+impl RegistrantPolicy {
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            required: config.require_registrant_signature,
+            allowlist: config
+                .registrant_allowlist
+                .iter()
+                .map(|k| normalize_key(k))
+                .collect(),
+        }
+    }
+
+    /// Check a registration against the policy, returning the verified registrant
+    pub fn authorize(
+        &self,
+        model_bytes: &[u8],
+        signature: Option<&RegistrantSignature>,
+    ) -> Result<Option<Registrant>, ApiError> {
+        let Some(signature) = signature else {
+            if self.required || !self.allowlist.is_empty() {
+                return Err(api_error(
+                    StatusCode::UNAUTHORIZED,
+                    "REGISTRANT_SIGNATURE_REQUIRED",
+                    "Model registration requires a registrant signature",
+                ));
+            }
+            return Ok(None);
+        };
+
+        signature.verify(model_bytes).map_err(|e| {
+            api_error(
+                StatusCode::UNAUTHORIZED,
+                "INVALID_REGISTRANT_SIGNATURE",
+                e.to_string(),
+            )
+        })?;
+
+        if !self.allowlist.is_empty()
+            && !self
+                .allowlist
+                .contains(&normalize_key(&signature.public_key))
+        {
+            return Err(api_error(
+                StatusCode::FORBIDDEN,
+                "REGISTRANT_NOT_ALLOWED",
+                format!("Registrant key {} is not allowlisted", signature.key_id()),
+            ));
+        }
+
+        Ok(Some(Registrant {
+            key_id: signature.key_id(),
+            signature: signature.clone(),
+        }))
+    }
+}
+
+/// Canonical form of a hex public key for comparisons
+pub fn normalize_key(key: &str) -> String {
+    key.trim_start_matches("0x").to_ascii_lowercase()
+}
+
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| anyhow!("Invalid {} encoding", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn policy(required: bool, allowlist: Vec<String>) -> RegistrantPolicy {
+        RegistrantPolicy {
+            required,
+            allowlist,
+        }
+    }
+
+    fn ed25519(message: &[u8]) -> RegistrantSignature {
+        use ed25519_dalek::{Signer, SigningKey};
+        let key = SigningKey::generate(&mut OsRng);
+        RegistrantSignature {
+            alg: RegistrantAlg::Ed25519,
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            signature: hex::encode(key.sign(message).to_bytes()),
+        }
+    }
+
+    fn p256(message: &[u8]) -> RegistrantSignature {
+        use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+        let key = SigningKey::random(&mut OsRng);
+        let signature: Signature = key.sign(message);
+        RegistrantSignature {
+            alg: RegistrantAlg::EcdsaP256,
+            public_key: hex::encode(key.verifying_key().to_sec1_bytes()),
+            signature: hex::encode(signature.to_der().as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_both_algorithms_verify() {
+        for signature in [ed25519(b"model"), p256(b"model")] {
+            assert!(signature.verify(b"model").is_ok());
+            assert!(signature.verify(b"other model").is_err());
+        }
+    }
+
+    #[test]
+    fn test_policy_enforces_requirement_and_allowlist() {
+        let signature = ed25519(b"model");
+
+        assert!(policy(false, vec![])
+            .authorize(b"model", None)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            policy(true, vec![])
+                .authorize(b"model", None)
+                .unwrap_err()
+                .0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let allowlisted = policy(true, vec![signature.public_key.clone()]);
+        let registrant = allowlisted
+            .authorize(b"model", Some(&signature))
+            .unwrap()
+            .unwrap();
+        assert_eq!(registrant.key_id, signature.key_id());
+
+        let stranger = ed25519(b"model");
+        assert_eq!(
+            allowlisted
+                .authorize(b"model", Some(&stranger))
+                .unwrap_err()
+                .0,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Inference recording and replay
+//!
+//! With `RECORD_INFERENCE=true` every proof's inference is recorded under
+//! `recordings/` in the storage backend: the model, the inputs that were
+//! proven, the public inputs (outputs, hashes, scheme and output checks),
+//! the backend and the proving time. Requests with sealed inputs are not
+//! recorded, since sealed inputs are never to be persisted in plaintext.
+//!
+//! `POST /replay/:proof_id` re-runs the recorded inference against the model
+//! as registered now and reports whether the input and output hashes still
+//! match, for post-incident forensics. Recordings are keyed by the proof's
+//! content-addressed id (see `proofs`), so anyone holding a proof can name
+//! its recording. Recordings made before ids were content-addressed are
+//! named by the bare hex SHA-256 of the proof's base64 encoding.
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::inference;
+use crate::jolt_atlas::hash_tensor;
+use crate::predicate::OutputPredicate;
+use crate::proofs::{proof_digest, proof_id};
+use crate::prover::bind_statements;
+use crate::storage::Storage;
+use crate::types::{api_error, ApiError, CommitmentScheme, ProveResponse, PublicInputs};
+use crate::verification::{compute_input_hash, compute_output_hash, ct_eq};
+use crate::AppState;
+
+/// Key prefix of inference recordings
+const PREFIX: &str = "recordings/";
+
+/// One proof's inference, as recorded
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InferenceRecord {
+    pub proof_id: String,
+    pub recorded_at: u64,
+    /// Key id the proof was generated for
+    pub key_id: String,
+    pub model_id: String,
+    /// The proven inputs, as raw f32 bit patterns so every value (NaN and
+    /// -0.0 included) replays exactly
+    #[serde(with = "f32_bits")]
+    pub inputs: Vec<f32>,
+    /// Outputs, hashes and output checks, as returned with the proof
+    pub public_inputs: PublicInputs,
+    /// Backend the proof was generated with
+    pub backend: String,
+    pub proving_time_ms: u64,
+}
+
+impl InferenceRecord {
+    /// Record of the inference behind `response`, proven over `inputs`
+    pub fn new(
+        caller: &Caller,
+        inputs: Vec<f32>,
+        response: &ProveResponse,
+        backend: String,
+    ) -> Self {
+        Self {
+            proof_id: proof_id(&response.proof),
+            recorded_at: now_secs(),
+            key_id: caller.key_id.clone(),
+            model_id: response.model_id.clone(),
+            inputs,
+            public_inputs: response.public_inputs.clone(),
+            backend,
+            proving_time_ms: response.proving_time_ms,
+        }
+    }
+}
+
+/// Result of replaying a recorded inference
+#[derive(Serialize)]
+pub struct ReplayResponse {
+    pub proof_id: String,
+    pub model_id: String,
+    /// Whether the model, inputs and outputs all still hash as recorded
+    pub consistent: bool,
+    /// Whether the registered model still has the recorded commitment
+    pub model_matches: bool,
+    pub input_hash_matches: bool,
+    pub output_hash_matches: bool,
+    pub recorded_output: Vec<f32>,
+    pub replayed_output: Vec<f32>,
+    pub recorded_backend: String,
+    pub backend: String,
+    pub replay_time_ms: u64,
+}
+
+/// Inference recordings in the storage backend
+pub struct Recorder {
+    storage: Arc<dyn Storage>,
+}
+
+impl Recorder {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    pub async fn put(&self, record: &InferenceRecord) -> Result<()> {
+        let digest = proof_digest(&record.proof_id)
+            .ok_or_else(|| anyhow!("Invalid proof id {}", record.proof_id))?;
+        self.storage
+            .put(&key(&digest), serde_json::to_vec_pretty(record)?)
+            .await
+    }
+
+    pub async fn get(&self, proof_id: &str) -> Result<Option<InferenceRecord>> {
+        // Legacy ids are bare hex digests; anything else cannot name a recording
+        let digest = match proof_digest(proof_id) {
+            Some(digest) => digest,
+            None if proof_id.len() == 64 && proof_id.chars().all(|c| c.is_ascii_hexdigit()) => {
+                proof_id.to_ascii_lowercase()
+            }
+            None => return Ok(None),
+        };
+        let key = key(&digest);
+        self.storage
+            .get(&key)
+            .await?
+            .map(|bytes| {
+                serde_json::from_slice(&bytes)
+                    .with_context(|| format!("Corrupt inference recording {}", key))
+            })
+            .transpose()
+    }
+}
+
+/// Key of the recording of the proof with hex digest `digest`
+fn key(digest: &str) -> String {
+    format!("{}{}.json", PREFIX, digest)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Input and output hashes of `inputs` and `output` under the recorded
+/// proof's scheme and output checks
+pub fn replay_hashes(
+    public_inputs: &PublicInputs,
+    inputs: &[f32],
+    output: &[f32],
+) -> Result<(String, String)> {
+    match public_inputs.commitment_scheme {
+        CommitmentScheme::Sha256V1 => Ok((compute_input_hash(inputs), compute_output_hash(output))),
+        CommitmentScheme::Sha256V2 => {
+            let bound = bind_statements(
+                CommitmentScheme::Sha256V2,
+                &public_inputs.model_commitment,
+                public_inputs.preprocessor.as_deref(),
+                public_inputs.quantization.as_ref(),
+                public_inputs.expected_output.as_ref(),
+                public_inputs.predicate.as_ref(),
+            )?;
+            Ok((
+                hash_tensor(&bound, "input", &[inputs.len()], inputs),
+                hash_tensor(&bound, "output", &[output.len()], output),
+            ))
+        }
+        other => Err(anyhow!("Cannot replay {} hashes", other)),
+    }
+}
+
+/// Compare a replayed model output with the recording, returning whether the
+/// input and output hashes match and the output the proof would disclose
+fn check(record: &InferenceRecord, model_output: Vec<f32>) -> Result<(bool, bool, Vec<f32>)> {
+    check_replayed(&record.public_inputs, &record.inputs, model_output)
+}
+
+/// Compare the output of re-running a proof's inference over `inputs` with
+/// its `public_inputs`, as `check` (also used by offline audits, see
+/// `offline_audit`)
+pub fn check_replayed(
+    public_inputs: &PublicInputs,
+    inputs: &[f32],
+    model_output: Vec<f32>,
+) -> Result<(bool, bool, Vec<f32>)> {
+    // In predicate mode only the predicate bit was proven and hashed
+    let output = match &public_inputs.predicate {
+        Some(predicate) => OutputPredicate::disclosed_output(predicate.evaluate(&model_output)?),
+        None => model_output,
+    };
+    let (input_hash, output_hash) = replay_hashes(public_inputs, inputs, &output)?;
+    Ok((
+        ct_eq(&input_hash, &public_inputs.input_hash),
+        ct_eq(&output_hash, &public_inputs.output_hash),
+        output,
+    ))
+}
+
+/// Re-run a recorded inference and check it still hashes as recorded
+pub async fn replay(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(proof_id): Path<String>,
+) -> Result<Json<ReplayResponse>, ApiError> {
+    caller.require(Scope::Prove)?;
+    let Some(recorder) = &state.recorder else {
+        return Err(api_error(
+            StatusCode::NOT_IMPLEMENTED,
+            "RECORDING_DISABLED",
+            "Inference recording is not enabled on this service",
+        ));
+    };
+    let record = recorder
+        .get(&proof_id)
+        .await
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RECORDING_UNREADABLE",
+                e.to_string(),
+            )
+        })?
+        .filter(|record| record.key_id == caller.key_id)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "RECORDING_NOT_FOUND",
+                format!("No inference recording for proof {}", proof_id),
+            )
+        })?;
+
+    let start = Instant::now();
+    let prover = state.prover.read().await;
+    let model = prover.get_model(&record.model_id).ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            "MODEL_NOT_FOUND",
+            format!("Model {} is no longer registered", record.model_id),
+        )
+    })?;
+    let model_matches = ct_eq(&model.commitment, &record.public_inputs.model_commitment);
+    let replay_error = |e: anyhow::Error| {
+        api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "REPLAY_FAILED",
+            e.to_string(),
+        )
+    };
+    let model_output = prover
+        .infer(&record.model_id, &record.inputs)
+        .await
+        .map_err(replay_error)?;
+    let (input_hash_matches, output_hash_matches, replayed_output) =
+        check(&record, inference::flatten(&model_output)).map_err(replay_error)?;
+    let backend = prover.get_prover_info().await;
+
+    let consistent = model_matches && input_hash_matches && output_hash_matches;
+    if !consistent {
+        tracing::warn!(
+            "Replay of proof {} diverged (model {}, inputs {}, outputs {})",
+            record.proof_id,
+            model_matches,
+            input_hash_matches,
+            output_hash_matches
+        );
+    }
+    Ok(Json(ReplayResponse {
+        proof_id: record.proof_id,
+        model_id: record.model_id,
+        consistent,
+        model_matches,
+        input_hash_matches,
+        output_hash_matches,
+        recorded_output: record.public_inputs.output,
+        replayed_output,
+        recorded_backend: record.backend,
+        backend,
+        replay_time_ms: start.elapsed().as_millis() as u64,
+    }))
+}
+
+/// Serde for f32 vectors as their bit patterns
+mod f32_bits {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[f32], serializer: S) -> Result<S::Ok, S::Error> {
+        values
+            .iter()
+            .map(|v| v.to_bits())
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f32>, D::Error> {
+        Ok(Vec::<u32>::deserialize(deserializer)?
+            .into_iter()
+            .map(f32::from_bits)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::ModelCommitments;
+    use crate::predicate::PredicateOp;
+
+    fn recorded(
+        predicate: Option<OutputPredicate>,
+        inputs: Vec<f32>,
+        output: &[f32],
+    ) -> InferenceRecord {
+        let model = ModelCommitments::compute(b"model");
+        let disclosed = match &predicate {
+            Some(p) => OutputPredicate::disclosed_output(p.evaluate(output).unwrap()),
+            None => output.to_vec(),
+        };
+        let bound = match &predicate {
+            Some(p) => p.bind(&model).unwrap(),
+            None => model.clone(),
+        };
+        let public_inputs: PublicInputs = serde_json::from_value(serde_json::json!({
+            "model_commitment": model.sha256,
+            "input_hash": bound.input_hash(&inputs),
+            "output_hash": bound.output_hash(&disclosed),
+            "output": disclosed,
+            "timestamp": 0,
+            "commitment_scheme": "sha256-v2",
+            "predicate": predicate,
+        }))
+        .unwrap();
+        InferenceRecord {
+            proof_id: proof_id("proof"),
+            recorded_at: 0,
+            key_id: "k".to_string(),
+            model_id: "m".to_string(),
+            inputs,
+            public_inputs,
+            backend: "mock".to_string(),
+            proving_time_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_replay_detects_diverging_outputs() {
+        let record = recorded(None, vec![1.0, -0.0, f32::NAN], &[0.25, 0.75]);
+        let round_tripped: InferenceRecord =
+            serde_json::from_slice(&serde_json::to_vec(&record).unwrap()).unwrap();
+        assert_eq!(round_tripped.inputs[1].to_bits(), (-0.0f32).to_bits());
+        assert!(round_tripped.inputs[2].is_nan());
+
+        assert_eq!(
+            check(&round_tripped, vec![0.25, 0.75]).unwrap(),
+            (true, true, vec![0.25, 0.75])
+        );
+        assert!(!check(&round_tripped, vec![0.25, 0.5]).unwrap().1);
+
+        // With a predicate, a drift that does not flip the bit still matches
+        let predicate = OutputPredicate {
+            output_index: 1,
+            op: PredicateOp::Gt,
+            threshold: 0.5,
+        };
+        let record = recorded(Some(predicate), vec![1.0], &[0.25, 0.75]);
+        assert!(check(&record, vec![0.3, 0.7]).unwrap().1);
+        assert!(!check(&record, vec![0.7, 0.3]).unwrap().1);
+    }
+}
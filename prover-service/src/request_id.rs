@@ -0,0 +1,161 @@
+//! Request ids
+//!
+//! Every call gets an id, taken from its `X-Request-Id` header when the
+//! caller sends a usable one (1-128 characters of `[A-Za-z0-9._:-]`) and
+//! generated (`req_<hex>`) otherwise. The id is echoed in the response's
+//! `X-Request-Id` header, added as `request_id` to JSON error bodies and
+//! recorded on the `request` span every log line of the call is emitted in.
+//!
+//! Work that outlives the call carries the id along: jobs store the id of
+//! the call that submitted them and run in a span with it. Work started by
+//! the service itself (schedules, chain event watchers) gets a fresh id per
+//! run, which its webhooks carry in their `X-Request-Id` header and payload.
+
+use axum::{
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, Request},
+    http::{header::CONTENT_TYPE, request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use tracing::Instrument;
+
+/// Header carrying the request id
+pub const HEADER: &str = "x-request-id";
+
+/// Longest accepted caller-supplied request id
+const MAX_LEN: usize = 128;
+
+/// Largest error body that is rewritten to include the request id
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// Id of the call being served, as a request extension
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// A fresh id
+    pub fn generate() -> Self {
+        Self(format!("req_{}", uuid::Uuid::new_v4().simple()))
+    }
+
+    /// `value` if it is usable as a request id
+    pub fn accept(value: &str) -> Option<Self> {
+        let usable = !value.is_empty()
+            && value.len() <= MAX_LEN
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | ':' | '-'));
+        usable.then(|| Self(value.to_string()))
+    }
+
+    /// Span for work done on behalf of this request
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!("request", request_id = %self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The id assigned by `propagate`, or a fresh one outside it
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::generate))
+    }
+}
+
+/// Middleware assigning every request its id
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(RequestId::accept)
+        .unwrap_or_else(RequestId::generate);
+    let header = HeaderValue::from_str(id.as_str()).expect("request ids are valid headers");
+    request.headers_mut().insert(HEADER, header.clone());
+    request.extensions_mut().insert(id.clone());
+
+    let span = id.span();
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(HEADER, header);
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !(response.status().is_client_error() || response.status().is_server_error()) || !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to read error response of {}: {}", id, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let body = match with_request_id(&bytes, &id) {
+        Some(body) => {
+            parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+            Body::from(body)
+        }
+        None => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// `body` with `request_id` added, if it is an error object
+fn with_request_id(body: &[u8], id: &RequestId) -> Option<Vec<u8>> {
+    let mut error: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(body).ok()?;
+    if !error.contains_key("error") {
+        return None;
+    }
+    error.insert("request_id".to_string(), id.as_str().into());
+    serde_json::to_vec(&error).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_ids_are_accepted_or_generated() {
+        assert_eq!(
+            RequestId::accept("trace-1234:abc_DEF.9"),
+            Some(RequestId("trace-1234:abc_DEF.9".to_string()))
+        );
+        assert_eq!(RequestId::accept(""), None);
+        assert_eq!(RequestId::accept("has space"), None);
+        assert_eq!(RequestId::accept(&"a".repeat(MAX_LEN + 1)), None);
+        assert!(RequestId::generate().as_str().starts_with("req_"));
+        assert_ne!(RequestId::generate(), RequestId::generate());
+
+        let id = RequestId("req_1".to_string());
+        let body = with_request_id(br#"{"error":"nope","code":"NOPE"}"#, &id).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "req_1");
+        assert_eq!(body["code"], "NOPE");
+        assert_eq!(with_request_id(b"[1]", &id), None);
+        assert_eq!(with_request_id(br#"{"success":true}"#, &id), None);
+    }
+}
@@ -0,0 +1,300 @@
+//! Retention of stored results
+//!
+//! Stored proofs and finished job records are kept until they are older
+//! than their TTL (`PROOF_TTL_SECS`, `JOB_TTL_SECS`; kept forever when
+//! unset). `TENANT_PROOF_TTL_SECS` and `TENANT_JOB_TTL_SECS` override the
+//! TTL per tenant (`tenant=secs`). Proving checkpoints, the service's cached
+//! intermediate results, expire after `CHECKPOINT_MAX_AGE_SECS`. A
+//! background task (every `RETENTION_INTERVAL_SECS`, disabled when unset)
+//! removes expired entries, and `GET /admin/storage` reports what each
+//! category currently holds.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::ServiceConfig;
+use crate::AppState;
+
+/// Kind of stored data
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    Proofs,
+    Jobs,
+    Checkpoints,
+    Models,
+}
+
+/// TTL for one category, with per-tenant overrides
+#[derive(Clone, Debug, Default)]
+pub struct Ttl {
+    pub default: Option<Duration>,
+    pub tenants: HashMap<String, Duration>,
+}
+
+impl Ttl {
+    fn new(default_secs: Option<u64>, tenant_entries: &[String]) -> Self {
+        Self {
+            default: default_secs.map(Duration::from_secs),
+            tenants: parse_tenant_ttls(tenant_entries),
+        }
+    }
+
+    /// TTL applying to `tenant`, if its entries expire at all
+    pub fn for_tenant(&self, tenant: &str) -> Option<Duration> {
+        self.tenants.get(tenant).copied().or(self.default)
+    }
+}
+
+/// Storage held by one category
+#[derive(Debug, Serialize)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub items: u64,
+    pub bytes: u64,
+    /// Default TTL (none when entries are kept until removed)
+    pub ttl_secs: Option<u64>,
+    /// Tenants with their own TTL
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub tenant_ttl_secs: HashMap<String, u64>,
+}
+
+/// Entries removed by one cleanup
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CleanupReport {
+    pub at: u64,
+    pub proofs_removed: usize,
+    pub jobs_removed: usize,
+    pub checkpoints_removed: usize,
+    pub errors: Vec<String>,
+}
+
+/// Storage usage by category
+#[derive(Debug, Serialize)]
+pub struct StorageUsage {
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_cleanup: Option<CleanupReport>,
+}
+
+/// Retention policy and the outcome of its last cleanup
+pub struct Retention {
+    proofs: Ttl,
+    jobs: Ttl,
+    checkpoints: Duration,
+    last_cleanup: Mutex<Option<CleanupReport>>,
+}
+
+impl Retention {
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            proofs: Ttl::new(config.proof_ttl_secs, &config.tenant_proof_ttls),
+            jobs: Ttl::new(config.job_ttl_secs, &config.tenant_job_ttls),
+            checkpoints: Duration::from_secs(config.checkpoint_max_age_secs),
+            last_cleanup: Mutex::new(None),
+        }
+    }
+
+    /// Remove expired proofs, job records and checkpoints
+    pub async fn cleanup(&self, state: &AppState) -> CleanupReport {
+        let mut report = CleanupReport {
+            at: now_secs(),
+            ..Default::default()
+        };
+        let proof_ttl = |tenant: &str| self.proofs.for_tenant(tenant);
+        match state.proofs.expire(&proof_ttl).await {
+            Ok(removed) => report.proofs_removed = removed,
+            Err(e) => report.errors.push(format!("proofs: {}", e)),
+        }
+        match state.jobs.expire(&|tenant| self.jobs.for_tenant(tenant)) {
+            Ok(removed) => report.jobs_removed = removed,
+            Err(e) => report.errors.push(format!("jobs: {}", e)),
+        }
+        let checkpoints = state.config.data_dir.join("checkpoints");
+        match crate::checkpoint::prune(&checkpoints, self.checkpoints) {
+            Ok(removed) => report.checkpoints_removed = removed,
+            Err(e) => report.errors.push(format!("checkpoints: {}", e)),
+        }
+
+        let removed = report.proofs_removed + report.jobs_removed + report.checkpoints_removed;
+        if removed > 0 {
+            tracing::info!(
+                "Retention removed {} proofs, {} jobs and {} checkpoints",
+                report.proofs_removed,
+                report.jobs_removed,
+                report.checkpoints_removed
+            );
+        }
+        *self.last_cleanup.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Current storage usage by category
+    pub async fn usage(&self, state: &AppState) -> StorageUsage {
+        let (proofs, proof_bytes) = state.proofs.usage().await.unwrap_or_default();
+        let (jobs, job_bytes) = state.jobs.usage().unwrap_or_default();
+        let (checkpoints, checkpoint_bytes) = dir_usage(&state.config.data_dir.join("checkpoints"));
+        let (models, model_bytes) = dir_usage(state.prover.read().await.model_dir());
+
+        let categories = vec![
+            category_usage(StorageCategory::Proofs, proofs, proof_bytes, &self.proofs),
+            category_usage(StorageCategory::Jobs, jobs, job_bytes, &self.jobs),
+            CategoryUsage {
+                category: StorageCategory::Checkpoints,
+                items: checkpoints,
+                bytes: checkpoint_bytes,
+                ttl_secs: Some(self.checkpoints.as_secs()),
+                tenant_ttl_secs: HashMap::new(),
+            },
+            CategoryUsage {
+                category: StorageCategory::Models,
+                items: models,
+                bytes: model_bytes,
+                ttl_secs: None,
+                tenant_ttl_secs: HashMap::new(),
+            },
+        ];
+        StorageUsage {
+            total_bytes: categories.iter().map(|c| c.bytes).sum(),
+            categories,
+            last_cleanup: self.last_cleanup.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn category_usage(category: StorageCategory, items: u64, bytes: u64, ttl: &Ttl) -> CategoryUsage {
+    CategoryUsage {
+        category,
+        items,
+        bytes,
+        ttl_secs: ttl.default.map(|ttl| ttl.as_secs()),
+        tenant_ttl_secs: ttl
+            .tenants
+            .iter()
+            .map(|(tenant, ttl)| (tenant.clone(), ttl.as_secs()))
+            .collect(),
+    }
+}
+
+/// Background task removing expired entries every `interval_secs`
+pub async fn run_forever(state: std::sync::Arc<AppState>, interval_secs: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        if !state.cluster.is_leader() {
+            continue;
+        }
+        let report = state.retention.cleanup(&state).await;
+        for error in report.errors {
+            tracing::warn!("Retention cleanup failed for {}", error);
+        }
+    }
+}
+
+/// Parse `tenant=secs` overrides, skipping malformed entries
+fn parse_tenant_ttls(entries: &[String]) -> HashMap<String, Duration> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (tenant, secs) = entry.split_once('=')?;
+            Some((
+                tenant.trim().to_string(),
+                Duration::from_secs(secs.trim().parse().ok()?),
+            ))
+        })
+        .collect()
+}
+
+/// Number of entries directly in `dir` and the total size of its files
+fn dir_usage(dir: &Path) -> (u64, u64) {
+    fn bytes(path: &Path) -> u64 {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => std::fs::read_dir(path)
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .map(|entry| bytes(&entry.path()))
+                .sum(),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        }
+    }
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .fold((0, 0), |(items, total), entry| {
+            (items + 1, total + bytes(&entry.path()))
+        })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Caller;
+    use crate::jobs::JobStore;
+    use crate::request_id::RequestId;
+    use crate::types::{ErrorResponse, ProveRequest};
+
+    #[test]
+    fn test_tenant_overrides_and_job_expiry() {
+        let ttl = Ttl::new(
+            Some(3600),
+            &["desk=0".to_string(), "archive=86400".to_string()],
+        );
+        assert_eq!(ttl.for_tenant("other"), Some(Duration::from_secs(3600)));
+        assert_eq!(ttl.for_tenant("archive"), Some(Duration::from_secs(86400)));
+        assert!(Ttl::new(None, &[]).for_tenant("other").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path(), 1).unwrap();
+        let error = ErrorResponse {
+            error: "bad".to_string(),
+            code: "PROOF_GENERATION_FAILED".to_string(),
+        };
+        for tenant in ["desk", "archive", "desk"] {
+            let caller = Caller {
+                key_id: "key_a".to_string(),
+                tenant: tenant.to_string(),
+                scopes: None,
+            };
+            let job = store
+                .submit(
+                    &caller,
+                    &RequestId::generate(),
+                    None,
+                    &ProveRequest::default(),
+                )
+                .unwrap();
+            store.complete(&job.id, Err(&error)).unwrap();
+        }
+        store
+            .submit(
+                &Caller {
+                    key_id: "key_a".to_string(),
+                    tenant: "desk".to_string(),
+                    scopes: None,
+                },
+                &RequestId::generate(),
+                None,
+                &ProveRequest::default(),
+            )
+            .unwrap();
+        assert_eq!(store.usage().unwrap().0, 4);
+
+        // Only finished jobs of the tenant with a zero TTL are removed
+        assert_eq!(store.expire(&|t| ttl.for_tenant(t)).unwrap(), 2);
+        assert_eq!(store.usage().unwrap().0, 2);
+    }
+}
@@ -0,0 +1,153 @@
+//! WASM-sandboxed model execution
+//!
+//! Models registered with `"execution": "wasm"` never reach the native ONNX
+//! runtime. Their inference runs in a WebAssembly inference engine (e.g.
+//! tract compiled to `wasm32-unknown-unknown`, configured with
+//! `WASM_ENGINE_PATH`) inside wasmtime, metered with `WASM_FUEL` units of
+//! fuel and capped at `WASM_MEMORY_MB` of linear memory. The engine gets no
+//! host imports, so a malicious model can at worst exhaust its own limits.
+//! Requires the `wasm-sandbox` feature.
+//!
+//! Engine ABI (all pointers are offsets into the exported `memory`):
+//!
+//! - `alloc(len: i32) -> i32` reserves `len` bytes;
+//! - `infer(model_ptr, model_len, input_ptr, input_count: i32) -> i64` runs
+//!   the ONNX model on `input_count` little-endian f32 features and returns
+//!   `output_ptr << 32 | output_count`, or a negative error code.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// How a model's inference is executed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelExecution {
+    /// Native ONNX runtime (or mock inference)
+    #[default]
+    Native,
+    /// WASM inference engine under wasmtime
+    Wasm,
+}
+
+/// WASM engine and the limits each inference runs under
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmSandbox {
+    pub engine_path: PathBuf,
+    pub fuel: u64,
+    pub max_memory_bytes: usize,
+}
+
+impl WasmSandbox {
+    /// Whether this build can run sandboxed models
+    pub const AVAILABLE: bool = cfg!(feature = "wasm-sandbox");
+
+    /// Run `model` on `inputs` inside the sandbox
+    #[cfg_attr(not(feature = "wasm-sandbox"), allow(unused_variables))]
+    pub fn infer(&self, model: &[u8], inputs: &[f32]) -> Result<Vec<f32>> {
+        #[cfg(feature = "wasm-sandbox")]
+        {
+            return engine::infer(self, model, inputs);
+        }
+
+        #[allow(unreachable_code)]
+        Err(anyhow!("Built without the wasm-sandbox feature"))
+    }
+}
+
+/// Split a packed `output_ptr << 32 | output_count` result
+#[cfg_attr(not(feature = "wasm-sandbox"), allow(dead_code))]
+fn unpack_output(packed: i64) -> Result<(usize, usize)> {
+    if packed < 0 {
+        return Err(anyhow!("Sandboxed inference failed with code {}", packed));
+    }
+    Ok(((packed >> 32) as usize, (packed & 0xffff_ffff) as usize))
+}
+
+#[cfg(feature = "wasm-sandbox")]
+mod engine {
+    use super::*;
+    use std::sync::{Mutex, OnceLock};
+    use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    /// Compiled engine module, compiled once per process
+    static COMPILED: OnceLock<Mutex<Option<(PathBuf, Engine, Module)>>> = OnceLock::new();
+
+    fn compiled(path: &PathBuf) -> Result<(Engine, Module)> {
+        let mut cache = COMPILED.get_or_init(Default::default).lock().unwrap();
+        if let Some((cached_path, engine, module)) = cache.as_ref() {
+            if cached_path == path {
+                return Ok((engine.clone(), module.clone()));
+            }
+        }
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)?;
+        *cache = Some((path.clone(), engine.clone(), module.clone()));
+        Ok((engine, module))
+    }
+
+    pub fn infer(sandbox: &WasmSandbox, model: &[u8], inputs: &[f32]) -> Result<Vec<f32>> {
+        let (engine, module) = compiled(&sandbox.engine_path)?;
+        let limits: StoreLimits = StoreLimitsBuilder::new()
+            .memory_size(sandbox.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(sandbox.fuel)?;
+
+        // No host functions: an engine with imports fails to instantiate
+        let instance = Linker::new(&engine).instantiate(&mut store, &module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("WASM engine exports no memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let run = instance.get_typed_func::<(i32, i32, i32, i32), i64>(&mut store, "infer")?;
+
+        let input_bytes: Vec<u8> = inputs.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let model_ptr = alloc.call(&mut store, model.len() as i32)?;
+        memory.write(&mut store, model_ptr as usize, model)?;
+        let input_ptr = alloc.call(&mut store, input_bytes.len() as i32)?;
+        memory.write(&mut store, input_ptr as usize, &input_bytes)?;
+
+        let packed = run
+            .call(
+                &mut store,
+                (
+                    model_ptr,
+                    model.len() as i32,
+                    input_ptr,
+                    inputs.len() as i32,
+                ),
+            )
+            .map_err(|e| match store.get_fuel() {
+                Ok(0) => anyhow!("Sandboxed inference ran out of fuel"),
+                _ => anyhow!("Sandboxed inference trapped: {}", e),
+            })?;
+        let (output_ptr, output_count) = unpack_output(packed)?;
+
+        let mut output = vec![0u8; output_count * 4];
+        memory.read(&store, output_ptr, &mut output)?;
+        Ok(output
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_unpacking_and_execution_modes() {
+        assert_eq!(unpack_output((1024 << 32) | 2).unwrap(), (1024, 2));
+        assert!(unpack_output(-3).is_err());
+        assert_eq!(
+            serde_json::from_str::<ModelExecution>("\"wasm\"").unwrap(),
+            ModelExecution::Wasm
+        );
+    }
+}
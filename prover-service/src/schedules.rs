@@ -0,0 +1,384 @@
+//! Scheduled recurring proofs
+//!
+//! `POST /schedules` registers a proof request to run on a cron expression
+//! (standard five fields: minute, hour, day of month, month, day of week;
+//! UTC). The request is a regular `ProveRequest`, so inputs can be static or
+//! come from an adapter such as `market_data`. Each run is proven on behalf
+//! of the key that created the schedule, stored in the proof store and, when
+//! `webhook_url` is set, announced as a `proof.completed` or `proof.failed`
+//! webhook signed with `webhook_secret` (generated when not supplied and
+//! returned with the schedule).
+//!
+//! Schedules are persisted to `schedules.json` in the data directory.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::Instrument;
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::persist::{load_json, save_json};
+use crate::request_id::RequestId;
+use crate::types::{api_error, ApiError, ProveRequest};
+use crate::webhooks::WebhookEndpoint;
+use crate::AppState;
+
+/// How often the runner checks for due schedules
+const TICK: Duration = Duration::from_secs(15);
+
+/// A recurring proof job
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    /// Five-field cron expression (UTC)
+    pub cron: String,
+    pub request: ProveRequest,
+    pub webhook: Option<WebhookEndpoint>,
+    /// Key id and tenant the proofs run as
+    pub key_id: String,
+    pub tenant: String,
+    pub created_at: u64,
+    pub next_run_at: Option<u64>,
+    pub last_run_at: Option<u64>,
+    pub last_proof_id: Option<String>,
+    pub last_error: Option<String>,
+}
+
+impl Schedule {
+    fn caller(&self) -> Caller {
+        Caller {
+            key_id: self.key_id.clone(),
+            tenant: self.tenant.clone(),
+            scopes: None,
+        }
+    }
+}
+
+/// Parse a five-field cron expression
+fn parse_cron(expression: &str) -> Result<cron::Schedule> {
+    if expression.split_whitespace().count() != 5 {
+        return Err(anyhow!(
+            "Cron expression must have five fields (minute hour day month weekday)"
+        ));
+    }
+    // The cron crate expects a leading seconds field
+    cron::Schedule::from_str(&format!("0 {}", expression))
+        .map_err(|e| anyhow!("Invalid cron expression: {}", e))
+}
+
+/// Next run strictly after `after` (unix seconds)
+fn next_run(expression: &str, after: u64) -> Option<u64> {
+    let after: DateTime<Utc> = Utc.timestamp_opt(after as i64, 0).single()?;
+    parse_cron(expression)
+        .ok()?
+        .after(&after)
+        .next()
+        .map(|t| t.timestamp() as u64)
+}
+
+/// Persistent set of schedules
+pub struct ScheduleStore {
+    path: PathBuf,
+    schedules: Mutex<BTreeMap<String, Schedule>>,
+}
+
+impl ScheduleStore {
+    pub fn open(data_dir: &std::path::Path) -> Result<Self> {
+        let path = data_dir.join("schedules.json");
+        let schedules = load_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            path,
+            schedules: Mutex::new(schedules),
+        })
+    }
+
+    fn insert(&self, schedule: Schedule) -> Result<()> {
+        let mut schedules = self.schedules.lock().unwrap();
+        schedules.insert(schedule.id.clone(), schedule);
+        save_json(&self.path, &*schedules)
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<Schedule>> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let removed = schedules.remove(id);
+        save_json(&self.path, &*schedules)?;
+        Ok(removed)
+    }
+
+    fn get(&self, id: &str) -> Option<Schedule> {
+        self.schedules.lock().unwrap().get(id).cloned()
+    }
+
+    fn list_for(&self, key_id: &str) -> Vec<Schedule> {
+        let schedules = self.schedules.lock().unwrap();
+        schedules
+            .values()
+            .filter(|s| s.key_id == key_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Claim schedules due at `now`, advancing their next run
+    fn take_due(&self, now: u64) -> Result<Vec<Schedule>> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let mut due = Vec::new();
+        for schedule in schedules.values_mut() {
+            if schedule.next_run_at.is_some_and(|t| t <= now) {
+                due.push(schedule.clone());
+                schedule.last_run_at = Some(now);
+                schedule.next_run_at = next_run(&schedule.cron, now);
+            }
+        }
+        if !due.is_empty() {
+            save_json(&self.path, &*schedules)?;
+        }
+        Ok(due)
+    }
+
+    /// Record the outcome of a run
+    fn record(&self, id: &str, outcome: Result<String, String>) -> Result<()> {
+        let mut schedules = self.schedules.lock().unwrap();
+        if let Some(schedule) = schedules.get_mut(id) {
+            match outcome {
+                Ok(proof_id) => {
+                    schedule.last_proof_id = Some(proof_id);
+                    schedule.last_error = None;
+                }
+                Err(error) => schedule.last_error = Some(error),
+            }
+            save_json(&self.path, &*schedules)?;
+        }
+        Ok(())
+    }
+}
+
+/// Background task running due schedules
+pub async fn run_forever(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        // Another replica runs the schedules
+        if !state.cluster.is_leader() {
+            continue;
+        }
+        let due = match state.schedules.take_due(now_secs()) {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::error!("Failed to update schedules: {}", e);
+                continue;
+            }
+        };
+        for schedule in due {
+            let request_id = RequestId::generate();
+            let span = request_id.span();
+            tokio::spawn(run_schedule(state.clone(), schedule, request_id).instrument(span));
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RunOutcome<'a> {
+    schedule_id: &'a str,
+    model_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<&'a crate::proofs::StoredProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+async fn run_schedule(state: Arc<AppState>, schedule: Schedule, request_id: RequestId) {
+    tracing::info!("Running schedule {}", schedule.id);
+    let source = format!("schedule:{}", schedule.id);
+    let caller = schedule.caller();
+    let result = match crate::execute_proof(&state, &caller, schedule.request.clone()).await {
+        Ok((_, response)) => state
+            .proofs
+            .put(&source, &caller, response)
+            .await
+            .map_err(|e| format!("Failed to store proof: {}", e)),
+        Err((_, Json(e))) => Err(e.error),
+    };
+
+    let outcome = result.as_ref().map(|p| p.id.clone()).map_err(Clone::clone);
+    if let Err(e) = &outcome {
+        tracing::warn!("Schedule {} failed: {}", schedule.id, e);
+    }
+    if let Err(e) = state.schedules.record(&schedule.id, outcome) {
+        tracing::error!("Failed to record run of schedule {}: {}", schedule.id, e);
+    }
+
+    if let Some(endpoint) = &schedule.webhook {
+        let (event, body) = match &result {
+            Ok(proof) => (
+                "proof.completed",
+                RunOutcome {
+                    schedule_id: &schedule.id,
+                    model_id: &schedule.request.model_id,
+                    proof: Some(proof),
+                    error: None,
+                },
+            ),
+            Err(e) => (
+                "proof.failed",
+                RunOutcome {
+                    schedule_id: &schedule.id,
+                    model_id: &schedule.request.model_id,
+                    proof: None,
+                    error: Some(e.clone()),
+                },
+            ),
+        };
+        state.webhooks.send(endpoint, event, &request_id, body);
+    }
+}
+
+/// Routes mounted under `/schedules`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_schedules).post(create_schedule))
+        .route("/:id", get(get_schedule).delete(delete_schedule))
+}
+
+#[derive(Deserialize)]
+struct CreateScheduleRequest {
+    cron: String,
+    request: ProveRequest,
+    #[serde(default)]
+    webhook_url: Option<String>,
+    #[serde(default)]
+    webhook_secret: Option<String>,
+}
+
+/// Register a recurring proof job
+async fn create_schedule(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Json(body): Json<CreateScheduleRequest>,
+) -> Result<Json<Schedule>, ApiError> {
+    caller.require(Scope::Prove)?;
+    parse_cron(&body.cron)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_SCHEDULE", e.to_string()))?;
+    if body.request.commitment_id.is_some() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_SCHEDULE",
+            "Input commitments are single-use and cannot be scheduled",
+        ));
+    }
+    let webhook = body
+        .webhook_url
+        .map(|url| WebhookEndpoint::new(&url, body.webhook_secret))
+        .transpose()
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "INVALID_SCHEDULE", e))?;
+
+    let now = now_secs();
+    let schedule = Schedule {
+        id: format!("sch_{}", uuid::Uuid::new_v4().simple()),
+        next_run_at: next_run(&body.cron, now),
+        cron: body.cron,
+        request: body.request,
+        webhook,
+        key_id: caller.key_id.clone(),
+        tenant: caller.tenant.clone(),
+        created_at: now,
+        last_run_at: None,
+        last_proof_id: None,
+        last_error: None,
+    };
+    state
+        .schedules
+        .insert(schedule.clone())
+        .map_err(internal_error)?;
+    tracing::info!(
+        "Created schedule {} ({}) for {}",
+        schedule.id,
+        schedule.cron,
+        caller.key_id
+    );
+    Ok(Json(schedule))
+}
+
+/// Schedules created by the calling key
+async fn list_schedules(State(state): State<Arc<AppState>>, caller: Caller) -> Json<Vec<Schedule>> {
+    Json(state.schedules.list_for(&caller.key_id))
+}
+
+async fn get_schedule(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> Result<Json<Schedule>, ApiError> {
+    state
+        .schedules
+        .get(&id)
+        .filter(|s| s.key_id == caller.key_id)
+        .map(Json)
+        .ok_or_else(|| schedule_not_found(&id))
+}
+
+async fn delete_schedule(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state
+        .schedules
+        .get(&id)
+        .filter(|s| s.key_id == caller.key_id)
+        .is_none()
+    {
+        return Err(schedule_not_found(&id));
+    }
+    state.schedules.remove(&id).map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn schedule_not_found(id: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "SCHEDULE_NOT_FOUND",
+        format!("No schedule {}", id),
+    )
+}
+
+fn internal_error(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "SCHEDULE_UPDATE_FAILED",
+        e.to_string(),
+    )
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_five_field_cron_expressions() {
+        // 2023-11-14T22:13:20Z
+        let now = 1_700_000_000;
+        assert_eq!(next_run("0 * * * *", now), Some(1_700_002_800));
+        assert_eq!(next_run("*/15 * * * *", now), Some(1_700_000_100));
+        assert!(parse_cron("0 * * * * *").is_err());
+        assert!(parse_cron("61 * * * *").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Secret management
+//!
+//! Secrets (admin token, service signing keys, and credentials for later
+//! integrations) are resolved by logical name through `Secrets`. Without a
+//! vault configured, names map to environment variables (`admin_token` ->
+//! `ADMIN_TOKEN`) and signing keys stay in the data directory.
+//!
+//! When `VAULT_ADDR` is set, secrets are read from and written to a HashiCorp
+//! Vault KV v2 engine at `<mount>/data/<prefix>/<name>` (field `value`), so
+//! nothing long-lived touches local disk or the process environment. The
+//! service authenticates with a token file, Kubernetes service-account login
+//! or AppRole, and a background task renews the token before its lease runs
+//! out, logging in again if renewal fails.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// How the service authenticates to Vault
+#[derive(Clone, Debug)]
+pub enum VaultAuth {
+    /// Static token read from a file (or `VAULT_TOKEN` for development)
+    Token { token_file: Option<PathBuf> },
+    /// Kubernetes service-account JWT login
+    Kubernetes { role: String, jwt_path: PathBuf },
+    /// AppRole login with the secret id read from a file
+    AppRole {
+        role_id: String,
+        secret_id_file: PathBuf,
+    },
+}
+
+/// Vault connection settings
+#[derive(Clone, Debug)]
+pub struct VaultConfig {
+    pub addr: String,
+    pub mount: String,
+    pub prefix: String,
+    pub auth: VaultAuth,
+}
+
+impl VaultConfig {
+    /// Read Vault settings from the environment (`None` when `VAULT_ADDR` is unset)
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let addr = var("VAULT_ADDR")?;
+
+        let auth = match var("VAULT_AUTH_METHOD").as_deref() {
+            Some("kubernetes") => VaultAuth::Kubernetes {
+                role: var("VAULT_ROLE").unwrap_or_else(|| "prover-service".to_string()),
+                jwt_path: var("VAULT_K8S_JWT_PATH")
+                    .unwrap_or_else(|| {
+                        "/var/run/secrets/kubernetes.io/serviceaccount/token".to_string()
+                    })
+                    .into(),
+            },
+            Some("approle") => VaultAuth::AppRole {
+                role_id: var("VAULT_ROLE_ID").unwrap_or_default(),
+                secret_id_file: var("VAULT_SECRET_ID_FILE").unwrap_or_default().into(),
+            },
+            _ => VaultAuth::Token {
+                token_file: var("VAULT_TOKEN_FILE").map(PathBuf::from),
+            },
+        };
+
+        Some(Self {
+            addr: addr.trim_end_matches('/').to_string(),
+            mount: var("VAULT_KV_MOUNT").unwrap_or_else(|| "secret".to_string()),
+            prefix: var("VAULT_SECRET_PREFIX").unwrap_or_else(|| "prover-service".to_string()),
+            auth,
+        })
+    }
+}
+
+struct VaultClient {
+    config: VaultConfig,
+    http: reqwest::Client,
+    token: RwLock<String>,
+}
+
+impl VaultClient {
+    async fn connect(config: VaultConfig) -> Result<Self> {
+        let client = Self {
+            config,
+            http: reqwest::Client::new(),
+            token: RwLock::new(String::new()),
+        };
+        client.login().await?;
+        Ok(client)
+    }
+
+    /// Obtain a fresh token, returning its lease duration
+    async fn login(&self) -> Result<Duration> {
+        let (token, lease) = match &self.config.auth {
+            VaultAuth::Token { token_file } => {
+                let token = match token_file {
+                    Some(path) => std::fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?,
+                    None => std::env::var("VAULT_TOKEN")
+                        .map_err(|_| anyhow!("VAULT_TOKEN or VAULT_TOKEN_FILE required"))?,
+                };
+                (token.trim().to_string(), None)
+            }
+            VaultAuth::Kubernetes { role, jwt_path } => {
+                let jwt = std::fs::read_to_string(jwt_path)
+                    .with_context(|| format!("Failed to read {}", jwt_path.display()))?;
+                self.auth_login("kubernetes", json!({ "role": role, "jwt": jwt.trim() }))
+                    .await?
+            }
+            VaultAuth::AppRole {
+                role_id,
+                secret_id_file,
+            } => {
+                let secret_id = std::fs::read_to_string(secret_id_file)
+                    .with_context(|| format!("Failed to read {}", secret_id_file.display()))?;
+                self.auth_login(
+                    "approle",
+                    json!({ "role_id": role_id, "secret_id": secret_id.trim() }),
+                )
+                .await?
+            }
+        };
+
+        *self.token.write().await = token;
+        match lease {
+            Some(lease) => Ok(lease),
+            // Static tokens may be non-renewable (e.g. root tokens); check again later
+            None => Ok(self.renew().await.unwrap_or(Duration::from_secs(3600))),
+        }
+    }
+
+    async fn auth_login(&self, method: &str, body: Value) -> Result<(String, Option<Duration>)> {
+        let url = format!("{}/v1/auth/{}/login", self.config.addr, method);
+        let response: Value = self
+            .http
+            .post(&url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let auth = &response["auth"];
+        let token = auth["client_token"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Vault {} login returned no token", method))?;
+        let lease = auth["lease_duration"].as_u64().map(Duration::from_secs);
+        Ok((token.to_string(), lease))
+    }
+
+    /// Renew the current token, returning the new lease duration
+    async fn renew(&self) -> Result<Duration> {
+        let url = format!("{}/v1/auth/token/renew-self", self.config.addr);
+        let response: Value = self
+            .http
+            .post(&url)
+            .header("X-Vault-Token", self.token.read().await.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let lease = response["auth"]["lease_duration"].as_u64().unwrap_or(0);
+        Ok(Duration::from_secs(lease))
+    }
+
+    fn secret_url(&self, name: &str) -> String {
+        format!(
+            "{}/v1/{}/data/{}/{}",
+            self.config.addr, self.config.mount, self.config.prefix, name
+        )
+    }
+
+    async fn read(&self, name: &str) -> Result<Option<String>> {
+        let response = self
+            .http
+            .get(self.secret_url(name))
+            .header("X-Vault-Token", self.token.read().await.as_str())
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body: Value = response.error_for_status()?.json().await?;
+        Ok(body["data"]["data"]["value"].as_str().map(String::from))
+    }
+
+    async fn write(&self, name: &str, value: &str) -> Result<()> {
+        self.http
+            .post(self.secret_url(name))
+            .header("X-Vault-Token", self.token.read().await.as_str())
+            .json(&json!({ "data": { "value": value } }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Resolves secrets by logical name
+pub struct Secrets {
+    vault: Option<VaultClient>,
+}
+
+impl Secrets {
+    /// Connect to Vault if configured, otherwise fall back to the environment
+    pub async fn connect(vault: Option<VaultConfig>) -> Result<Self> {
+        let vault = match vault {
+            Some(config) => {
+                tracing::info!("Loading secrets from Vault at {}", config.addr);
+                Some(VaultClient::connect(config).await?)
+            }
+            None => None,
+        };
+        Ok(Self { vault })
+    }
+
+    /// Whether secrets live in an external store rather than env/disk
+    pub fn is_external(&self) -> bool {
+        self.vault.is_some()
+    }
+
+    /// Read a secret
+    pub async fn get(&self, name: &str) -> Result<Option<String>> {
+        match &self.vault {
+            Some(vault) => vault.read(name).await,
+            None => Ok(std::env::var(name.to_ascii_uppercase())
+                .ok()
+                .filter(|v| !v.is_empty())),
+        }
+    }
+
+    /// Write a secret (external stores only)
+    pub async fn put(&self, name: &str, value: &str) -> Result<()> {
+        match &self.vault {
+            Some(vault) => vault.write(name, value).await,
+            None => Err(anyhow!("No external secret store configured")),
+        }
+    }
+
+    /// Keep the Vault token alive for the lifetime of the process
+    pub async fn renew_forever(self: std::sync::Arc<Self>) {
+        let Some(vault) = &self.vault else {
+            return;
+        };
+        loop {
+            let lease = match vault.renew().await {
+                Ok(lease) => lease,
+                Err(e) => {
+                    tracing::warn!("Vault token renewal failed ({}), logging in again", e);
+                    vault.login().await.unwrap_or_else(|e| {
+                        tracing::error!("Vault login failed: {}", e);
+                        Duration::from_secs(60)
+                    })
+                }
+            };
+            let wait = (lease / 2).clamp(Duration::from_secs(30), Duration::from_secs(3600));
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Startup self-test
+//!
+//! On boot the service proves and verifies a small bundled model (the
+//! authorization classifier) end to end, through the same isolation, backend
+//! and verification as user models. `GET /ready` answers 503 until that
+//! round trip has passed, so a broken ONNX runtime install, a missing GPU
+//! driver or a misconfigured backend keeps the instance out of rotation
+//! before any traffic reaches it. `SELF_TEST=false` skips the check.
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::AppState;
+
+/// The bundled self-test model
+const MODEL: &[u8] = include_bytes!("../jolt-atlas/models/authorization/network.onnx");
+
+/// Features the self-test model takes
+const FEATURES: usize = 64;
+
+/// Outcome of the startup self-test
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    /// Still proving
+    Pending,
+    /// Disabled by configuration
+    Skipped,
+    Passed {
+        duration_ms: u64,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl SelfTestStatus {
+    /// Whether the instance may take traffic
+    pub fn is_ready(&self) -> bool {
+        matches!(
+            self,
+            SelfTestStatus::Passed { .. } | SelfTestStatus::Skipped
+        )
+    }
+}
+
+/// The self-test's current status
+pub struct SelfTest {
+    status: Mutex<SelfTestStatus>,
+}
+
+impl SelfTest {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            status: Mutex::new(if enabled {
+                SelfTestStatus::Pending
+            } else {
+                SelfTestStatus::Skipped
+            }),
+        }
+    }
+
+    pub fn status(&self) -> SelfTestStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn set(&self, status: SelfTestStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+/// Inputs the self-test model is proven over
+fn inputs() -> Vec<f32> {
+    (0..FEATURES)
+        .map(|i| if i % 16 == 0 { 1.0 } else { 0.0 })
+        .collect()
+}
+
+/// Prove and verify the bundled model, recording the outcome
+pub async fn run(state: Arc<AppState>) {
+    let started = Instant::now();
+    let dir = state.config.data_dir.join("self-test");
+    let model_path = dir.join("network.onnx");
+    let result =
+        match std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&model_path, MODEL)) {
+            Ok(()) => {
+                state
+                    .prover
+                    .read()
+                    .await
+                    .round_trip(&model_path, &inputs())
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+    let status = match result {
+        Ok(()) => {
+            let duration_ms = started.elapsed().as_millis() as u64;
+            tracing::info!("Startup self-test passed in {}ms", duration_ms);
+            SelfTestStatus::Passed { duration_ms }
+        }
+        Err(e) => {
+            tracing::error!("Startup self-test failed; reporting not ready: {}", e);
+            SelfTestStatus::Failed {
+                error: e.to_string(),
+            }
+        }
+    };
+    state.self_test.set(status);
+}
+
+/// Readiness probe: 200 once the self-test has passed, 503 until then
+pub async fn ready(State(state): State<Arc<AppState>>) -> (StatusCode, Json<SelfTestStatus>) {
+    let status = state.self_test.status();
+    let code = if status.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ready_only_after_passing_or_when_skipped() {
+        let self_test = SelfTest::new(true);
+        assert!(!self_test.status().is_ready());
+
+        self_test.set(SelfTestStatus::Failed {
+            error: "ORT not found".to_string(),
+        });
+        assert!(!self_test.status().is_ready());
+        assert_eq!(
+            serde_json::to_value(self_test.status()).unwrap()["status"],
+            "failed"
+        );
+
+        self_test.set(SelfTestStatus::Passed { duration_ms: 12 });
+        assert!(self_test.status().is_ready());
+        assert!(SelfTest::new(false).status().is_ready());
+        assert_eq!(inputs().len(), FEATURES);
+    }
+}
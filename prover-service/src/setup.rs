@@ -0,0 +1,235 @@
+//! Ahead-of-time proving setup
+//!
+//! `POST /models/:id/setup` starts the backend's per-model setup (proving
+//! preprocessing) as a background job and returns at once; its progress is
+//! read from `GET /models/:id/setup`. Operators run it off-peak after
+//! registering a model, so the first user request does not pay for setup.
+//! Setup runs once per model: starting it again returns the run already
+//! recorded unless that run failed.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use crate::api_keys::Scope;
+use crate::auth::Caller;
+use crate::jobs::JobStatus;
+use crate::jolt_atlas::{ModelCommitments, ZkmlProver};
+use crate::types::{api_error, ApiError};
+use crate::AppState;
+
+/// Setup of one model, as reported to operators
+#[derive(Clone, Serialize)]
+pub struct SetupRun {
+    pub model_id: String,
+    pub status: JobStatus,
+    /// Backend the setup was run for
+    pub prover: String,
+    pub requested_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setup_time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A model's setup, detached from the registry so it runs without holding it
+pub struct SetupJob {
+    pub model: ModelCommitments,
+    pub prover: Arc<RwLock<Box<dyn ZkmlProver>>>,
+}
+
+impl SetupJob {
+    /// Run the backend's preprocessing on a blocking thread
+    pub async fn run(self) -> Result<()> {
+        tokio::task::spawn_blocking(move || self.prover.blocking_read().preprocess(&self.model))
+            .await?
+    }
+}
+
+/// Setup runs by model id
+#[derive(Default)]
+pub struct SetupTracker {
+    runs: Mutex<HashMap<String, SetupRun>>,
+}
+
+impl SetupTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, model_id: &str) -> Option<SetupRun> {
+        self.runs.lock().unwrap().get(model_id).cloned()
+    }
+
+    /// Record a new queued run, or return the existing one if it has not
+    /// failed (`false` when nothing new was queued)
+    fn queue(&self, model_id: &str, prover: &str) -> (SetupRun, bool) {
+        let mut runs = self.runs.lock().unwrap();
+        if let Some(run) = runs.get(model_id) {
+            if run.status != JobStatus::Failed {
+                return (run.clone(), false);
+            }
+        }
+        let run = SetupRun {
+            model_id: model_id.to_string(),
+            status: JobStatus::Queued,
+            prover: prover.to_string(),
+            requested_at: now_secs(),
+            started_at: None,
+            finished_at: None,
+            setup_time_ms: None,
+            error: None,
+        };
+        runs.insert(model_id.to_string(), run.clone());
+        (run, true)
+    }
+
+    fn update(&self, model_id: &str, f: impl FnOnce(&mut SetupRun)) {
+        if let Some(run) = self.runs.lock().unwrap().get_mut(model_id) {
+            f(run);
+        }
+    }
+
+    /// Forget the setup of a purged model
+    pub fn remove(&self, model_id: &str) {
+        self.runs.lock().unwrap().remove(model_id);
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn model_not_found(model_id: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "MODEL_NOT_FOUND",
+        format!("Model not found: {}", model_id),
+    )
+}
+
+/// Start a model's setup in the background
+pub async fn start_setup(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(model_id): Path<String>,
+) -> Result<(StatusCode, Json<SetupRun>), ApiError> {
+    caller.require(Scope::Register)?;
+    let (job, prover_id) = {
+        let prover = state.prover.read().await;
+        let job = prover
+            .setup_job(&model_id)
+            .ok_or_else(|| model_not_found(&model_id))?;
+        (job, prover.get_prover_info().await)
+    };
+
+    let (run, queued) = state.setups.queue(&model_id, &prover_id);
+    if !queued {
+        return Ok((StatusCode::OK, Json(run)));
+    }
+    tracing::info!("Queued proving setup of model {}", model_id);
+    tokio::spawn(run_setup(state.clone(), model_id, job));
+    Ok((StatusCode::ACCEPTED, Json(run)))
+}
+
+/// Run a model's setup now unless it was already started, recording it as
+/// if started through `POST /models/:id/setup`
+pub async fn ensure_setup(state: &Arc<AppState>, model_id: &str) -> Result<()> {
+    let (job, prover_id) = {
+        let prover = state.prover.read().await;
+        let job = prover
+            .setup_job(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?;
+        (job, prover.get_prover_info().await)
+    };
+    if !state.setups.queue(model_id, &prover_id).1 {
+        return Ok(());
+    }
+    run_setup(state.clone(), model_id.to_string(), job).await;
+    match state.setups.get(model_id) {
+        Some(run) if run.status == JobStatus::Failed => Err(anyhow!(
+            "Proving setup failed: {}",
+            run.error.unwrap_or_default()
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn run_setup(state: Arc<AppState>, model_id: String, job: SetupJob) {
+    state.setups.update(&model_id, |run| {
+        run.status = JobStatus::Running;
+        run.started_at = Some(now_secs());
+    });
+    let started = Instant::now();
+    let result = job.run().await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(()) => tracing::info!("Proving setup of model {} took {}ms", model_id, elapsed_ms),
+        Err(e) => tracing::error!("Proving setup of model {} failed: {}", model_id, e),
+    }
+    state.setups.update(&model_id, |run| {
+        run.finished_at = Some(now_secs());
+        run.setup_time_ms = Some(elapsed_ms);
+        match result {
+            Ok(()) => run.status = JobStatus::Succeeded,
+            Err(e) => {
+                run.status = JobStatus::Failed;
+                run.error = Some(e.to_string());
+            }
+        }
+    });
+}
+
+/// Status of a model's setup
+pub async fn get_setup(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+    Path(model_id): Path<String>,
+) -> Result<Json<SetupRun>, ApiError> {
+    caller.require(Scope::Register)?;
+    state.setups.get(&model_id).map(Json).ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            "SETUP_NOT_FOUND",
+            format!("No setup has been started for model {}", model_id),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_is_queued_once_unless_it_failed() {
+        let tracker = SetupTracker::new();
+        let (run, queued) = tracker.queue("m", "mock");
+        assert!(queued);
+        assert_eq!(run.status, JobStatus::Queued);
+
+        tracker.update("m", |run| run.status = JobStatus::Succeeded);
+        let (run, queued) = tracker.queue("m", "mock");
+        assert!(!queued);
+        assert_eq!(run.status, JobStatus::Succeeded);
+
+        tracker.update("m", |run| run.status = JobStatus::Failed);
+        assert!(tracker.queue("m", "mock").1);
+
+        tracker.remove("m");
+        assert!(tracker.get("m").is_none());
+    }
+}
@@ -0,0 +1,339 @@
+//! Shadow proving
+//!
+//! Before a new backend version becomes the default it can be shadowed: with
+//! `SHADOW_BACKEND` set (`mock` or the path of a Jolt Atlas binary, as for
+//! `AB_BACKEND`), a `SHADOW_FRACTION` share of requests (adjustable with
+//! `PUT /admin/shadow`, `{"fraction": 0.05}`) is proven again on it in the
+//! background once the client's proof is done. Shadow proofs are never
+//! returned, stored or logged: each is verified with the shadow backend and
+//! its public inputs are compared with the client's proof's, except for the
+//! fields that legitimately differ between backends. `GET /admin/shadow`
+//! reports how many matched, which fields diverged and the latest
+//! divergences.
+//!
+//! At most `MAX_IN_FLIGHT` shadow proofs run at once; samples drawn while
+//! they are all busy are counted as skipped rather than queued, so shadowing
+//! never holds up client work. Metrics restart with the service.
+
+use anyhow::Result;
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::auth::AdminAuth;
+use crate::overview::Recent;
+use crate::prover::JoltAtlasProver;
+use crate::types::{api_error, ApiError, ProofResult, ProveRequest, PublicInputs};
+use crate::AppState;
+
+/// Most shadow proofs running at once
+const MAX_IN_FLIGHT: usize = 2;
+
+/// Public input fields not compared: they are per backend (`timestamp`,
+/// `circuit_hash`) or set by the service rather than the backend
+/// (`consensus`)
+const UNCOMPARED_FIELDS: [&str; 3] = ["timestamp", "circuit_hash", "consensus"];
+
+/// Counters of shadow proving since the service started
+#[derive(Clone, Default, Serialize)]
+pub struct ShadowMetrics {
+    /// Requests sent to the shadow backend
+    pub sampled: u64,
+    /// Requests drawn while every shadow slot was busy
+    pub skipped: u64,
+    pub matched: u64,
+    pub diverged: u64,
+    /// Shadow proofs that failed to generate
+    pub failures: u64,
+    /// Shadow proofs the shadow backend did not verify
+    pub verification_failures: u64,
+    /// Public input field -> shadow proofs in which it diverged
+    pub divergent_fields: BTreeMap<String, u64>,
+    pub total_proving_ms: u64,
+}
+
+/// A shadow proof that did not match the client's
+#[derive(Clone, Serialize)]
+pub struct ShadowDivergence {
+    pub at: u64,
+    pub model_id: String,
+    /// Public input fields that differ
+    pub fields: Vec<String>,
+    /// Why there is no shadow proof to compare, if there is none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Shadow proving configuration and metrics, as reported
+#[derive(Serialize)]
+pub struct ShadowReport {
+    /// Prover id of the shadow backend, if one is configured
+    pub backend: Option<String>,
+    pub fraction: f64,
+    #[serde(flatten)]
+    pub metrics: ShadowMetrics,
+    pub mean_proving_ms: Option<f64>,
+    pub recent_divergences: Vec<ShadowDivergence>,
+}
+
+/// Outcomes of shadow proofs, shared with their tasks
+#[derive(Default)]
+struct Outcomes {
+    metrics: Mutex<ShadowMetrics>,
+    divergences: Recent<ShadowDivergence>,
+}
+
+impl Outcomes {
+    fn record(
+        &self,
+        model_id: &str,
+        primary: &PublicInputs,
+        outcome: Result<(ProofResult, bool)>,
+        proving_ms: u64,
+    ) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.total_proving_ms += proving_ms;
+        let (fields, error) = match outcome {
+            Ok((shadow, true)) => {
+                let fields = divergent_fields(primary, &shadow.public_inputs);
+                if fields.is_empty() {
+                    metrics.matched += 1;
+                    return;
+                }
+                metrics.diverged += 1;
+                for field in &fields {
+                    *metrics.divergent_fields.entry(field.clone()).or_default() += 1;
+                }
+                (fields, None)
+            }
+            Ok((_, false)) => {
+                metrics.verification_failures += 1;
+                (Vec::new(), Some("proof does not verify".to_string()))
+            }
+            Err(e) => {
+                metrics.failures += 1;
+                (Vec::new(), Some(e.to_string()))
+            }
+        };
+        drop(metrics);
+
+        tracing::warn!(
+            "Shadow proof of model {} diverged: {}",
+            model_id,
+            error.clone().unwrap_or_else(|| fields.join(", "))
+        );
+        self.divergences.push(ShadowDivergence {
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            model_id: model_id.to_string(),
+            fields,
+            error,
+        });
+    }
+}
+
+/// Public input fields, other than `UNCOMPARED_FIELDS`, that differ
+fn divergent_fields(primary: &PublicInputs, shadow: &PublicInputs) -> Vec<String> {
+    let (Ok(Value::Object(primary)), Ok(Value::Object(shadow))) =
+        (serde_json::to_value(primary), serde_json::to_value(shadow))
+    else {
+        return vec!["public_inputs".to_string()];
+    };
+    primary
+        .keys()
+        .chain(shadow.keys())
+        .filter(|field| !UNCOMPARED_FIELDS.contains(&field.as_str()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|field| primary.get(*field) != shadow.get(*field))
+        .cloned()
+        .collect()
+}
+
+/// Samples requests onto the shadow backend
+pub struct Shadow {
+    fraction: Mutex<f64>,
+    slots: Arc<Semaphore>,
+    outcomes: Arc<Outcomes>,
+}
+
+impl Shadow {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction: Mutex::new(fraction),
+            slots: Arc::new(Semaphore::new(MAX_IN_FLIGHT)),
+            outcomes: Arc::new(Outcomes::default()),
+        }
+    }
+
+    /// If `request` is drawn, prove it again on the shadow backend in the
+    /// background and compare the result with `primary`, its client's proof
+    pub fn sample(&self, prover: &JoltAtlasProver, request: &ProveRequest, primary: &ProofResult) {
+        let fraction = *self.fraction.lock().unwrap();
+        if fraction <= 0.0 || rand::thread_rng().gen::<f64>() >= fraction {
+            return;
+        }
+        let Ok(slot) = self.slots.clone().try_acquire_owned() else {
+            self.outcomes.metrics.lock().unwrap().skipped += 1;
+            return;
+        };
+        let task = match prover.shadow_proof(request) {
+            Ok(task) => task,
+            Err(e) => {
+                tracing::warn!("Failed to start shadow proof: {}", e);
+                return;
+            }
+        };
+        self.outcomes.metrics.lock().unwrap().sampled += 1;
+
+        let outcomes = self.outcomes.clone();
+        let model_id = request.model_id.clone();
+        let primary = primary.public_inputs.clone();
+        tokio::spawn(
+            async move {
+                let start = Instant::now();
+                let outcome = task.await;
+                drop(slot);
+                let proving_ms = start.elapsed().as_millis() as u64;
+                outcomes.record(&model_id, &primary, outcome, proving_ms);
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    pub fn report(&self, backend: Option<String>) -> ShadowReport {
+        let metrics = self.outcomes.metrics.lock().unwrap().clone();
+        let completed =
+            metrics.matched + metrics.diverged + metrics.failures + metrics.verification_failures;
+        ShadowReport {
+            backend,
+            fraction: *self.fraction.lock().unwrap(),
+            mean_proving_ms: (completed > 0)
+                .then(|| metrics.total_proving_ms as f64 / completed as f64),
+            metrics,
+            recent_divergences: self.outcomes.divergences.list(),
+        }
+    }
+
+    fn set_fraction(&self, fraction: f64) {
+        *self.fraction.lock().unwrap() = fraction;
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetShadowRequest {
+    pub fraction: f64,
+}
+
+/// Shadow backend and how its proofs compare
+pub async fn get_shadow(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<ShadowReport> {
+    let backend = state.prover.read().await.shadow_backend();
+    Json(state.shadow.report(backend))
+}
+
+/// Change the share of requests shadow-proven
+pub async fn set_shadow(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetShadowRequest>,
+) -> Result<Json<ShadowReport>, ApiError> {
+    if !(0.0..=1.0).contains(&request.fraction) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_FRACTION",
+            "fraction must be between 0 and 1",
+        ));
+    }
+    let Some(backend) = state.prover.read().await.shadow_backend() else {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            "NO_SHADOW_BACKEND",
+            "Set SHADOW_BACKEND to shadow-prove requests",
+        ));
+    };
+    state.shadow.set_fraction(request.fraction);
+    tracing::info!(
+        "Shadow-proving {:.1}% of requests on {}",
+        request.fraction * 100.0,
+        backend
+    );
+    Ok(Json(state.shadow.report(Some(backend))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public_inputs() -> PublicInputs {
+        let value = serde_json::json!({
+            "model_commitment": "0xmodel",
+            "input_hash": "0xin",
+            "output_hash": "0xout",
+            "output": [0.5],
+            "timestamp": 1,
+            "circuit_hash": "0xcircuit",
+        });
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn result(public_inputs: PublicInputs) -> ProofResult {
+        ProofResult {
+            proof: String::new(),
+            model_commitment: public_inputs.model_commitment.clone(),
+            input_hash: public_inputs.input_hash.clone(),
+            output_hash: public_inputs.output_hash.clone(),
+            public_inputs,
+            fallback: None,
+            cpu_time_ms: None,
+            timings: None,
+        }
+    }
+
+    #[test]
+    fn test_shadow_outcomes_are_compared_and_counted() {
+        let primary = public_inputs();
+        let mut same = public_inputs();
+        same.timestamp = 2;
+        same.circuit_hash = Some("0xother-circuit".to_string());
+        assert!(divergent_fields(&primary, &same).is_empty());
+
+        let mut diverging = public_inputs();
+        diverging.output_hash = "0xwrong".to_string();
+        diverging.output = vec![0.7];
+        assert_eq!(
+            divergent_fields(&primary, &diverging),
+            ["output", "output_hash"]
+        );
+
+        let shadow = Shadow::new(0.0);
+        let outcomes = &shadow.outcomes;
+        outcomes.record("m", &primary, Ok((result(same), true)), 10);
+        outcomes.record("m", &primary, Ok((result(diverging.clone()), true)), 20);
+        outcomes.record("m", &primary, Ok((result(diverging), false)), 30);
+        outcomes.record("m", &primary, Err(anyhow::anyhow!("boom")), 40);
+
+        let report = shadow.report(Some("jolt-atlas-mock-v1".to_string()));
+        assert_eq!(report.metrics.matched, 1);
+        assert_eq!(report.metrics.diverged, 1);
+        assert_eq!(report.metrics.verification_failures, 1);
+        assert_eq!(report.metrics.failures, 1);
+        assert_eq!(report.metrics.divergent_fields["output_hash"], 1);
+        assert_eq!(report.mean_proving_ms, Some(25.0));
+        assert_eq!(report.recent_divergences.len(), 3);
+        assert_eq!(report.recent_divergences[0].error.as_deref(), Some("boom"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Sharded proving
+//!
+//! For large models the witness and commitment work can be split into
+//! shards that are proven in parallel and combined into one proof. The
+//! shard count is a per-model tuning knob: set `shards` when registering a
+//! model, or change it later with `PUT /admin/models/:id/shards`. Shards
+//! run on their own threads within the proving job, so under `process`
+//! isolation they share the job's worker process and its resource limits.
+//! Backends without sharding support prove such models in one piece.
+
+use anyhow::{anyhow, Result};
+
+use crate::jolt_atlas::{JoltAtlasProof, ModelCommitments, ZkmlProver};
+
+/// Most shards a model may be split into
+pub const MAX_SHARDS: u32 = 64;
+
+/// Check a requested shard count
+pub fn validate_shards(shards: u32) -> Result<u32> {
+    if (1..=MAX_SHARDS).contains(&shards) {
+        Ok(shards)
+    } else {
+        Err(anyhow!("Shard count must be between 1 and {}", MAX_SHARDS))
+    }
+}
+
+/// Prove `count` shards in parallel and combine them
+pub fn prove_sharded(
+    prover: &dyn ZkmlProver,
+    model: &ModelCommitments,
+    inputs: &[f32],
+    outputs: &[f32],
+    challenge: Option<&str>,
+    count: u32,
+) -> Result<JoltAtlasProof> {
+    let shards = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..count)
+            .map(|index| {
+                scope.spawn(move || {
+                    prover.prove_shard(model, inputs, outputs, challenge, index, count)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().map_err(|_| anyhow!("Proof shard panicked"))?)
+            .collect::<Result<Vec<_>>>()
+    })?;
+    prover.combine_shards(model, inputs, outputs, challenge, shards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::create_prover;
+
+    #[test]
+    fn test_sharded_proofs_verify() {
+        let prover = create_prover().unwrap();
+        let model = ModelCommitments::compute(b"model");
+        let proof = prove_sharded(prover.as_ref(), &model, &[1.0, 2.0], &[0.5], None, 4).unwrap();
+        assert_eq!(proof.proof_data.commitments.len(), 2 + 4);
+        assert!(prover.verify(&proof).unwrap().valid);
+
+        // Dropping a shard's commitment breaks the proof
+        let mut truncated = proof.clone();
+        truncated.proof_data.commitments.remove(3);
+        assert!(!prover.verify(&truncated).unwrap().valid);
+        assert!(validate_shards(0).is_err());
+        assert!(validate_shards(MAX_SHARDS + 1).is_err());
+    }
+}
@@ -0,0 +1,619 @@
+//! Service signing keys
+//!
+//! The service signs proof responses (and later attestations) with Ed25519.
+//! Several keys may be live at once: one `active` key signs new material
+//! while recently rotated-out keys stay `retiring` so verifiers holding
+//! older signatures can still resolve them. Every signature carries the id
+//! of the key that produced it, and `GET /keys` advertises the public half
+//! of all non-retired keys.
+//!
+//! Keys are persisted to `signing_keys.json` in the data directory, readable
+//! by the service's user only, or to the `signing_keys` secret when an
+//! external secret store is configured, and rotated on a schedule when
+//! `SIGNING_KEY_ROTATION_SECS` is set.
+//!
+//! Signed messages are byte strings any verifier can rebuild: a domain tag
+//! and newline-separated fields, with structured fields such as public
+//! inputs in the RFC 8785 (JCS) canonical JSON form.
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::persist::{load_json, save_secret_json};
+use crate::secrets::Secrets;
+use crate::types::{PublicInputs, VerificationReceipt};
+
+/// Signature algorithm identifier
+pub const SIGNATURE_ALG: &str = "ed25519";
+
+/// Lifecycle state of a signing key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    /// Signs new material
+    Active,
+    /// No longer signs, but signatures remain verifiable until `retire_at`
+    Retiring,
+}
+
+/// Signature over service-issued material
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServiceSignature {
+    /// Id of the signing key
+    pub kid: String,
+
+    /// Signature algorithm
+    pub alg: String,
+
+    /// Signature bytes (hex)
+    pub sig: String,
+}
+
+/// Public key as advertised by `GET /keys`
+#[derive(Clone, Serialize)]
+pub struct PublicKeyInfo {
+    pub kid: String,
+    pub alg: String,
+    pub public_key: String,
+    pub status: KeyStatus,
+    pub created_at: u64,
+    pub retire_at: Option<u64>,
+}
+
+/// A key of the ring, including its secret
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredKey {
+    kid: String,
+    status: KeyStatus,
+    created_at: u64,
+    retire_at: Option<u64>,
+    /// Ed25519 secret key (hex)
+    secret: String,
+}
+
+impl StoredKey {
+    fn generate(now: u64) -> Self {
+        let key = SigningKey::generate(&mut OsRng);
+        Self {
+            kid: key_id(&key.verifying_key()),
+            status: KeyStatus::Active,
+            created_at: now,
+            retire_at: None,
+            secret: hex::encode(key.to_bytes()),
+        }
+    }
+
+    fn signing_key(&self) -> Result<SigningKey> {
+        let bytes: [u8; 32] = hex::decode(&self.secret)?
+            .try_into()
+            .map_err(|_| anyhow!("Signing key {} has invalid length", self.kid))?;
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+}
+
+/// Name of the secret holding the key ring in an external secret store
+const SIGNING_KEYS_SECRET: &str = "signing_keys";
+
+/// Where the key ring is persisted
+enum KeyStorage {
+    #[cfg(test)]
+    Memory,
+    File(PathBuf),
+    Secrets(Arc<Secrets>),
+}
+
+/// Set of live service signing keys
+pub struct KeyRing {
+    storage: KeyStorage,
+    retire_after_secs: u64,
+    keys: RwLock<Vec<StoredKey>>,
+    /// Serializes changes to the ring across their persisting
+    updates: tokio::sync::Mutex<()>,
+}
+
+impl KeyRing {
+    /// Load the key ring, generating a key if none is active
+    ///
+    /// Keys come from the external secret store when one is configured and
+    /// from the data directory otherwise.
+    pub async fn open(
+        data_dir: &Path,
+        secrets: Arc<Secrets>,
+        retire_after_secs: u64,
+    ) -> Result<Self> {
+        let (storage, keys) = if secrets.is_external() {
+            let keys = match secrets.get(SIGNING_KEYS_SECRET).await? {
+                Some(json) => serde_json::from_str(&json)?,
+                None => Vec::new(),
+            };
+            (KeyStorage::Secrets(secrets), keys)
+        } else {
+            let path = data_dir.join("signing_keys.json");
+            let keys: Vec<StoredKey> = load_json(&path)?.unwrap_or_default();
+            (KeyStorage::File(path), keys)
+        };
+
+        let ring = Self {
+            storage,
+            retire_after_secs,
+            keys: RwLock::new(keys),
+            updates: tokio::sync::Mutex::new(()),
+        };
+        ring.update(|_| {}).await?;
+        Ok(ring)
+    }
+
+    /// Build a non-persistent key ring with a fresh key
+    #[cfg(test)]
+    pub fn ephemeral(retire_after_secs: u64) -> Self {
+        Self {
+            storage: KeyStorage::Memory,
+            retire_after_secs,
+            keys: RwLock::new(vec![StoredKey::generate(now_secs())]),
+            updates: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Id of the key currently used for signing
+    pub fn active_kid(&self) -> Option<String> {
+        let keys = self.keys.read().unwrap();
+        keys.iter()
+            .find(|k| k.status == KeyStatus::Active)
+            .map(|k| k.kid.clone())
+    }
+
+    /// Sign a message with the active key
+    pub fn sign(&self, message: &[u8]) -> Result<ServiceSignature> {
+        let keys = self.keys.read().unwrap();
+        let active = keys
+            .iter()
+            .find(|k| k.status == KeyStatus::Active)
+            .ok_or_else(|| anyhow!("No active signing key"))?;
+        let signature = active.signing_key()?.sign(message);
+
+        Ok(ServiceSignature {
+            kid: active.kid.clone(),
+            alg: SIGNATURE_ALG.to_string(),
+            sig: hex::encode(signature.to_bytes()),
+        })
+    }
+
+    /// Signed receipt that a verification of `proof_id` returned `valid`
+    pub fn receipt(&self, proof_id: &str, valid: bool) -> Result<VerificationReceipt> {
+        let verified_at = now_secs();
+        let signature = self.sign(&verification_receipt_message(proof_id, valid, verified_at))?;
+        Ok(VerificationReceipt {
+            proof_id: proof_id.to_string(),
+            valid,
+            verified_at,
+            signature,
+        })
+    }
+
+    /// Public keys of all non-retired keys
+    pub fn public_keys(&self) -> Vec<PublicKeyInfo> {
+        let keys = self.keys.read().unwrap();
+        keys.iter()
+            .filter_map(|k| {
+                let public = k.signing_key().ok()?.verifying_key();
+                Some(PublicKeyInfo {
+                    kid: k.kid.clone(),
+                    alg: SIGNATURE_ALG.to_string(),
+                    public_key: hex::encode(public.to_bytes()),
+                    status: k.status,
+                    created_at: k.created_at,
+                    retire_at: k.retire_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Generate a new active key and move the current one to retiring
+    pub async fn rotate(&self) -> Result<String> {
+        let now = now_secs();
+        let new_key = StoredKey::generate(now);
+        let kid = new_key.kid.clone();
+
+        self.update(|keys| {
+            for key in keys.iter_mut().filter(|k| k.status == KeyStatus::Active) {
+                key.status = KeyStatus::Retiring;
+                key.retire_at = Some(now + self.retire_after_secs);
+            }
+            keys.push(new_key);
+        })
+        .await?;
+        tracing::info!("Rotated service signing key, new kid {}", kid);
+        Ok(kid)
+    }
+
+    /// Every key of the ring, including secrets, for a registry snapshot
+    pub fn snapshot(&self) -> Vec<StoredKey> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Replace the ring with `keys`, so proofs keep being signed by (and
+    /// verifiable against) the keys of the instance they were taken from
+    pub async fn restore(&self, keys: Vec<StoredKey>) -> Result<()> {
+        self.update(|current| *current = keys).await
+    }
+
+    /// Rotate if the active key is older than `max_age_secs`, otherwise
+    /// drop retiring keys whose time is up
+    pub async fn rotate_if_due(&self, max_age_secs: u64) -> Result<Option<String>> {
+        let now = now_secs();
+        let (due, expired) = {
+            let keys = self.keys.read().unwrap();
+            (
+                keys.iter()
+                    .find(|k| k.status == KeyStatus::Active)
+                    .is_none_or(|k| now >= k.created_at + max_age_secs),
+                keys.iter().any(|k| k.retire_at.is_some_and(|t| now >= t)),
+            )
+        };
+        if due {
+            return self.rotate().await.map(Some);
+        }
+        // The ring is only written back when a key actually expired
+        if expired {
+            self.update(|_| {}).await?;
+        }
+        Ok(None)
+    }
+
+    /// Load the ring again from where it is persisted, picking up keys the
+    /// leader rotated in a shared store
+    pub async fn reload(&self) -> Result<()> {
+        let _update = self.updates.lock().await;
+        let keys: Option<Vec<StoredKey>> = match &self.storage {
+            #[cfg(test)]
+            KeyStorage::Memory => None,
+            KeyStorage::File(path) => load_json(path)?,
+            KeyStorage::Secrets(secrets) => match secrets.get(SIGNING_KEYS_SECRET).await? {
+                Some(json) => Some(serde_json::from_str(&json)?),
+                None => None,
+            },
+        };
+        if let Some(keys) = keys.filter(|keys| keys.iter().any(|k| k.status == KeyStatus::Active)) {
+            *self.keys.write().unwrap() = keys;
+        }
+        Ok(())
+    }
+
+    /// Apply `change` to a copy of the ring, drop retired keys and make sure
+    /// one key is active, then install the result only once it is persisted,
+    /// so the advertised keys never differ from the saved ones
+    async fn update(&self, change: impl FnOnce(&mut Vec<StoredKey>)) -> Result<()> {
+        let _update = self.updates.lock().await;
+        let mut keys = self.keys.read().unwrap().clone();
+        change(&mut keys);
+        let now = now_secs();
+        keys.retain(|k| k.retire_at.is_none_or(|t| now < t));
+        if !keys.iter().any(|k| k.status == KeyStatus::Active) {
+            keys.push(StoredKey::generate(now));
+        }
+        self.persist(keys.clone()).await?;
+        *self.keys.write().unwrap() = keys;
+        Ok(())
+    }
+
+    async fn persist(&self, keys: Vec<StoredKey>) -> Result<()> {
+        match &self.storage {
+            #[cfg(test)]
+            KeyStorage::Memory => Ok(()),
+            KeyStorage::File(path) => save_secret_json(path, &keys),
+            KeyStorage::Secrets(secrets) => {
+                secrets
+                    .put(SIGNING_KEYS_SECRET, &serde_json::to_string(&keys)?)
+                    .await
+            }
+        }
+    }
+}
+
+/// Message signed for a proof response: domain tag, content-addressed proof id
+/// (see `proofs`) and the canonical JSON of the public inputs
+pub fn prove_response_message(proof_id: &str, public_inputs: &PublicInputs) -> Result<Vec<u8>> {
+    // Canonicalize the public inputs as a verifier parses them from the
+    // response body, where outputs appear in their shortest `f32` form
+    let parsed: serde_json::Value = serde_json::from_slice(&serde_json::to_vec(public_inputs)?)?;
+    let mut message = b"jolt-atlas-prover/prove-response/v3\n".to_vec();
+    message.extend_from_slice(proof_id.as_bytes());
+    message.push(b'\n');
+    message.extend_from_slice(canonical_json(&parsed).as_bytes());
+    Ok(message)
+}
+
+/// RFC 8785 (JCS) canonical form of `value`: no whitespace, object members
+/// sorted by the UTF-16 code units of their names, and numbers in their
+/// ECMAScript form
+pub fn canonical_json(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => ecmascript_number(n.as_f64().unwrap_or_default()),
+        // serde_json escapes exactly what JCS requires
+        Value::String(s) => serde_json::to_string(s).expect("strings serialize"),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(canonical_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            let members: Vec<String> = members
+                .into_iter()
+                .map(|(name, value)| {
+                    let name = serde_json::to_string(name).expect("strings serialize");
+                    format!("{}:{}", name, canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+    }
+}
+
+/// `Number.prototype.toString` of `x`, as JCS serializes numbers
+fn ecmascript_number(x: f64) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return "0".to_string();
+    }
+    // Shortest round-tripping digits and decimal exponent, e.g. `1.25e-7`
+    let scientific = format!("{:e}", x.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("scientific notation");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("decimal exponent") + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = if n > 0 { '+' } else { '-' };
+        match digits.split_at(1) {
+            (first, "") => format!("{}e{}{}", first, sign, (n - 1).abs()),
+            (first, rest) => format!("{}.{}e{}{}", first, rest, sign, (n - 1).abs()),
+        }
+    };
+    if x < 0.0 {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Check `signature` over `message` against the hex Ed25519 `public_key`
+pub fn verify_signature(
+    public_key: &str,
+    message: &[u8],
+    signature: &ServiceSignature,
+) -> Result<()> {
+    use ed25519_dalek::{Signature, Verifier};
+
+    if signature.alg != SIGNATURE_ALG {
+        return Err(anyhow!("Unsupported signature algorithm {}", signature.alg));
+    }
+    let public: [u8; 32] = hex::decode(public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("Public key must be 32 bytes"))?;
+    let sig: [u8; 64] = hex::decode(&signature.sig)?
+        .try_into()
+        .map_err(|_| anyhow!("Signature must be 64 bytes"))?;
+    VerifyingKey::from_bytes(&public)?
+        .verify(message, &Signature::from_bytes(&sig))
+        .map_err(|_| anyhow!("Signature does not verify"))
+}
+
+/// Message signed for a verification receipt: domain tag, content-addressed
+/// proof id, verdict and time of verification
+pub fn verification_receipt_message(proof_id: &str, valid: bool, verified_at: u64) -> Vec<u8> {
+    format!(
+        "jolt-atlas-prover/verification-receipt/v1\n{}\n{}\n{}",
+        proof_id, valid, verified_at
+    )
+    .into_bytes()
+}
+
+/// Message signed for a nonce issued by `GET /challenge` (see `challenges`)
+pub fn challenge_message(nonce: &str, issued_at: u64) -> Vec<u8> {
+    format!("jolt-atlas-prover/challenge/v1\n{}\n{}", nonce, issued_at).into_bytes()
+}
+
+/// Message signed for a transparency log tree head (see `transparency`)
+pub fn tree_head_message(tree_size: u64, timestamp: u64, root_hash: &str) -> Vec<u8> {
+    format!(
+        "jolt-atlas-prover/tree-head/v1\n{}\n{}\n{}",
+        tree_size, timestamp, root_hash
+    )
+    .into_bytes()
+}
+
+/// Key id: truncated SHA-256 of the public key
+fn key_id(public: &VerifyingKey) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(public.as_bytes());
+    format!("sk_{}", &hex::encode(digest)[..16])
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    /// Verify against the advertised public keys, as an external verifier would
+    fn verify(ring: &KeyRing, message: &[u8], signature: &ServiceSignature) -> bool {
+        let Some(info) = ring
+            .public_keys()
+            .into_iter()
+            .find(|k| k.kid == signature.kid)
+        else {
+            return false;
+        };
+        let public: [u8; 32] = hex::decode(info.public_key).unwrap().try_into().unwrap();
+        let sig: [u8; 64] = hex::decode(&signature.sig).unwrap().try_into().unwrap();
+        VerifyingKey::from_bytes(&public)
+            .unwrap()
+            .verify(message, &Signature::from_bytes(&sig))
+            .is_ok()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let ring = KeyRing::ephemeral(3600);
+        let signature = ring.sign(b"hello").unwrap();
+        assert_eq!(Some(signature.kid.clone()), ring.active_kid());
+        assert!(verify(&ring, b"hello", &signature));
+        assert!(!verify(&ring, b"tampered", &signature));
+    }
+
+    #[test]
+    fn test_receipt_binds_verdict() {
+        let ring = KeyRing::ephemeral(3600);
+        let receipt = ring.receipt("proof:sha256:ab", true).unwrap();
+        let message =
+            verification_receipt_message(&receipt.proof_id, receipt.valid, receipt.verified_at);
+        assert!(verify(&ring, &message, &receipt.signature));
+        let flipped = verification_receipt_message(&receipt.proof_id, false, receipt.verified_at);
+        assert!(!verify(&ring, &flipped, &receipt.signature));
+    }
+
+    #[test]
+    fn test_canonical_json_follows_rfc8785() {
+        // Number serialization samples from RFC 8785, appendix B
+        for (x, expected) in [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (1e-7, "1e-7"),
+            (1e-6, "0.000001"),
+            (333333333.3333333, "333333333.3333333"),
+            (-5e-324, "-5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (4.5, "4.5"),
+            (295147905179352830000.0, "295147905179352830000"),
+        ] {
+            assert_eq!(ecmascript_number(x), expected);
+        }
+
+        let value = serde_json::json!({"b": [1.0, 0.5, "\u{1f}"], "a": {"€": null, "z": true}});
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"a":{"z":true,"€":null},"b":[1,0.5,"\u001f"]}"#
+        );
+    }
+
+    #[test]
+    fn test_prove_response_message_is_canonical() {
+        let public_inputs: PublicInputs = serde_json::from_value(serde_json::json!({
+            "model_commitment": "0xaa",
+            "input_hash": "0xbb",
+            "output_hash": "0xcc",
+            "output": [0.1, 1.0],
+            "timestamp": 7
+        }))
+        .unwrap();
+        let message = prove_response_message("proof:sha256:ab", &public_inputs).unwrap();
+        let body = String::from_utf8(message).unwrap();
+        let (_, canonical) = body.rsplit_once('\n').unwrap();
+        assert_eq!(
+            canonical,
+            r#"{"commitment_scheme":"sha256-v1","expected_output_enforced":false,"input_hash":"0xbb","model_commitment":"0xaa","output":[0.1,1],"output_hash":"0xcc","timestamp":7}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_retiring_key_verifiable() {
+        let ring = KeyRing::ephemeral(3600);
+        let old_signature = ring.sign(b"hello").unwrap();
+
+        let new_kid = ring.rotate().await.unwrap();
+        assert_ne!(new_kid, old_signature.kid);
+        assert!(verify(&ring, b"hello", &old_signature));
+
+        let keys = ring.public_keys();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            keys.iter()
+                .filter(|k| k.status == KeyStatus::Active)
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotation_without_grace_retires_immediately() {
+        let ring = KeyRing::ephemeral(0);
+        let old_signature = ring.sign(b"hello").unwrap();
+        ring.rotate().await.unwrap();
+        assert!(!verify(&ring, b"hello", &old_signature));
+        assert_eq!(ring.public_keys().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_ring_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = Arc::new(Secrets::connect(None).await.unwrap());
+        let kid = KeyRing::open(dir.path(), secrets.clone(), 3600)
+            .await
+            .unwrap()
+            .active_kid();
+        let reopened = KeyRing::open(dir.path(), secrets.clone(), 3600)
+            .await
+            .unwrap();
+        assert_eq!(reopened.active_kid(), kid);
+
+        // A replica sharing the ring picks up the leader's rotation on reload
+        let leader = KeyRing::open(dir.path(), secrets, 3600).await.unwrap();
+        let rotated = leader.rotate().await.unwrap();
+        assert_eq!(reopened.active_kid(), kid);
+        reopened.reload().await.unwrap();
+        assert_eq!(reopened.active_kid(), Some(rotated));
+        assert_eq!(reopened.public_keys().len(), 2);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(dir.path().join("signing_keys.json")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_rotation_keeps_the_ring() {
+        let dir = tempfile::tempdir().unwrap();
+        // A file where the key file's directory should be fails every write
+        std::fs::write(dir.path().join("blocked"), b"").unwrap();
+        let ring = KeyRing {
+            storage: KeyStorage::File(dir.path().join("blocked/signing_keys.json")),
+            ..KeyRing::ephemeral(3600)
+        };
+        let kid = ring.active_kid();
+
+        // Nothing is due or expired, so nothing is written
+        assert_eq!(ring.rotate_if_due(3600).await.unwrap(), None);
+        assert!(ring.rotate().await.is_err());
+        assert_eq!(ring.active_kid(), kid);
+        assert_eq!(ring.public_keys().len(), 1);
+    }
+}
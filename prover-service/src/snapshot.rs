@@ -0,0 +1,436 @@
+//! Registry snapshot and restore
+//!
+//! `GET /admin/snapshot` exports a consistent snapshot of the registry: every
+//! model (file, metadata and commitments), pinned aliases, running
+//! experiments, tenant defaults and API keys (their secret hashes, never
+//! secrets). With `?signing_keys=true` it also carries the service signing
+//! keys, secrets included, so the snapshot must then be stored like any
+//! other key backup.
+//!
+//! `POST /admin/restore` loads a snapshot into an instance, typically a fresh
+//! one, for disaster recovery or to clone an environment. Models keep their
+//! ids and are re-registered from their files; a model whose recomputed
+//! commitment differs from the snapshot is refused before anything changes.
+//! Models already registered under the same id and commitment are left as
+//! they are, aliases, experiments, tenant defaults and keys replace those
+//! with the same names, and restored signing keys replace the key ring.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::aliases::AliasRecord;
+use crate::api_keys::ApiKeyRecord;
+use crate::audit::AuditAction;
+use crate::auth::AdminAuth;
+use crate::experiments::Experiment;
+use crate::jolt_atlas::ModelCommitments;
+use crate::onnx;
+use crate::plugins;
+use crate::provenance::ProvenanceRecord;
+use crate::quantization::Quantization;
+use crate::queue::ModelLimits;
+use crate::registrants::RegistrantSignature;
+use crate::sandbox::ModelExecution;
+use crate::signing::StoredKey;
+use crate::tenants::TenantDefaults;
+use crate::types::{api_error, ApiError, ModelInfo, RegisterModelRequest};
+use crate::AppState;
+
+/// Version of the snapshot format
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// A registered model with its file
+#[derive(Serialize, Deserialize)]
+pub struct ModelSnapshot {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Model file as registered (base64); empty when shipped separately, as
+    /// in backups
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub model_bytes: String,
+    pub commitment: String,
+    pub circuit_commitment: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ProvenanceRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registrant: Option<RegistrantSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<u64>,
+    #[serde(default)]
+    pub execution: ModelExecution,
+    pub shards: u32,
+    #[serde(default)]
+    pub hot: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "ModelLimits::is_unlimited")]
+    pub limits: ModelLimits,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+    /// Preprocessing plugin module (base64)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+}
+
+impl ModelSnapshot {
+    fn capture(model: &ModelInfo) -> Result<Self> {
+        let preprocessor = match &model.preprocessor {
+            Some(_) => Some(BASE64.encode(std::fs::read(plugins::plugin_path(&model.path))?)),
+            None => None,
+        };
+        Ok(Self {
+            id: model.id.clone(),
+            name: model.name.clone(),
+            description: model.description.clone(),
+            model_bytes: BASE64.encode(std::fs::read(&model.path)?),
+            commitment: model.commitment.clone(),
+            circuit_commitment: model.circuit_commitment.clone(),
+            provenance: model.provenance.as_ref().map(|p| p.record.clone()),
+            registrant: model.registrant.as_ref().map(|r| r.signature.clone()),
+            archived_at: model.archived_at,
+            execution: model.execution,
+            shards: model.shards,
+            hot: model.hot,
+            pinned: model.pinned,
+            limits: model.limits,
+            quantization: model.quantization.clone(),
+            preprocessor,
+        })
+    }
+
+    fn registration(&self) -> RegisterModelRequest {
+        RegisterModelRequest {
+            name: self.name.clone(),
+            model_bytes: self.model_bytes.clone(),
+            description: self.description.clone(),
+            provenance: self.provenance.clone(),
+            registrant: self.registrant.clone(),
+            execution: self.execution,
+            shards: Some(self.shards),
+            hot: self.hot,
+            limits: self.limits,
+            quantization: self.quantization.clone(),
+            preprocessor: self.preprocessor.clone(),
+            warmup: None,
+        }
+    }
+
+    /// Check the model file still commits to the recorded commitments
+    fn check_commitment(&self) -> Result<Vec<u8>> {
+        let model_bytes = self.registration().decode_model_bytes()?;
+        let commitments = ModelCommitments::compute(&onnx::canonicalize(&model_bytes)?);
+        if commitments.sha256 != self.commitment || commitments.keccak256 != self.circuit_commitment
+        {
+            return Err(anyhow!(
+                "Model {} commits to {}, not {} as recorded in the snapshot",
+                self.id,
+                commitments.sha256,
+                self.commitment
+            ));
+        }
+        Ok(model_bytes)
+    }
+}
+
+/// Consistent snapshot of the registry
+#[derive(Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    pub version: u32,
+    pub created_at: u64,
+    /// Models in registration order
+    pub models: Vec<ModelSnapshot>,
+    #[serde(default)]
+    pub aliases: Vec<AliasRecord>,
+    #[serde(default)]
+    pub experiments: Vec<Experiment>,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyRecord>,
+    #[serde(default)]
+    pub tenant_defaults: Vec<TenantDefaults>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_keys: Option<Vec<StoredKey>>,
+}
+
+/// What a restore changed
+#[derive(Serialize)]
+pub struct RestoreReport {
+    /// Models registered from the snapshot
+    pub models_restored: Vec<String>,
+    /// Models already registered with the same commitment
+    pub models_present: Vec<String>,
+    pub aliases: usize,
+    pub experiments: usize,
+    pub api_keys: usize,
+    pub tenant_defaults: usize,
+    pub signing_keys: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotQuery {
+    /// Include the signing keys (with their secrets)
+    #[serde(default)]
+    signing_keys: bool,
+}
+
+/// Take a consistent snapshot of the registry
+pub async fn capture(state: &AppState, signing_keys: bool) -> Result<RegistrySnapshot> {
+    // Holding the registry lock keeps models and their aliases consistent
+    let prover = state.prover.read().await;
+    let models = prover
+        .models()
+        .map(ModelSnapshot::capture)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RegistrySnapshot {
+        version: SNAPSHOT_VERSION,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        models,
+        aliases: state.aliases.list(),
+        experiments: state.experiments.snapshot(),
+        api_keys: state.api_keys.snapshot(),
+        tenant_defaults: state.tenants.snapshot(),
+        signing_keys: signing_keys.then(|| state.signing_keys.snapshot()),
+    })
+}
+
+/// Export a snapshot of the registry
+pub async fn get_snapshot(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SnapshotQuery>,
+) -> Result<Json<RegistrySnapshot>, ApiError> {
+    let snapshot = capture(&state, query.signing_keys).await.map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "SNAPSHOT_FAILED",
+            e.to_string(),
+        )
+    })?;
+    tracing::info!(
+        "Exported registry snapshot with {} models{}",
+        snapshot.models.len(),
+        if query.signing_keys {
+            " and signing keys"
+        } else {
+            ""
+        }
+    );
+    Ok(Json(snapshot))
+}
+
+fn restore_failed(e: impl ToString) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "RESTORE_FAILED",
+        e.to_string(),
+    )
+}
+
+/// Restore a registry snapshot
+pub async fn restore_snapshot(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Json(snapshot): Json<RegistrySnapshot>,
+) -> Result<Json<RestoreReport>, ApiError> {
+    restore(&state, snapshot).await.map(Json)
+}
+
+/// Load `snapshot` into the registry
+pub async fn restore(
+    state: &AppState,
+    snapshot: RegistrySnapshot,
+) -> Result<RestoreReport, ApiError> {
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "UNSUPPORTED_SNAPSHOT",
+            format!("Unsupported snapshot version {}", snapshot.version),
+        ));
+    }
+
+    let mut prover = state.prover.write().await;
+
+    // Check every model before registering any
+    let mut pending = Vec::new();
+    let mut present = Vec::new();
+    for model in &snapshot.models {
+        let model_bytes = model
+            .check_commitment()
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, "SNAPSHOT_INVALID", e.to_string()))?;
+        match prover.get_model(&model.id) {
+            Some(existing) if existing.commitment == model.commitment => {
+                present.push(model.id.clone());
+                continue;
+            }
+            Some(existing) => {
+                return Err(api_error(
+                    StatusCode::CONFLICT,
+                    "MODEL_ID_TAKEN",
+                    format!(
+                        "Model {} is already registered with commitment {}",
+                        model.id, existing.commitment
+                    ),
+                ));
+            }
+            None => {}
+        }
+        if state.config.immutable_registry {
+            if let Some(existing) = state
+                .audit
+                .model_for_name(&model.name)
+                .filter(|id| *id != model.id)
+            {
+                return Err(api_error(
+                    StatusCode::CONFLICT,
+                    "MODEL_NAME_TAKEN",
+                    format!(
+                        "Model name {} is already registered as {} and the registry is immutable",
+                        model.name, existing
+                    ),
+                ));
+            }
+        }
+        let registrant = state
+            .registrants
+            .authorize(&model_bytes, model.registrant.as_ref())?;
+        pending.push((model, registrant));
+    }
+
+    let mut restored = Vec::new();
+    for (model, registrant) in pending {
+        let info = prover
+            .register_model_as(model.id.clone(), &model.registration(), registrant)
+            .await
+            .map_err(|e| {
+                api_error(
+                    StatusCode::BAD_REQUEST,
+                    "MODEL_REGISTRATION_FAILED",
+                    format!("{}: {}", model.id, e),
+                )
+            })?;
+        if let Some(archived_at) = model.archived_at {
+            prover.set_archived(&model.id, Some(archived_at));
+        }
+        if model.pinned {
+            prover.set_pinned(&model.id, true);
+        }
+        let details = serde_json::json!({
+            "name": info.name,
+            "commitment": info.commitment,
+            "circuit_commitment": info.circuit_commitment,
+            "weights_root": info.weights_root,
+            "registrant": info.registrant.as_ref().map(|r| &r.key_id),
+            "snapshot": snapshot.created_at,
+        });
+        if let Err(e) = state
+            .audit
+            .append(AuditAction::Register, &info.id, "admin", details)
+        {
+            prover.rollback_registration(&info.id);
+            return Err(restore_failed(e));
+        }
+        restored.push(info.id);
+    }
+    drop(prover);
+
+    let report = RestoreReport {
+        models_restored: restored,
+        models_present: present,
+        aliases: snapshot.aliases.len(),
+        experiments: snapshot.experiments.len(),
+        api_keys: snapshot.api_keys.len(),
+        tenant_defaults: snapshot.tenant_defaults.len(),
+        signing_keys: snapshot.signing_keys.is_some(),
+    };
+    for alias in &snapshot.aliases {
+        state
+            .audit
+            .append(
+                AuditAction::SetAlias,
+                &alias.model_id,
+                "admin",
+                serde_json::json!({ "alias": alias.alias, "snapshot": snapshot.created_at }),
+            )
+            .map_err(restore_failed)?;
+    }
+    state
+        .aliases
+        .restore(snapshot.aliases)
+        .map_err(restore_failed)?;
+    state
+        .experiments
+        .restore(snapshot.experiments)
+        .map_err(restore_failed)?;
+    state
+        .api_keys
+        .restore(snapshot.api_keys)
+        .map_err(restore_failed)?;
+    state
+        .tenants
+        .restore(snapshot.tenant_defaults)
+        .map_err(restore_failed)?;
+    if let Some(keys) = snapshot.signing_keys {
+        state
+            .signing_keys
+            .restore(keys)
+            .await
+            .map_err(restore_failed)?;
+    }
+
+    tracing::info!(
+        "Restored registry snapshot: {} models registered, {} already present",
+        report.models_restored.len(),
+        report.models_present.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo;
+
+    #[test]
+    fn test_snapshot_model_commitment_is_checked() {
+        let bytes = demo::MODELS[0].bytes();
+        let commitments = ModelCommitments::compute(&onnx::canonicalize(bytes).unwrap());
+        let mut model = ModelSnapshot {
+            id: "m".to_string(),
+            name: "m".to_string(),
+            description: None,
+            model_bytes: BASE64.encode(bytes),
+            commitment: commitments.sha256,
+            circuit_commitment: commitments.keccak256,
+            provenance: None,
+            registrant: None,
+            archived_at: None,
+            execution: ModelExecution::Native,
+            shards: 1,
+            hot: false,
+            pinned: false,
+            limits: ModelLimits::default(),
+            quantization: None,
+            preprocessor: None,
+        };
+        assert_eq!(model.check_commitment().unwrap(), bytes);
+
+        model.model_bytes = BASE64.encode(demo::MODELS[1].bytes());
+        assert!(model.check_commitment().is_err());
+
+        // Older snapshots without optional sections still load
+        let json = serde_json::json!({ "version": 1, "created_at": 1, "models": [] });
+        let snapshot: RegistrySnapshot = serde_json::from_value(json).unwrap();
+        assert!(snapshot.signing_keys.is_none() && snapshot.api_keys.is_empty());
+    }
+}
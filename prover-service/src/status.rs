@@ -0,0 +1,20 @@
+//! Embedded status page
+//!
+//! `GET /status` serves a single self-contained HTML page (inline styles and
+//! script, no external assets) for quick checks on airgapped deployments. The
+//! page itself is public and holds no data: it asks for the admin token, keeps
+//! it in session storage, and polls `GET /admin/overview` to render jobs in
+//! flight, recent proof latencies, registered models, backend health and
+//! recent errors.
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+};
+
+const PAGE: &str = include_str!("status.html");
+
+/// Serve the status page
+pub async fn status_page() -> impl IntoResponse {
+    ([(header::CACHE_CONTROL, "no-store")], Html(PAGE))
+}
@@ -0,0 +1,615 @@
+//! Artifact storage backends
+//!
+//! Stored proofs and copies of registered model files go through
+//! `Storage`, selected with `STORAGE_BACKEND`:
+//!
+//! - `local` (default): files under the data directory
+//! - `gcs`: a Google Cloud Storage bucket (`GCS_BUCKET`), authenticated with
+//!   the `gcs_access_token` secret or the GCE metadata server's service
+//!   account
+//! - `azure`: an Azure Blob Storage container (`AZURE_STORAGE_ACCOUNT`,
+//!   `AZURE_STORAGE_CONTAINER`), authenticated with the SAS token in the
+//!   `azure_storage_sas_token` secret
+//!
+//! Credentials are resolved through `Secrets`: from Vault when it is
+//! configured, otherwise from `GCS_ACCESS_TOKEN` and `AZURE_STORAGE_SAS_TOKEN`.
+//!
+//! Object keys are `/`-separated (`proofs/<id>.json`, `models/<id>.onnx`)
+//! and prefixed with `STORAGE_PREFIX` on remote backends. `GCS_ENDPOINT` and
+//! `AZURE_STORAGE_ENDPOINT` point the clients at emulators.
+//!
+//! Model files are still proven from the local model directory; with a
+//! remote backend they are also uploaded on registration so they outlive
+//! the node. Proving checkpoints stay on local disk, as backends write them
+//! while a proof runs.
+
+use anyhow::{anyhow, Context, Result};
+use axum::async_trait;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::secrets::Secrets;
+
+/// Secret holding a static GCS bearer token
+const GCS_TOKEN_SECRET: &str = "gcs_access_token";
+
+/// Secret holding the Azure SAS token
+const AZURE_SAS_SECRET: &str = "azure_storage_sas_token";
+
+/// Azure Blob REST API version used for requests
+const AZURE_API_VERSION: &str = "2021-08-06";
+
+/// Refresh metadata-server tokens this long before they expire
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Which backend artifacts are stored in
+#[derive(Clone, Debug, Default)]
+pub enum StorageConfig {
+    #[default]
+    Local,
+    Gcs {
+        bucket: String,
+        prefix: String,
+        endpoint: String,
+        /// Static bearer token; the metadata server is asked without one
+        access_token: Option<String>,
+    },
+    Azure {
+        container: String,
+        sas_token: String,
+        prefix: String,
+        endpoint: String,
+    },
+}
+
+impl StorageConfig {
+    /// Read the backend from the environment and its credentials from
+    /// `secrets`
+    pub async fn from_env(secrets: &Secrets) -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let required = |name: &str| var(name).ok_or_else(|| anyhow!("{} is required", name));
+        let prefix = var("STORAGE_PREFIX").unwrap_or_default();
+
+        match var("STORAGE_BACKEND").as_deref() {
+            None | Some("local") => Ok(StorageConfig::Local),
+            Some("gcs") => Ok(StorageConfig::Gcs {
+                bucket: required("GCS_BUCKET")?,
+                prefix,
+                endpoint: var("GCS_ENDPOINT")
+                    .unwrap_or_else(|| "https://storage.googleapis.com".to_string()),
+                access_token: secrets.get(GCS_TOKEN_SECRET).await?,
+            }),
+            Some("azure") => {
+                let account = required("AZURE_STORAGE_ACCOUNT")?;
+                Ok(StorageConfig::Azure {
+                    endpoint: var("AZURE_STORAGE_ENDPOINT")
+                        .unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account)),
+                    container: required("AZURE_STORAGE_CONTAINER")?,
+                    sas_token: secrets
+                        .get(AZURE_SAS_SECRET)
+                        .await?
+                        .ok_or_else(|| anyhow!("{} is required", AZURE_SAS_SECRET))?,
+                    prefix,
+                })
+            }
+            Some(other) => Err(anyhow!("Unknown STORAGE_BACKEND {}", other)),
+        }
+    }
+
+    /// Open the configured backend; local storage lives under `data_dir`
+    pub fn open(&self, data_dir: &Path) -> Result<Arc<dyn Storage>> {
+        Ok(match self.clone() {
+            StorageConfig::Local => Arc::new(LocalStorage::new(data_dir)?),
+            StorageConfig::Gcs {
+                bucket,
+                prefix,
+                endpoint,
+                access_token,
+            } => Arc::new(GcsStorage::new(endpoint, bucket, prefix, access_token)),
+            StorageConfig::Azure {
+                container,
+                sas_token,
+                prefix,
+                endpoint,
+            } => Arc::new(AzureBlobStorage::new(
+                endpoint, container, sas_token, prefix,
+            )),
+        })
+    }
+}
+
+/// A stored object
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+}
+
+/// Key-value store for artifacts
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Backend name, for logs and reports
+    fn name(&self) -> &'static str;
+
+    /// Whether objects live outside this node
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()>;
+
+    /// Read an object, or `None` if there is none under `key`
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Remove an object; removing a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Objects whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+}
+
+/// Files under a local directory
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: &Path) -> Result<Self> {
+        std::fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key
+            .split('/')
+            .any(|part| part.is_empty() || part == "." || part == "..")
+        {
+            return Err(anyhow!("Invalid storage key {}", key));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write then rename, so readers never see a partial object
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        // Keys are listed one directory deep: the part of the prefix up to
+        // its last `/` names the directory
+        let (dir, _) = prefix.rsplit_once('/').unwrap_or(("", prefix));
+        let mut entries = match tokio::fs::read_dir(self.root.join(dir)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut objects = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+            let key = if dir.is_empty() {
+                name
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            if metadata.is_file() && key.starts_with(prefix) && !key.ends_with(".tmp") {
+                objects.push(ObjectInfo {
+                    key,
+                    size: metadata.len(),
+                });
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+}
+
+/// Objects in a Google Cloud Storage bucket (JSON API)
+pub struct GcsStorage {
+    http: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    prefix: String,
+    /// Static bearer token, when one is configured
+    access_token: Option<String>,
+    /// Metadata-server token and when it expires
+    token: RwLock<Option<(String, Instant)>>,
+}
+
+#[derive(Deserialize)]
+struct GcsObjects {
+    #[serde(default)]
+    items: Vec<GcsObject>,
+    #[serde(rename = "nextPageToken")]
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GcsObject {
+    name: String,
+    /// Decimal string, as the JSON API encodes 64-bit integers
+    size: String,
+}
+
+#[derive(Deserialize)]
+struct MetadataToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+impl GcsStorage {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        prefix: String,
+        access_token: Option<String>,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket,
+            prefix,
+            access_token,
+            token: RwLock::new(None),
+        }
+    }
+
+    /// The configured bearer token, or one from the metadata server
+    async fn token(&self) -> Result<String> {
+        if let Some(token) = &self.access_token {
+            return Ok(token.clone());
+        }
+        if let Some((token, expires)) = self.token.read().await.as_ref() {
+            if Instant::now() + TOKEN_REFRESH_MARGIN < *expires {
+                return Ok(token.clone());
+            }
+        }
+        let token: MetadataToken = self
+            .http
+            .get("http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token")
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .context("No GCS access token is configured and the metadata server is unreachable")?
+            .error_for_status()?
+            .json()
+            .await?;
+        let expires = Instant::now() + Duration::from_secs(token.expires_in);
+        *self.token.write().await = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    fn object_url(&self, key: &str) -> Result<Url> {
+        let mut url = Url::parse(&self.endpoint)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid GCS endpoint {}", self.endpoint))?
+            .extend(["storage", "v1", "b", &self.bucket, "o"])
+            .push(&format!("{}{}", self.prefix, key));
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Storage for GcsStorage {
+    fn name(&self) -> &'static str {
+        "gcs"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let url = format!("{}/upload/storage/v1/b/{}/o", self.endpoint, self.bucket);
+        self.http
+            .post(url)
+            .bearer_auth(self.token().await?)
+            .query(&[
+                ("uploadType", "media"),
+                ("name", &format!("{}{}", self.prefix, key)),
+            ])
+            .body(bytes)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .http
+            .get(self.object_url(key)?)
+            .bearer_auth(self.token().await?)
+            .query(&[("alt", "media")])
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self
+            .http
+            .delete(self.object_url(key)?)
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let url = format!("{}/storage/v1/b/{}/o", self.endpoint, self.bucket);
+        let full_prefix = format!("{}{}", self.prefix, prefix);
+        let mut objects = Vec::new();
+        let mut page_token = None;
+        loop {
+            let mut request = self
+                .http
+                .get(&url)
+                .bearer_auth(self.token().await?)
+                .query(&[("prefix", &full_prefix)]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+            let page: GcsObjects = request.send().await?.error_for_status()?.json().await?;
+            objects.extend(page.items.into_iter().map(|object| ObjectInfo {
+                key: object.name[self.prefix.len()..].to_string(),
+                size: object.size.parse().unwrap_or(0),
+            }));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(objects),
+            }
+        }
+    }
+}
+
+/// Blobs in an Azure Blob Storage container
+pub struct AzureBlobStorage {
+    http: reqwest::Client,
+    endpoint: String,
+    container: String,
+    sas_token: String,
+    prefix: String,
+}
+
+impl AzureBlobStorage {
+    pub fn new(endpoint: String, container: String, sas_token: String, prefix: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            container,
+            sas_token: sas_token.trim_start_matches('?').to_string(),
+            prefix,
+        }
+    }
+
+    fn url(&self, key: Option<&str>) -> Result<Url> {
+        let mut url = Url::parse(&self.endpoint)?;
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| anyhow!("Invalid Azure endpoint {}", self.endpoint))?;
+            segments.pop_if_empty().push(&self.container);
+            if let Some(key) = key {
+                segments.extend(format!("{}{}", self.prefix, key).split('/'));
+            }
+        }
+        url.set_query(Some(&self.sas_token));
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Storage for AzureBlobStorage {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        self.http
+            .put(self.url(Some(key))?)
+            .header("x-ms-version", AZURE_API_VERSION)
+            .header("x-ms-blob-type", "BlockBlob")
+            .body(bytes)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .http
+            .get(self.url(Some(key))?)
+            .header("x-ms-version", AZURE_API_VERSION)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self
+            .http
+            .delete(self.url(Some(key))?)
+            .header("x-ms-version", AZURE_API_VERSION)
+            .send()
+            .await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let full_prefix = format!("{}{}", self.prefix, prefix);
+        let mut objects = Vec::new();
+        let mut marker = String::new();
+        loop {
+            let mut url = self.url(None)?;
+            url.query_pairs_mut()
+                .append_pair("restype", "container")
+                .append_pair("comp", "list")
+                .append_pair("prefix", &full_prefix);
+            if !marker.is_empty() {
+                url.query_pairs_mut().append_pair("marker", &marker);
+            }
+            let body = self
+                .http
+                .get(url)
+                .header("x-ms-version", AZURE_API_VERSION)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let (page, next) = parse_blob_list(&body);
+            objects.extend(page.into_iter().map(|object| ObjectInfo {
+                key: object.key[self.prefix.len().min(object.key.len())..].to_string(),
+                size: object.size,
+            }));
+            match next {
+                Some(next) => marker = next,
+                None => return Ok(objects),
+            }
+        }
+    }
+}
+
+/// Blobs and the continuation marker of a List Blobs response
+fn parse_blob_list(xml: &str) -> (Vec<ObjectInfo>, Option<String>) {
+    let blobs = xml
+        .split("<Blob>")
+        .skip(1)
+        .filter_map(|blob| {
+            Some(ObjectInfo {
+                key: xml_unescape(xml_element(blob, "Name")?),
+                size: xml_element(blob, "Content-Length")?.parse().ok()?,
+            })
+        })
+        .collect();
+    let next = xml_element(xml, "NextMarker")
+        .filter(|marker| !marker.is_empty())
+        .map(xml_unescape);
+    (blobs, next)
+}
+
+/// Text of the first `<name>` element in `xml`
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", name))? + start;
+    Some(&xml[start..end])
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_storage_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path()).unwrap();
+        storage.put("proofs/a.json", b"{}".to_vec()).await.unwrap();
+        storage.put("proofs/b.json", b"[1]".to_vec()).await.unwrap();
+        storage
+            .put("models/m.onnx", b"onnx".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get("proofs/b.json").await.unwrap(),
+            Some(b"[1]".to_vec())
+        );
+        assert!(storage.get("proofs/c.json").await.unwrap().is_none());
+        let proofs = storage.list("proofs/").await.unwrap();
+        assert_eq!(
+            proofs,
+            vec![
+                ObjectInfo {
+                    key: "proofs/a.json".to_string(),
+                    size: 2
+                },
+                ObjectInfo {
+                    key: "proofs/b.json".to_string(),
+                    size: 3
+                },
+            ]
+        );
+
+        storage.delete("proofs/a.json").await.unwrap();
+        storage.delete("proofs/a.json").await.unwrap();
+        assert_eq!(storage.list("proofs/").await.unwrap().len(), 1);
+        assert!(storage.get("../escape").await.is_err());
+    }
+
+    #[test]
+    fn test_parse_azure_blob_list() {
+        let xml = "<?xml version=\"1.0\"?><EnumerationResults><Blobs>\
+            <Blob><Name>svc/proofs/a&amp;b.json</Name><Properties>\
+            <Content-Length>12</Content-Length></Properties></Blob>\
+            <Blob><Name>svc/models/m.onnx</Name><Properties>\
+            <Content-Length>3</Content-Length></Properties></Blob>\
+            </Blobs><NextMarker>2!abc</NextMarker></EnumerationResults>";
+        let (blobs, next) = parse_blob_list(xml);
+        assert_eq!(blobs.len(), 2);
+        assert_eq!(blobs[0].key, "svc/proofs/a&b.json");
+        assert_eq!(blobs[0].size, 12);
+        assert_eq!(next.as_deref(), Some("2!abc"));
+        assert!(parse_blob_list("<NextMarker />").1.is_none());
+    }
+}
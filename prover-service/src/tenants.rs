@@ -0,0 +1,351 @@
+//! Per-tenant request defaults
+//!
+//! Operators can give a tenant defaults for how its proofs are issued, each
+//! chosen from the set the service approves:
+//!
+//! - `commitment_scheme`: scheme of the `model_commitment`, `input_hash` and
+//!   `output_hash` at the top of the prove response, `sha256-v2` (the
+//!   default) or `keccak256-v1`, the circuit-friendly commitments every
+//!   proof binds next to the SHA-256 ones (verify them with
+//!   `commitment_scheme: keccak256-v1`)
+//! - `output_disclosure`: `full` (the default) or `hash_only`, which
+//!   withholds the output values from the response, its public inputs and
+//!   the proof itself so only their hash is disclosed
+//! - `proof_format`: the proof format to prove in, as
+//!   `ProveRequest::proof_format`
+//!
+//! Defaults are set with `PUT /admin/tenants/:tenant/defaults`, persisted to
+//! `tenant_defaults.json` and carried in registry snapshots. They fill in
+//! whatever a prove request of the tenant leaves unset; a request's own
+//! choice always wins.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::auth::AdminAuth;
+use crate::persist::{load_json, save_json};
+use crate::types::{api_error, ApiError, CommitmentScheme, ProofResult, ProveRequest};
+use crate::AppState;
+
+/// Schemes a prove response's top-level commitments may be issued under
+pub const APPROVED_SCHEMES: [CommitmentScheme; 2] =
+    [CommitmentScheme::Sha256V2, CommitmentScheme::Keccak256V1];
+
+/// How much of the model output a prove response discloses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputDisclosure {
+    /// Output values and their hash
+    #[default]
+    Full,
+    /// Only the hash of the output
+    HashOnly,
+}
+
+/// Defaults applied to one tenant's prove requests
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantDefaults {
+    #[serde(default)]
+    pub tenant: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment_scheme: Option<CommitmentScheme>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_disclosure: Option<OutputDisclosure>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_format: Option<String>,
+}
+
+impl TenantDefaults {
+    /// Fill in the options `request` leaves unset
+    pub fn apply(&self, request: &mut ProveRequest) {
+        if request.commitment_scheme.is_none() {
+            request.commitment_scheme = self.commitment_scheme;
+        }
+        if request.output_disclosure.is_none() {
+            request.output_disclosure = self.output_disclosure;
+        }
+        if request.proof_format.is_none() {
+            request.proof_format = self.proof_format.clone();
+        }
+    }
+}
+
+/// Refuse a commitment scheme outside the approved set
+pub fn check_scheme(scheme: CommitmentScheme) -> Result<()> {
+    if APPROVED_SCHEMES.contains(&scheme) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Commitment scheme {} is not offered; use one of {}",
+            scheme,
+            APPROVED_SCHEMES.map(|s| s.as_str()).join(", ")
+        ))
+    }
+}
+
+/// Issue `result` under `scheme`
+///
+/// The output disclosure is applied by the proving job, so that withheld
+/// outputs never reach the proof. `circuit_commitment` is the registered
+/// model's commitment under the circuit-friendly scheme; like the SHA-256
+/// one, it is the commitment to the model alone, which verifiers compose
+/// with the proof's output checks.
+pub fn present(
+    result: &mut ProofResult,
+    scheme: CommitmentScheme,
+    circuit_commitment: &str,
+) -> Result<()> {
+    if scheme != result.public_inputs.commitment_scheme {
+        let circuit = result
+            .public_inputs
+            .circuit_commitments
+            .as_ref()
+            .filter(|c| c.scheme == scheme)
+            .ok_or_else(|| anyhow!("The proof carries no {} commitments", scheme))?;
+        result.model_commitment = circuit_commitment.to_string();
+        result.input_hash = circuit.input_hash.clone();
+        result.output_hash = circuit.output_hash.clone();
+    }
+    Ok(())
+}
+
+/// Persistent defaults by tenant
+pub struct TenantStore {
+    path: PathBuf,
+    defaults: Mutex<BTreeMap<String, TenantDefaults>>,
+}
+
+impl TenantStore {
+    /// Open (or create) the tenant defaults store in the data directory
+    pub fn open(data_dir: &std::path::Path) -> Result<Self> {
+        let path = data_dir.join("tenant_defaults.json");
+        let defaults = load_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            path,
+            defaults: Mutex::new(defaults),
+        })
+    }
+
+    pub fn get(&self, tenant: &str) -> Option<TenantDefaults> {
+        self.defaults.lock().unwrap().get(tenant).cloned()
+    }
+
+    /// Fill in `request` from `tenant`'s defaults, if it has any
+    pub fn apply(&self, tenant: &str, request: &mut ProveRequest) {
+        if let Some(defaults) = self.defaults.lock().unwrap().get(tenant) {
+            defaults.apply(request);
+        }
+    }
+
+    pub fn set(&self, defaults: TenantDefaults) -> Result<()> {
+        let mut all = self.defaults.lock().unwrap();
+        all.insert(defaults.tenant.clone(), defaults);
+        save_json(&self.path, &*all)
+    }
+
+    pub fn remove(&self, tenant: &str) -> Result<Option<TenantDefaults>> {
+        let mut all = self.defaults.lock().unwrap();
+        let removed = all.remove(tenant);
+        save_json(&self.path, &*all)?;
+        Ok(removed)
+    }
+
+    /// Every tenant's defaults, for a registry snapshot
+    pub fn snapshot(&self) -> Vec<TenantDefaults> {
+        self.defaults.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Replace the defaults of the tenants in `restored`
+    pub fn restore(&self, restored: Vec<TenantDefaults>) -> Result<()> {
+        let mut all = self.defaults.lock().unwrap();
+        for defaults in restored {
+            all.insert(defaults.tenant.clone(), defaults);
+        }
+        save_json(&self.path, &*all)
+    }
+}
+
+/// Every tenant's defaults
+pub async fn list_tenant_defaults(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<TenantDefaults>> {
+    Json(state.tenants.snapshot())
+}
+
+/// A tenant's defaults
+pub async fn get_tenant_defaults(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(tenant): Path<String>,
+) -> Result<Json<TenantDefaults>, ApiError> {
+    state
+        .tenants
+        .get(&tenant)
+        .map(Json)
+        .ok_or_else(|| tenant_not_found(&tenant))
+}
+
+/// Set a tenant's defaults, replacing any it had
+pub async fn set_tenant_defaults(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(tenant): Path<String>,
+    Json(mut defaults): Json<TenantDefaults>,
+) -> Result<Json<TenantDefaults>, ApiError> {
+    if let Some(scheme) = defaults.commitment_scheme {
+        check_scheme(scheme).map_err(|e| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "UNSUPPORTED_COMMITMENT_SCHEME",
+                e.to_string(),
+            )
+        })?;
+    }
+    if let Some(format) = &defaults.proof_format {
+        let capabilities = state.prover.read().await.capabilities().await;
+        if !capabilities
+            .iter()
+            .any(|backend| backend.selectable && backend.proof_format == *format)
+        {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "UNSUPPORTED_BACKEND",
+                format!("No backend produces {} proofs", format),
+            ));
+        }
+    }
+
+    defaults.tenant = tenant;
+    state
+        .tenants
+        .set(defaults.clone())
+        .map_err(tenant_update_failed)?;
+    tracing::info!("Set request defaults of tenant {}", defaults.tenant);
+    Ok(Json(defaults))
+}
+
+/// Remove a tenant's defaults
+pub async fn delete_tenant_defaults(
+    _admin: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(tenant): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .tenants
+        .remove(&tenant)
+        .map_err(tenant_update_failed)?
+        .ok_or_else(|| tenant_not_found(&tenant))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn tenant_not_found(tenant: &str) -> ApiError {
+    api_error(
+        StatusCode::NOT_FOUND,
+        "TENANT_DEFAULTS_NOT_FOUND",
+        format!("Tenant {} has no defaults", tenant),
+    )
+}
+
+fn tenant_update_failed(e: anyhow::Error) -> ApiError {
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "TENANT_UPDATE_FAILED",
+        e.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::{create_prover, deserialize_proof, ModelCommitments};
+    use crate::onnx;
+    use crate::prover::ProveJob;
+
+    #[test]
+    fn test_defaults_fill_only_unset_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TenantStore::open(dir.path()).unwrap();
+        store
+            .set(TenantDefaults {
+                tenant: "acme".to_string(),
+                commitment_scheme: Some(CommitmentScheme::Keccak256V1),
+                output_disclosure: Some(OutputDisclosure::HashOnly),
+                proof_format: Some("jolt-atlas-v2".to_string()),
+            })
+            .unwrap();
+
+        let mut request: ProveRequest = serde_json::from_value(serde_json::json!({
+            "model_id": "m",
+            "proof_format": "jolt-atlas-v1"
+        }))
+        .unwrap();
+        store.apply("other", &mut request);
+        assert_eq!(request.commitment_scheme, None);
+        store.apply("acme", &mut request);
+        assert_eq!(
+            request.commitment_scheme,
+            Some(CommitmentScheme::Keccak256V1)
+        );
+        assert_eq!(request.output_disclosure, Some(OutputDisclosure::HashOnly));
+        assert_eq!(request.proof_format.as_deref(), Some("jolt-atlas-v1"));
+
+        assert!(check_scheme(CommitmentScheme::Keccak256V1).is_ok());
+        assert!(check_scheme(CommitmentScheme::Sha256V1).is_err());
+
+        let reopened = TenantStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.snapshot().len(), 1);
+        assert!(reopened.remove("acme").unwrap().is_some());
+        assert!(reopened.get("acme").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hash_only_outputs_stay_out_of_the_proof() {
+        let model_path = std::path::Path::new("models/demo-logistic.onnx");
+        let commitments = ModelCommitments::compute(
+            &onnx::canonicalize(&std::fs::read(model_path).unwrap()).unwrap(),
+        );
+        let job = |disclosure| ProveJob {
+            model_path: model_path.to_path_buf(),
+            model_bytes: None,
+            commitment: commitments.sha256.clone(),
+            circuit_commitment: commitments.keccak256.clone(),
+            request: ProveRequest {
+                model_id: "demo-logistic".to_string(),
+                inputs: vec![0.5, 1.0, -0.5, 0.25],
+                output_disclosure: Some(disclosure),
+                ..Default::default()
+            },
+            sandbox: None,
+            checkpoints: None,
+            shards: 1,
+            witness_budget: None,
+            memory_budget: None,
+            quantization: None,
+            preprocessor: None,
+            strict: false,
+            gpu: false,
+            backend: None,
+            inference_engine: Default::default(),
+            cancellation: None,
+        };
+        let prover = create_prover().unwrap();
+        let full = job(OutputDisclosure::Full).run(&*prover).await.unwrap();
+        let hidden = job(OutputDisclosure::HashOnly).run(&*prover).await.unwrap();
+
+        assert!(hidden.public_inputs.output.is_empty());
+        assert_eq!(hidden.output_hash, full.output_hash);
+        let proof = deserialize_proof(&hidden.proof).unwrap();
+        assert!(proof.outputs.is_empty());
+        assert!(!deserialize_proof(&full.proof).unwrap().outputs.is_empty());
+        assert_eq!(proof.output_hash, full.output_hash);
+        assert!(prover.verify(&proof).unwrap().valid);
+    }
+}
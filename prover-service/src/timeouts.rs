@@ -0,0 +1,129 @@
+//! Request timeouts
+//!
+//! Every route has a time budget for producing its response, so a slow
+//! backend cannot hold connections open until the listener runs out:
+//! `PROVE_TIMEOUT_SECS` for proving and for the routes that take large
+//! uploads (models, batches, registry restores), `VERIFY_TIMEOUT_SECS` for
+//! verification, `HEALTH_TIMEOUT_SECS` for the health and readiness probes,
+//! and `REQUEST_TIMEOUT_SECS` for everything else, job long-polls included.
+//! A request over its budget is abandoned and answered with 504
+//! `REQUEST_TIMEOUT`, which clients can tell apart from the 503s of open
+//! breakers, paused intake and load shedding. The budget covers the response
+//! head only: a streamed body (`/prove/stream`) keeps flowing once it has
+//! started.
+//!
+//! Abandoning a `/prove` request drops its handler: the quota reservation is
+//! returned, a proving worker process is killed, and a job on a proving
+//! thread is cancelled, stopping before its next stage or as soon as its
+//! proving binary is killed (see `isolation`). The abandoned proof is neither
+//! issued, charged nor metered.
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Duration;
+
+use crate::config::ServiceConfig;
+use crate::types::api_error;
+
+/// Time budgets by route
+#[derive(Clone, Copy)]
+pub struct RequestTimeouts {
+    default: Duration,
+    prove: Duration,
+    verify: Duration,
+    health: Duration,
+}
+
+impl RequestTimeouts {
+    pub fn from_config(config: &ServiceConfig) -> Self {
+        Self {
+            default: Duration::from_secs(config.request_timeout_secs),
+            prove: Duration::from_secs(config.prove_timeout_secs),
+            verify: Duration::from_secs(config.verify_timeout_secs),
+            health: Duration::from_secs(config.health_timeout_secs),
+        }
+    }
+
+    /// Budget of `method` `path`
+    fn budget_for(&self, method: &Method, path: &str) -> Duration {
+        let path = path.trim_end_matches('/');
+        match path {
+            "/health" | "/ready" => return self.health,
+            "/verify" | "/verify/aggregate" | "/verify/proof" => return self.verify,
+            _ => {}
+        }
+        if method != Method::POST {
+            return self.default;
+        }
+        match path {
+            "/prove" | "/prove/stream" | "/models" | "/batches" | "/admin/restore" => self.prove,
+            _ if path.starts_with("/replay/") => self.prove,
+            _ => self.default,
+        }
+    }
+}
+
+/// Middleware abandoning requests that run over their route's budget
+pub async fn enforce(
+    State(timeouts): State<RequestTimeouts>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let budget = timeouts.budget_for(request.method(), request.uri().path());
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(budget, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("{} timed out after {:?}", path, budget);
+            api_error(
+                StatusCode::GATEWAY_TIMEOUT,
+                "REQUEST_TIMEOUT",
+                format!(
+                    "Request exceeded the {}s budget of this route",
+                    budget.as_secs()
+                ),
+            )
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budgets_by_route() {
+        let timeouts = RequestTimeouts {
+            default: Duration::from_secs(120),
+            prove: Duration::from_secs(600),
+            verify: Duration::from_secs(30),
+            health: Duration::from_secs(5),
+        };
+        assert_eq!(timeouts.budget_for(&Method::POST, "/prove"), timeouts.prove);
+        assert_eq!(
+            timeouts.budget_for(&Method::POST, "/replay/proof:sha256:ab"),
+            timeouts.prove
+        );
+        assert_eq!(
+            timeouts.budget_for(&Method::POST, "/verify/"),
+            timeouts.verify
+        );
+        assert_eq!(
+            timeouts.budget_for(&Method::GET, "/health"),
+            timeouts.health
+        );
+        assert_eq!(
+            timeouts.budget_for(&Method::GET, "/models"),
+            timeouts.default
+        );
+        assert_eq!(
+            timeouts.budget_for(&Method::GET, "/jobs/j1"),
+            timeouts.default
+        );
+    }
+}
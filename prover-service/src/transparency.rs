@@ -0,0 +1,701 @@
+//! Transparency log of issued proofs
+//!
+//! Every proof the service issues is appended to a public, append-only log
+//! in the style of Certificate Transparency (RFC 6962), kept in
+//! `transparency_log.jsonl` in the data directory. An entry records the
+//! proof's content-addressed id (see `proofs`), its public inputs and the
+//! time it was logged, and carries the hash of the entry before it, like the
+//! registry audit log. Log timestamps never go backwards, so a proof whose
+//! public inputs claim a time well before it was logged has been backdated.
+//!
+//! The entries are the leaves of an RFC 6962 Merkle tree over SHA-256
+//! (`0x00` leaf / `0x01` node prefixes). The service signs tree heads with
+//! its signing key (see `signing`), and serves the proofs monitors need to
+//! hold it to them without trusting it:
+//!
+//! - `GET /log/tree-head`: the signed size and root hash of the tree;
+//! - `GET /log/entries?start=&end=`: entries `start..end`, at most
+//!   `MAX_ENTRIES` at a time;
+//! - `GET /log/inclusion?proof_id=&tree_size=`: audit path of a proof's
+//!   entry in the tree of `tree_size` entries (default: the current tree);
+//! - `GET /log/consistency?first=&second=`: proof that the tree of `first`
+//!   entries is a prefix of the tree of `second` entries.
+//!
+//! A monitor that has seen two signed tree heads the consistency proof
+//! cannot reconcile has caught the service equivocating. Prove responses
+//! carry the `log_index` of their entry and a `log_inclusion`: the entry's
+//! audit path in the tree ending at it, with that tree's signed head, so a
+//! client can hand downstream verifiers the ZK proof and the ledger proof
+//! together. Later heads are tied to it with `GET /log/consistency`.
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::signing::{tree_head_message, KeyRing, ServiceSignature};
+use crate::types::{api_error, ApiError};
+use crate::verification::ct_eq;
+use crate::AppState;
+
+type Hash = [u8; 32];
+
+/// Hash preceding the first entry
+const GENESIS_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Most entries served by one `GET /log/entries`
+const MAX_ENTRIES: u64 = 1000;
+
+/// One entry of the transparency log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub index: u64,
+    /// When the proof was logged (unix seconds)
+    pub timestamp: u64,
+    /// Content-addressed id of the proof
+    pub proof_id: String,
+    pub public_inputs: Value,
+    pub prev_hash: String,
+    /// Merkle leaf hash of the entry, over all fields above
+    pub leaf_hash: String,
+}
+
+impl LogEntry {
+    fn compute_leaf_hash(&self) -> Result<Hash> {
+        let unhashed = LogEntry {
+            leaf_hash: String::new(),
+            ..self.clone()
+        };
+        Ok(hash_leaf(&serde_json::to_vec(&unhashed)?))
+    }
+}
+
+/// Signed size and root of the log's Merkle tree
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    pub timestamp: u64,
+    pub root_hash: String,
+    /// Service signature over `signing::tree_head_message`
+    pub signature: Option<ServiceSignature>,
+}
+
+/// Audit path of an entry
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    pub leaf_hash: String,
+    pub audit_path: Vec<String>,
+}
+
+/// An entry's audit path together with the signed head of its tree
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogInclusion {
+    pub tree_head: TreeHead,
+    pub inclusion: InclusionProof,
+}
+
+/// Proof that one tree is a prefix of another
+#[derive(Clone, Debug, Serialize)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    pub proof: Vec<String>,
+}
+
+/// Leaf hashes and index of the entries logged so far
+struct Head {
+    leaves: Vec<Hash>,
+    /// Proof id -> index of its first entry
+    indices: HashMap<String, u64>,
+    last_timestamp: u64,
+}
+
+/// Append-only, hash-chained log of issued proofs
+pub struct TransparencyLog {
+    path: PathBuf,
+    head: Mutex<Head>,
+}
+
+impl TransparencyLog {
+    /// Open the log, verifying the existing chain
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("transparency_log.jsonl");
+        let entries = read_entries(&path)?;
+        let leaves = verify_chain(&entries)?;
+        tracing::info!(
+            "Transparency log {} verified ({} entries)",
+            path.display(),
+            entries.len()
+        );
+
+        let mut indices = HashMap::new();
+        for entry in &entries {
+            indices.entry(entry.proof_id.clone()).or_insert(entry.index);
+        }
+        let head = Head {
+            leaves,
+            indices,
+            last_timestamp: entries.last().map_or(0, |entry| entry.timestamp),
+        };
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+        })
+    }
+
+    /// Log an issued proof, returning its entry
+    pub fn append(&self, proof_id: &str, public_inputs: Value) -> Result<LogEntry> {
+        let mut head = self.head.lock().unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut entry = LogEntry {
+            index: head.leaves.len() as u64,
+            timestamp: now.max(head.last_timestamp),
+            proof_id: proof_id.to_string(),
+            public_inputs,
+            prev_hash: head.leaves.last().map_or(GENESIS_HASH.to_string(), to_hex),
+            leaf_hash: String::new(),
+        };
+        let leaf = entry.compute_leaf_hash()?;
+        entry.leaf_hash = to_hex(&leaf);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.sync_data()?;
+
+        head.leaves.push(leaf);
+        head.indices
+            .entry(entry.proof_id.clone())
+            .or_insert(entry.index);
+        head.last_timestamp = entry.timestamp;
+        Ok(entry)
+    }
+
+    /// Number of entries
+    pub fn size(&self) -> u64 {
+        self.head.lock().unwrap().leaves.len() as u64
+    }
+
+    /// Root hash of the tree of the first `size` entries
+    pub fn root(&self, size: u64) -> Result<Hash> {
+        Ok(tree_hash(&self.leaves(size)?))
+    }
+
+    /// Audit path of `proof_id`'s entry in the tree of `size` entries
+    pub fn inclusion(&self, proof_id: &str, size: u64) -> Result<Option<InclusionProof>> {
+        let index = self.head.lock().unwrap().indices.get(proof_id).copied();
+        match index {
+            Some(index) if index < size => self.inclusion_at(index, size).map(Some),
+            // Still fails for a tree larger than the log
+            _ => self.leaves(size).map(|_| None),
+        }
+    }
+
+    /// `entry`'s audit path in the tree ending at it, with that tree's head
+    /// signed by `keys`
+    pub fn entry_inclusion(&self, entry: &LogEntry, keys: &KeyRing) -> Result<LogInclusion> {
+        let size = entry.index + 1;
+        Ok(LogInclusion {
+            tree_head: self.signed_head(size, keys)?,
+            inclusion: self.inclusion_at(entry.index, size)?,
+        })
+    }
+
+    /// Head of the tree of `size` entries, signed by `keys` if it can
+    pub fn signed_head(&self, size: u64, keys: &KeyRing) -> Result<TreeHead> {
+        let root_hash = to_hex(&self.root(size)?);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signature = keys
+            .sign(&tree_head_message(size, timestamp, &root_hash))
+            .map_err(|e| tracing::error!("Failed to sign tree head: {}", e))
+            .ok();
+        Ok(TreeHead {
+            tree_size: size,
+            timestamp,
+            root_hash,
+            signature,
+        })
+    }
+
+    fn inclusion_at(&self, index: u64, size: u64) -> Result<InclusionProof> {
+        let leaves = self.leaves(size)?;
+        Ok(InclusionProof {
+            leaf_index: index,
+            tree_size: size,
+            leaf_hash: to_hex(&leaves[index as usize]),
+            audit_path: inclusion_path(index as usize, &leaves)
+                .iter()
+                .map(to_hex)
+                .collect(),
+        })
+    }
+
+    /// Proof that the tree of `first` entries is a prefix of that of `second`
+    pub fn consistency(&self, first: u64, second: u64) -> Result<ConsistencyProof> {
+        if first > second {
+            return Err(anyhow!(
+                "First tree size {} is larger than second tree size {}",
+                first,
+                second
+            ));
+        }
+        let leaves = self.leaves(second)?;
+        let proof = match first {
+            0 => Vec::new(),
+            first => consistency_path(first as usize, &leaves, true),
+        };
+        Ok(ConsistencyProof {
+            first,
+            second,
+            proof: proof.iter().map(to_hex).collect(),
+        })
+    }
+
+    /// Entries `start..end`
+    pub fn entries(&self, start: u64, end: u64) -> Result<Vec<LogEntry>> {
+        let _head = self.head.lock().unwrap();
+        Ok(read_entries(&self.path)?
+            .into_iter()
+            .skip(start as usize)
+            .take(end.saturating_sub(start) as usize)
+            .collect())
+    }
+
+    /// The log file as written, for backups
+    pub fn export(&self) -> Result<Vec<u8>> {
+        let _head = self.head.lock().unwrap();
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Leaf hashes of the first `size` entries
+    fn leaves(&self, size: u64) -> Result<Vec<Hash>> {
+        let head = self.head.lock().unwrap();
+        if size > head.leaves.len() as u64 {
+            return Err(anyhow!(
+                "Tree size {} is larger than the log ({} entries)",
+                size,
+                head.leaves.len()
+            ));
+        }
+        Ok(head.leaves[..size as usize].to_vec())
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<LogEntry>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Malformed transparency log entry on line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Check the chain, returning the leaf hashes
+fn verify_chain(entries: &[LogEntry]) -> Result<Vec<Hash>> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut last_timestamp = 0;
+    let mut leaves = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.index != i as u64 || !ct_eq(&entry.prev_hash, &prev_hash) {
+            return Err(anyhow!("Transparency log chain broken at entry {}", i));
+        }
+        if entry.timestamp < last_timestamp {
+            return Err(anyhow!("Transparency log entry {} is backdated", i));
+        }
+        let leaf = entry.compute_leaf_hash()?;
+        if !ct_eq(&to_hex(&leaf), &entry.leaf_hash) {
+            return Err(anyhow!("Transparency log entry {} has been modified", i));
+        }
+        prev_hash = entry.leaf_hash.clone();
+        last_timestamp = entry.timestamp;
+        leaves.push(leaf);
+    }
+    Ok(leaves)
+}
+
+/// Leaf hash: `sha256(0x00 || data)`
+fn hash_leaf(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Interior node hash: `sha256(0x01 || left || right)`
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Largest power of two smaller than `n` (`n > 1`)
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - (n - 1).leading_zeros() - 1)
+}
+
+/// Merkle tree hash of `leaves` (RFC 6962 MTH); sha256 of nothing if empty
+fn tree_hash(leaves: &[Hash]) -> Hash {
+    match leaves {
+        [] => Sha256::digest([]).into(),
+        [leaf] => *leaf,
+        _ => {
+            let k = split(leaves.len());
+            hash_node(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path of leaf `m` (RFC 6962 PATH)
+fn inclusion_path(m: usize, leaves: &[Hash]) -> Vec<Hash> {
+    if leaves.len() <= 1 {
+        return Vec::new();
+    }
+    let k = split(leaves.len());
+    let (mut path, sibling) = if m < k {
+        (inclusion_path(m, &leaves[..k]), tree_hash(&leaves[k..]))
+    } else {
+        (inclusion_path(m - k, &leaves[k..]), tree_hash(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// Consistency proof of the first `m` leaves (RFC 6962 SUBPROOF)
+fn consistency_path(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+    if m == leaves.len() {
+        return if complete {
+            Vec::new()
+        } else {
+            vec![tree_hash(leaves)]
+        };
+    }
+    let k = split(leaves.len());
+    let (mut path, sibling) = if m <= k {
+        (
+            consistency_path(m, &leaves[..k], complete),
+            tree_hash(&leaves[k..]),
+        )
+    } else {
+        (
+            consistency_path(m - k, &leaves[k..], false),
+            tree_hash(&leaves[..k]),
+        )
+    };
+    path.push(sibling);
+    path
+}
+
+fn to_hex(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+/// Routes mounted under `/log`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/tree-head", get(get_tree_head))
+        .route("/entries", get(get_entries))
+        .route("/inclusion", get(get_inclusion))
+        .route("/consistency", get(get_consistency))
+}
+
+fn log_error(e: anyhow::Error) -> ApiError {
+    api_error(StatusCode::BAD_REQUEST, "INVALID_LOG_RANGE", e.to_string())
+}
+
+/// Current tree head, signed with the active service key
+async fn get_tree_head(State(state): State<Arc<AppState>>) -> Result<Json<TreeHead>, ApiError> {
+    let log = &state.transparency;
+    log.signed_head(log.size(), &state.signing_keys)
+        .map(Json)
+        .map_err(log_error)
+}
+
+#[derive(Deserialize)]
+struct EntriesQuery {
+    start: u64,
+    end: Option<u64>,
+}
+
+/// Entries `start..end`, capped at `MAX_ENTRIES`
+async fn get_entries(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EntriesQuery>,
+) -> Result<Json<Vec<LogEntry>>, ApiError> {
+    let size = state.transparency.size();
+    let end = query
+        .end
+        .unwrap_or(size)
+        .min(size)
+        .min(query.start.saturating_add(MAX_ENTRIES));
+    state
+        .transparency
+        .entries(query.start, end)
+        .map(Json)
+        .map_err(|e| {
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "TRANSPARENCY_LOG_UNREADABLE",
+                e.to_string(),
+            )
+        })
+}
+
+#[derive(Deserialize)]
+struct InclusionQuery {
+    proof_id: String,
+    tree_size: Option<u64>,
+}
+
+/// Audit path of a proof's entry
+async fn get_inclusion(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<InclusionQuery>,
+) -> Result<Json<InclusionProof>, ApiError> {
+    let size = query.tree_size.unwrap_or_else(|| state.transparency.size());
+    state
+        .transparency
+        .inclusion(&query.proof_id, size)
+        .map_err(log_error)?
+        .map(Json)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::NOT_FOUND,
+                "PROOF_NOT_LOGGED",
+                format!(
+                    "Proof {} is not in the first {} log entries",
+                    query.proof_id, size
+                ),
+            )
+        })
+}
+
+#[derive(Deserialize)]
+struct ConsistencyQuery {
+    first: u64,
+    second: u64,
+}
+
+/// Proof that the log only grew between two tree sizes
+async fn get_consistency(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<ConsistencyProof>, ApiError> {
+    state
+        .transparency
+        .consistency(query.first, query.second)
+        .map(Json)
+        .map_err(log_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn from_hex(hex: &str) -> Hash {
+        hex::decode(hex.trim_start_matches("0x"))
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Root from an audit path (RFC 9162 2.1.3.2)
+    fn root_from_inclusion(index: u64, size: u64, leaf: Hash, path: &[Hash]) -> Option<Hash> {
+        if index >= size {
+            return None;
+        }
+        let (mut fn_, mut sn, mut r) = (index, size - 1, leaf);
+        for p in path {
+            if sn == 0 {
+                return None;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                r = hash_node(p, &r);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                r = hash_node(&r, p);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        (sn == 0).then_some(r)
+    }
+
+    /// Check a consistency proof (RFC 9162 2.1.4.2)
+    fn consistent(
+        first: u64,
+        second: u64,
+        first_root: Hash,
+        second_root: Hash,
+        proof: &[Hash],
+    ) -> bool {
+        if first == second {
+            return proof.is_empty() && first_root == second_root;
+        }
+        let mut path = proof.to_vec();
+        if first.is_power_of_two() {
+            path.insert(0, first_root);
+        }
+        let (mut fn_, mut sn) = (first - 1, second - 1);
+        while fn_ & 1 == 1 {
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        let Some((start, rest)) = path.split_first() else {
+            return false;
+        };
+        let (mut fr, mut sr) = (*start, *start);
+        for c in rest {
+            if sn == 0 {
+                return false;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                fr = hash_node(c, &fr);
+                sr = hash_node(c, &sr);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                sr = hash_node(&sr, c);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        fr == first_root && sr == second_root && sn == 0
+    }
+
+    #[test]
+    fn test_inclusion_and_consistency_proofs_check_out() {
+        let leaves: Vec<Hash> = (0..9u8).map(|i| hash_leaf(&[i])).collect();
+        for n in 1..=leaves.len() {
+            let tree = &leaves[..n];
+            let root = tree_hash(tree);
+            for (m, leaf) in tree.iter().enumerate() {
+                let path = inclusion_path(m, tree);
+                assert_eq!(
+                    root_from_inclusion(m as u64, n as u64, *leaf, &path),
+                    Some(root),
+                    "n={n} m={m}"
+                );
+                let proof = consistency_path(m + 1, tree, true);
+                let first = tree_hash(&tree[..m + 1]);
+                assert!(
+                    consistent(m as u64 + 1, n as u64, first, root, &proof),
+                    "n={n} m={m}"
+                );
+                assert!(!consistent(
+                    m as u64 + 1,
+                    n as u64,
+                    hash_leaf(b"x"),
+                    root,
+                    &proof
+                ));
+            }
+        }
+        let path = inclusion_path(1, &leaves[..4]);
+        assert_ne!(
+            root_from_inclusion(1, 4, hash_leaf(b"x"), &path),
+            Some(tree_hash(&leaves[..4]))
+        );
+    }
+
+    #[test]
+    fn test_log_survives_reopen_and_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = TransparencyLog::open(dir.path()).unwrap();
+        let first = log
+            .append("proof:sha256:aa", json!({ "timestamp": 1 }))
+            .unwrap();
+        let root = log.root(1).unwrap();
+
+        let log = TransparencyLog::open(dir.path()).unwrap();
+        let second = log
+            .append("proof:sha256:bb", json!({ "timestamp": 2 }))
+            .unwrap();
+        assert_eq!(second.index, 1);
+        assert_eq!(second.prev_hash, first.leaf_hash);
+        assert!(second.timestamp >= first.timestamp);
+
+        let inclusion = log.inclusion("proof:sha256:aa", 2).unwrap().unwrap();
+        let path: Vec<Hash> = inclusion.audit_path.iter().map(|h| from_hex(h)).collect();
+        assert_eq!(
+            root_from_inclusion(0, 2, from_hex(&first.leaf_hash), &path),
+            Some(log.root(2).unwrap())
+        );
+        assert!(log.inclusion("proof:sha256:bb", 1).unwrap().is_none());
+
+        // A prove response's inclusion proof is against the tree ending at
+        // its entry, whose head the service signs
+        let logged = log
+            .entry_inclusion(&second, &KeyRing::ephemeral(3600))
+            .unwrap();
+        let path: Vec<Hash> = logged
+            .inclusion
+            .audit_path
+            .iter()
+            .map(|h| from_hex(h))
+            .collect();
+        assert_eq!(
+            root_from_inclusion(1, 2, from_hex(&second.leaf_hash), &path),
+            Some(from_hex(&logged.tree_head.root_hash))
+        );
+        assert_eq!(logged.tree_head.tree_size, 2);
+        assert!(logged.tree_head.signature.is_some());
+        assert!(log.inclusion("proof:sha256:aa", 3).is_err());
+
+        let consistency = log.consistency(1, 2).unwrap();
+        let proof: Vec<Hash> = consistency.proof.iter().map(|h| from_hex(h)).collect();
+        assert!(consistent(1, 2, root, log.root(2).unwrap(), &proof));
+        assert!(log.consistency(2, 1).is_err());
+        assert_eq!(log.entries(1, 5).unwrap().len(), 1);
+
+        let path = dir.path().join("transparency_log.jsonl");
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"timestamp\":1}", "\"timestamp\":0}");
+        std::fs::write(&path, tampered).unwrap();
+        assert!(TransparencyLog::open(dir.path()).is_err());
+    }
+}
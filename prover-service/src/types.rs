@@ -1,43 +1,185 @@
 //! Type definitions for the prover service API
 
+use axum::{http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
 
+use crate::aliases::RolloutTag;
+use crate::breaker::BreakerState;
+use crate::commitments::InputCommitment;
+use crate::consensus::Consensus;
+use crate::encryption::EncryptedInputs;
+use crate::flags::FlagValues;
+use crate::jobs::JobStatus;
+use crate::manifest::ReproducibilityManifest;
+use crate::market_data::{MarketDataRecord, MarketDataRequest};
+use crate::metering::ModelUsage;
+use crate::onnx::TensorSchema;
+use crate::oracles::{OracleAttestation, OracleSignature};
+use crate::predicate::{ExpectedOutput, OutputPredicate};
+use crate::provenance::{ModelProvenance, ProvenanceRecord};
+use crate::quantization::Quantization;
+use crate::queue::ModelLimits;
+use crate::registrants::{Registrant, RegistrantSignature};
+use crate::sandbox::ModelExecution;
+use crate::signing::ServiceSignature;
+use crate::tenants::OutputDisclosure;
+use crate::transparency::LogInclusion;
+use crate::warmup::WarmupRun;
+
 /// Health check response
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
     pub prover: String,
+    pub flags: FlagValues,
+}
+
+/// A configured proving backend, as listed by `GET /capabilities`
+#[derive(Serialize, Clone, Debug)]
+pub struct BackendCapabilities {
+    /// Circuit version of the backend, which `ProveRequest::backend` selects
+    pub id: String,
+    /// Prover id stamped into its proofs
+    pub prover_id: String,
+    /// What the service uses it for: `default`, `experiment`, `consensus`
+    /// or `shadow`
+    pub roles: Vec<&'static str>,
+    /// Whether prove requests may select it
+    pub selectable: bool,
+    /// State of its circuit breaker (see `breaker`)
+    pub breaker: BreakerState,
+    /// Format of the proofs it produces, which `ProveRequest::proof_format`
+    /// selects
+    pub proof_format: String,
+    pub checkpoints: bool,
+    pub witness_streaming: bool,
+    pub gpu: bool,
+    pub sharding: bool,
 }
 
 /// Error response
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 pub struct ErrorResponse {
     pub error: String,
     pub code: String,
 }
 
+/// Error returned by handlers and extractors
+pub type ApiError = (StatusCode, Json<ErrorResponse>);
+
+/// Build an `ApiError` from a status, error code and message
+pub fn api_error(status: StatusCode, code: &str, error: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.into(),
+            code: code.to_string(),
+        }),
+    )
+}
+
 /// Request to generate a proof
-#[derive(Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ProveRequest {
-    /// Model identifier (registered model ID or path)
+    /// Model identifier (registered model ID or alias such as `name:stable`)
     pub model_id: String,
 
     /// Input features as a flat vector of f32 values
+    #[serde(default)]
     pub inputs: Vec<f32>,
 
-    /// Expected output (for commitment)
+    /// Input features sealed to the service encryption key, instead of `inputs`
+    #[serde(default)]
+    pub encrypted_inputs: Option<EncryptedInputs>,
+
+    /// Fetch the inputs from Coinbase market data instead of sending them
+    #[serde(default)]
+    pub market_data: Option<MarketDataRequest>,
+
+    /// Signature from a trusted oracle over the inputs
+    #[serde(default)]
+    pub oracle_signature: Option<OracleSignature>,
+
+    /// Id of an earlier input commitment (`POST /commitments`) the inputs open
+    #[serde(default)]
+    pub commitment_id: Option<String>,
+
+    /// Salt (hex) opening the input commitment
+    #[serde(default)]
+    pub salt: Option<String>,
+
+    /// Verifier-supplied randomness (hex, at least 16 bytes) to absorb into
+    /// the proof transcript
+    #[serde(default)]
+    pub challenge: Option<String>,
+
+    /// Nonce from `GET /challenge`, absorbed as the proof's challenge
+    /// instead of `challenge`; each nonce proves once, before it expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+
+    /// Prove only this predicate over the output, keeping the output hidden
+    #[serde(default)]
+    pub predicate: Option<OutputPredicate>,
+
+    /// Expected output; when set, the proof also shows the model output
+    /// equals it within `output_tolerance`
     pub expected_output: Option<Vec<f32>>,
 
+    /// Declared quantization tolerance for `expected_output` (per element)
+    #[serde(default)]
+    pub output_tolerance: f32,
+
+    /// Accept NaN and infinite inputs instead of rejecting them
+    #[serde(default)]
+    pub allow_non_finite: bool,
+
+    /// Raw data for the model's preprocessing plugin, instead of `inputs`
+    #[serde(default)]
+    pub raw: Option<serde_json::Value>,
+
+    /// Prove on every consensus backend and require them to agree (see
+    /// `consensus`)
+    #[serde(default)]
+    pub consensus: bool,
+
+    /// Backend to prove on, by id or prover id (see `GET /capabilities`);
+    /// the default backend when unset
+    #[serde(default)]
+    pub backend: Option<String>,
+
+    /// Proof format the proof must be in, such as `jolt-atlas-v2` (see
+    /// `GET /capabilities`)
+    #[serde(default)]
+    pub proof_format: Option<String>,
+
+    /// Scheme of the response's top-level commitment and hashes (see
+    /// `tenants`); the tenant default, or `sha256-v2`, when unset
+    #[serde(default)]
+    pub commitment_scheme: Option<CommitmentScheme>,
+
+    /// Whether the response discloses the output values or only their hash;
+    /// the tenant default, or `full`, when unset
+    #[serde(default)]
+    pub output_disclosure: Option<OutputDisclosure>,
+
     /// Optional: Input names for structured inputs
     pub input_names: Option<Vec<String>>,
 }
 
 /// Response from proof generation
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProveResponse {
     pub success: bool,
 
+    /// Id of the model that was proven (aliases are resolved)
+    pub model_id: String,
+
+    /// Content-addressed id of the proof, `proof:sha256:<hex>` (see `proofs`)
+    #[serde(default)]
+    pub proof_id: String,
+
     /// The ZK proof (base64 encoded)
     pub proof: String,
 
@@ -56,10 +198,172 @@ pub struct ProveResponse {
     /// Time taken to generate proof in milliseconds
     pub proving_time_ms: u64,
 
+    /// CPU time the proving job consumed, when it ran in a worker process
+    /// (`PROVE_ISOLATION=process`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+
+    /// Where the proving time went, stage by stage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StageTimings>,
+
+    /// Service signature over the proof and public inputs
+    pub signature: Option<ServiceSignature>,
+
+    /// Index of the proof's entry in the transparency log (see `transparency`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_index: Option<u64>,
+
+    /// Inclusion proof of the proof's log entry, with the signed tree head
+    /// it is against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_inclusion: Option<LogInclusion>,
+
+    /// Backends that failed before the one that produced the proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Fallback>,
+
+    /// What is needed to reproduce the computation (see `manifest`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<ReproducibilityManifest>,
+
     /// Error message if failed
     pub error: Option<String>,
 }
 
+/// Time spent in each stage of a proof, in milliseconds
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    /// Checking and preparing the inputs (decryption, market data,
+    /// preprocessing, oracle and commitment checks)
+    pub validation_ms: u64,
+    pub inference_ms: u64,
+    /// Witness generation, for backends that report it separately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_ms: Option<u64>,
+    /// Committing to the witness, for backends that report it separately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment_ms: Option<u64>,
+    /// Proof generation, less any witness and commitment time reported above
+    pub proof_ms: u64,
+    /// Encoding the proof and hashing the public inputs
+    pub serialization_ms: u64,
+}
+
+/// How a proof fell back from the preferred backend
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Fallback {
+    /// Backend that produced the proof, by id (see `GET /capabilities`)
+    pub backend: String,
+    /// Backends that failed first, in the order they were tried
+    pub failures: Vec<BackendFailure>,
+}
+
+/// A backend a proof could not be generated on
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BackendFailure {
+    pub backend: String,
+    pub error: String,
+}
+
+/// Hashing scheme behind a proof's commitments
+///
+/// Recorded alongside every proof so verifiers know how to recompute the
+/// model commitment and input/output hashes, and so schemes can be migrated
+/// without invalidating proofs issued under an older one. Proofs that predate
+/// the field are `sha256-v1`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CommitmentScheme {
+    /// SHA-256 over canonical ONNX bytes and little-endian f32 values
+    #[default]
+    #[serde(rename = "sha256-v1")]
+    Sha256V1,
+
+    /// SHA-256 over canonical ONNX bytes, with inputs and outputs hashed as
+    /// domain-separated, length-prefixed tensors of canonical floats (see
+    /// `hash_tensor`)
+    #[serde(rename = "sha256-v2")]
+    Sha256V2,
+
+    /// Keccak-256 over canonical ONNX bytes and little-endian canonical f32
+    /// values (see `canonical_f32`)
+    #[serde(rename = "keccak256-v1")]
+    Keccak256V1,
+
+    /// Poseidon over BN254 field elements
+    #[serde(rename = "poseidon-v1")]
+    PoseidonV1,
+
+    /// Keccak-256 Merkle tree over weight and value chunks
+    #[serde(rename = "merkle-keccak-v1")]
+    MerkleKeccakV1,
+}
+
+impl CommitmentScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommitmentScheme::Sha256V1 => "sha256-v1",
+            CommitmentScheme::Sha256V2 => "sha256-v2",
+            CommitmentScheme::Keccak256V1 => "keccak256-v1",
+            CommitmentScheme::PoseidonV1 => "poseidon-v1",
+            CommitmentScheme::MerkleKeccakV1 => "merkle-keccak-v1",
+        }
+    }
+
+    /// Whether this service can recompute commitments under the scheme
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            CommitmentScheme::Sha256V1 | CommitmentScheme::Sha256V2 | CommitmentScheme::Keccak256V1
+        )
+    }
+}
+
+impl std::fmt::Display for CommitmentScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Model, input and output commitments under a circuit-friendly hash
+///
+/// Issued next to the SHA-256 values so contracts and circuits can check
+/// them natively; the proof binds both sets together.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CircuitCommitments {
+    pub scheme: CommitmentScheme,
+    pub model_commitment: String,
+    pub input_hash: String,
+    pub output_hash: String,
+}
+
+impl CircuitCommitments {
+    /// Bytes binding these commitments into a proof
+    pub fn binding(&self) -> Vec<u8> {
+        [
+            self.scheme.as_str(),
+            &self.model_commitment,
+            &self.input_hash,
+            &self.output_hash,
+        ]
+        .join("\n")
+        .into_bytes()
+    }
+}
+
+/// One named output tensor of a multi-output model
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputTensor {
+    /// Output name declared by the model
+    pub name: String,
+
+    /// Tensor values
+    pub values: Vec<f32>,
+
+    /// `sha256-v2` hash of this tensor alone
+    pub hash: String,
+}
+
 /// Public inputs embedded in the proof
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PublicInputs {
@@ -72,11 +376,86 @@ pub struct PublicInputs {
     /// Output hash
     pub output_hash: String,
 
-    /// The actual inference output
+    /// The actual inference output; every output tensor, concatenated in
+    /// the model's output order, for models with several
     pub output: Vec<f32>,
 
+    /// Each output tensor of a model with several, by name and in the
+    /// model's output order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<OutputTensor>>,
+
     /// Timestamp
     pub timestamp: u64,
+
+    /// Scheme used to compute the commitment and hashes
+    #[serde(default)]
+    pub commitment_scheme: CommitmentScheme,
+
+    /// The same commitments under the circuit-friendly hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_commitments: Option<CircuitCommitments>,
+
+    /// Predicate proven over the hidden model output; `output` is then
+    /// `[1.0]` when it holds and `[0.0]` otherwise, and `circuit_commitments`
+    /// commit to the model composed with the predicate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<OutputPredicate>,
+
+    /// Verifier-supplied challenge absorbed into the proof transcript
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+
+    /// Whether the proof enforces `expected_output`
+    #[serde(default)]
+    pub expected_output_enforced: bool,
+
+    /// The enforced expected output check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_output: Option<ExpectedOutput>,
+
+    /// Market data the inputs were fetched from server-side
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_data: Option<MarketDataRecord>,
+
+    /// Fixed-point encoding the proven integers are decoded with, for
+    /// quantized models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+
+    /// Hash of the preprocessing plugin that produced the inputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+
+    /// Hash of the raw data the plugin was run over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_input_hash: Option<String>,
+
+    /// Hash of the circuit the proof was generated for; verification fails
+    /// if it differs from the proof's or from the verifier's own circuit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_hash: Option<String>,
+
+    /// Prover id of the backend that generated the proof, which verification
+    /// dispatches on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prover_id: Option<String>,
+
+    /// Oracle that signed the inputs, and the hash of the signed data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oracle: Option<OracleAttestation>,
+
+    /// Commitment the inputs were fixed by before proving
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_commitment: Option<InputCommitment>,
+
+    /// Side of an alias's traffic split that served the proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<RolloutTag>,
+
+    /// Backends that independently produced agreeing proofs (see `consensus`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<Consensus>,
 }
 
 /// Request to verify a proof
@@ -94,8 +473,20 @@ pub struct VerifyRequest {
     /// Output hash to verify against
     pub output_hash: String,
 
+    /// Scheme the expected commitment and hashes were computed with
+    #[serde(default)]
+    pub commitment_scheme: CommitmentScheme,
+
     /// Public inputs
     pub public_inputs: Option<PublicInputs>,
+
+    /// Challenge the proof must have absorbed, if the verifier issued one
+    #[serde(default)]
+    pub challenge: Option<String>,
+
+    /// Return a signed receipt of the verdict
+    #[serde(default)]
+    pub receipt: bool,
 }
 
 /// Response from proof verification
@@ -104,6 +495,123 @@ pub struct VerifyResponse {
     pub valid: bool,
     pub verification_time_ms: u64,
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<VerificationReceipt>,
+}
+
+/// Service-signed statement that this verifier returned `valid` for the
+/// proof `proof_id` at `verified_at`, so a client can show a third party
+/// the proof was accepted; the signature is over
+/// `signing::verification_receipt_message` and checks against `GET /keys`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerificationReceipt {
+    /// Content-addressed id of the verified proof (see `proofs`)
+    pub proof_id: String,
+    pub valid: bool,
+    /// Unix time of the verification
+    pub verified_at: u64,
+    pub signature: ServiceSignature,
+}
+
+/// Most proofs one `POST /verify/aggregate` call may check
+pub const MAX_AGGREGATE_INSTANCES: usize = 256;
+
+/// Request to verify a batch of proofs together
+#[derive(Deserialize)]
+pub struct AggregateVerifyRequest {
+    pub instances: Vec<VerifyRequest>,
+}
+
+/// Outcome of one proof in a batch
+#[derive(Serialize)]
+pub struct InstanceVerification {
+    pub index: usize,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response from verifying a batch of proofs; `valid` only when every
+/// instance is
+#[derive(Serialize)]
+pub struct AggregateVerifyResponse {
+    pub valid: bool,
+    pub verified: usize,
+    pub failed: usize,
+    pub verification_time_ms: u64,
+    pub instances: Vec<InstanceVerification>,
+}
+
+/// Request to verify a proof on its own, without expected values
+#[derive(Deserialize)]
+pub struct VerifyProofRequest {
+    /// The proof to verify (base64 encoded)
+    pub proof: String,
+
+    /// Challenge the proof must have absorbed, if the verifier issued one
+    #[serde(default)]
+    pub challenge: Option<String>,
+}
+
+/// Request to decode a proof without verifying it
+#[derive(Deserialize)]
+pub struct InspectProofRequest {
+    /// The proof to inspect (base64 encoded, or its decoded JSON)
+    pub proof: String,
+}
+
+/// Response from verifying a proof on its own
+#[derive(Serialize)]
+pub struct VerifyProofResponse {
+    pub valid: bool,
+    pub verification_time_ms: u64,
+    /// What the proof proves; only returned for valid proofs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_inputs: Option<EmbeddedPublicInputs>,
+}
+
+/// Public inputs read from a verified proof
+///
+/// Compare these against values you trust (e.g. the commitment of the model
+/// you expect, or a hash of the inputs you sent); they only say what the
+/// proof proves, not that it is the proof you wanted.
+#[derive(Serialize)]
+pub struct EmbeddedPublicInputs {
+    /// Model registered with this service the proof was made for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+
+    /// Registered commitment of that model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_commitment: Option<String>,
+
+    /// Commitment the proof was made against: the model commitment composed
+    /// with any quantization, expected output check and predicate
+    pub proven_commitment: String,
+
+    pub input_hash: String,
+    pub output_hash: String,
+    pub commitment_scheme: CommitmentScheme,
+    pub output: Vec<f32>,
+    pub timestamp: u64,
+    pub prover_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_commitments: Option<CircuitCommitments>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<OutputPredicate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_output: Option<ExpectedOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_hash: Option<String>,
 }
 
 /// Request to register a model
@@ -117,6 +625,66 @@ pub struct RegisterModelRequest {
 
     /// Optional description
     pub description: Option<String>,
+
+    /// Optional signed provenance record
+    pub provenance: Option<ProvenanceRecord>,
+
+    /// Optional signature over the decoded model bytes by the uploader
+    pub registrant: Option<RegistrantSignature>,
+
+    /// Where inference runs; `wasm` sandboxes untrusted models
+    #[serde(default)]
+    pub execution: ModelExecution,
+
+    /// Shards each proof is split into (default 1)
+    #[serde(default)]
+    pub shards: Option<u32>,
+
+    /// Keep the model warm for low first-proof latency
+    #[serde(default)]
+    pub hot: bool,
+
+    /// Caps on the model's concurrent proofs and requests per minute
+    #[serde(default)]
+    pub limits: ModelLimits,
+
+    /// Fixed-point encoding of the model's inputs and outputs, for quantized
+    /// models
+    #[serde(default)]
+    pub quantization: Option<Quantization>,
+
+    /// WASM preprocessing plugin (base64) turning `raw` prove data into the
+    /// feature vector
+    #[serde(default)]
+    pub preprocessor: Option<String>,
+
+    /// Warm-up inferences to run after registering (see `warmup`; default
+    /// `MODEL_WARMUP_INFERENCES`)
+    #[serde(default)]
+    pub warmup: Option<u32>,
+}
+
+impl RegisterModelRequest {
+    /// Decode the base64 model bytes
+    pub fn decode_model_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+        BASE64
+            .decode(&self.model_bytes)
+            .map_err(|e| anyhow::anyhow!("Invalid base64: {}", e))
+    }
+
+    /// Decode the base64 preprocessing plugin, if any
+    pub fn decode_preprocessor(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+        self.preprocessor
+            .as_ref()
+            .map(|module| {
+                BASE64
+                    .decode(module)
+                    .map_err(|e| anyhow::anyhow!("Invalid base64 preprocessor: {}", e))
+            })
+            .transpose()
+    }
 }
 
 /// Response from model registration
@@ -125,6 +693,16 @@ pub struct RegisterModelResponse {
     pub success: bool,
     pub model_id: String,
     pub commitment: String,
+    pub circuit_commitment: String,
+    /// Key id of the verified registrant, when the upload was signed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrant: Option<String>,
+    /// Commitment over the model and its provenance, when provenance was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_commitment: Option<String>,
+    pub execution: ModelExecution,
+    pub shards: u32,
+    pub hot: bool,
     pub error: Option<String>,
 }
 
@@ -133,6 +711,98 @@ pub struct RegisterModelResponse {
 pub struct ModelCommitmentResponse {
     pub model_id: String,
     pub commitment: String,
+    pub circuit_commitment: String,
+    pub weights_root: String,
+    /// Set while the model is archived (unix seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<u64>,
+    /// Fixed-point encoding of a quantized model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+    /// Hash of the model's preprocessing plugin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+    /// Circuit hash of the model's proofs without an expected output check
+    /// or predicate, under the current backend
+    pub circuit_hash: String,
+}
+
+/// Everything known about a model, for client tooling and dashboards
+#[derive(Serialize)]
+pub struct ModelDetailResponse {
+    pub model_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub commitment: String,
+    pub circuit_commitment: String,
+    pub weights_root: String,
+    /// As in `GET /models/:id/commitment`
+    pub circuit_hash: String,
+    /// Size of the registered model file
+    pub size_bytes: u64,
+    /// Declared graph inputs and outputs; absent when the file cannot be
+    /// parsed as ONNX
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<TensorSchema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<TensorSchema>>,
+    /// Default proving backend (see `GET /capabilities`)
+    pub backend: String,
+    pub execution: ModelExecution,
+    pub shards: u32,
+    pub hot: bool,
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<Quantization>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preprocessor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<u64>,
+    /// Status of the backend setup, once one was started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setup_status: Option<JobStatus>,
+    /// Progress of the warm-up run at registration, if one was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<WarmupRun>,
+    /// Models registered under the same name, oldest first
+    pub versions: Vec<ModelVersion>,
+    /// Aliases routing traffic to this model
+    pub aliases: Vec<String>,
+    pub usage: ModelUsage,
+}
+
+/// One registration of a model name
+#[derive(Serialize)]
+pub struct ModelVersion {
+    pub model_id: String,
+    pub commitment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<u64>,
+}
+
+/// Weight tensors of a model
+#[derive(Serialize)]
+pub struct ModelWeightsResponse {
+    pub model_id: String,
+    pub weights_root: String,
+    pub layers: Vec<String>,
+}
+
+/// Provenance of a model
+#[derive(Serialize)]
+pub struct ModelProvenanceResponse {
+    pub model_id: String,
+    pub commitment: String,
+    #[serde(flatten)]
+    pub provenance: ModelProvenance,
+}
+
+/// Query for a weight inclusion proof
+#[derive(Deserialize)]
+pub struct WeightProofQuery {
+    /// Weight tensor (initializer) name
+    pub layer: String,
 }
 
 /// Internal model info
@@ -140,15 +810,64 @@ pub struct ModelCommitmentResponse {
 pub struct ModelInfo {
     pub id: String,
     pub name: String,
+    pub description: Option<String>,
     pub commitment: String,
+    pub circuit_commitment: String,
+    /// Merkle root over the weight tensors (`merkle-keccak-v1`)
+    pub weights_root: String,
+    /// Verified provenance bound into an extended commitment
+    pub provenance: Option<ModelProvenance>,
+    /// Verified uploader signature
+    pub registrant: Option<Registrant>,
+    /// When the model was archived (unix seconds); archived models serve no
+    /// new proofs but stay available for verifying historical ones
+    pub archived_at: Option<u64>,
+    pub execution: ModelExecution,
+    /// Shards each proof is split into
+    pub shards: u32,
+    /// Kept warm (see `warm`)
+    pub hot: bool,
+    /// Exempt from archiving, purging and release of its warm state
+    pub pinned: bool,
+    /// Enforced by the proving queue (see `queue`)
+    pub limits: ModelLimits,
+    pub quantization: Option<Quantization>,
+    /// Hash of the preprocessing plugin, stored next to the model file
+    pub preprocessor: Option<String>,
     pub path: std::path::PathBuf,
 }
 
 /// Internal proof result
+#[derive(Serialize, Deserialize)]
 pub struct ProofResult {
     pub proof: String,
     pub model_commitment: String,
     pub input_hash: String,
     pub output_hash: String,
     pub public_inputs: PublicInputs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Fallback>,
+    /// CPU time the job consumed, when it ran in a worker process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+    /// Time spent in each stage of the job; validation happens before it
+    /// and is filled in by the caller
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StageTimings>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_inputs_without_scheme_default_to_sha256() {
+        let legacy = r#"{"model_commitment":"0xabc","input_hash":"0x1","output_hash":"0x2","output":[0.5],"timestamp":1}"#;
+        let inputs: PublicInputs = serde_json::from_str(legacy).unwrap();
+        assert_eq!(inputs.commitment_scheme, CommitmentScheme::Sha256V1);
+
+        let json = serde_json::to_value(&inputs).unwrap();
+        assert_eq!(json["commitment_scheme"], "sha256-v1");
+        assert!(serde_json::from_str::<CommitmentScheme>(r#""md5-v1""#).is_err());
+    }
 }
@@ -0,0 +1,443 @@
+//! Golden test vectors
+//!
+//! `trustless-agentkit-prover --export-vectors <dir>` writes canonical test
+//! vectors for third-party verifier implementations to test against:
+//!
+//! - `<model-id>/model.onnx` and `canonical.onnx`: each bundled demo model as
+//!   shipped and after canonicalization, which is what commitments cover;
+//! - `<model-id>/vector.json`: its example inputs and outputs (as numbers
+//!   and as little-endian `f32` hex), and the expected model commitment,
+//!   input hash and output hash under every supported scheme;
+//! - `<model-id>/proofs/<format>.json`: a proof in each format the backend
+//!   produces (`single`, `sharded`, `challenge`), base64 as the API returns
+//!   it, decoded, and with the public inputs `/verify` takes;
+//! - `floats.json`: hashes of edge-case floats (signed zeros, subnormals,
+//!   infinities, NaN payloads), which `sha256-v2` canonicalizes and the raw
+//!   schemes do not;
+//! - `manifest.json`: the SHA-256 of every file above.
+//!
+//! Proof timestamps are pinned to 0, so with a deterministic backend a
+//! re-export is byte-identical. The backends have no verification key
+//! separate from the proof: a verifier checks a proof against the model
+//! commitment and its public inputs. Each proof vector records instead the
+//! backend id and proof version a verifier must accept.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::demo::{self, DemoModel};
+use crate::jolt_atlas::{
+    compute_model_commitment, compute_model_commitment_keccak, create_prover, deserialize_proof,
+    hash_tensor, keccak_floats, serialize_proof, ModelCommitments, ZkmlProver,
+};
+use crate::onnx;
+use crate::prover::ProveJob;
+use crate::types::{CommitmentScheme, ProveRequest, PublicInputs};
+use crate::verification::{compute_input_hash, compute_output_hash};
+
+/// Command-line flag starting the binary as a test-vector exporter
+pub const EXPORT_VECTORS_ARG: &str = "--export-vectors";
+
+/// Version of the vector layout, bumped on incompatible changes
+const VECTORS_VERSION: u32 = 1;
+
+/// Challenge the `challenge` proof format absorbs
+const CHALLENGE: &str = "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+/// Shards the `sharded` proof format is split into
+const SHARDS: u32 = 2;
+
+/// Expected commitment and hashes under one scheme
+#[derive(Serialize)]
+pub struct SchemeVector {
+    pub model_commitment: String,
+    pub input_hash: String,
+    pub output_hash: String,
+}
+
+/// A proof in one format
+#[derive(Serialize)]
+pub struct ProofVector {
+    pub format: &'static str,
+    pub file: String,
+    pub prover_id: String,
+    pub version: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    pub shards: u32,
+}
+
+/// Test vector for one model
+#[derive(Serialize)]
+pub struct ModelVector {
+    pub model_id: &'static str,
+    pub description: &'static str,
+    /// Whether outputs came from ONNX inference or the mock fallback
+    pub inference: &'static str,
+    pub inputs: Vec<f32>,
+    pub inputs_hex: String,
+    pub outputs: Vec<f32>,
+    pub outputs_hex: String,
+    pub schemes: BTreeMap<&'static str, SchemeVector>,
+    pub proofs: Vec<ProofVector>,
+}
+
+/// Hashes of one edge-case tensor
+#[derive(Serialize)]
+pub struct FloatVector {
+    pub name: &'static str,
+    pub values_hex: String,
+    pub sha256_v1: String,
+    pub sha256_v2: String,
+    pub keccak256_v1: String,
+}
+
+/// Edge-case float vectors, hashed as the input of a model
+#[derive(Serialize)]
+pub struct FloatVectors {
+    pub model_commitment: String,
+    pub tensor: &'static str,
+    pub cases: Vec<FloatVector>,
+}
+
+/// Index of an exported vector directory
+#[derive(Serialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub service_version: &'static str,
+    pub prover_id: String,
+    pub schemes: Vec<&'static str>,
+    /// SHA-256 of every file, by path relative to the directory
+    pub files: BTreeMap<String, String>,
+}
+
+/// A proof file as written
+#[derive(Serialize)]
+struct ProofFile<'a> {
+    proof: &'a str,
+    public_inputs: &'a PublicInputs,
+    decoded: serde_json::Value,
+}
+
+/// Little-endian `f32` bytes, hex-encoded
+fn floats_hex(values: &[f32]) -> String {
+    hex::encode(
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    )
+}
+
+/// Writes files under the export directory, recording their hashes
+struct Writer<'a> {
+    dir: &'a Path,
+    files: BTreeMap<String, String>,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, path: &str, bytes: &[u8]) -> Result<()> {
+        let full = self.dir.join(path);
+        if let Some(parent) = full.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&full, bytes).with_context(|| format!("Failed to write {:?}", full))?;
+        self.files
+            .insert(path.to_string(), hex::encode(Sha256::digest(bytes)));
+        Ok(())
+    }
+
+    fn write_json<T: Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let mut json = serde_json::to_vec_pretty(value)?;
+        json.push(b'\n');
+        self.write(path, &json)
+    }
+}
+
+/// Expected commitment and hashes of an inference under every scheme
+fn scheme_vectors(
+    canonical: &[u8],
+    inputs: &[f32],
+    outputs: &[f32],
+) -> BTreeMap<&'static str, SchemeVector> {
+    let commitments = ModelCommitments::compute(canonical);
+    let mut schemes = BTreeMap::new();
+    schemes.insert(
+        CommitmentScheme::Sha256V1.as_str(),
+        SchemeVector {
+            model_commitment: compute_model_commitment(canonical),
+            input_hash: compute_input_hash(inputs),
+            output_hash: compute_output_hash(outputs),
+        },
+    );
+    schemes.insert(
+        CommitmentScheme::Sha256V2.as_str(),
+        SchemeVector {
+            model_commitment: commitments.sha256.clone(),
+            input_hash: commitments.input_hash(inputs),
+            output_hash: commitments.output_hash(outputs),
+        },
+    );
+    schemes.insert(
+        CommitmentScheme::Keccak256V1.as_str(),
+        SchemeVector {
+            model_commitment: compute_model_commitment_keccak(canonical),
+            input_hash: keccak_floats(inputs),
+            output_hash: keccak_floats(outputs),
+        },
+    );
+    schemes
+}
+
+/// Prove `model` in one format, with the timestamp pinned
+async fn prove(
+    prover: &dyn ZkmlProver,
+    model_path: &Path,
+    canonical: &[u8],
+    model: &DemoModel,
+    challenge: Option<&str>,
+    shards: u32,
+) -> Result<(String, PublicInputs)> {
+    let commitments = ModelCommitments::compute(canonical);
+    let job = ProveJob {
+        model_path: model_path.to_path_buf(),
+        model_bytes: None,
+        commitment: commitments.sha256,
+        circuit_commitment: commitments.keccak256,
+        request: ProveRequest {
+            model_id: model.id.to_string(),
+            inputs: model.example_inputs.to_vec(),
+            challenge: challenge.map(String::from),
+            ..Default::default()
+        },
+        sandbox: None,
+        checkpoints: None,
+        shards,
+        witness_budget: None,
+        memory_budget: None,
+        quantization: None,
+        preprocessor: None,
+        strict: false,
+        gpu: false,
+        backend: None,
+        inference_engine: Default::default(),
+        cancellation: None,
+    };
+    let mut result = job.run(prover).await?;
+
+    let mut proof = deserialize_proof(&result.proof)?;
+    proof.timestamp = 0;
+    result.public_inputs.timestamp = 0;
+    let verified = prover.verify(&proof)?;
+    if !verified.valid {
+        return Err(anyhow!(
+            "{} proof of {} does not verify: {:?}",
+            prover.prover_id(),
+            model.id,
+            verified.error
+        ));
+    }
+    Ok((serialize_proof(&proof)?, result.public_inputs))
+}
+
+/// Export the vectors of one demo model
+async fn export_model(
+    writer: &mut Writer<'_>,
+    prover: &dyn ZkmlProver,
+    model: &DemoModel,
+) -> Result<()> {
+    let canonical = onnx::canonicalize(model.bytes())?;
+    writer.write(&format!("{}/model.onnx", model.id), model.bytes())?;
+    writer.write(&format!("{}/canonical.onnx", model.id), &canonical)?;
+    let model_path = writer.dir.join(model.id).join("model.onnx");
+
+    let mut formats = vec![("single", None, 1)];
+    if prover.supports_sharding() {
+        formats.push(("sharded", None, SHARDS));
+    }
+    formats.push(("challenge", Some(CHALLENGE), 1));
+
+    let mut outputs = Vec::new();
+    let mut proofs = Vec::new();
+    for (format, challenge, shards) in formats {
+        let (proof, public_inputs) =
+            prove(prover, &model_path, &canonical, model, challenge, shards).await?;
+        let decoded = deserialize_proof(&proof)?;
+        let file = format!("proofs/{}.json", format);
+        writer.write_json(
+            &format!("{}/{}", model.id, file),
+            &ProofFile {
+                proof: &proof,
+                public_inputs: &public_inputs,
+                decoded: serde_json::to_value(&decoded)?,
+            },
+        )?;
+        outputs = public_inputs.output;
+        proofs.push(ProofVector {
+            format,
+            file,
+            prover_id: decoded.prover_id,
+            version: decoded.version,
+            challenge: decoded.challenge,
+            shards,
+        });
+    }
+
+    let vector = ModelVector {
+        model_id: model.id,
+        description: model.description,
+        inference: if cfg!(feature = "ort") {
+            "onnx"
+        } else {
+            "mock"
+        },
+        inputs: model.example_inputs.to_vec(),
+        inputs_hex: floats_hex(model.example_inputs),
+        outputs_hex: floats_hex(&outputs),
+        schemes: scheme_vectors(&canonical, model.example_inputs, &outputs),
+        outputs,
+        proofs,
+    };
+    writer.write_json(&format!("{}/vector.json", model.id), &vector)
+}
+
+/// Hashes of edge-case floats as an input of the model committed to by
+/// `model_commitment`
+fn float_vectors(model_commitment: String) -> FloatVectors {
+    let cases: [(&'static str, Vec<f32>); 5] = [
+        ("signed-zeros", vec![0.0, -0.0]),
+        (
+            "subnormals",
+            vec![f32::from_bits(1), -f32::from_bits(0x007f_ffff)],
+        ),
+        ("extremes", vec![f32::MAX, f32::MIN, f32::MIN_POSITIVE]),
+        ("infinities", vec![f32::INFINITY, f32::NEG_INFINITY]),
+        (
+            "nans",
+            vec![f32::from_bits(0x7fc0_0000), f32::from_bits(0xffc0_0001)],
+        ),
+    ];
+    let tensor = "input";
+    FloatVectors {
+        cases: cases
+            .into_iter()
+            .map(|(name, values)| FloatVector {
+                name,
+                values_hex: floats_hex(&values),
+                sha256_v1: compute_input_hash(&values),
+                sha256_v2: hash_tensor(&model_commitment, tensor, &[values.len()], &values),
+                keccak256_v1: keccak_floats(&values),
+            })
+            .collect(),
+        model_commitment,
+        tensor,
+    }
+}
+
+/// Export every vector into `dir`
+pub async fn export(dir: &Path) -> Result<Manifest> {
+    let prover = create_prover()?;
+    let mut writer = Writer {
+        dir,
+        files: BTreeMap::new(),
+    };
+    for model in demo::MODELS {
+        export_model(&mut writer, prover.as_ref(), model).await?;
+    }
+    let first = &demo::MODELS[0];
+    writer.write_json(
+        "floats.json",
+        &float_vectors(compute_model_commitment(&onnx::canonicalize(
+            first.bytes(),
+        )?)),
+    )?;
+
+    let manifest = Manifest {
+        version: VECTORS_VERSION,
+        service_version: env!("CARGO_PKG_VERSION"),
+        prover_id: prover.prover_id().to_string(),
+        schemes: vec![
+            CommitmentScheme::Sha256V1.as_str(),
+            CommitmentScheme::Sha256V2.as_str(),
+            CommitmentScheme::Keccak256V1.as_str(),
+        ],
+        files: writer.files,
+    };
+    let mut json = serde_json::to_vec_pretty(&manifest)?;
+    json.push(b'\n');
+    std::fs::write(dir.join("manifest.json"), json)?;
+    Ok(manifest)
+}
+
+/// Entry point of `--export-vectors <dir>`; returns the exit code
+pub async fn export_main(mut args: impl Iterator<Item = String>) -> i32 {
+    let Some(dir) = args.next() else {
+        eprintln!("usage: {} <dir>", EXPORT_VECTORS_ARG);
+        return 2;
+    };
+    match export(Path::new(&dir)).await {
+        Ok(manifest) => {
+            println!(
+                "Wrote {} test vector files to {} ({})",
+                manifest.files.len() + 1,
+                dir,
+                manifest.prover_id
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Test vector export failed: {:#}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_export_is_reproducible_and_consistent() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = export(dir.path()).await.unwrap();
+        for (path, hash) in &manifest.files {
+            let bytes = std::fs::read(dir.path().join(path)).unwrap();
+            assert_eq!(&hex::encode(Sha256::digest(&bytes)), hash, "{}", path);
+        }
+
+        // Proof public inputs carry the sha256-v2 hashes of the vector
+        let vector: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(dir.path().join("demo-logistic/vector.json")).unwrap(),
+        )
+        .unwrap();
+        let proof: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(dir.path().join("demo-logistic/proofs/single.json")).unwrap(),
+        )
+        .unwrap();
+        let v2 = &vector["schemes"]["sha256-v2"];
+        assert_eq!(proof["public_inputs"]["input_hash"], v2["input_hash"]);
+        assert_eq!(proof["public_inputs"]["output_hash"], v2["output_hash"]);
+        assert_eq!(
+            proof["decoded"]["circuit_commitments"]["input_hash"],
+            vector["schemes"]["keccak256-v1"]["input_hash"]
+        );
+
+        let again = tempfile::tempdir().unwrap();
+        assert_eq!(export(again.path()).await.unwrap().files, manifest.files);
+    }
+
+    #[test]
+    fn test_only_sha256_v2_canonicalizes_floats() {
+        let vectors = float_vectors("0xabc".to_string());
+        let zeros = &vectors.cases[0];
+        assert_eq!(zeros.values_hex, "0000000000000080");
+        assert_eq!(
+            zeros.sha256_v2,
+            hash_tensor("0xabc", "input", &[2], &[0.0, 0.0])
+        );
+        assert_ne!(zeros.sha256_v1, compute_input_hash(&[0.0, 0.0]));
+    }
+}
@@ -3,7 +3,23 @@
 //! This module provides utilities for proof verification that can be
 //! used both in the service and compiled to WASM for client-side verification.
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::jolt_atlas::{is_mock_prover, JoltAtlasProof, ProofData};
+use crate::proofs::proof_id;
+use crate::prover::normalize_challenge;
+
+/// Compare two hashes or commitments in constant time
+///
+/// Every hash and commitment equality check goes through here, so the time
+/// a comparison takes does not reveal how long a prefix of a forged value
+/// matched. Only the lengths are compared in variable time.
+pub fn ct_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
 
 /// Verify proof commitments without full proof verification
 ///
@@ -21,12 +37,13 @@ pub fn verify_commitments(
     expected_input_hash: &str,
     expected_output_hash: &str,
 ) -> bool {
-    model_commitment == expected_model_commitment
-        && input_hash == expected_input_hash
-        && output_hash == expected_output_hash
+    // Non-short-circuiting, so a mismatch does not reveal which value differed
+    ct_eq(model_commitment, expected_model_commitment)
+        & ct_eq(input_hash, expected_input_hash)
+        & ct_eq(output_hash, expected_output_hash)
 }
 
-/// Compute input hash from feature vector
+/// Compute input hash from feature vector (`sha256-v1`)
 pub fn compute_input_hash(inputs: &[f32]) -> String {
     let mut hasher = Sha256::new();
     for input in inputs {
@@ -35,7 +52,7 @@ pub fn compute_input_hash(inputs: &[f32]) -> String {
     format!("0x{}", hex::encode(hasher.finalize()))
 }
 
-/// Compute output hash from inference result
+/// Compute output hash from inference result (`sha256-v1`)
 pub fn compute_output_hash(outputs: &[f32]) -> String {
     let mut hasher = Sha256::new();
     for output in outputs {
@@ -45,6 +62,9 @@ pub fn compute_output_hash(outputs: &[f32]) -> String {
 }
 
 /// Verify that a proof contains valid structure (without cryptographic verification)
+///
+/// Accepts proofs in the current format and the legacy layout, which named
+/// the prover `prover` and carried no proof data.
 pub fn verify_proof_structure(proof_bytes: &[u8]) -> Result<ProofMetadata, String> {
     #[derive(serde::Deserialize)]
     struct MockProof {
@@ -57,10 +77,28 @@ pub fn verify_proof_structure(proof_bytes: &[u8]) -> Result<ProofMetadata, Strin
         timestamp: u64,
     }
 
-    let proof: MockProof =
+    let value: serde_json::Value =
         serde_json::from_slice(proof_bytes).map_err(|e| format!("Invalid proof format: {}", e))?;
+    if value.get("proof_data").is_some() {
+        let proof: JoltAtlasProof =
+            serde_json::from_value(value).map_err(|e| format!("Invalid proof format: {}", e))?;
+        return Ok(ProofMetadata {
+            format: ProofFormat::JoltAtlas,
+            version: proof.version,
+            prover: proof.prover_id,
+            model_commitment: proof.model_commitment,
+            input_hash: proof.input_hash,
+            output_hash: proof.output_hash,
+            outputs: proof.outputs,
+            timestamp: proof.timestamp,
+        });
+    }
+
+    let proof: MockProof =
+        serde_json::from_value(value).map_err(|e| format!("Invalid proof format: {}", e))?;
 
     Ok(ProofMetadata {
+        format: ProofFormat::Legacy,
         version: proof.version,
         prover: proof.prover,
         model_commitment: proof.model_commitment,
@@ -74,18 +112,261 @@ pub fn verify_proof_structure(proof_bytes: &[u8]) -> Result<ProofMetadata, Strin
 /// Metadata extracted from a proof
 #[derive(Debug, Clone)]
 pub struct ProofMetadata {
+    pub format: ProofFormat,
     pub version: u8,
     pub prover: String,
     pub model_commitment: String,
     pub input_hash: String,
     pub output_hash: String,
     pub outputs: Vec<f32>,
+    #[allow(dead_code)]
     pub timestamp: u64,
 }
 
+/// Layout of a decoded proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProofFormat {
+    /// `JoltAtlasProof`: public inputs and SNARK proof data
+    JoltAtlas,
+    /// Public inputs only, with the prover named `prover`
+    Legacy,
+    /// Not a proof this service can decode
+    Unknown,
+}
+
+/// Proof versions produced by the backends (1 = mock, 2 = real)
+const KNOWN_VERSIONS: [u8; 2] = [1, 2];
+
+/// Size of a list component of a proof
+#[derive(Debug, Default, Serialize)]
+pub struct ComponentSize {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl ComponentSize {
+    fn of(items: &[String]) -> Self {
+        Self {
+            count: items.len(),
+            bytes: items.iter().map(String::len).sum(),
+        }
+    }
+}
+
+/// Sizes of a proof and its components, in bytes
+#[derive(Debug, Default, Serialize)]
+pub struct ProofSizes {
+    /// The proof as submitted
+    pub encoded: usize,
+    /// The proof once base64-decoded
+    pub decoded: usize,
+    /// Public inputs: everything but the proof data, as JSON
+    pub public_inputs: usize,
+    pub commitments: ComponentSize,
+    pub sumcheck_proof: usize,
+    pub lookup_proof: usize,
+    pub opening_proofs: ComponentSize,
+}
+
+/// What a proof decodes to, without cryptographic verification
+#[derive(Debug, Serialize)]
+pub struct ProofInspection {
+    /// Whether the proof decodes and every component has the expected shape
+    pub well_formed: bool,
+    pub format: ProofFormat,
+    /// `base64` as returned by the service, or `json` if sent undecoded
+    pub encoding: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u8>,
+    /// `mock` or `real`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prover_id: Option<String>,
+    /// Content-addressed id of the proof (see `proofs`)
+    pub proof_id: String,
+    /// Every field of the proof other than its proof data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_inputs: Option<serde_json::Value>,
+    pub sizes: ProofSizes,
+    /// Why the proof is not well-formed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    /// Plain-language summary, when asked for (see `inspect::explain`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+/// Bytes of a proof sent base64 encoded, as returned by the service, or as
+/// its decoded JSON, with the encoding it was sent in
+fn proof_bytes(proof: &str) -> (Vec<u8>, &'static str) {
+    match BASE64.decode(proof.trim()) {
+        Ok(bytes) => (bytes, "base64"),
+        Err(_) => (proof.as_bytes().to_vec(), "json"),
+    }
+}
+
+/// Decode a proof in the current format from either encoding
+pub fn decode_proof(proof: &str) -> Option<JoltAtlasProof> {
+    serde_json::from_slice(&proof_bytes(proof).0).ok()
+}
+
+/// Decode a proof of any supported format and check its structure
+pub fn inspect_proof(proof: &str) -> ProofInspection {
+    let (bytes, encoding) = proof_bytes(proof);
+    let mut inspection = ProofInspection {
+        well_formed: false,
+        format: ProofFormat::Unknown,
+        encoding,
+        version: None,
+        backend: None,
+        prover_id: None,
+        proof_id: proof_id(proof.trim()),
+        public_inputs: None,
+        sizes: ProofSizes {
+            encoded: proof.len(),
+            decoded: bytes.len(),
+            ..Default::default()
+        },
+        problems: Vec::new(),
+        explanation: None,
+    };
+    let metadata = match verify_proof_structure(&bytes) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            inspection.problems.push(e);
+            return inspection;
+        }
+    };
+    inspection.format = metadata.format;
+    inspection.version = Some(metadata.version);
+    inspection.backend = Some(if is_mock_prover(&metadata.prover) {
+        "mock"
+    } else {
+        "real"
+    });
+    inspection.prover_id = Some(metadata.prover.clone());
+
+    let mut public_inputs: serde_json::Value =
+        serde_json::from_slice(&bytes).expect("decoded by verify_proof_structure");
+    let proof_data = public_inputs
+        .as_object_mut()
+        .and_then(|fields| fields.remove("proof_data"));
+    inspection.sizes.public_inputs = public_inputs.to_string().len();
+    inspection.public_inputs = Some(public_inputs);
+
+    let problems = &mut inspection.problems;
+    if !KNOWN_VERSIONS.contains(&metadata.version) {
+        problems.push(format!("Unknown proof version {}", metadata.version));
+    }
+    for (name, hash) in [
+        ("model_commitment", &metadata.model_commitment),
+        ("input_hash", &metadata.input_hash),
+        ("output_hash", &metadata.output_hash),
+    ] {
+        if !is_hash(hash) {
+            problems.push(format!("{} is not a 0x-prefixed 32-byte hex hash", name));
+        }
+    }
+    if metadata.outputs.is_empty() {
+        problems.push("Proof has no outputs".to_string());
+    }
+
+    if let Some(proof_data) = proof_data {
+        let proof: JoltAtlasProof =
+            serde_json::from_slice(&bytes).expect("decoded by verify_proof_structure");
+        let data: ProofData = serde_json::from_value(proof_data).expect("part of the proof");
+        inspection.sizes.commitments = ComponentSize::of(&data.commitments);
+        inspection.sizes.sumcheck_proof = data.sumcheck_proof.len();
+        inspection.sizes.lookup_proof = data.lookup_proof.len();
+        inspection.sizes.opening_proofs = ComponentSize::of(&data.opening_proofs);
+        check_jolt_atlas(&proof, problems);
+    }
+    inspection.well_formed = inspection.problems.is_empty();
+    inspection
+}
+
+/// Structural checks of a proof in the current format
+fn check_jolt_atlas(proof: &JoltAtlasProof, problems: &mut Vec<String>) {
+    if proof
+        .circuit_hash
+        .as_deref()
+        .is_some_and(|hash| !is_hash(hash))
+    {
+        problems.push("circuit_hash is not a 0x-prefixed 32-byte hex hash".to_string());
+    }
+    if !proof.commitment_scheme.is_supported() {
+        problems.push(format!(
+            "Unsupported commitment scheme {}",
+            proof.commitment_scheme
+        ));
+    }
+    if let Some(circuit) = &proof.circuit_commitments {
+        if !circuit.scheme.is_supported() {
+            problems.push(format!(
+                "Unsupported circuit commitment scheme {}",
+                circuit.scheme
+            ));
+        }
+        for hash in [
+            &circuit.model_commitment,
+            &circuit.input_hash,
+            &circuit.output_hash,
+        ] {
+            if !is_hash(hash) {
+                problems.push("Circuit commitments are not 0x-prefixed hex hashes".to_string());
+                break;
+            }
+        }
+    }
+    if let Some(challenge) = &proof.challenge {
+        if normalize_challenge(challenge).ok().as_ref() != Some(challenge) {
+            problems.push("Challenge is not a normalized hex challenge".to_string());
+        }
+    }
+
+    let data = &proof.proof_data;
+    // Two commitments to the trace, then one per shard of a sharded proof
+    if data.commitments.len() < 2 {
+        problems.push("Proof data has fewer than two commitments".to_string());
+    }
+    if data.sumcheck_proof.is_empty() || data.lookup_proof.is_empty() {
+        problems.push("Proof data is missing its sumcheck or lookup proof".to_string());
+    }
+    if data.opening_proofs.is_empty() {
+        problems.push("Proof data has no opening proofs".to_string());
+    }
+    // The mock backend hex-encodes every component
+    if is_mock_prover(&proof.prover_id) {
+        let components = data
+            .commitments
+            .iter()
+            .chain(&data.opening_proofs)
+            .chain([&data.sumcheck_proof, &data.lookup_proof]);
+        if !components.into_iter().all(|c| is_hex(c)) {
+            problems.push("Mock proof data is not hex encoded".to_string());
+        }
+    }
+}
+
+/// Whether `value` is a 0x-prefixed 32-byte hex hash
+fn is_hash(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .is_some_and(|digest| digest.len() == 64 && is_hex(digest))
+}
+
+fn is_hex(value: &str) -> bool {
+    !value.is_empty()
+        && value.len().is_multiple_of(2)
+        && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jolt_atlas::{create_prover, serialize_proof, ModelCommitments};
 
     #[test]
     fn test_input_hash_deterministic() {
@@ -104,6 +385,14 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq("0xabc", "0xabc"));
+        assert!(!ct_eq("0xabc", "0xabd"));
+        assert!(!ct_eq("0xabc", "0xabcd"));
+        assert!(ct_eq("", ""));
+    }
+
     #[test]
     fn test_verify_commitments() {
         assert!(verify_commitments(
@@ -124,4 +413,57 @@ mod tests {
             "0x456"
         ));
     }
+
+    #[test]
+    fn test_inspect_proof_reports_structure() {
+        let model = ModelCommitments::compute(b"model");
+        let proof = create_prover()
+            .unwrap()
+            .prove(&model, &[1.0, 2.0], &[0.5], None)
+            .unwrap();
+        let encoded = serialize_proof(&proof).unwrap();
+
+        let inspection = inspect_proof(&encoded);
+        assert!(inspection.well_formed, "{:?}", inspection.problems);
+        assert_eq!(inspection.format, ProofFormat::JoltAtlas);
+        assert_eq!(
+            (inspection.encoding, inspection.backend),
+            ("base64", Some("mock"))
+        );
+        assert_eq!(inspection.proof_id, proof_id(&encoded));
+        assert_eq!(inspection.sizes.commitments.count, 2);
+        let public_inputs = inspection.public_inputs.unwrap();
+        assert_eq!(public_inputs["input_hash"], model.input_hash(&[1.0, 2.0]));
+        assert!(public_inputs.get("proof_data").is_none());
+
+        // Undecoded JSON is accepted; a tampered component is reported
+        let mut tampered = proof.clone();
+        tampered.proof_data.opening_proofs.clear();
+        tampered.input_hash = "0x12".to_string();
+        let inspection = inspect_proof(&serde_json::to_string(&tampered).unwrap());
+        assert_eq!(inspection.encoding, "json");
+        assert!(!inspection.well_formed);
+        assert_eq!(inspection.problems.len(), 2);
+
+        let inspection = inspect_proof("not a proof");
+        assert_eq!(inspection.format, ProofFormat::Unknown);
+        assert!(!inspection.well_formed);
+    }
+
+    #[test]
+    fn test_legacy_proof_structure() {
+        let legacy = serde_json::json!({
+            "version": 1,
+            "prover": "jolt-atlas-mock-v1",
+            "model_commitment": format!("0x{}", "ab".repeat(32)),
+            "input_hash": format!("0x{}", "cd".repeat(32)),
+            "output_hash": format!("0x{}", "ef".repeat(32)),
+            "outputs": [0.25],
+            "timestamp": 1,
+        });
+        let inspection = inspect_proof(&BASE64.encode(legacy.to_string()));
+        assert!(inspection.well_formed, "{:?}", inspection.problems);
+        assert_eq!(inspection.format, ProofFormat::Legacy);
+        assert_eq!(inspection.sizes.commitments.count, 0);
+    }
 }
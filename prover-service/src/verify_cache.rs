@@ -0,0 +1,142 @@
+//! Verification result cache
+//!
+//! With `VERIFY_CACHE_TTL_SECS` set, `POST /verify` results are cached,
+//! keyed by a hash of the proof and the values it is checked against. When
+//! several services independently re-check the same proof, only the first
+//! check pays for verification; the rest are answered from memory (marked
+//! `X-Verify-Cache: hit`). Whether mock proofs are accepted is part of the
+//! key, so flipping strict mode never serves a stale verdict. Requests with
+//! `Cache-Control: no-cache` always verify the proof again.
+//!
+//! Only verdicts are cached: a request that fails to verify (a malformed
+//! proof, a scheme mismatch) is re-checked every time.
+
+use axum::http::{header::CACHE_CONTROL, HeaderMap};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::ServiceConfig;
+use crate::types::VerifyRequest;
+
+/// Response header reporting whether a verdict came from the cache
+pub const CACHE_STATUS_HEADER: &str = "x-verify-cache";
+
+/// Cache of verification verdicts
+pub struct VerifyCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, (Instant, bool)>>,
+}
+
+impl VerifyCache {
+    /// The configured cache, or `None` when caching is disabled
+    pub fn from_config(config: &ServiceConfig) -> Option<Self> {
+        config
+            .verify_cache_ttl_secs
+            .map(|ttl| Self::new(Duration::from_secs(ttl), config.verify_cache_max_entries))
+    }
+
+    /// Cache holding at most `max_entries` verdicts for `ttl`
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the caller asked for the proof to be verified again
+    pub fn bypassed(headers: &HeaderMap) -> bool {
+        headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+    }
+
+    /// Cache key of `request`, checked with mock proofs refused if `strict`
+    pub fn key(request: &VerifyRequest, strict: bool) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            request.proof.as_str(),
+            request.model_commitment.as_str(),
+            request.input_hash.as_str(),
+            request.output_hash.as_str(),
+            request.challenge.as_deref().unwrap_or_default(),
+        ] {
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.update(serde_json::to_vec(&request.commitment_scheme).unwrap_or_default());
+        hasher.update(serde_json::to_vec(&request.public_inputs).unwrap_or_default());
+        hasher.update([strict as u8]);
+        hex::encode(hasher.finalize())
+    }
+
+    /// Cached verdict for `key`
+    pub fn get(&self, key: &str) -> Option<bool> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, valid)| *valid)
+    }
+
+    /// Cache `valid` under `key`, evicting the oldest verdict when full
+    pub fn put(&self, key: String, valid: bool) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, (expires, _)| *expires > now);
+        }
+        if entries.len() >= self.max_entries {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (expires, _))| *expires)
+                .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (now + self.ttl, valid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(proof: &str) -> VerifyRequest {
+        serde_json::from_value(serde_json::json!({
+            "proof": proof,
+            "model_commitment": "0xaa",
+            "input_hash": "0xbb",
+            "output_hash": "0xcc"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verdicts_are_keyed_by_request_and_mode() {
+        let key = VerifyCache::key(&request("p1"), true);
+        assert_eq!(key, VerifyCache::key(&request("p1"), true));
+        assert_ne!(key, VerifyCache::key(&request("p2"), true));
+        assert_ne!(key, VerifyCache::key(&request("p1"), false));
+
+        let cache = VerifyCache::new(Duration::from_secs(60), 1);
+        assert_eq!(cache.get(&key), None);
+        cache.put(key.clone(), true);
+        assert_eq!(cache.get(&key), Some(true));
+        cache.put("other".to_string(), false);
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(cache.get("other"), Some(false));
+
+        let mut headers = HeaderMap::new();
+        assert!(!VerifyCache::bypassed(&headers));
+        headers.insert(CACHE_CONTROL, "max-age=0, No-Cache".parse().unwrap());
+        assert!(VerifyCache::bypassed(&headers));
+    }
+}
@@ -0,0 +1,80 @@
+//! Warm pools for hot models
+//!
+//! Operators pin latency-sensitive models as hot, either when registering
+//! them (`"hot": true`), later with `PUT /admin/models/:id/hot`, or by name
+//! in `HOT_MODELS` so that a model re-registered after a deploy is warmed
+//! the moment it is registered. Warming loads the model file into memory and
+//! runs the backend's per-model proving preprocessing, and the result stays
+//! resident until the model is unpinned or purged, so the first `/prove`
+//! pays no more than steady-state ones. A model pinned with
+//! `POST /admin/models/:id/pin` keeps its warm state: it cannot be taken off
+//! hot until `POST /admin/models/:id/unpin`. Under `process` isolation the
+//! worker still reads the model file itself; preprocessing is shared only
+//! by in-process proving.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::jolt_atlas::{ModelCommitments, ZkmlProver};
+
+/// Resident state of a hot model
+pub struct WarmModel {
+    /// The model file, kept in memory
+    pub bytes: Arc<Vec<u8>>,
+    pub stats: WarmStats,
+}
+
+/// When and how quickly a model was warmed
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct WarmStats {
+    pub warmed_at: u64,
+    pub warm_ms: u64,
+    pub resident_bytes: u64,
+}
+
+impl WarmModel {
+    /// Load the model at `path` and run `prover`'s preprocessing for it
+    pub fn load(path: &Path, model: &ModelCommitments, prover: &dyn ZkmlProver) -> Result<Self> {
+        let started = Instant::now();
+        let bytes = std::fs::read(path)?;
+        prover.preprocess(model)?;
+        Ok(Self {
+            stats: WarmStats {
+                warmed_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                warm_ms: started.elapsed().as_millis() as u64,
+                resident_bytes: bytes.len() as u64,
+            },
+            bytes: Arc::new(bytes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jolt_atlas::create_prover;
+
+    #[test]
+    fn test_warm_model_keeps_file_resident() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+        std::fs::write(&path, b"onnx bytes").unwrap();
+        let model = ModelCommitments::compute(b"onnx bytes");
+
+        let prover = create_prover().unwrap();
+        let warm = WarmModel::load(&path, &model, prover.as_ref()).unwrap();
+        assert_eq!(warm.bytes.as_slice(), b"onnx bytes");
+        assert_eq!(warm.stats.resident_bytes, 10);
+
+        // Later changes to the file do not affect the resident copy
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(warm.bytes.as_slice(), b"onnx bytes");
+        assert!(WarmModel::load(&path, &model, prover.as_ref()).is_err());
+    }
+}
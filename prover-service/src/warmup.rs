@@ -0,0 +1,206 @@
+//! Warm-up inferences at registration
+//!
+//! A model registered with `"warmup": n` (or, by default, with
+//! `MODEL_WARMUP_INFERENCES` set) is warmed up in the background once the
+//! registration has returned: the backend's proving setup runs (recorded as
+//! if started with `POST /models/:id/setup`), then `n` inferences over an
+//! all-zero input of the model's declared shape. Loading the model, the
+//! inference engine's session initialization and the backend preprocessing
+//! are thereby paid before the first real `/prove`. Progress is reported as
+//! `warmup` in `GET /models/:id`.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::jobs::JobStatus;
+use crate::onnx::{self, Dimension};
+use crate::setup;
+use crate::AppState;
+
+/// Most warm-up inferences a registration may ask for
+pub const MAX_WARMUP_INFERENCES: u32 = 100;
+
+/// Warm-up of one model, as reported in its detail
+#[derive(Clone, Debug, Serialize)]
+pub struct WarmupRun {
+    pub status: JobStatus,
+    /// Inferences requested
+    pub inferences: u32,
+    /// Inferences run so far
+    pub completed: u32,
+    pub requested_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// Duration of the first inference, the one paying for the cold start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_inference_ms: Option<u64>,
+    /// Duration of the last inference, close to steady state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_inference_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup_time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Warm-up runs by model id
+#[derive(Default)]
+pub struct WarmupTracker {
+    runs: Mutex<HashMap<String, WarmupRun>>,
+}
+
+impl WarmupTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, model_id: &str) -> Option<WarmupRun> {
+        self.runs.lock().unwrap().get(model_id).cloned()
+    }
+
+    fn queue(&self, model_id: &str, inferences: u32) {
+        self.runs.lock().unwrap().insert(
+            model_id.to_string(),
+            WarmupRun {
+                status: JobStatus::Queued,
+                inferences,
+                completed: 0,
+                requested_at: now_secs(),
+                finished_at: None,
+                first_inference_ms: None,
+                last_inference_ms: None,
+                warmup_time_ms: None,
+                error: None,
+            },
+        );
+    }
+
+    fn update(&self, model_id: &str, f: impl FnOnce(&mut WarmupRun)) {
+        if let Some(run) = self.runs.lock().unwrap().get_mut(model_id) {
+            f(run);
+        }
+    }
+
+    /// Forget the warm-up of a purged model
+    pub fn remove(&self, model_id: &str) {
+        self.runs.lock().unwrap().remove(model_id);
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// All-zero input for the model in `model_bytes`: its first declared input
+/// with open dimensions taken as 1, or a single feature if it declares none
+pub fn warmup_inputs(model_bytes: &[u8]) -> Vec<f32> {
+    let features = onnx::interface(model_bytes)
+        .ok()
+        .and_then(|(inputs, _)| inputs.into_iter().next())
+        .map(|input| {
+            input
+                .shape
+                .iter()
+                .map(|dim| match dim {
+                    Dimension::Fixed(size) if *size > 0 => *size as usize,
+                    _ => 1,
+                })
+                .product()
+        })
+        .unwrap_or(1);
+    vec![0.0; features]
+}
+
+/// Warm up `model_id` with `inferences` inferences in the background
+pub fn start(state: &Arc<AppState>, model_id: &str, inferences: u32) {
+    state.warmups.queue(model_id, inferences);
+    tracing::info!(
+        "Queued warm-up of model {} ({} inferences)",
+        model_id,
+        inferences
+    );
+    tokio::spawn(run(state.clone(), model_id.to_string(), inferences));
+}
+
+async fn run(state: Arc<AppState>, model_id: String, inferences: u32) {
+    state
+        .warmups
+        .update(&model_id, |run| run.status = JobStatus::Running);
+    let started = Instant::now();
+    let result = warm_up(&state, &model_id, inferences).await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(()) => tracing::info!("Warm-up of model {} took {}ms", model_id, elapsed_ms),
+        Err(e) => tracing::warn!("Warm-up of model {} failed: {}", model_id, e),
+    }
+    state.warmups.update(&model_id, |run| {
+        run.finished_at = Some(now_secs());
+        run.warmup_time_ms = Some(elapsed_ms);
+        match result {
+            Ok(()) => run.status = JobStatus::Succeeded,
+            Err(e) => {
+                run.status = JobStatus::Failed;
+                run.error = Some(e.to_string());
+            }
+        }
+    });
+}
+
+async fn warm_up(state: &Arc<AppState>, model_id: &str, inferences: u32) -> Result<()> {
+    setup::ensure_setup(state, model_id).await?;
+
+    let path = {
+        let prover = state.prover.read().await;
+        prover
+            .get_model(model_id)
+            .ok_or_else(|| anyhow!("Model not found: {}", model_id))?
+            .path
+            .clone()
+    };
+    let inputs = warmup_inputs(&tokio::fs::read(&path).await?);
+    for _ in 0..inferences {
+        let started = Instant::now();
+        state.prover.read().await.infer(model_id, &inputs).await?;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        state.warmups.update(model_id, |run| {
+            run.completed += 1;
+            run.first_inference_ms.get_or_insert(elapsed_ms);
+            run.last_inference_ms = Some(elapsed_ms);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warmup_progress_is_tracked() {
+        let tracker = WarmupTracker::new();
+        assert!(tracker.get("m").is_none());
+        tracker.queue("m", 3);
+        tracker.update("m", |run| {
+            run.completed += 1;
+            run.first_inference_ms.get_or_insert(40);
+        });
+        tracker.update("m", |run| {
+            run.completed += 1;
+            run.first_inference_ms.get_or_insert(5);
+        });
+        let run = tracker.get("m").unwrap();
+        assert_eq!(run.status, JobStatus::Queued);
+        assert_eq!((run.inferences, run.completed), (3, 2));
+        assert_eq!(run.first_inference_ms, Some(40));
+
+        tracker.remove("m");
+        assert!(tracker.get("m").is_none());
+        assert_eq!(warmup_inputs(b"not onnx"), vec![0.0]);
+    }
+}
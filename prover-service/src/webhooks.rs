@@ -0,0 +1,362 @@
+//! Outbound webhooks
+//!
+//! Background jobs report their results by POSTing a JSON event to a
+//! caller-supplied endpoint. Every delivery is signed with the endpoint's
+//! secret so receivers can authenticate it:
+//!
+//! ```text
+//! X-Webhook-Id: whd_...
+//! X-Webhook-Signature: t=<unix seconds>,v1=<hex HMAC-SHA256(secret, "<t>.<body>")>
+//! X-Request-Id: req_...
+//! ```
+//!
+//! The request id (see `request_id`) names the work that produced the event
+//! and is repeated as `request_id` in the payload.
+//!
+//! Deliveries failing with a network error, 408, 429 or 5xx are retried with
+//! exponential backoff, up to `WEBHOOK_MAX_ATTEMPTS` attempts. Deliveries
+//! that still fail, or that the endpoint rejects with another status, are
+//! parked in the dead-letter store (`webhook_dead_letters.json`), listed at
+//! `GET /admin/webhooks/dead-letters`, where they can be retried or dropped.
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::persist::{load_json, save_json};
+use crate::request_id::{self, RequestId};
+
+/// Timeout for a single delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry; doubled for each further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Dead letters kept before the oldest are dropped
+const MAX_DEAD_LETTERS: usize = 1000;
+
+/// Header carrying the delivery id (stable across retries)
+pub const ID_HEADER: &str = "X-Webhook-Id";
+
+/// Header carrying the payload signature
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Event posted to a webhook
+#[derive(Serialize)]
+pub struct WebhookEvent<T: Serialize> {
+    /// Event type, e.g. `proof.completed`
+    pub event: &'static str,
+    pub timestamp: u64,
+    /// Id of the request the event was produced for
+    pub request_id: String,
+    pub data: T,
+}
+
+/// A webhook URL and the secret its deliveries are signed with
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    pub secret: String,
+}
+
+impl WebhookEndpoint {
+    /// Validate `url` and pair it with `secret`, generating one when unset
+    pub fn new(url: &str, secret: Option<String>) -> Result<Self, String> {
+        validate_url(url)?;
+        let secret = match secret {
+            Some(secret) if secret.len() < 16 => {
+                return Err("Webhook secret must be at least 16 characters".to_string())
+            }
+            Some(secret) => secret,
+            None => generate_secret(),
+        };
+        Ok(Self {
+            url: url.to_string(),
+            secret,
+        })
+    }
+}
+
+/// Random endpoint secret
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+/// Signature header value for `body` sent at `timestamp`
+pub fn signature(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    format!("t={},v1={}", timestamp, hmac_sha256_hex(secret, &message))
+}
+
+fn hmac_sha256_hex(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a webhook URL is absolute http(s)
+pub fn validate_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        _ => Err(format!("Invalid webhook URL {}", url)),
+    }
+}
+
+/// A delivery that exhausted its attempts
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: String,
+    pub endpoint: WebhookEndpoint,
+    pub event: String,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: u64,
+}
+
+/// Dead letter as listed by the admin API (without the endpoint secret)
+#[derive(Serialize)]
+pub struct DeadLetterSummary {
+    pub id: String,
+    pub url: String,
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: u64,
+}
+
+impl From<&DeadLetter> for DeadLetterSummary {
+    fn from(letter: &DeadLetter) -> Self {
+        Self {
+            id: letter.id.clone(),
+            url: letter.endpoint.url.clone(),
+            event: letter.event.clone(),
+            request_id: letter.request_id.clone(),
+            payload: letter.payload.clone(),
+            attempts: letter.attempts,
+            last_error: letter.last_error.clone(),
+            failed_at: letter.failed_at,
+        }
+    }
+}
+
+/// Persistent dead-letter queue
+struct DeadLetterStore {
+    path: PathBuf,
+    letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl DeadLetterStore {
+    fn push(&self, letter: DeadLetter) {
+        let mut letters = self.letters.lock().unwrap();
+        letters.push(letter);
+        let excess = letters.len().saturating_sub(MAX_DEAD_LETTERS);
+        letters.drain(..excess);
+        if let Err(e) = save_json(&self.path, &*letters) {
+            tracing::error!("Failed to persist webhook dead letters: {}", e);
+        }
+    }
+
+    fn take(&self, id: &str) -> Result<Option<DeadLetter>> {
+        let mut letters = self.letters.lock().unwrap();
+        let Some(index) = letters.iter().position(|l| l.id == id) else {
+            return Ok(None);
+        };
+        let letter = letters.remove(index);
+        save_json(&self.path, &*letters)?;
+        Ok(Some(letter))
+    }
+}
+
+/// One event on its way to an endpoint
+struct Delivery {
+    id: String,
+    endpoint: WebhookEndpoint,
+    event: String,
+    request_id: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Delivers webhook events
+#[derive(Clone)]
+pub struct WebhookSender {
+    http: reqwest::Client,
+    max_attempts: u32,
+    dead_letters: Arc<DeadLetterStore>,
+}
+
+impl WebhookSender {
+    pub fn open(data_dir: &Path, max_attempts: u32) -> Result<Self> {
+        let path = data_dir.join("webhook_dead_letters.json");
+        let letters = load_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()?,
+            max_attempts: max_attempts.max(1),
+            dead_letters: Arc::new(DeadLetterStore {
+                path,
+                letters: Mutex::new(letters),
+            }),
+        })
+    }
+
+    /// Queue `data` as an `event` of request `request_id` for `endpoint`,
+    /// delivering in the background
+    pub fn send<T: Serialize>(
+        &self,
+        endpoint: &WebhookEndpoint,
+        event: &'static str,
+        request_id: &RequestId,
+        data: T,
+    ) {
+        let body = WebhookEvent {
+            event,
+            timestamp: now_secs(),
+            request_id: request_id.to_string(),
+            data,
+        };
+        let delivery = Delivery {
+            id: format!("whd_{}", uuid::Uuid::new_v4().simple()),
+            endpoint: endpoint.clone(),
+            event: event.to_string(),
+            request_id: Some(request_id.to_string()),
+            body: serde_json::to_vec(&body).expect("webhook events serialize"),
+        };
+        tokio::spawn(self.clone().deliver(delivery));
+    }
+
+    /// Dead letters, oldest first
+    pub fn dead_letters(&self) -> Vec<DeadLetterSummary> {
+        let letters = self.dead_letters.letters.lock().unwrap();
+        letters.iter().map(DeadLetterSummary::from).collect()
+    }
+
+    /// Remove a dead letter, returning whether it existed
+    pub fn drop_dead_letter(&self, id: &str) -> Result<bool> {
+        Ok(self.dead_letters.take(id)?.is_some())
+    }
+
+    /// Move a dead letter back to delivery, returning whether it existed
+    pub fn retry_dead_letter(&self, id: &str) -> Result<bool> {
+        let Some(letter) = self.dead_letters.take(id)? else {
+            return Ok(false);
+        };
+        let delivery = Delivery {
+            id: letter.id,
+            endpoint: letter.endpoint,
+            event: letter.event,
+            request_id: letter.request_id,
+            body: serde_json::to_vec(&letter.payload)?,
+        };
+        tokio::spawn(self.clone().deliver(delivery));
+        Ok(true)
+    }
+
+    async fn deliver(self, delivery: Delivery) {
+        let mut attempts = 0;
+        let last_error = loop {
+            attempts += 1;
+            let (error, retryable) = match self.attempt(&delivery).await {
+                Ok(()) => return,
+                Err(failure) => failure,
+            };
+            if !retryable || attempts >= self.max_attempts {
+                break error;
+            }
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempts - 1)).await;
+        };
+
+        tracing::warn!(
+            "Webhook {} to {} dead-lettered after {} attempt(s): {}",
+            delivery.event,
+            delivery.endpoint.url,
+            attempts,
+            last_error
+        );
+        self.dead_letters.push(DeadLetter {
+            id: delivery.id,
+            endpoint: delivery.endpoint,
+            event: delivery.event,
+            request_id: delivery.request_id,
+            payload: serde_json::from_slice(&delivery.body).unwrap_or_default(),
+            attempts,
+            last_error,
+            failed_at: now_secs(),
+        });
+    }
+
+    /// POST once, returning the error and whether it is worth retrying
+    async fn attempt(&self, delivery: &Delivery) -> Result<(), (String, bool)> {
+        let mut request = self
+            .http
+            .post(&delivery.endpoint.url)
+            .header("Content-Type", "application/json")
+            .header(ID_HEADER, &delivery.id)
+            .header(
+                SIGNATURE_HEADER,
+                signature(&delivery.endpoint.secret, now_secs(), &delivery.body),
+            );
+        if let Some(request_id) = &delivery.request_id {
+            request = request.header(request_id::HEADER, request_id);
+        }
+        let response = request
+            .body(delivery.body.clone())
+            .send()
+            .await
+            .map_err(|e| (e.to_string(), true))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let retryable = status.is_server_error() || matches!(status.as_u16(), 408 | 429);
+        Err((format!("Endpoint returned {}", status), retryable))
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_is_hmac_over_timestamp_and_body() {
+        // RFC 4231-style reference value for HMAC-SHA256
+        assert_eq!(
+            hmac_sha256_hex("key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(
+            signature("key", 1_700_000_000, b"{}"),
+            format!(
+                "t=1700000000,v1={}",
+                hmac_sha256_hex("key", b"1700000000.{}")
+            )
+        );
+
+        assert!(WebhookEndpoint::new("https://example.com/hook", Some("short".into())).is_err());
+        let endpoint = WebhookEndpoint::new("https://example.com/hook", None).unwrap();
+        assert!(endpoint.secret.starts_with("whsec_"));
+    }
+}
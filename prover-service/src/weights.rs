@@ -0,0 +1,163 @@
+//! Layer-level weight commitments
+//!
+//! Besides the whole-model commitment, each registered model gets a
+//! `merkle-keccak-v1` weights root: a Merkle tree whose leaves are the
+//! model's weight tensors in name order. An auditor holding a tensor from a
+//! published training run can request the opening for that layer and check
+//! it against the root without downloading the model.
+//!
+//! Leaf data is `keccak256(name) || keccak256(tensor)`, where `tensor` is the
+//! tensor's canonical ONNX `TensorProto` encoding (see `onnx::canonicalize`).
+//! Large models have their tensors hashed on all available cores.
+
+use anyhow::Result;
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+use crate::jolt_atlas::PARALLEL_HASH_MIN_BYTES;
+use crate::merkle::{hash_leaf, to_hex, Hash, MerkleTree, ProofStep};
+use crate::onnx;
+use crate::types::CommitmentScheme;
+
+/// Inclusion proof for a single weight tensor
+#[derive(Serialize)]
+pub struct WeightProof {
+    pub model_id: String,
+    pub layer: String,
+    pub scheme: CommitmentScheme,
+    pub weights_root: String,
+    /// keccak256 of the canonical tensor bytes
+    pub tensor_hash: String,
+    pub leaf: String,
+    pub leaf_index: usize,
+    pub leaf_count: usize,
+    /// Sibling hashes from the leaf up to the root
+    pub proof: Vec<ProofStep>,
+}
+
+/// Merkle tree over a model's weight tensors
+pub struct WeightsTree {
+    layers: Vec<(String, Hash)>,
+    tree: MerkleTree,
+}
+
+impl WeightsTree {
+    /// Build the tree from canonical model bytes
+    pub fn build(canonical: &[u8]) -> Result<Self> {
+        let tensors = onnx::weight_tensors(canonical)?;
+        let layers = digest_tensors(tensors);
+        let tree = MerkleTree::new(
+            layers
+                .iter()
+                .map(|(name, tensor_hash)| leaf(name, tensor_hash))
+                .collect(),
+        );
+        Ok(Self { layers, tree })
+    }
+
+    /// Tensor names in leaf order
+    pub fn layers(&self) -> Vec<String> {
+        self.layers.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Root of the tree (hex, 0x-prefixed)
+    pub fn root(&self) -> String {
+        to_hex(&self.tree.root())
+    }
+
+    /// Opening for the tensor named `layer`
+    pub fn prove(&self, model_id: &str, layer: &str) -> Option<WeightProof> {
+        let index = self.layers.iter().position(|(name, _)| name == layer)?;
+        let tensor_hash = self.layers[index].1;
+        Some(WeightProof {
+            model_id: model_id.to_string(),
+            layer: layer.to_string(),
+            scheme: CommitmentScheme::MerkleKeccakV1,
+            weights_root: self.root(),
+            tensor_hash: to_hex(&tensor_hash),
+            leaf: to_hex(&leaf(layer, &tensor_hash)),
+            leaf_index: index,
+            leaf_count: self.layers.len(),
+            proof: self.tree.proof(index)?,
+        })
+    }
+}
+
+/// Hash each tensor, splitting the work across threads for large models
+fn digest_tensors(tensors: Vec<(String, Vec<u8>)>) -> Vec<(String, Hash)> {
+    let digest = |(name, bytes): (String, Vec<u8>)| (name, Keccak256::digest(bytes).into());
+    let total: usize = tensors.iter().map(|(_, bytes)| bytes.len()).sum();
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if total < PARALLEL_HASH_MIN_BYTES || threads == 1 || tensors.len() == 1 {
+        return tensors.into_iter().map(digest).collect();
+    }
+
+    // Deal tensors out round-robin so large layers spread across threads
+    let mut groups = vec![Vec::new(); threads];
+    for (i, tensor) in tensors.into_iter().enumerate() {
+        groups[i % threads].push((i, tensor));
+    }
+    let mut layers: Vec<(usize, (String, Hash))> = std::thread::scope(|scope| {
+        let handles: Vec<_> = groups
+            .into_iter()
+            .map(|group| {
+                scope.spawn(move || {
+                    group
+                        .into_iter()
+                        .map(|(i, tensor)| (i, digest(tensor)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Tensor hashing panicked"))
+            .collect()
+    });
+    layers.sort_by_key(|(i, _)| *i);
+    layers.into_iter().map(|(_, layer)| layer).collect()
+}
+
+fn leaf(name: &str, tensor_hash: &Hash) -> Hash {
+    let mut data = Keccak256::digest(name.as_bytes()).to_vec();
+    data.extend_from_slice(tensor_hash);
+    hash_leaf(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::root_from_proof;
+
+    #[test]
+    fn test_parallel_tensor_digests_keep_order() {
+        let tensors: Vec<(String, Vec<u8>)> = (0..5u8)
+            .map(|i| (format!("w{}", i), vec![i; PARALLEL_HASH_MIN_BYTES / 4]))
+            .collect();
+        let expected: Vec<(String, Hash)> = tensors
+            .iter()
+            .map(|(name, bytes)| (name.clone(), Keccak256::digest(bytes).into()))
+            .collect();
+        assert_eq!(digest_tensors(tensors), expected);
+    }
+
+    #[test]
+    fn test_layer_opening_verifies_against_root() {
+        let model = include_bytes!("../jolt-atlas/models/authorization/network.onnx");
+        let canonical = onnx::canonicalize(model).unwrap();
+        let tree = WeightsTree::build(&canonical).unwrap();
+
+        let (layer, tensor) = onnx::weight_tensors(&canonical).unwrap().pop().unwrap();
+        let proof = tree.prove("m", &layer).unwrap();
+
+        // Recompute the leaf from the tensor alone, as an auditor would
+        let expected_leaf = leaf(&layer, &Keccak256::digest(tensor).into());
+        assert_eq!(proof.leaf, to_hex(&expected_leaf));
+        assert_eq!(
+            root_from_proof(expected_leaf, &proof.proof).map(|r| to_hex(&r)),
+            Some(tree.root())
+        );
+
+        assert!(tree.prove("m", "no-such-layer").is_none());
+    }
+}
@@ -0,0 +1,56 @@
+//! Streamed witness generation
+//!
+//! Proving a large model by materializing its full execution trace can need
+//! more memory than the machine has. With `WITNESS_MEMORY_BUDGET_MB` set,
+//! backends that support it generate the witness in chunks of at most
+//! `WITNESS_CHUNK_ROWS` trace rows, committing to each chunk and dropping it
+//! before the next, so peak memory stays within the budget regardless of
+//! model size. Backends without streaming support prove as before and log a
+//! warning.
+
+use serde::{Deserialize, Serialize};
+
+/// Memory limits for streamed witness generation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WitnessBudget {
+    /// Peak memory the witness may occupy
+    pub max_bytes: u64,
+    /// Trace rows generated and committed per chunk
+    pub chunk_rows: u64,
+}
+
+impl WitnessBudget {
+    /// Environment passed to external proving binaries
+    pub fn env(&self) -> [(&'static str, String); 2] {
+        [
+            (
+                "JOLT_ATLAS_WITNESS_BUDGET_MB",
+                (self.max_bytes >> 20).max(1).to_string(),
+            ),
+            (
+                "JOLT_ATLAS_WITNESS_CHUNK_ROWS",
+                self.chunk_rows.max(1).to_string(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_environment() {
+        let budget = WitnessBudget {
+            max_bytes: 48 << 30,
+            chunk_rows: 0,
+        };
+        assert_eq!(
+            budget.env(),
+            [
+                ("JOLT_ATLAS_WITNESS_BUDGET_MB", "49152".to_string()),
+                ("JOLT_ATLAS_WITNESS_CHUNK_ROWS", "1".to_string()),
+            ]
+        );
+    }
+}